@@ -12,15 +12,22 @@ use eyre::{eyre, WrapErr};
 use pgx_utils::{
     get_target_dir,
     pg_config::{PgConfig, PgConfigSelector, Pgx},
+    test_matrix::{TestMatrix, TestResults},
 };
 use std::{
     path::{Path, PathBuf},
     process::{Command, Stdio},
 };
 
-use crate::CommandExecute;
+use crate::{
+    command::{get::find_control_file, install::get_version},
+    CommandExecute,
+};
 
 /// Run the test suite for this crate
+///
+/// Against `all` the Postgres versions, the suite runs against each of them, even once it fails
+/// against one, and then which tests passed, failed, or were skipped against each is reported.
 #[derive(clap::Args, Debug)]
 #[clap(author)]
 pub(crate) struct Test {
@@ -41,6 +48,11 @@ pub(crate) struct Test {
     /// Don't regenerate the schema
     #[clap(long, short)]
     no_schema: bool,
+    /// Test upgrading from a previous release, a git tag or commit, or a directory of the files
+    /// `cargo pgx package` made for it, by creating the extension at its version, and updating it
+    /// to this one with `ALTER EXTENSION ... UPDATE`, before the tests run
+    #[clap(long, value_name = "TAG_OR_DIR")]
+    upgrade_from: Option<String>,
     #[clap(flatten)]
     features: clap_cargo::Features,
     #[clap(from_global, parse(from_occurrences))]
@@ -67,6 +79,10 @@ impl CommandExecute for Test {
                 .ok_or(eyre!("No provided `pg$VERSION` flag."))?,
         };
 
+        // each version's tests run, even once those of one fail, and then which passed against
+        // which versions is reported
+        let mut matrix = TestMatrix::default();
+        let mut passed = true;
         for pg_config in pgx.iter(PgConfigSelector::new(&pg_version)) {
             let mut testname = self.testname.clone();
             let pg_config = match pg_config {
@@ -84,7 +100,8 @@ impl CommandExecute for Test {
                 }
                 Ok(config) => config,
             };
-            let pg_version = format!("pg{}", pg_config.major_version()?);
+            let pg_major_version = pg_config.major_version()?;
+            let pg_version = format!("pg{}", pg_major_version);
 
             let features = crate::manifest::features_for_version(
                 self.features.clone(),
@@ -92,7 +109,22 @@ impl CommandExecute for Test {
                 &pg_version,
             );
 
-            test_extension(
+            let upgrade_from = match self.upgrade_from {
+                Some(ref release) => Some(install_previous_release(
+                    release,
+                    pg_config,
+                    &pg_version,
+                    &package_manifest_path,
+                )?),
+                None => None,
+            };
+
+            let results_path = TestResults::path(pg_major_version)?;
+            if results_path.exists() {
+                std::fs::remove_file(&results_path)?;
+            }
+
+            passed &= test_extension(
                 pg_config,
                 self.manifest_path.as_ref(),
                 self.package.as_ref(),
@@ -100,7 +132,17 @@ impl CommandExecute for Test {
                 self.no_schema,
                 &features,
                 testname.clone(),
-            )?
+                upgrade_from.as_deref(),
+            )?;
+            matrix.push(pg_major_version, TestResults::load(&results_path)?);
+        }
+
+        if pg_version == "all" {
+            println!("\n{}", matrix);
+        }
+        if !passed {
+            // We explicitly do not want to return a spantraced error here.
+            std::process::exit(1)
         }
 
         Ok(())
@@ -120,7 +162,8 @@ pub fn test_extension(
     no_schema: bool,
     features: &clap_cargo::Features,
     testname: Option<impl AsRef<str>>,
-) -> eyre::Result<()> {
+    upgrade_from: Option<&str>,
+) -> eyre::Result<bool> {
     if let Some(ref testname) = testname {
         tracing::Span::current().record("testname", &tracing::field::display(&testname.as_ref()));
     }
@@ -166,6 +209,10 @@ pub fn test_extension(
         command.env("RUST_LOG", rust_log);
     }
 
+    if let Some(version) = upgrade_from {
+        command.env("PGX_UPGRADE_FROM", version);
+    }
+
     if !features_arg.trim().is_empty() {
         command.arg("--features");
         command.arg(&features_arg);
@@ -202,12 +249,166 @@ pub fn test_extension(
     tracing::debug!(command = ?command, "Running");
     let status = command.status().wrap_err("failed to run cargo test")?;
     tracing::trace!(status_code = %status, command = ?command, "Finished");
+
+    Ok(status.success())
+}
+
+/// Install the extension script of the previous release `release`, a git tag or commit, or a
+/// directory of packaged files, into `pg_config`'s extension directory, returning its version
+///
+/// The tests install this version's shared library, which the previous release's script is run
+/// against, before its upgrade scripts, from `sql/`, update it to this version.
+fn install_previous_release(
+    release: &str,
+    pg_config: &PgConfig,
+    pg_version: &str,
+    package_manifest_path: impl AsRef<Path>,
+) -> eyre::Result<String> {
+    let (_, extname) = find_control_file(&package_manifest_path)?;
+    let (script, version) = if Path::new(release).is_dir() {
+        packaged_script(Path::new(release), &extname)?
+    } else {
+        build_previous_script(release, pg_version, &package_manifest_path, &extname)?
+    };
+
+    let current_version = get_version(&package_manifest_path)?;
+    if version == current_version {
+        return Err(eyre!(
+            "`{}` is already version {} of `{}`, so there's nothing to upgrade",
+            release,
+            version,
+            extname
+        ));
+    }
+
+    let mut dest = pg_config.extension_dir()?;
+    dest.push(format!("{}--{}.sql", extname, version));
+    std::fs::copy(&script, &dest).wrap_err_with(|| {
+        format!(
+            "couldn't copy `{}` to `{}`",
+            script.display(),
+            dest.display()
+        )
+    })?;
+    println!(
+        "testing the upgrade of `{}` from version {} to {}",
+        extname, version, current_version
+    );
+    Ok(version)
+}
+
+/// The script, and version, of the release of the extension `extname` which `cargo pgx package`
+/// made the files in `dir` for
+fn packaged_script(dir: &Path, extname: &str) -> eyre::Result<(PathBuf, String)> {
+    let control_file = find_file(dir, &format!("{}.control", extname))?
+        .ok_or_else(|| eyre!("no `{}.control` in `{}`", extname, dir.display()))?;
+    let control = std::fs::read_to_string(&control_file)
+        .wrap_err_with(|| format!("couldn't read `{}`", control_file.display()))?;
+    let version = control
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .find(|(name, _)| name.trim() == "default_version")
+        .map(|(_, version)| version.trim().trim_matches('\'').to_string())
+        .ok_or_else(|| eyre!("no `default_version` in `{}`", control_file.display()))?;
+
+    let script_name = format!("{}--{}.sql", extname, version);
+    let script = find_file(dir, &script_name)?
+        .ok_or_else(|| eyre!("no `{}` in `{}`", script_name, dir.display()))?;
+    Ok((script, version))
+}
+
+/// The script, and version, of the extension `extname` at the git tag or commit `release`, which
+/// is generated by `cargo pgx schema` in a worktree checked out at it, in
+/// `target/pgx-upgrade-from/`
+fn build_previous_script(
+    release: &str,
+    pg_version: &str,
+    package_manifest_path: impl AsRef<Path>,
+    extname: &str,
+) -> eyre::Result<(PathBuf, String)> {
+    let package_dir = package_manifest_path
+        .as_ref()
+        .canonicalize()?
+        .parent()
+        .ok_or_else(|| eyre!("the package manifest has no parent directory"))?
+        .to_path_buf();
+    let repository = PathBuf::from(git(&package_dir, &["rev-parse", "--show-toplevel"])?);
+    let package_in_repository = package_dir.strip_prefix(&repository)?;
+
+    let mut upgrade_dir = get_target_dir()?;
+    upgrade_dir.push("pgx-upgrade-from");
+    let worktree = upgrade_dir.join(release.replace('/', "_"));
+    if !worktree.exists() {
+        let worktree = worktree.display().to_string();
+        git(
+            &repository,
+            &["worktree", "add", "--detach", &worktree, release],
+        )?;
+    }
+    let previous_package_dir = worktree.join(package_in_repository);
+
+    let script = upgrade_dir.join(format!(
+        "{}--{}-{}.sql",
+        extname,
+        release.replace('/', "_"),
+        pg_version
+    ));
+    let mut command = Command::new("cargo");
+    command
+        .current_dir(&previous_package_dir)
+        .arg("pgx")
+        .arg("schema")
+        .arg(pg_version)
+        .arg("--out")
+        .arg(&script)
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit());
+    tracing::debug!(command = ?command, "Running");
+    let status = command
+        .status()
+        .wrap_err("failed to run `cargo pgx schema`")?;
     if !status.success() {
-        if !status.success() {
-            // We explicitly do not want to return a spantraced error here.
-            std::process::exit(1)
-        }
+        return Err(eyre!(
+            "`cargo pgx schema` failed for `{}` at `{}`",
+            extname,
+            release
+        ));
+    }
+
+    let version = get_version(previous_package_dir.join("Cargo.toml"))?;
+    Ok((script, version))
+}
+
+/// Run `git` with `args` in `dir`, returning what it output
+fn git(dir: &Path, args: &[&str]) -> eyre::Result<String> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .wrap_err("problem running `git`")?;
+    if !output.status.success() {
+        return Err(eyre!(
+            "`git {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
     }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
 
-    Ok(())
+/// The file named `name` in `dir`, or any directory within it
+fn find_file(dir: &Path, name: &str) -> eyre::Result<Option<PathBuf>> {
+    for entry in
+        std::fs::read_dir(dir).wrap_err_with(|| format!("couldn't read `{}`", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.is_dir() {
+            if let Some(found) = find_file(&path, name)? {
+                return Ok(Some(found));
+            }
+        } else if path.file_name() == Some(name.as_ref()) {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
 }
@@ -114,7 +114,7 @@ impl Aggregate for IntegerAvgState {
     //     unimplemented!()
     // }
 
-    // fn deserial(current: Self::State, _buf: Vec<u8>, _internal: PgBox<Self::State>, _fcinfo: pgx::pg_sys::FunctionCallInfo) -> PgBox<Self::State> {
+    // fn deserial(_buf: Vec<u8>, _fcinfo: pgx::pg_sys::FunctionCallInfo) -> Self::State {
     //     unimplemented!()
     // }
 
@@ -10,12 +10,17 @@ Use of this source code is governed by the MIT license that can be found in the
 extern crate proc_macro;
 
 mod operators;
+mod pg_config;
+mod pg_fdw;
+mod pg_stats;
 use operators::{impl_postgres_eq, impl_postgres_hash, impl_postgres_ord};
 
 use pgx_utils::rewriter::*;
 use pgx_utils::{
     sql_entity_graph::{
-        ExtensionSql, ExtensionSqlFile, PgAggregate, PgExtern, PostgresEnum, PostgresType, Schema,
+        ExtensionSql, ExtensionSqlFile, PgAggregate, PgBench, PgCast, PgEventTrigger, PgExtern,
+        PgLanguageHandler, PgOpClass, PgOpClassMethod, PgProptest, PgTest, PgTrigger,
+        PostgresEnum, PostgresType, Schema,
     },
     *,
 };
@@ -50,12 +55,62 @@ pub fn pg_guard(_attr: TokenStream, item: TokenStream) -> TokenStream {
     }
 }
 
-/// `#[pg_test]` functions are test functions (akin to `#[test]`), but they run in-process inside
-/// Postgres during `cargo pgx test`.
+/**
+`#[pg_test]` functions are test functions (akin to `#[test]`), but they run in-process inside
+Postgres during `cargo pgx test`.
+
+Each test runs in its own transaction, which is rolled back once it's done, with its own schema
+first in the `search_path`, so the tables it creates can't collide with those of other tests.
+
+```rust,ignore
+use pgx::*;
+
+fn create_dogs() {
+    Spi::run("CREATE TABLE dogs (name text)");
+}
+
+#[pg_test(setup = "create_dogs")]
+fn test_no_dogs() {
+    assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM dogs"), Some(0));
+}
+```
+
+Accepts the same arguments as [`#[pg_extern]`](macro@pg_extern), as well as:
+
+* `error = "message"`: The test passes only if it raises an ERROR with the message.
+* `sqlstate = "22012"`, or `sqlstate = ERRCODE_DIVISION_BY_ZERO`: The test passes only if it
+  raises an ERROR with the SQLSTATE, which, unlike a message, is the same across Postgres versions
+  and locales.
+* `error_contains = "text"`, `detail_contains = "text"`, `hint_contains = "text"`: The test passes
+  only if it raises an ERROR whose message, detail, or hint contains the text.
+* `setup = "function"`: Call the function before the test, in its transaction.
+* `teardown = "function"`: Call the function after the test, in its transaction, unless it
+  failed, as its transaction is then already aborted.  That transaction is always rolled back,
+  which undoes whatever the test changed in the database, so the teardown is for checking what
+  the test left behind, or for cleaning up state outside the database, such as files or statics,
+  which is then left as it is when the test fails.
+* `guc = "name=value"`, such as `guc = "work_mem=64MB"`: Set the GUC for the test only, as by
+  `SET LOCAL`, which can be repeated for each GUC to set.  A GUC which a session can't set, as it
+  needs a restart or a reload, belongs in `pg_test::postgresql_conf_options()` instead, where it's
+  set for every test.
+* `role = "app_user"`: Run the test as the role, rather than as the superuser, so it's subject to
+  permission checks and row-level security.  The role is created for the test if it doesn't
+  exist, and granted `USAGE` on the extension's schema, and `USAGE` and `CREATE` on the test's own
+  schema.
+* `min_pg = 13`, `max_pg = 13`, `skip_pg = 13`: Run the test only against Postgres 13 and newer,
+  13 and older, or other than 13, where `skip_pg` can be repeated for each version to skip.  The
+  test is only compiled for the versions it runs against, so it can use what the others lack, and
+  is reported as skipped against the others.  `cargo pgx test all` reports which tests passed,
+  failed, or were skipped against each version.
+*/
 #[proc_macro_attribute]
 pub fn pg_test(attr: TokenStream, item: TokenStream) -> TokenStream {
     let mut stream = proc_macro2::TokenStream::new();
-    let args = parse_extern_attributes(proc_macro2::TokenStream::from(attr.clone()));
+    let (test, extern_attr) = match PgTest::split_attributes(attr.into()) {
+        Ok(split) => split,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let args = parse_extern_attributes(extern_attr.clone());
 
     let mut expected_error = None;
     args.into_iter().for_each(|v| {
@@ -64,19 +119,67 @@ pub fn pg_test(attr: TokenStream, item: TokenStream) -> TokenStream {
         }
     });
 
-    stream.extend(proc_macro2::TokenStream::from(pg_extern(
-        attr,
-        item.clone(),
-    )));
+    let expected_error = if expected_error.is_some() || test.expects_error() {
+        let option = |value: Option<String>| match value {
+            Some(value) => quote! { Some(#value) },
+            None => quote! { None },
+        };
+        let message = option(expected_error);
+        let message_contains = option(test.error_contains.as_ref().map(|text| text.value()));
+        let detail_contains = option(test.detail_contains.as_ref().map(|text| text.value()));
+        let hint_contains = option(test.hint_contains.as_ref().map(|text| text.value()));
+        quote! {
+            Some(pgx_tests::ExpectedError {
+                message: #message,
+                sqlstate: expected_sqlstate.as_deref(),
+                message_contains: #message_contains,
+                detail_contains: #detail_contains,
+                hint_contains: #hint_contains,
+            })
+        }
+    } else {
+        quote! { None }
+    };
 
-    let expected_error = match expected_error {
-        Some(msg) => quote! {Some(#msg)},
-        None => quote! {None},
+    // a `PgSqlErrorCode`'s SQLSTATE is only known once the test runs
+    let expected_sqlstate = match &test.sqlstate {
+        Some(syn::Expr::Path(code)) => {
+            let code = code.path.segments.last().map(|segment| &segment.ident);
+            quote! { Some(::pgx::PgSqlErrorCode::#code.sqlstate()) }
+        }
+        Some(sqlstate) => quote! { Some(String::from(#sqlstate)) },
+        None => quote! { None },
     };
+    let role = match &test.role {
+        Some(role) => quote! { Some(#role) },
+        None => quote! { None },
+    };
+    let setting_names = test.settings.iter().map(|(name, _)| name);
+    let setting_values = test.settings.iter().map(|(_, value)| value);
+
+    // a test which only runs against some versions is only compiled for them, so it can use what
+    // the others lack, and is skipped against the others
+    let pg_versions = test.pg_versions().map(|versions| {
+        let features = versions.iter().map(|version| format!("pg{}", version));
+        quote! { any(#(feature = #features),*) }
+    });
 
     let ast = parse_macro_input!(item as syn::Item);
     match ast {
         Item::Fn(func) => {
+            let func = test.wrap(func);
+            let test_extern = proc_macro2::TokenStream::from(pg_extern(
+                extern_attr.into(),
+                func.to_token_stream().into(),
+            ));
+            stream.extend(match &pg_versions {
+                Some(pg_versions) => quote! {
+                    #[cfg(#pg_versions)]
+                    const _: () = { #test_extern };
+                },
+                None => test_extern,
+            });
+
             let sql_funcname = func.sig.ident.to_string();
             let test_func_name =
                 Ident::new(&format!("pg_{}", func.sig.ident.to_string()), func.span());
@@ -91,14 +194,33 @@ pub fn pg_test(attr: TokenStream, item: TokenStream) -> TokenStream {
                 });
             }
 
+            let skip = pg_versions.map(|pg_versions| {
+                quote! {
+                    if !cfg!(#pg_versions) {
+                        pgx_tests::skip_test(test_name);
+                        return;
+                    }
+                }
+            });
+
             stream.extend(quote! {
                 #[test]
                 fn #test_func_name() {
+                    let test_name = concat!(module_path!(), "::", #sql_funcname);
+                    #skip
+                    let _outcome = pgx_tests::RecordOutcome::new(test_name);
+
                     let mut options = Vec::new();
                     #att_stream
 
                     crate::pg_test::setup(options);
-                    let res = pgx_tests::run_test(#sql_funcname, #expected_error, crate::pg_test::postgresql_conf_options());
+                    let expected_sqlstate: Option<String> = #expected_sqlstate;
+                    let test_options = pgx_tests::TestOptions {
+                        expected_error: #expected_error,
+                        settings: vec![#((#setting_names, #setting_values)),*],
+                        role: #role,
+                    };
+                    let res = pgx_tests::run_test_with(#sql_funcname, test_options, crate::pg_test::postgresql_conf_options());
                     match res {
                         Ok(()) => (),
                         Err(e) => panic!("{:?}", e)
@@ -113,6 +235,108 @@ pub fn pg_test(attr: TokenStream, item: TokenStream) -> TokenStream {
     stream.into()
 }
 
+/**
+`#[pg_proptest]` functions are property tests, which run as a [`#[pg_test]`](macro@pg_test) with
+the inputs [proptest](https://docs.rs/proptest) generates for their arguments, and need pgx's
+`proptest` feature.
+
+```rust,ignore
+use pgx::*;
+
+#[pg_proptest(cases = 64)]
+fn prop_abs(value: i32, #[strategy(1..10i32)] factor: i32) {
+    let abs = Spi::get_one::<i64>(&format!("SELECT abs({}::bigint * {})", value, factor));
+    assert_eq!(abs, Some((value as i64 * factor as i64).abs()));
+}
+```
+
+Each case runs in its own subtransaction, so when a case panics, or raises an ERROR, proptest can
+go on to shrink its input, and the test fails with the smallest input which still fails.
+
+An argument's input is generated by its `#[strategy(...)]`, if it has one, or else by proptest's
+`any::<T>()` for its type.
+
+Accepts the same arguments as [`#[pg_test]`](macro@pg_test), as well as:
+
+* `cases = 64`: The number of passing cases the test needs, rather than proptest's default of 256.
+*/
+#[proc_macro_attribute]
+pub fn pg_proptest(attr: TokenStream, item: TokenStream) -> TokenStream {
+    fn wrapped(attr: TokenStream, item: TokenStream) -> Result<TokenStream, syn::Error> {
+        let (proptest, test_attr) = PgProptest::split_attributes(attr.into())?;
+        let func = proptest.rewrite(syn::parse::<ItemFn>(item)?)?;
+        Ok(pg_test(test_attr.into(), func.to_token_stream().into()))
+    }
+
+    match wrapped(attr, item) {
+        Ok(tokens) => tokens,
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/**
+`#[pg_bench]` functions are micro-benchmarks, which time a routine inside Postgres, with the
+`Bencher` they're passed, and report its statistics.
+
+```rust,ignore
+use pgx::*;
+
+#[pg_bench(samples = 50)]
+fn bench_spi_get_one(b: &mut Bencher) {
+    b.iter(|| Spi::get_one::<i32>("SELECT 1"));
+}
+```
+
+The routine runs in a backend, for a warm-up before it's measured, so its caches are warm.  The
+benchmark's `BenchResult`, with the mean, standard deviation, median, minimum, and maximum time of
+an iteration, is returned to the test process, which prints it, along with how its mean changed
+since the benchmark last ran against the same Postgres version, as recorded in
+`target/pgx-bench-$PGVER/`.
+
+As a benchmark takes a while, its test is `#[ignore]`d, and is run by
+`cargo pgx test -- --ignored bench_`.
+
+Accepts the same arguments as [`#[pg_extern]`](macro@pg_extern), as well as:
+
+* `samples = 50`: The number of samples taken, rather than 100.
+* `warm_up_ms = 1000`: The milliseconds the routine runs before it's measured, rather than 3000.
+* `measurement_ms = 2000`: The milliseconds the routine is measured for, rather than 5000.
+*/
+#[proc_macro_attribute]
+pub fn pg_bench(attr: TokenStream, item: TokenStream) -> TokenStream {
+    fn wrapped(attr: TokenStream, item: TokenStream) -> Result<TokenStream, syn::Error> {
+        let (bench, extern_attr) = PgBench::split_attributes(attr.into())?;
+        let func = bench.rewrite(syn::parse::<ItemFn>(item)?)?;
+
+        let sql_funcname = func.sig.ident.to_string();
+        let test_func_name = Ident::new(&format!("pg_{}", sql_funcname), func.span());
+        let options = func.attrs.iter().map(|a| a.tokens.to_string());
+
+        let mut stream = proc_macro2::TokenStream::from(pg_extern(
+            extern_attr.into(),
+            func.to_token_stream().into(),
+        ));
+        stream.extend(quote! {
+            #[test]
+            #[ignore]
+            fn #test_func_name() {
+                crate::pg_test::setup(vec![#(#options),*]);
+                let res = pgx_tests::run_pg_bench(#sql_funcname, crate::pg_test::postgresql_conf_options());
+                match res {
+                    Ok(_) => (),
+                    Err(e) => panic!("{:?}", e)
+                }
+            }
+        });
+        Ok(stream.into())
+    }
+
+    match wrapped(attr, item) {
+        Ok(tokens) => tokens,
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
 /// Associated macro for `#[pg_test]` to provide context back to your test framework to indicate
 /// that the test system is being initialized
 #[proc_macro_attribute]
@@ -127,6 +351,51 @@ pub fn pg_operator(attr: TokenStream, item: TokenStream) -> TokenStream {
     pg_extern(attr, item)
 }
 
+/**
+Declare a function as `#[pg_cast]` to indicate that it represents a Postgres cast from its
+argument type to its return type.
+
+`cargo pgx schema` will automatically generate the underlying `CREATE FUNCTION` and `CREATE CAST`.
+
+```rust,ignore
+use pgx::*;
+
+#[pg_cast(implicit, immutable, parallel_safe)]
+fn celsius_to_fahrenheit(value: Celsius) -> Fahrenheit {
+    Fahrenheit(value.0 * 9.0 / 5.0 + 32.0)
+}
+```
+
+Accepts the same arguments as [`#[pg_extern]`](macro@pg_extern), as well as one of:
+
+* `implicit`: The cast may be invoked implicitly in any context (`AS IMPLICIT`).
+* `assignment`: The cast may be invoked implicitly in assignments (`AS ASSIGNMENT`).
+
+Without either, the cast may only be invoked explicitly.
+*/
+#[proc_macro_attribute]
+pub fn pg_cast(attr: TokenStream, item: TokenStream) -> TokenStream {
+    fn wrapped(attr: TokenStream, item: TokenStream) -> Result<TokenStream, syn::Error> {
+        let (cast, extern_attr) = PgCast::split_attributes(attr.into())?;
+        let args = parse_extern_attributes(extern_attr.clone());
+        let sql_graph_entity_item =
+            PgExtern::new(extern_attr, item.clone().into())?.with_cast(cast);
+
+        let func = syn::parse::<ItemFn>(item)?;
+        Ok(rewrite_item_fn(func, args, &sql_graph_entity_item).into())
+    }
+
+    match wrapped(attr, item) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
 /// Used with `#[pg_operator]`.  1 value which is the operator name itself
 #[proc_macro_attribute]
 pub fn opname(_attr: TokenStream, item: TokenStream) -> TokenStream {
@@ -411,6 +680,15 @@ Optionally accepts the following attributes:
 * `parallel_safe`: Corresponds to [`PARALLEL SAFE`](https://www.postgresql.org/docs/current/sql-createfunction.html).
 * `parallel_unsafe`: Corresponds to [`PARALLEL UNSAFE`](https://www.postgresql.org/docs/current/sql-createfunction.html).
 * `parallel_restricted`: Corresponds to [`PARALLEL RESTRICTED`](https://www.postgresql.org/docs/current/sql-createfunction.html).
+* `window`: Corresponds to [`WINDOW`](https://www.postgresql.org/docs/current/sql-createfunction.html).
+  + Window functions are usually declared with [`macro@pg_window`] instead.
+* `cost = 100`: Corresponds to [`COST`](https://www.postgresql.org/docs/current/sql-createfunction.html).
+* `rows = 10`: Corresponds to [`ROWS`](https://www.postgresql.org/docs/current/sql-createfunction.html), for a function which returns a set.
+* `leakproof`: Corresponds to [`LEAKPROOF`](https://www.postgresql.org/docs/current/sql-createfunction.html).
+  + Only a superuser can create a leakproof function, so the extension must be created by one.
+* `security_definer`: Corresponds to [`SECURITY DEFINER`](https://www.postgresql.org/docs/current/sql-createfunction.html).
+  + A security definer function should also `set` its `search_path`, so it can't be made to run objects other users created.
+* `set = "search_path = pg_catalog, pg_temp"`: Corresponds to [`SET`](https://www.postgresql.org/docs/current/sql-createfunction.html), and may be given more than once.
 * `no_guard`: Do not use `#[pg_guard]` with the function.
 * `sql`: Same arguments as [`#[pgx(sql = ..)]`](macro@pgx).
 
@@ -499,19 +777,51 @@ fn floop() -> impl Iterator<Item = (name!(a, i32), name!(b, i32))> {
     None.into_iter() // Help type inference...
 }
 
+```
+
+Returning a tuple directly returns a single row through `OUT` arguments, so the columns can be
+named with `name!()` and are available from `SELECT * FROM singular_floop()`. Returning `None` from
+an `Option` of a tuple returns `NULL`:
+
+```rust,ignore
+use pgx::*;
+#[pg_extern]
+fn singular_floop() -> (name!(a, i32), name!(b, i32)) {
+    todo!()
+}
+
 #[pg_extern]
-fn singlular_floop() -> (name!(a, i32), name!(b, i32)) {
+fn maybe_floop() -> Option<(name!(a, i32), name!(b, i32))> {
     todo!()
 }
 ```
 
-The `name!()` macro may only be used in return position inside the `Item` of an `impl Iterator`.
+The `name!()` macro may only be used in return position inside a tuple, either returned directly
+or as the `Item` of an `impl Iterator`.
 
 It accepts 2 arguments:
 
 * A name, such as `example`
 * A type
 
+Tables can also be returned as a [`TableIterator`](pgx::TableIterator) of a struct which derives
+[`TableRow`](macro@TableRow), whose fields name the columns:
+
+```rust,ignore
+use pgx::*;
+
+#[derive(TableRow)]
+struct Floop {
+    a: i32,
+    b: Option<i32>,
+}
+
+#[pg_extern]
+fn struct_floop() -> TableIterator<'static, Floop> {
+    TableIterator::new(vec![Floop { a: 1, b: None }])
+}
+```
+
 # Special Cases
 
 `pg_sys::Oid` is a special cased type alias, in order to use it as an argument or return it must be
@@ -536,7 +846,10 @@ fn example_return() -> pg_sys::Oid {
 pub fn pg_extern(attr: TokenStream, item: TokenStream) -> TokenStream {
     let args = parse_extern_attributes(proc_macro2::TokenStream::from(attr.clone()));
 
-    let sql_graph_entity_item = PgExtern::new(attr.clone().into(), item.clone().into()).unwrap();
+    let sql_graph_entity_item = match PgExtern::new(attr.clone().into(), item.clone().into()) {
+        Ok(sql_graph_entity_item) => sql_graph_entity_item,
+        Err(e) => return e.to_compile_error().into(),
+    };
 
     let ast = parse_macro_input!(item as syn::Item);
     match ast {
@@ -545,141 +858,823 @@ pub fn pg_extern(attr: TokenStream, item: TokenStream) -> TokenStream {
     }
 }
 
-fn rewrite_item_fn(
-    mut func: ItemFn,
-    extern_args: HashSet<ExternArgs>,
-    sql_graph_entity_submission: &PgExtern,
-) -> proc_macro2::TokenStream {
-    let is_raw = extern_args.contains(&ExternArgs::Raw);
-    let no_guard = extern_args.contains(&ExternArgs::NoGuard);
+/**
+Declare a function as `#[pg_trigger]` to use it as the function of a `CREATE TRIGGER` statement.
 
-    let finfo_name = syn::Ident::new(
-        &format!("pg_finfo_{}_wrapper", func.sig.ident),
-        Span::call_site(),
-    );
+The function is passed the firing [`PgTrigger`](pgx::PgTrigger), whose `OLD` and `NEW` rows are
+available as [`PgHeapTuple`](pgx::PgHeapTuple)s or as any [`FromHeapTuple`](pgx::FromHeapTuple)
+type, and returns the row to store as an [`IntoTriggerResult`](pgx::IntoTriggerResult), such as
+`Result<Option<PgHeapTuple>, E>`.  Returning `None` from a `BEFORE` row trigger skips the operation
+on the row, and an `Err` raises an ERROR with its message.
 
-    // use the PgGuardRewriter to go ahead and wrap the function here, rather than applying
-    // a #[pg_guard] macro to the original function.  This is necessary so that compiler
-    // errors/warnings indicate the proper line numbers
-    let rewriter = PgGuardRewriter::new();
+```rust,ignore
+use pgx::*;
 
-    // make the function 'extern "C"' because this is for the #[pg_extern[ macro
-    func.sig.abi = Some(syn::parse_str("extern \"C\"").unwrap());
-    let func_span = func.span();
-    let (rewritten_func, need_wrapper) = rewriter.item_fn(
-        func,
-        Some(sql_graph_entity_submission),
-        true,
-        is_raw,
-        no_guard,
-    );
+#[derive(FromHeapTuple, IntoHeapTuple)]
+struct Dog {
+    name: String,
+    scritches: i32,
+}
 
-    if need_wrapper {
-        quote_spanned! {func_span=>
-            #[no_mangle]
-            #[doc(hidden)]
-            pub extern "C" fn #finfo_name() -> &'static pg_sys::Pg_finfo_record {
-                const V1_API: pg_sys::Pg_finfo_record = pg_sys::Pg_finfo_record { api_version: 1 };
-                &V1_API
-            }
+#[pg_trigger]
+fn count_scritch(trigger: &PgTrigger) -> Result<Option<PgHeapTuple<'_>>, PgHeapTupleError> {
+    let mut dog = trigger.new_as::<Dog>()?.expect("not fired by INSERT or UPDATE");
+    dog.scritches += 1;
+    trigger.new_with(dog).map(Some)
+}
+```
 
-            #rewritten_func
-        }
-    } else {
-        quote_spanned! {func_span=>
+Generates the SQL for `CREATE FUNCTION count_scritch() RETURNS trigger`, which a trigger can then
+use with `EXECUTE PROCEDURE count_scritch()`.
 
-            #rewritten_func
+`FOR EACH STATEMENT` triggers, and `AFTER` triggers, whose result is ignored, may return `()`.  The
+rows a statement affected are available from the transition tables the trigger declares with
+`REFERENCING OLD TABLE AS ... NEW TABLE AS ...`, through
+[`PgTrigger::old_table`](pgx::PgTrigger::old_table) and
+[`PgTrigger::new_table`](pgx::PgTrigger::new_table):
+
+```rust,ignore
+use pgx::*;
+
+#[pg_trigger]
+fn count_new_dogs(trigger: &PgTrigger) -> Result<(), PgHeapTupleError> {
+    if let Some(new_dogs) = trigger.new_table() {
+        for dog in new_dogs.rows::<Dog>() {
+            notice!("welcome, {}", dog?.name);
         }
     }
+    Ok(())
 }
+```
 
-/**
-Generate necessary bindings for using the enum with PostgreSQL.
+When given a `table`, also generates the `CREATE TRIGGER` which executes the function on it, named
+like the function, which can be limited to updates of some columns, or to rows meeting a condition:
 
 ```rust,ignore
-# use pgx_pg_sys as pg_sys;
 use pgx::*;
-use serde::{Deserialize, Serialize};
-#[derive(Debug, Serialize, Deserialize, PostgresEnum)]
-enum DogNames {
-    Nami,
-    Brandy,
-}
-```
-
-*/
-#[proc_macro_derive(PostgresEnum, attributes(requires, pgx))]
-pub fn postgres_enum(input: TokenStream) -> TokenStream {
-    let ast = parse_macro_input!(input as syn::DeriveInput);
 
-    impl_postgres_enum(ast).into()
+#[pg_trigger(
+    table = "dogs",
+    timing = "before",
+    events = ["update"],
+    update_of = ["scritches"],
+    level = "row",
+    when = "NEW.scritches > OLD.scritches",
+    requires = ["create_dogs"]
+)]
+fn count_scritch(trigger: &PgTrigger) -> Result<Option<PgHeapTuple<'_>>, PgHeapTupleError> {
+    todo!()
 }
+```
 
-fn impl_postgres_enum(ast: DeriveInput) -> proc_macro2::TokenStream {
-    let mut stream = proc_macro2::TokenStream::new();
-    let sql_graph_entity_ast = ast.clone();
-    let enum_ident = ast.ident;
-    let enum_name = enum_ident.to_string();
-
-    // validate that we're only operating on an enum
-    let enum_data = match ast.data {
-        Data::Enum(e) => e,
-        _ => panic!("#[derive(PostgresEnum)] can only be applied to enums"),
-    };
+Generates `CREATE TRIGGER "count_scritch" BEFORE UPDATE OF "scritches" ON dogs FOR EACH ROW WHEN
+(NEW.scritches > OLD.scritches) EXECUTE PROCEDURE count_scritch()`.  The table must be created
+before the trigger, such as by the `extension_sql!()` it `requires`.
 
-    let mut from_datum = proc_macro2::TokenStream::new();
-    let mut into_datum = proc_macro2::TokenStream::new();
+An `instead of` trigger makes a view updatable, by doing each row's operation itself.  It returns
+the [`PgTrigger::affected_row`](pgx::PgTrigger::affected_row) to report that the row was affected,
+which becomes the row of a `RETURNING` clause, or `None` to report that it wasn't:
 
-    for d in enum_data.variants.clone() {
-        let label_ident = &d.ident;
-        let label_string = label_ident.to_string();
+```rust,ignore
+use pgx::*;
 
-        from_datum.extend(quote! { #label_string => Some(#enum_ident::#label_ident), });
-        into_datum.extend(quote! { #enum_ident::#label_ident => Some(pgx::lookup_enum_by_label(#enum_name, #label_string)), });
-    }
+#[pg_trigger(
+    table = "dog_names",
+    timing = "instead of",
+    events = ["insert"],
+    requires = ["create_dog_names"]
+)]
+fn insert_dog_name(trigger: &PgTrigger) -> Result<Option<PgHeapTuple<'_>>, PgHeapTupleError> {
+    let new = trigger.affected_row().expect("not fired by INSERT");
+    let name = new.get_by_name::<String>("name")?.unwrap_or_default();
+    Spi::run(&format!("INSERT INTO dogs (name) VALUES ({})", sql::quote_literal(&name)));
+    Ok(Some(new))
+}
+```
 
-    stream.extend(quote! {
-        impl pgx::FromDatum for #enum_ident {
-            #[inline]
-            unsafe fn from_datum(datum: pgx::pg_sys::Datum, is_null: bool, typeoid: pgx::pg_sys::Oid) -> Option<#enum_ident> {
-                if is_null {
-                    None
-                } else {
-                    let (name, _, _) = pgx::lookup_enum_by_oid(datum as pgx::pg_sys::Oid);
-                    match name.as_str() {
-                        #from_datum
-                        _ => panic!("invalid enum value: {}", name)
-                    }
-                }
-            }
-        }
+Optionally accepts the following attributes:
 
-        impl pgx::IntoDatum for #enum_ident {
-            #[inline]
-            fn into_datum(self) -> Option<pgx::pg_sys::Datum> {
-                match self {
-                    #into_datum
-                }
+* `table`: The table to create a trigger on, which may be schema-qualified.
+* `timing`: When the trigger fires, `before` or `after` the event, or `instead of` it, for a
+  `row` trigger on a view.
+* `events`: The events the trigger fires for, any of `insert`, `update`, `delete`, and `truncate`.
+* `update_of`: The columns an `update` must set for the trigger to fire.
+* `level`: Whether the trigger fires for each `row` or `statement`, which is the default.
+* `when`: The condition, in SQL, rows must meet for the trigger to fire.
+* `constraint`: Create a `CONSTRAINT TRIGGER`, which must be an `after`, `row` trigger.
+* `deferrable`: Make the constraint trigger `DEFERRABLE`, so that `SET CONSTRAINTS ... DEFERRED`
+  makes it fire at the end of the transaction.
+* `initially_deferred`: Make the constraint trigger `DEFERRABLE INITIALLY DEFERRED`, so that it
+  fires at the end of the transaction unless `SET CONSTRAINTS ... IMMEDIATE`.
+
+Along with the same attributes as [`macro@pg_extern`], such as `name` and `sql`.
+*/
+#[proc_macro_attribute]
+pub fn pg_trigger(attr: TokenStream, item: TokenStream) -> TokenStream {
+    fn wrapped(attr: TokenStream, item: TokenStream) -> Result<TokenStream, syn::Error> {
+        let (trigger, extern_attr) = PgTrigger::split_attributes(attr.into())?;
+        let args = parse_extern_attributes(extern_attr.clone());
+
+        let mut trigger_fn = syn::parse::<ItemFn>(item)?;
+        let ident = trigger_fn.sig.ident.clone();
+        let attrs = std::mem::take(&mut trigger_fn.attrs);
+        let vis = std::mem::replace(&mut trigger_fn.vis, syn::Visibility::Inherited);
+
+        // the trigger function is nested inside the `#[pg_extern]` function of the same name, so
+        // that it's the name used in SQL and in `requires = [...]`
+        let extern_fn = quote! {
+            #(#attrs)*
+            #vis unsafe fn #ident(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+                #trigger_fn
+
+                let trigger = ::pgx::PgTrigger::from_fcinfo(fcinfo);
+                ::pgx::IntoTriggerResult::into_trigger_result(#ident(&trigger))
             }
+        };
 
-            fn type_oid() -> pg_sys::Oid {
-                pgx::regtypein(#enum_name)
-            }
+        let sql_graph_entity_item =
+            PgExtern::new(extern_attr, extern_fn.clone())?.with_trigger(trigger);
+        let func = syn::parse2::<ItemFn>(extern_fn)?;
+        Ok(rewrite_item_fn(func, args, &sql_graph_entity_item).into())
+    }
 
+    match wrapped(attr, item) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
         }
-    });
-
-    let sql_graph_entity_item = PostgresEnum::from_derive_input(sql_graph_entity_ast).unwrap();
-    sql_graph_entity_item.to_tokens(&mut stream);
-
-    stream
+    }
 }
 
 /**
-Generate necessary bindings for using the type with PostgreSQL.
+Declare a function as `#[pg_event_trigger]` to use it as the function of a `CREATE EVENT TRIGGER`
+statement.
+
+The function is passed the firing [`PgEventTrigger`](pgx::PgEventTrigger), and returns `()` or a
+`Result<(), E>`, whose `Err` raises an ERROR with its message.
 
 ```rust,ignore
-# use pgx_pg_sys as pg_sys;
+use pgx::*;
+
+#[pg_event_trigger(event = "ddl_command_end", tags = ["CREATE TABLE", "ALTER TABLE"])]
+fn log_table_ddl(trigger: &PgEventTrigger) {
+    for command in trigger.ddl_commands() {
+        notice!("{} {}", command.command_tag, command.object_identity);
+    }
+}
+```
+
+Generates the SQL for `CREATE FUNCTION log_table_ddl() RETURNS event_trigger`, and, when given an
+`event`, the `CREATE EVENT TRIGGER` which executes it.  Event triggers can only be created by
+superusers.
+
+Optionally accepts the following attributes:
+
+* `event`: The event to create an event trigger for, one of `ddl_command_start`,
+  `ddl_command_end`, `sql_drop`, or `table_rewrite`.
+* `tags`: The command tags the event trigger is limited to, such as `"DROP TABLE"`.
+
+Along with the same attributes as [`macro@pg_extern`], such as `name` and `sql`.
+*/
+#[proc_macro_attribute]
+pub fn pg_event_trigger(attr: TokenStream, item: TokenStream) -> TokenStream {
+    fn wrapped(attr: TokenStream, item: TokenStream) -> Result<TokenStream, syn::Error> {
+        let (event_trigger, extern_attr) = PgEventTrigger::split_attributes(attr.into())?;
+        let args = parse_extern_attributes(extern_attr.clone());
+
+        let mut trigger_fn = syn::parse::<ItemFn>(item)?;
+        let ident = trigger_fn.sig.ident.clone();
+        let attrs = std::mem::take(&mut trigger_fn.attrs);
+        let vis = std::mem::replace(&mut trigger_fn.vis, syn::Visibility::Inherited);
+
+        // as with `#[pg_trigger]`, the function is nested inside the `#[pg_extern]` function of
+        // the same name
+        let extern_fn = quote! {
+            #(#attrs)*
+            #vis unsafe fn #ident(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+                #trigger_fn
+
+                let trigger = ::pgx::PgEventTrigger::from_fcinfo(fcinfo);
+                ::pgx::IntoEventTriggerResult::into_event_trigger_result(#ident(&trigger));
+                0 as pg_sys::Datum
+            }
+        };
+
+        let sql_graph_entity_item =
+            PgExtern::new(extern_attr, extern_fn.clone())?.with_event_trigger(event_trigger);
+        let func = syn::parse2::<ItemFn>(extern_fn)?;
+        Ok(rewrite_item_fn(func, args, &sql_graph_entity_item).into())
+    }
+
+    match wrapped(attr, item) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
+/**
+Declare a function as `#[pg_window]` to use it as a window function, which is called with an
+`OVER (...)` clause.
+
+The function is passed the [`PgWindow`](pgx::PgWindow) it's computed over, followed by a
+[`WindowArg<T>`](pgx::WindowArg) for each of its SQL arguments.  A window function's arguments
+aren't passed to it directly, but are read from any row of the partition or frame through the
+`PgWindow`.
+
+```rust,ignore
+use pgx::*;
+
+#[pg_window]
+fn weighted_avg(window: &PgWindow, value: WindowArg<f64>, weight: WindowArg<f64>) -> Option<f64> {
+    let (mut sum, mut weights) = (0.0, 0.0);
+    for row in 0.. {
+        match (
+            window.arg_in_frame(value, row, WindowSeek::Head, false),
+            window.arg_in_frame(weight, row, WindowSeek::Head, false),
+        ) {
+            (Some(Some(value)), Some(Some(weight))) => {
+                sum += value * weight;
+                weights += weight;
+            }
+            (Some(_), Some(_)) => continue,
+            _ => break,
+        }
+    }
+    (weights != 0.0).then(|| sum / weights)
+}
+```
+
+Generates the SQL for `CREATE FUNCTION weighted_avg(value double precision, weight double precision)
+RETURNS double precision WINDOW`, which is called like
+`SELECT weighted_avg(price, volume) OVER (ORDER BY day ROWS 6 PRECEDING) FROM trades`.
+
+Optionally accepts the same attributes as [`macro@pg_extern`], such as `name` and `immutable`.
+*/
+#[proc_macro_attribute]
+pub fn pg_window(attr: TokenStream, item: TokenStream) -> TokenStream {
+    fn wrapped(attr: TokenStream, item: TokenStream) -> Result<TokenStream, syn::Error> {
+        let mut window_fn = syn::parse::<ItemFn>(item)?;
+        let ident = window_fn.sig.ident.clone();
+        let attrs = std::mem::take(&mut window_fn.attrs);
+        let vis = std::mem::replace(&mut window_fn.vis, syn::Visibility::Inherited);
+        let output = &window_fn.sig.output;
+
+        if window_fn.sig.inputs.is_empty() {
+            return Err(syn::Error::new(
+                window_fn.sig.span(),
+                "`#[pg_window]` functions must accept a `&PgWindow`",
+            ));
+        }
+
+        // the SQL arguments are declared by the `WindowArg<T>`s after the window, and are read
+        // through the window rather than passed, so they're `Option`s which are always NULL
+        let mut arg_names = Vec::new();
+        let mut arg_types = Vec::new();
+        for input in window_fn.sig.inputs.iter().skip(1) {
+            let pat_type = match input {
+                syn::FnArg::Typed(pat_type) => Some(pat_type),
+                syn::FnArg::Receiver(_) => None,
+            };
+            match pat_type
+                .and_then(|pat_type| Some((&*pat_type.pat, window_arg_type(&pat_type.ty)?)))
+            {
+                Some((syn::Pat::Ident(pat_ident), arg_type)) => {
+                    arg_names.push(pat_ident.ident.clone());
+                    arg_types.push(arg_type);
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        input.span(),
+                        "`#[pg_window]` arguments after the window must be `name: WindowArg<T>`",
+                    ))
+                }
+            }
+        }
+        let arg_numbers = 0..arg_types.len() as i32;
+
+        // as with `#[pg_trigger]`, the function is nested inside the `#[pg_extern]` function of
+        // the same name
+        let extern_fn = quote! {
+            #(#attrs)*
+            #[allow(unused_variables)]
+            #vis unsafe fn #ident(#(#arg_names: Option<#arg_types>,)* fcinfo: pg_sys::FunctionCallInfo) #output {
+                #window_fn
+
+                let window = ::pgx::PgWindow::from_fcinfo(fcinfo);
+                #ident(&window, #(::pgx::WindowArg::<#arg_types>::new(#arg_numbers)),*)
+            }
+        };
+
+        let attr = proc_macro2::TokenStream::from(attr);
+        let attr = if attr.is_empty() {
+            quote! { window }
+        } else {
+            quote! { window, #attr }
+        };
+        Ok(pg_extern(attr.into(), extern_fn.into()))
+    }
+
+    /// The `T` of a `WindowArg<T>`
+    fn window_arg_type(ty: &syn::Type) -> Option<syn::Type> {
+        let segment = match ty {
+            syn::Type::Path(type_path) => type_path.path.segments.last()?,
+            _ => return None,
+        };
+        match &segment.arguments {
+            syn::PathArguments::AngleBracketed(arguments) if segment.ident == "WindowArg" => {
+                match arguments.args.first() {
+                    Some(syn::GenericArgument::Type(arg_type)) => Some(arg_type.clone()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    match wrapped(attr, item) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
+/**
+Declare a function as `#[pg_procedure]` to create it with `CREATE PROCEDURE`, which is invoked with
+`CALL` rather than in a query.
+
+The function may accept a [`&PgProcedure`](pgx::PgProcedure) before its SQL arguments, which can
+commit or roll back the transaction `CALL` is in, and start a new one.  As the commit frees the
+memory the arguments were passed in, they must be owned types, such as `String` rather than `&str`.
+Procedures don't return anything.
+
+```rust,ignore
+use pgx::*;
+
+#[pg_procedure]
+fn archive_dogs(procedure: &PgProcedure, batch_size: i32) {
+    loop {
+        let archived = Spi::get_one::<i64>(&format!(
+            "WITH archived AS (
+                DELETE FROM dogs WHERE id IN (SELECT id FROM dogs LIMIT {}) RETURNING *
+            ), inserted AS (
+                INSERT INTO dog_archive SELECT * FROM archived RETURNING 1
+            ) SELECT count(*) FROM inserted",
+            batch_size
+        ));
+        procedure.commit();
+        if archived.unwrap_or(0) == 0 {
+            break;
+        }
+    }
+}
+```
+
+Generates the SQL for `CREATE PROCEDURE archive_dogs(batch_size integer)`, which is invoked with
+`CALL archive_dogs(1000)`.  Only a procedure `CALL`ed outside of a transaction block, or from another
+procedure `CALL`ed that way, can end its transaction.
+
+Optionally accepts the same attributes as [`macro@pg_extern`], such as `name` and `sql`, except
+those which only apply to functions, such as `immutable`, `strict`, and `parallel_safe`.
+
+Procedures require Postgres 11 or later.
+*/
+#[proc_macro_attribute]
+pub fn pg_procedure(attr: TokenStream, item: TokenStream) -> TokenStream {
+    fn wrapped(attr: TokenStream, item: TokenStream) -> Result<TokenStream, syn::Error> {
+        let attr = proc_macro2::TokenStream::from(attr);
+        let args = parse_extern_attributes(attr.clone());
+        if let Some(arg) = args.iter().find(|arg| {
+            matches!(
+                arg,
+                ExternArgs::Immutable
+                    | ExternArgs::Stable
+                    | ExternArgs::Volatile
+                    | ExternArgs::Strict
+                    | ExternArgs::ParallelSafe
+                    | ExternArgs::ParallelUnsafe
+                    | ExternArgs::ParallelRestricted
+                    | ExternArgs::Window
+                    | ExternArgs::Cost(_)
+                    | ExternArgs::Rows(_)
+                    | ExternArgs::Leakproof
+                    | ExternArgs::Support(_)
+            )
+        }) {
+            return Err(syn::Error::new(
+                attr.span(),
+                format!("`#[pg_procedure]` functions can't be `{}`", arg),
+            ));
+        }
+
+        let mut procedure_fn = syn::parse::<ItemFn>(item)?;
+        if let syn::ReturnType::Type(_, ty) = &procedure_fn.sig.output {
+            return Err(syn::Error::new(
+                ty.span(),
+                "`#[pg_procedure]` functions can't return anything",
+            ));
+        }
+        let ident = procedure_fn.sig.ident.clone();
+        let attrs = std::mem::take(&mut procedure_fn.attrs);
+        let vis = std::mem::replace(&mut procedure_fn.vis, syn::Visibility::Inherited);
+
+        // the SQL arguments are those after the optional `&PgProcedure`, and are passed through
+        let mut inputs = procedure_fn.sig.inputs.iter().peekable();
+        let procedure = inputs
+            .next_if(|input| is_procedure_arg(input))
+            .map(|_| quote! { &procedure, });
+        let inputs = inputs.cloned().collect::<Vec<_>>();
+        let mut arg_names = Vec::new();
+        for input in &inputs {
+            match input {
+                syn::FnArg::Typed(pat_type) => {
+                    // a commit frees the memory the arguments were passed in
+                    if borrows(&pat_type.ty) {
+                        return Err(syn::Error::new(
+                            pat_type.ty.span(),
+                            "`#[pg_procedure]` arguments can't borrow from Postgres' memory, which \
+                             the procedure's commit frees, so must be owned, such as `String`",
+                        ));
+                    }
+                    match &*pat_type.pat {
+                        syn::Pat::Ident(pat_ident) => arg_names.push(pat_ident.ident.clone()),
+                        _ => {
+                            return Err(syn::Error::new(
+                                input.span(),
+                                "`#[pg_procedure]` arguments must be `name: Type`",
+                            ))
+                        }
+                    }
+                }
+                syn::FnArg::Receiver(_) => {
+                    return Err(syn::Error::new(
+                        input.span(),
+                        "`#[pg_procedure]` functions can't accept `self`",
+                    ))
+                }
+            }
+        }
+
+        // as with `#[pg_trigger]`, the function is nested inside the `#[pg_extern]` function of
+        // the same name
+        let extern_fn = quote! {
+            #(#attrs)*
+            #vis unsafe fn #ident(#(#inputs,)* fcinfo: pg_sys::FunctionCallInfo) {
+                #procedure_fn
+
+                let procedure = ::pgx::PgProcedure::from_fcinfo(fcinfo);
+                #ident(#procedure #(#arg_names),*)
+            }
+        };
+
+        let sql_graph_entity_item = PgExtern::new(attr, extern_fn.clone())?.with_procedure();
+        let func = syn::parse2::<ItemFn>(extern_fn)?;
+        Ok(rewrite_item_fn(func, args, &sql_graph_entity_item).into())
+    }
+
+    /// Does this type borrow, being or containing a reference, or having a lifetime, as
+    /// `&str`, `Option<&str>`, and `Array<'a, i32>` do?
+    fn borrows(ty: &syn::Type) -> bool {
+        match ty {
+            syn::Type::Reference(_) => true,
+            syn::Type::Array(array) => borrows(&array.elem),
+            syn::Type::Slice(slice) => borrows(&slice.elem),
+            syn::Type::Group(group) => borrows(&group.elem),
+            syn::Type::Paren(paren) => borrows(&paren.elem),
+            syn::Type::Tuple(tuple) => tuple.elems.iter().any(borrows),
+            syn::Type::Path(type_path) => {
+                type_path
+                    .path
+                    .segments
+                    .iter()
+                    .any(|segment| match &segment.arguments {
+                        syn::PathArguments::AngleBracketed(args) => {
+                            args.args.iter().any(|arg| match arg {
+                                syn::GenericArgument::Lifetime(_) => true,
+                                syn::GenericArgument::Type(ty) => borrows(ty),
+                                _ => false,
+                            })
+                        }
+                        _ => false,
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    /// Is this a `&PgProcedure` argument?
+    fn is_procedure_arg(input: &syn::FnArg) -> bool {
+        let ty = match input {
+            syn::FnArg::Typed(pat_type) => &*pat_type.ty,
+            syn::FnArg::Receiver(_) => return false,
+        };
+        match ty {
+            syn::Type::Reference(reference) => match &*reference.elem {
+                syn::Type::Path(type_path) => matches!(
+                    type_path.path.segments.last(),
+                    Some(segment) if segment.ident == "PgProcedure"
+                ),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    match wrapped(attr, item) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
+/**
+Declare a function as `#[pg_language_handler]` to use it as the call handler of a procedural
+language, which is called for every call of a function written in the language.
+
+The function is passed the [`PgLanguageCall`](pgx::PgLanguageCall), which has the called
+function's source code and arguments, and returns the called function's result as an
+`Option<pg_sys::Datum>`, where `None` is NULL, or a `Result` of one, whose `Err` raises an ERROR
+with its message.
+
+```rust,ignore
+use pgx::*;
+
+#[pg_language_handler(language = "plecho", validator = "plecho_validator", requires = [plecho_validator])]
+fn plecho_call_handler(call: &PgLanguageCall) -> Option<pg_sys::Datum> {
+    call.function().src.clone().into_datum()
+}
+```
+
+Generates the SQL for `CREATE FUNCTION plecho_call_handler() RETURNS language_handler`, and, when
+given a `language`, the `CREATE LANGUAGE plecho HANDLER plecho_call_handler`, after which
+`CREATE FUNCTION ... LANGUAGE plecho AS '...'` creates functions in it.
+
+Optionally accepts the following attributes:
+
+* `language`: The name of the language to create with the handler.
+* `trusted`: Create a `TRUSTED` language, which unprivileged users may create functions in.
+* `validator`: The SQL name of the language's validator function, such as a
+  [`macro@pg_language_validator`] function, which must be created first, such as by `requires`.
+
+Along with the same attributes as [`macro@pg_extern`], such as `name` and `sql`.
+*/
+#[proc_macro_attribute]
+pub fn pg_language_handler(attr: TokenStream, item: TokenStream) -> TokenStream {
+    fn wrapped(attr: TokenStream, item: TokenStream) -> Result<TokenStream, syn::Error> {
+        let (handler, extern_attr) = PgLanguageHandler::split_attributes(attr.into())?;
+        let args = parse_extern_attributes(extern_attr.clone());
+
+        let mut handler_fn = syn::parse::<ItemFn>(item)?;
+        let ident = handler_fn.sig.ident.clone();
+        let attrs = std::mem::take(&mut handler_fn.attrs);
+        let vis = std::mem::replace(&mut handler_fn.vis, syn::Visibility::Inherited);
+
+        // as with `#[pg_trigger]`, the function is nested inside the `#[pg_extern]` function of
+        // the same name
+        let extern_fn = quote! {
+            #(#attrs)*
+            #vis unsafe fn #ident(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+                #handler_fn
+
+                let call = ::pgx::PgLanguageCall::from_fcinfo(fcinfo);
+                ::pgx::IntoLanguageCallResult::into_language_call_result(#ident(&call), fcinfo)
+            }
+        };
+
+        let sql_graph_entity_item =
+            PgExtern::new(extern_attr, extern_fn.clone())?.with_language_handler(handler);
+        let func = syn::parse2::<ItemFn>(extern_fn)?;
+        Ok(rewrite_item_fn(func, args, &sql_graph_entity_item).into())
+    }
+
+    match wrapped(attr, item) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
+/**
+Declare a function as `#[pg_language_validator]` to use it as the validator of a procedural
+language, which checks each function created in the language.
+
+The function is passed the [`PgLanguageFunction`](pgx::PgLanguageFunction) being created, and
+returns `()` or a `Result<(), E>`, whose `Err` raises an ERROR with its message, rejecting the
+function.  It's only called when the caller is allowed to validate the function.  Checking the
+function's source code should be skipped when [`check_function_bodies`](pgx::check_function_bodies)
+is off, as it is while restoring a dump.
+
+```rust,ignore
+use pgx::*;
+
+#[pg_language_validator]
+fn plecho_validator(function: &PgLanguageFunction) -> Result<(), String> {
+    if check_function_bodies() && function.src.is_empty() {
+        return Err(format!("{} has nothing to echo", function.name));
+    }
+    Ok(())
+}
+```
+
+Generates the SQL for `CREATE FUNCTION plecho_validator(function_oid oid) RETURNS void`, to be
+named by the `validator` of a [`macro@pg_language_handler`].
+
+Optionally accepts the same attributes as [`macro@pg_extern`], such as `name` and `sql`.
+*/
+#[proc_macro_attribute]
+pub fn pg_language_validator(attr: TokenStream, item: TokenStream) -> TokenStream {
+    fn wrapped(attr: TokenStream, item: TokenStream) -> Result<TokenStream, syn::Error> {
+        let attr = proc_macro2::TokenStream::from(attr);
+        let args = parse_extern_attributes(attr.clone());
+
+        let mut validator_fn = syn::parse::<ItemFn>(item)?;
+        let ident = validator_fn.sig.ident.clone();
+        let attrs = std::mem::take(&mut validator_fn.attrs);
+        let vis = std::mem::replace(&mut validator_fn.vis, syn::Visibility::Inherited);
+
+        // as with `#[pg_trigger]`, the function is nested inside the `#[pg_extern]` function of
+        // the same name, whose only SQL argument is the oid of the function to validate
+        let extern_fn = quote! {
+            #(#attrs)*
+            #vis unsafe fn #ident(function_oid: pg_sys::Oid, fcinfo: pg_sys::FunctionCallInfo) {
+                #validator_fn
+
+                if let Some(function) = ::pgx::PgLanguageFunction::for_validator(fcinfo, function_oid) {
+                    ::pgx::IntoLanguageValidatorResult::into_language_validator_result(#ident(&function))
+                }
+            }
+        };
+
+        let sql_graph_entity_item = PgExtern::new(attr, extern_fn.clone())?;
+        let func = syn::parse2::<ItemFn>(extern_fn)?;
+        Ok(rewrite_item_fn(func, args, &sql_graph_entity_item).into())
+    }
+
+    match wrapped(attr, item) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
+fn rewrite_item_fn(
+    mut func: ItemFn,
+    extern_args: HashSet<ExternArgs>,
+    sql_graph_entity_submission: &PgExtern,
+) -> proc_macro2::TokenStream {
+    let is_raw = extern_args.contains(&ExternArgs::Raw);
+    let no_guard = extern_args.contains(&ExternArgs::NoGuard);
+
+    let finfo_name = syn::Ident::new(
+        &format!("pg_finfo_{}_wrapper", func.sig.ident),
+        Span::call_site(),
+    );
+
+    // use the PgGuardRewriter to go ahead and wrap the function here, rather than applying
+    // a #[pg_guard] macro to the original function.  This is necessary so that compiler
+    // errors/warnings indicate the proper line numbers
+    let rewriter = PgGuardRewriter::new();
+
+    // make the function 'extern "C"' because this is for the #[pg_extern[ macro
+    func.sig.abi = Some(syn::parse_str("extern \"C\"").unwrap());
+    let func_span = func.span();
+    let (rewritten_func, need_wrapper) = rewriter.item_fn(
+        func,
+        Some(sql_graph_entity_submission),
+        true,
+        is_raw,
+        no_guard,
+    );
+
+    if need_wrapper {
+        quote_spanned! {func_span=>
+            #[no_mangle]
+            #[doc(hidden)]
+            pub extern "C" fn #finfo_name() -> &'static pg_sys::Pg_finfo_record {
+                const V1_API: pg_sys::Pg_finfo_record = pg_sys::Pg_finfo_record { api_version: 1 };
+                &V1_API
+            }
+
+            #rewritten_func
+        }
+    } else {
+        quote_spanned! {func_span=>
+
+            #rewritten_func
+        }
+    }
+}
+
+/**
+Generate necessary bindings for using the enum with PostgreSQL.
+
+```rust,ignore
+# use pgx_pg_sys as pg_sys;
+use pgx::*;
+use serde::{Deserialize, Serialize};
+#[derive(Debug, Serialize, Deserialize, PostgresEnum)]
+enum DogNames {
+    Nami,
+    Brandy,
+}
+```
+
+*/
+#[proc_macro_derive(PostgresEnum, attributes(requires, pgx))]
+pub fn postgres_enum(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+
+    impl_postgres_enum(ast).into()
+}
+
+fn impl_postgres_enum(ast: DeriveInput) -> proc_macro2::TokenStream {
+    let mut stream = proc_macro2::TokenStream::new();
+    let sql_graph_entity_ast = ast.clone();
+    let enum_ident = ast.ident;
+    let enum_name = enum_ident.to_string();
+
+    // validate that we're only operating on an enum
+    let enum_data = match ast.data {
+        Data::Enum(e) => e,
+        _ => panic!("#[derive(PostgresEnum)] can only be applied to enums"),
+    };
+
+    let mut from_datum = proc_macro2::TokenStream::new();
+    let mut into_datum = proc_macro2::TokenStream::new();
+
+    for d in enum_data.variants.clone() {
+        let label_ident = &d.ident;
+        let label_string = label_ident.to_string();
+
+        from_datum.extend(quote! { #label_string => Some(#enum_ident::#label_ident), });
+        into_datum.extend(quote! { #enum_ident::#label_ident => Some(pgx::lookup_enum_by_label(#enum_name, #label_string)), });
+    }
+
+    stream.extend(quote! {
+        impl pgx::FromDatum for #enum_ident {
+            #[inline]
+            unsafe fn from_datum(datum: pgx::pg_sys::Datum, is_null: bool, typeoid: pgx::pg_sys::Oid) -> Option<#enum_ident> {
+                if is_null {
+                    None
+                } else {
+                    let (name, _, _) = pgx::lookup_enum_by_oid(datum as pgx::pg_sys::Oid);
+                    match name.as_str() {
+                        #from_datum
+                        _ => panic!("invalid enum value: {}", name)
+                    }
+                }
+            }
+        }
+
+        impl pgx::IntoDatum for #enum_ident {
+            #[inline]
+            fn into_datum(self) -> Option<pgx::pg_sys::Datum> {
+                match self {
+                    #into_datum
+                }
+            }
+
+            fn type_oid() -> pg_sys::Oid {
+                pgx::regtypein(#enum_name)
+            }
+
+        }
+    });
+
+    let sql_graph_entity_item = PostgresEnum::from_derive_input(sql_graph_entity_ast).unwrap();
+    sql_graph_entity_item.to_tokens(&mut stream);
+
+    stream
+}
+
+/**
+Generate necessary bindings for using the type with PostgreSQL.
+
+```rust,ignore
+# use pgx_pg_sys as pg_sys;
 use pgx::*;
 use serde::{Deserialize, Serialize};
 #[derive(Debug, Serialize, Deserialize, PostgresType)]
@@ -886,6 +1881,485 @@ fn impl_guc_enum(ast: DeriveInput) -> proc_macro2::TokenStream {
     stream
 }
 
+/**
+Generate a [`PgConfig`](pgx::PgConfig) implementation, declaring each of a struct's fields as a
+GUC, named like the field and prefixed by the extension's name, as in `my_ext.batch_size`.
+
+```rust,ignore
+use pgx::*;
+
+#[derive(PgConfig)]
+#[pg_config(prefix = "my_ext")]
+struct Config {
+    /// The number of rows in a batch
+    #[pg_config(default = 64, min = 1, max = 1024)]
+    batch_size: i32,
+    /// Should rows be compressed?
+    compress: bool,
+    /// The schemas to search
+    ///
+    /// Schemas which don't exist are ignored.
+    #[pg_config(default = ["public"], context = Suset)]
+    schemas: Vec<String>,
+}
+
+#[pg_guard]
+pub extern "C" fn _PG_init() {
+    Config::define();
+}
+
+#[pg_extern]
+fn batch_size() -> i32 {
+    Config::snapshot().batch_size
+}
+```
+
+A field's short description is the first paragraph of its doc comment, and its long description
+is the rest.  A field is a GUC of the kind of its type, which is one of `bool`, `i32`, `f64`,
+`Option<String>`, `Vec<String>`, for a [list GUC](pgx::GucRegistry::define_list_guc), or an enum
+which is `#[derive(PostgresGucEnum)]`.
+
+It also generates a `settings()` function, so a DBA can discover the extension's configuration
+without reading its source.  It returns each GUC the extension defined, with its current setting,
+its default, and its description, from
+[`GucRegistry::describe`](pgx::GucRegistry::describe):
+
+```sql
+SELECT name, setting, default_setting, description FROM my_ext.settings();
+```
+
+Optionally accepts the following arguments of `#[pg_config(...)]` on the struct:
+
+* `prefix = "my_ext"`: Prefix the GUCs' names with `my_ext.` rather than the crate's name.
+* `settings_function = "name"`: Name the `settings()` function otherwise, as all but one of the
+  structs deriving `PgConfig` in a schema must.
+
+Optionally accepts the following arguments of `#[pg_config(...)]` on fields:
+
+* `name = "name"`: Name the GUC rather than naming it like the field.
+* `default = value`: The GUC's default, which otherwise is `false`, zero, `None`, or empty, and
+  which an enum's GUC must have.
+* `min = value`, `max = value`: The range of an `i32` or `f64` GUC.
+* `context = Suset`: The [`GucContext`](pgx::GucContext) of the GUC, which otherwise is `Userset`.
+* `flags = GucFlags::UNIT_KB`: The [`GucFlags`](pgx::GucFlags) of the GUC.
+*/
+#[proc_macro_derive(PgConfig, attributes(pg_config))]
+pub fn pg_config(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+
+    match pg_config::impl_pg_config(ast) {
+        Ok(stream) => stream.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/**
+Generate a [`PgStats`](pgx::PgStats) implementation, declaring each of a struct's fields as a
+statistic kept in shared memory, and a view showing them, like Postgres' own `pg_stat_*` views.
+
+```rust,ignore
+use pgx::*;
+
+#[derive(PgStats, Default)]
+#[repr(C)]
+#[pg_stats(view = "my_ext_stats")]
+struct Stats {
+    /// Rows processed since the counters were reset
+    rows_processed: PgStatCounter,
+    /// Rows waiting to be processed
+    queue_depth: PgStatGauge,
+}
+
+#[pg_guard]
+pub extern "C" fn _PG_init() {
+    Stats::init();
+}
+
+#[pg_extern]
+fn process(rows: i64) {
+    Stats::get().rows_processed.add(rows as u64);
+}
+```
+
+Each field is a [`PgStatCounter`](pgx::PgStatCounter) or a [`PgStatGauge`](pgx::PgStatGauge),
+and is described by the first paragraph of its doc comment.  The struct must be `#[repr(C)]` and
+implement `Default`, and the extension must be loaded by `shared_preload_libraries`.
+
+The view has a row for each statistic, with its `name`, its `kind`, which is `counter` or `gauge`,
+its `value`, and its `description`.  A superuser, or a role they grant `EXECUTE` to, resets the
+counters with the reset function:
+
+```sql
+SELECT name, value FROM my_ext_stats;
+SELECT my_ext_stats_reset();
+```
+
+Optionally accepts the following arguments of `#[pg_stats(...)]` on the struct:
+
+* `view = "name"`: Name the view otherwise than after the crate, as in `my_ext_stats`, as all but
+  one of the structs deriving `PgStats` in an extension must.  The view reads the function
+  `name_values()`.
+* `reset_function = "name"`: Name the reset function otherwise than `view_reset`.
+*/
+#[proc_macro_derive(PgStats, attributes(pg_stats))]
+pub fn pg_stats(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+
+    match pg_stats::impl_pg_stats(ast) {
+        Ok(stream) => stream.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/**
+Generate a [`FromSpiRow`](pgx::FromSpiRow) implementation, building a struct from a row of an SPI
+result by matching its fields to the row's columns by name.
+
+```rust,ignore
+use pgx::*;
+
+#[derive(FromSpiRow)]
+struct Dog {
+    name: String,
+    #[spi(rename = "scritches")]
+    scritch_count: Option<i32>,
+}
+```
+
+Fields of type `Option<T>` are `None` when their column is NULL.  A NULL column for any other field
+is an error.
+
+Optionally accepts the following attributes on fields:
+
+* `#[spi(rename = "column")]`: Read the field from the named column rather than the one named like
+  the field.
+*/
+#[proc_macro_derive(FromSpiRow, attributes(spi))]
+pub fn from_spi_row(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+
+    match impl_from_spi_row(ast) {
+        Ok(stream) => stream.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn impl_from_spi_row(ast: DeriveInput) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let mut field_inits = proc_macro2::TokenStream::new();
+    for field in named_fields(&ast, "FromSpiRow")? {
+        let ident = field.ident.as_ref().expect("named field without a name");
+        let column = match rename_attribute(&field.attrs, "spi")? {
+            Some(column) => column,
+            None => ident.to_string(),
+        };
+
+        field_inits.extend(match option_inner_type(&field.ty) {
+            Some(inner) => quote! {
+                #ident: row.get_by_name::<#inner>(#column)?,
+            },
+            None => {
+                let ty = &field.ty;
+                quote! {
+                    #ident: row.get_by_name::<#ty>(#column)?.ok_or_else(|| {
+                        ::pgx::FromSpiRowError::UnexpectedNull(#column.to_string())
+                    })?,
+                }
+            }
+        });
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::pgx::FromSpiRow for #name #ty_generics #where_clause {
+            fn from_spi_row(row: &::pgx::SpiHeapTupleData) -> ::std::result::Result<Self, ::pgx::FromSpiRowError> {
+                Ok(Self {
+                    #field_inits
+                })
+            }
+        }
+    })
+}
+
+/**
+Generate a [`FromHeapTuple`](pgx::FromHeapTuple) implementation, building a struct from a
+[`PgHeapTuple`](pgx::PgHeapTuple) by matching its fields to the tuple's attributes by name.
+
+```rust,ignore
+use pgx::*;
+
+#[derive(FromHeapTuple)]
+struct Dog {
+    name: String,
+    #[heap_tuple(rename = "scritches")]
+    scritch_count: Option<i32>,
+}
+```
+
+Fields of type `Option<T>` are `None` when their attribute is NULL.  A NULL attribute for any other
+field is an error.
+
+Optionally accepts the following attributes on fields:
+
+* `#[heap_tuple(rename = "attribute")]`: Read the field from the named attribute rather than the
+  one named like the field.
+*/
+#[proc_macro_derive(FromHeapTuple, attributes(heap_tuple))]
+pub fn from_heap_tuple(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+
+    match impl_from_heap_tuple(ast) {
+        Ok(stream) => stream.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn impl_from_heap_tuple(ast: DeriveInput) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let mut field_inits = proc_macro2::TokenStream::new();
+    for field in named_fields(&ast, "FromHeapTuple")? {
+        let ident = field.ident.as_ref().expect("named field without a name");
+        let attribute = match rename_attribute(&field.attrs, "heap_tuple")? {
+            Some(attribute) => attribute,
+            None => ident.to_string(),
+        };
+
+        field_inits.extend(match option_inner_type(&field.ty) {
+            Some(inner) => quote! {
+                #ident: tuple.get_by_name::<#inner>(#attribute)?,
+            },
+            None => {
+                let ty = &field.ty;
+                quote! {
+                    #ident: tuple.get_by_name::<#ty>(#attribute)?.ok_or_else(|| {
+                        ::pgx::PgHeapTupleError::UnexpectedNull(#attribute.to_string())
+                    })?,
+                }
+            }
+        });
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::pgx::FromHeapTuple for #name #ty_generics #where_clause {
+            fn from_heap_tuple(tuple: &::pgx::PgHeapTuple) -> ::std::result::Result<Self, ::pgx::PgHeapTupleError> {
+                Ok(Self {
+                    #field_inits
+                })
+            }
+        }
+    })
+}
+
+/**
+Generate an [`IntoHeapTuple`](pgx::IntoHeapTuple) implementation, writing the fields of a struct
+to the attributes of a [`PgHeapTuple`](pgx::PgHeapTuple) with the same names.
+
+```rust,ignore
+use pgx::*;
+
+#[derive(IntoHeapTuple)]
+struct Dog {
+    name: String,
+    #[heap_tuple(rename = "scritches")]
+    scritch_count: Option<i32>,
+}
+```
+
+Fields of type `Option<T>` set their attribute to NULL when they're `None`.
+
+Optionally accepts the following attributes on fields:
+
+* `#[heap_tuple(rename = "attribute")]`: Write the field to the named attribute rather than the
+  one named like the field.
+*/
+#[proc_macro_derive(IntoHeapTuple, attributes(heap_tuple))]
+pub fn into_heap_tuple(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+
+    match impl_into_heap_tuple(ast) {
+        Ok(stream) => stream.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn impl_into_heap_tuple(ast: DeriveInput) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let mut field_sets = proc_macro2::TokenStream::new();
+    for field in named_fields(&ast, "IntoHeapTuple")? {
+        let ident = field.ident.as_ref().expect("named field without a name");
+        let attribute = match rename_attribute(&field.attrs, "heap_tuple")? {
+            Some(attribute) => attribute,
+            None => ident.to_string(),
+        };
+
+        field_sets.extend(quote! {
+            tuple.set_by_name(#attribute, self.#ident)?;
+        });
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::pgx::IntoHeapTuple for #name #ty_generics #where_clause {
+            fn into_heap_tuple(self, tuple: &mut ::pgx::PgHeapTuple) -> ::std::result::Result<(), ::pgx::PgHeapTupleError> {
+                #field_sets
+                Ok(())
+            }
+        }
+    })
+}
+
+/**
+Generate a [`TableRow`](pgx::TableRow) implementation, so that a struct can be the row of a
+function returning a [`TableIterator`](pgx::TableIterator), whose fields are the columns of its
+`RETURNS TABLE (...)`.
+
+```rust,ignore
+use pgx::*;
+
+#[derive(TableRow)]
+struct Dog {
+    name: String,
+    #[table_row(rename = "scritches")]
+    scritch_count: Option<i32>,
+}
+
+#[pg_extern]
+fn dogs() -> TableIterator<'static, Dog> {
+    TableIterator::once(Dog { name: "Nami".into(), scritch_count: Some(42) })
+}
+```
+
+Generates the SQL for `CREATE FUNCTION dogs() RETURNS TABLE (name text, scritches integer)`.
+Fields of type `Option<T>` are NULL when they're `None`.
+
+Optionally accepts the following attributes on fields:
+
+* `#[table_row(rename = "column")]`: Name the field's column rather than naming it like the field.
+*/
+#[proc_macro_derive(TableRow, attributes(table_row))]
+pub fn table_row(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+
+    match impl_table_row(ast) {
+        Ok(stream) => stream.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn impl_table_row(ast: DeriveInput) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let mut columns = proc_macro2::TokenStream::new();
+    let mut datums = proc_macro2::TokenStream::new();
+    for field in named_fields(&ast, "TableRow")? {
+        let ident = field.ident.as_ref().expect("named field without a name");
+        let column = match rename_attribute(&field.attrs, "table_row")? {
+            Some(column) => column,
+            None => ident.to_string(),
+        };
+        let ty = &field.ty;
+        let ty_string = ty.to_token_stream().to_string().replace(" ", "");
+
+        columns.extend(quote! {
+            ::pgx::table_column::<#ty>(#ty_string, #column),
+        });
+        datums.extend(quote! {
+            ::pgx::IntoDatum::into_datum(self.#ident),
+        });
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::pgx::TableRow for #name #ty_generics #where_clause {
+            fn table_columns() -> ::std::vec::Vec<::pgx::TableColumn> {
+                vec![#columns]
+            }
+
+            fn into_datums(self) -> ::std::vec::Vec<::std::option::Option<::pgx::pg_sys::Datum>> {
+                vec![#datums]
+            }
+        }
+    })
+}
+
+/// The named fields of the struct a derive is applied to.
+fn named_fields<'a>(
+    ast: &'a DeriveInput,
+    derive: &str,
+) -> Result<&'a syn::punctuated::Punctuated<syn::Field, syn::Token![,]>, syn::Error> {
+    match &ast.data {
+        Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => Ok(&fields.named),
+        _ => Err(syn::Error::new(
+            ast.span(),
+            format!(
+                "#[derive({})] can only be applied to structs with named fields",
+                derive
+            ),
+        )),
+    }
+}
+
+/// The `column` of a field's `#[attr(rename = "column")]` attribute, if it has one, where `attr`
+/// is the derive's helper attribute such as `spi`.
+fn rename_attribute(attrs: &[Attribute], attr_name: &str) -> Result<Option<String>, syn::Error> {
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident(attr_name)) {
+        match attr.parse_meta()? {
+            syn::Meta::List(list) => {
+                for nested in list.nested.iter() {
+                    match nested {
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                            path,
+                            lit: syn::Lit::Str(column),
+                            ..
+                        })) if path.is_ident("rename") => return Ok(Some(column.value())),
+                        other => {
+                            return Err(syn::Error::new(
+                                other.span(),
+                                "expected `rename = \"column\"`",
+                            ))
+                        }
+                    }
+                }
+            }
+            other => {
+                return Err(syn::Error::new(
+                    other.span(),
+                    format!("expected `#[{}(rename = \"column\")]`", attr_name),
+                ))
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// The `T` of an `Option<T>` type.
+fn option_inner_type(ty: &syn::Type) -> Option<&syn::Type> {
+    let path = match ty {
+        syn::Type::Path(type_path) if type_path.qself.is_none() => &type_path.path,
+        _ => return None,
+    };
+    let last = path.segments.last()?;
+    if last.ident != "Option" {
+        return None;
+    }
+    match &last.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => {
+            match args.args.first()? {
+                syn::GenericArgument::Type(inner) => Some(inner),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
 #[derive(Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
 enum PostgresTypeAttribute {
     InOutFuncs,
@@ -933,6 +2407,8 @@ enum DogNames {
 Optionally accepts the following attributes:
 
 * `sql`: Same arguments as [`#[pgx(sql = ..)]`](macro@pgx).
+* `cross_type`: A list of types to also generate `=` and `<>` operators against, requiring
+  `PartialEq<CrossType>`, eg `#[pgx(cross_type(i32))]`.
 */
 #[proc_macro_derive(PostgresEq, attributes(pgx))]
 pub fn postgres_eq(input: TokenStream) -> TokenStream {
@@ -958,9 +2434,15 @@ enum DogNames {
     Brandy,
 }
 ```
+This also creates a default `btree` operator class (and family) for the type, so it can be
+indexed and used in `ORDER BY`, `DISTINCT`, and merge joins.
+
 Optionally accepts the following attributes:
 
 * `sql`: Same arguments as [`#[pgx(sql = ..)]`](macro@pgx).
+* `cross_type`: A list of types to also generate `<`, `<=`, `>=`, `>` operators and a comparison
+  function against, requiring `PartialOrd<CrossType>`. These are added to the type's `btree`
+  operator family. The same `cross_type` list should be given to `#[derive(PostgresEq)]`.
 */
 #[proc_macro_derive(PostgresOrd, attributes(pgx))]
 pub fn postgres_ord(input: TokenStream) -> TokenStream {
@@ -983,9 +2465,14 @@ enum DogNames {
     Brandy,
 }
 ```
+This also creates a default `hash` operator class (and family) for the type, so it can be used
+in hash joins and hash aggregation.
+
 Optionally accepts the following attributes:
 
 * `sql`: Same arguments as [`#[pgx(sql = ..)]`](macro@pgx).
+* `cross_type`: A list of types whose `=` operators (from `#[derive(PostgresEq)]`) are added to
+  the type's `hash` operator family. Values which compare equal must hash identically.
 */
 #[proc_macro_derive(PostgresHash, attributes(pgx))]
 pub fn postgres_hash(input: TokenStream) -> TokenStream {
@@ -995,9 +2482,73 @@ pub fn postgres_hash(input: TokenStream) -> TokenStream {
         .into()
 }
 
+/**
+Declare a `pgx::GinOpClass` implementation on a type as a GIN operator class for that type.
+
+The operators listed in `GinOpClass::OPERATORS` must already be declared, for example with
+[`#[pg_operator]`](macro@pg_operator).
+
+```rust,ignore
+use pgx::*;
+
+#[pg_gin_opclass]
+impl GinOpClass for Tags {
+    type Key = String;
+    type Query = Tags;
+    const OPERATORS: &'static [(u16, &'static str)] = &[(1, "@>")];
+    // ...
+}
+```
+
+Optionally accepts the following attributes:
+
+* `sql`: Same arguments as [`#[pgx(sql = ..)]`](macro@pgx).
+*/
+#[proc_macro_attribute]
+pub fn pg_gin_opclass(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    pg_opclass(item, PgOpClassMethod::Gin)
+}
+
+/**
+Declare a `pgx::GistOpClass` implementation on a type as a GiST operator class for that type.
+
+The operators listed in `GistOpClass::OPERATORS` must already be declared, for example with
+[`#[pg_operator]`](macro@pg_operator).
+
+Optionally accepts the following attributes:
+
+* `sql`: Same arguments as [`#[pgx(sql = ..)]`](macro@pgx).
+*/
+#[proc_macro_attribute]
+pub fn pg_gist_opclass(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    pg_opclass(item, PgOpClassMethod::Gist)
+}
+
+fn pg_opclass(item: TokenStream, method: PgOpClassMethod) -> TokenStream {
+    fn wrapped(item_impl: ItemImpl, method: PgOpClassMethod) -> Result<TokenStream, syn::Error> {
+        let sql_graph_entity_item = PgOpClass::new(item_impl, method)?;
+
+        Ok(sql_graph_entity_item.to_token_stream().into())
+    }
+
+    let parsed_base = parse_macro_input!(item as syn::ItemImpl);
+    match wrapped(parsed_base, method) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
 /**
 Declare a `pgx::Aggregate` implentation on a type as able to used by Postgres as an aggregate.
 
+A `pgx::SimpleAggregate` implementation may be declared instead, which is made into the
+equivalent `pgx::Aggregate`.
+
 Functions inside the `impl` may use the [`#[pgx]`](macro@pgx) attribute.
 */
 #[proc_macro_attribute]
@@ -1021,6 +2572,49 @@ pub fn pg_aggregate(_attr: TokenStream, item: TokenStream) -> TokenStream {
     }
 }
 
+/**
+Declare a [`ForeignDataWrapper`](pgx::ForeignDataWrapper) implementation on a type as a Postgres
+foreign-data wrapper, generating its `fdw_handler` function and its `CREATE FOREIGN DATA WRAPPER`.
+
+```rust,ignore
+use pgx::*;
+use std::collections::HashMap;
+
+struct Numbers {
+    next: i32,
+}
+
+#[pg_fdw(name = "numbers_fdw")]
+impl ForeignDataWrapper for Numbers {
+    type Row = (i32,);
+
+    fn new(_relation: &PgRelation, _options: HashMap<String, String>) -> Self {
+        Numbers { next: 0 }
+    }
+
+    fn iterate_scan(&mut self) -> Option<Self::Row> {
+        self.next += 1;
+        (self.next <= 10).then(|| (self.next,))
+    }
+}
+```
+
+Optionally accepts the following arguments:
+
+* `name = "name"`: Name the foreign-data wrapper otherwise than after the type, as in
+  `numbers_fdw`.
+* `handler = "name"`: Name the handler function otherwise than `name_handler`.
+*/
+#[proc_macro_attribute]
+pub fn pg_fdw(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item_impl = parse_macro_input!(item as syn::ItemImpl);
+
+    match pg_fdw::impl_pg_fdw(attr.into(), item_impl) {
+        Ok(stream) => stream.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
 /**
 A helper attribute for various contexts.
 
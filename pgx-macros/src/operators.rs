@@ -8,7 +8,7 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 use pgx_utils::{
     operator_common::*,
-    sql_entity_graph::{PostgresHash, PostgresOrd},
+    sql_entity_graph::{CrossType, PostgresHash, PostgresOrd},
 };
 
 use quote::ToTokens;
@@ -20,6 +20,11 @@ pub(crate) fn impl_postgres_eq(ast: DeriveInput) -> syn::Result<proc_macro2::Tok
     stream.extend(eq(&ast.ident));
     stream.extend(ne(&ast.ident));
 
+    for cross in CrossType::from_attributes(ast.attrs.as_slice())? {
+        stream.extend(eq_cross(&ast.ident, &cross));
+        stream.extend(ne_cross(&ast.ident, &cross));
+    }
+
     Ok(stream)
 }
 
@@ -33,6 +38,13 @@ pub(crate) fn impl_postgres_ord(ast: DeriveInput) -> syn::Result<proc_macro2::To
     stream.extend(cmp(&ast.ident));
 
     let sql_graph_entity_item = PostgresOrd::from_derive_input(ast)?;
+    for cross in &sql_graph_entity_item.cross_types {
+        stream.extend(lt_cross(&sql_graph_entity_item.name, cross));
+        stream.extend(gt_cross(&sql_graph_entity_item.name, cross));
+        stream.extend(le_cross(&sql_graph_entity_item.name, cross));
+        stream.extend(ge_cross(&sql_graph_entity_item.name, cross));
+        stream.extend(cmp_cross(&sql_graph_entity_item.name, cross));
+    }
     sql_graph_entity_item.to_tokens(&mut stream);
 
     Ok(stream)
@@ -44,6 +56,9 @@ pub(crate) fn impl_postgres_hash(ast: DeriveInput) -> syn::Result<proc_macro2::T
     stream.extend(hash(&ast.ident));
 
     let sql_graph_entity_item = PostgresHash::from_derive_input(ast)?;
+    for cross in &sql_graph_entity_item.cross_types {
+        stream.extend(hash_cross(&sql_graph_entity_item.name, cross));
+    }
     sql_graph_entity_item.to_tokens(&mut stream);
 
     Ok(stream)
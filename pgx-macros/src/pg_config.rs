@@ -0,0 +1,276 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::{named_fields, option_inner_type};
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{Attribute, DeriveInput, Expr, Token};
+
+/// A `name = value` argument of a `#[pg_config(...)]` attribute
+pub(crate) struct ConfigArg {
+    pub(crate) name: Ident,
+    pub(crate) value: Expr,
+}
+
+impl Parse for ConfigArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let value = input.parse()?;
+        Ok(ConfigArg { name, value })
+    }
+}
+
+/// The kind of GUC a field is, by its type
+enum ConfigKind {
+    Bool,
+    Int,
+    Float,
+    String,
+    List,
+    Enum,
+}
+
+impl ConfigKind {
+    fn of(ty: &syn::Type) -> Self {
+        if let Some(inner) = option_inner_type(ty) {
+            if is_type(inner, "String") {
+                return ConfigKind::String;
+            }
+        }
+        if is_vec_of_string(ty) {
+            return ConfigKind::List;
+        }
+
+        match ty.to_token_stream().to_string().as_str() {
+            "bool" => ConfigKind::Bool,
+            "i32" => ConfigKind::Int,
+            "f64" => ConfigKind::Float,
+            _ => ConfigKind::Enum,
+        }
+    }
+}
+
+pub(crate) fn impl_pg_config(ast: DeriveInput) -> syn::Result<TokenStream> {
+    let name = &ast.ident;
+    if !ast.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            ast.generics.span(),
+            "#[derive(PgConfig)] can't be applied to a generic struct",
+        ));
+    }
+
+    let mut prefix = quote! { env!("CARGO_CRATE_NAME") };
+    let mut settings_function = "settings".to_string();
+    for arg in config_args(&ast.attrs)? {
+        match arg.name.to_string().as_str() {
+            "prefix" => prefix = arg.value.to_token_stream(),
+            "settings_function" => settings_function = string_value(&arg.value)?,
+            _ => {
+                return Err(syn::Error::new(
+                    arg.name.span(),
+                    "expected `prefix` or `settings_function`",
+                ))
+            }
+        }
+    }
+
+    let mut settings = TokenStream::new();
+    let mut defines = TokenStream::new();
+    let mut fields = TokenStream::new();
+    for field in named_fields(&ast, "PgConfig")? {
+        let ident = field.ident.as_ref().expect("named field without a name");
+        let ty = &field.ty;
+        let kind = ConfigKind::of(ty);
+        let setting = format_ident!("__PGX_CONFIG_{}", ident.to_string().to_uppercase());
+
+        let mut guc_name = ident.to_string();
+        let mut default = None;
+        let mut min = None;
+        let mut max = None;
+        let mut context = quote! { ::pgx::GucContext::Userset };
+        let mut flags = quote! { ::pgx::GucFlags::default() };
+        for arg in config_args(&field.attrs)? {
+            let value = arg.value;
+            match arg.name.to_string().as_str() {
+                "name" => guc_name = string_value(&value)?,
+                "default" => default = Some(value),
+                "min" | "max" if !matches!(kind, ConfigKind::Int | ConfigKind::Float) => {
+                    return Err(syn::Error::new(
+                        arg.name.span(),
+                        "only an `i32` or `f64` setting has a `min` and `max`",
+                    ))
+                }
+                "min" => min = Some(value),
+                "max" => max = Some(value),
+                "context" => context = quote! { ::pgx::GucContext::#value },
+                "flags" => flags = value.to_token_stream(),
+                _ => {
+                    return Err(syn::Error::new(
+                        arg.name.span(),
+                        "expected `name`, `default`, `min`, `max`, `context`, or `flags`",
+                    ))
+                }
+            }
+        }
+
+        let (short_description, long_description) = descriptions(&field.attrs);
+        let guc_name = quote! { concat!(#prefix, ".", #guc_name) };
+        let description = quote! { #guc_name, #short_description, #long_description };
+
+        let (setting_ty, default, define) = match kind {
+            ConfigKind::Bool => (
+                quote! { bool },
+                default.map_or_else(|| quote! { false }, |d| d.to_token_stream()),
+                quote! { ::pgx::GucRegistry::define_bool_guc(#description, &#setting, #context, #flags) },
+            ),
+            ConfigKind::Int => {
+                let min = min.map_or_else(|| quote! { i32::MIN }, |m| m.to_token_stream());
+                let max = max.map_or_else(|| quote! { i32::MAX }, |m| m.to_token_stream());
+                (
+                    quote! { i32 },
+                    default.map_or_else(|| quote! { 0 }, |d| d.to_token_stream()),
+                    quote! { ::pgx::GucRegistry::define_int_guc(#description, &#setting, #min, #max, #context, #flags) },
+                )
+            }
+            ConfigKind::Float => {
+                let min = min.map_or_else(|| quote! { f64::MIN }, |m| m.to_token_stream());
+                let max = max.map_or_else(|| quote! { f64::MAX }, |m| m.to_token_stream());
+                (
+                    quote! { f64 },
+                    default.map_or_else(|| quote! { 0.0 }, |d| d.to_token_stream()),
+                    quote! { ::pgx::GucRegistry::define_float_guc(#description, &#setting, #min, #max, #context, #flags) },
+                )
+            }
+            ConfigKind::String => (
+                quote! { ::std::option::Option<&'static str> },
+                default.map_or_else(|| quote! { None }, |d| quote! { Some(#d) }),
+                quote! { ::pgx::GucRegistry::define_string_guc(#description, &#setting, #context, #flags) },
+            ),
+            ConfigKind::List => (
+                quote! { &'static [&'static str] },
+                default.map_or_else(|| quote! { &[] }, |d| quote! { &#d }),
+                quote! { ::pgx::GucRegistry::define_list_guc(#description, &#setting, #context, #flags) },
+            ),
+            ConfigKind::Enum => match default {
+                Some(default) => (
+                    ty.to_token_stream(),
+                    default.to_token_stream(),
+                    quote! { ::pgx::GucRegistry::define_enum_guc(#description, &#setting, #context, #flags) },
+                ),
+                None => {
+                    return Err(syn::Error::new(
+                        ty.span(),
+                        "a `#[derive(PostgresGucEnum)]` setting must have a `#[pg_config(default = ...)]`",
+                    ))
+                }
+            },
+        };
+
+        settings.extend(quote! {
+            static #setting: ::pgx::GucSetting<#setting_ty> = ::pgx::GucSetting::new(#default);
+        });
+        defines.extend(quote! { #define; });
+        fields.extend(quote! { #ident: #setting.get(), });
+    }
+
+    let settings_fn = format_ident!("__pgx_config_settings_{}", name.to_string().to_lowercase());
+    Ok(quote! {
+        #[doc(hidden)]
+        #[::pgx::pg_extern(stable, name = #settings_function)]
+        pub fn #settings_fn() -> ::pgx::TableIterator<'static, ::pgx::GucDescription> {
+            ::pgx::TableIterator::new(::pgx::GucRegistry::describe())
+        }
+
+        const _: () = {
+            #settings
+
+            impl ::pgx::PgConfig for #name {
+                fn define() {
+                    #defines
+                }
+
+                fn snapshot() -> Self {
+                    #name { #fields }
+                }
+            }
+        };
+    })
+}
+
+/// The arguments of every `#[pg_config(...)]` attribute in `attrs`
+fn config_args(attrs: &[Attribute]) -> syn::Result<Vec<ConfigArg>> {
+    let mut args = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("pg_config")) {
+        let parsed = attr.parse_args_with(Punctuated::<ConfigArg, Token![,]>::parse_terminated)?;
+        args.extend(parsed);
+    }
+    Ok(args)
+}
+
+pub(crate) fn string_value(value: &Expr) -> syn::Result<String> {
+    match value {
+        Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(lit),
+            ..
+        }) => Ok(lit.value()),
+        _ => Err(syn::Error::new(value.span(), "expected a string")),
+    }
+}
+
+/// A field's short and long descriptions, which are the first paragraph of its doc comment, and
+/// the rest of it
+pub(crate) fn descriptions(attrs: &[Attribute]) -> (String, String) {
+    let mut lines = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("doc")) {
+        if let Ok(syn::Meta::NameValue(syn::MetaNameValue {
+            lit: syn::Lit::Str(doc),
+            ..
+        })) = attr.parse_meta()
+        {
+            lines.push(doc.value().trim().to_string());
+        }
+    }
+
+    let doc = lines.join("\n");
+    let mut paragraphs = doc.trim().splitn(2, "\n\n");
+    let short = paragraphs.next().unwrap_or_default().replace('\n', " ");
+    let long = paragraphs
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .replace('\n', " ");
+    (short, long)
+}
+
+fn is_type(ty: &syn::Type, name: &str) -> bool {
+    match ty {
+        syn::Type::Path(type_path) => type_path.path.is_ident(name),
+        _ => false,
+    }
+}
+
+fn is_vec_of_string(ty: &syn::Type) -> bool {
+    let last = match ty {
+        syn::Type::Path(type_path) => type_path.path.segments.last(),
+        _ => None,
+    };
+    match last {
+        Some(segment) if segment.ident == "Vec" => match &segment.arguments {
+            syn::PathArguments::AngleBracketed(args) => matches!(
+                args.args.first(),
+                Some(syn::GenericArgument::Type(inner)) if is_type(inner, "String")
+            ),
+            _ => false,
+        },
+        _ => false,
+    }
+}
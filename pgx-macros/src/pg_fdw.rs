@@ -0,0 +1,88 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::pg_config::{string_value, ConfigArg};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{ItemImpl, Token};
+
+pub(crate) fn impl_pg_fdw(attr: TokenStream, item: ItemImpl) -> syn::Result<TokenStream> {
+    let trait_name = match &item.trait_ {
+        Some((None, path, _)) => path.segments.last().map(|last| last.ident.to_string()),
+        _ => None,
+    };
+    let is_fdw_impl = trait_name.as_deref() == Some("ForeignDataWrapper");
+    if !is_fdw_impl {
+        return Err(syn::Error::new(
+            item.span(),
+            "#[pg_fdw] must be applied to an `impl ForeignDataWrapper for ...` block",
+        ));
+    }
+    if !item.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            item.generics.span(),
+            "#[pg_fdw] can't be applied to a generic implementation",
+        ));
+    }
+    let self_ty = &item.self_ty;
+    let type_name = match &**self_ty {
+        syn::Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .expect("type path without segments")
+            .ident
+            .to_string(),
+        _ => {
+            return Err(syn::Error::new(
+                self_ty.span(),
+                "#[pg_fdw] must be applied to the implementation for a named type",
+            ))
+        }
+    };
+
+    let lowercase = type_name.to_lowercase();
+    let mut name = format!("{}_fdw", lowercase);
+    let mut handler = None;
+    for arg in Punctuated::<ConfigArg, Token![,]>::parse_terminated.parse2(attr)? {
+        match arg.name.to_string().as_str() {
+            "name" => name = string_value(&arg.value)?,
+            "handler" => handler = Some(string_value(&arg.value)?),
+            _ => {
+                return Err(syn::Error::new(
+                    arg.name.span(),
+                    "expected `name` or `handler`",
+                ))
+            }
+        }
+    }
+    let handler = handler.unwrap_or_else(|| format!("{}_handler", name));
+
+    let handler_fn = format_ident!("__pgx_fdw_handler_{}", lowercase);
+    let create_sql = format!(
+        "CREATE FOREIGN DATA WRAPPER {name} HANDLER {handler};",
+        name = name,
+        handler = handler,
+    );
+    let create_sql_name = format!("create_{}", name);
+
+    Ok(quote! {
+        #item
+
+        #[doc(hidden)]
+        #[::pgx::pg_extern(name = #handler)]
+        pub fn #handler_fn() -> ::pgx::PgBox<::pgx::pg_sys::FdwRoutine> {
+            ::pgx::fdw_routine::<#self_ty>()
+        }
+
+        ::pgx::extension_sql!(#create_sql, name = #create_sql_name, requires = [#handler_fn]);
+    })
+}
@@ -0,0 +1,136 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::named_fields;
+use crate::pg_config::{descriptions, string_value, ConfigArg};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{Attribute, DeriveInput, Token};
+
+pub(crate) fn impl_pg_stats(ast: DeriveInput) -> syn::Result<TokenStream> {
+    let name = &ast.ident;
+    if !ast.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            ast.generics.span(),
+            "#[derive(PgStats)] can't be applied to a generic struct",
+        ));
+    }
+    if !is_repr_c(&ast.attrs) {
+        return Err(syn::Error::new(
+            name.span(),
+            "#[derive(PgStats)] must be applied to a `#[repr(C)]` struct",
+        ));
+    }
+
+    let crate_name = std::env::var("CARGO_CRATE_NAME").unwrap_or_else(|_| "extension".into());
+    let mut view = format!("{}_stats", crate_name);
+    let mut reset_function = None;
+    for arg in stats_args(&ast.attrs)? {
+        match arg.name.to_string().as_str() {
+            "view" => view = string_value(&arg.value)?,
+            "reset_function" => reset_function = Some(string_value(&arg.value)?),
+            _ => {
+                return Err(syn::Error::new(
+                    arg.name.span(),
+                    "expected `view` or `reset_function`",
+                ))
+            }
+        }
+    }
+    let reset_function = reset_function.unwrap_or_else(|| format!("{}_reset", view));
+    let values_function = format!("{}_values", view);
+
+    let mut describe = TokenStream::new();
+    let mut reset = TokenStream::new();
+    for field in named_fields(&ast, "PgStats")? {
+        let ident = field.ident.as_ref().expect("named field without a name");
+        let stat_name = ident.to_string();
+        let (description, _) = descriptions(&field.attrs);
+        describe.extend(quote! {
+            ::pgx::PgStatDescription::new(#stat_name, &self.#ident, #description),
+        });
+        reset.extend(quote! { ::pgx::PgStat::reset(&self.#ident); });
+    }
+
+    let lowercase = name.to_string().to_lowercase();
+    let values_fn = format_ident!("__pgx_stats_values_{}", lowercase);
+    let reset_fn = format_ident!("__pgx_stats_reset_{}", lowercase);
+    let view_sql = format!(
+        "CREATE VIEW {view} AS SELECT name, kind, value, description FROM {values}();\n\
+         REVOKE ALL ON FUNCTION {reset}() FROM PUBLIC;",
+        view = view,
+        values = values_function,
+        reset = reset_function,
+    );
+    let view_sql_name = format!("create_{}_view", view);
+
+    Ok(quote! {
+        unsafe impl ::pgx::PGXSharedAtomic for #name {}
+
+        impl ::pgx::PgStats for #name {
+            const NAME: &'static str = concat!(module_path!(), "::", stringify!(#name));
+
+            fn shared() -> &'static ::pgx::PgAtomic<Self> {
+                static SHARED: ::pgx::PgAtomic<#name> = ::pgx::PgAtomic::new();
+                &SHARED
+            }
+
+            fn describe(&self) -> Vec<::pgx::PgStatDescription> {
+                vec![#describe]
+            }
+
+            fn reset(&self) {
+                #reset
+            }
+        }
+
+        #[doc(hidden)]
+        #[::pgx::pg_extern(volatile, name = #values_function)]
+        pub fn #values_fn() -> ::pgx::TableIterator<'static, ::pgx::PgStatDescription> {
+            ::pgx::TableIterator::new(<#name as ::pgx::PgStats>::get().describe())
+        }
+
+        #[doc(hidden)]
+        #[::pgx::pg_extern(volatile, name = #reset_function)]
+        pub fn #reset_fn() {
+            <#name as ::pgx::PgStats>::get().reset()
+        }
+
+        ::pgx::extension_sql!(
+            #view_sql,
+            name = #view_sql_name,
+            requires = [#values_fn, #reset_fn]
+        );
+    })
+}
+
+/// The arguments of every `#[pg_stats(...)]` attribute in `attrs`
+fn stats_args(attrs: &[Attribute]) -> syn::Result<Vec<ConfigArg>> {
+    let mut args = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("pg_stats")) {
+        let parsed = attr.parse_args_with(Punctuated::<ConfigArg, Token![,]>::parse_terminated)?;
+        args.extend(parsed);
+    }
+    Ok(args)
+}
+
+fn is_repr_c(attrs: &[Attribute]) -> bool {
+    let is_c = |nested: &syn::NestedMeta| match nested {
+        syn::NestedMeta::Meta(syn::Meta::Path(path)) => path.is_ident("C"),
+        _ => false,
+    };
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("repr"))
+        .any(|attr| match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => list.nested.iter().any(is_c),
+            _ => false,
+        })
+}
@@ -32,6 +32,24 @@ MemoryContext pgx_GetMemoryContextChunk(void *ptr) {
     return GetMemoryChunkContext(ptr);
 }
 
+PGDLLEXPORT void pgx_MemoryContextCounters(MemoryContext context, bool recurse, MemoryContextCounters *totals);
+void pgx_MemoryContextCounters(MemoryContext context, bool recurse, MemoryContextCounters *totals) {
+    MemoryContext child;
+
+#if IS_PG_10
+    context->methods->stats(context, 0, false, totals);
+#elif IS_PG_11 || IS_PG_12 || IS_PG_13
+    context->methods->stats(context, NULL, NULL, totals);
+#else
+    context->methods->stats(context, NULL, NULL, totals, false);
+#endif
+
+    if (recurse) {
+        for (child = context->firstchild; child != NULL; child = child->nextchild)
+            pgx_MemoryContextCounters(child, true, totals);
+    }
+}
+
 PGDLLEXPORT void pgx_elog(int32 level, char *message);
 void pgx_elog(int32 level, char *message) {
     elog(level, "%s", message);
@@ -49,6 +67,39 @@ void pgx_ereport(int level, int code, char *message, char *file, int lineno, int
                     errmsg("%s", message), errcontext_msg("%s:%d:%d", file, lineno, colno)));
 }
 
+PGDLLEXPORT void pgx_ereport_panic(int level, int code, char *message, char *file, int lineno, int colno, char *backtrace);
+void pgx_ereport_panic(int level, int code, char *message, char *file, int lineno, int colno, char *backtrace) {
+    ereport(level,
+            (errcode(code),
+                    errmsg("%s", message), errcontext_msg("%s:%d:%d", file, lineno, colno),
+                    backtrace != NULL ? errdetail_log("backtrace:\n%s", backtrace) : 0));
+}
+
+PGDLLEXPORT void pgx_ereport_full(int level, int code, char *message, char *detail, char *hint, char *context, int position, char *file, int lineno, char *funcname);
+void pgx_ereport_full(int level, int code, char *message, char *detail, char *hint, char *context, int position, char *file, int lineno, char *funcname) {
+#if IS_PG_10 || IS_PG_11 || IS_PG_12
+    if (errstart(level, file, lineno, funcname, TEXTDOMAIN)) {
+#else
+    if (errstart(level, TEXTDOMAIN)) {
+#endif
+        errcode(code);
+        errmsg("%s", message);
+        if (detail != NULL)
+            errdetail("%s", detail);
+        if (hint != NULL)
+            errhint("%s", hint);
+        if (context != NULL)
+            errcontext("%s", context);
+        if (position > 0)
+            errposition(position);
+#if IS_PG_10 || IS_PG_11 || IS_PG_12
+        errfinish(0);
+#else
+        errfinish(file, lineno, funcname);
+#endif
+    }
+}
+
 PGDLLEXPORT void pgx_SET_VARSIZE(struct varlena *ptr, int size);
 void pgx_SET_VARSIZE(struct varlena *ptr, int size) {
     SET_VARSIZE(ptr, size);
@@ -239,6 +239,23 @@ mod all_versions {
         pub fn pgx_list_nth_oid(list: *mut super::List, nth: i32) -> super::Oid;
         pub fn pgx_list_nth_cell(list: *mut super::List, nth: i32) -> *mut super::ListCell;
         pub fn pgx_GETSTRUCT(tuple: pg_sys::HeapTuple) -> *mut std::os::raw::c_char;
+        pub fn pgx_ereport_full(
+            level: i32,
+            code: i32,
+            message: *const std::os::raw::c_char,
+            detail: *const std::os::raw::c_char,
+            hint: *const std::os::raw::c_char,
+            context: *const std::os::raw::c_char,
+            position: i32,
+            file: *const std::os::raw::c_char,
+            lineno: i32,
+            funcname: *const std::os::raw::c_char,
+        );
+        pub fn pgx_MemoryContextCounters(
+            context: super::MemoryContext,
+            recurse: bool,
+            totals: *mut super::MemoryContextCounters,
+        );
     }
 
     #[inline]
@@ -14,18 +14,19 @@ use std::any::Any;
 use std::cell::Cell;
 use std::panic::catch_unwind;
 use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
 
 extern "C" {
     fn pg_re_throw();
-    fn pgx_ereport(
+    fn pgx_ereport_panic(
         level: i32,
         code: i32,
         message: *const std::os::raw::c_char,
         file: *const std::os::raw::c_char,
         lineno: i32,
         colno: i32,
+        backtrace: *const std::os::raw::c_char,
     );
-
 }
 
 #[derive(Clone, Debug)]
@@ -54,6 +55,48 @@ struct PanicLocation {
     file: String,
     line: u32,
     col: u32,
+    backtrace: Option<String>,
+}
+
+/// What a Rust panic which reaches Postgres becomes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanicAction {
+    /// An `ERROR`, which aborts the transaction, and which is the default
+    Error,
+    /// A `FATAL`, which also ends the session, and so its backend
+    Fatal,
+    /// A `PANIC`, which aborts the backend, and makes the postmaster restart the cluster, for
+    /// extensions which can't trust shared memory after a panic
+    Abort,
+}
+
+static PANIC_SQLSTATE: AtomicI32 = AtomicI32::new(2600); // ERRCODE_INTERNAL_ERROR
+static PANIC_LEVEL: AtomicI32 = AtomicI32::new(crate::ERROR as i32);
+static PANIC_BACKTRACE: AtomicBool = AtomicBool::new(false);
+
+/// Report the Rust panics which reach Postgres with the SQLSTATE `code`, as packed by
+/// `MAKE_SQLSTATE()`, instead of `XX000` (`internal_error`)
+///
+/// Each extension links its own copy of this setting, so it only applies to the extension which
+/// sets it.
+pub fn set_panic_sqlstate(code: i32) {
+    PANIC_SQLSTATE.store(code, Ordering::Relaxed);
+}
+
+/// Make the Rust panics which reach Postgres into `action`, instead of an `ERROR`
+pub fn set_panic_action(action: PanicAction) {
+    let level = match action {
+        PanicAction::Error => crate::ERROR,
+        PanicAction::Fatal => crate::FATAL,
+        PanicAction::Abort => crate::PANIC,
+    };
+    PANIC_LEVEL.store(level as i32, Ordering::Relaxed);
+}
+
+/// Capture a backtrace of every Rust panic, which is logged with it, rather than only when the
+/// backend's `RUST_BACKTRACE` environment variable asks for one
+pub fn set_panic_backtrace(always: bool) {
+    PANIC_BACKTRACE.store(always, Ordering::Relaxed);
 }
 
 thread_local! { static PANIC_LOCATION: Cell<Option<PanicLocation>> = Cell::new(None) }
@@ -67,12 +110,29 @@ fn take_panic_location() -> PanicLocation {
             file: "<unknown>".to_string(),
             line: 0,
             col: 0,
+            backtrace: None,
         },
     })
 }
 
 pub fn register_pg_guard_panic_handler() {
     std::panic::set_hook(Box::new(|info| {
+        // a Postgres ERROR is already reported, so it's only the Rust panics that need a backtrace
+        let backtrace = || {
+            if info.payload().is::<JumpContext>() {
+                return None;
+            }
+            let backtrace = if PANIC_BACKTRACE.load(Ordering::Relaxed) {
+                std::backtrace::Backtrace::force_capture()
+            } else {
+                std::backtrace::Backtrace::capture()
+            };
+            match backtrace.status() {
+                std::backtrace::BacktraceStatus::Captured => Some(backtrace.to_string()),
+                _ => None,
+            }
+        };
+
         PANIC_LOCATION.with(|p| {
             let existing = p.take();
 
@@ -82,6 +142,7 @@ pub fn register_pg_guard_panic_handler() {
                         file: location.file().to_string(),
                         line: location.line(),
                         col: location.column(),
+                        backtrace: backtrace(),
                     }),
                     None => None,
                 }
@@ -227,21 +288,27 @@ where
     // determine how to rethrow the error
     match downcast_err(error) {
         // the error is a String, which means it was originally a Rust panic!(), so
-        // translate it into an elog(ERROR), including the code location that caused
-        // the panic!()
+        // translate it into an elog(ERROR), or whatever the extension set, including the
+        // code location that caused the panic!(), and its backtrace in the server log
         Ok(message) => {
             let location = take_panic_location();
-            let c_message = std::ffi::CString::new(message).unwrap();
+            let c_message = std::ffi::CString::new(message.replace('\0', "")).unwrap();
             let c_file = std::ffi::CString::new(location.file).unwrap();
+            let c_backtrace = location
+                .backtrace
+                .map(|backtrace| std::ffi::CString::new(backtrace.replace('\0', "")).unwrap());
 
             unsafe {
-                pgx_ereport(
-                    crate::ERROR as i32,
-                    2600, // ERRCODE_INTERNAL_ERROR
+                pgx_ereport_panic(
+                    PANIC_LEVEL.load(Ordering::Relaxed),
+                    PANIC_SQLSTATE.load(Ordering::Relaxed),
                     c_message.as_ptr(),
                     c_file.as_ptr(),
                     location.line as i32,
                     location.col as i32,
+                    c_backtrace
+                        .as_ref()
+                        .map_or(std::ptr::null(), |backtrace| backtrace.as_ptr()),
                 );
             }
             unreachable!("ereport() failed at depth==0");
@@ -16,6 +16,7 @@ use eyre::{eyre, WrapErr};
 use owo_colors::OwoColorize;
 use pgx::*;
 use pgx_utils::pg_config::{PgConfig, Pgx};
+use pgx_utils::test_matrix::{TestOutcome, TestResults};
 use pgx_utils::{createdb, get_named_capture, get_target_dir};
 use postgres::error::DbError;
 use postgres::Client;
@@ -39,6 +40,8 @@ static TEST_MUTEX: Lazy<Mutex<SetupState>> = Lazy::new(|| {
         system_session_id: "NONE".to_string(),
     })
 });
+static TEST_RESULTS_PATH: Lazy<Mutex<Option<PathBuf>>> =
+    Lazy::new(|| Mutex::new(TestResults::path(pg_sys::get_pg_major_version_num()).ok()));
 static SHUTDOWN_HOOKS: Lazy<Mutex<Vec<Box<dyn Fn() + Send>>>> =
     Lazy::new(|| Mutex::new(Vec::new()));
 
@@ -58,20 +61,150 @@ where
     SHUTDOWN_HOOKS.lock().unwrap().push(Box::new(func));
 }
 
+/// Records the outcome of a `#[pg_test]` as it's dropped at the end of the test, which failed if
+/// it's panicking, for the version × test matrix of `cargo pgx test all`
+pub struct RecordOutcome {
+    name: &'static str,
+}
+
+impl RecordOutcome {
+    pub fn new(name: &'static str) -> Self {
+        RecordOutcome { name }
+    }
+}
+
+impl Drop for RecordOutcome {
+    fn drop(&mut self) {
+        let outcome = if std::thread::panicking() {
+            TestOutcome::Failed
+        } else {
+            TestOutcome::Passed
+        };
+        record_outcome(self.name, outcome);
+    }
+}
+
+/// Skip the `#[pg_test]` named `name`, which doesn't run against this Postgres version
+pub fn skip_test(name: &str) {
+    eprintln!("{name} skipped on pg{}", pg_sys::get_pg_major_version_num());
+    record_outcome(name, TestOutcome::Skipped);
+}
+
+fn record_outcome(name: &str, outcome: TestOutcome) {
+    // a test which panicked while it held the lock can't have left the results half-written
+    let path = TEST_RESULTS_PATH
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(path) = path.as_ref() {
+        if let Err(e) = TestResults::record(path, name, outcome) {
+            eprintln!("couldn't record the outcome of {name}: {e}");
+        }
+    }
+}
+
+/// The ERROR a `#[pg_test]` expects its test to raise, which must match each of the fields which
+/// are `Some`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ExpectedError<'a> {
+    /// The ERROR's message, exactly
+    pub message: Option<&'a str>,
+    /// The ERROR's SQLSTATE, such as `"22012"`
+    pub sqlstate: Option<&'a str>,
+    /// Text the ERROR's message contains
+    pub message_contains: Option<&'a str>,
+    /// Text the ERROR's detail contains
+    pub detail_contains: Option<&'a str>,
+    /// Text the ERROR's hint contains
+    pub hint_contains: Option<&'a str>,
+}
+
+impl<'a> ExpectedError<'a> {
+    fn assert_matches(&self, error: &DbError) {
+        if let Some(message) = self.message {
+            assert_eq!(error.message(), message);
+        }
+        if let Some(sqlstate) = self.sqlstate {
+            assert_eq!(
+                error.code().code(),
+                sqlstate,
+                "expected SQLSTATE {sqlstate}, but got: {}",
+                error.message()
+            );
+        }
+        if let Some(text) = self.message_contains {
+            assert!(
+                error.message().contains(text),
+                "expected an ERROR message containing {text:?}, but got: {}",
+                error.message()
+            );
+        }
+        if let Some(text) = self.detail_contains {
+            assert!(
+                error.detail().unwrap_or_default().contains(text),
+                "expected an ERROR detail containing {text:?}, but got: {:?}",
+                error.detail()
+            );
+        }
+        if let Some(text) = self.hint_contains {
+            assert!(
+                error.hint().unwrap_or_default().contains(text),
+                "expected an ERROR hint containing {text:?}, but got: {:?}",
+                error.hint()
+            );
+        }
+    }
+}
+
+/// How a `#[pg_test]` function is run
+#[derive(Debug, Default, Clone)]
+pub struct TestOptions<'a> {
+    /// The ERROR the test must raise, if any
+    pub expected_error: Option<ExpectedError<'a>>,
+    /// The settings of GUCs, such as `("work_mem", "64MB")`, which are `SET LOCAL` for the test
+    pub settings: Vec<(&'a str, &'a str)>,
+    /// The role the test runs as, rather than the superuser, which is created if it doesn't exist
+    pub role: Option<&'a str>,
+}
+
 pub fn run_test(
     sql_funcname: &str,
     expected_error: Option<&str>,
     postgresql_conf: Vec<&'static str>,
 ) -> eyre::Result<()> {
+    let expected_error = expected_error.map(|message| ExpectedError {
+        message: Some(message),
+        ..Default::default()
+    });
+    let options = TestOptions {
+        expected_error,
+        ..Default::default()
+    };
+    run_test_with(sql_funcname, options, postgresql_conf)
+}
+
+/// Run the `#[pg_test]` function named `sql_funcname`, as `options` say
+pub fn run_test_with(
+    sql_funcname: &str,
+    options: TestOptions,
+    postgresql_conf: Vec<&'static str>,
+) -> eyre::Result<()> {
+    let expected_error = options.expected_error;
     let (loglines, system_session_id) = initialize_test_framework(postgresql_conf)?;
 
     let (mut client, session_id) = client();
 
-    let schema = "tests"; // get_extension_schema();
+    // an upgraded extension lacks the tests, which are declared in the test's own schema instead
+    let own_schema = format!("pgx_test_{sql_funcname}");
+    let schema = match get_upgrade_from() {
+        Some(_) => own_schema.as_str(),
+        None => "tests", // get_extension_schema();
+    };
     let result = match client.transaction() {
         // run the test function in a transaction
         Ok(mut tx) => {
-            let result = tx.simple_query(&format!("SELECT \"{schema}\".\"{sql_funcname}\"();"));
+            let result = prepare_test(&mut tx, schema, sql_funcname, &options).and_then(|_| {
+                tx.simple_query(&format!("SELECT \"{schema}\".\"{sql_funcname}\"();"))
+            });
 
             if result.is_ok() {
                 // and abort the transaction when complete
@@ -93,9 +226,9 @@ pub fn run_test(
                 // we got an ERROR
                 let received_error_message: &str = dberror.message();
 
-                if let Some(expected_error_message) = expected_error {
+                if let Some(expected_error) = expected_error {
                     // and we expected an error, so assert what we got is what we expect
-                    assert_eq!(received_error_message, expected_error_message);
+                    expected_error.assert_matches(dberror);
                     Ok(())
                 } else {
                     // we weren't expecting an error
@@ -135,14 +268,165 @@ pub fn run_test(
                 e = error_as_string.bold().red()
             )
         }
-    } else if let Some(message) = expected_error {
+    } else if let Some(expected_error) = expected_error {
         // we expected an ERROR, but didn't get one
-        return Err(eyre!("Expected error: {message}"));
+        return Err(eyre!("Expected error: {expected_error:?}"));
     } else {
         Ok(())
     }
 }
 
+/// Run the `#[pg_bench]` benchmark named `sql_funcname`, and print its result, along with how its
+/// mean changed since it last ran
+pub fn run_pg_bench(
+    sql_funcname: &str,
+    postgresql_conf: Vec<&'static str>,
+) -> eyre::Result<BenchResult> {
+    initialize_test_framework(postgresql_conf)?;
+    let (mut client, _) = client();
+
+    let schema = "tests"; // get_extension_schema();
+    let mut tx = client.transaction()?;
+    prepare_test(&mut tx, schema, sql_funcname, &TestOptions::default())?;
+    let row = tx
+        .query_one(
+            &format!("SELECT * FROM \"{schema}\".\"{sql_funcname}\"();"),
+            &[],
+        )
+        .map_err(|e| match e.as_db_error() {
+            Some(dberror) => eyre!("{sql_funcname} failed: {}", dberror.message()),
+            None => eyre!("{sql_funcname} failed: {e}"),
+        })?;
+    tx.rollback()?;
+
+    let result = BenchResult {
+        name: row.try_get("name")?,
+        samples: row.try_get("samples")?,
+        iterations: row.try_get("iterations")?,
+        mean_ns: row.try_get("mean_ns")?,
+        stddev_ns: row.try_get("stddev_ns")?,
+        median_ns: row.try_get("median_ns")?,
+        min_ns: row.try_get("min_ns")?,
+        max_ns: row.try_get("max_ns")?,
+        outliers: row.try_get("outliers")?,
+    };
+
+    // compare it with the last run, and record it for the next
+    let path = get_bench_path(sql_funcname)?;
+    let previous_mean_ns = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+        .and_then(|json| json["mean_ns"].as_f64());
+    eprintln!("{}", format_bench(&result, previous_mean_ns));
+
+    std::fs::create_dir_all(path.parent().expect("benchmark path has no parent"))?;
+    let json = serde_json::json!({
+        "name": result.name,
+        "samples": result.samples,
+        "iterations": result.iterations,
+        "mean_ns": result.mean_ns,
+        "stddev_ns": result.stddev_ns,
+        "median_ns": result.median_ns,
+        "min_ns": result.min_ns,
+        "max_ns": result.max_ns,
+        "outliers": result.outliers,
+    });
+    std::fs::write(&path, json.to_string())
+        .wrap_err_with(|| format!("failed to record {}", path.display()))?;
+
+    Ok(result)
+}
+
+fn format_bench(result: &BenchResult, previous_mean_ns: Option<f64>) -> String {
+    let mut report = format!(
+        "{name}\n    time:   [{min} {median} {max}]\n    mean:   {mean} ± {stddev}, over {samples} samples of {iterations} iterations, with {outliers} outliers",
+        name = result.name.bold(),
+        min = format_ns(result.min_ns),
+        median = format_ns(result.median_ns).bold(),
+        max = format_ns(result.max_ns),
+        mean = format_ns(result.mean_ns),
+        stddev = format_ns(result.stddev_ns),
+        samples = result.samples,
+        iterations = result.iterations / result.samples.max(1),
+        outliers = result.outliers,
+    );
+    if let Some(previous) = previous_mean_ns {
+        let change = (result.mean_ns - previous) / previous * 100.0;
+        let _ = write!(
+            report,
+            "\n    change: {change:+.2}% since the last run, of {}",
+            format_ns(previous)
+        );
+    }
+    report
+}
+
+/// Format a time of `ns` nanoseconds in the largest unit which keeps it at least 1
+fn format_ns(ns: f64) -> String {
+    if ns >= 1e9 {
+        format!("{:.4} s", ns / 1e9)
+    } else if ns >= 1e6 {
+        format!("{:.4} ms", ns / 1e6)
+    } else if ns >= 1e3 {
+        format!("{:.4} µs", ns / 1e3)
+    } else {
+        format!("{:.4} ns", ns)
+    }
+}
+
+/// Prepare the transaction `tx` to run the test `sql_funcname`, in `schema`, as `options` say
+fn prepare_test(
+    tx: &mut postgres::Transaction,
+    schema: &str,
+    sql_funcname: &str,
+    options: &TestOptions,
+) -> Result<(), postgres::Error> {
+    // give the test its own schema, first in the `search_path`, so the tables it creates can't
+    // collide with those of the tests running alongside it
+    tx.simple_query(&format!(
+        "CREATE SCHEMA \"pgx_test_{sql_funcname}\"; \
+         SELECT set_config('search_path', \
+            '\"pgx_test_{sql_funcname}\", ' || current_setting('search_path'), true);"
+    ))?;
+
+    // and, when the extension was updated from a previous release, whose upgrade scripts don't
+    // create the tests, the test function itself
+    if get_upgrade_from().is_some() {
+        tx.simple_query(&format!(
+            "CREATE FUNCTION \"pgx_test_{sql_funcname}\".\"{sql_funcname}\"() RETURNS void \
+             LANGUAGE c AS '$libdir/{}', '{sql_funcname}_wrapper';",
+            get_extension_name()
+        ))?;
+    }
+
+    // and its settings, which are undone along with the transaction
+    for (name, value) in &options.settings {
+        tx.execute("SELECT set_config($1, $2, true)", &[name, value])?;
+    }
+
+    // and its role, which is created in the transaction too, so a test which runs alongside it
+    // as the same role waits until it's done
+    if let Some(role) = options.role {
+        let row = tx.query_one(
+            "SELECT quote_ident($1), extnamespace::regnamespace::text, \
+                    NOT EXISTS (SELECT FROM pg_roles WHERE rolname = $1) \
+               FROM pg_extension WHERE extname = $2",
+            &[&role, &get_extension_name()],
+        )?;
+        let role: String = row.get(0);
+        let extension_schema: String = row.get(1);
+        if row.get::<_, bool>(2) {
+            tx.simple_query(&format!("CREATE ROLE {role};"))?;
+        }
+        tx.simple_query(&format!(
+            "GRANT USAGE ON SCHEMA {extension_schema}, \"{schema}\" TO {role}; \
+             GRANT USAGE, CREATE ON SCHEMA \"pgx_test_{sql_funcname}\" TO {role}; \
+             SET LOCAL ROLE {role};"
+        ))?;
+    }
+    Ok(())
+}
+
 fn format_loglines(session_id: &str, loglines: &LogLines) -> String {
     let mut result = String::new();
 
@@ -510,13 +794,31 @@ fn dropdb() {
 
 fn create_extension() {
     let (mut client, _) = client();
+    let extension_name = get_extension_name();
+
+    match get_upgrade_from() {
+        // the previous release's script is run against this version's shared library, which may
+        // no longer have the functions it declares
+        Some(version) => client
+            .simple_query(&format!(
+                "SET check_function_bodies TO off; \
+                 CREATE EXTENSION {extension_name} VERSION '{version}' CASCADE; \
+                 RESET check_function_bodies; \
+                 ALTER EXTENSION {extension_name} UPDATE;"
+            ))
+            .unwrap_or_else(|e| {
+                panic!("failed to update {extension_name} from version {version}:\n{e}")
+            }),
+        None => client
+            .simple_query(&format!("CREATE EXTENSION {extension_name} CASCADE;"))
+            .unwrap(),
+    };
+}
 
-    client
-        .simple_query(&format!(
-            "CREATE EXTENSION {} CASCADE;",
-            get_extension_name()
-        ))
-        .unwrap();
+/// The version of the extension the tests update to this version from, with
+/// `ALTER EXTENSION ... UPDATE`, as by `cargo pgx test --upgrade-from`
+fn get_upgrade_from() -> Option<String> {
+    std::env::var("PGX_UPGRADE_FROM").ok()
 }
 
 fn get_extension_name() -> String {
@@ -534,6 +836,13 @@ fn get_pgdata_path() -> eyre::Result<PathBuf> {
     Ok(target_dir)
 }
 
+fn get_bench_path(sql_funcname: &str) -> eyre::Result<PathBuf> {
+    let mut target_dir = get_target_dir()?;
+    target_dir.push(&format!("pgx-bench-{}", pg_sys::get_pg_major_version_num()));
+    target_dir.push(&format!("{sql_funcname}.json"));
+    Ok(target_dir)
+}
+
 fn get_pg_dbname() -> &'static str {
     "pgx_tests"
 }
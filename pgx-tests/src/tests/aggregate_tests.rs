@@ -141,6 +141,170 @@ impl Aggregate for DemoPercentileDisc {
     }
 }
 
+#[derive(Copy, Clone, Default, Debug)]
+pub struct DemoHypotheticalRank;
+
+#[pg_aggregate]
+impl Aggregate for DemoHypotheticalRank {
+    const ORDERED_SET: bool = true;
+    const HYPOTHETICAL: bool = true;
+
+    type Args = (name!(score, i32), name!(name, String));
+    type OrderedSetArgs = (name!(score, i32), name!(name, String));
+    type State = Internal;
+    type Finalize = i64;
+
+    fn state(
+        mut current: Self::State,
+        (score, name): Self::Args,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::State {
+        let inner = unsafe { current.get_or_insert_default::<Vec<(i32, String)>>() };
+
+        inner.push((score, name));
+        current
+    }
+
+    fn finalize(
+        mut current: Self::State,
+        hypothetical: Self::OrderedSetArgs,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::Finalize {
+        let inner = unsafe { current.get_or_insert_default::<Vec<(i32, String)>>() };
+
+        // The rows are sorted, so the rank is one more than the number of rows before the hypothetical one.
+        inner.iter().take_while(|row| **row < hypothetical).count() as i64 + 1
+    }
+}
+
+#[derive(Copy, Clone, Default, Debug)]
+pub struct DemoDistinct;
+
+#[pg_aggregate]
+impl Aggregate for DemoDistinct {
+    const PARALLEL: Option<ParallelOption> = Some(pgx::aggregate::ParallelOption::Safe);
+
+    type Args = i32;
+    type State = Internal;
+    type Finalize = i64;
+
+    #[pgx(parallel_safe)]
+    fn state(
+        mut current: Self::State,
+        arg: Self::Args,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::State {
+        let inner = unsafe { current.get_or_insert_default::<HashSet<i32>>() };
+
+        inner.insert(arg);
+        current
+    }
+
+    #[pgx(parallel_safe)]
+    fn combine(
+        mut first: Self::State,
+        mut second: Self::State,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::State {
+        let second_inner = unsafe { second.get_or_insert_default::<HashSet<i32>>() }.clone();
+        let first_inner = unsafe { first.get_or_insert_default::<HashSet<i32>>() };
+
+        first_inner.extend(second_inner);
+        first
+    }
+
+    #[pgx(parallel_safe)]
+    fn serial(mut current: Self::State, _fcinfo: pg_sys::FunctionCallInfo) -> Vec<u8> {
+        let inner = unsafe { current.get_or_insert_default::<HashSet<i32>>() };
+
+        inner.iter().flat_map(|value| value.to_le_bytes()).collect()
+    }
+
+    #[pgx(parallel_safe)]
+    fn deserial(buf: Vec<u8>, _fcinfo: pg_sys::FunctionCallInfo) -> Self::State {
+        let inner = buf
+            .chunks_exact(4)
+            .map(|chunk| i32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect::<HashSet<i32>>();
+
+        Internal::new(inner)
+    }
+
+    #[pgx(parallel_safe)]
+    fn finalize(
+        mut current: Self::State,
+        _direct_args: Self::OrderedSetArgs,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::Finalize {
+        let inner = unsafe { current.get_or_insert_default::<HashSet<i32>>() };
+
+        inner.len() as i64
+    }
+}
+
+#[derive(Copy, Clone, Default, Debug)]
+pub struct DemoWeightedSum;
+
+#[pg_aggregate]
+impl Aggregate for DemoWeightedSum {
+    const INITIAL_CONDITION: Option<&'static str> = Some(r#"0"#);
+    const MOVING_INITIAL_CONDITION: Option<&'static str> = Some(r#"0"#);
+
+    type Args = (name!(value, i32), name!(weight, i32));
+    type State = i64;
+    type MovingState = i64;
+
+    fn state(
+        current: Self::State,
+        (value, weight): Self::Args,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::State {
+        current + value as i64 * weight as i64
+    }
+
+    fn moving_state(
+        current: Self::MovingState,
+        args: Self::Args,
+        fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::MovingState {
+        Self::state(current, args, fcinfo)
+    }
+
+    fn moving_state_inverse(
+        current: Self::MovingState,
+        (value, weight): Self::Args,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::MovingState {
+        current - value as i64 * weight as i64
+    }
+}
+
+#[derive(Copy, Clone, Default, Debug, PostgresType, Serialize, Deserialize)]
+pub struct DemoMeanState {
+    count: i64,
+    total: i64,
+}
+
+#[derive(Copy, Clone, Default, Debug)]
+pub struct DemoMean;
+
+#[pg_aggregate]
+impl SimpleAggregate for DemoMean {
+    type State = DemoMeanState;
+    type Args = name!(value, i32);
+    type Finalize = Option<f64>;
+
+    fn state(mut current: Self::State, value: Self::Args) -> Self::State {
+        current.count += 1;
+        current.total += value as i64;
+        current
+    }
+
+    fn finish(current: Self::State) -> Self::Finalize {
+        (current.count > 0).then(|| current.total as f64 / current.count as f64)
+    }
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
 mod tests {
@@ -178,6 +342,70 @@ mod tests {
         assert_eq!(retval, 2);
     }
 
+    #[pg_test]
+    fn aggregate_demo_hypothetical_rank() {
+        let scores =
+            "FROM UNNEST(ARRAY [10, 30, 20, 20], ARRAY ['a', 'b', 'c', 'd']) as t(score, name)";
+
+        let retval = Spi::get_one::<i64>(&format!(
+            "SELECT DemoHypotheticalRank(20, 'c') WITHIN GROUP (ORDER BY score, name) {}",
+            scores
+        ))
+        .expect("SQL select failed");
+        assert_eq!(retval, 2);
+
+        let retval = Spi::get_one::<i64>(&format!(
+            "SELECT DemoHypotheticalRank(25, 'a') WITHIN GROUP (ORDER BY score, name) {}",
+            scores
+        ))
+        .expect("SQL select failed");
+        assert_eq!(retval, 4);
+
+        // The same as the built in hypothetical-set `rank`
+        let retval = Spi::get_one::<bool>(&format!(
+            "SELECT DemoHypotheticalRank(20, 'd') WITHIN GROUP (ORDER BY score, name) \
+                = rank(20, 'd') WITHIN GROUP (ORDER BY score, name) {}",
+            scores
+        ))
+        .expect("SQL select failed");
+        assert!(retval);
+    }
+
+    #[pg_test]
+    fn aggregate_demo_distinct_parallel() {
+        Spi::run("CREATE TABLE demo_distinct_values AS SELECT value % 1000 AS value FROM generate_series(1, 100000) value;");
+        Spi::run("SET parallel_setup_cost = 0");
+        Spi::run("SET parallel_tuple_cost = 0");
+        Spi::run("SET min_parallel_table_scan_size = 0");
+        Spi::run("SET max_parallel_workers_per_gather = 2");
+
+        let plan = Spi::get_one::<String>(
+            "EXPLAIN (FORMAT JSON, COSTS OFF) SELECT DemoDistinct(value) FROM demo_distinct_values;",
+        )
+        .expect("SQL select failed");
+        assert!(plan.contains("\"Partial Mode\": \"Partial\""), "{}", plan);
+
+        let retval = Spi::get_one::<i64>("SELECT DemoDistinct(value) FROM demo_distinct_values;")
+            .expect("SQL select failed");
+        assert_eq!(retval, 1000);
+    }
+
+    #[pg_test]
+    fn aggregate_demo_weighted_sum() {
+        // Moving-aggregate mode, with more than one argument
+        let retval = Spi::get_one::<Vec<i64>>(
+            "
+            SELECT array_agg(calculated) FROM (
+                SELECT DemoWeightedSum(value, weight) OVER (
+                    ROWS BETWEEN 1 PRECEDING AND CURRENT ROW
+                ) as calculated FROM UNNEST(ARRAY [1, 20, 300], ARRAY [1, 2, 3]) as t(value, weight)
+            ) as results;
+        ",
+        )
+        .expect("SQL select failed");
+        assert_eq!(retval, vec![1, 41, 940]);
+    }
+
     #[pg_test]
     fn aggregate_demo_percentile_disc() {
         // Example from https://www.postgresql.org/docs/current/xaggr.html#XAGGR-ORDERED-SET-AGGREGATES
@@ -191,4 +419,20 @@ mod tests {
         ).expect("SQL select failed");
         assert_eq!(retval, 5);
     }
+
+    #[pg_test]
+    fn aggregate_demo_simple_mean() {
+        // `NULL`s are skipped
+        let retval = Spi::get_one::<f64>(
+            "SELECT DemoMean(value) FROM UNNEST(ARRAY [1, NULL, 2, 6]) as value;",
+        )
+        .expect("SQL select failed");
+        assert_eq!(retval, 3.0);
+
+        // `finish` is passed the `Default` state when no rows are aggregated
+        let retval = Spi::get_one::<f64>(
+            "SELECT DemoMean(value) FROM UNNEST(ARRAY [NULL]::integer[]) as value;",
+        );
+        assert_eq!(retval, None);
+    }
 }
@@ -15,6 +15,38 @@ fn anyarray_arg(array: AnyArray) -> Json {
         .expect("conversion to json returned null")
 }
 
+#[pg_extern]
+fn anyarray_count_nulls(array: AnyArray) -> i64 {
+    array.iter().filter(|element| element.is_none()).count() as i64
+}
+
+#[pg_extern]
+fn anyarray_dims(array: AnyArray) -> Vec<i32> {
+    array.dims().to_vec()
+}
+
+#[pg_extern]
+fn anyarray_lower_bounds(array: AnyArray) -> Vec<i32> {
+    array.lower_bounds().to_vec()
+}
+
+#[pg_extern]
+fn anyarray_element_type(array: AnyArray) -> String {
+    unsafe { std::ffi::CStr::from_ptr(pg_sys::format_type_be(array.element_oid())) }
+        .to_str()
+        .expect("type name was not valid UTF8")
+        .to_string()
+}
+
+#[pg_extern]
+fn anyarray_sum_text_lengths(array: AnyArray) -> i64 {
+    array
+        .iter()
+        .flatten()
+        .map(|element| element.into::<&str>().map(|s| s.len()).unwrap_or(0) as i64)
+        .sum()
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
 mod tests {
@@ -30,4 +62,38 @@ mod tests {
             .expect("anyarray_arg() returned null");
         assert_eq!(json.0, json! {[1,2,3]})
     }
+
+    #[pg_test]
+    fn test_anyarray_count_nulls() {
+        let nulls = Spi::get_one::<i64>("SELECT anyarray_count_nulls(ARRAY[1, NULL, 3, NULL]);")
+            .expect("anyarray_count_nulls() returned null");
+        assert_eq!(nulls, 2);
+    }
+
+    #[pg_test]
+    fn test_anyarray_dims() {
+        let rc = Spi::get_one::<bool>(
+            "SELECT anyarray_dims('[0:1][1:3]={{1,2,3},{4,5,6}}'::int[]) = ARRAY[2, 3] \
+                AND anyarray_lower_bounds('[0:1][1:3]={{1,2,3},{4,5,6}}'::int[]) = ARRAY[0, 1] \
+                AND anyarray_dims('{}'::int[]) = '{}';",
+        )
+        .expect("failed to get SPI result");
+        assert!(rc)
+    }
+
+    #[pg_test]
+    fn test_anyarray_element_type() {
+        let name = Spi::get_one::<String>("SELECT anyarray_element_type(ARRAY['a'::text]);")
+            .expect("anyarray_element_type() returned null");
+        assert_eq!(name, "text");
+    }
+
+    #[pg_test]
+    fn test_anyarray_elements() {
+        let len = Spi::get_one::<i64>(
+            "SELECT anyarray_sum_text_lengths(ARRAY['one', NULL, 'three']::text[]);",
+        )
+        .expect("anyarray_sum_text_lengths() returned null");
+        assert_eq!(len, 8);
+    }
 }
@@ -99,6 +99,21 @@ fn return_zero_length_vec() -> Vec<i32> {
     Vec::new()
 }
 
+#[pg_extern]
+fn double_nullable_i64(values: Vec<Option<i64>>) -> Vec<Option<i64>> {
+    values.into_iter().map(|v| v.map(|v| v * 2)).collect()
+}
+
+#[pg_extern]
+fn negate_f64(values: Vec<f64>) -> Vec<f64> {
+    values.into_iter().map(|v| -v).collect()
+}
+
+#[pg_extern]
+fn invert_nullable_bool(values: Vec<Option<bool>>) -> Vec<Option<bool>> {
+    values.into_iter().map(|v| v.map(|v| !v)).collect()
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
 mod tests {
@@ -240,4 +255,55 @@ mod tests {
         .expect("Failed to return json even though it's right there ^^");
         assert_eq!(json.0, json! {{"values": [1, 2, 3, null, 4]}});
     }
+
+    #[pg_test]
+    fn test_nullable_primitive_vec_roundtrip() {
+        let rc = Spi::get_one::<bool>(
+            "SELECT double_nullable_i64(ARRAY[1, NULL, 2, 3, NULL, 4, 5, 6, 7, NULL, 8]::bigint[]) \
+                = ARRAY[2, NULL, 4, 6, NULL, 8, 10, 12, 14, NULL, 16]::bigint[];",
+        )
+        .expect("failed to get SPI result");
+        assert!(rc)
+    }
+
+    #[pg_test]
+    fn test_nullable_primitive_vec_without_nulls() {
+        let rc = Spi::get_one::<bool>(
+            "SELECT double_nullable_i64(ARRAY[1, 2, 3]::bigint[]) = ARRAY[2, 4, 6]::bigint[];",
+        )
+        .expect("failed to get SPI result");
+        assert!(rc)
+    }
+
+    #[pg_test]
+    fn test_primitive_vec_roundtrip() {
+        let rc = Spi::get_one::<bool>(
+            "SELECT negate_f64(ARRAY[1.5, -2, 0]::float8[]) = ARRAY[-1.5, 2, -0]::float8[];",
+        )
+        .expect("failed to get SPI result");
+        assert!(rc)
+    }
+
+    #[pg_test(error = "array element was NULL")]
+    fn test_primitive_vec_with_nulls() {
+        Spi::get_one::<bool>("SELECT negate_f64(ARRAY[1, NULL]::float8[]) IS NULL;");
+    }
+
+    #[pg_test]
+    fn test_nullable_bool_vec_roundtrip() {
+        let rc = Spi::get_one::<bool>(
+            "SELECT invert_nullable_bool(ARRAY[true, NULL, false]) = ARRAY[false, NULL, true];",
+        )
+        .expect("failed to get SPI result");
+        assert!(rc)
+    }
+
+    #[pg_test]
+    fn test_nullable_primitive_vec_into_datum() {
+        let values = vec![Some(1_i32), None, Some(3)];
+        let datum = values.clone().into_datum();
+        let roundtrip =
+            unsafe { Vec::<Option<i32>>::from_datum(datum.unwrap(), false, pg_sys::INT4ARRAYOID) };
+        assert_eq!(roundtrip, Some(values));
+    }
 }
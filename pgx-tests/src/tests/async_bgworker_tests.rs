@@ -0,0 +1,65 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::async_bgworker;
+    use pgx::*;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    /// Becomes ready once another thread sets `done`, which wakes the main thread
+    struct OtherThread {
+        done: Arc<AtomicBool>,
+        started: bool,
+    }
+
+    impl Future for OtherThread {
+        type Output = &'static str;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            if self.done.load(Ordering::SeqCst) {
+                return Poll::Ready("woken");
+            }
+            if !self.started {
+                self.started = true;
+                let done = self.done.clone();
+                let waker = cx.waker().clone();
+                std::thread::spawn(move || {
+                    std::thread::sleep(Duration::from_millis(50));
+                    done.store(true, Ordering::SeqCst);
+                    waker.wake();
+                });
+            }
+            Poll::Pending
+        }
+    }
+
+    #[pg_test]
+    fn test_block_on_ready() {
+        assert_eq!(async_bgworker::block_on(async { 42 }), 42);
+    }
+
+    #[pg_test]
+    fn test_block_on_woken_from_thread() {
+        let future = OtherThread {
+            done: Arc::new(AtomicBool::new(false)),
+            started: false,
+        };
+        assert_eq!(async_bgworker::block_on(future), "woken");
+    }
+}
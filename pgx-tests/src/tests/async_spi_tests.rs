@@ -0,0 +1,64 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::async_spi::{AsyncQuery, AsyncQueryError};
+    use pgx::*;
+
+    #[pg_test]
+    fn test_async_query_returns_rows() {
+        let query = AsyncQuery::start(
+            "pgx_tests",
+            "SELECT x, x::text || 'a' AS label, NULL::int AS nothing FROM generate_series(1, 3) x",
+        );
+        let result = query.wait().expect("async query failed");
+        assert_eq!(result.columns, vec!["x", "label", "nothing"]);
+        assert_eq!(result.processed, 3);
+        assert_eq!(
+            result.rows[2],
+            vec![Some("3".to_string()), Some("3a".to_string()), None]
+        );
+    }
+
+    #[pg_test]
+    fn test_concurrent_async_queries() {
+        let queries = (1..=3)
+            .map(|n| AsyncQuery::start("pgx_tests", &format!("SELECT {}", n)))
+            .collect::<Vec<_>>();
+        let values = queries
+            .into_iter()
+            .map(|query| query.wait().expect("async query failed").rows[0][0].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                Some("1".to_string()),
+                Some("2".to_string()),
+                Some("3".to_string())
+            ]
+        );
+    }
+
+    #[pg_test]
+    fn test_async_query_error() {
+        let query = AsyncQuery::start("pgx_tests", "SELECT 1 / 0");
+        match query.wait() {
+            Err(AsyncQueryError::Sql(e)) => {
+                assert!(e.is(PgSqlErrorCode::ERRCODE_DIVISION_BY_ZERO));
+                assert_eq!(e.message, "division by zero");
+            }
+            other => panic!("unexpected async query result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_shared_atomic_u64() {
+        let counter = SharedAtomicU64::new(0);
+        assert_eq!(counter.increment(), 1);
+        assert_eq!(counter.fetch_add(10), 1);
+        assert_eq!(counter.fetch_sub(1), 11);
+        assert_eq!(counter.swap(100), 10);
+        assert_eq!(counter.compare_exchange(1, 2), Err(100));
+        assert_eq!(counter.compare_exchange(100, 2), Ok(100));
+        assert_eq!(counter.load(), 2);
+
+        // it's usable by Postgres' own atomics
+        assert_eq!(unsafe { (*counter.as_ptr()).value }, 2);
+    }
+
+    #[pg_test]
+    fn test_shared_spin_lock() {
+        let lock = SharedSpinLock::new((1, 2));
+        {
+            let mut guard = lock.lock();
+            guard.0 += 10;
+            guard.1 += 20;
+            assert!(lock.try_lock().is_none());
+        }
+        assert_eq!(*lock.try_lock().expect("lock wasn't released"), (11, 22));
+    }
+}
@@ -0,0 +1,190 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::bgworkers::{BackgroundWorker, SignalWakeFlags};
+use pgx::*;
+use std::time::Duration;
+
+/// Does nothing until it's told to stop
+#[pg_guard]
+pub extern "C" fn bgworker_tests_idle_main(_arg: pg_sys::Datum) {
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGTERM);
+    while BackgroundWorker::wait_latch(Some(Duration::from_secs(1))) {}
+}
+
+/// Runs a failing transaction and then one that succeeds, and sends whether each did as
+/// expected over the queue at the start of the segment
+#[pg_guard]
+pub extern "C" fn bgworker_tests_transaction_main(arg: pg_sys::Datum) {
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGTERM);
+    BackgroundWorker::connect_worker_to_spi(Some(BackgroundWorker::get_extra()), None);
+
+    let failed = BackgroundWorker::try_transaction(|| Spi::run("SELECT 1/0"));
+    let value = BackgroundWorker::transaction(|| Spi::get_one::<i32>("SELECT 42"));
+
+    unsafe {
+        let segment = pg_sys::dsm_attach(arg as pg_sys::dsm_handle);
+        let mq = pg_sys::dsm_segment_address(segment) as *mut pg_sys::shm_mq;
+        let mut sender = ShmMqSender::attach(mq, segment);
+        sender
+            .send(&(failed.is_none(), value))
+            .expect("failed to send");
+    }
+}
+
+/// Sends the names of the databases it can see, connected to only the shared catalogs, over the
+/// queue at the start of the segment
+#[pg_guard]
+pub extern "C" fn bgworker_tests_databases_main(arg: pg_sys::Datum) {
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGTERM);
+    BackgroundWorker::connect_worker_to_spi(None, None);
+
+    let names = BackgroundWorker::databases()
+        .into_iter()
+        .map(|database| database.name)
+        .collect::<Vec<_>>();
+
+    unsafe {
+        let segment = pg_sys::dsm_attach(arg as pg_sys::dsm_handle);
+        let mq = pg_sys::dsm_segment_address(segment) as *mut pg_sys::shm_mq;
+        let mut sender = ShmMqSender::attach(mq, segment);
+        sender.send(&names).expect("failed to send");
+    }
+}
+
+/// Runs until it's sent a SIGUSR2
+#[pg_guard]
+pub extern "C" fn bgworker_tests_sigusr2_main(_arg: pg_sys::Datum) {
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGTERM | SignalWakeFlags::SIGUSR2);
+    loop {
+        let signals = BackgroundWorker::wait_for_signals(Some(Duration::from_secs(1)));
+        if signals.intersects(SignalWakeFlags::SIGTERM | SignalWakeFlags::SIGUSR2) {
+            break;
+        }
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::bgworkers::{BackgroundWorkerBuilder, BackgroundWorkerStatus, SignalWakeFlags};
+    use pgx::*;
+    use std::time::Duration;
+
+    #[pg_test]
+    fn test_dynamic_bgworker_lifecycle() {
+        let worker = BackgroundWorkerBuilder::new("pgx_tests idle worker")
+            .set_library("pgx_tests")
+            .set_function("bgworker_tests_idle_main")
+            .load_dynamic()
+            .expect("failed to register worker");
+
+        let pid = worker.wait_for_startup().expect("worker didn't start");
+        assert!(pid > 0);
+        assert_eq!(worker.pid(), Some(pid));
+        assert_eq!(worker.status(), BackgroundWorkerStatus::Started(pid));
+
+        worker.terminate();
+        assert_eq!(worker.wait_for_shutdown(), Ok(()));
+        assert_eq!(worker.pid(), None);
+        assert_eq!(worker.status(), BackgroundWorkerStatus::Stopped);
+    }
+
+    #[pg_test]
+    fn test_dynamic_bgworker_terminated_before_startup() {
+        let worker = BackgroundWorkerBuilder::new("pgx_tests idle worker")
+            .set_library("pgx_tests")
+            .set_function("bgworker_tests_idle_main")
+            .load_dynamic()
+            .expect("failed to register worker");
+
+        // whether or not the postmaster got to it first, the worker ends up stopped
+        worker.terminate();
+        assert_eq!(worker.wait_for_shutdown(), Ok(()));
+    }
+
+    #[pg_test]
+    fn test_dynamic_bgworker_sigusr2() {
+        let worker = BackgroundWorkerBuilder::new("pgx_tests sigusr2 worker")
+            .set_library("pgx_tests")
+            .set_function("bgworker_tests_sigusr2_main")
+            .load_dynamic()
+            .expect("failed to register worker");
+        worker.wait_for_startup().expect("worker didn't start");
+
+        // the worker ignores SIGUSR2 until it attaches its handler, so keep sending it
+        for _ in 0..100 {
+            if !worker.signal(SignalWakeFlags::SIGUSR2) {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        assert_eq!(worker.wait_for_shutdown(), Ok(()));
+    }
+
+    #[pg_test]
+    fn test_bgworker_transactions() {
+        const QUEUE_SIZE: usize = 16384;
+        let database = Spi::get_one::<String>("SELECT current_database()::text").unwrap();
+
+        unsafe {
+            let segment = pg_sys::dsm_create(QUEUE_SIZE, 0);
+            let mut receiver =
+                ShmMqReceiver::create(pg_sys::dsm_segment_address(segment), QUEUE_SIZE, segment);
+
+            let worker = BackgroundWorkerBuilder::new("pgx_tests transaction worker")
+                .set_library("pgx_tests")
+                .set_function("bgworker_tests_transaction_main")
+                .set_argument(Some(pg_sys::dsm_segment_handle(segment) as pg_sys::Datum))
+                .set_extra(&database)
+                .enable_spi_access()
+                .load_dynamic()
+                .expect("failed to register worker");
+            receiver.set_worker(&worker);
+
+            assert_eq!(receiver.recv::<(bool, Option<i32>)>(), Ok((true, Some(42))));
+
+            drop(receiver);
+            pg_sys::dsm_detach(segment);
+        }
+    }
+
+    #[pg_test]
+    fn test_bgworker_databases() {
+        const QUEUE_SIZE: usize = 16384;
+        let database = Spi::get_one::<String>("SELECT current_database()::text").unwrap();
+
+        unsafe {
+            let segment = pg_sys::dsm_create(QUEUE_SIZE, 0);
+            let mut receiver =
+                ShmMqReceiver::create(pg_sys::dsm_segment_address(segment), QUEUE_SIZE, segment);
+
+            let worker = BackgroundWorkerBuilder::new("pgx_tests databases worker")
+                .set_library("pgx_tests")
+                .set_function("bgworker_tests_databases_main")
+                .set_argument(Some(pg_sys::dsm_segment_handle(segment) as pg_sys::Datum))
+                .enable_spi_access()
+                .load_dynamic()
+                .expect("failed to register worker");
+            receiver.set_worker(&worker);
+
+            let names = receiver.recv::<Vec<String>>().expect("failed to receive");
+            assert!(names.contains(&database));
+            assert!(names.contains(&"template1".to_string()));
+            // it doesn't allow connections
+            assert!(!names.contains(&"template0".to_string()));
+
+            drop(receiver);
+            pg_sys::dsm_detach(segment);
+        }
+    }
+}
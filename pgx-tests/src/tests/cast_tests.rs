@@ -0,0 +1,65 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, Debug, PostgresType, Serialize, Deserialize)]
+pub struct Cents {
+    value: i64,
+}
+
+#[pg_cast(assignment, immutable, parallel_safe)]
+fn int8_to_cents(value: i64) -> Cents {
+    Cents { value }
+}
+
+#[pg_cast(immutable, parallel_safe)]
+fn cents_to_int8(cents: Cents) -> i64 {
+    cents.value
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_explicit_cast() {
+        let value = Spi::get_one::<i64>("SELECT (42::bigint::Cents)::bigint;")
+            .expect("failed to get SPI result");
+        assert_eq!(value, 42);
+    }
+
+    #[pg_test]
+    fn test_assignment_cast() {
+        Spi::run("CREATE TABLE prices (price Cents);");
+        Spi::run("INSERT INTO prices VALUES (1999::bigint);");
+        let value = Spi::get_one::<i64>("SELECT price::bigint FROM prices;")
+            .expect("failed to get SPI result");
+        assert_eq!(value, 1999);
+    }
+
+    #[pg_test]
+    fn test_cast_contexts() {
+        let context = Spi::get_one::<String>(
+            "SELECT castcontext::text FROM pg_cast WHERE castsource = 'int8'::regtype AND casttarget = 'Cents'::regtype;",
+        )
+        .expect("failed to get SPI result");
+        assert_eq!(context, "a");
+
+        let context = Spi::get_one::<String>(
+            "SELECT castcontext::text FROM pg_cast WHERE castsource = 'Cents'::regtype AND casttarget = 'int8'::regtype;",
+        )
+        .expect("failed to get SPI result");
+        assert_eq!(context, "e");
+    }
+}
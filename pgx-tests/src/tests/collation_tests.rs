@@ -0,0 +1,65 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+
+#[pg_extern]
+fn collation_name(_value: &str, fcinfo: pg_sys::FunctionCallInfo) -> Option<String> {
+    PgCollation::from_fcinfo(fcinfo).name()
+}
+
+#[pg_extern]
+fn collation_cmp(a: &str, b: &str, fcinfo: pg_sys::FunctionCallInfo) -> i32 {
+    PgCollation::from_fcinfo(fcinfo).cmp(a, b) as i32
+}
+
+#[pg_extern]
+fn collation_upper(value: &str, fcinfo: pg_sys::FunctionCallInfo) -> String {
+    PgCollation::from_fcinfo(fcinfo).to_upper(value)
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::cmp::Ordering;
+
+    #[pg_test]
+    fn test_collation_from_fcinfo() {
+        let name = Spi::get_one::<String>(r#"SELECT collation_name('a' COLLATE "C");"#)
+            .expect("collation_name() returned null");
+        assert_eq!(name, "C");
+
+        let name = Spi::get_one::<String>("SELECT collation_name('a');")
+            .expect("collation_name() returned null");
+        assert_eq!(name, "default");
+    }
+
+    #[pg_test]
+    fn test_collation_cmp() {
+        let order = Spi::get_one::<i32>(r#"SELECT collation_cmp('B', 'a' COLLATE "C");"#)
+            .expect("collation_cmp() returned null");
+        assert_eq!(order, Ordering::Less as i32);
+        assert_eq!(PgCollation::c().cmp("abc", "abc"), Ordering::Equal);
+        assert_eq!(PgCollation::c().cmp("b", "a"), Ordering::Greater);
+    }
+
+    #[pg_test]
+    fn test_collation_case_mapping() {
+        let upper = Spi::get_one::<String>("SELECT collation_upper('hello');")
+            .expect("collation_upper() returned null");
+        assert_eq!(upper, "HELLO");
+
+        let collation = PgCollation::default();
+        assert_eq!(collation.to_lower("HeLLo"), "hello");
+        assert_eq!(collation.initcap("hello world"), "Hello World");
+    }
+}
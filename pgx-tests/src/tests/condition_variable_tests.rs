@@ -0,0 +1,44 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_condition_variable_already_true() {
+        let cv = PgConditionVariable::default();
+        let mut checks = 0;
+        cv.sleep_until(WaitEvent::EXTENSION, || {
+            checks += 1;
+            true
+        });
+        assert_eq!(checks, 1);
+
+        // with no one sleeping, these do nothing
+        cv.signal();
+        cv.broadcast();
+    }
+
+    #[cfg(any(feature = "pg13", feature = "pg14"))]
+    #[pg_test]
+    fn test_condition_variable_timeout() {
+        let cv = PgConditionVariable::default();
+        let satisfied = cv.sleep_until_timeout(
+            std::time::Duration::from_millis(50),
+            WaitEvent::EXTENSION,
+            || false,
+        );
+        assert!(!satisfied);
+    }
+}
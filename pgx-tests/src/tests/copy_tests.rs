@@ -0,0 +1,115 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(all(
+    any(test, feature = "pg_test"),
+    any(feature = "pg12", feature = "pg13", feature = "pg14")
+))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    fn create_dogs() {
+        Spi::run("CREATE TABLE dogs (id serial, name text NOT NULL, age int)");
+    }
+
+    #[pg_test(setup = "create_dogs")]
+    fn test_copy_from_text() {
+        let copied = CopyFrom::new("dogs")
+            .columns(&["name", "age"])
+            .execute("brandy\t3\nnami\t\\N\n".as_bytes());
+        assert_eq!(copied, 2);
+        assert_eq!(
+            Spi::get_one::<String>(
+                "SELECT string_agg(id || ':' || name || ':' || coalesce(age::text, '?'), ',' ORDER BY id) FROM dogs"
+            ),
+            Some("1:brandy:3,2:nami:?".to_string())
+        );
+    }
+
+    #[pg_test(setup = "create_dogs")]
+    fn test_copy_from_csv() {
+        let csv = "name,age\n\"brandy, the dog\",3\nnami,5\n";
+        let copied = CopyFrom::new("dogs")
+            .columns(&["name", "age"])
+            .format(CopyFormat::Csv)
+            .option("header", "true")
+            .execute(csv.as_bytes());
+        assert_eq!(copied, 2);
+        assert_eq!(
+            Spi::get_one::<String>("SELECT name FROM dogs WHERE age = 3"),
+            Some("brandy, the dog".to_string())
+        );
+    }
+
+    #[pg_test(setup = "create_dogs")]
+    fn test_copy_from_many_rows() {
+        // more than Postgres reads at once, and than it inserts in a batch
+        let data: String = (0..10_000).map(|i| format!("dog{}\t{}\n", i, i)).collect();
+        let copied = CopyFrom::new("dogs")
+            .columns(&["name", "age"])
+            .execute(data.as_bytes());
+        assert_eq!(copied, 10_000);
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT sum(age) FROM dogs"),
+            Some(49_995_000)
+        );
+    }
+
+    #[pg_test(setup = "create_dogs", sqlstate = ERRCODE_NOT_NULL_VIOLATION)]
+    fn test_copy_from_constraint() {
+        CopyFrom::new("dogs")
+            .columns(&["name", "age"])
+            .execute("\\N\t3\n".as_bytes());
+    }
+
+    #[pg_test(sqlstate = ERRCODE_UNDEFINED_TABLE)]
+    fn test_copy_from_no_such_table() {
+        CopyFrom::new("no_such_table").execute("".as_bytes());
+    }
+
+    #[pg_test(
+        setup = "create_dogs",
+        error = "could not read COPY data: the dog ran off"
+    )]
+    fn test_copy_from_read_error() {
+        struct Failing;
+        impl std::io::Read for Failing {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "the dog ran off",
+                ))
+            }
+        }
+        CopyFrom::new("dogs").execute(Failing);
+    }
+
+    #[pg_test(setup = "create_dogs")]
+    fn test_copy_for_each_row() {
+        let mut dogs = Vec::new();
+        let read = CopyFrom::new("dogs")
+            .columns(&["name", "age"])
+            .format(CopyFormat::Csv)
+            .for_each_row("brandy,3\nnami,\n".as_bytes(), |row| {
+                assert_eq!(row.len(), 3);
+                dogs.push((row.get::<String>(2).unwrap(), row.get::<i32>(3)));
+            });
+        assert_eq!(read, 2);
+        assert_eq!(
+            dogs,
+            vec![("brandy".to_string(), Some(3)), ("nami".to_string(), None)]
+        );
+        // nothing was inserted
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM dogs"), Some(0));
+    }
+}
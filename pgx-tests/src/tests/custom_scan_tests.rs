@@ -0,0 +1,98 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    /// Scans `tests.custom_scan_numbers` by producing 1 through 5, whatever the table holds
+    struct Numbers;
+    struct NumbersScan(i32);
+
+    impl CustomScanProvider for Numbers {
+        fn name(&self) -> &'static str {
+            "TestNumbers"
+        }
+
+        fn create_path(
+            &self,
+            _root: &PgBox<pg_sys::PlannerInfo>,
+            _rel: &PgBox<pg_sys::RelOptInfo>,
+            rte: &PgBox<pg_sys::RangeTblEntry>,
+        ) -> Option<CustomPathCost> {
+            let relation = PgRelation::with_lock(rte.relid, pg_sys::AccessShareLock as _);
+            if relation.name() != "custom_scan_numbers" {
+                return None;
+            }
+            Some(CustomPathCost {
+                rows: 5.0,
+                startup_cost: 0.0,
+                total_cost: 0.0,
+            })
+        }
+
+        fn begin_scan(
+            &self,
+            _scan: &PgBox<pg_sys::CustomScan>,
+            _relation: &PgRelation,
+            _eflags: i32,
+        ) -> Box<dyn CustomScanExecution> {
+            Box::new(NumbersScan(0))
+        }
+    }
+
+    impl CustomScanExecution for NumbersScan {
+        fn next(&mut self, slot: &mut CustomScanSlot) -> bool {
+            self.0 += 1;
+            self.0 <= 5 && slot.store(&[self.0.into_datum()])
+        }
+
+        fn rescan(&mut self) {
+            self.0 = 0;
+        }
+
+        fn explain(&self, explain: &mut Explain) {
+            explain.property("Numbers", "1 to 5");
+        }
+    }
+
+    static NUMBERS: Numbers = Numbers;
+    static REGISTER: std::sync::Once = std::sync::Once::new();
+
+    #[pg_test]
+    fn test_custom_scan() {
+        REGISTER.call_once(|| register_custom_scan(&NUMBERS));
+        Spi::run("CREATE TABLE tests.custom_scan_numbers (n int)");
+        Spi::run("INSERT INTO tests.custom_scan_numbers VALUES (100)");
+
+        let sum = Spi::get_one::<i64>("SELECT sum(n) FROM tests.custom_scan_numbers WHERE n > 2");
+        assert_eq!(sum, Some(12));
+
+        Spi::execute(|mut client| {
+            let output = client.explain(
+                "SELECT * FROM tests.custom_scan_numbers",
+                ExplainOptions {
+                    format: ExplainFormat::Text,
+                    ..Default::default()
+                },
+            );
+            match output {
+                ExplainOutput::Text(plan) => {
+                    assert!(plan.starts_with("Custom Scan (TestNumbers)"));
+                    assert!(plan.contains("Numbers: 1 to 5"));
+                }
+                other => panic!("unexpected EXPLAIN output: {:?}", other),
+            }
+        });
+    }
+}
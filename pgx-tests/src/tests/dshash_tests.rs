@@ -0,0 +1,54 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(all(
+    any(test, feature = "pg_test"),
+    any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14")
+))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_shared_hash_map() {
+        let area = DsaArea::create("pgx_tests_dshash");
+        let map = SharedHashMap::<u32, i64>::create(&area, "pgx_tests_dshash");
+
+        assert!(map.get(&1).is_none());
+        assert_eq!(map.insert(&1, 10), None);
+        assert_eq!(map.insert(&1, 11), Some(10));
+        assert_eq!(*map.get(&1).unwrap(), 11);
+
+        *map.get_mut(&1).unwrap() += 1;
+        assert_eq!(*map.get_or_insert(&1, 0), 12);
+        assert_eq!(*map.get_or_insert(&2, 20), 20);
+        assert_eq!(map.get(&2).unwrap().key(), &2);
+
+        assert_eq!(map.remove(&1), Some(12));
+        assert_eq!(map.remove(&1), None);
+        assert!(!map.contains_key(&1));
+        assert!(map.contains_key(&2));
+    }
+
+    #[pg_test]
+    fn test_shared_hash_map_attach() {
+        let area = DsaArea::create("pgx_tests_dshash");
+        let map = SharedHashMap::<(u32, u32), u64>::create(&area, "pgx_tests_dshash");
+        for i in 0..1000 {
+            map.insert(&(i, i * 2), i as u64);
+        }
+
+        let attached = unsafe { SharedHashMap::<(u32, u32), u64>::attach(&area, map.handle()) };
+        assert_eq!(*attached.get(&(500, 1000)).unwrap(), 500);
+        assert!(attached.get(&(500, 500)).is_none());
+    }
+}
@@ -0,0 +1,57 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_dsm_segment() {
+        let segment = DsmSegment::create(4096);
+        assert!(segment.len() >= 4096);
+
+        let bytes = segment.address() as *mut u8;
+        unsafe {
+            bytes.write(42);
+            assert_eq!(*bytes, 42);
+        }
+
+        // once its only attached process detaches, it's gone
+        let handle = segment.handle();
+        drop(segment);
+        assert!(DsmSegment::attach(handle).is_none());
+        assert!(DsaArea::attach(handle).is_none());
+    }
+
+    #[pg_test]
+    fn test_dsa_area() {
+        let area = DsaArea::create("pgx_tests_dsa");
+
+        let counter = area.alloc(SharedAtomicU64::new(41));
+        assert!(!counter.is_null());
+        assert_eq!(area.get(counter).increment(), 42);
+
+        let raw = counter.into_raw();
+        let counter = unsafe { DsaPointer::<SharedAtomicU64>::from_raw(raw) };
+        assert_eq!(area.get(counter).load(), 42);
+
+        let bytes = area.alloc_bytes(1024 * 1024);
+        let slice = unsafe { std::slice::from_raw_parts(area.as_mut_ptr(bytes), 1024 * 1024) };
+        assert!(slice.iter().all(|b| *b == 0));
+
+        unsafe {
+            area.free(bytes);
+            area.free(counter);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    static mut COMMENTED: Vec<String> = Vec::new();
+    static mut FIRED: Vec<String> = Vec::new();
+
+    #[pg_event_trigger(event = "ddl_command_end", tags = ["COMMENT"])]
+    fn record_comments(trigger: &PgEventTrigger) {
+        for command in trigger.ddl_commands() {
+            unsafe { COMMENTED.push(command.object_identity) };
+        }
+    }
+
+    #[pg_event_trigger]
+    fn record_firing(trigger: &PgEventTrigger) {
+        unsafe { FIRED.push(format!("{:?} {}", trigger.event(), trigger.tag())) };
+    }
+
+    #[pg_event_trigger]
+    fn forbid_dropping_dogs(trigger: &PgEventTrigger) -> Result<(), String> {
+        match trigger
+            .dropped_objects()
+            .into_iter()
+            .find(|dropped| dropped.object_name.as_deref() == Some("dogs"))
+        {
+            Some(dropped) => Err(format!("{} can't be dropped", dropped.object_identity)),
+            None => Ok(()),
+        }
+    }
+
+    #[pg_test]
+    fn test_event_trigger_ddl_commands() {
+        Spi::run("CREATE TABLE tests.dogs (name text)");
+        Spi::run("COMMENT ON TABLE tests.dogs IS 'good dogs'");
+        assert_eq!(unsafe { COMMENTED.as_slice() }, ["tests.dogs"]);
+    }
+
+    #[pg_test]
+    fn test_event_trigger_event_and_tag() {
+        Spi::run(
+            "CREATE EVENT TRIGGER record_start ON ddl_command_start \
+             EXECUTE PROCEDURE tests.record_firing()",
+        );
+        Spi::run(
+            "CREATE EVENT TRIGGER record_end ON ddl_command_end \
+             EXECUTE PROCEDURE tests.record_firing()",
+        );
+        Spi::run("CREATE TABLE tests.dogs (name text)");
+        assert_eq!(
+            unsafe { FIRED.as_slice() },
+            ["DdlCommandStart CREATE TABLE", "DdlCommandEnd CREATE TABLE"]
+        );
+    }
+
+    #[pg_test(error = "tests.dogs can't be dropped")]
+    fn test_event_trigger_dropped_objects() {
+        Spi::run(
+            "CREATE EVENT TRIGGER forbid_dropping_dogs ON sql_drop \
+             EXECUTE PROCEDURE tests.forbid_dropping_dogs()",
+        );
+        Spi::run("CREATE TABLE tests.cats (name text)");
+        Spi::run("DROP TABLE tests.cats");
+        Spi::run("CREATE TABLE tests.dogs (name text)");
+        Spi::run("DROP TABLE tests.dogs");
+    }
+}
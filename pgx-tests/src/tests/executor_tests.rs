@@ -0,0 +1,65 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_planned_query_select() {
+        let query = PlannedQuery::new(
+            "SELECT x, x::text FROM generate_series(1, $1) x",
+            &[PgBuiltInOids::INT4OID.oid()],
+        );
+
+        for n in 1..=3 {
+            let mut values = Vec::new();
+            let processed = query.execute(vec![n.into_datum()], |row| {
+                assert_eq!(row.len(), 2);
+                values.push((row.get::<i32>(1).unwrap(), row.get::<String>(2).unwrap()));
+            });
+            assert_eq!(processed, n as u64);
+            assert_eq!(values.len(), n as usize);
+            assert_eq!(values.last(), Some(&(n, n.to_string())));
+        }
+    }
+
+    #[pg_test]
+    fn test_planned_query_insert() {
+        Spi::run("CREATE TABLE tests.planned_query (id int, name text)");
+        let query = PlannedQuery::new(
+            "INSERT INTO tests.planned_query VALUES ($1, $2)",
+            &[PgBuiltInOids::INT4OID.oid(), PgBuiltInOids::TEXTOID.oid()],
+        );
+        for id in 0..10 {
+            let name = if id % 2 == 0 { Some("even") } else { None };
+            let processed = query.execute(vec![id.into_datum(), name.into_datum()], |_| {
+                panic!("INSERT without RETURNING produced a row")
+            });
+            assert_eq!(processed, 1);
+        }
+
+        let count = Spi::get_one::<i64>("SELECT count(name) FROM tests.planned_query");
+        assert_eq!(count, Some(5));
+    }
+
+    #[pg_test(error = "PlannedQuery requires exactly one statement, but the query has 2")]
+    fn test_planned_query_multiple_statements() {
+        PlannedQuery::new("SELECT 1; SELECT 2", &[]);
+    }
+
+    #[pg_test(error = "PlannedQuery cannot execute utility statements")]
+    fn test_planned_query_utility_statement() {
+        PlannedQuery::new("CREATE TABLE tests.planned_utility (id int)", &[]);
+    }
+}
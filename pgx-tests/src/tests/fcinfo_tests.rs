@@ -131,6 +131,11 @@ fn fcinfo_not_named_no_arg(fcinfo: pg_sys::FunctionCallInfo) -> i32 {
     todo!()
 }
 
+#[pg_extern]
+fn first_value_seen(value: i32, fcinfo: pg_sys::FunctionCallInfo) -> i32 {
+    *pg_func_cache(fcinfo, || value)
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
 mod tests {
@@ -303,4 +308,16 @@ mod tests {
     fn test_same_name() {
         assert_eq!("test", same_name("test"));
     }
+
+    #[pg_test]
+    fn test_pg_func_cache() {
+        let result = Spi::get_one::<Vec<i32>>(
+            "SELECT array_agg(first_value_seen(x)) FROM generate_series(1, 3) x",
+        );
+        assert_eq!(result, Some(vec![1, 1, 1]));
+
+        // a new statement gets a new cache
+        let result = Spi::get_one::<i32>("SELECT first_value_seen(42)");
+        assert_eq!(result, Some(42));
+    }
 }
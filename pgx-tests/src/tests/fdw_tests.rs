@@ -0,0 +1,121 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+use std::collections::HashMap;
+
+/// Produces the numbers from 1 to the table's `last` option, and checks that inserted numbers
+/// are positive
+struct Numbers {
+    next: i32,
+    last: i32,
+}
+
+#[pg_fdw(name = "numbers_fdw")]
+impl ForeignDataWrapper for Numbers {
+    type Row = (i32,);
+
+    const SUPPORTS_INSERT: bool = true;
+
+    fn new(_relation: &PgRelation, options: HashMap<String, String>) -> Self {
+        let last = options
+            .get("last")
+            .map_or(3, |last| last.parse().expect("`last` is not a number"));
+        Numbers { next: 1, last }
+    }
+
+    fn get_rel_size(_relation: &PgRelation, _options: &HashMap<String, String>) -> f64 {
+        10.0
+    }
+
+    fn iterate_scan(&mut self) -> Option<Self::Row> {
+        if self.next > self.last {
+            return None;
+        }
+        self.next += 1;
+        Some((self.next - 1,))
+    }
+
+    fn rescan(&mut self) {
+        self.next = 1;
+    }
+
+    fn insert(&mut self, row: &[Option<pg_sys::Datum>]) {
+        let n = unsafe { i32::from_datum(row[0].unwrap_or(0), row[0].is_none(), pg_sys::INT4OID) };
+        if !matches!(n, Some(n) if n > 0) {
+            error!("only positive numbers can be inserted");
+        }
+    }
+
+    fn import_foreign_schema(
+        stmt: &PgBox<pg_sys::ImportForeignSchemaStmt>,
+        server_options: HashMap<String, String>,
+    ) -> Vec<String> {
+        let schema = unsafe { std::ffi::CStr::from_ptr(stmt.local_schema) }.to_string_lossy();
+        let server = server_options
+            .get("name")
+            .expect("server has no `name` option");
+        vec![format!(
+            "CREATE FOREIGN TABLE {}.imported_numbers (n int) SERVER {}",
+            schema, server
+        )]
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    fn create_server() {
+        Spi::run("CREATE SERVER numbers_server FOREIGN DATA WRAPPER numbers_fdw OPTIONS (name 'numbers_server')");
+    }
+
+    #[pg_test]
+    fn test_fdw_scan() {
+        create_server();
+        Spi::run(
+            "CREATE FOREIGN TABLE tests.numbers (n int) SERVER numbers_server OPTIONS (last '4')",
+        );
+
+        let sum = Spi::get_one::<i64>("SELECT sum(n) FROM tests.numbers WHERE n > 1");
+        assert_eq!(sum, Some(9));
+
+        let count = Spi::get_one::<i64>(
+            "SELECT count(*) FROM tests.numbers a, tests.numbers b WHERE a.n = b.n",
+        );
+        assert_eq!(count, Some(4));
+    }
+
+    #[pg_test]
+    fn test_fdw_insert() {
+        create_server();
+        Spi::run("CREATE FOREIGN TABLE tests.numbers (n int) SERVER numbers_server");
+        Spi::run("INSERT INTO tests.numbers VALUES (1), (2)");
+    }
+
+    #[pg_test(error = "only positive numbers can be inserted")]
+    fn test_fdw_insert_error() {
+        create_server();
+        Spi::run("CREATE FOREIGN TABLE tests.numbers (n int) SERVER numbers_server");
+        Spi::run("INSERT INTO tests.numbers VALUES (-1)");
+    }
+
+    #[pg_test]
+    fn test_fdw_import_foreign_schema() {
+        create_server();
+        Spi::run("IMPORT FOREIGN SCHEMA remote FROM SERVER numbers_server INTO tests");
+
+        let sum = Spi::get_one::<i64>("SELECT sum(n) FROM tests.imported_numbers");
+        assert_eq!(sum, Some(6));
+    }
+}
@@ -0,0 +1,84 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    fn open(create: &str, name: &str) -> PgRelation {
+        Spi::run(create);
+        let oid =
+            Spi::get_one::<pg_sys::Oid>(&format!("SELECT '{}'::regclass::oid", name)).unwrap();
+        PgRelation::with_lock(oid, pg_sys::AccessExclusiveLock as pg_sys::LOCKMODE)
+    }
+
+    fn page_lsn(page: pg_sys::Page) -> pg_sys::XLogRecPtr {
+        let lsn = unsafe { (*(page as *mut pg_sys::PageHeaderData)).pd_lsn };
+        (lsn.xlogid as u64) << 32 | lsn.xrecoff as u64
+    }
+
+    /// Add a new, empty page to `relation`, returning its buffer, pinned and locked, and the
+    /// LSN of its record
+    fn add_page(relation: &PgRelation) -> (pg_sys::Buffer, pg_sys::XLogRecPtr) {
+        unsafe {
+            let buffer = pg_sys::ReadBuffer(relation.as_ptr(), pg_sys::InvalidBlockNumber);
+            pg_sys::LockBuffer(buffer, pg_sys::BUFFER_LOCK_EXCLUSIVE as i32);
+
+            let mut xlog = GenericXLog::start(relation);
+            let page = xlog.register_buffer(buffer, true);
+            pg_sys::PageInit(page, pg_sys::BLCKSZ as usize, 0);
+            (buffer, xlog.finish())
+        }
+    }
+
+    #[pg_test]
+    fn test_generic_xlog_finish() {
+        let relation = open("CREATE TABLE pages ()", "pages");
+        let (buffer, lsn) = add_page(&relation);
+        unsafe {
+            assert!(lsn > 0);
+            assert_eq!(page_lsn(pg_sys::BufferGetPage(buffer)), lsn);
+            assert_eq!(pg_sys::BufferGetBlockNumber(buffer), 0);
+            pg_sys::UnlockReleaseBuffer(buffer);
+        }
+    }
+
+    #[pg_test]
+    fn test_generic_xlog_unlogged() {
+        let relation = open("CREATE UNLOGGED TABLE pages ()", "pages");
+        let (buffer, lsn) = add_page(&relation);
+        unsafe {
+            assert_eq!(lsn, 0);
+            pg_sys::UnlockReleaseBuffer(buffer);
+        }
+    }
+
+    #[pg_test]
+    fn test_generic_xlog_abort() {
+        let relation = open("CREATE TABLE pages ()", "pages");
+        let (buffer, lsn) = add_page(&relation);
+        unsafe {
+            let header = pg_sys::BufferGetPage(buffer) as *mut pg_sys::PageHeaderData;
+            let lower = (*header).pd_lower;
+
+            let mut xlog = GenericXLog::start(&relation);
+            let page = xlog.register_buffer(buffer, false);
+            (*(page as *mut pg_sys::PageHeaderData)).pd_lower += 8;
+            drop(xlog);
+
+            assert_eq!((*header).pd_lower, lower);
+            assert_eq!(page_lsn(header as pg_sys::Page), lsn);
+            pg_sys::UnlockReleaseBuffer(buffer);
+        }
+    }
+}
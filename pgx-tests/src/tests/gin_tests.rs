@@ -0,0 +1,81 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PostgresType, Serialize, Deserialize)]
+pub struct Tags {
+    tags: Vec<String>,
+}
+
+#[pg_operator(immutable, parallel_safe)]
+#[opname(@>)]
+fn tags_contains(left: Tags, right: Tags) -> bool {
+    right.tags.iter().all(|tag| left.tags.contains(tag))
+}
+
+#[pg_gin_opclass]
+impl GinOpClass for Tags {
+    type Key = String;
+    type Query = Tags;
+    const OPERATORS: &'static [(u16, &'static str)] = &[(1, "@>")];
+
+    fn extract_value(value: Self) -> Vec<Option<String>> {
+        value.tags.into_iter().map(Some).collect()
+    }
+
+    fn extract_query(query: Tags, _strategy: u16) -> (Vec<Option<String>>, GinSearchMode) {
+        let mode = if query.tags.is_empty() {
+            GinSearchMode::All
+        } else {
+            GinSearchMode::Default
+        };
+        (query.tags.into_iter().map(Some).collect(), mode)
+    }
+
+    fn consistent(check: &[bool], _strategy: u16, _query: Tags) -> (bool, bool) {
+        (check.iter().all(|present| *present), false)
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_gin_opclass_exists() {
+        let opclass = Spi::get_one::<bool>(
+            "SELECT EXISTS(SELECT 1 FROM pg_opclass WHERE opcname = 'tags_gin_ops');",
+        )
+        .expect("failed to get SPI result");
+        assert!(opclass);
+    }
+
+    #[pg_test]
+    fn test_gin_index_scan() {
+        Spi::run("CREATE TABLE tagged (id int, tags Tags);");
+        Spi::run(
+            r#"INSERT INTO tagged VALUES
+                (1, '{"tags": ["red", "blue"]}'),
+                (2, '{"tags": ["red"]}'),
+                (3, '{"tags": ["green"]}');"#,
+        );
+        Spi::run("CREATE INDEX idxtagged ON tagged USING gin (tags);");
+        Spi::run("SET enable_seqscan TO off;");
+        let count = Spi::get_one::<i64>(
+            r#"SELECT count(*) FROM tagged WHERE tags @> '{"tags": ["red"]}';"#,
+        )
+        .expect("failed to get SPI result");
+        assert_eq!(count, 2);
+    }
+}
@@ -24,6 +24,7 @@ mod tests {
             "test bool gucs",
             &GUC,
             GucContext::Userset,
+            GucFlags::default(),
         );
         assert_eq!(GUC.get(), true);
 
@@ -45,6 +46,7 @@ mod tests {
             -1,
             42,
             GucContext::Userset,
+            GucFlags::default(),
         );
         assert_eq!(GUC.get(), 42);
 
@@ -66,6 +68,7 @@ mod tests {
             -1.0f64,
             43.0f64,
             GucContext::Userset,
+            GucFlags::default(),
         );
         assert_eq!(GUC.get(), 42.42);
 
@@ -88,6 +91,7 @@ mod tests {
             "test string guc",
             &GUC,
             GucContext::Userset,
+            GucFlags::default(),
         );
         assert!(GUC.get().is_some());
         assert_eq!(GUC.get().unwrap(), "this is a test");
@@ -108,6 +112,7 @@ mod tests {
             "test string guc",
             &GUC,
             GucContext::Userset,
+            GucFlags::default(),
         );
         assert!(GUC.get().is_none());
 
@@ -133,6 +138,7 @@ mod tests {
             "test enum guc",
             &GUC,
             GucContext::Userset,
+            GucFlags::default(),
         );
         assert_eq!(GUC.get(), TestEnum::Two);
 
@@ -142,4 +148,515 @@ mod tests {
         Spi::run("SET test.enum = 'three'");
         assert_eq!(GUC.get(), TestEnum::Three);
     }
+
+    struct EvenHooks;
+    impl GucHooks<i32> for EvenHooks {
+        fn check(value: &i32) -> Result<(), String> {
+            match value % 2 {
+                0 => Ok(()),
+                _ => Err(format!("{} is odd", value)),
+            }
+        }
+    }
+
+    #[pg_test]
+    fn test_int_guc_check_hook() {
+        static GUC: GucSetting<i32> = GucSetting::new(2);
+        GucRegistry::define_int_guc_with_hooks::<EvenHooks>(
+            "test.even_int",
+            "test int guc hooks",
+            "test int guc hooks",
+            &GUC,
+            0,
+            100,
+            GucContext::Userset,
+            GucFlags::default(),
+        );
+
+        Spi::run("SET test.even_int = 42");
+        assert_eq!(GUC.get(), 42);
+    }
+
+    #[pg_test(error = "invalid value for parameter \"test.odd_int\": \"3\"")]
+    fn test_int_guc_check_hook_rejects() {
+        static GUC: GucSetting<i32> = GucSetting::new(2);
+        GucRegistry::define_int_guc_with_hooks::<EvenHooks>(
+            "test.odd_int",
+            "test int guc hooks",
+            "test int guc hooks",
+            &GUC,
+            0,
+            100,
+            GucContext::Userset,
+            GucFlags::default(),
+        );
+
+        Spi::run("SET test.odd_int = 3");
+    }
+
+    #[pg_test]
+    fn test_enum_guc_hooks() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(PostgresGucEnum, Clone, Copy, PartialEq, Debug)]
+        enum Level {
+            Low,
+            High,
+        }
+        static GUC: GucSetting<Level> = GucSetting::new(Level::Low);
+        static ASSIGNED: AtomicUsize = AtomicUsize::new(0);
+
+        struct LevelHooks;
+        impl GucHooks<Level> for LevelHooks {
+            fn assign(_value: &Level) {
+                ASSIGNED.fetch_add(1, Ordering::SeqCst);
+            }
+
+            const SHOW: bool = true;
+            fn show() -> String {
+                format!("{:?}!", GUC.get())
+            }
+        }
+
+        GucRegistry::define_enum_guc_with_hooks::<Level, LevelHooks>(
+            "test.hooked_enum",
+            "test enum guc hooks",
+            "test enum guc hooks",
+            &GUC,
+            GucContext::Userset,
+            GucFlags::default(),
+        );
+        let assigned = ASSIGNED.load(Ordering::SeqCst);
+
+        Spi::run("SET test.hooked_enum = 'high'");
+        assert_eq!(GUC.get(), Level::High);
+        assert_eq!(ASSIGNED.load(Ordering::SeqCst), assigned + 1);
+        assert_eq!(
+            Spi::get_one::<&str>("SELECT current_setting('test.hooked_enum')"),
+            Some("High!")
+        );
+    }
+
+    #[pg_test]
+    fn test_string_guc_check_hook() {
+        static GUC: GucSetting<Option<&'static str>> = GucSetting::new(None);
+
+        struct NoSpaces;
+        impl GucHooks<Option<String>> for NoSpaces {
+            fn check(value: &Option<String>) -> Result<(), String> {
+                match value {
+                    Some(value) if value.contains(' ') => Err("spaces aren't allowed".into()),
+                    _ => Ok(()),
+                }
+            }
+        }
+
+        GucRegistry::define_string_guc_with_hooks::<NoSpaces>(
+            "test.no_spaces",
+            "test string guc hooks",
+            "test string guc hooks",
+            &GUC,
+            GucContext::Userset,
+            GucFlags::default(),
+        );
+
+        Spi::run("SET test.no_spaces = 'foo'");
+        assert_eq!(GUC.get().unwrap(), "foo");
+    }
+
+    #[pg_test]
+    fn test_list_guc() {
+        static GUC: GucSetting<&'static [&'static str]> = GucSetting::new(&["public", "My Schema"]);
+        GucRegistry::define_list_guc(
+            "test.list",
+            "test list guc",
+            "test list guc",
+            &GUC,
+            GucContext::Userset,
+            GucFlags::default(),
+        );
+        assert_eq!(GUC.get(), vec!["public", "My Schema"]);
+
+        Spi::run("SET test.list = a, \"b, c\", \"with \"\"quotes\"\"\"");
+        assert_eq!(GUC.get(), vec!["a", "b, c", "with \"quotes\""]);
+
+        // SET quotes a string as one element, which set_config() doesn't
+        Spi::run("SET test.list = 'Not Split, At All'");
+        assert_eq!(GUC.get(), vec!["Not Split, At All"]);
+
+        Spi::run("SELECT set_config('test.list', 'x, \"Y\"', false)");
+        assert_eq!(GUC.get(), vec!["x", "Y"]);
+
+        Spi::run("SELECT set_config('test.list', '', false)");
+        assert!(GUC.get().is_empty());
+    }
+
+    #[pg_test(error = "invalid value for parameter \"test.allowed_roles\": \"postgres, nobody\"")]
+    fn test_list_guc_check_hook() {
+        static GUC: GucSetting<&'static [&'static str]> = GucSetting::new(&[]);
+
+        struct NoSuperusers;
+        impl GucHooks<Vec<String>> for NoSuperusers {
+            fn check(roles: &Vec<String>) -> Result<(), String> {
+                match roles.iter().find(|role| *role == "postgres") {
+                    Some(role) => Err(format!("{} is a superuser", role)),
+                    None => Ok(()),
+                }
+            }
+        }
+
+        GucRegistry::define_list_guc_with_hooks::<NoSuperusers>(
+            "test.allowed_roles",
+            "test list guc hooks",
+            "test list guc hooks",
+            &GUC,
+            GucContext::Userset,
+            GucFlags::default(),
+        );
+
+        Spi::run("SET test.allowed_roles = nobody");
+        assert_eq!(GUC.get(), vec!["nobody"]);
+
+        Spi::run("SET test.allowed_roles = postgres, nobody");
+    }
+
+    #[pg_test]
+    fn test_int_guc_unit() {
+        static GUC: GucSetting<i32> = GucSetting::new(1024);
+        GucRegistry::define_int_guc(
+            "test.cache_size",
+            "test int guc unit",
+            "test int guc unit",
+            &GUC,
+            0,
+            i32::MAX,
+            GucContext::Userset,
+            GucFlags::UNIT_KB,
+        );
+        assert_eq!(GUC.get(), 1024);
+        assert_eq!(
+            Spi::get_one::<&str>("SELECT current_setting('test.cache_size')"),
+            Some("1MB")
+        );
+
+        Spi::run("SET test.cache_size = '64MB'");
+        assert_eq!(GUC.get(), 64 * 1024);
+
+        Spi::run("SET test.cache_size = 16");
+        assert_eq!(GUC.get(), 16);
+    }
+
+    #[pg_test]
+    fn test_int_guc_time_unit() {
+        static GUC: GucSetting<i32> = GucSetting::new(1000);
+        GucRegistry::define_int_guc(
+            "test.interval",
+            "test int guc unit",
+            "test int guc unit",
+            &GUC,
+            0,
+            i32::MAX,
+            GucContext::Userset,
+            GucFlags::UNIT_MS,
+        );
+
+        Spi::run("SET test.interval = '5min'");
+        assert_eq!(GUC.get(), 5 * 60 * 1000);
+    }
+
+    #[pg_test]
+    fn test_reserve_prefix() {
+        // warns of the placeholder, which is still set
+        Spi::run("SET test_reserved.typo = 'oops'");
+        GucRegistry::reserve_prefix("test_reserved");
+        assert_eq!(
+            Spi::get_one::<&str>("SELECT current_setting('test_reserved.typo')"),
+            Some("oops")
+        );
+    }
+
+    #[pg_test]
+    fn test_get_server_settings() {
+        use std::time::Duration;
+
+        Spi::run("SET work_mem = '64MB'");
+        assert_eq!(
+            Guc::get::<GucBytes>("work_mem"),
+            Some(GucBytes(64 * 1024 * 1024))
+        );
+        assert_eq!(Guc::get::<String>("work_mem"), Some("64MB".to_string()));
+        assert_eq!(Guc::get::<i32>("work_mem"), None);
+
+        Spi::run("SET statement_timeout = '90s'");
+        assert_eq!(
+            Guc::get::<Duration>("statement_timeout"),
+            Some(Duration::from_secs(90))
+        );
+        Spi::run("SET statement_timeout = 0");
+        assert_eq!(
+            Guc::get::<Duration>("statement_timeout"),
+            Some(Duration::ZERO)
+        );
+
+        Spi::run("SET log_min_duration_statement = -1");
+        assert_eq!(Guc::get::<Duration>("log_min_duration_statement"), None);
+
+        Spi::run("SET enable_seqscan = off");
+        assert_eq!(Guc::get::<bool>("enable_seqscan"), Some(false));
+
+        Spi::run("SET geqo_threshold = 14");
+        assert_eq!(Guc::get::<i32>("geqo_threshold"), Some(14));
+
+        assert_eq!(Guc::get::<String>("no_such.setting"), None);
+    }
+
+    #[derive(PostgresGucEnum, Clone, Copy, PartialEq, Debug)]
+    enum ConfigMode {
+        Fast,
+        Safe,
+    }
+
+    #[derive(PgConfig, Debug, PartialEq)]
+    #[pg_config(prefix = "test_config")]
+    struct TestConfig {
+        /// The number of rows in a batch
+        ///
+        /// Which must be at least one.
+        #[pg_config(default = 64, min = 1, max = 1024)]
+        batch_size: i32,
+        /// Should rows be compressed?
+        compress: bool,
+        #[pg_config(default = 0.5, min = 0.0, max = 1.0)]
+        ratio: f64,
+        #[pg_config(name = "url", default = "http://localhost")]
+        endpoint: Option<String>,
+        #[pg_config(default = ["public"])]
+        schemas: Vec<String>,
+        #[pg_config(default = ConfigMode::Safe)]
+        mode: ConfigMode,
+    }
+
+    #[pg_test]
+    fn test_pg_config() {
+        TestConfig::define();
+        assert_eq!(
+            TestConfig::snapshot(),
+            TestConfig {
+                batch_size: 64,
+                compress: false,
+                ratio: 0.5,
+                endpoint: Some("http://localhost".into()),
+                schemas: vec!["public".into()],
+                mode: ConfigMode::Safe,
+            }
+        );
+
+        Spi::run("SET test_config.batch_size = 128");
+        Spi::run("SET test_config.compress = on");
+        Spi::run("SET test_config.url = 'http://example.com'");
+        Spi::run("SET test_config.schemas = public, other");
+        Spi::run("SET test_config.mode = fast");
+        let config = TestConfig::snapshot();
+        assert_eq!(config.batch_size, 128);
+        assert!(config.compress);
+        assert_eq!(config.endpoint.as_deref(), Some("http://example.com"));
+        assert_eq!(config.schemas, vec!["public", "other"]);
+        assert_eq!(config.mode, ConfigMode::Fast);
+
+        assert_eq!(
+            Spi::get_one::<&str>(
+                "SELECT short_desc FROM pg_settings WHERE name = 'test_config.batch_size'"
+            ),
+            Some("The number of rows in a batch")
+        );
+    }
+
+    #[pg_test]
+    fn test_pg_config_settings_function() {
+        TestConfig::define();
+        Spi::run("SET test_config.batch_size = 128");
+
+        let described = GucRegistry::describe();
+        let batch_size = described
+            .iter()
+            .find(|guc| guc.name == "test_config.batch_size")
+            .expect("test_config.batch_size wasn't described");
+        assert_eq!(batch_size.setting.as_deref(), Some("128"));
+        assert_eq!(batch_size.default_setting.as_deref(), Some("64"));
+        assert_eq!(batch_size.description, "The number of rows in a batch");
+        assert_eq!(batch_size.context, "user");
+
+        assert_eq!(
+            Spi::get_one::<&str>(
+                "SELECT default_setting FROM tests.settings() WHERE name = 'test_config.mode'"
+            ),
+            Some("safe")
+        );
+        assert_eq!(
+            Spi::get_one::<i64>(
+                "SELECT count(*) FROM tests.settings() WHERE name LIKE 'test_config.%'"
+            ),
+            Some(6)
+        );
+    }
+
+    #[pg_test]
+    fn test_on_reload() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        static GUC: GucSetting<i32> = GucSetting::new(1);
+        GucRegistry::define_int_guc(
+            "test.reload_int",
+            "test reload callbacks",
+            "test reload callbacks",
+            &GUC,
+            0,
+            100,
+            GucContext::Sighup,
+            GucFlags::default(),
+        );
+
+        let changed = Rc::new(RefCell::new(Vec::new()));
+        let seen = changed.clone();
+        GucRegistry::on_reload(&["test.reload_int", "work_mem"], move |names| {
+            seen.borrow_mut().extend(names.iter().cloned())
+        });
+
+        // nothing happens until the configuration is reloaded
+        Spi::run("SET work_mem = '12MB'");
+        GucRegistry::process_reload();
+        assert!(changed.borrow().is_empty());
+
+        // as though the configuration files were reloaded
+        unsafe {
+            pg_sys::PgReloadTime += 1;
+        }
+        GucRegistry::process_reload();
+        assert_eq!(*changed.borrow(), vec!["work_mem"]);
+
+        unsafe {
+            pg_sys::PgReloadTime += 1;
+        }
+        GucRegistry::process_reload();
+        assert_eq!(changed.borrow().len(), 1);
+    }
+
+    #[pg_test]
+    fn test_guc_flags() {
+        static GUC: GucSetting<bool> = GucSetting::new(false);
+        GucRegistry::define_bool_guc(
+            "test.hidden",
+            "test guc flags",
+            "test guc flags",
+            &GUC,
+            GucContext::Suset,
+            GucFlags::SUPERUSER_ONLY | GucFlags::NO_SHOW_ALL | GucFlags::NOT_IN_SAMPLE,
+        );
+
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT count(*) FROM pg_settings WHERE name = 'test.hidden'"),
+            Some(0)
+        );
+        assert_eq!(
+            Spi::get_one::<&str>("SELECT current_setting('test.hidden')"),
+            Some("off")
+        );
+    }
+
+    #[pg_test(error = "permission denied to set parameter \"test.secure\"")]
+    fn test_suset_guc() {
+        static GUC: GucSetting<bool> = GucSetting::new(false);
+        GucRegistry::define_bool_guc(
+            "test.secure",
+            "test suset guc",
+            "test suset guc",
+            &GUC,
+            GucContext::Suset,
+            GucFlags::default(),
+        );
+
+        Spi::run("SET test.secure = on");
+        assert_eq!(GUC.get(), true);
+
+        Spi::run("CREATE ROLE test_suset_guc_user");
+        Spi::run("SET ROLE test_suset_guc_user");
+        Spi::run("SET test.secure = off");
+    }
+
+    #[pg_test]
+    fn test_get_for_role() {
+        let database = Spi::get_one::<String>("SELECT current_database()").unwrap();
+        Spi::run("CREATE ROLE test_get_for_role_user");
+        assert_eq!(
+            Guc::get_for_role::<String>("work_mem", "test_get_for_role_user", &database),
+            None
+        );
+
+        Spi::run("ALTER ROLE test_get_for_role_user SET work_mem = '32MB'");
+        assert_eq!(
+            Guc::get_for_role::<GucBytes>("work_mem", "test_get_for_role_user", &database),
+            Some(GucBytes(32 * 1024 * 1024))
+        );
+
+        Spi::run(&format!(
+            "ALTER ROLE test_get_for_role_user IN DATABASE {} SET work_mem = '48MB'",
+            sql::quote_identifier(&database)
+        ));
+        assert_eq!(
+            Guc::get_for_role::<String>("work_mem", "test_get_for_role_user", &database),
+            Some("48MB".to_string())
+        );
+        assert_eq!(
+            Guc::get_for_role::<String>("work_mem", "test_get_for_role_user", "postgres_other"),
+            Some("32MB".to_string())
+        );
+    }
+
+    #[pg_test]
+    fn test_alter_system() {
+        let auto_conf_setting = || {
+            Spi::get_one::<String>(
+                "SELECT setting FROM pg_file_settings \
+                 WHERE name = 'work_mem' AND sourcefile LIKE '%postgresql.auto.conf'",
+            )
+        };
+
+        Guc::alter_system("work_mem", Some("24MB"));
+        assert_eq!(auto_conf_setting(), Some("24MB".to_string()));
+
+        Guc::alter_system("work_mem", None);
+        assert_eq!(auto_conf_setting(), None);
+    }
+
+    #[pg_test(error = "invalid value for parameter \"work_mem\": \"lots\"")]
+    fn test_alter_system_invalid() {
+        Guc::alter_system("work_mem", Some("lots"));
+    }
+
+    #[derive(PgConfig)]
+    #[pg_config(prefix = "test_settings", settings_function = "test_settings")]
+    struct SettingsConfig {
+        #[pg_config(default = 10, min = 0, max = 100)]
+        limit: i32,
+    }
+
+    #[pg_test]
+    fn test_settings() {
+        static SETTINGS: Settings<SettingsConfig> = Settings::new();
+        SettingsConfig::define();
+
+        let first = SETTINGS.get();
+        assert_eq!(first.limit, 10);
+        assert!(std::sync::Arc::ptr_eq(&first, &SETTINGS.get()));
+
+        let generation = GucRegistry::generation();
+        Spi::run("SET test_settings.limit = 20");
+        assert!(GucRegistry::generation() > generation);
+
+        let second = SETTINGS.get();
+        assert_eq!(second.limit, 20);
+        assert!(!std::sync::Arc::ptr_eq(&first, &second));
+        assert!(std::sync::Arc::ptr_eq(&second, &SETTINGS.get()));
+    }
 }
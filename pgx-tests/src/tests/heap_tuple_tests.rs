@@ -0,0 +1,124 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+
+#[pg_extern]
+fn heap_tuple_get_name(dog: PgHeapTuple<'static>) -> Option<String> {
+    dog.get_by_name::<String>("name")
+        .expect("no `name` attribute")
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::num::NonZeroUsize;
+
+    #[derive(FromHeapTuple, IntoHeapTuple)]
+    struct Dog {
+        name: String,
+        #[heap_tuple(rename = "scritches")]
+        scritch_count: Option<i32>,
+    }
+
+    #[pg_test]
+    fn test_heap_tuple_from_composite_datum() {
+        Spi::run("CREATE TYPE Dog AS (name text, scritches integer);");
+        let name = Spi::get_one::<String>("SELECT heap_tuple_get_name(ROW('Nami', 10)::Dog);")
+            .expect("failed to get SPI result");
+        assert_eq!(name, "Nami");
+    }
+
+    #[pg_test]
+    fn test_heap_tuple_build() {
+        Spi::run("CREATE TYPE Dog AS (name text, scritches integer);");
+        let mut dog = PgHeapTuple::new_composite_type("Dog").expect("Dog is a composite type");
+        assert_eq!(dog.get_by_name::<String>("name"), Ok(None));
+
+        dog.set_by_name("name", "Brandy").unwrap();
+        dog.set_by_index(NonZeroUsize::new(2).unwrap(), 42_i32)
+            .unwrap();
+        assert_eq!(
+            dog.get_by_name::<String>("name"),
+            Ok(Some("Brandy".to_string()))
+        );
+        assert_eq!(dog.get_by_name::<i32>("scritches"), Ok(Some(42)));
+
+        dog.set_by_name("name", Option::<String>::None).unwrap();
+        assert_eq!(dog.get_by_name::<String>("name"), Ok(None));
+    }
+
+    #[pg_test]
+    fn test_heap_tuple_errors() {
+        Spi::run("CREATE TYPE Dog AS (name text, scritches integer);");
+        let mut dog = PgHeapTuple::new_composite_type("Dog").expect("Dog is a composite type");
+        assert_eq!(
+            dog.get_by_name::<String>("age"),
+            Err(PgHeapTupleError::NoSuchAttributeName("age".to_string()))
+        );
+        assert!(matches!(
+            dog.set_by_name("scritches", "many"),
+            Err(PgHeapTupleError::MismatchedType { .. })
+        ));
+        dog.set_by_name("name", "Brandy").unwrap();
+        assert_eq!(
+            dog.get_by_name::<i64>("name"),
+            Err(PgHeapTupleError::MismatchedType {
+                attribute: NonZeroUsize::new(1).unwrap(),
+                expected: pg_sys::TEXTOID,
+                found: pg_sys::INT8OID,
+            })
+        );
+        assert_eq!(
+            PgHeapTuple::new_composite_type("integer").err(),
+            Some(PgHeapTupleError::NotACompositeType("integer".to_string()))
+        );
+    }
+
+    #[pg_test]
+    fn test_heap_tuple_binary_coercible() {
+        Spi::run("CREATE TYPE Cat AS (name varchar(50), tag name);");
+        let mut cat = PgHeapTuple::new_composite_type("Cat").expect("Cat is a composite type");
+        cat.set_by_name("name", "Luna").unwrap();
+        assert_eq!(cat.get_by_name::<&str>("name"), Ok(Some("Luna")));
+        assert_eq!(
+            cat.get_by_name::<String>("name"),
+            Ok(Some("Luna".to_string()))
+        );
+        // a name isn't a varlena, so it isn't text
+        assert!(matches!(
+            cat.get_by_name::<String>("tag"),
+            Err(PgHeapTupleError::MismatchedType { .. })
+        ));
+    }
+
+    #[pg_test]
+    fn test_heap_tuple_derives() {
+        Spi::run("CREATE TYPE Dog AS (name text, scritches integer);");
+        let mut tuple = PgHeapTuple::new_composite_type("Dog").expect("Dog is a composite type");
+        assert_eq!(
+            Dog::from_heap_tuple(&tuple).err(),
+            Some(PgHeapTupleError::UnexpectedNull("name".to_string()))
+        );
+
+        let dog = Dog {
+            name: "Nami".to_string(),
+            scritch_count: Some(3),
+        };
+        dog.into_heap_tuple(&mut tuple).unwrap();
+        assert_eq!(tuple.get_by_name::<i32>("scritches"), Ok(Some(3)));
+
+        let dog = Dog::from_heap_tuple(&tuple).unwrap();
+        assert_eq!(dog.name, "Nami");
+        assert_eq!(dog.scritch_count, Some(3));
+    }
+}
@@ -99,4 +99,330 @@ mod tests {
         // TODO:  it'd be nice to also test that .commit() and .abort() also get called
         //    but I don't see how to do that since we're running *inside* a transaction here
     }
+
+    #[pg_test]
+    unsafe fn test_query_desc() {
+        use pgx::pg_sys::*;
+
+        #[derive(Debug, PartialEq)]
+        struct Started {
+            query_text: Option<String>,
+            operation: CmdType,
+            estimated_rows: f64,
+            rows_processed: Option<u64>,
+        }
+
+        struct QueryDescHook {
+            started: Vec<Started>,
+            ended: Vec<(Option<u64>, bool)>,
+        }
+        impl PgHooks for QueryDescHook {
+            fn executor_start(
+                &mut self,
+                mut query_desc: PgBox<QueryDesc>,
+                eflags: i32,
+                prev_hook: fn(PgBox<QueryDesc>, i32) -> HookResult<()>,
+            ) -> HookResult<()> {
+                self.started.push(Started {
+                    query_text: query_desc.query_text().map(|text| text.to_string()),
+                    operation: query_desc.operation(),
+                    estimated_rows: query_desc.estimated_rows(),
+                    rows_processed: query_desc.rows_processed(),
+                });
+                query_desc.enable_timing();
+                prev_hook(query_desc, eflags)
+            }
+
+            fn executor_end(
+                &mut self,
+                mut query_desc: PgBox<QueryDesc>,
+                prev_hook: fn(PgBox<QueryDesc>) -> HookResult<()>,
+            ) -> HookResult<()> {
+                self.ended.push((
+                    query_desc.rows_processed(),
+                    query_desc.total_time().is_some(),
+                ));
+                prev_hook(query_desc)
+            }
+        }
+
+        static mut HOOK: QueryDescHook = QueryDescHook {
+            started: Vec::new(),
+            ended: Vec::new(),
+        };
+        pgx::hooks::register_hook(&mut HOOK);
+        Spi::run("SELECT 1");
+
+        assert_eq!(
+            HOOK.started,
+            vec![Started {
+                query_text: Some("SELECT 1".to_string()),
+                operation: CmdType_CMD_SELECT,
+                estimated_rows: 1.0,
+                rows_processed: None,
+            }]
+        );
+        assert_eq!(HOOK.ended, vec![(Some(1), true)]);
+    }
+
+    #[pg_test]
+    unsafe fn test_hook_chaining() {
+        use pgx::pg_sys::*;
+
+        static mut CALLS: Vec<&'static str> = Vec::new();
+
+        struct NamedHook(&'static str);
+        impl PgHooks for NamedHook {
+            fn executor_start(
+                &mut self,
+                query_desc: PgBox<QueryDesc>,
+                eflags: i32,
+                prev_hook: fn(PgBox<QueryDesc>, i32) -> HookResult<()>,
+            ) -> HookResult<()> {
+                unsafe { CALLS.push(self.0) };
+                prev_hook(query_desc, eflags)
+            }
+        }
+
+        static mut FIRST: NamedHook = NamedHook("first");
+        static mut SECOND: NamedHook = NamedHook("second");
+        pgx::hooks::register_hook(&mut FIRST);
+        pgx::hooks::register_hook(&mut SECOND);
+
+        Spi::run("SELECT 1");
+        assert_eq!(CALLS, vec!["second", "first"]);
+        Spi::run("SELECT 1");
+        assert_eq!(CALLS, vec!["second", "first", "second", "first"]);
+    }
+
+    #[pg_test]
+    unsafe fn test_post_parse_analyze() {
+        use pgx::pg_sys::*;
+
+        struct AnalyzeHook {
+            statements: Vec<(String, CmdType)>,
+        }
+        impl PgHooks for AnalyzeHook {
+            fn post_parse_analyze(
+                &mut self,
+                parse_state: PgBox<ParseState>,
+                query: PgBox<Query>,
+                jumble_state: Option<PgBox<JumbleState>>,
+                prev_hook: fn(
+                    PgBox<ParseState>,
+                    PgBox<Query>,
+                    Option<PgBox<JumbleState>>,
+                ) -> HookResult<()>,
+            ) -> HookResult<()> {
+                self.statements.push((
+                    parse_state.source_text().unwrap().to_string(),
+                    query.commandType,
+                ));
+                prev_hook(parse_state, query, jumble_state)
+            }
+        }
+
+        static mut HOOK: AnalyzeHook = AnalyzeHook {
+            statements: Vec::new(),
+        };
+        pgx::hooks::register_hook(&mut HOOK);
+        Spi::run("SELECT 1");
+        Spi::run("CREATE TABLE tests.analyzed (id int)");
+        Spi::run("INSERT INTO tests.analyzed VALUES (1)");
+
+        assert_eq!(
+            HOOK.statements,
+            vec![
+                ("SELECT 1".to_string(), CmdType_CMD_SELECT),
+                (
+                    "CREATE TABLE tests.analyzed (id int)".to_string(),
+                    CmdType_CMD_UTILITY
+                ),
+                (
+                    "INSERT INTO tests.analyzed VALUES (1)".to_string(),
+                    CmdType_CMD_INSERT
+                ),
+            ]
+        );
+    }
+
+    #[pg_test]
+    unsafe fn test_object_access() {
+        struct AuditHook {
+            events: Vec<String>,
+        }
+        impl PgHooks for AuditHook {
+            fn object_access(
+                &mut self,
+                event: ObjectAccessEvent,
+                prev_hook: fn(ObjectAccessEvent) -> HookResult<()>,
+            ) -> HookResult<()> {
+                if event.class_id == pg_sys::RelationRelationId {
+                    let access = match &event.access {
+                        ObjectAccess::Create { .. } => "create",
+                        ObjectAccess::Drop { .. } => "drop",
+                        ObjectAccess::Alter { .. } => "alter",
+                        _ => "other",
+                    };
+                    self.events
+                        .push(format!("{} {} {}", access, event.object_id, event.sub_id));
+                }
+                prev_hook(event)
+            }
+        }
+
+        static mut HOOK: AuditHook = AuditHook { events: Vec::new() };
+        pgx::hooks::register_hook(&mut HOOK);
+        Spi::run("CREATE TABLE tests.audited (id int)");
+        let relid = Spi::get_one::<pg_sys::Oid>("SELECT 'tests.audited'::regclass::oid").unwrap();
+        Spi::run("ALTER TABLE tests.audited ADD COLUMN name text");
+        Spi::run("DROP TABLE tests.audited");
+
+        assert!(HOOK.events.contains(&format!("create {} 0", relid)));
+        assert!(HOOK.events.contains(&format!("create {} 2", relid)));
+        assert!(HOOK.events.contains(&format!("drop {} 0", relid)));
+    }
+
+    #[pg_test]
+    fn test_port() {
+        let port = unsafe { PgBox::from_pg(pg_sys::MyProcPort) };
+        assert_eq!(
+            port.user_name().map(str::to_string),
+            Spi::get_one::<String>("SELECT session_user::text")
+        );
+        assert_eq!(
+            port.database_name().map(str::to_string),
+            Spi::get_one::<String>("SELECT current_database()::text")
+        );
+        assert!(port.remote_host().is_some());
+        assert!(port.auth_method().is_some());
+    }
+
+    struct PasswordPolicy;
+    impl PgHooks for PasswordPolicy {
+        fn check_password(
+            &mut self,
+            username: &str,
+            password: Password,
+            valid_until: Option<pg_sys::TimestampTz>,
+            prev_hook: fn(&str, Password, Option<pg_sys::TimestampTz>) -> HookResult<()>,
+        ) -> HookResult<()> {
+            if !password.is_hashed() && password.as_str().len() < 8 {
+                reject_password("password is too short");
+            }
+            prev_hook(username, password, valid_until)
+        }
+    }
+
+    #[pg_test]
+    unsafe fn test_check_password() {
+        static mut HOOK: PasswordPolicy = PasswordPolicy;
+        pgx::hooks::register_hook(&mut HOOK);
+        Spi::run("CREATE ROLE tests_long_password PASSWORD 'correct horse battery staple'");
+        Spi::run(
+            "CREATE ROLE tests_hashed_password PASSWORD 'md5a3556571e93b0d20722ba62be61e8c2d'",
+        );
+    }
+
+    #[pg_test(error = "password is too short")]
+    unsafe fn test_check_password_rejected() {
+        static mut HOOK: PasswordPolicy = PasswordPolicy;
+        pgx::hooks::register_hook(&mut HOOK);
+        Spi::run("CREATE ROLE tests_short_password PASSWORD 'hunter2'");
+    }
+
+    #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+    #[pg_test]
+    unsafe fn test_explain_one_query() {
+        use pgx::pg_sys::*;
+
+        struct ExplainHook;
+        impl PgHooks for ExplainHook {
+            fn explain_one_query(
+                &mut self,
+                query: PgBox<Query>,
+                cursor_options: i32,
+                into: PgBox<IntoClause>,
+                explain: &mut Explain,
+                query_string: &std::ffi::CStr,
+                params: PgBox<ParamListInfoData>,
+                query_env: PgBox<QueryEnvironment>,
+                prev_hook: fn(
+                    PgBox<Query>,
+                    i32,
+                    PgBox<IntoClause>,
+                    &mut Explain,
+                    &std::ffi::CStr,
+                    PgBox<ParamListInfoData>,
+                    PgBox<QueryEnvironment>,
+                ) -> HookResult<()>,
+            ) -> HookResult<()> {
+                let result = prev_hook(
+                    query,
+                    cursor_options,
+                    into,
+                    explain,
+                    query_string,
+                    params,
+                    query_env,
+                );
+                explain.section("Cache", |explain| {
+                    explain.property("Served From", "cache");
+                    explain.property_integer("Entries", None, 3);
+                });
+                result
+            }
+        }
+
+        static mut HOOK: ExplainHook = ExplainHook;
+        pgx::hooks::register_hook(&mut HOOK);
+        let explain = Spi::get_one::<Json>("EXPLAIN (FORMAT JSON) SELECT 1")
+            .expect("EXPLAIN returned nothing")
+            .0;
+        assert!(explain[0].get("Plan").is_some(), "{}", explain);
+        assert_eq!(explain[1]["Served From"], "cache", "{}", explain);
+        assert_eq!(explain[1]["Entries"], 3, "{}", explain);
+    }
+
+    #[pg_test]
+    fn test_relation_access() {
+        Spi::run("CREATE TABLE tests.relation_access (id int, name text, score int)");
+        let relid = Spi::get_one::<pg_sys::Oid>("SELECT 'tests.relation_access'::regclass::oid");
+        let query = PlannedQuery::new(
+            "UPDATE tests.relation_access SET score = 0 WHERE name IS NULL",
+            &[],
+        );
+        let range_table = unsafe { PgList::from_pg((*query.as_ptr()).rtable) };
+
+        let access = RelationAccess::from_range_table(&range_table);
+        assert_eq!(access.len(), 1);
+        assert_eq!(Some(access[0].relid), relid);
+        assert!(access[0].requires(pg_sys::ACL_UPDATE | pg_sys::ACL_SELECT));
+        assert!(!access[0].requires(pg_sys::ACL_DELETE));
+        assert_eq!(access[0].selected_cols, vec![2]);
+        assert_eq!(access[0].updated_cols, vec![3]);
+    }
+
+    #[pg_test]
+    fn test_error_data() {
+        let mut error_data = PgBox::<pg_sys::ErrorData>::alloc0().into_pg_boxed();
+        error_data.elevel = pg_sys::WARNING as i32;
+        error_data.sqlerrcode = PgSqlErrorCode::ERRCODE_UNDEFINED_TABLE as i32;
+        error_data.assoc_context = unsafe { pg_sys::CurrentMemoryContext };
+        error_data.set_message("password is hunter2");
+        error_data.set_detail(Some("some detail"));
+
+        assert_eq!(error_data.severity(), "WARNING");
+        assert_eq!(error_data.sqlstate(), "42P01");
+        assert_eq!(error_data.message(), Some("password is hunter2"));
+        assert_eq!(error_data.detail(), Some("some detail"));
+        assert_eq!(error_data.hint(), None);
+        assert_eq!(error_data.location(), None);
+        assert_eq!(error_data.backend_pid(), unsafe { pg_sys::MyProcPid });
+
+        error_data.set_message("password is [redacted]");
+        error_data.set_detail(None);
+        assert_eq!(error_data.message(), Some("password is [redacted]"));
+        assert_eq!(error_data.detail(), None);
+    }
 }
@@ -0,0 +1,113 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+use std::collections::HashMap;
+
+/// The entries of each `test_index` index, which only last as long as the backend
+static mut ENTRIES: Option<HashMap<pg_sys::Oid, Vec<(i32, pg_sys::ItemPointerData)>>> = None;
+
+fn entries(index: &PgRelation) -> &'static mut Vec<(i32, pg_sys::ItemPointerData)> {
+    unsafe {
+        ENTRIES
+            .get_or_insert_with(HashMap::new)
+            .entry(index.oid())
+            .or_default()
+    }
+}
+
+/// An index supporting `=`, which finds matching entries by looking at every one of them
+struct TestIndexScan {
+    index: PgRelation,
+    matches: Vec<pg_sys::ItemPointerData>,
+}
+
+impl IndexAccessMethod for TestIndexScan {
+    type Key = i32;
+    type Query = i32;
+    const STRATEGIES: u16 = 1;
+
+    fn build_empty(index: &PgRelation) {
+        entries(index).clear();
+    }
+
+    fn insert(index: &PgRelation, key: i32, tid: pg_sys::ItemPointerData) {
+        entries(index).push((key, tid));
+    }
+
+    fn bulk_delete(
+        index: &PgRelation,
+        is_dead: &dyn Fn(pg_sys::ItemPointerData) -> bool,
+    ) -> IndexVacuumStats {
+        let entries = entries(index);
+        let before = entries.len();
+        entries.retain(|(_, tid)| !is_dead(*tid));
+        IndexVacuumStats {
+            index_tuples: entries.len() as f64,
+            tuples_removed: (before - entries.len()) as f64,
+        }
+    }
+
+    fn begin_scan(index: &PgRelation) -> Self {
+        TestIndexScan {
+            index: index.clone(),
+            matches: Vec::new(),
+        }
+    }
+
+    fn rescan(&mut self, keys: Vec<IndexScanKey<i32>>) {
+        self.matches = entries(&self.index)
+            .iter()
+            .filter(|(key, _)| keys.iter().all(|scan_key| scan_key.argument == *key))
+            .map(|(_, tid)| *tid)
+            .collect();
+    }
+
+    fn next(&mut self) -> Option<(pg_sys::ItemPointerData, bool)> {
+        self.matches.pop().map(|tid| (tid, false))
+    }
+}
+
+#[pg_extern(sql = r#"
+    CREATE FUNCTION test_index_handler(internal) RETURNS index_am_handler
+    LANGUAGE c AS '@MODULE_PATHNAME@', '@FUNCTION_NAME@';
+    CREATE ACCESS METHOD test_index TYPE INDEX HANDLER test_index_handler;
+    CREATE OPERATOR CLASS test_index_int4_ops DEFAULT FOR TYPE int4 USING test_index AS
+        OPERATOR 1 =(int4, int4);
+"#)]
+fn test_index_handler(_internal: Internal) -> PgBox<pg_sys::IndexAmRoutine> {
+    index_am_routine::<TestIndexScan>()
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_index_am() {
+        Spi::run("CREATE TABLE tests.indexed (n int)");
+        Spi::run("INSERT INTO tests.indexed SELECT n % 5 FROM generate_series(1, 100) n");
+        Spi::run("CREATE INDEX indexed_n ON tests.indexed USING test_index (n)");
+        Spi::run("INSERT INTO tests.indexed VALUES (2), (NULL)");
+        Spi::run("SET enable_seqscan = off");
+
+        let plan = Spi::get_one::<String>("EXPLAIN SELECT * FROM tests.indexed WHERE n = 2")
+            .expect("EXPLAIN returned nothing");
+        assert!(plan.contains("Index Scan using indexed_n"), "{}", plan);
+
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM tests.indexed WHERE n = 2");
+        assert_eq!(count, Some(21));
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM tests.indexed WHERE n = 7");
+        assert_eq!(count, Some(0));
+    }
+}
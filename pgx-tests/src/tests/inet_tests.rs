@@ -42,4 +42,55 @@ mod tests {
         .expect("failed to get SPI result");
         assert!(rc)
     }
+
+    #[pg_extern]
+    fn take_and_return_ip_addr(addr: std::net::IpAddr) -> std::net::IpAddr {
+        addr
+    }
+
+    #[pg_extern]
+    fn ipv4_loopback() -> std::net::Ipv4Addr {
+        std::net::Ipv4Addr::LOCALHOST
+    }
+
+    #[pg_extern]
+    fn ipv6_segments(addr: std::net::Ipv6Addr) -> Vec<i32> {
+        addr.segments().iter().map(|s| *s as i32).collect()
+    }
+
+    #[pg_test]
+    fn test_take_and_return_ip_addr() {
+        let rc = Spi::get_one::<bool>(
+            "SELECT tests.take_and_return_ip_addr('192.168.0.1') = '192.168.0.1'::inet \
+                AND tests.take_and_return_ip_addr('::1') = '::1'::inet;",
+        )
+        .expect("failed to get SPI result");
+        assert!(rc)
+    }
+
+    #[pg_test]
+    fn test_return_ipv4_addr() {
+        let rc = Spi::get_one::<bool>("SELECT tests.ipv4_loopback() = '127.0.0.1'::inet;")
+            .expect("failed to get SPI result");
+        assert!(rc)
+    }
+
+    #[pg_test]
+    fn test_ipv6_addr_arg() {
+        let rc = Spi::get_one::<bool>(
+            "SELECT tests.ipv6_segments('2001:db8::1') = ARRAY[8193, 3512, 0, 0, 0, 0, 0, 1];",
+        )
+        .expect("failed to get SPI result");
+        assert!(rc)
+    }
+
+    #[pg_test(error = "inet value `10.0.0.0/8` is not a host address")]
+    fn test_ip_addr_rejects_network() {
+        Spi::get_one::<bool>("SELECT tests.take_and_return_ip_addr('10.0.0.0/8') IS NULL;");
+    }
+
+    #[pg_test(error = "inet value `127.0.0.1` is not an IPv6 address")]
+    fn test_ipv6_addr_rejects_ipv4() {
+        Spi::get_one::<bool>("SELECT tests.ipv6_segments('127.0.0.1') IS NULL;");
+    }
 }
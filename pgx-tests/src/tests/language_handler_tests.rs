@@ -0,0 +1,78 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[pg_language_validator]
+fn plecho_validator(function: &PgLanguageFunction) -> Result<(), String> {
+    let is_text = |ty: &pg_sys::Oid| *ty == pg_sys::TEXTOID;
+    if !is_text(&function.return_type) || !function.arg_types.iter().all(is_text) {
+        return Err(format!(
+            "{} must only accept and return text",
+            function.name
+        ));
+    }
+    Ok(())
+}
+
+#[pg_language_handler(
+    language = "plecho",
+    validator = "plecho_validator",
+    requires = [plecho_validator]
+)]
+fn plecho_call_handler(call: &PgLanguageCall) -> Option<pg_sys::Datum> {
+    let mut echo = call.function().src.clone();
+    for argno in 0..call.nargs() {
+        let arg = call.arg::<String>(argno);
+        echo = echo.replace(&format!("${}", argno + 1), arg.as_deref().unwrap_or("NULL"));
+    }
+    echo.into_datum()
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_language_call() {
+        Spi::run(
+            "CREATE FUNCTION tests.greet(name text, greeting text) RETURNS text \
+             LANGUAGE plecho AS '$2, $1!'",
+        );
+        let greeting = Spi::get_one::<String>("SELECT tests.greet('Nami', 'hello')");
+        assert_eq!(greeting, Some("hello, Nami!".to_string()));
+        let greeting = Spi::get_one::<String>("SELECT tests.greet(NULL, 'hello')");
+        assert_eq!(greeting, Some("hello, NULL!".to_string()));
+    }
+
+    #[pg_test]
+    fn test_language_function() {
+        Spi::run(
+            "CREATE FUNCTION tests.greet(name text, text) RETURNS text \
+             LANGUAGE plecho STRICT AS 'hello, $1'",
+        );
+        let oid = Spi::get_one::<pg_sys::Oid>("SELECT 'tests.greet'::regproc::oid")
+            .expect("function not found");
+        let function = PgLanguageFunction::from_oid(oid);
+        assert_eq!(function.name, "greet");
+        assert_eq!(function.src, "hello, $1");
+        assert_eq!(function.arg_types, vec![pg_sys::TEXTOID, pg_sys::TEXTOID]);
+        assert_eq!(function.arg_names, vec![Some("name".to_string()), None]);
+        assert!(function.is_strict && !function.returns_set);
+    }
+
+    #[pg_test(error = "count must only accept and return text")]
+    fn test_language_validator() {
+        Spi::run("CREATE FUNCTION tests.count() RETURNS integer LANGUAGE plecho AS '1'");
+    }
+}
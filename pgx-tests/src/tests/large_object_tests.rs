@@ -0,0 +1,71 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    #[pg_test]
+    fn test_large_object_write_and_read() {
+        let lo = LargeObject::create();
+        let mut handle = lo.open(LargeObjectMode::ReadWrite);
+        handle.write_all(b"hello, world").unwrap();
+        assert_eq!(handle.tell(), 12);
+
+        handle.seek(SeekFrom::Start(7)).unwrap();
+        let mut contents = String::new();
+        handle.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "world");
+    }
+
+    #[pg_test]
+    fn test_large_object_visible_to_sql() {
+        let lo = LargeObject::create();
+        lo.open(LargeObjectMode::Write)
+            .write_all(b"from rust")
+            .unwrap();
+
+        let contents = Spi::get_one::<Vec<u8>>(&format!("SELECT lo_get({});", lo.oid()))
+            .expect("failed to get SPI result");
+        assert_eq!(contents, b"from rust");
+    }
+
+    #[pg_test]
+    fn test_large_object_seek_and_truncate() {
+        let lo = LargeObject::create();
+        let mut handle = lo.open(LargeObjectMode::ReadWrite);
+        handle.write_all(&[1, 2, 3, 4, 5]).unwrap();
+        handle.truncate(3);
+
+        assert_eq!(handle.seek(SeekFrom::End(0)).unwrap(), 3);
+        assert_eq!(handle.seek(SeekFrom::Current(-2)).unwrap(), 1);
+        let mut rest = Vec::new();
+        handle.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, vec![2, 3]);
+    }
+
+    #[pg_test]
+    fn test_large_object_unlink() {
+        let lo = LargeObject::create();
+        let oid = lo.oid();
+        lo.unlink();
+
+        let exists = Spi::get_one::<bool>(&format!(
+            "SELECT EXISTS(SELECT 1 FROM pg_largeobject_metadata WHERE oid = {});",
+            oid
+        ))
+        .expect("failed to get SPI result");
+        assert!(!exists);
+    }
+}
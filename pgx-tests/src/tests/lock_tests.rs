@@ -0,0 +1,108 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    /// How many advisory locks this backend holds on `objid`, in `mode`
+    fn advisory_locks(objid: i64, mode: &str) -> i64 {
+        Spi::get_one_with_args(
+            "SELECT count(*) FROM pg_locks
+              WHERE locktype = 'advisory' AND pid = pg_backend_pid()
+                AND objid::text::bigint = $1 AND mode = $2",
+            vec![
+                (PgBuiltInOids::INT8OID.oid(), objid.into_datum()),
+                (PgBuiltInOids::TEXTOID.oid(), mode.into_datum()),
+            ],
+        )
+        .expect("count was NULL")
+    }
+
+    #[pg_test]
+    fn test_advisory_lock_released_on_drop() {
+        let guard = AdvisoryLock::new(4201).lock();
+        assert_eq!(advisory_locks(4201, "ExclusiveLock"), 1);
+        // the same session can take it again
+        assert!(AdvisoryLock::new(4201).try_lock().is_some());
+        assert_eq!(advisory_locks(4201, "ExclusiveLock"), 1);
+
+        drop(guard);
+        assert_eq!(advisory_locks(4201, "ExclusiveLock"), 0);
+    }
+
+    #[pg_test]
+    fn test_advisory_lock_matches_sql() {
+        let guard = AdvisoryLock::shared((1, 4202)).lock();
+        assert_eq!(advisory_locks(4202, "ShareLock"), 1);
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT pg_advisory_unlock_shared(1, 4202)"),
+            Some(true)
+        );
+        assert_eq!(advisory_locks(4202, "ShareLock"), 0);
+        // it's been unlocked already
+        std::mem::forget(guard);
+    }
+
+    #[pg_test]
+    fn test_advisory_xact_lock() {
+        let lock = AdvisoryLock::new(4203);
+        lock.lock_xact();
+        assert!(lock.try_lock_xact());
+        assert_eq!(advisory_locks(4203, "ExclusiveLock"), 1);
+        // a session's unlock_all() leaves the transaction's locks
+        AdvisoryLock::unlock_all();
+        assert_eq!(advisory_locks(4203, "ExclusiveLock"), 1);
+    }
+
+    #[pg_test]
+    fn test_advisory_lock_keys() {
+        assert_eq!(AdvisoryLock::new(7).key(), AdvisoryLockKey::Int8(7));
+        assert_eq!(
+            AdvisoryLock::shared((1, 2)).key(),
+            AdvisoryLockKey::Int4(1, 2)
+        );
+        assert!(AdvisoryLock::shared(7).is_shared());
+        assert!(!AdvisoryLock::new(7).is_shared());
+    }
+
+    #[pg_test]
+    fn test_relation_lock() {
+        Spi::run("CREATE TABLE lock_test (id int)");
+        let oid = RelationLock::acquire_by_name("lock_test", PgLockMode::ShareRowExclusive);
+        assert_eq!(
+            Spi::get_one::<pg_sys::Oid>("SELECT 'lock_test'::regclass::oid"),
+            Some(oid)
+        );
+        assert!(RelationLock::try_acquire(oid, PgLockMode::AccessExclusive));
+
+        let modes = || {
+            Spi::get_one_with_args::<String>(
+                "SELECT string_agg(mode, ',' ORDER BY mode) FROM pg_locks
+                  WHERE relation = $1 AND pid = pg_backend_pid()",
+                vec![(PgBuiltInOids::OIDOID.oid(), oid.into_datum())],
+            )
+            .expect("no locks")
+        };
+        assert!(modes().contains("ShareRowExclusiveLock"));
+
+        RelationLock::release(oid, PgLockMode::ShareRowExclusive);
+        assert!(!modes().contains("ShareRowExclusiveLock"));
+        assert!(modes().contains("AccessExclusiveLock"));
+    }
+
+    #[pg_test(error = "relation \"no_such_table\" does not exist")]
+    fn test_relation_lock_missing() {
+        RelationLock::acquire_by_name("no_such_table", PgLockMode::AccessShare);
+    }
+}
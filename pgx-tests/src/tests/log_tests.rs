@@ -70,6 +70,19 @@ mod tests {
         check_for_interrupts!();
     }
 
+    #[pg_test]
+    fn test_interruptible() {
+        assert_eq!((0..10).interruptible(3).sum::<i32>(), 45);
+        assert_eq!((0..10).interruptible(1).len(), 10);
+    }
+
+    #[pg_test(sqlstate = ERRCODE_QUERY_CANCELED, guc = "statement_timeout=100ms")]
+    fn test_interruptible_timeout() {
+        (0..).interruptible(1000).for_each(|i: u64| {
+            std::hint::black_box(i);
+        });
+    }
+
     #[pg_test(error = "ereport error")]
     fn test_ereport() {
         ereport(
@@ -82,8 +95,115 @@ mod tests {
         )
     }
 
+    #[pg_test(
+        sqlstate = ERRCODE_INVALID_PARAMETER_VALUE,
+        error = "no dogs",
+        detail_contains = "0 dogs",
+        hint_contains = "adopt"
+    )]
+    fn test_ereport_macro() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            "no dogs",
+            detail = format!("{} dogs were asked for.", 0),
+            hint = "Try to adopt one.",
+        );
+    }
+
+    #[pg_test]
+    fn test_ereport_macro_fields() {
+        let error = assert_pg_error!(PgSqlErrorCode::ERRCODE_SYNTAX_ERROR, || {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_SYNTAX_ERROR,
+                "bad dog",
+                detail = "It chewed the query.",
+                hint = "Use a leash.",
+                context = "walking the dog",
+                position = 7,
+            )
+        });
+        assert_eq!(error.message, "bad dog");
+        assert_eq!(error.detail.as_deref(), Some("It chewed the query."));
+        assert_eq!(error.hint.as_deref(), Some("Use a leash."));
+        assert!(error.context.unwrap().contains("walking the dog"));
+        assert_eq!(error.position, Some(7));
+    }
+
+    #[pg_test]
+    fn test_ereport_macro_warning() {
+        ereport!(
+            WARNING,
+            PgSqlErrorCode::ERRCODE_WARNING_DEPRECATED_FEATURE,
+            "old dog",
+            hint = "Teach it new tricks."
+        );
+        ereport!(
+            NOTICE,
+            PgSqlErrorCode::ERRCODE_SUCCESSFUL_COMPLETION,
+            "good dog"
+        );
+    }
+
     #[pg_test(error = "panic message")]
     fn test_panic() {
         panic!("panic message")
     }
+
+    // each test runs in its own backend, so the handling doesn't outlive it
+    #[pg_test(sqlstate = ERRCODE_DATA_EXCEPTION, error = "bad dog")]
+    fn test_panic_handling() {
+        PanicHandling {
+            sqlstate: PgSqlErrorCode::ERRCODE_DATA_EXCEPTION,
+            backtrace: true,
+            ..Default::default()
+        }
+        .install();
+        panic!("bad dog")
+    }
+
+    // each test runs in its own backend, so the layer is only installed once in it
+    #[pg_test]
+    fn test_log_layer() {
+        PgLogLayer::new()
+            .with_level(tracing::Level::INFO, PgLogLevel::NOTICE)
+            .install()
+            .expect("a tracing subscriber was already installed");
+
+        let span = tracing::info_span!("adopt", name = "brandy");
+        let _entered = span.enter();
+        tracing::info!(dogs = 1, "adopted");
+        tracing::error!("logged as a WARNING, which doesn't abort the transaction");
+        tracing::trace!("filtered out by log_min_messages");
+        assert_eq!(Spi::get_one::<i32>("SELECT 1"), Some(1));
+    }
+
+    #[pg_test(
+        error = "tracing events can't be logged at ERROR or above, as that aborts the transaction"
+    )]
+    fn test_log_layer_error_level() {
+        PgLogLayer::new().with_level(tracing::Level::ERROR, PgLogLevel::ERROR);
+    }
+
+    #[pg_test]
+    fn test_structured_log() {
+        let log = StructuredLog::new("adopted a dog")
+            .field("name", "brandy")
+            .field("age", 3)
+            .field("owner", "the neighbours")
+            .field("tricks", vec!["sit", "stay"]);
+        assert_eq!(
+            log.format(LogFormat::Json),
+            r#"{"msg":"adopted a dog","name":"brandy","age":3,"owner":"the neighbours","tricks":["sit","stay"]}"#
+        );
+        assert_eq!(
+            log.format(LogFormat::Text),
+            r#"adopted a dog name=brandy age=3 owner="the neighbours" tricks=["sit","stay"]"#
+        );
+
+        // jsonlog is new in Postgres 15
+        assert_eq!(LogFormat::current(), LogFormat::Text);
+        structured_log!(LOG, "adopted a dog", name = "brandy", age = 3);
+    }
 }
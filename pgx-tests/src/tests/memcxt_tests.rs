@@ -47,4 +47,91 @@ mod tests {
 
         assert!(did_drop.load(Ordering::SeqCst))
     }
+
+    #[pg_test]
+    fn test_child_context() {
+        let mut batch = PgMemoryContexts::CurrentMemoryContext.new_child("batch");
+        assert_eq!(batch.name(), "batch");
+        assert_eq!(
+            batch.parent().unwrap().value(),
+            PgMemoryContexts::CurrentMemoryContext.value()
+        );
+
+        let nested = batch.new_child("nested");
+        assert_eq!(nested.parent().unwrap().value(), batch.value());
+
+        batch.delete_children();
+        batch.reset();
+        drop(nested); // already deleted with the rest of the children
+    }
+
+    #[pg_test]
+    fn test_child_context_drop() {
+        let mut batch = PgMemoryContexts::new("batch");
+        let has_children =
+            |batch: &PgMemoryContexts| unsafe { !(*batch.value()).firstchild.is_null() };
+
+        // a child that's reset is still deleted when it's dropped
+        let mut child = batch.new_child("child");
+        child.palloc(64);
+        child.reset();
+        assert!(has_children(&batch));
+        drop(child);
+        assert!(!has_children(&batch));
+
+        // but not once its parent's reset deleted it
+        let child = batch.new_child("child");
+        batch.reset();
+        assert!(!has_children(&batch));
+        drop(child);
+    }
+
+    #[pg_test]
+    fn test_switch() {
+        let outer = PgMemoryContexts::CurrentMemoryContext.value();
+        let batch = PgMemoryContexts::new("batch");
+        {
+            let _switched = batch.switch();
+            assert_eq!(
+                PgMemoryContexts::CurrentMemoryContext.value(),
+                batch.value()
+            );
+
+            let ptr = unsafe { pg_sys::palloc(64) };
+            assert_eq!(PgMemoryContexts::Of(ptr).value(), batch.value());
+        }
+        assert_eq!(PgMemoryContexts::CurrentMemoryContext.value(), outer);
+    }
+
+    #[pg_test]
+    fn test_stats() {
+        let mut batch = PgMemoryContexts::new("batch");
+        let empty = batch.stats(false);
+        batch.palloc(1024 * 1024);
+        let full = batch.stats(false);
+        assert!(full.total_bytes > empty.total_bytes);
+        assert!(full.used_bytes() >= 1024 * 1024);
+        assert!(full.blocks > empty.blocks);
+
+        let mut child = batch.new_child("child");
+        child.palloc(1024 * 1024);
+        assert!(batch.stats(true).used_bytes() >= 2 * 1024 * 1024);
+        assert_eq!(batch.stats(false), full);
+
+        batch.reset();
+        assert!(batch.stats(false).used_bytes() < full.used_bytes());
+        drop(child); // already deleted by the reset
+    }
+
+    #[pg_test]
+    fn test_reset_callback() {
+        let did_reset = Arc::new(AtomicBool::new(false));
+        let mut batch = PgMemoryContexts::new("batch");
+        let flag = did_reset.clone();
+        batch.register_reset_callback(move || flag.store(true, Ordering::SeqCst));
+        assert!(!did_reset.load(Ordering::SeqCst));
+
+        batch.reset();
+        assert!(did_reset.load(Ordering::SeqCst));
+    }
 }
@@ -10,33 +10,74 @@ Use of this source code is governed by the MIT license that can be found in the
 mod aggregate_tests;
 mod anyarray_tests;
 mod array_tests;
+mod async_bgworker_tests;
+mod async_spi_tests;
+mod atomics_tests;
+mod bgworker_tests;
 mod bytea_tests;
+mod cast_tests;
 mod cfg_tests;
+mod collation_tests;
+mod condition_variable_tests;
+mod copy_tests;
+mod custom_scan_tests;
 mod datetime_tests;
 mod default_arg_value_tests;
 mod derive_pgtype_lifetimes;
+mod dshash_tests;
+mod dsm_tests;
 mod enum_type_tests;
+mod event_trigger_tests;
+mod executor_tests;
 mod fcinfo_tests;
+mod fdw_tests;
+mod generic_xlog_tests;
+mod gin_tests;
 mod guc_tests;
+mod heap_tuple_tests;
 mod hooks_tests;
+mod index_am_tests;
 mod inet_tests;
 mod internal_tests;
 mod json_tests;
+mod language_handler_tests;
+mod large_object_tests;
 mod lifetime_tests;
+mod lock_tests;
 mod log_tests;
 mod memcxt_tests;
 mod name_tests;
+mod notify_tests;
 mod numeric_tests;
+mod operator_class_tests;
+mod output_plugin_tests;
+mod parallel_tests;
 mod pg_extern_tests;
+mod pg_test_tests;
 mod pg_try_tests;
 mod pgbox_tests;
+mod pgvec_tests;
+mod planner_support_tests;
+mod procedure_tests;
 mod postgres_type_tests;
+mod refcursor_tests;
+mod replication_slot_tests;
+mod scheduler_tests;
 mod schema_tests;
+mod shm_mq_tests;
+mod snapshot_tests;
 mod spi_tests;
+mod sql_tests;
 mod srf_tests;
+mod stats_tests;
 mod struct_type_tests;
+mod table_am_tests;
+mod trigger_tests;
+mod utility_tests;
 mod uuid_tests;
 mod variadic_tests;
+mod wait_event_tests;
+mod window_tests;
 mod xact_callback_tests;
 mod xid64_tests;
 
@@ -0,0 +1,53 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_notify_send() {
+        Notify::send("pgx_tests_dogs", "brandy");
+        Notify::send("pgx_tests_dogs", "");
+        // the notifications are only queued once the transaction commits
+        assert_eq!(
+            Spi::get_one::<f64>("SELECT pg_notification_queue_usage()"),
+            Some(0.0)
+        );
+    }
+
+    #[pg_test(sqlstate = ERRCODE_INVALID_PARAMETER_VALUE, error = "channel name cannot be empty")]
+    fn test_notify_send_empty_channel() {
+        Notify::send("", "brandy");
+    }
+
+    #[pg_test(sqlstate = ERRCODE_INVALID_PARAMETER_VALUE, error = "payload string too long")]
+    fn test_notify_send_long_payload() {
+        Notify::send("pgx_tests_dogs", &"x".repeat(8000));
+    }
+
+    #[pg_test]
+    fn test_notify_listen() {
+        Notify::listen("pgx_tests_dogs");
+        Notify::listen("pgx_tests_cats");
+        Notify::unlisten("pgx_tests_cats");
+        Notify::unlisten_all();
+        // LISTEN takes effect at commit
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT count(*) FROM pg_listening_channels()"),
+            Some(0)
+        );
+        // and notifications are only received outside of a transaction
+        assert_eq!(Notify::receive(), vec![]);
+    }
+}
@@ -0,0 +1,89 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+#[derive(
+    PostgresEnum,
+    Debug,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    PostgresEq,
+    PostgresOrd,
+    PostgresHash,
+)]
+#[pgx(cross_type(i32))]
+pub enum ShirtSize {
+    Small = 1,
+    Medium = 2,
+    Large = 3,
+}
+
+impl Hash for ShirtSize {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (*self as i32).hash(state)
+    }
+}
+
+impl PartialEq<i32> for ShirtSize {
+    fn eq(&self, other: &i32) -> bool {
+        *self as i32 == *other
+    }
+}
+
+impl PartialOrd<i32> for ShirtSize {
+    fn partial_cmp(&self, other: &i32) -> Option<Ordering> {
+        (*self as i32).partial_cmp(other)
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_btree_opclass_exists() {
+        let opclass = Spi::get_one::<bool>(
+            "SELECT EXISTS(SELECT 1 FROM pg_opclass WHERE opcname = 'shirtsize_btree_ops');",
+        )
+        .expect("failed to get SPI result");
+        assert!(opclass);
+    }
+
+    #[pg_test]
+    fn test_cross_type_operators() {
+        let result = Spi::get_one::<bool>("SELECT 'Medium'::ShirtSize = 2;")
+            .expect("failed to get SPI result");
+        assert!(result);
+
+        let result = Spi::get_one::<bool>("SELECT 'Large'::ShirtSize < 2;")
+            .expect("failed to get SPI result");
+        assert!(!result);
+    }
+
+    #[pg_test]
+    fn test_btree_index_on_type() {
+        Spi::run("CREATE TABLE shirts (size ShirtSize);");
+        Spi::run("INSERT INTO shirts VALUES ('Large'), ('Small'), ('Medium');");
+        Spi::run("CREATE INDEX idxshirts ON shirts USING btree (size);");
+        Spi::run("CREATE INDEX idxshirts_hash ON shirts USING hash (size);");
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM shirts WHERE size >= 2;")
+            .expect("failed to get SPI result");
+        assert_eq!(count, 2);
+    }
+}
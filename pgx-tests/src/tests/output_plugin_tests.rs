@@ -0,0 +1,224 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+/// Writes each change as `INSERT dogs: name=brandy`, with the columns of its new tuple, or the
+/// replica identity columns of its old tuple
+pub struct ChangeLines {
+    skip_empty: bool,
+    changes: usize,
+}
+
+impl OutputPlugin for ChangeLines {
+    fn startup(options: &[(String, Option<String>)], _is_init: bool) -> Self {
+        let mut skip_empty = false;
+        for (name, value) in options {
+            match (name.as_str(), value.as_deref()) {
+                ("skip-empty-xacts", Some(value)) => skip_empty = value == "1",
+                _ => error!("option \"{}\" = {:?} is unknown", name, value),
+            }
+        }
+        ChangeLines {
+            skip_empty,
+            changes: 0,
+        }
+    }
+
+    fn begin(&mut self, out: &mut OutputWriter, txn: &DecodedTransaction) {
+        self.changes = 0;
+        if !self.skip_empty {
+            out.write(format!("BEGIN {}", txn.xid()));
+        }
+    }
+
+    fn change(
+        &mut self,
+        out: &mut OutputWriter,
+        _txn: &DecodedTransaction,
+        relation: &PgRelation,
+        change: Change,
+    ) {
+        self.changes += 1;
+        let (kind, tuple) = match change {
+            Change::Insert { new } => ("INSERT", Some(new)),
+            Change::Update { new, .. } => ("UPDATE", Some(new)),
+            Change::Delete { old } => ("DELETE", old),
+        };
+
+        let mut line = format!("{} {}:", kind, relation.name());
+        if let Some(tuple) = tuple {
+            for (attno, attribute) in tuple.attributes() {
+                let name = name_data_to_str(&attribute.attname);
+                if is_unchanged_toast(&tuple, attno.get()) {
+                    line.push_str(&format!(" {}=<unchanged>", name));
+                } else {
+                    let value = unsafe {
+                        heap_getattr_raw(tuple.as_ptr(), attno.get(), tuple.tupdesc().as_ptr())
+                            .map(|datum| output_text(datum, attribute.atttypid))
+                    };
+                    line.push_str(&format!(" {}={}", name, value.as_deref().unwrap_or("null")));
+                }
+            }
+        }
+        out.write(line);
+    }
+
+    fn commit(
+        &mut self,
+        out: &mut OutputWriter,
+        txn: &DecodedTransaction,
+        _commit_lsn: pg_sys::XLogRecPtr,
+    ) {
+        if !self.skip_empty || self.changes > 0 {
+            out.write(format!("COMMIT {}", txn.xid()));
+        }
+    }
+}
+
+unsafe fn output_text(datum: pg_sys::Datum, typoid: pg_sys::Oid) -> String {
+    let mut output_func = pg_sys::InvalidOid;
+    let mut is_varlena = false;
+    pg_sys::getTypeOutputInfo(typoid, &mut output_func, &mut is_varlena);
+    std::ffi::CStr::from_ptr(pg_sys::OidOutputFunctionCall(output_func, datum))
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[allow(non_snake_case)]
+#[pg_guard]
+pub unsafe extern "C" fn _PG_output_plugin_init(callbacks: *mut pg_sys::OutputPluginCallbacks) {
+    output_plugin_callbacks::<ChangeLines>(callbacks);
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use super::ChangeLines;
+    use pgx::pg_sys::AsPgCStr;
+    use pgx::*;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static WRITTEN: RefCell<Vec<String>> = RefCell::new(Vec::new());
+    }
+
+    #[pg_guard]
+    unsafe extern "C" fn prepare_write(
+        ctx: *mut pg_sys::LogicalDecodingContext,
+        _lsn: pg_sys::XLogRecPtr,
+        _xid: pg_sys::TransactionId,
+        _last_write: bool,
+    ) {
+        pg_sys::resetStringInfo((*ctx).out);
+    }
+
+    #[pg_guard]
+    unsafe extern "C" fn write(
+        ctx: *mut pg_sys::LogicalDecodingContext,
+        _lsn: pg_sys::XLogRecPtr,
+        _xid: pg_sys::TransactionId,
+        _last_write: bool,
+    ) {
+        let out = &*(*ctx).out;
+        let bytes = std::slice::from_raw_parts(out.data as *const u8, out.len as usize);
+        WRITTEN.with(|written| {
+            written
+                .borrow_mut()
+                .push(String::from_utf8(bytes.to_vec()).unwrap())
+        });
+    }
+
+    /// A decoding context which writes to `WRITTEN`, passing `options` to the plugin
+    unsafe fn decoding_context(options: &[(&str, &str)]) -> pg_sys::LogicalDecodingContext {
+        let mut ctx = pg_sys::LogicalDecodingContext::default();
+        ctx.context = pg_sys::CurrentMemoryContext;
+        ctx.out = pg_sys::makeStringInfo();
+        ctx.accept_writes = true;
+        ctx.prepare_write = Some(prepare_write);
+        ctx.write = Some(write);
+        for (name, value) in options {
+            let value = pg_sys::makeString(value.as_pg_cstr()) as *mut pg_sys::Node;
+            ctx.output_plugin_options = pg_sys::lappend(
+                ctx.output_plugin_options,
+                pg_sys::makeDefElem(name.as_pg_cstr(), value, -1) as *mut _,
+            );
+        }
+        ctx
+    }
+
+    #[pg_test]
+    fn test_output_plugin() {
+        Spi::run("CREATE TABLE dogs (name text, age int)");
+        let oid = Spi::get_one::<pg_sys::Oid>("SELECT 'dogs'::regclass::oid").unwrap();
+        let relation = PgRelation::with_lock(oid, pg_sys::AccessShareLock as pg_sys::LOCKMODE);
+
+        unsafe {
+            let mut callbacks = pg_sys::OutputPluginCallbacks::default();
+            output_plugin_callbacks::<ChangeLines>(&mut callbacks);
+
+            let mut ctx = decoding_context(&[("skip-empty-xacts", "1")]);
+            let mut options = pg_sys::OutputPluginOptions::default();
+            callbacks.startup_cb.unwrap()(&mut ctx, &mut options, false);
+            assert_eq!(
+                options.output_type,
+                pg_sys::OutputPluginOutputType_OUTPUT_PLUGIN_TEXTUAL_OUTPUT
+            );
+
+            let mut txn = pg_sys::ReorderBufferTXN::default();
+            txn.xid = 42;
+            callbacks.begin_cb.unwrap()(&mut ctx, &mut txn);
+
+            let mut values = ["brandy".into_datum().unwrap(), 3_i32.into_datum().unwrap()];
+            let mut nulls = [false, true];
+            let tuple =
+                pg_sys::heap_form_tuple(relation.rd_att, values.as_mut_ptr(), nulls.as_mut_ptr());
+            let mut buf = pg_sys::ReorderBufferTupleBuf::default();
+            buf.tuple = *tuple;
+            let mut change = pg_sys::ReorderBufferChange::default();
+            change.action = pg_sys::ReorderBufferChangeType_REORDER_BUFFER_CHANGE_INSERT;
+            change.data.tp.newtuple = &mut buf;
+            callbacks.change_cb.unwrap()(&mut ctx, &mut txn, relation.as_ptr(), &mut change);
+
+            change.action = pg_sys::ReorderBufferChangeType_REORDER_BUFFER_CHANGE_DELETE;
+            change.data.tp.newtuple = std::ptr::null_mut();
+            callbacks.change_cb.unwrap()(&mut ctx, &mut txn, relation.as_ptr(), &mut change);
+
+            callbacks.commit_cb.unwrap()(&mut ctx, &mut txn, 0);
+            callbacks.shutdown_cb.unwrap()(&mut ctx);
+            assert!(ctx.output_plugin_private.is_null());
+        }
+
+        WRITTEN.with(|written| {
+            assert_eq!(
+                *written.borrow(),
+                vec![
+                    "INSERT dogs: name=brandy age=null",
+                    "DELETE dogs:",
+                    "COMMIT 42"
+                ]
+            )
+        });
+    }
+
+    #[pg_test(error = "option \"bark\" = Some(\"loudly\") is unknown")]
+    fn test_output_plugin_unknown_option() {
+        unsafe {
+            let mut callbacks = pg_sys::OutputPluginCallbacks::default();
+            output_plugin_callbacks::<ChangeLines>(&mut callbacks);
+
+            let mut ctx = decoding_context(&[("bark", "loudly")]);
+            let mut options = pg_sys::OutputPluginOptions::default();
+            callbacks.startup_cb.unwrap()(&mut ctx, &mut options, false);
+        }
+    }
+}
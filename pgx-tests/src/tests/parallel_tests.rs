@@ -0,0 +1,62 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+/// Sends the worker's number and its share of the sum of the job's numbers
+#[pg_guard]
+pub extern "C" fn parallel_tests_sum_main(
+    seg: *mut pg_sys::dsm_segment,
+    toc: *mut pg_sys::shm_toc,
+) {
+    let mut worker = unsafe { ParallelWorker::new(seg, toc) };
+    let numbers: Vec<i64> = worker.job().unwrap();
+    let sum = numbers
+        .iter()
+        .skip(worker.number())
+        .step_by(worker.nworkers())
+        .sum::<i64>();
+    worker.send(&(worker.number(), sum)).unwrap();
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_parallel_sum() {
+        let numbers = (1..=100).collect::<Vec<i64>>();
+        let mut pcxt = ParallelContext::new("pgx_tests", "parallel_tests_sum_main", 2, &numbers);
+        let launched = pcxt.launch();
+
+        let results = pcxt.collect::<(usize, i64)>().unwrap();
+        assert_eq!(results.len(), launched);
+        assert!(results
+            .iter()
+            .enumerate()
+            .all(|(i, (number, _))| i == *number));
+
+        let mut sum = results.iter().map(|(_, sum)| sum).sum::<i64>();
+        for number in launched..2 {
+            sum += numbers.iter().skip(number).step_by(2).sum::<i64>();
+        }
+        assert_eq!(sum, 5050);
+    }
+
+    #[pg_test]
+    fn test_parallel_no_workers() {
+        let mut pcxt = ParallelContext::new("pgx_tests", "parallel_tests_sum_main", 0, &());
+        assert_eq!(pcxt.launch(), 0);
+        assert_eq!(pcxt.collect::<(usize, i64)>().unwrap(), vec![]);
+    }
+}
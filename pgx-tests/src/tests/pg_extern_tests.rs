@@ -14,6 +14,11 @@ fn returns_tuple_with_attributes() -> (name!(arg, String), name!(arg2, String))
     ("hi".to_string(), "bye".to_string())
 }
 
+#[pg_extern(immutable)]
+fn returns_optional_tuple(value: Option<i32>) -> Option<(name!(value, i32), name!(doubled, i64))> {
+    value.map(|value| (value, value as i64 * 2))
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
 mod tests {
@@ -34,6 +39,77 @@ mod tests {
         assert!(result)
     }
 
+    #[pg_extern(stable, strict, parallel_safe)]
+    fn is_stable_strict_parallel_safe(value: i32) -> i32 {
+        value
+    }
+
+    #[pg_test]
+    fn test_stable_strict_parallel_safe() {
+        let result = Spi::get_one::<bool>(
+            "SELECT provolatile = 's' AND proisstrict AND proparallel = 's' FROM pg_proc WHERE proname = 'is_stable_strict_parallel_safe'",
+        )
+        .expect("failed to get SPI result");
+        assert!(result)
+    }
+
+    #[pg_extern(volatile, parallel_restricted)]
+    fn is_volatile_parallel_restricted() {}
+
+    #[pg_test]
+    fn test_volatile_parallel_restricted() {
+        let result = Spi::get_one::<bool>(
+            "SELECT provolatile = 'v' AND NOT proisstrict AND proparallel = 'r' FROM pg_proc WHERE proname = 'is_volatile_parallel_restricted'",
+        )
+        .expect("failed to get SPI result");
+        assert!(result)
+    }
+
+    #[pg_extern(immutable, leakproof, cost = 5)]
+    fn is_leakproof_costly(value: i32) -> bool {
+        value > 0
+    }
+
+    #[pg_test]
+    fn test_leakproof_cost() {
+        let result = Spi::get_one::<bool>(
+            "SELECT proleakproof AND procost = 5 FROM pg_proc WHERE proname = 'is_leakproof_costly'",
+        )
+        .expect("failed to get SPI result");
+        assert!(result)
+    }
+
+    #[pg_extern(
+        security_definer,
+        set = "search_path = pg_catalog, pg_temp",
+        set = "work_mem = '8MB'"
+    )]
+    fn is_security_definer() -> String {
+        Spi::get_one::<String>("SELECT current_setting('work_mem')").expect("no work_mem")
+    }
+
+    #[pg_test]
+    fn test_security_definer_set() {
+        let result = Spi::get_one::<bool>(
+            "SELECT prosecdef FROM pg_proc WHERE proname = 'is_security_definer'",
+        )
+        .expect("failed to get SPI result");
+        assert!(result);
+
+        let result = Spi::get_one::<Vec<String>>(
+            "SELECT proconfig FROM pg_proc WHERE proname = 'is_security_definer'",
+        )
+        .expect("failed to get SPI result");
+        assert_eq!(
+            result,
+            vec!["search_path=pg_catalog, pg_temp", "work_mem=8MB"]
+        );
+
+        let result = Spi::get_one::<String>("SELECT tests.is_security_definer()")
+            .expect("failed to get SPI result");
+        assert_eq!(result, "8MB");
+    }
+
     // Ensures `@MODULE_PATHNAME@` and `@FUNCTION_NAME@` are handled.
     #[pg_extern(sql = r#"
         CREATE FUNCTION tests."overridden_sql_with_fn_name"() RETURNS void
@@ -51,4 +127,27 @@ mod tests {
             .expect("failed to get SPI result");
         assert!(result)
     }
+
+    #[pg_test]
+    fn test_returns_tuple_out_arguments() {
+        let result = Spi::get_one::<String>(
+            "SELECT array_to_string(proargmodes, ',') FROM pg_proc WHERE proname = 'returns_tuple_with_attributes'",
+        )
+        .expect("failed to get SPI result");
+        assert_eq!(result, "o,o");
+
+        let result = Spi::get_one::<String>("SELECT (returns_tuple_with_attributes()).arg2")
+            .expect("failed to get SPI result");
+        assert_eq!(result, "bye");
+    }
+
+    #[pg_test]
+    fn test_returns_optional_tuple() {
+        let result = Spi::get_two::<i32, i64>("SELECT * FROM returns_optional_tuple(21)");
+        assert_eq!(result, (Some(21), Some(42)));
+
+        let result = Spi::get_one::<bool>("SELECT returns_optional_tuple(NULL) IS NULL")
+            .expect("failed to get SPI result");
+        assert!(result);
+    }
 }
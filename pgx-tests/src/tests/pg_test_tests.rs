@@ -0,0 +1,176 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    fn create_dogs() {
+        Spi::run("CREATE TABLE dogs (name text)");
+        Spi::run("INSERT INTO dogs VALUES ('brandy')");
+    }
+
+    fn check_dogs() {
+        error!(
+            "{} dogs were left",
+            Spi::get_one::<i64>("SELECT count(*) FROM dogs").unwrap()
+        );
+    }
+
+    #[pg_test(setup = "create_dogs")]
+    fn test_setup() {
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM dogs"), Some(1));
+    }
+
+    #[pg_test(
+        setup = "create_dogs",
+        teardown = "check_dogs",
+        error = "2 dogs were left"
+    )]
+    fn test_teardown() {
+        Spi::run("INSERT INTO dogs VALUES ('nami')");
+    }
+
+    #[pg_test]
+    fn test_own_schema() {
+        Spi::run("CREATE TABLE dogs (name text)");
+        assert_eq!(
+            Spi::get_one::<&str>(
+                "SELECT relnamespace::regnamespace::text FROM pg_class WHERE oid = 'dogs'::regclass"
+            ),
+            Some("pgx_test_test_own_schema")
+        );
+    }
+
+    #[pg_test(sqlstate = "22012")]
+    fn test_sqlstate() {
+        Spi::run("SELECT 1 / 0");
+    }
+
+    #[pg_test(sqlstate = ERRCODE_UNDEFINED_TABLE, error_contains = "no_such_table")]
+    fn test_sqlstate_code() {
+        Spi::run("SELECT * FROM no_such_table");
+    }
+
+    #[pg_test(detail_contains = "no dogs", hint_contains = "adopt")]
+    fn test_detail_and_hint() {
+        Spi::run(
+            "DO $$ BEGIN \
+                RAISE EXCEPTION 'lonely' USING DETAIL = 'There are no dogs.', HINT = 'Adopt one, or adopt two.'; \
+             END $$",
+        );
+    }
+
+    #[pg_test]
+    fn test_assert_pg_error() {
+        assert_eq!(PgSqlErrorCode::ERRCODE_UNDEFINED_TABLE.sqlstate(), "42P01");
+
+        let error = assert_pg_error!(PgSqlErrorCode::ERRCODE_DIVISION_BY_ZERO, || {
+            Spi::get_one::<i32>("SELECT 1 / 0")
+        });
+        assert_eq!(error.message, "division by zero");
+        assert_pg_error!("42P01", || Spi::run("SELECT * FROM no_such_table"));
+
+        // the ERRORs were rolled back, so the transaction goes on
+        assert_eq!(Spi::get_one::<i32>("SELECT 1"), Some(1));
+    }
+
+    #[pg_test(error = "expected an ERROR with SQLSTATE 22012, but got: Some(1)")]
+    fn test_assert_pg_error_without_error() {
+        assert_pg_error!("22012", || Spi::get_one::<i32>("SELECT 1"));
+    }
+
+    #[pg_test(guc = "work_mem=64MB", guc = "pgx_tests.mode = strict")]
+    fn test_guc() {
+        assert_eq!(Spi::get_one::<&str>("SHOW work_mem"), Some("64MB"));
+        assert_eq!(
+            Spi::get_one::<&str>("SELECT current_setting('pgx_tests.mode')"),
+            Some("strict")
+        );
+    }
+
+    #[pg_test(role = "pgx_test_user")]
+    fn test_role() {
+        assert_eq!(
+            Spi::get_one::<&str>("SELECT current_user::text"),
+            Some("pgx_test_user")
+        );
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT rolsuper FROM pg_roles WHERE rolname = current_user"),
+            Some(false)
+        );
+
+        // it can still create tables in its own schema
+        Spi::run("CREATE TABLE dogs (name text)");
+    }
+
+    #[pg_test(role = "pgx_test_user", sqlstate = ERRCODE_INSUFFICIENT_PRIVILEGE)]
+    fn test_role_permission_denied() {
+        Spi::run("CREATE ROLE pgx_test_admin SUPERUSER");
+    }
+
+    #[pg_test(min_pg = 12, skip_pg = 13)]
+    fn test_pg_versions() {
+        let version = pg_sys::get_pg_major_version_num();
+        assert!(version >= 12 && version != 13);
+    }
+
+    #[pg_test]
+    fn test_run_bench() {
+        let config = BenchConfig {
+            warm_up: std::time::Duration::from_millis(10),
+            measurement: std::time::Duration::from_millis(50),
+            samples: 10,
+        };
+        let result = run_bench("bench_sum", config, |b| b.iter(|| (1..100i64).sum::<i64>()));
+        assert_eq!(result.name, "bench_sum");
+        assert_eq!(result.samples, 10);
+        assert!(result.iterations >= 10);
+        assert!(result.min_ns <= result.median_ns && result.median_ns <= result.max_ns);
+        assert!(result.min_ns <= result.mean_ns && result.mean_ns <= result.max_ns);
+    }
+
+    #[pg_test(error = "bench_nothing didn't time anything with `Bencher::iter()`")]
+    fn test_run_bench_without_iter() {
+        run_bench("bench_nothing", BenchConfig::default(), |_| ());
+    }
+
+    #[pg_bench(samples = 20, warm_up_ms = 200, measurement_ms = 1000)]
+    fn bench_spi_get_one(b: &mut Bencher) {
+        b.iter(|| Spi::get_one::<i32>("SELECT 1"));
+    }
+
+    // text can't hold a NUL character
+    #[pg_proptest(cases = 32)]
+    fn prop_text_roundtrip(#[strategy("[^\\x00]*")] value: String) {
+        let returned = Spi::get_one_with_args::<String>(
+            "SELECT $1",
+            vec![(PgBuiltInOids::TEXTOID.oid(), value.clone().into_datum())],
+        );
+        assert_eq!(returned, Some(value));
+    }
+
+    #[pg_proptest(error = "prop_shrinks failed: too big\nminimal failing input: (100,)")]
+    fn prop_shrinks(value: i32) {
+        if value >= 100 {
+            panic!("too big");
+        }
+    }
+
+    #[pg_proptest(
+        error = "prop_shrinks_error failed: division by zero (SQLSTATE 22012)\nminimal failing input: (0,)"
+    )]
+    fn prop_shrinks_error(#[strategy(-5..5i32)] value: i32) {
+        Spi::get_one::<i32>(&format!("SELECT 1 / {}", value));
+    }
+}
@@ -100,4 +100,56 @@ mod tests {
     fn test_pg_try_unwrap_or_rethrow_with_error_in_rethrow() {
         pg_try(|| panic!("rethrow a panic")).unwrap_or_rethrow(|| panic!("panic in rethrow"));
     }
+
+    #[pg_test]
+    fn test_catch_error_no_error() {
+        assert_eq!(catch_error(|| 42), Ok(42));
+    }
+
+    #[pg_test]
+    fn test_catch_error() {
+        Spi::run("CREATE TABLE dogs (name text)");
+        let error = catch_error(|| {
+            Spi::run("INSERT INTO dogs VALUES ('brandy')");
+            Spi::get_one::<i32>("SELECT 1 / 0")
+        })
+        .unwrap_err();
+        assert!(error.is(PgSqlErrorCode::ERRCODE_DIVISION_BY_ZERO));
+        assert_eq!(error.sqlstate, "22012");
+        assert_eq!(error.message, "division by zero");
+        assert!(!error.must_rethrow());
+
+        // the subtransaction was rolled back, and the transaction goes on
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM dogs"), Some(0));
+    }
+
+    #[pg_test]
+    fn test_catch_error_must_rethrow() {
+        let error = catch_error(|| {
+            Spi::run("DO $$ BEGIN RAISE EXCEPTION 'stop' USING ERRCODE = 'query_canceled'; END $$")
+        })
+        .unwrap_err();
+        assert!(error.must_rethrow());
+    }
+
+    #[pg_test(sqlstate = "22012", error = "division by zero")]
+    fn test_catch_error_rethrow() {
+        match catch_error(|| Spi::get_one::<i32>("SELECT 1 / 0")) {
+            Ok(_) => (),
+            Err(error) => error.rethrow(),
+        }
+    }
+
+    #[pg_test(detail_contains = "no dogs", hint_contains = "adopt")]
+    fn test_catch_error_rethrow_detail_and_hint() {
+        let error = catch_error(|| {
+            Spi::run(
+                "DO $$ BEGIN \
+                    RAISE EXCEPTION 'lonely' USING DETAIL = 'There are no dogs.', HINT = 'Go adopt one.'; \
+                 END $$",
+            )
+        })
+        .unwrap_err();
+        error.rethrow()
+    }
 }
@@ -0,0 +1,67 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::fmt::Write;
+
+    #[pg_test]
+    fn test_pgvec() {
+        let mut names = unsafe { PgVec::new() };
+        names.extend((0..100).map(|i| format!("dog {}", i)));
+        assert_eq!(names.len(), 100);
+        assert_eq!(names.pop().as_deref(), Some("dog 99"));
+        names.truncate(10);
+        assert_eq!(names.last().map(String::as_str), Some("dog 9"));
+        assert_eq!(
+            PgMemoryContexts::Of(names.as_ptr() as void_ptr).value(),
+            PgMemoryContexts::CurrentMemoryContext.value()
+        );
+    }
+
+    #[pg_test]
+    fn test_pgvec_in_context() {
+        let mut batch = PgMemoryContexts::new("batch");
+        let before = batch.stats(false).used_bytes();
+
+        let mut ids = unsafe { PgVec::with_capacity_in(1024 * 1024, &batch) };
+        ids.extend(0..1024 * 1024i64);
+        assert_eq!(
+            PgMemoryContexts::Of(ids.as_ptr() as void_ptr).value(),
+            batch.value()
+        );
+        assert!(batch.stats(false).used_bytes() >= before + 8 * 1024 * 1024);
+
+        // the elements are freed with the context
+        ids.into_pg();
+        batch.reset();
+        assert!(batch.stats(false).used_bytes() < 8 * 1024 * 1024);
+    }
+
+    #[pg_test]
+    fn test_pgstring() {
+        let mut sql =
+            unsafe { PgString::from_str_in("SELECT", &PgMemoryContexts::CurrentMemoryContext) };
+        write!(sql, " {} FROM dogs", "name").unwrap();
+        sql.push(';');
+        assert_eq!(sql.as_str(), "SELECT name FROM dogs;");
+        assert_eq!(sql.to_string(), "SELECT name FROM dogs;");
+
+        let cstr = sql.into_pg_cstr();
+        assert_eq!(
+            unsafe { std::ffi::CStr::from_ptr(cstr) }.to_str(),
+            Ok("SELECT name FROM dogs;")
+        );
+    }
+}
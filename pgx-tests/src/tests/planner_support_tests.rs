@@ -0,0 +1,103 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(all(
+    any(test, feature = "pg_test"),
+    any(feature = "pg12", feature = "pg13", feature = "pg14")
+))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    static mut PLUS_CALLS: u64 = 0;
+
+    #[pg_extern(support = plus_support)]
+    fn plus(a: i32, b: i32) -> i32 {
+        unsafe { PLUS_CALLS += 1 };
+        a + b
+    }
+
+    /// Folds `plus()` of two constants, and `plus(x, 0)` into `x`
+    #[pg_extern]
+    fn plus_support(request: SupportRequest) -> SupportResponse {
+        match request {
+            SupportRequest::Simplify(simplify) => {
+                match (simplify.const_arg::<i32>(0), simplify.const_arg::<i32>(1)) {
+                    (_, Some(Some(0))) => simplify.replace_with(simplify.args()[0]),
+                    (Some(Some(a)), Some(Some(b))) => simplify.replace_with_const(Some(a + b)),
+                    _ => SupportResponse::none(),
+                }
+            }
+            _ => SupportResponse::none(),
+        }
+    }
+
+    #[pg_extern(support = is_even_support)]
+    fn is_even(n: i32) -> bool {
+        n % 2 == 0
+    }
+
+    #[pg_extern]
+    fn is_even_support(request: SupportRequest) -> SupportResponse {
+        match request {
+            SupportRequest::Selectivity(selectivity) => selectivity.set_selectivity(0.5),
+            _ => SupportResponse::none(),
+        }
+    }
+
+    #[pg_extern(support = countdown_support)]
+    fn countdown(from: i32) -> impl Iterator<Item = i32> {
+        (1..=from).rev()
+    }
+
+    /// Estimates `countdown(n)` returns `n` rows, when `n` is a constant
+    #[pg_extern]
+    fn countdown_support(request: SupportRequest) -> SupportResponse {
+        match request {
+            SupportRequest::Rows(rows) => match rows.const_arg::<i32>(0) {
+                Some(Some(from)) => rows.set_rows(from as f64),
+                _ => SupportResponse::none(),
+            },
+            _ => SupportResponse::none(),
+        }
+    }
+
+    #[pg_test]
+    fn test_support_simplify() {
+        let calls = unsafe { PLUS_CALLS };
+        let sum = Spi::get_one::<i64>("SELECT sum(tests.plus(n, 0)) FROM generate_series(1, 3) n");
+        assert_eq!(sum, Some(6));
+        let five = Spi::get_one::<i32>("SELECT tests.plus(2, 3)");
+        assert_eq!(five, Some(5));
+        assert_eq!(unsafe { PLUS_CALLS }, calls);
+
+        let sum = Spi::get_one::<i64>("SELECT sum(tests.plus(n, 1)) FROM generate_series(1, 3) n");
+        assert_eq!(sum, Some(9));
+        assert_eq!(unsafe { PLUS_CALLS }, calls + 3);
+    }
+
+    #[pg_test]
+    fn test_support_selectivity() {
+        let plan = Spi::get_one::<String>(
+            "EXPLAIN SELECT * FROM generate_series(1, 1000) n WHERE tests.is_even(n)",
+        )
+        .expect("EXPLAIN returned nothing");
+        assert!(plan.contains("rows=500 "), "{}", plan);
+    }
+
+    #[pg_test]
+    fn test_support_rows() {
+        let plan = Spi::get_one::<String>("EXPLAIN SELECT * FROM tests.countdown(42)")
+            .expect("EXPLAIN returned nothing");
+        assert!(plan.contains("rows=42 "), "{}", plan);
+    }
+}
@@ -0,0 +1,65 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(all(
+    any(test, feature = "pg_test"),
+    any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14")
+))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_procedure]
+    fn insert_numbers(table: String, count: i32) {
+        for n in 1..=count {
+            Spi::run(&format!("INSERT INTO {} VALUES ({})", table, n));
+        }
+    }
+
+    #[pg_procedure]
+    fn check_atomic(procedure: &PgProcedure) {
+        assert!(procedure.is_atomic());
+    }
+
+    #[pg_procedure]
+    fn commit_numbers(procedure: &PgProcedure) {
+        Spi::run("INSERT INTO numbers VALUES (1)");
+        procedure.commit();
+    }
+
+    #[pg_test]
+    fn test_call_procedure() {
+        Spi::run("CREATE TABLE numbers (n int)");
+        Spi::run("CALL tests.insert_numbers('numbers', 3)");
+        let sum = Spi::get_one::<i64>("SELECT sum(n) FROM numbers").expect("SQL select failed");
+        assert_eq!(sum, 6);
+    }
+
+    #[pg_test]
+    fn test_procedure_prokind() {
+        let prokind =
+            Spi::get_one::<i8>("SELECT prokind FROM pg_proc WHERE proname = 'insert_numbers'")
+                .expect("SQL select failed");
+        assert_eq!(prokind as u8, b'p');
+    }
+
+    #[pg_test]
+    fn test_procedure_in_transaction_is_atomic() {
+        Spi::run("CALL tests.check_atomic()");
+    }
+
+    #[pg_test(error = "invalid transaction termination")]
+    fn test_commit_in_transaction() {
+        Spi::run("CREATE TABLE numbers (n int)");
+        Spi::run("CALL tests.commit_numbers()");
+    }
+}
@@ -0,0 +1,88 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[pg_extern]
+fn refcursor_numbers(n: i32) -> RefCursor {
+    Spi::connect(|client| {
+        let cursor = client.open_cursor(
+            "SELECT * FROM generate_series(1, $1)",
+            Some(vec![(PgBuiltInOids::INT4OID.oid(), n.into_datum())]),
+        );
+        Ok(Some(cursor.into_refcursor()))
+    })
+    .expect("refcursor_numbers returned NULL")
+}
+
+#[pg_extern]
+fn refcursor_named_numbers(name: &str, n: i32) -> RefCursor {
+    Spi::connect(|client| {
+        let cursor = client.open_named_cursor(
+            name,
+            "SELECT * FROM generate_series(1, $1)",
+            Some(vec![(PgBuiltInOids::INT4OID.oid(), n.into_datum())]),
+        );
+        Ok(Some(cursor.into_refcursor()))
+    })
+    .expect("refcursor_named_numbers returned NULL")
+}
+
+#[pg_extern]
+fn refcursor_sum(cursor: RefCursor) -> i64 {
+    Spi::connect(|client| {
+        let sum = client
+            .find_cursor(cursor.name())
+            .expect("no such cursor")
+            .map(|row| row.by_ordinal(1).unwrap().value::<i32>().unwrap() as i64)
+            .sum::<i64>();
+        Ok(Some(sum))
+    })
+    .expect("refcursor_sum returned NULL")
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_refcursor_survives_function_exit() {
+        let cursor = Spi::get_one::<RefCursor>("SELECT refcursor_numbers(5)")
+            .expect("refcursor_numbers returned NULL");
+        let count = Spi::connect(|client| {
+            let rows = client.select(
+                &format!("FETCH ALL FROM {}", sql::Ident::new(cursor.name())),
+                None,
+                None,
+            );
+            Ok(Some(rows.len() as i64))
+        });
+        assert_eq!(count, Some(5));
+    }
+
+    #[pg_test]
+    fn test_named_refcursor() {
+        let cursor = Spi::get_one::<RefCursor>("SELECT refcursor_named_numbers('numbers', 3)")
+            .expect("refcursor_named_numbers returned NULL");
+        assert_eq!(cursor.name(), "numbers");
+        let sum = Spi::get_one::<i64>("SELECT refcursor_sum('numbers')");
+        assert_eq!(sum, Some(6));
+    }
+
+    #[pg_test]
+    fn test_find_missing_cursor() {
+        Spi::execute(|client| {
+            assert!(client.find_cursor("no_such_cursor").is_none());
+        });
+    }
+}
@@ -0,0 +1,39 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_replication_slot_physical() {
+        let slot = ReplicationSlot::create_physical("pgx_tests_standby", true, true);
+        let status = slot.status().expect("slot wasn't created");
+        assert_eq!(status.name, "pgx_tests_standby");
+        assert_eq!(status.kind, ReplicationSlotKind::Physical);
+        assert_eq!(status.plugin, None);
+        assert_eq!(status.database, None);
+        assert!(status.temporary);
+        assert!(status.restart_lsn.unwrap() > 0);
+        assert_eq!(status.confirmed_flush_lsn, None);
+        assert!(ReplicationSlot::all().contains(&status));
+
+        slot.clone().drop_slot();
+        assert_eq!(slot.status(), None);
+    }
+
+    #[pg_test(sqlstate = ERRCODE_UNDEFINED_OBJECT, error = "replication slot \"pgx_tests_missing\" does not exist")]
+    fn test_replication_slot_drop_missing() {
+        ReplicationSlot::named("pgx_tests_missing").drop_slot();
+    }
+}
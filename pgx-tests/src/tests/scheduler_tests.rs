@@ -0,0 +1,79 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::scheduler::{Schedule, ScheduleError};
+    use pgx::*;
+    use time::macros::datetime;
+
+    #[pg_test]
+    fn test_schedule_matches() {
+        let every_five = Schedule::parse("*/5 * * * *").unwrap();
+        assert!(every_five.matches(datetime!(2022-03-01 12:35 UTC)));
+        assert!(!every_five.matches(datetime!(2022-03-01 12:36 UTC)));
+
+        let weekday_mornings = Schedule::parse("30 9 * * 1-5").unwrap();
+        // a Tuesday
+        assert!(weekday_mornings.matches(datetime!(2022-03-01 9:30 UTC)));
+        // a Sunday
+        assert!(!weekday_mornings.matches(datetime!(2022-03-06 9:30 UTC)));
+
+        // with both days restricted, either matches, and 7 is Sunday
+        let firsts_and_sundays = Schedule::parse("0 0 1 * 7").unwrap();
+        assert!(firsts_and_sundays.matches(datetime!(2022-03-01 0:00 UTC)));
+        assert!(firsts_and_sundays.matches(datetime!(2022-03-06 0:00 UTC)));
+        assert!(!firsts_and_sundays.matches(datetime!(2022-03-07 0:00 UTC)));
+    }
+
+    #[pg_test]
+    fn test_schedule_next_after() {
+        let hourly = Schedule::parse("@hourly").unwrap();
+        assert_eq!(
+            hourly.next_after(datetime!(2022-03-01 12:00:30 UTC)),
+            Some(datetime!(2022-03-01 13:00 UTC))
+        );
+
+        let list = Schedule::parse("15,45 2-3 * * *").unwrap();
+        assert_eq!(
+            list.next_after(datetime!(2022-03-01 3:45 UTC)),
+            Some(datetime!(2022-03-02 2:15 UTC))
+        );
+
+        let leap_day = Schedule::parse("0 0 29 2 *").unwrap();
+        assert_eq!(
+            leap_day.next_after(datetime!(2022-03-01 0:00 UTC)),
+            Some(datetime!(2024-02-29 0:00 UTC))
+        );
+
+        let never = Schedule::parse("0 0 31 2 *").unwrap();
+        assert_eq!(never.next_after(datetime!(2022-03-01 0:00 UTC)), None);
+    }
+
+    #[pg_test]
+    fn test_schedule_errors() {
+        assert_eq!(
+            Schedule::parse("* * * *"),
+            Err(ScheduleError::FieldCount(4))
+        );
+        assert_eq!(
+            Schedule::parse("60 * * * *"),
+            Err(ScheduleError::InvalidField {
+                field: "minute",
+                value: "60".to_string()
+            })
+        );
+        assert!(Schedule::parse("*/0 * * * *").is_err());
+        assert!(Schedule::parse("5-1 * * * *").is_err());
+    }
+}
@@ -0,0 +1,96 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+/// Sends the numbers from 1 through 100 over the queue at the start of the segment
+#[pg_guard]
+pub extern "C" fn shm_mq_tests_worker_main(arg: pg_sys::Datum) {
+    unsafe {
+        pg_sys::BackgroundWorkerUnblockSignals();
+        let segment = pg_sys::dsm_attach(arg as pg_sys::dsm_handle);
+        let mq = pg_sys::dsm_segment_address(segment) as *mut pg_sys::shm_mq;
+
+        let mut sender = ShmMqSender::attach(mq, segment);
+        for i in 1..=100_i64 {
+            sender.send(&i).expect("failed to send");
+        }
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::bgworkers::BackgroundWorkerBuilder;
+    use pgx::*;
+
+    const QUEUE_SIZE: usize = 16384;
+
+    #[pg_test]
+    fn test_shm_mq_round_trip() {
+        unsafe {
+            let segment = pg_sys::dsm_create(QUEUE_SIZE, 0);
+            let mut receiver =
+                ShmMqReceiver::create(pg_sys::dsm_segment_address(segment), QUEUE_SIZE, segment);
+            let mut sender =
+                ShmMqSender::attach(pg_sys::shm_mq_get_queue(receiver.as_ptr()), segment);
+
+            assert_eq!(receiver.try_recv_bytes(), Ok(None));
+
+            assert_eq!(sender.try_send_bytes(b"raw"), Ok(true));
+            sender
+                .send(&("typed".to_string(), vec![1, 2, 3]))
+                .expect("failed to send");
+            drop(sender);
+
+            assert_eq!(receiver.recv_bytes(), Ok(b"raw".to_vec()));
+            assert_eq!(
+                receiver.try_recv::<(String, Vec<i32>)>(),
+                Ok(Some(("typed".to_string(), vec![1, 2, 3])))
+            );
+            assert_eq!(receiver.recv_bytes(), Err(ShmMqError::Detached));
+
+            drop(receiver);
+            pg_sys::dsm_detach(segment);
+        }
+    }
+
+    #[pg_test]
+    fn test_shm_mq_from_worker() {
+        unsafe {
+            let segment = pg_sys::dsm_create(QUEUE_SIZE, 0);
+            let mut receiver =
+                ShmMqReceiver::create(pg_sys::dsm_segment_address(segment), QUEUE_SIZE, segment);
+
+            let worker = BackgroundWorkerBuilder::new("pgx_tests shm_mq worker")
+                .set_library("pgx_tests")
+                .set_function("shm_mq_tests_worker_main")
+                .set_argument(Some(pg_sys::dsm_segment_handle(segment) as pg_sys::Datum))
+                .load_dynamic()
+                .expect("failed to register worker");
+            receiver.set_worker(&worker);
+
+            let mut sum = 0;
+            loop {
+                match receiver.recv::<i64>() {
+                    Ok(i) => sum += i,
+                    Err(ShmMqError::Detached) => break,
+                    Err(e) => panic!("{}", e),
+                }
+            }
+            assert_eq!(sum, 5050);
+
+            drop(receiver);
+            pg_sys::dsm_detach(segment);
+        }
+    }
+}
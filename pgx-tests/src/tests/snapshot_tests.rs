@@ -0,0 +1,66 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_test]
+    fn test_snapshot_xmin_xmax() {
+        let snapshot = PgSnapshot::transaction();
+        assert!(pg_sys::TransactionIdIsNormal(snapshot.xmin()));
+        assert!(snapshot.xmin() <= snapshot.xmax());
+        assert!(snapshot
+            .in_progress()
+            .iter()
+            .all(|&xid| snapshot.xmin() <= xid && xid < snapshot.xmax()));
+
+        let latest = PgSnapshot::latest();
+        assert!(latest.xmax() >= snapshot.xmax());
+    }
+
+    #[pg_test]
+    fn test_snapshot_export() {
+        let id = PgSnapshot::transaction().export();
+        assert_eq!(id.split('-').count(), 3);
+        // a second export is another snapshot
+        assert_ne!(PgSnapshot::transaction().export(), id);
+    }
+
+    #[pg_test]
+    fn test_snapshot_push_active() {
+        let snapshot = PgSnapshot::latest();
+        {
+            let _active = snapshot.push_active();
+            assert_eq!(unsafe { pg_sys::GetActiveSnapshot() }, snapshot.as_ptr());
+            assert_eq!(
+                PgSnapshot::active().map(|active| active.as_ptr()),
+                Some(snapshot.as_ptr())
+            );
+        }
+        assert_ne!(unsafe { pg_sys::GetActiveSnapshot() }, snapshot.as_ptr());
+    }
+
+    #[pg_test]
+    fn test_snapshot_clone() {
+        let snapshot = PgSnapshot::latest();
+        let clone = snapshot.clone();
+        drop(snapshot);
+        assert!(clone.xmin() <= clone.xmax());
+    }
+
+    #[pg_test(sqlstate = ERRCODE_ACTIVE_SQL_TRANSACTION, error = "SET TRANSACTION SNAPSHOT must be called before any query")]
+    fn test_snapshot_import_after_query() {
+        PgSnapshot::import("00000003-00000001-1");
+    }
+}
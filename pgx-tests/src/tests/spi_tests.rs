@@ -168,4 +168,593 @@ mod tests {
         );
         assert_eq!(result, Some(1));
     }
+
+    #[pg_test]
+    fn test_prepared_statement() {
+        Spi::execute(|client| {
+            let stmt = client.prepare(
+                "SELECT $1 + $2",
+                &[PgBuiltInOids::INT4OID.oid(), PgBuiltInOids::INT4OID.oid()],
+            );
+            assert_eq!(stmt.nargs(), 2);
+
+            for i in 0..3_i32 {
+                let sum = stmt
+                    .execute(None, vec![i.into_datum(), 10_i32.into_datum()])
+                    .first()
+                    .get_one::<i32>();
+                assert_eq!(sum, Some(i + 10));
+            }
+        });
+    }
+
+    #[pg_test]
+    fn test_prepared_statement_null_arg() {
+        Spi::execute(|client| {
+            let stmt = client.prepare("SELECT $1 IS NULL", &[PgBuiltInOids::TEXTOID.oid()]);
+            let is_null = stmt.execute(None, vec![None]).first().get_one::<bool>();
+            assert_eq!(is_null, Some(true));
+        });
+    }
+
+    #[pg_test]
+    fn test_kept_prepared_statement() {
+        let mut kept = None;
+        Spi::connect(|client| {
+            kept = Some(
+                client
+                    .prepare("SELECT $1 * 2", &[PgBuiltInOids::INT8OID.oid()])
+                    .keep(),
+            );
+            Ok(Some(()))
+        });
+
+        let stmt = kept.expect("statement was not kept");
+        let doubled = Spi::connect(|client| {
+            Ok(stmt
+                .with_client(&client)
+                .execute(Some(1), vec![21_i64.into_datum()])
+                .first()
+                .get_one::<i64>())
+        });
+        assert_eq!(doubled, Some(42));
+    }
+
+    #[pg_test(error = "prepared statement expects 1 arguments, but 0 were provided")]
+    fn test_prepared_statement_wrong_nargs() {
+        Spi::execute(|client| {
+            client
+                .prepare("SELECT $1", &[PgBuiltInOids::INT4OID.oid()])
+                .execute(None, vec![]);
+        });
+    }
+
+    #[pg_test]
+    fn test_cursor_iterates_in_batches() {
+        Spi::execute(|client| {
+            let cursor = client
+                .open_cursor("SELECT * FROM generate_series(1, 2500)", None)
+                .batch_size(100);
+            let sum = cursor
+                .map(|row| row.by_ordinal(1).unwrap().value::<i32>().unwrap() as i64)
+                .sum::<i64>();
+            assert_eq!(sum, 2500 * 2501 / 2);
+        });
+    }
+
+    #[pg_test]
+    fn test_cursor_fetch() {
+        Spi::execute(|client| {
+            let mut cursor = client.open_cursor(
+                "SELECT * FROM generate_series(1, $1)",
+                Some(vec![(PgBuiltInOids::INT4OID.oid(), 5_i32.into_datum())]),
+            );
+            assert_eq!(cursor.fetch(3).len(), 3);
+            assert_eq!(cursor.fetch(3).len(), 2);
+            assert!(cursor.fetch(3).is_empty());
+        });
+    }
+
+    #[pg_test]
+    fn test_prepared_statement_cursor() {
+        Spi::execute(|client| {
+            let stmt = client.prepare(
+                "SELECT x::text FROM generate_series(1, $1) x",
+                &[PgBuiltInOids::INT4OID.oid()],
+            );
+            let values = stmt
+                .open_cursor(vec![3_i32.into_datum()])
+                .batch_size(2)
+                .map(|row| row.by_ordinal(1).unwrap().value::<String>().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(values, vec!["1", "2", "3"]);
+        });
+    }
+
+    #[derive(Debug, PartialEq, FromSpiRow)]
+    struct Dog {
+        name: String,
+        #[spi(rename = "scritches")]
+        scritch_count: Option<i32>,
+    }
+
+    #[pg_test]
+    fn test_from_spi_row() {
+        Spi::execute(|client| {
+            let dogs = client
+                .select(
+                    "SELECT * FROM (VALUES ('Nami', 10), ('Brandy', NULL)) AS dogs(name, scritches)",
+                    None,
+                    None,
+                )
+                .map(|row| Dog::from_spi_row(&row).unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                dogs,
+                vec![
+                    Dog {
+                        name: "Nami".to_string(),
+                        scritch_count: Some(10),
+                    },
+                    Dog {
+                        name: "Brandy".to_string(),
+                        scritch_count: None,
+                    },
+                ]
+            );
+        });
+    }
+
+    #[pg_test]
+    fn test_from_spi_row_errors() {
+        Spi::execute(|client| {
+            let row = client
+                .select("SELECT 'Nami' AS name", None, None)
+                .first()
+                .get_heap_tuple()
+                .unwrap();
+            assert_eq!(
+                Dog::from_spi_row(&row).unwrap_err(),
+                FromSpiRowError::NoSuchColumn("scritches".to_string())
+            );
+
+            let row = client
+                .select("SELECT NULL::text AS name, 1 AS scritches", None, None)
+                .first()
+                .get_heap_tuple()
+                .unwrap();
+            assert_eq!(
+                Dog::from_spi_row(&row).unwrap_err(),
+                FromSpiRowError::UnexpectedNull("name".to_string())
+            );
+        });
+    }
+
+    #[pg_test]
+    fn test_subtransaction_commits_on_ok() {
+        Spi::run("CREATE TABLE tests.subxact_ok (id int)");
+        let result: Result<i32, ()> = Spi::subtransaction(|mut client| {
+            client.update("INSERT INTO tests.subxact_ok VALUES (1)", None, None);
+            Ok(42)
+        });
+        assert_eq!(result, Ok(42));
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT count(*) FROM tests.subxact_ok"),
+            Some(1)
+        );
+    }
+
+    #[pg_test]
+    fn test_subtransaction_rolls_back_on_err() {
+        Spi::run("CREATE TABLE tests.subxact_err (id int)");
+        Spi::run("INSERT INTO tests.subxact_err VALUES (1)");
+        let result: Result<(), &str> = Spi::subtransaction(|mut client| {
+            client.update("INSERT INTO tests.subxact_err VALUES (2)", None, None);
+            Err("rollback")
+        });
+        assert_eq!(result, Err("rollback"));
+        assert_eq!(
+            Spi::get_one::<i32>("SELECT max(id) FROM tests.subxact_err"),
+            Some(1)
+        );
+
+        // the outer transaction is still usable
+        Spi::run("INSERT INTO tests.subxact_err VALUES (3)");
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT count(*) FROM tests.subxact_err"),
+            Some(2)
+        );
+    }
+
+    #[pg_test(error = "division by zero")]
+    fn test_subtransaction_rethrows_error() {
+        let _: Result<(), ()> = Spi::subtransaction(|client| {
+            client.select("SELECT 1 / 0", None, None);
+            Ok(())
+        });
+    }
+
+    #[pg_test]
+    fn test_try_run_traps_error() {
+        let error = Spi::try_run("SELECT 1 / 0").unwrap_err();
+        assert_eq!(error.sqlstate, "22012");
+        assert_eq!(error.message, "division by zero");
+        assert!(error.is(PgSqlErrorCode::ERRCODE_DIVISION_BY_ZERO));
+
+        // the transaction is still usable
+        assert_eq!(Spi::get_one::<i32>("SELECT 1"), Some(1));
+    }
+
+    #[pg_test]
+    fn test_try_get_one() {
+        assert_eq!(Spi::try_get_one::<i32>("SELECT 42"), Ok(Some(42)));
+        let error = Spi::try_get_one::<i32>("SELECT * FROM tests.no_such_table").unwrap_err();
+        assert!(error.is(PgSqlErrorCode::ERRCODE_UNDEFINED_TABLE));
+    }
+
+    #[pg_test]
+    fn test_try_update_rolls_back_only_the_failed_statement() {
+        Spi::run("CREATE TABLE tests.try_update (id int PRIMARY KEY)");
+        Spi::execute(|mut client| {
+            client.update("INSERT INTO tests.try_update VALUES (1)", None, None);
+            let error = client
+                .try_update("INSERT INTO tests.try_update VALUES (2), (1)", None, None)
+                .unwrap_err();
+            assert!(error.is(PgSqlErrorCode::ERRCODE_UNIQUE_VIOLATION));
+            assert!(error.detail.is_some());
+
+            let count = client
+                .try_select("SELECT count(*) FROM tests.try_update", None, None)
+                .unwrap()
+                .first()
+                .get_one::<i64>();
+            assert_eq!(count, Some(1));
+        });
+    }
+
+    #[pg_test]
+    fn test_select_read_only() {
+        Spi::execute(|client| {
+            let one = client
+                .select_read_only("SELECT 1", None, None)
+                .first()
+                .get_one::<i32>();
+            assert_eq!(one, Some(1));
+        });
+    }
+
+    #[pg_test(error = "CREATE TABLE is not allowed in a non-volatile function")]
+    fn test_read_only_prepared_statement_rejects_writes() {
+        Spi::execute(|client| {
+            client
+                .prepare("CREATE TABLE tests.read_only (id int)", &[])
+                .read_only(true)
+                .execute(None, vec![]);
+        });
+    }
+
+    #[pg_test]
+    fn test_prepare_with_mode() {
+        Spi::execute(|client| {
+            for mode in [SpiPlanMode::Auto, SpiPlanMode::Generic, SpiPlanMode::Custom] {
+                let stmt = client.prepare_with_mode(
+                    "SELECT $1 * 2",
+                    &[PgBuiltInOids::INT4OID.oid()],
+                    mode,
+                );
+                let doubled = stmt
+                    .execute(None, vec![21_i32.into_datum()])
+                    .first()
+                    .get_one::<i32>();
+                assert_eq!(doubled, Some(42));
+            }
+        });
+    }
+
+    #[pg_test]
+    fn test_prepare_cached() {
+        let first = Spi::connect(|client| {
+            let stmt = client.prepare_cached("SELECT 'cached'", &[], SpiPlanMode::Auto);
+            Ok(Some(stmt.as_ptr() as i64))
+        });
+        let second = Spi::connect(|client| {
+            let stmt = client.prepare_cached("SELECT 'cached'", &[], SpiPlanMode::Auto);
+            assert_eq!(
+                stmt.execute(None, vec![]).first().get_one::<&str>(),
+                Some("cached")
+            );
+            Ok(Some(stmt.as_ptr() as i64))
+        });
+        assert_eq!(first, second);
+
+        Spi::clear_plan_cache();
+    }
+
+    #[pg_test]
+    fn test_explain_json() {
+        Spi::execute(|mut client| {
+            let output = client.explain("SELECT 1", ExplainOptions::default());
+            match output {
+                ExplainOutput::Json(Json(plan)) => {
+                    assert_eq!(plan[0]["Plan"]["Node Type"], "Result");
+                    assert!(plan[0]["Plan"].get("Actual Rows").is_none());
+                }
+                other => panic!("unexpected EXPLAIN output: {:?}", other),
+            }
+        });
+    }
+
+    #[pg_test]
+    fn test_explain_analyze_text() {
+        Spi::execute(|mut client| {
+            let output = client.explain(
+                "SELECT 1",
+                ExplainOptions {
+                    analyze: true,
+                    format: ExplainFormat::Text,
+                    ..Default::default()
+                },
+            );
+            match output {
+                ExplainOutput::Text(plan) => {
+                    assert!(plan.starts_with("Result"));
+                    assert!(plan.contains("actual time="));
+                }
+                other => panic!("unexpected EXPLAIN output: {:?}", other),
+            }
+        });
+    }
+
+    #[pg_test]
+    fn test_copy_in() {
+        Spi::run("CREATE TABLE tests.copy_in (id int, name text)");
+        Spi::execute(|mut client| {
+            let mut rows = client.copy_in::<(i32, String)>("tests.copy_in", &["id", "name"]);
+            rows.extend((1..=2500).map(|i| (i, format!("row {}", i))));
+            assert_eq!(rows.finish(), 2500);
+        });
+        assert_eq!(
+            Spi::get_two::<i64, i64>("SELECT count(*), sum(id) FROM tests.copy_in"),
+            (Some(2500), Some(2500 * 2501 / 2))
+        );
+        assert_eq!(
+            Spi::get_one::<String>("SELECT name FROM tests.copy_in WHERE id = 42"),
+            Some("row 42".to_string())
+        );
+    }
+
+    #[pg_test]
+    fn test_copy_in_flushes_on_drop() {
+        Spi::run("CREATE TABLE tests.copy_in_drop (id int, note text)");
+        Spi::execute(|mut client| {
+            let mut rows = client
+                .copy_in::<(i32, Option<&str>)>("tests.copy_in_drop", &["id", "note"])
+                .batch_size(2);
+            rows.insert((1, Some("one")));
+            rows.insert((2, None));
+            rows.insert((3, Some("three")));
+        });
+        assert_eq!(
+            Spi::get_two::<i64, i64>("SELECT count(*), count(note) FROM tests.copy_in_drop"),
+            (Some(3), Some(2))
+        );
+    }
+
+    #[pg_test(error = "copy_in of 1 columns, but rows have 2 values")]
+    fn test_copy_in_wrong_number_of_columns() {
+        Spi::execute(|mut client| {
+            client.copy_in::<(i32, i32)>("tests.no_such_table", &["id"]);
+        });
+    }
+
+    #[pg_test]
+    fn test_owned_spi_result_outlives_connection() {
+        let result = Spi::select_owned(
+            "SELECT x, x::text AS name FROM generate_series(1, 3) x",
+            None,
+            None,
+        );
+        assert_eq!(result.len(), 3);
+        assert_eq!(result.get_one::<i32>(), Some(1));
+
+        let names = result
+            .iter()
+            .map(|row| row.get_by_name::<String>("name").unwrap().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["1", "2", "3"]);
+        assert!(result.get(3).is_none());
+    }
+
+    static mut CACHED_RESULT: Option<OwnedSpiResult> = None;
+
+    #[pg_test]
+    fn test_owned_spi_result_in_top_memory_context() {
+        Spi::execute(|client| {
+            let result = client
+                .select("SELECT 'cached'::text", None, None)
+                .to_owned_in(&PgMemoryContexts::TopMemoryContext);
+            unsafe { CACHED_RESULT = Some(result) };
+        });
+
+        let cached = unsafe { CACHED_RESULT.take() }.unwrap();
+        assert_eq!(cached.get_one::<String>(), Some("cached".to_string()));
+    }
+
+    #[pg_test]
+    fn test_owned_spi_result_without_rows() {
+        Spi::run("CREATE TABLE tests.owned_result (id int)");
+        let result = Spi::select_owned("INSERT INTO tests.owned_result VALUES (1)", None, None);
+        assert!(result.is_empty());
+        assert!(result.tupdesc().is_none());
+    }
+
+    #[pg_test]
+    fn test_spi_tuple_table_columns() {
+        Spi::execute(|client| {
+            let table = client.select("SELECT 1::int AS id, 'x'::varchar(10) AS label", None, None);
+            let columns = table.columns();
+            assert_eq!(
+                columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(),
+                vec!["id", "label"]
+            );
+            assert_eq!(columns[0].type_oid, PgBuiltInOids::INT4OID.value());
+            assert_eq!(columns[0].typmod, -1);
+            assert_eq!(columns[1].type_oid, PgBuiltInOids::VARCHAROID.value());
+            // varchar's typmod includes the 4-byte varlena header
+            assert_eq!(columns[1].typmod, 14);
+        });
+    }
+
+    #[pg_test]
+    fn test_prepared_statement_columns() {
+        Spi::execute(|client| {
+            let stmt = client.prepare(
+                "SELECT $1::text AS greeting, $2 + 1 AS next",
+                &[PgBuiltInOids::TEXTOID.oid(), PgBuiltInOids::INT8OID.oid()],
+            );
+            assert_eq!(
+                stmt.arg_types(),
+                vec![PgBuiltInOids::TEXTOID.oid(), PgBuiltInOids::INT8OID.oid()]
+            );
+            let columns = stmt.columns();
+            assert_eq!(columns.len(), 2);
+            assert_eq!(columns[0].name, "greeting");
+            assert_eq!(columns[1].type_oid, PgBuiltInOids::INT8OID.value());
+        });
+        Spi::run("CREATE TABLE tests.prepared_columns (id int)");
+        Spi::execute(|client| {
+            let stmt = client.prepare("INSERT INTO tests.prepared_columns VALUES (1)", &[]);
+            assert!(stmt.columns().is_empty());
+        });
+    }
+
+    #[pg_test]
+    fn test_run_script() {
+        Spi::execute(|mut client| {
+            client
+                .run_script(
+                    "CREATE TABLE tests.script (id int);
+                     INSERT INTO tests.script VALUES (1), (2);
+                     DO $$ BEGIN INSERT INTO tests.script VALUES (3); END $$;
+                     UPDATE tests.script SET id = id * 10;",
+                )
+                .expect("script failed");
+        });
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT sum(id) FROM tests.script"),
+            Some(60)
+        );
+    }
+
+    #[pg_test]
+    fn test_run_script_error_location() {
+        Spi::execute(|mut client| {
+            let error = client
+                .run_script(
+                    "CREATE TABLE tests.script_error (id int);\n\
+                     INSERT INTO tests.script_error VALUES (1);\n\
+                     SELECT * FROM tests.no_such_table;",
+                )
+                .unwrap_err();
+            assert_eq!(error.statement, Some(2));
+            assert_eq!(error.line, 3);
+            assert_eq!(error.column, 15);
+            assert_eq!(error.error.sqlstate, "42P01");
+        });
+        // the whole script was undone
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT to_regclass('tests.script_error') IS NULL"),
+            Some(true)
+        );
+    }
+
+    #[pg_test]
+    fn test_run_script_syntax_error() {
+        Spi::execute(|mut client| {
+            let error = client.run_script("SELECT 1;\nSELEC 2;").unwrap_err();
+            assert_eq!(error.statement, None);
+            assert_eq!((error.line, error.column), (2, 1));
+            assert_eq!(error.error.sqlstate, "42601");
+        });
+    }
+
+    #[pg_test]
+    fn test_spi_get_tuple() {
+        let row = Spi::get::<(i32, String, Option<i64>, bool, Option<String>)>(
+            "SELECT 1, 'two', NULL::bigint, true, 'five'",
+            None,
+        );
+        assert_eq!(
+            row,
+            Ok(Some((
+                1,
+                "two".to_string(),
+                None,
+                true,
+                Some("five".to_string())
+            )))
+        );
+
+        let row = Spi::get::<(i32, i32)>(
+            "SELECT $1, $1 * 2",
+            Some(vec![(PgBuiltInOids::INT4OID.oid(), 21.into_datum())]),
+        );
+        assert_eq!(row, Ok(Some((21, 42))));
+
+        let row = Spi::get::<(i32,)>("SELECT 1 WHERE false", None);
+        assert_eq!(row, Ok(None));
+    }
+
+    #[pg_test]
+    fn test_spi_get_tuple_errors() {
+        assert_eq!(
+            Spi::get::<(i32, String)>("SELECT 1, NULL::text AS name", None),
+            Err(FromSpiRowError::UnexpectedNull("name".to_string()))
+        );
+        assert_eq!(
+            Spi::get::<(i32, String)>("SELECT 1", None),
+            Err(FromSpiRowError::NoSuchOrdinal(2))
+        );
+
+        // unlike `Spi::get()`, the missing columns of `Spi::get_two()` and `get_three()` are `None`
+        assert_eq!(Spi::get_two::<i32, i32>("SELECT 1"), (Some(1), None));
+        assert_eq!(
+            Spi::get_three::<i32, i32, i32>("SELECT 1, 2"),
+            (Some(1), Some(2), None)
+        );
+    }
+
+    #[pg_test]
+    fn test_with_timeout() {
+        let result = Spi::with_timeout(std::time::Duration::from_secs(60), |client| {
+            client
+                .select("SELECT 42", None, None)
+                .first()
+                .get_one::<i32>()
+        });
+        assert_eq!(result, Ok(Some(42)));
+    }
+
+    #[pg_test]
+    fn test_with_timeout_times_out() {
+        let timeout = std::time::Duration::from_millis(100);
+        let result = Spi::with_timeout(timeout, |client| {
+            client.select("SELECT pg_sleep(60)", None, None);
+        });
+        assert_eq!(result, Err(SpiTimeoutError::TimedOut(timeout)));
+
+        // the cancellation doesn't leak into later statements
+        assert_eq!(Spi::get_one::<i32>("SELECT 1"), Some(1));
+    }
+
+    #[pg_test]
+    fn test_with_timeout_error() {
+        let result = Spi::with_timeout(std::time::Duration::from_secs(60), |client| {
+            client.select("SELECT 1 / 0", None, None);
+        });
+        match result {
+            Err(SpiTimeoutError::Sql(error)) => assert_eq!(error.sqlstate, "22012"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,61 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::sql::{Ident, Literal};
+    use pgx::*;
+
+    #[pg_test]
+    fn test_ident() {
+        assert_eq!(Ident::new("dogs").to_string(), "dogs");
+        assert_eq!(Ident::new("Dogs").to_string(), "\"Dogs\"");
+        assert_eq!(Ident::new("select").to_string(), "\"select\"");
+        assert_eq!(
+            Ident::new("bobby\"; DROP TABLE dogs; --").to_string(),
+            "\"bobby\"\"; DROP TABLE dogs; --\""
+        );
+        assert_eq!(
+            Ident::qualified("tests", "Dogs").to_string(),
+            "tests.\"Dogs\""
+        );
+    }
+
+    #[pg_test]
+    fn test_literal() {
+        assert_eq!(Literal::new("Nami").to_string(), "'Nami'");
+        assert_eq!(Literal::new("O'Reilly").to_string(), "'O''Reilly'");
+        assert_eq!(Literal::new("back\\slash").to_string(), "E'back\\\\slash'");
+        assert_eq!(Literal::null().to_string(), "NULL");
+        assert_eq!(Literal::from(None::<&str>).to_string(), "NULL");
+    }
+
+    #[pg_test]
+    fn test_dynamic_ddl() {
+        let table = Ident::qualified("tests", "Weird \"Table\"");
+        Spi::run(&format!(
+            "CREATE TABLE {} ({} text)",
+            table,
+            Ident::new("Value")
+        ));
+        Spi::run(&format!(
+            "INSERT INTO {} VALUES ({})",
+            table,
+            Literal::new("it's")
+        ));
+        assert_eq!(
+            Spi::get_one::<String>(&format!("SELECT {} FROM {}", Ident::new("Value"), table)),
+            Some("it's".to_string())
+        );
+    }
+}
@@ -53,6 +53,11 @@ fn return_none_iterator(
     }
 }
 
+#[pg_extern(rows = 3)]
+fn example_estimated_set() -> impl std::iter::Iterator<Item = i32> {
+    vec![1, 2, 3].into_iter()
+}
+
 #[pg_extern]
 fn return_some_setof_iterator() -> Option<impl std::iter::Iterator<Item = i32>> {
     Some(vec![1, 2, 3].into_iter())
@@ -67,6 +72,33 @@ fn return_none_setof_iterator() -> Option<impl std::iter::Iterator<Item = i32>>
     }
 }
 
+#[derive(TableRow)]
+struct Letter {
+    idx: i32,
+    #[table_row(rename = "letter")]
+    value: String,
+    upper: Option<String>,
+}
+
+#[pg_extern]
+fn example_table_row_set() -> TableIterator<'static, Letter> {
+    TableIterator::new(
+        vec!["a", "b", "c"]
+            .into_iter()
+            .enumerate()
+            .map(|(idx, value)| Letter {
+                idx: (idx + 1) as i32,
+                value: value.to_string(),
+                upper: (idx != 1).then(|| value.to_uppercase()),
+            }),
+    )
+}
+
+#[pg_extern]
+fn return_none_table_row_set() -> Option<TableIterator<'static, Letter>> {
+    None
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
 mod tests {
@@ -122,6 +154,31 @@ mod tests {
         assert_eq!(cnt.unwrap(), 3)
     }
 
+    #[pg_test]
+    fn test_table_row_set() {
+        let letters = Spi::get_one::<String>(
+            "SELECT string_agg(idx || letter || coalesce(upper, '-'), ',' ORDER BY idx)
+               FROM example_table_row_set()",
+        )
+        .expect("SQL select failed");
+        assert_eq!(letters, "1aA,2b-,3cC");
+    }
+
+    #[pg_test]
+    fn test_table_row_set_columns() {
+        let columns = Spi::get_one::<Vec<String>>(
+            "SELECT proargnames FROM pg_proc WHERE proname = 'example_table_row_set'",
+        )
+        .expect("SQL select failed");
+        assert_eq!(columns, vec!["idx", "letter", "upper"]);
+    }
+
+    #[pg_test]
+    fn test_return_none_table_row_set() {
+        let cnt = Spi::get_one::<i64>("SELECT count(*) FROM return_none_table_row_set()");
+        assert_eq!(cnt, Some(0))
+    }
+
     #[pg_test]
     fn test_return_some_iterator() {
         let cnt = Spi::connect(|client| {
@@ -165,4 +222,11 @@ mod tests {
 
         assert_eq!(cnt, Some(0))
     }
+
+    #[pg_test]
+    fn test_srf_rows_estimate() {
+        let plan = Spi::get_one::<String>("EXPLAIN SELECT * FROM example_estimated_set()")
+            .expect("EXPLAIN returned nothing");
+        assert!(plan.contains("rows=3 "), "{}", plan);
+    }
 }
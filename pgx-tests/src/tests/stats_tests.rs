@@ -0,0 +1,106 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[derive(PgStats, Default)]
+#[repr(C)]
+#[pg_stats(view = "test_stats")]
+struct TestStats {
+    /// Dogs walked since the counters were reset
+    walks: PgStatCounter,
+    treats: PgStatCounter,
+    /// Dogs waiting for a walk
+    waiting: PgStatGauge,
+}
+
+/// The statistics, in this backend's memory, as the tests' extension isn't loaded by
+/// `shared_preload_libraries`
+#[allow(dead_code)]
+fn test_stats() -> &'static TestStats {
+    static ATTACH: std::sync::Once = std::sync::Once::new();
+    ATTACH.call_once(|| TestStats::shared().attach(Box::leak(Box::new(TestStats::default()))));
+    TestStats::get()
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use super::*;
+
+    #[pg_test]
+    fn test_stats_view() {
+        let stats = test_stats();
+        stats.reset();
+        stats.walks.increment();
+        stats.walks.add(2);
+        stats.waiting.set(5);
+        stats.waiting.sub(1);
+
+        assert_eq!(
+            Spi::get::<(String, i64, String)>(
+                "SELECT kind, value, description FROM test_stats WHERE name = 'walks'",
+                None
+            ),
+            Ok(Some((
+                "counter".to_string(),
+                3,
+                "Dogs walked since the counters were reset".to_string()
+            )))
+        );
+        assert_eq!(
+            Spi::get::<(String, i64)>(
+                "SELECT kind, value FROM test_stats WHERE name = 'waiting'",
+                None
+            ),
+            Ok(Some(("gauge".to_string(), 4)))
+        );
+        assert_eq!(
+            Spi::get_one::<Vec<String>>("SELECT array_agg(name) FROM test_stats"),
+            Some(vec![
+                "walks".to_string(),
+                "treats".to_string(),
+                "waiting".to_string()
+            ])
+        );
+    }
+
+    #[pg_test]
+    fn test_stats_reset() {
+        let stats = test_stats();
+        stats.treats.add(10);
+        stats.waiting.set(2);
+
+        Spi::run("SELECT test_stats_reset()");
+        assert_eq!(stats.treats.get(), 0);
+        assert_eq!(stats.walks.get(), 0);
+        // gauges aren't reset
+        assert_eq!(stats.waiting.get(), 2);
+    }
+
+    #[pg_test]
+    fn test_stats_reset_revoked() {
+        assert_eq!(
+            Spi::get_one::<bool>(
+                "SELECT has_function_privilege('public', 'test_stats_reset()', 'EXECUTE')"
+            ),
+            Some(false)
+        );
+    }
+
+    #[pg_test(
+        error = "shared memory can only be initialized by an extension loaded via `shared_preload_libraries`"
+    )]
+    fn test_stats_init_outside_preload() {
+        TestStats::init();
+    }
+}
@@ -0,0 +1,92 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(all(
+    any(test, feature = "pg_test"),
+    any(feature = "pg12", feature = "pg13", feature = "pg14")
+))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    static mut INSERTED: u64 = 0;
+    static mut SCANS: u64 = 0;
+
+    /// The heap, counting the tuples inserted into it and the scans of it
+    struct CountingHeap;
+
+    impl TableAccessMethod for CountingHeap {
+        fn scan_begin(
+            relation: &PgRelation,
+            snapshot: pg_sys::Snapshot,
+            nkeys: i32,
+            keys: *mut pg_sys::ScanKeyData,
+            parallel_scan: pg_sys::ParallelTableScanDesc,
+            flags: u32,
+        ) -> pg_sys::TableScanDesc {
+            unsafe {
+                SCANS += 1;
+                (heap_table_am().scan_begin.unwrap())(
+                    relation.as_ptr(),
+                    snapshot,
+                    nkeys,
+                    keys,
+                    parallel_scan,
+                    flags,
+                )
+            }
+        }
+
+        fn tuple_insert(
+            relation: &PgRelation,
+            slot: *mut pg_sys::TupleTableSlot,
+            cid: pg_sys::CommandId,
+            options: i32,
+            bistate: *mut pg_sys::BulkInsertStateData,
+        ) {
+            unsafe {
+                INSERTED += 1;
+                (heap_table_am().tuple_insert.unwrap())(
+                    relation.as_ptr(),
+                    slot,
+                    cid,
+                    options,
+                    bistate,
+                )
+            }
+        }
+    }
+
+    #[pg_extern]
+    fn counting_heap_handler(_internal: Internal) -> PgBox<pg_sys::TableAmRoutine> {
+        table_am_routine::<CountingHeap>()
+    }
+
+    #[pg_test]
+    fn test_table_am() {
+        Spi::run(
+            "CREATE ACCESS METHOD counting_heap TYPE TABLE HANDLER tests.counting_heap_handler",
+        );
+        Spi::run("CREATE TABLE tests.counted (n int) USING counting_heap");
+
+        let (inserted, scans) = unsafe { (INSERTED, SCANS) };
+        Spi::run("INSERT INTO tests.counted VALUES (1)");
+        Spi::run("INSERT INTO tests.counted VALUES (2)");
+        let sum = Spi::get_one::<i64>("SELECT sum(n) FROM tests.counted");
+        assert_eq!(sum, Some(3));
+
+        unsafe {
+            assert_eq!(INSERTED - inserted, 2);
+            assert!(SCANS > scans);
+        }
+    }
+}
@@ -0,0 +1,420 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+extension_sql!(
+    r#"CREATE TABLE declared_dogs (name text, scritches integer NOT NULL, scritch_updates integer NOT NULL DEFAULT 0);"#,
+    name = "create_declared_dogs"
+);
+
+#[pg_trigger(
+    table = "declared_dogs",
+    timing = "before",
+    events = ["update"],
+    update_of = ["scritches"],
+    level = "row",
+    when = "NEW.scritches > OLD.scritches",
+    requires = ["create_declared_dogs"]
+)]
+fn count_scritch_updates(trigger: &PgTrigger) -> Result<PgHeapTuple<'_>, PgHeapTupleError> {
+    let mut new = trigger.new().expect("not fired by UPDATE");
+    let updates = new
+        .get_by_name::<i32>("scritch_updates")?
+        .unwrap_or_default();
+    new.set_by_name("scritch_updates", updates + 1)?;
+    Ok(new)
+}
+
+extension_sql!(
+    r#"CREATE TABLE leashed_dogs (name text, leash text);"#,
+    name = "create_leashed_dogs"
+);
+
+#[pg_trigger(
+    table = "leashed_dogs",
+    timing = "after",
+    events = ["insert", "update"],
+    constraint,
+    initially_deferred,
+    requires = ["create_leashed_dogs"]
+)]
+fn check_leashes(trigger: &PgTrigger) -> Result<(), String> {
+    assert!(trigger.is_constraint_trigger());
+    assert!(trigger.is_deferrable() && trigger.is_initially_deferred());
+    // by the time a deferred trigger fires, its row may have been updated again
+    let unleashed = Spi::get_one::<i64>("SELECT count(*) FROM leashed_dogs WHERE leash IS NULL");
+    match unleashed {
+        Some(0) => Ok(()),
+        _ => Err("every dog needs a leash".to_string()),
+    }
+}
+
+extension_sql!(
+    r#"
+CREATE TABLE kennel (name text, scritches integer NOT NULL DEFAULT 0);
+CREATE VIEW kennel_names AS SELECT name FROM kennel;
+"#,
+    name = "create_kennel"
+);
+
+#[pg_trigger(
+    table = "kennel_names",
+    timing = "instead of",
+    events = ["insert", "delete"],
+    requires = ["create_kennel"]
+)]
+fn update_kennel(trigger: &PgTrigger) -> Result<Option<PgHeapTuple<'_>>, PgHeapTupleError> {
+    let row = trigger.affected_row().expect("not fired for a row");
+    let name = sql::quote_literal(&row.get_by_name::<String>("name")?.unwrap_or_default());
+    let affected = match trigger.event() {
+        TriggerEvent::Insert => Spi::get_one::<i64>(&format!(
+            "WITH inserted AS (INSERT INTO kennel (name) VALUES ({}) RETURNING 1) \
+             SELECT count(*) FROM inserted",
+            name
+        )),
+        // duplicate names are all deleted by the first of their rows
+        _ => Spi::get_one::<i64>(&format!(
+            "WITH deleted AS (DELETE FROM kennel WHERE name = {} RETURNING 1) \
+             SELECT count(*) FROM deleted",
+            name
+        )),
+    };
+    Ok(affected.filter(|affected| *affected > 0).map(|_| row))
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[derive(FromHeapTuple, IntoHeapTuple)]
+    struct Dog {
+        name: String,
+        scritches: i32,
+    }
+
+    #[pg_trigger]
+    fn scritch_dog(trigger: &PgTrigger) -> Result<Option<PgHeapTuple<'_>>, PgHeapTupleError> {
+        let mut dog = trigger
+            .new_as::<Dog>()?
+            .expect("not fired by INSERT or UPDATE");
+        dog.scritches += 1;
+        trigger.new_with(dog).map(Some)
+    }
+
+    #[pg_trigger]
+    fn remember_name(trigger: &PgTrigger) -> Result<PgHeapTuple<'_>, PgHeapTupleError> {
+        let old = trigger.old().expect("not fired by UPDATE");
+        let mut new = trigger.new().expect("not fired by UPDATE");
+        new.set_by_name("previous_name", old.get_by_name::<String>("name")?)?;
+        Ok(new)
+    }
+
+    #[pg_trigger]
+    fn skip_nameless(trigger: &PgTrigger) -> Option<PgHeapTuple<'_>> {
+        let new = trigger.new()?;
+        match new.get_by_name::<String>("name") {
+            Ok(Some(_)) => Some(new),
+            _ => None,
+        }
+    }
+
+    #[pg_trigger]
+    fn describe_firing(trigger: &PgTrigger) -> Result<PgHeapTuple<'_>, PgHeapTupleError> {
+        let mut new = trigger.new().expect("not fired by INSERT");
+        let firing = format!(
+            "{} {:?} {:?} {:?} {} {}",
+            trigger.name(),
+            trigger.timing(),
+            trigger.event(),
+            trigger.level(),
+            trigger.relation().name(),
+            trigger.args().join(",")
+        );
+        new.set_by_name("firing", firing)?;
+        Ok(new)
+    }
+
+    static mut WELCOMED: Vec<String> = Vec::new();
+
+    #[pg_trigger]
+    fn welcome_dogs(trigger: &PgTrigger) -> Result<(), PgHeapTupleError> {
+        assert_eq!(trigger.level(), TriggerLevel::Statement);
+        assert!(trigger.new().is_none());
+        let new_dogs = trigger.new_table().expect("no NEW TABLE");
+        for dog in new_dogs.rows::<Dog>() {
+            unsafe { WELCOMED.push(dog?.name) };
+        }
+        Ok(())
+    }
+
+    #[pg_trigger]
+    fn check_scritches(trigger: &PgTrigger) -> Result<(), String> {
+        let old_dogs = trigger.old_table().expect("no OLD TABLE");
+        let new_dogs = trigger.new_table().expect("no NEW TABLE");
+        assert_eq!(old_dogs.len(), new_dogs.len());
+
+        let total = |dogs: &TransitionTable| -> Result<i32, PgHeapTupleError> {
+            let mut total = 0;
+            for dog in dogs {
+                total += dog.get_by_name::<i32>("scritches")?.unwrap_or_default();
+            }
+            Ok(total)
+        };
+        let given = total(&new_dogs).map_err(|e| e.to_string())?
+            - total(&old_dogs).map_err(|e| e.to_string())?;
+        if given > 10 {
+            return Err(format!("{} scritches is too many", given));
+        }
+        Ok(())
+    }
+
+    #[pg_trigger]
+    fn record_nest_level(trigger: &PgTrigger) -> Result<PgHeapTuple<'_>, PgHeapTupleError> {
+        let mut new = trigger.new().expect("not fired by INSERT");
+        new.set_by_name("nest_level", trigger.transaction_nest_level())?;
+        new.set_by_name("in_subtransaction", trigger.in_subtransaction())?;
+        Ok(new)
+    }
+
+    #[pg_trigger]
+    fn defer_audit(trigger: &PgTrigger) {
+        let name = trigger.name().to_string();
+        defer_until_commit(move || {
+            info!("audited {}", name);
+        });
+    }
+
+    #[pg_trigger]
+    fn reject_all(_trigger: &PgTrigger) -> Result<PgHeapTuple<'_>, String> {
+        Err("dogs are not allowed".to_string())
+    }
+
+    #[pg_test]
+    fn test_trigger_typed_new() {
+        Spi::run("CREATE TABLE tests.dogs (name text, scritches integer NOT NULL)");
+        Spi::run(
+            "CREATE TRIGGER scritch BEFORE INSERT OR UPDATE ON tests.dogs \
+             FOR EACH ROW EXECUTE PROCEDURE tests.scritch_dog()",
+        );
+        Spi::run("INSERT INTO tests.dogs VALUES ('Nami', 0)");
+        Spi::run("UPDATE tests.dogs SET scritches = scritches + 10");
+        let scritches = Spi::get_one::<i32>("SELECT scritches FROM tests.dogs");
+        assert_eq!(scritches, Some(12));
+    }
+
+    #[pg_test]
+    fn test_trigger_old_and_new() {
+        Spi::run("CREATE TABLE tests.dogs (name text, previous_name text)");
+        Spi::run(
+            "CREATE TRIGGER remember BEFORE UPDATE ON tests.dogs \
+             FOR EACH ROW EXECUTE PROCEDURE tests.remember_name()",
+        );
+        Spi::run("INSERT INTO tests.dogs VALUES ('Nami', NULL)");
+        Spi::run("UPDATE tests.dogs SET name = 'Brandy'");
+        let previous = Spi::get_one::<String>("SELECT previous_name FROM tests.dogs");
+        assert_eq!(previous, Some("Nami".to_string()));
+    }
+
+    #[pg_test]
+    fn test_trigger_skip_row() {
+        Spi::run("CREATE TABLE tests.dogs (name text)");
+        Spi::run(
+            "CREATE TRIGGER skip BEFORE INSERT ON tests.dogs \
+             FOR EACH ROW EXECUTE PROCEDURE tests.skip_nameless()",
+        );
+        Spi::run("INSERT INTO tests.dogs VALUES ('Nami'), (NULL), ('Brandy')");
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM tests.dogs");
+        assert_eq!(count, Some(2));
+    }
+
+    #[pg_test]
+    fn test_trigger_metadata() {
+        Spi::run("CREATE TABLE tests.dogs (name text, firing text)");
+        Spi::run(
+            "CREATE TRIGGER describe BEFORE INSERT ON tests.dogs \
+             FOR EACH ROW EXECUTE PROCEDURE tests.describe_firing('a', 'b')",
+        );
+        Spi::run("INSERT INTO tests.dogs VALUES ('Nami')");
+        let firing = Spi::get_one::<String>("SELECT firing FROM tests.dogs");
+        assert_eq!(
+            firing,
+            Some("describe Before Insert Row dogs a,b".to_string())
+        );
+    }
+
+    #[pg_test]
+    fn test_trigger_new_table() {
+        Spi::run("CREATE TABLE tests.dogs (name text, scritches integer NOT NULL)");
+        Spi::run(
+            "CREATE TRIGGER welcome AFTER INSERT ON tests.dogs \
+             REFERENCING NEW TABLE AS new_dogs \
+             FOR EACH STATEMENT EXECUTE PROCEDURE tests.welcome_dogs()",
+        );
+        Spi::run("INSERT INTO tests.dogs VALUES ('Nami', 0), ('Brandy', 0)");
+        let mut welcomed = unsafe { WELCOMED.clone() };
+        welcomed.sort();
+        assert_eq!(welcomed, ["Brandy", "Nami"]);
+    }
+
+    #[pg_test(error = "12 scritches is too many")]
+    fn test_trigger_old_and_new_tables() {
+        Spi::run("CREATE TABLE tests.dogs (name text, scritches integer NOT NULL)");
+        Spi::run(
+            "CREATE TRIGGER check_scritches AFTER UPDATE ON tests.dogs \
+             REFERENCING OLD TABLE AS old_dogs NEW TABLE AS new_dogs \
+             FOR EACH STATEMENT EXECUTE PROCEDURE tests.check_scritches()",
+        );
+        Spi::run("INSERT INTO tests.dogs VALUES ('Nami', 0), ('Brandy', 0)");
+        Spi::run("UPDATE tests.dogs SET scritches = scritches + 5");
+        Spi::run("UPDATE tests.dogs SET scritches = scritches + 6");
+    }
+
+    #[pg_test]
+    fn test_declared_trigger() {
+        Spi::run("INSERT INTO declared_dogs VALUES ('Nami', 0)");
+        Spi::run("UPDATE declared_dogs SET scritches = 1");
+        Spi::run("UPDATE declared_dogs SET name = 'Brandy'");
+        Spi::run("UPDATE declared_dogs SET scritches = 0");
+        let updates = Spi::get_one::<i32>("SELECT scritch_updates FROM declared_dogs");
+        assert_eq!(updates, Some(1));
+    }
+
+    #[pg_test]
+    fn test_deferred_constraint_trigger() {
+        Spi::run("INSERT INTO leashed_dogs VALUES ('Nami', NULL)");
+        Spi::run("UPDATE leashed_dogs SET leash = 'red'");
+        Spi::run("SET CONSTRAINTS ALL IMMEDIATE");
+    }
+
+    #[pg_test(error = "every dog needs a leash")]
+    fn test_deferred_constraint_trigger_error() {
+        Spi::run("INSERT INTO leashed_dogs VALUES ('Nami', NULL)");
+        Spi::run("SET CONSTRAINTS ALL IMMEDIATE");
+    }
+
+    #[pg_test]
+    fn test_trigger_transaction_nest_level() {
+        Spi::run(
+            "CREATE TABLE tests.dogs (name text, nest_level integer, in_subtransaction boolean)",
+        );
+        Spi::run(
+            "CREATE TRIGGER nest_level BEFORE INSERT ON tests.dogs \
+             FOR EACH ROW EXECUTE PROCEDURE tests.record_nest_level()",
+        );
+        Spi::run("INSERT INTO tests.dogs (name) VALUES ('Nami')");
+        Spi::run(
+            "DO $$ BEGIN
+                INSERT INTO tests.dogs (name) VALUES ('Brandy');
+             EXCEPTION WHEN others THEN NULL;
+             END $$",
+        );
+        let levels = Spi::get_one::<Vec<String>>(
+            "SELECT array_agg(name || ' ' || nest_level || ' ' || in_subtransaction ORDER BY name) \
+               FROM tests.dogs",
+        )
+        .expect("SQL select failed");
+        assert_eq!(levels, vec!["Brandy 2 true", "Nami 1 false"]);
+    }
+
+    #[pg_test]
+    fn test_defer_until_commit_in_rolled_back_subtransaction() {
+        Spi::run("CREATE TABLE tests.dogs (name text)");
+        Spi::run(
+            "CREATE TRIGGER audit AFTER INSERT ON tests.dogs \
+             FOR EACH ROW EXECUTE PROCEDURE tests.defer_audit()",
+        );
+        Spi::run("INSERT INTO tests.dogs VALUES ('Nami')");
+        Spi::run(
+            "DO $$ BEGIN
+                INSERT INTO tests.dogs VALUES ('Brandy');
+                RAISE EXCEPTION 'no Brandy';
+             EXCEPTION WHEN others THEN NULL;
+             END $$",
+        );
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM tests.dogs");
+        assert_eq!(count, Some(1));
+    }
+
+    #[pg_test]
+    fn test_instead_of_trigger() {
+        let inserted = Spi::get_one::<Vec<String>>(
+            "WITH inserted AS (INSERT INTO kennel_names VALUES ('Nami'), ('Nami') RETURNING name) \
+             SELECT array_agg(name) FROM inserted",
+        );
+        assert_eq!(inserted, Some(vec!["Nami".to_string(), "Nami".to_string()]));
+
+        let deleted = Spi::get_one::<i64>(
+            "WITH deleted AS (DELETE FROM kennel_names RETURNING name) SELECT count(*) FROM deleted",
+        );
+        assert_eq!(deleted, Some(1));
+        let remaining = Spi::get_one::<i64>("SELECT count(*) FROM kennel");
+        assert_eq!(remaining, Some(0));
+    }
+
+    #[pg_test]
+    fn test_trigger_test_table() {
+        #[derive(serde::Deserialize)]
+        struct ScritchedDog {
+            name: String,
+            scritches: i32,
+        }
+
+        let test = TriggerTest::new("scratch_dogs", "name text, scritches integer NOT NULL");
+        test.attach("tests.scritch_dog", "BEFORE INSERT OR UPDATE");
+
+        let fired = test.execute("INSERT INTO scratch_dogs VALUES ('Nami', 0), ('Brandy', 5)");
+        assert_eq!(fired.len(), 2);
+        assert_eq!(fired[0].name, "scritch_dog");
+        assert_eq!(fired[0].table, "scratch_dogs");
+        assert_eq!(
+            (fired[0].timing, fired[0].event, fired[0].level),
+            (
+                TriggerTiming::Before,
+                TriggerEvent::Insert,
+                TriggerLevel::Row
+            )
+        );
+        assert_eq!(fired[0].old, None);
+        assert_eq!(
+            fired[1].new,
+            Some(serde_json::json!({ "name": "Brandy", "scritches": 5 }))
+        );
+
+        let fired = test.execute("UPDATE scratch_dogs SET scritches = 10 WHERE name = 'Nami'");
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].old.as_ref().unwrap()["scritches"], 1);
+        assert_eq!(fired[0].new.as_ref().unwrap()["scritches"], 10);
+
+        let dogs = test.rows::<ScritchedDog>("name");
+        let dogs = dogs
+            .iter()
+            .map(|dog| (dog.name.as_str(), dog.scritches))
+            .collect::<Vec<_>>();
+        assert_eq!(dogs, vec![("Brandy", 6), ("Nami", 11)]);
+
+        // nothing is recorded outside of `execute`
+        Spi::run("INSERT INTO scratch_dogs VALUES ('Ziggy', 0)");
+        assert!(test.execute("SELECT 1").is_empty());
+    }
+
+    #[pg_test(error = "dogs are not allowed")]
+    fn test_trigger_error() {
+        Spi::run("CREATE TABLE tests.dogs (name text)");
+        Spi::run(
+            "CREATE TRIGGER reject BEFORE INSERT ON tests.dogs \
+             FOR EACH ROW EXECUTE PROCEDURE tests.reject_all()",
+        );
+        Spi::run("INSERT INTO tests.dogs VALUES ('Nami')");
+    }
+}
@@ -0,0 +1,100 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    fn parse(query: &str) -> UtilityStatement {
+        let query = std::ffi::CString::new(query).unwrap();
+        unsafe {
+            let statements = PgList::<pg_sys::RawStmt>::from_pg(pg_sys::pg_parse_query(
+                query.as_ptr(),
+            ));
+            UtilityStatement::from_node((*statements.head().unwrap()).stmt)
+        }
+    }
+
+    #[pg_test]
+    fn test_utility_create_table() {
+        assert_eq!(
+            parse("CREATE TABLE IF NOT EXISTS tests.t (id int)"),
+            UtilityStatement::CreateTable {
+                table: QualifiedName {
+                    schema: Some("tests".to_string()),
+                    name: "t".to_string()
+                },
+                if_not_exists: true,
+            }
+        );
+    }
+
+    #[pg_test]
+    fn test_utility_drop_object() {
+        assert_eq!(
+            parse("DROP TABLE IF EXISTS tests.a, b CASCADE"),
+            UtilityStatement::DropObject {
+                object_type: pg_sys::ObjectType_OBJECT_TABLE,
+                names: vec!["tests.a".to_string(), "b".to_string()],
+                missing_ok: true,
+                cascade: true,
+            }
+        );
+        assert_eq!(
+            parse("DROP FUNCTION tests.f(int)"),
+            UtilityStatement::DropObject {
+                object_type: pg_sys::ObjectType_OBJECT_FUNCTION,
+                names: vec!["tests.f".to_string()],
+                missing_ok: false,
+                cascade: false,
+            }
+        );
+    }
+
+    #[pg_test]
+    fn test_utility_roles() {
+        assert_eq!(
+            parse("ALTER ROLE bob WITH SUPERUSER PASSWORD 'x'"),
+            UtilityStatement::AlterRole {
+                role: "bob".to_string(),
+                options: vec!["superuser".to_string(), "password".to_string()],
+            }
+        );
+        assert_eq!(
+            parse("GRANT SELECT, INSERT ON tests.t TO bob, PUBLIC"),
+            UtilityStatement::Grant {
+                is_grant: true,
+                privileges: Some(vec!["select".to_string(), "insert".to_string()]),
+                grantees: vec!["bob".to_string(), "PUBLIC".to_string()],
+            }
+        );
+        assert_eq!(
+            parse("DROP ROLE IF EXISTS bob"),
+            UtilityStatement::DropRole {
+                roles: vec!["bob".to_string()],
+                missing_ok: true,
+            }
+        );
+    }
+
+    #[pg_test]
+    fn test_utility_other() {
+        let statement = parse("VACUUM");
+        match statement {
+            UtilityStatement::Other(node) => unsafe {
+                assert!(is_a(node, pg_sys::NodeTag_T_VacuumStmt))
+            },
+            _ => panic!("VACUUM parsed as {:?}", statement),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    static TEST_EVENT: WaitEvent = WaitEvent::new("PgxTestsWait");
+
+    fn reported_wait_event() -> u32 {
+        unsafe { (*pg_sys::MyProc).wait_event_info }
+    }
+
+    #[pg_test]
+    fn test_wait_event_ids() {
+        assert_eq!(TEST_EVENT.name(), "PgxTestsWait");
+        assert_eq!(
+            TEST_EVENT.info() & 0xFF000000,
+            pg_sys::PG_WAIT_EXTENSION,
+            "not an extension wait event"
+        );
+        assert_eq!(TEST_EVENT, WaitEvent::new("PgxTestsWait"));
+        assert_ne!(
+            TEST_EVENT.info(),
+            WaitEvent::new("PgxTestsOtherWait").info()
+        );
+        assert_eq!(WaitEvent::default(), WaitEvent::EXTENSION);
+    }
+
+    #[pg_test]
+    fn test_wait_event_during() {
+        let reported = TEST_EVENT.during(reported_wait_event);
+        assert_eq!(reported, TEST_EVENT.info());
+        assert_eq!(reported_wait_event(), 0);
+    }
+}
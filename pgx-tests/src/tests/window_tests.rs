@@ -0,0 +1,115 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_window]
+    fn position_of(window: &PgWindow) -> String {
+        format!(
+            "{} of {}",
+            window.current_position() + 1,
+            window.partition_row_count()
+        )
+    }
+
+    #[pg_window]
+    fn previous(window: &PgWindow, value: WindowArg<i32>) -> Option<i32> {
+        window
+            .arg_in_partition(value, -1, WindowSeek::Current, false)
+            .flatten()
+    }
+
+    #[pg_window]
+    fn weighted_avg(
+        window: &PgWindow,
+        value: WindowArg<f64>,
+        weight: WindowArg<f64>,
+    ) -> Option<f64> {
+        let (mut sum, mut weights) = (0.0, 0.0);
+        for row in 0.. {
+            match (
+                window.arg_in_frame(value, row, WindowSeek::Head, false),
+                window.arg_in_frame(weight, row, WindowSeek::Head, false),
+            ) {
+                (Some(Some(value)), Some(Some(weight))) => {
+                    sum += value * weight;
+                    weights += weight;
+                }
+                (Some(_), Some(_)) => continue,
+                _ => break,
+            }
+        }
+        (weights != 0.0).then(|| sum / weights)
+    }
+
+    #[pg_window]
+    fn running_total(window: &PgWindow, value: WindowArg<i64>) -> i64 {
+        let total = window.partition_local::<i64>() + window.current_arg(value).unwrap_or(0);
+        window.set_partition_local(total);
+        total
+    }
+
+    #[pg_test]
+    fn test_window_positions() {
+        let positions = Spi::get_one::<Vec<String>>(
+            "SELECT array_agg(position ORDER BY n) FROM (
+                SELECT n, tests.position_of() OVER (PARTITION BY n % 2 ORDER BY n) AS position
+                  FROM generate_series(1, 5) n
+            ) positions",
+        )
+        .expect("SQL select failed");
+        assert_eq!(
+            positions,
+            vec!["1 of 3", "1 of 2", "2 of 3", "2 of 2", "3 of 3"]
+        );
+    }
+
+    #[pg_test]
+    fn test_window_arg_in_partition() {
+        let previous = Spi::get_one::<Vec<Option<i32>>>(
+            "SELECT array_agg(previous ORDER BY n) FROM (
+                SELECT n, tests.previous(n) OVER (ORDER BY n) AS previous
+                  FROM unnest(ARRAY[10, 20, 30]) n
+            ) previous",
+        )
+        .expect("SQL select failed");
+        assert_eq!(previous, vec![None, Some(10), Some(20)]);
+    }
+
+    #[pg_test]
+    fn test_window_arg_in_frame() {
+        let averages = Spi::get_one::<Vec<f64>>(
+            "SELECT array_agg(avg ORDER BY day) FROM (
+                SELECT day, tests.weighted_avg(price, volume) OVER (ORDER BY day ROWS 1 PRECEDING) AS avg
+                  FROM unnest(ARRAY[1, 2, 3], ARRAY[10, 20, 40]::float8[], ARRAY[1, 3, 1]::float8[])
+                    AS trades(day, price, volume)
+            ) averages",
+        )
+        .expect("SQL select failed");
+        assert_eq!(averages, vec![10.0, 17.5, 25.0]);
+    }
+
+    #[pg_test]
+    fn test_window_partition_local() {
+        let totals = Spi::get_one::<Vec<i64>>(
+            "SELECT array_agg(total ORDER BY n) FROM (
+                SELECT n, tests.running_total(n) OVER (PARTITION BY n % 2 ORDER BY n) AS total
+                  FROM generate_series(1::bigint, 6) n
+            ) totals",
+        )
+        .expect("SQL select failed");
+        assert_eq!(totals, vec![1, 2, 4, 6, 9, 12]);
+    }
+}
@@ -24,4 +24,57 @@ mod tests {
             info!("TESTMSG: Called on abort")
         });
     }
+
+    #[pg_test]
+    fn test_on_commit_on_abort() {
+        on_commit(|| {
+            info!("TESTMSG: Called on commit");
+        });
+        on_abort(|| {
+            info!("TESTMSG: Called on abort");
+        })
+        .unregister_callback();
+    }
+
+    #[pg_test]
+    fn test_persistent_subxact_callback() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static ABORTED: AtomicUsize = AtomicUsize::new(0);
+        static RELEASED: AtomicUsize = AtomicUsize::new(0);
+
+        let aborted =
+            register_persistent_subxact_callback(PgSubXactCallbackEvent::AbortSub, |_, _| {
+                // callbacks allocate in a context of their own
+                let context = PgMemoryContexts::CurrentMemoryContext.name();
+                assert_eq!(context, "pgx transaction callback");
+                ABORTED.fetch_add(1, Ordering::SeqCst);
+            });
+        let released =
+            register_persistent_subxact_callback(PgSubXactCallbackEvent::CommitSub, |_, _| {
+                RELEASED.fetch_add(1, Ordering::SeqCst);
+            });
+
+        let _: Result<(), ()> = Spi::subtransaction(|_| Err(()));
+        let _: Result<(), ()> = Spi::subtransaction(|_| Err(()));
+        let _: Result<(), ()> = Spi::subtransaction(|_| Ok(()));
+        assert_eq!(ABORTED.load(Ordering::SeqCst), 2);
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 1);
+
+        aborted.unregister_callback();
+        released.unregister_callback();
+        let _: Result<(), ()> = Spi::subtransaction(|_| Err(()));
+        assert_eq!(ABORTED.load(Ordering::SeqCst), 2);
+    }
+
+    #[pg_test]
+    fn test_persistent_xact_callback_unregister() {
+        on_every_commit(|| {
+            info!("TESTMSG: Called on every commit");
+        })
+        .unregister_callback();
+        on_every_abort(|| {
+            info!("TESTMSG: Called on every abort");
+        })
+        .unregister_callback();
+    }
 }
@@ -27,6 +27,7 @@ pub mod operator_common;
 pub mod pg_config;
 pub mod rewriter;
 pub mod sql_entity_graph;
+pub mod test_matrix;
 
 pub use pgx_pg_sys_stub::PgxPgSysStub;
 
@@ -186,11 +187,17 @@ pub enum ExternArgs {
     ParallelSafe,
     ParallelUnsafe,
     ParallelRestricted,
+    Window,
     Error(String),
     Schema(String),
     Name(String),
     Cost(String),
+    Rows(String),
+    Leakproof,
+    SecurityDefiner,
+    Set(String),
     Requires(Vec<PositioningRef>),
+    Support(PositioningRef),
 }
 
 impl core::fmt::Display for ExternArgs {
@@ -204,12 +211,20 @@ impl core::fmt::Display for ExternArgs {
             ExternArgs::ParallelSafe => write!(f, "PARALLEL SAFE"),
             ExternArgs::ParallelUnsafe => write!(f, "PARALLEL UNSAFE"),
             ExternArgs::ParallelRestricted => write!(f, "PARALLEL RESTRICTED"),
+            ExternArgs::Window => write!(f, "WINDOW"),
             ExternArgs::Error(_) => Ok(()),
             ExternArgs::NoGuard => Ok(()),
             ExternArgs::Schema(_) => Ok(()),
             ExternArgs::Name(_) => Ok(()),
             ExternArgs::Cost(cost) => write!(f, "COST {}", cost),
+            ExternArgs::Rows(rows) => write!(f, "ROWS {}", rows),
+            ExternArgs::Leakproof => write!(f, "LEAKPROOF"),
+            ExternArgs::SecurityDefiner => write!(f, "SECURITY DEFINER"),
+            // is written by `PgExternEntity::to_sql()`, as its value mustn't be uppercased
+            ExternArgs::Set(_) => Ok(()),
             ExternArgs::Requires(_) => Ok(()),
+            // needs the support function's schema, so is written by `PgExternEntity::to_sql()`
+            ExternArgs::Support(_) => Ok(()),
         }
     }
 }
@@ -226,6 +241,7 @@ impl ToTokens for ExternArgs {
             ExternArgs::ParallelSafe => tokens.append(format_ident!("ParallelSafe")),
             ExternArgs::ParallelUnsafe => tokens.append(format_ident!("ParallelUnsafe")),
             ExternArgs::ParallelRestricted => tokens.append(format_ident!("ParallelRestricted")),
+            ExternArgs::Window => tokens.append(format_ident!("Window")),
             ExternArgs::Error(_s) => {
                 tokens.append_all(
                     quote! {
@@ -258,6 +274,24 @@ impl ToTokens for ExternArgs {
                     .to_token_stream(),
                 );
             }
+            ExternArgs::Rows(_s) => {
+                tokens.append_all(
+                    quote! {
+                        Rows(String::from("#_s"))
+                    }
+                    .to_token_stream(),
+                );
+            }
+            ExternArgs::Leakproof => tokens.append(format_ident!("Leakproof")),
+            ExternArgs::SecurityDefiner => tokens.append(format_ident!("SecurityDefiner")),
+            ExternArgs::Set(_s) => {
+                tokens.append_all(
+                    quote! {
+                        Set(String::from("#_s"))
+                    }
+                    .to_token_stream(),
+                );
+            }
             ExternArgs::Requires(items) => {
                 tokens.append_all(
                     quote! {
@@ -266,6 +300,14 @@ impl ToTokens for ExternArgs {
                     .to_token_stream(),
                 );
             }
+            ExternArgs::Support(item) => {
+                tokens.append_all(
+                    quote! {
+                        Support(#item)
+                    }
+                    .to_token_stream(),
+                );
+            }
         }
     }
 }
@@ -279,6 +321,9 @@ pub enum FunctionArgs {
 pub enum CategorizedType {
     Iterator(Vec<String>),
     OptionalIterator(Vec<String>),
+    /// A `TableIterator` of the named row type
+    TableIterator(String),
+    OptionalTableIterator(String),
     Tuple(Vec<String>),
     Default,
 }
@@ -305,6 +350,7 @@ pub fn parse_extern_attributes(attr: TokenStream) -> HashSet<ExternArgs> {
                     "parallel_safe" => args.insert(ExternArgs::ParallelSafe),
                     "parallel_unsafe" => args.insert(ExternArgs::ParallelUnsafe),
                     "parallel_restricted" => args.insert(ExternArgs::ParallelRestricted),
+                    "window" => args.insert(ExternArgs::Window),
                     "error" => {
                         let _punc = itr.next().unwrap();
                         let literal = itr.next().unwrap();
@@ -376,6 +422,9 @@ pub fn categorize_type(ty: &Type) -> CategorizedType {
                                     CategorizedType::Iterator(i) => {
                                         CategorizedType::OptionalIterator(i)
                                     }
+                                    CategorizedType::TableIterator(row) => {
+                                        CategorizedType::OptionalTableIterator(row)
+                                    }
 
                                     _ => result,
                                 };
@@ -389,6 +438,16 @@ pub fn categorize_type(ty: &Type) -> CategorizedType {
                         }
                     }
                 }
+                if segment_ident == "TableIterator" {
+                    if let PathArguments::AngleBracketed(a) = &segment.arguments {
+                        for arg in &a.args {
+                            if let GenericArgument::Type(row) = arg {
+                                return CategorizedType::TableIterator(quote! {#row}.to_string());
+                            }
+                        }
+                    }
+                    break;
+                }
                 if segment_ident == "Box" {
                     match &segment.arguments {
                         PathArguments::AngleBracketed(a) => match a.args.first().unwrap() {
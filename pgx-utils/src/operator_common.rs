@@ -9,6 +9,8 @@ Use of this source code is governed by the MIT license that can be found in the
 use proc_macro2::Ident;
 use quote::quote;
 
+use crate::sql_entity_graph::CrossType;
+
 pub fn eq(type_name: &Ident) -> proc_macro2::TokenStream {
     let pg_name = Ident::new(
         &format!("{}_eq", type_name).to_lowercase(),
@@ -151,3 +153,139 @@ pub fn hash(type_name: &Ident) -> proc_macro2::TokenStream {
         }
     }
 }
+
+fn cross_fn_name(type_name: &Ident, op: &str, cross: &CrossType) -> Ident {
+    Ident::new(
+        &format!("{}_{}_{}", type_name, op, cross.ident()).to_lowercase(),
+        type_name.span(),
+    )
+}
+
+pub fn eq_cross(type_name: &Ident, cross: &CrossType) -> proc_macro2::TokenStream {
+    let pg_name = cross_fn_name(type_name, "eq", cross);
+    let cross_ty = &cross.ty;
+    quote! {
+        #[allow(non_snake_case)]
+        #[pg_operator(immutable, parallel_safe)]
+        #[opname(=)]
+        #[negator(<>)]
+        #[restrict(eqsel)]
+        #[join(eqjoinsel)]
+        #[merges]
+        #[hashes]
+        fn #pg_name(left: #type_name, right: #cross_ty) -> bool {
+            left == right
+        }
+    }
+}
+
+pub fn ne_cross(type_name: &Ident, cross: &CrossType) -> proc_macro2::TokenStream {
+    let pg_name = cross_fn_name(type_name, "ne", cross);
+    let cross_ty = &cross.ty;
+    quote! {
+        #[allow(non_snake_case)]
+        #[pg_operator(immutable, parallel_safe)]
+        #[opname(<>)]
+        #[negator(=)]
+        #[restrict(neqsel)]
+        #[join(neqjoinsel)]
+        fn #pg_name(left: #type_name, right: #cross_ty) -> bool {
+            left != right
+        }
+    }
+}
+
+pub fn lt_cross(type_name: &Ident, cross: &CrossType) -> proc_macro2::TokenStream {
+    let pg_name = cross_fn_name(type_name, "lt", cross);
+    let cross_ty = &cross.ty;
+    quote! {
+        #[allow(non_snake_case)]
+        #[pg_operator(immutable, parallel_safe)]
+        #[opname(<)]
+        #[negator(>=)]
+        #[restrict(scalarltsel)]
+        #[join(scalarltjoinsel)]
+        fn #pg_name(left: #type_name, right: #cross_ty) -> bool {
+            left < right
+        }
+    }
+}
+
+pub fn gt_cross(type_name: &Ident, cross: &CrossType) -> proc_macro2::TokenStream {
+    let pg_name = cross_fn_name(type_name, "gt", cross);
+    let cross_ty = &cross.ty;
+    quote! {
+        #[allow(non_snake_case)]
+        #[pg_operator(immutable, parallel_safe)]
+        #[opname(>)]
+        #[negator(<=)]
+        #[restrict(scalargtsel)]
+        #[join(scalargtjoinsel)]
+        fn #pg_name(left: #type_name, right: #cross_ty) -> bool {
+            left > right
+        }
+    }
+}
+
+pub fn le_cross(type_name: &Ident, cross: &CrossType) -> proc_macro2::TokenStream {
+    let pg_name = cross_fn_name(type_name, "le", cross);
+    let cross_ty = &cross.ty;
+    quote! {
+        #[allow(non_snake_case)]
+        #[pg_operator(immutable, parallel_safe)]
+        #[opname(<=)]
+        #[negator(>)]
+        #[restrict(scalarlesel)]
+        #[join(scalarlejoinsel)]
+        fn #pg_name(left: #type_name, right: #cross_ty) -> bool {
+            left <= right
+        }
+    }
+}
+
+pub fn ge_cross(type_name: &Ident, cross: &CrossType) -> proc_macro2::TokenStream {
+    let pg_name = cross_fn_name(type_name, "ge", cross);
+    let cross_ty = &cross.ty;
+    quote! {
+        #[allow(non_snake_case)]
+        #[pg_operator(immutable, parallel_safe)]
+        #[opname(>=)]
+        #[negator(<)]
+        #[restrict(scalargesel)]
+        #[join(scalargejoinsel)]
+        fn #pg_name(left: #type_name, right: #cross_ty) -> bool {
+            left >= right
+        }
+    }
+}
+
+pub fn cmp_cross(type_name: &Ident, cross: &CrossType) -> proc_macro2::TokenStream {
+    let pg_name = cross_fn_name(type_name, "cmp", cross);
+    let cross_ty = &cross.ty;
+    quote! {
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #pg_name(left: #type_name, right: #cross_ty) -> i32 {
+            match left.partial_cmp(&right) {
+                Some(ordering) => ordering as i32,
+                None => pgx::error!(
+                    "`{}` and `{}` values are not comparable",
+                    stringify!(#type_name),
+                    stringify!(#cross_ty)
+                ),
+            }
+        }
+    }
+}
+
+pub fn hash_cross(type_name: &Ident, cross: &CrossType) -> proc_macro2::TokenStream {
+    let pg_name = cross_fn_name(type_name, "hash", cross);
+    let cross_ty = &cross.ty;
+    quote! {
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #pg_name(value: #cross_ty) -> i32 {
+            pgx::misc::pgx_seahash(&value) as i32
+        }
+    }
+}
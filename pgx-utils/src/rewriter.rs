@@ -15,11 +15,10 @@ use proc_macro2::{Ident, Span};
 use quote::{quote, quote_spanned, ToTokens};
 use std::ops::Deref;
 use std::str::FromStr;
-use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
 use syn::{
     FnArg, ForeignItem, ForeignItemFn, Generics, ItemFn, ItemForeignMod, Pat, ReturnType,
-    Signature, Token, Type, Visibility,
+    Signature, Type, Visibility,
 };
 
 pub struct PgGuardRewriter();
@@ -122,9 +121,19 @@ impl PgGuardRewriter {
                 true,
             ),
 
-            CategorizedType::Tuple(_types) => (
-                PgGuardRewriter::impl_tuple_udf(func, entity_submission.clone()),
-                false,
+            CategorizedType::Tuple(types) => (
+                PgGuardRewriter::impl_tuple_udf(
+                    types,
+                    func_span,
+                    prolog,
+                    vis,
+                    func_name_wrapper,
+                    generics,
+                    func_call,
+                    entity_submission,
+                    return_type_is_option(&func.sig.output),
+                ),
+                true,
             ),
 
             CategorizedType::Iterator(types) if types.len() == 1 => (
@@ -186,6 +195,36 @@ impl PgGuardRewriter {
                 ),
                 true,
             ),
+
+            CategorizedType::TableIterator(row_type) => (
+                PgGuardRewriter::impl_table_row_srf(
+                    row_type,
+                    func_span,
+                    prolog,
+                    vis,
+                    func_name_wrapper,
+                    generics,
+                    func_call,
+                    entity_submission,
+                    false,
+                ),
+                true,
+            ),
+
+            CategorizedType::OptionalTableIterator(row_type) => (
+                PgGuardRewriter::impl_table_row_srf(
+                    row_type,
+                    func_span,
+                    prolog,
+                    vis,
+                    func_name_wrapper,
+                    generics,
+                    func_call,
+                    entity_submission,
+                    true,
+                ),
+                true,
+            ),
         }
     }
 
@@ -223,35 +262,70 @@ impl PgGuardRewriter {
     }
 
     fn impl_tuple_udf(
-        mut func: ItemFn,
+        types: Vec<String>,
+        func_span: Span,
+        prolog: proc_macro2::TokenStream,
+        vis: Visibility,
+        func_name_wrapper: Ident,
+        generics: &Generics,
+        func_call: proc_macro2::TokenStream,
         entity_submission: Option<&PgExtern>,
+        optional: bool,
     ) -> proc_macro2::TokenStream {
-        let func_span = func.span();
-        let return_type = func.sig.output;
-        let return_type = format!("{}", quote! {#return_type});
-        let return_type =
-            proc_macro2::TokenStream::from_str(return_type.trim_start_matches("->")).unwrap();
-        let return_type = quote! {impl std::iter::Iterator<Item = #return_type>};
-        let attrs = entity_submission
-            .unwrap()
-            .extern_attrs()
-            .iter()
-            .collect::<Punctuated<_, Token![,]>>();
-
-        func.sig.output = ReturnType::Default;
-        let sig = func.sig;
-        let body = func.block;
-
-        // We do **not** put an entity submission here as there still exists a `pg_extern` attribute.
-        //
-        // This is because we quietely rewrite the function signature to `Iterator<Item = T>` and
-        // rely on #[pg_extern] being called again during compilation.  It is important that we
-        // include the original #[pg_extern(<attributes>)] in the generated code.
+        let numtypes = types.len();
+        let i = (0..numtypes).map(syn::Index::from);
+        let create_heap_tuple = quote! {
+            let mut datums: [usize; #numtypes] = [0; #numtypes];
+            let mut nulls: [bool; #numtypes] = [false; #numtypes];
+
+            #(
+                let datum = result.#i.into_datum();
+                match datum {
+                    Some(datum) => { datums[#i] = datum as usize; },
+                    None => { nulls[#i] = true; }
+                }
+            )*
+
+            let heap_tuple = pgx::pg_sys::heap_form_tuple(tupdesc, datums.as_mut_ptr(), nulls.as_mut_ptr());
+        };
+
+        let result_handler = if optional {
+            quote! {
+                let result = match { #func_call result } {
+                    Some(result) => result,
+                    None => return pgx::pg_return_null(fcinfo),
+                };
+            }
+        } else {
+            quote! {
+                let result = { #func_call result };
+            }
+        };
+        let sql_graph_entity_submission = entity_submission.cloned().into_iter();
+
+        // A single row is returned through the function's `OUT` arguments, so the tuple
+        // descriptor comes from the function's own result type.
         quote_spanned! {func_span=>
-            #[pg_extern(#attrs)]
-            #sig -> #return_type {
-                Some(#body).into_iter()
+            #prolog
+            #[pg_guard]
+            #vis unsafe extern "C" fn #func_name_wrapper #generics(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+
+                #result_handler
+
+                let mut tupdesc: *mut pgx::pg_sys::TupleDescData = std::ptr::null_mut();
+
+                /* Build a tuple descriptor for our result type */
+                if pgx::pg_sys::get_call_result_type(fcinfo, std::ptr::null_mut(), &mut tupdesc) != pgx::pg_sys::TypeFuncClass_TYPEFUNC_COMPOSITE {
+                    pgx::error!("return type must be a row type");
+                }
+                let tupdesc = pgx::pg_sys::BlessTupleDesc(tupdesc);
+
+                #create_heap_tuple
+
+                pgx::heap_tuple_get_datum(heap_tuple)
             }
+
+            #(#sql_graph_entity_submission)*
         }
     }
 
@@ -377,6 +451,76 @@ impl PgGuardRewriter {
         let mut generic_type = syn::parse2::<syn::Type>(generic_type).unwrap();
         crate::anonymonize_lifetimes(&mut generic_type);
 
+        PgGuardRewriter::impl_srf_of_rows(
+            generic_type,
+            create_heap_tuple,
+            func_span,
+            prolog,
+            vis,
+            func_name_wrapper,
+            generics,
+            func_call,
+            entity_submission,
+            optional,
+        )
+    }
+
+    fn impl_table_row_srf(
+        row_type: String,
+        func_span: Span,
+        prolog: proc_macro2::TokenStream,
+        vis: Visibility,
+        func_name_wrapper: Ident,
+        generics: &Generics,
+        func_call: proc_macro2::TokenStream,
+        entity_submission: Option<&PgExtern>,
+        optional: bool,
+    ) -> proc_macro2::TokenStream {
+        let create_heap_tuple = quote! {
+            let (mut datums, mut nulls): (Vec<usize>, Vec<bool>) = pgx::TableRow::into_datums(result)
+                .into_iter()
+                .map(|datum| match datum {
+                    Some(datum) => (datum as usize, false),
+                    None => (0, true),
+                })
+                .unzip();
+
+            let heap_tuple = pgx::pg_sys::heap_form_tuple(funcctx.tuple_desc, datums.as_mut_ptr(), nulls.as_mut_ptr());
+        };
+
+        let generic_type = proc_macro2::TokenStream::from_str(&row_type).unwrap();
+        let mut generic_type = syn::parse2::<syn::Type>(generic_type).unwrap();
+        crate::anonymonize_lifetimes(&mut generic_type);
+
+        PgGuardRewriter::impl_srf_of_rows(
+            generic_type,
+            create_heap_tuple,
+            func_span,
+            prolog,
+            vis,
+            func_name_wrapper,
+            generics,
+            func_call,
+            entity_submission,
+            optional,
+        )
+    }
+
+    /// A set-returning function whose items are turned into rows of its `RETURNS TABLE (...)` by
+    /// `create_heap_tuple`
+    #[allow(clippy::too_many_arguments)]
+    fn impl_srf_of_rows(
+        generic_type: syn::Type,
+        create_heap_tuple: proc_macro2::TokenStream,
+        func_span: Span,
+        prolog: proc_macro2::TokenStream,
+        vis: Visibility,
+        func_name_wrapper: Ident,
+        generics: &Generics,
+        func_call: proc_macro2::TokenStream,
+        entity_submission: Option<&PgExtern>,
+        optional: bool,
+    ) -> proc_macro2::TokenStream {
         let result_handler = if optional {
             quote! {
                 let result = match pgx::PgMemoryContexts::For(funcctx.multi_call_memory_ctx).switch_to(|_| { #func_call result }) {
@@ -764,6 +908,21 @@ impl FunctionSignatureRewriter {
     }
 }
 
+fn return_type_is_option(output: &ReturnType) -> bool {
+    match output {
+        ReturnType::Type(_, ty) => match ty.deref() {
+            Type::Path(type_path) => type_path
+                .path
+                .segments
+                .last()
+                .map(|segment| segment.ident == "Option")
+                .unwrap_or(false),
+            _ => false,
+        },
+        ReturnType::Default => false,
+    }
+}
+
 fn type_matches(ty: &Type, pattern: &str) -> bool {
     let type_string = format!("{}", quote! {#ty});
     type_string.starts_with(pattern)
@@ -213,7 +213,7 @@ impl ToSql for PgAggregateEntity {
         }
         if let Some(value) = self.deserialfunc {
             optional_attributes.push((
-                format!("\tDESERIALFUNC ={} \"{}\"", schema, value),
+                format!("\tDESERIALFUNC = {}\"{}\"", schema, value),
                 format!("/* {}::deserial */", self.full_path),
             ));
         }
@@ -238,7 +238,7 @@ impl ToSql for PgAggregateEntity {
         if let Some(value) = self.mfinalfunc {
             optional_attributes.push((
                 format!("\tMFINALFUNC = {}\"{}\"", schema, value),
-                format!("/* {}::moving_state_finalize */", self.full_path),
+                format!("/* {}::moving_finalize */", self.full_path),
             ));
         }
         if let Some(value) = self.mfinalfunc_modify {
@@ -418,7 +418,7 @@ impl ToSql for PgAggregateEntity {
                     );
                     args.push(buf);
                 }
-                "\n".to_string() + &args.join("\n,") + "\n"
+                "\n".to_string() + &args.join("\n") + "\n"
             } else {
                 String::default()
             },
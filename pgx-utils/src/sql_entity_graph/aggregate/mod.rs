@@ -68,6 +68,7 @@ const ARG_NAMES: [&str; 32] = [
 #[derive(Debug, Clone)]
 pub struct PgAggregate {
     item_impl: ItemImpl,
+    simple_item_impl: Option<ItemImpl>,
     name: Expr,
     pg_externs: Vec<ItemFn>,
     // Note these should not be considered *writable*, they're snapshots from construction.
@@ -96,6 +97,27 @@ pub struct PgAggregate {
 
 impl PgAggregate {
     pub fn new(mut item_impl: ItemImpl) -> Result<Self, syn::Error> {
+        // TODO: Consider checking the path if there is more than one segment to make sure it's pgx.
+        let trait_ident = item_impl
+            .trait_
+            .as_ref()
+            .and_then(|(_, path, _)| path.segments.last())
+            .map(|last| last.ident.clone());
+        let mut simple_item_impl = None;
+        match trait_ident {
+            Some(ident) if ident == "SimpleAggregate" => {
+                // A `SimpleAggregate` is made with the `Aggregate` it's equivalent to.
+                let (simple, aggregate) = simple_aggregate_impls(item_impl)?;
+                simple_item_impl = Some(simple);
+                item_impl = aggregate;
+            }
+            Some(ident) if ident != "Aggregate" => return Err(syn::Error::new(
+                ident.span(),
+                "`#[pg_aggregate]` only works with the `Aggregate` and `SimpleAggregate` traits.",
+            )),
+            _ => (),
+        }
+
         let to_sql_config =
             ToSqlConfig::from_attributes(item_impl.attrs.as_slice())?.unwrap_or_default();
         let target_path = get_target_path(&item_impl)?;
@@ -109,18 +131,6 @@ impl PgAggregate {
         // and mutate the actual one.
         let item_impl_snapshot = item_impl.clone();
 
-        if let Some((_, ref path, _)) = item_impl.trait_ {
-            // TODO: Consider checking the path if there is more than one segment to make sure it's pgx.
-            if let Some(last) = path.segments.last() {
-                if last.ident.to_string() != "Aggregate" {
-                    return Err(syn::Error::new(
-                        last.ident.span(),
-                        "`#[pg_aggregate]` only works with the `Aggregate` trait.",
-                    ));
-                }
-            }
-        }
-
         let name = match get_impl_const_by_name(&item_impl_snapshot, "NAME") {
             Some(item_const) => match item_const.expr {
                 syn::Expr::Lit(ref expr) => {
@@ -388,17 +398,17 @@ impl PgAggregate {
             pg_externs.push(parse_quote! {
                 #[allow(non_snake_case, clippy::too_many_arguments)]
                 #pg_extern_attr
-                fn #fn_name(this: #type_state_without_self, buf: Vec<u8>, internal: pgx::PgBox<#type_state_without_self>, fcinfo: pgx::pg_sys::FunctionCallInfo) -> pgx::PgBox<#type_state_without_self> {
+                fn #fn_name(buf: Vec<u8>, _internal: pgx::Internal, fcinfo: pgx::pg_sys::FunctionCallInfo) -> #type_state_without_self {
                     <#target_path as pgx::Aggregate>::in_memory_context(
                         fcinfo,
-                        move |_context| <#target_path as pgx::Aggregate>::deserial(this, buf, internal, fcinfo)
+                        move |_context| <#target_path as pgx::Aggregate>::deserial(buf, fcinfo)
                     )
                 }
             });
             Some(fn_name)
         } else {
             item_impl.items.push(parse_quote! {
-                fn deserial(current: #type_state_without_self, _buf: Vec<u8>, _internal: pgx::PgBox<#type_state_without_self>, _fcinfo: pgx::pg_sys::FunctionCallInfo) -> pgx::PgBox<#type_state_without_self> {
+                fn deserial(_buf: Vec<u8>, _fcinfo: pgx::pg_sys::FunctionCallInfo) -> #type_state_without_self {
                     unimplemented!("Call to deserial on an aggregate which does not support it.")
                 }
             });
@@ -462,17 +472,31 @@ impl PgAggregate {
                 found.sig.ident.span(),
             );
             let pg_extern_attr = pg_extern_attr(found);
+            let args = type_args_value
+                .found
+                .iter()
+                .map(|x| x.variadic_ty.clone().unwrap_or(x.ty.clone()))
+                .collect::<Vec<_>>();
+            let args_with_names = args.iter().zip(ARG_NAMES.iter()).map(|(arg, name)| {
+                let name_ident = Ident::new(name, Span::call_site());
+                quote! {
+                    #name_ident: #arg
+                }
+            });
+            let arg_names = ARG_NAMES[0..args.len()]
+                .iter()
+                .map(|name| Ident::new(name, fn_state.span()));
             pg_externs.push(parse_quote! {
                 #[allow(non_snake_case, clippy::too_many_arguments)]
                 #pg_extern_attr
                 fn #fn_name(
                     mstate: <#target_path as pgx::Aggregate>::MovingState,
-                    v: <#target_path as pgx::Aggregate>::Args,
+                    #(#args_with_names),*,
                     fcinfo: pgx::pg_sys::FunctionCallInfo,
                 ) -> <#target_path as pgx::Aggregate>::MovingState {
                     <#target_path as pgx::Aggregate>::in_memory_context(
                         fcinfo,
-                        move |_context| <#target_path as pgx::Aggregate>::moving_state_inverse(mstate, v, fcinfo)
+                        move |_context| <#target_path as pgx::Aggregate>::moving_state_inverse(mstate, (#(#arg_names),*), fcinfo)
                     )
                 }
             });
@@ -523,8 +547,39 @@ impl PgAggregate {
             None
         };
 
+        let const_ordered_set = get_impl_const_by_name(&item_impl_snapshot, "ORDERED_SET")
+            .and_then(get_const_litbool)
+            .unwrap_or(false);
+        let hypothetical = if let Some(value) =
+            get_impl_const_by_name(&item_impl_snapshot, "HYPOTHETICAL")
+        {
+            match &value.expr {
+                syn::Expr::Lit(expr_lit) => match &expr_lit.lit {
+                    syn::Lit::Bool(lit) => lit.value,
+                    _ => return Err(syn::Error::new(value.span(), "`#[pg_aggregate]` required the `HYPOTHETICAL` value to be a literal boolean.")),
+                },
+                _ => return Err(syn::Error::new(value.span(), "`#[pg_aggregate]` required the `HYPOTHETICAL` value to be a literal boolean.")),
+            }
+        } else {
+            false
+        };
+        if hypothetical {
+            // The hypothetical row is passed as the last direct arguments, one for each argument.
+            let direct_args_len = type_ordered_set_args_value
+                .as_ref()
+                .map(|direct_args| direct_args.found.len())
+                .unwrap_or(0);
+            if !const_ordered_set || direct_args_len < type_args_value.found.len() {
+                return Err(syn::Error::new(
+                    item_impl.span(),
+                    "`#[pg_aggregate]` requires `HYPOTHETICAL` aggregates to be `ORDERED_SET`, with an `OrderedSetArgs` for each of the `Args`.",
+                ));
+            }
+        }
+
         Ok(Self {
             item_impl,
+            simple_item_impl,
             pg_externs,
             name,
             type_args: type_args_value,
@@ -545,9 +600,7 @@ impl PgAggregate {
                 "INITIAL_CONDITION",
             )
             .and_then(get_const_litstr),
-            const_ordered_set: get_impl_const_by_name(&item_impl_snapshot, "ORDERED_SET")
-                .and_then(get_const_litbool)
-                .unwrap_or(false),
+            const_ordered_set,
             const_sort_operator: get_impl_const_by_name(&item_impl_snapshot, "SORT_OPERATOR")
                 .and_then(get_const_litstr),
             const_moving_intial_condition: get_impl_const_by_name(
@@ -563,19 +616,7 @@ impl PgAggregate {
             fn_moving_state: fn_moving_state_name,
             fn_moving_state_inverse: fn_moving_state_inverse_name,
             fn_moving_finalize: fn_moving_finalize_name,
-            hypothetical: if let Some(value) =
-                get_impl_const_by_name(&item_impl_snapshot, "HYPOTHETICAL")
-            {
-                match &value.expr {
-                    syn::Expr::Lit(expr_lit) => match &expr_lit.lit {
-                        syn::Lit::Bool(lit) => lit.value,
-                        _ => return Err(syn::Error::new(value.span(), "`#[pg_aggregate]` required the `HYPOTHETICAL` value to be a literal boolean.")),
-                    },
-                    _ => return Err(syn::Error::new(value.span(), "`#[pg_aggregate]` required the `HYPOTHETICAL` value to be a literal boolean.")),
-                }
-            } else {
-                false
-            },
+            hypothetical,
             to_sql_config,
         })
     }
@@ -676,8 +717,11 @@ impl ToTokens for PgAggregate {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
         let entity_fn = self.entity_tokens();
         let impl_item = &self.item_impl;
+        let simple_impl_item = self.simple_item_impl.iter();
         let pg_externs = self.pg_externs.iter();
         let inv = quote! {
+            #(#simple_impl_item)*
+
             #impl_item
 
             #(#pg_externs)*
@@ -688,6 +732,155 @@ impl ToTokens for PgAggregate {
     }
 }
 
+/// The `SimpleAggregate` implementation `item_impl`, with its optional items filled in, and the
+/// `Aggregate` implementation it's equivalent to.
+///
+/// The `Aggregate`'s state is an `Option` of the `SimpleAggregate`'s, which starts out as `NULL`, and
+/// is its `Default` until a row is aggregated, so no `INITIAL_CONDITION` is needed. Arguments which
+/// aren't already `Option`s are made `Option`s, and rows where any of them are `NULL` are skipped,
+/// as they would be by a `STRICT` state function.
+fn simple_aggregate_impls(mut item_impl: ItemImpl) -> Result<(ItemImpl, ItemImpl), syn::Error> {
+    let target_path = get_target_path(&item_impl)?;
+    let target_ident = get_target_ident(&target_path)?;
+    let snapshot = item_impl.clone();
+
+    let name = match get_impl_const_by_name(&snapshot, "NAME") {
+        Some(item_const) => Some(item_const.clone()),
+        None => {
+            item_impl.items.push(parse_quote! {
+                const NAME: &'static str = stringify!(Self);
+            });
+            None
+        }
+    };
+
+    let mut type_state = match get_impl_type_by_name(&snapshot, "State") {
+        Some(found) => found.ty.clone(),
+        None => {
+            item_impl.items.push(parse_quote! {
+                type State = Self;
+            });
+            parse_quote!(Self)
+        }
+    };
+    remap_self_to_target(&mut type_state, &target_ident);
+
+    let fn_state = get_impl_func_by_name(&snapshot, "state").ok_or_else(|| {
+        syn::Error::new(
+            snapshot.span(),
+            "SimpleAggregate implementation must include state function.",
+        )
+    })?;
+    let fn_state_attrs = &fn_state.attrs;
+
+    let (mut type_finalize, fn_finish_attrs) = match get_impl_func_by_name(&snapshot, "finish") {
+        Some(found) => {
+            let type_finalize = get_impl_type_by_name(&snapshot, "Finalize").ok_or_else(|| {
+                syn::Error::new(
+                    found.span(),
+                    "`#[pg_aggregate]` requires the `Finalize` type defined with a finish function.",
+                )
+            })?;
+            (type_finalize.ty.clone(), found.attrs.clone())
+        }
+        None => {
+            item_impl.items.push(parse_quote! {
+                type Finalize = Self::State;
+            });
+            item_impl.items.push(parse_quote! {
+                fn finish(current: Self::State) -> Self::Finalize {
+                    current
+                }
+            });
+            (type_state.clone(), Vec::default())
+        }
+    };
+    remap_self_to_target(&mut type_finalize, &target_ident);
+
+    let type_args = get_impl_type_by_name(&snapshot, "Args").ok_or_else(|| {
+        syn::Error::new(
+            snapshot.span(),
+            "`#[pg_aggregate]` requires the `Args` type defined.",
+        )
+    })?;
+    let type_args_value = MaybeNamedVariadicTypeList::new(type_args.ty.clone())?;
+    let mut optional_args = Vec::default();
+    let mut arg_names = Vec::default();
+    let mut skip_nulls = Vec::default();
+    for (arg, arg_name) in type_args_value.found.iter().zip(ARG_NAMES.iter()) {
+        if arg.variadic_ty.is_some() {
+            return Err(syn::Error::new(
+                type_args.ty.span(),
+                "`#[pg_aggregate]` doesn't support variadic arguments for `SimpleAggregate`s.",
+            ));
+        }
+        let arg_name = Ident::new(arg_name, Span::mixed_site());
+        let ty = &arg.ty;
+        let optional_ty: syn::Type = if is_option(ty) {
+            ty.clone()
+        } else {
+            skip_nulls.push(quote! {
+                let #arg_name = match #arg_name {
+                    Some(#arg_name) => #arg_name,
+                    None => return current,
+                };
+            });
+            parse_quote!(Option<#ty>)
+        };
+        optional_args.push(match &arg.name {
+            Some(name) => {
+                let name = Ident::new(name, ty.span());
+                quote! { pgx::name!(#name, #optional_ty) }
+            }
+            None => optional_ty.to_token_stream(),
+        });
+        arg_names.push(arg_name);
+    }
+    let (type_optional_args, args): (TokenStream2, TokenStream2) = match arg_names.len() {
+        1 => (optional_args[0].clone(), arg_names[0].to_token_stream()),
+        _ => (
+            quote! { (#(#optional_args),*) },
+            quote! { (#(#arg_names),*) },
+        ),
+    };
+
+    let attrs = std::mem::take(&mut item_impl.attrs);
+    let name = name.iter();
+    let aggregate_impl = parse_quote! {
+        #(#attrs)*
+        impl pgx::Aggregate for #target_path {
+            #(#name)*
+            type State = Option<#type_state>;
+            type Args = #type_optional_args;
+            type Finalize = #type_finalize;
+
+            #(#fn_state_attrs)*
+            fn state(current: Self::State, #args: Self::Args, _fcinfo: pgx::pg_sys::FunctionCallInfo) -> Self::State {
+                #(#skip_nulls)*
+                Some(<#target_path as pgx::SimpleAggregate>::state(current.unwrap_or_default(), #args))
+            }
+
+            #(#fn_finish_attrs)*
+            fn finalize(current: Self::State, _direct_args: Self::OrderedSetArgs, _fcinfo: pgx::pg_sys::FunctionCallInfo) -> Self::Finalize {
+                <#target_path as pgx::SimpleAggregate>::finish(current.unwrap_or_default())
+            }
+        }
+    };
+    Ok((item_impl, aggregate_impl))
+}
+
+fn is_option(ty: &syn::Type) -> bool {
+    match ty {
+        Type::Path(ty_path) => ty_path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident == "Option")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
 fn get_target_ident(path: &Path) -> Result<Ident, syn::Error> {
     let last = path.segments.last().ok_or_else(|| {
         syn::Error::new(
@@ -938,11 +1131,13 @@ mod tests {
             impl Aggregate for DemoAgg {
                 type State = PgVarlena<Self>;
                 type Args = i32;
-                type OrderBy = i32;
+                type OrderedSetArgs = i32;
                 type MovingState = i32;
 
                 const NAME: &'static str = "DEMO";
 
+                const ORDERED_SET: bool = true;
+
                 const PARALLEL: Option<ParallelOption> = Some(ParallelOption::Safe);
                 const FINALIZE_MODIFY: Option<FinalizeModify> = Some(FinalizeModify::ReadWrite);
                 const MOVING_FINALIZE_MODIFY: Option<FinalizeModify> = Some(FinalizeModify::ReadWrite);
@@ -966,7 +1161,7 @@ mod tests {
                     todo!()
                 }
 
-                fn deserial(current: Self::State, _buf: Vec<u8>, _internal: PgBox<Self>) -> PgBox<Self> {
+                fn deserial(_buf: Vec<u8>) -> Self::State {
                     todo!()
                 }
 
@@ -997,6 +1192,85 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn agg_hypothetical_without_direct_args() -> Result<()> {
+        // This is not valid as the hypothetical row needs a direct argument for each argument.
+        let tokens: ItemImpl = parse_quote! {
+            #[pg_aggregate]
+            impl Aggregate for DemoAgg {
+                type State = PgVarlena<Self>;
+                type Args = (i32, i32);
+                type OrderedSetArgs = i32;
+                const NAME: &'static str = "DEMO";
+                const ORDERED_SET: bool = true;
+                const HYPOTHETICAL: bool = true;
+
+                fn state(current: Self::State, v: Self::Args) -> Self::State {
+                    todo!()
+                }
+            }
+        };
+        let agg = PgAggregate::new(tokens);
+        assert!(agg.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn agg_simple() -> Result<()> {
+        let tokens: ItemImpl = parse_quote! {
+            #[pg_aggregate]
+            impl SimpleAggregate for DemoSum {
+                type State = i64;
+                type Args = (name!(value, i32), name!(weight, Option<i32>));
+
+                fn state(current: Self::State, (value, weight): Self::Args) -> Self::State {
+                    todo!()
+                }
+            }
+        };
+        // It should not error, as it's valid.
+        let agg = PgAggregate::new(tokens);
+        assert!(agg.is_ok());
+        // It should create 2 externs, the state and the finalize.
+        let agg = agg.unwrap();
+        assert_eq!(agg.pg_externs.len(), 2);
+        assert_eq!(agg.pg_externs[0].sig.ident.to_string(), "demo_sum_state");
+        assert_eq!(agg.pg_externs[1].sig.ident.to_string(), "demo_sum_finalize");
+        // The state is `NULL` until a row is aggregated, and so are the arguments.
+        let stype = &agg.type_stype.ty;
+        assert_eq!(quote::quote!(#stype).to_string(), "Option < i64 >");
+        assert_eq!(agg.type_args.found[0].name.as_deref(), Some("value"));
+        let value_ty = &agg.type_args.found[0].ty;
+        assert_eq!(quote::quote!(#value_ty).to_string(), "Option < i32 >");
+        let weight_ty = &agg.type_args.found[1].ty;
+        assert_eq!(quote::quote!(#weight_ty).to_string(), "Option < i32 >");
+        // The `SimpleAggregate` is kept, with its `finish` filled in.
+        let simple = agg.simple_item_impl.as_ref().unwrap();
+        assert!(super::get_impl_func_by_name(simple, "finish").is_some());
+        // It should be possible to generate entity tokens.
+        let _ = agg.entity_tokens();
+        Ok(())
+    }
+
+    #[test]
+    fn agg_simple_variadic() -> Result<()> {
+        // This is not valid as a row can't be skipped for some `NULL` variadic argument.
+        let tokens: ItemImpl = parse_quote! {
+            #[pg_aggregate]
+            impl SimpleAggregate for DemoSum {
+                type State = i64;
+                type Args = variadic!(i32);
+
+                fn state(current: Self::State, values: Self::Args) -> Self::State {
+                    todo!()
+                }
+            }
+        };
+        let agg = PgAggregate::new(tokens);
+        assert!(agg.is_err());
+        Ok(())
+    }
+
     #[test]
     fn agg_missing_required() -> Result<()> {
         // This is not valid as it is missing required types/consts.
@@ -0,0 +1,105 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use eyre::eyre;
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::{quote, ToTokens, TokenStreamExt};
+use syn::{spanned::Spanned, Attribute, Meta, NestedMeta};
+
+use crate::sql_entity_graph::{
+    pgx_attribute::{PgxArg, PgxAttribute},
+    pgx_sql::PgxSql,
+};
+
+/// A parsed `#[pgx(cross_type(..))]` argument of `#[derive(PostgresEq)]`, `#[derive(PostgresOrd)]`,
+/// or `#[derive(PostgresHash)]`.
+///
+/// Each cross type causes operators between the deriving type and the cross type to be
+/// generated, and added to the deriving type's operator family.
+#[derive(Debug, Clone)]
+pub struct CrossType {
+    pub ty: syn::Path,
+}
+
+impl CrossType {
+    /// The identifier used to suffix generated function names, eg `mytype_lt_i32`.
+    pub fn ident(&self) -> Ident {
+        self.ty
+            .segments
+            .last()
+            .map(|segment| segment.ident.clone())
+            .expect("cross_type path had no segments")
+    }
+
+    /// Collect all `cross_type(..)` entries from any `#[pgx(..)]` attributes.
+    pub fn from_attributes(attrs: &[Attribute]) -> Result<Vec<Self>, syn::Error> {
+        let mut found = Vec::new();
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("pgx")) {
+            let attr = attr.parse_args::<PgxAttribute>()?;
+            for arg in attr.args {
+                if let PgxArg::List(list) = arg {
+                    if !list.path.is_ident("cross_type") {
+                        continue;
+                    }
+                    for nested in list.nested.iter() {
+                        match nested {
+                            NestedMeta::Meta(Meta::Path(path)) => found.push(Self {
+                                ty: path.clone(),
+                            }),
+                            other => {
+                                return Err(syn::Error::new(
+                                    other.span(),
+                                    "expected `#[pgx(cross_type(Type, ..))]`, where `Type` is a path to a type",
+                                ))
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(found)
+    }
+}
+
+impl ToTokens for CrossType {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let ty = &self.ty;
+        let ty_string = ty.to_token_stream().to_string().replace(" ", "");
+        let ident = self.ident();
+        tokens.append_all(quote! {
+            ::pgx::utils::sql_entity_graph::CrossTypeEntity {
+                name: stringify!(#ident),
+                ty_source: #ty_string,
+                ty_id: core::any::TypeId::of::<#ty>(),
+                full_path: core::any::type_name::<#ty>(),
+            }
+        });
+    }
+}
+
+/// The output of a [`CrossType`] from `quote::ToTokens::to_tokens`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CrossTypeEntity {
+    pub name: &'static str,
+    pub ty_source: &'static str,
+    pub ty_id: core::any::TypeId,
+    pub full_path: &'static str,
+}
+
+impl CrossTypeEntity {
+    /// The name of a generated function for `{type_name}` and this cross type, eg `mytype_lt_i32`.
+    pub(crate) fn fn_name(&self, type_name: &str, op: &str) -> String {
+        format!("{}_{}_{}", type_name, op, self.name).to_lowercase()
+    }
+
+    pub(crate) fn sql_type(&self, context: &PgxSql) -> eyre::Result<String> {
+        context
+            .rust_to_sql(self.ty_id, self.ty_source, self.full_path)
+            .ok_or_else(|| eyre!("Failed to map cross type `{}` to SQL type.", self.full_path))
+    }
+}
@@ -8,9 +8,11 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 pub(crate) mod aggregate;
 pub(crate) mod control_file;
+pub(crate) mod cross_type;
 pub(crate) mod extension_sql;
 pub(crate) mod mapping;
 pub(crate) mod pg_extern;
+pub(crate) mod pg_opclass;
 pub(crate) mod pgx_attribute;
 pub(crate) mod pgx_sql;
 pub(crate) mod positioning_ref;
@@ -26,14 +28,23 @@ pub use aggregate::{
     AggregateType, AggregateTypeList, FinalizeModify, ParallelOption, PgAggregate,
 };
 pub use control_file::ControlFile;
+pub use cross_type::{CrossType, CrossTypeEntity};
 pub use extension_sql::{
     entity::{ExtensionSqlEntity, SqlDeclaredEntity},
     ExtensionSql, ExtensionSqlFile, SqlDeclared,
 };
 pub use mapping::{RustSourceOnlySqlMapping, RustSqlMapping};
 pub use pg_extern::{
-    entity::{PgExternArgumentEntity, PgExternEntity, PgExternReturnEntity, PgOperatorEntity},
-    NameMacro, PgExtern, PgExternArgument, PgOperator,
+    entity::{
+        PgCastEntity, PgEventTriggerEntity, PgExternArgumentEntity, PgExternEntity,
+        PgExternReturnEntity, PgLanguageHandlerEntity, PgOperatorEntity, PgTriggerEntity,
+    },
+    NameMacro, PgBench, PgCast, PgEventTrigger, PgExtern, PgExternArgument, PgLanguageHandler,
+    PgOperator, PgProptest, PgTest, PgTrigger,
+};
+pub use pg_opclass::{
+    entity::{PgOpClassEntity, PgOpClassTypeEntity},
+    PgOpClass, PgOpClassMethod,
 };
 pub use pgx_sql::PgxSql;
 pub use positioning_ref::PositioningRef;
@@ -79,6 +90,7 @@ pub enum SqlGraphEntity {
     Ord(PostgresOrdEntity),
     Hash(PostgresHashEntity),
     Aggregate(PgAggregateEntity),
+    OpClass(PgOpClassEntity),
 }
 
 impl SqlGraphEntity {
@@ -111,6 +123,7 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Ord(item) => item.dot_identifier(),
             SqlGraphEntity::Hash(item) => item.dot_identifier(),
             SqlGraphEntity::Aggregate(item) => item.dot_identifier(),
+            SqlGraphEntity::OpClass(item) => item.dot_identifier(),
             SqlGraphEntity::ExtensionRoot(item) => item.dot_identifier(),
         }
     }
@@ -126,6 +139,7 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Ord(item) => item.rust_identifier(),
             SqlGraphEntity::Hash(item) => item.rust_identifier(),
             SqlGraphEntity::Aggregate(item) => item.rust_identifier(),
+            SqlGraphEntity::OpClass(item) => item.rust_identifier(),
             SqlGraphEntity::ExtensionRoot(item) => item.rust_identifier(),
         }
     }
@@ -141,6 +155,7 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Ord(item) => item.file(),
             SqlGraphEntity::Hash(item) => item.file(),
             SqlGraphEntity::Aggregate(item) => item.file(),
+            SqlGraphEntity::OpClass(item) => item.file(),
             SqlGraphEntity::ExtensionRoot(item) => item.file(),
         }
     }
@@ -156,6 +171,7 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Ord(item) => item.line(),
             SqlGraphEntity::Hash(item) => item.line(),
             SqlGraphEntity::Aggregate(item) => item.line(),
+            SqlGraphEntity::OpClass(item) => item.line(),
             SqlGraphEntity::ExtensionRoot(item) => item.line(),
         }
     }
@@ -220,6 +236,10 @@ impl ToSql for SqlGraphEntity {
                 .to_sql_config
                 .to_sql(self, context)
                 .unwrap_or_else(|| item.to_sql(context)),
+            SqlGraphEntity::OpClass(item) => item
+                .to_sql_config
+                .to_sql(self, context)
+                .unwrap_or_else(|| item.to_sql(context)),
             SqlGraphEntity::ExtensionRoot(item) => item.to_sql(context),
         }
     }
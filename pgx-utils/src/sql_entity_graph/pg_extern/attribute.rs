@@ -6,6 +6,7 @@ All rights reserved.
 
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
+use super::returning::Returning;
 use crate::sql_entity_graph::{positioning_ref::PositioningRef, to_sql::ToSqlConfig};
 use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::{quote, ToTokens, TokenStreamExt};
@@ -26,11 +27,17 @@ pub enum Attribute {
     ParallelSafe,
     ParallelUnsafe,
     ParallelRestricted,
+    Window,
     Error(syn::LitStr),
     Schema(syn::LitStr),
     Name(syn::LitStr),
     Cost(syn::Expr),
+    Rows(syn::Expr),
+    Leakproof,
+    SecurityDefiner,
+    Set(syn::LitStr),
     Requires(Punctuated<PositioningRef, Token![,]>),
+    Support(PositioningRef),
     Sql(ToSqlConfig),
 }
 
@@ -52,6 +59,7 @@ impl Attribute {
             Attribute::ParallelRestricted => {
                 quote! { ::pgx::utils::ExternArgs::ParallelRestricted }
             }
+            Attribute::Window => quote! { ::pgx::utils::ExternArgs::Window },
             Attribute::Error(s) => {
                 quote! { ::pgx::utils::ExternArgs::Error(String::from(#s)) }
             }
@@ -64,6 +72,14 @@ impl Attribute {
             Attribute::Cost(s) => {
                 quote! { ::pgx::utils::ExternArgs::Cost(format!("{}", #s)) }
             }
+            Attribute::Rows(s) => {
+                quote! { ::pgx::utils::ExternArgs::Rows(format!("{}", #s)) }
+            }
+            Attribute::Leakproof => quote! { ::pgx::utils::ExternArgs::Leakproof },
+            Attribute::SecurityDefiner => quote! { ::pgx::utils::ExternArgs::SecurityDefiner },
+            Attribute::Set(s) => {
+                quote! { ::pgx::utils::ExternArgs::Set(String::from(#s)) }
+            }
             Attribute::Requires(items) => {
                 let items_iter = items
                     .iter()
@@ -71,6 +87,9 @@ impl Attribute {
                     .collect::<Vec<_>>();
                 quote! { ::pgx::utils::ExternArgs::Requires(vec![#(#items_iter),*],) }
             }
+            Attribute::Support(item) => {
+                quote! { ::pgx::utils::ExternArgs::Support(#item) }
+            }
             // This attribute is handled separately
             Attribute::Sql(_) => {
                 quote! {}
@@ -79,6 +98,128 @@ impl Attribute {
     }
 }
 
+impl Attribute {
+    /// Check that `attrs` don't contradict one another, as `CREATE FUNCTION` would only reject
+    /// them once the extension's SQL is loaded
+    pub(crate) fn validate(attrs: &[Attribute]) -> Result<(), syn::Error> {
+        let volatility = attrs
+            .iter()
+            .filter(|attr| {
+                matches!(
+                    attr,
+                    Attribute::Immutable | Attribute::Stable | Attribute::Volatile
+                )
+            })
+            .collect::<Vec<_>>();
+        if volatility.len() > 1 {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                format!(
+                    "conflicting volatility attributes: `{}`; use only one of `immutable`, `stable`, or `volatile`",
+                    join_attributes(&volatility)
+                ),
+            ));
+        }
+
+        let parallel = attrs
+            .iter()
+            .filter(|attr| {
+                matches!(
+                    attr,
+                    Attribute::ParallelSafe
+                        | Attribute::ParallelRestricted
+                        | Attribute::ParallelUnsafe
+                )
+            })
+            .collect::<Vec<_>>();
+        if parallel.len() > 1 {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                format!(
+                    "conflicting parallel attributes: `{}`; use only one of `parallel_safe`, `parallel_restricted`, or `parallel_unsafe`",
+                    join_attributes(&parallel)
+                ),
+            ));
+        }
+
+        for attr in attrs {
+            if let Attribute::Set(set) = attr {
+                let value = set.value().to_lowercase();
+                if !value.contains('=')
+                    && !value.contains(" to ")
+                    && !value.ends_with(" from current")
+                {
+                    return Err(syn::Error::new(
+                        set.span(),
+                        "`set` must be a configuration parameter and its value, as in `set = \"search_path = pg_catalog, public\"`",
+                    ));
+                }
+            }
+        }
+
+        let mut flags = attrs
+            .iter()
+            .filter(|attr| attr.is_flag())
+            .collect::<Vec<_>>();
+        let count = flags.len();
+        flags.sort_by_key(|attr| attr.to_token_stream().to_string());
+        flags.dedup();
+        if flags.len() != count {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "an attribute is specified more than once",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Check that `rows` is only given for a function which returns a set, as `CREATE FUNCTION`
+    /// would only reject it once the extension's SQL is loaded
+    pub(crate) fn validate_returning(
+        attrs: &[Attribute],
+        returning: &Returning,
+    ) -> Result<(), syn::Error> {
+        let returns_set = matches!(
+            returning,
+            Returning::SetOf(_) | Returning::Iterated(_) | Returning::TableRow(_)
+        );
+        match attrs.iter().find(|attr| matches!(attr, Attribute::Rows(_))) {
+            Some(rows) if !returns_set => Err(syn::Error::new(
+                Span::call_site(),
+                format!(
+                    "`{}` is only allowed for a function which returns a set, such as an `impl Iterator` or a `TableIterator`",
+                    rows.to_token_stream()
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn is_flag(&self) -> bool {
+        !matches!(
+            self,
+            Attribute::Error(_)
+                | Attribute::Schema(_)
+                | Attribute::Name(_)
+                | Attribute::Cost(_)
+                | Attribute::Rows(_)
+                | Attribute::Set(_)
+                | Attribute::Requires(_)
+                | Attribute::Support(_)
+                | Attribute::Sql(_)
+        )
+    }
+}
+
+fn join_attributes(attrs: &[&Attribute]) -> String {
+    attrs
+        .iter()
+        .map(|attr| attr.to_token_stream().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 impl ToTokens for Attribute {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
         let quoted = match self {
@@ -97,6 +238,7 @@ impl ToTokens for Attribute {
             Attribute::ParallelRestricted => {
                 quote! { parallel_restricted }
             }
+            Attribute::Window => quote! { window },
             Attribute::Error(s) => {
                 quote! { error = #s }
             }
@@ -109,6 +251,14 @@ impl ToTokens for Attribute {
             Attribute::Cost(s) => {
                 quote! { cost = #s }
             }
+            Attribute::Rows(s) => {
+                quote! { rows = #s }
+            }
+            Attribute::Leakproof => quote! { leakproof },
+            Attribute::SecurityDefiner => quote! { security_definer },
+            Attribute::Set(s) => {
+                quote! { set = #s }
+            }
             Attribute::Requires(items) => {
                 let items_iter = items
                     .iter()
@@ -116,6 +266,9 @@ impl ToTokens for Attribute {
                     .collect::<Vec<_>>();
                 quote! { requires = [#(#items_iter),*] }
             }
+            Attribute::Support(item) => {
+                quote! { support = #item }
+            }
             // This attribute is handled separately
             Attribute::Sql(to_sql_config) => {
                 quote! { sql = #to_sql_config }
@@ -138,6 +291,9 @@ impl Parse for Attribute {
             "parallel_safe" => Self::ParallelSafe,
             "parallel_unsafe" => Self::ParallelUnsafe,
             "parallel_restricted" => Self::ParallelRestricted,
+            "window" => Self::Window,
+            "leakproof" => Self::Leakproof,
+            "security_definer" => Self::SecurityDefiner,
             "error" => {
                 let _eq: Token![=] = input.parse()?;
                 let literal: syn::LitStr = input.parse()?;
@@ -158,12 +314,26 @@ impl Parse for Attribute {
                 let literal: syn::Expr = input.parse()?;
                 Self::Cost(literal)
             }
+            "rows" => {
+                let _eq: Token![=] = input.parse()?;
+                let literal: syn::Expr = input.parse()?;
+                Self::Rows(literal)
+            }
+            "set" => {
+                let _eq: Token![=] = input.parse()?;
+                let literal: syn::LitStr = input.parse()?;
+                Self::Set(literal)
+            }
             "requires" => {
                 let _eq: syn::token::Eq = input.parse()?;
                 let content;
                 let _bracket = syn::bracketed!(content in input);
                 Self::Requires(content.parse_terminated(PositioningRef::parse)?)
             }
+            "support" => {
+                let _eq: Token![=] = input.parse()?;
+                Self::Support(input.parse()?)
+            }
             "sql" => {
                 use crate::sql_entity_graph::pgx_attribute::ArgValue;
                 use syn::Lit;
@@ -0,0 +1,86 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use quote::{quote, ToTokens, TokenStreamExt};
+
+/// A parsed `#[pg_cast]` cast.
+///
+/// It is attached to a [`PgExtern`](crate::sql_entity_graph::PgExtern) by `#[pg_cast]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgCast {
+    /// The cast may only be invoked explicitly, eg `value::target`.
+    Default,
+    /// The cast may be invoked implicitly when assigning to a column of the target type.
+    Assignment,
+    /// The cast may be invoked implicitly in any context.
+    Implicit,
+}
+
+impl PgCast {
+    /// Separate the `implicit` or `assignment` argument of a `#[pg_cast]` attribute from the
+    /// remaining arguments, which are passed along to `#[pg_extern]`.
+    pub fn split_attributes(attr: TokenStream2) -> Result<(Self, TokenStream2), syn::Error> {
+        let mut cast = None;
+        let mut extern_attrs = Vec::new();
+        let mut current = Vec::new();
+        let mut tokens = attr.into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            let at_end = match &token {
+                TokenTree::Punct(punct) if punct.as_char() == ',' => true,
+                _ => {
+                    current.push(token);
+                    tokens.peek().is_none()
+                }
+            };
+            if !at_end {
+                continue;
+            }
+
+            let found = match current.as_slice() {
+                [TokenTree::Ident(ident)] if ident == "implicit" => {
+                    Some((PgCast::Implicit, ident.span()))
+                }
+                [TokenTree::Ident(ident)] if ident == "assignment" => {
+                    Some((PgCast::Assignment, ident.span()))
+                }
+                _ => None,
+            };
+            match found {
+                Some((_, span)) if cast.is_some() => {
+                    return Err(syn::Error::new(
+                        span,
+                        "`#[pg_cast]` accepts only one of `implicit` or `assignment`",
+                    ))
+                }
+                Some((found, _)) => cast = Some(found),
+                None if current.is_empty() => (),
+                None => extern_attrs.push(current.drain(..).collect::<TokenStream2>()),
+            }
+            current.clear();
+        }
+
+        Ok((
+            cast.unwrap_or(PgCast::Default),
+            quote! { #(#extern_attrs),* },
+        ))
+    }
+}
+
+impl ToTokens for PgCast {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let quoted = match self {
+            PgCast::Default => quote! { ::pgx::utils::sql_entity_graph::PgCastEntity::Default },
+            PgCast::Assignment => {
+                quote! { ::pgx::utils::sql_entity_graph::PgCastEntity::Assignment }
+            }
+            PgCast::Implicit => quote! { ::pgx::utils::sql_entity_graph::PgCastEntity::Implicit },
+        };
+        tokens.append_all(quoted);
+    }
+}
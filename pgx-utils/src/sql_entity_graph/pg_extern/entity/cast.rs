@@ -0,0 +1,28 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use serde::{Deserialize, Serialize};
+
+/// The output of a [`PgCast`](crate::sql_entity_graph::PgCast) from `quote::ToTokens::to_tokens`.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum PgCastEntity {
+    Default,
+    Assignment,
+    Implicit,
+}
+
+impl PgCastEntity {
+    /// The `AS ...` clause of the `CREATE CAST` statement, if any.
+    pub(crate) fn sql_context(&self) -> &'static str {
+        match self {
+            PgCastEntity::Default => "",
+            PgCastEntity::Assignment => " AS ASSIGNMENT",
+            PgCastEntity::Implicit => " AS IMPLICIT",
+        }
+    }
+}
@@ -0,0 +1,31 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+/// The output of a [`PgEventTrigger`](crate::sql_entity_graph::PgEventTrigger) from `quote::ToTokens::to_tokens`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PgEventTriggerEntity {
+    pub event: Option<&'static str>,
+    pub tags: Vec<&'static str>,
+}
+
+impl PgEventTriggerEntity {
+    /// The `WHEN TAG IN (...)` clause of the `CREATE EVENT TRIGGER` statement, if any.
+    pub(crate) fn sql_when(&self) -> String {
+        if self.tags.is_empty() {
+            return String::new();
+        }
+
+        let tags = self
+            .tags
+            .iter()
+            .map(|tag| format!("'{}'", tag.replace('\'', "''")))
+            .collect::<Vec<_>>();
+        format!("\tWHEN TAG IN ({})\n", tags.join(", "))
+    }
+}
@@ -0,0 +1,35 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+/// The output of a [`PgLanguageHandler`](crate::sql_entity_graph::PgLanguageHandler) from `quote::ToTokens::to_tokens`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PgLanguageHandlerEntity {
+    pub language: Option<&'static str>,
+    pub trusted: bool,
+    pub validator: Option<&'static str>,
+}
+
+impl PgLanguageHandlerEntity {
+    /// The `CREATE LANGUAGE` or `CREATE TRUSTED LANGUAGE` of the statement.
+    pub(crate) fn sql_create(&self) -> &'static str {
+        if self.trusted {
+            "CREATE TRUSTED LANGUAGE"
+        } else {
+            "CREATE LANGUAGE"
+        }
+    }
+
+    /// The `VALIDATOR` clause of the `CREATE LANGUAGE` statement, if any.
+    pub(crate) fn sql_validator(&self) -> String {
+        match self.validator {
+            Some(validator) => format!("\n\tVALIDATOR {}", validator),
+            None => String::new(),
+        }
+    }
+}
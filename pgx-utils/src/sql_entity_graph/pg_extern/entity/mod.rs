@@ -7,17 +7,25 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 mod argument;
+mod cast;
+mod event_trigger;
+mod language_handler;
 mod operator;
 mod returning;
+mod trigger;
 
 pub use argument::PgExternArgumentEntity;
+pub use cast::PgCastEntity;
+pub use event_trigger::PgEventTriggerEntity;
+pub use language_handler::PgLanguageHandlerEntity;
 pub use operator::PgOperatorEntity;
 pub use returning::PgExternReturnEntity;
+pub use trigger::PgTriggerEntity;
 
 use crate::{
     sql_entity_graph::{
         extension_sql::SqlDeclared,
-        pgx_sql::PgxSql,
+        pgx_sql::{find_positioning_ref_target, PgxSql},
         to_sql::{entity::ToSqlConfigEntity, ToSql},
         SqlGraphEntity, SqlGraphIdentifier,
     },
@@ -42,6 +50,11 @@ pub struct PgExternEntity {
     pub fn_args: Vec<PgExternArgumentEntity>,
     pub fn_return: PgExternReturnEntity,
     pub operator: Option<PgOperatorEntity>,
+    pub cast: Option<PgCastEntity>,
+    pub event_trigger: Option<PgEventTriggerEntity>,
+    pub language_handler: Option<PgLanguageHandlerEntity>,
+    pub procedure: bool,
+    pub trigger: Option<PgTriggerEntity>,
     pub to_sql_config: ToSqlConfigEntity,
 }
 
@@ -91,8 +104,9 @@ impl ToSql for PgExternEntity {
     fn to_sql(&self, context: &PgxSql) -> eyre::Result<String> {
         let self_index = context.externs[self];
         let mut extern_attrs = self.extern_attrs.clone();
-        // if we already have a STRICT marker we do not need to add it
-        let mut strict_upgrade = !extern_attrs.iter().any(|i| i == &ExternArgs::Strict);
+        // if we already have a STRICT marker we do not need to add it, and procedures can't have one
+        let mut strict_upgrade =
+            !self.procedure && !extern_attrs.iter().any(|i| i == &ExternArgs::Strict);
         if strict_upgrade {
             for arg in &self.fn_args {
                 if arg.is_optional {
@@ -107,18 +121,78 @@ impl ToSql for PgExternEntity {
 
         let module_pathname = &context.get_module_pathname();
 
+        let mut out_args = Vec::new();
+        if let PgExternReturnEntity::Record(out_items) = &self.fn_return {
+            if out_items.len() < 2 {
+                return Err(eyre!("Function `{}` must return a tuple of at least two values to use `OUT` arguments.", self.name));
+            }
+            for (idx, (id, source, ty_name, _module_path, col_name)) in out_items.iter().enumerate() {
+                let graph_index = context.graph.neighbors_undirected(self_index).find(|neighbor| match &context.graph[*neighbor] {
+                    SqlGraphEntity::Type(ty) => ty.id_matches(id),
+                    SqlGraphEntity::Enum(en) => en.id_matches(id),
+                    SqlGraphEntity::BuiltinType(defined) => defined == ty_name,
+                    _ => false,
+                });
+                let needs_comma = idx < (out_items.len() - 1);
+                let buf = format!("\tOUT {col_name}{schema_prefix}{ty_resolved}{maybe_comma}/* {ty_name} */",
+                                  col_name = col_name.map(|col_name| format!("\"{}\" ", col_name)).unwrap_or_default(),
+                                  schema_prefix = if let Some(graph_index) = graph_index {
+                                      context.schema_prefix_for(&graph_index)
+                                  } else { "".into() },
+                                  ty_resolved = context.source_only_to_sql_type(source).or_else(|| {
+                                      context.type_id_to_sql_type(*id)
+                                  }).ok_or_else(|| eyre!("Failed to map OUT argument type `{}` to SQL type while building function `{}`.", ty_name, self.name))?,
+                                  maybe_comma = if needs_comma { ", " } else { " " },
+                                  ty_name = ty_name,
+                );
+                out_args.push(buf);
+            }
+        }
+
+        let support = match self.extern_attrs.iter().find_map(|attr| match attr {
+            ExternArgs::Support(support) => Some(support),
+            _ => None,
+        }) {
+            Some(support) => {
+                let support_index = find_positioning_ref_target(
+                    support,
+                    &context.types,
+                    &context.enums,
+                    &context.externs,
+                    &context.schemas,
+                    &context.extension_sqls,
+                )
+                .ok_or_else(|| eyre!("Could not find `support` target: {:?}", support))?;
+                match &context.graph[*support_index] {
+                    SqlGraphEntity::Function(support_fn) => format!(
+                        "SUPPORT {schema}\"{name}\"\n",
+                        schema = support_fn
+                            .schema
+                            .map(|schema| format!("{}.", schema))
+                            .unwrap_or_else(|| context.schema_prefix_for(support_index)),
+                        name = support_fn.name,
+                    ),
+                    _ => return Err(eyre!("`support` target is not a function: {:?}", support)),
+                }
+            }
+            None => String::default(),
+        };
+
         let fn_sql = format!("\
-                                CREATE FUNCTION {schema}\"{name}\"({arguments}) {returns}\n\
+                                CREATE {kind} {schema}\"{name}\"({arguments}){returns}\n\
                                 {extern_attrs}\
+                                {support}\
                                 {search_path}\
+                                {set}\
                                 LANGUAGE c /* Rust */\n\
                                 AS '{module_pathname}', '{unaliased_name}_wrapper';\
                             ",
+                             kind = if self.procedure { "PROCEDURE" } else { "FUNCTION" },
                              schema = self.schema.map(|schema| format!("{}.", schema)).unwrap_or_else(|| context.schema_prefix_for(&self_index)),
                              name = self.name,
                              unaliased_name = self.unaliased_name,
                              module_pathname = module_pathname,
-                             arguments = if !self.fn_args.is_empty() {
+                             arguments = if !self.fn_args.is_empty() || !out_args.is_empty() {
                                  let mut args = Vec::new();
                                  for (idx, arg) in self.fn_args.iter().enumerate() {
                                      let graph_index = context.graph.neighbors_undirected(self_index).find(|neighbor| match &context.graph[*neighbor] {
@@ -127,7 +201,7 @@ impl ToSql for PgExternEntity {
                                          SqlGraphEntity::BuiltinType(defined) => defined == &arg.full_path,
                                          _ => false,
                                      }).ok_or_else(|| eyre!("Could not find arg type in graph. Got: {:?}", arg))?;
-                                     let needs_comma = idx < (self.fn_args.len() - 1);
+                                     let needs_comma = idx < (self.fn_args.len() - 1) || !out_args.is_empty();
                                      let buf = format!("\
                                             \t\"{pattern}\" {variadic}{schema_prefix}{sql_type}{default}{maybe_comma}/* {full_path} */\
                                         ",
@@ -147,10 +221,12 @@ impl ToSql for PgExternEntity {
                                      );
                                      args.push(buf);
                                  };
+                                 args.extend(out_args.iter().cloned());
                                  String::from("\n") + &args.join("\n") + "\n"
                              } else { Default::default() },
                              returns = match &self.fn_return {
-                                 PgExternReturnEntity::None => String::from("RETURNS void"),
+                                 PgExternReturnEntity::None if self.procedure => String::default(),
+                                 PgExternReturnEntity::None => String::from(" RETURNS void"),
                                  PgExternReturnEntity::Type { id, source, full_path, .. } => {
                                      let graph_index = context.graph.neighbors_undirected(self_index).find(|neighbor| match &context.graph[*neighbor] {
                                          SqlGraphEntity::Type(ty) => ty.id_matches(&id),
@@ -158,7 +234,7 @@ impl ToSql for PgExternEntity {
                                          SqlGraphEntity::BuiltinType(defined) => &*defined == full_path,
                                          _ => false,
                                      }).ok_or_else(|| eyre!("Could not find return type in graph."))?;
-                                     format!("RETURNS {schema_prefix}{sql_type} /* {full_path} */",
+                                     format!(" RETURNS {schema_prefix}{sql_type} /* {full_path} */",
                                              sql_type = context.source_only_to_sql_type(source).or_else(|| {
                                                  context.type_id_to_sql_type(*id)
                                              }).or_else(|| {
@@ -182,7 +258,7 @@ impl ToSql for PgExternEntity {
                                          SqlGraphEntity::BuiltinType(defined) => defined == full_path,
                                          _ => false,
                                      }).ok_or_else(|| eyre!("Could not find return type in graph."))?;
-                                     format!("RETURNS SETOF {schema_prefix}{sql_type} /* {full_path} */",
+                                     format!(" RETURNS SETOF {schema_prefix}{sql_type} /* {full_path} */",
                                              sql_type = context.source_only_to_sql_type(source).or_else(|| {
                                                  context.type_id_to_sql_type(*id)
                                              }).or_else(|| {
@@ -231,14 +307,22 @@ impl ToSql for PgExternEntity {
                                          );
                                          items.push_str(&item);
                                      }
-                                     format!("RETURNS TABLE ({}\n)", items)
+                                     format!(" RETURNS TABLE ({}\n)", items)
                                  },
-                                 PgExternReturnEntity::Trigger => String::from("RETURNS trigger"),
+                                 PgExternReturnEntity::Record(_) => String::from(" RETURNS record"),
+                                 PgExternReturnEntity::Trigger if self.event_trigger.is_some() => String::from(" RETURNS event_trigger"),
+                                 PgExternReturnEntity::Trigger if self.language_handler.is_some() => String::from(" RETURNS language_handler"),
+                                 PgExternReturnEntity::Trigger => String::from(" RETURNS trigger"),
                              },
+                             support = support,
                              search_path = if let Some(search_path) = &self.search_path {
                                  let retval = format!("SET search_path TO {}", search_path.join(", "));
                                  retval + "\n"
                              } else { Default::default() },
+                             set = self.extern_attrs.iter().filter_map(|attr| match attr {
+                                 ExternArgs::Set(set) => Some(format!("SET {}\n", set)),
+                                 _ => None,
+                             }).collect::<String>(),
                              extern_attrs = if extern_attrs.is_empty() {
                                  String::default()
                              } else {
@@ -365,6 +449,170 @@ impl ToSql for PgExternEntity {
         } else {
             ext_sql
         };
+
+        let rendered = if let Some(cast) = &self.cast {
+            let schema_prefix_for_type = |ty_id: &core::any::TypeId| {
+                context
+                    .graph
+                    .neighbors_undirected(self_index)
+                    .find(|neighbor| match &context.graph[*neighbor] {
+                        SqlGraphEntity::Type(ty) => ty.id_matches(ty_id),
+                        SqlGraphEntity::Enum(en) => en.id_matches(ty_id),
+                        _ => false,
+                    })
+                    .map(|graph_index| context.schema_prefix_for(&graph_index))
+                    .unwrap_or_default()
+            };
+
+            let source_arg = self
+                .fn_args
+                .first()
+                .ok_or_else(|| eyre!("Did not find source argument for cast `{}`.", self.name))?;
+            let mut arguments = Vec::new();
+            for arg in &self.fn_args {
+                arguments.push(format!(
+                    "{schema_prefix}{sql_type}",
+                    schema_prefix = schema_prefix_for_type(&arg.ty_id),
+                    sql_type = context.rust_to_sql(arg.ty_id, arg.ty_source, arg.full_path).ok_or_else(|| eyre!(
+                        "Failed to map argument `{}` type `{}` to SQL type while building cast `{}`.",
+                        arg.pattern,
+                        arg.full_path,
+                        self.name
+                    ))?,
+                ));
+            }
+            let (target_id, target_source, target_full_path) = match &self.fn_return {
+                PgExternReturnEntity::Type {
+                    id,
+                    source,
+                    full_path,
+                    ..
+                } => (id, source, full_path),
+                _ => {
+                    return Err(eyre!(
+                        "Cast `{}` must return a single value of the target type.",
+                        self.name
+                    ))
+                }
+            };
+
+            let target_sql_type = context
+                .source_only_to_sql_type(target_source)
+                .or_else(|| context.type_id_to_sql_type(*target_id))
+                .ok_or_else(|| {
+                    eyre!(
+                        "Failed to map return type `{}` to SQL type while building cast `{}`.",
+                        target_full_path,
+                        self.name
+                    )
+                })?;
+
+            let cast_sql = format!("\n\n\
+                                    -- {file}:{line}\n\
+                                    -- {module_path}::{unaliased_name}\n\
+                                    CREATE CAST (\n\
+                                        \t{source} /* {source_name} */\n\
+                                        \tAS\n\
+                                        \t{target_schema}{target} /* {target_name} */\n\
+                                    )\n\
+                                    WITH FUNCTION {schema}\"{name}\"({arguments}){context};\
+                                    ",
+                                    file = self.file,
+                                    line = self.line,
+                                    module_path = self.module_path,
+                                    unaliased_name = self.unaliased_name,
+                                    source = arguments[0],
+                                    source_name = source_arg.full_path,
+                                    target_schema = schema_prefix_for_type(target_id),
+                                    target = target_sql_type,
+                                    target_name = target_full_path,
+                                    schema = self.schema.map(|schema| format!("{}.", schema)).unwrap_or_else(|| context.schema_prefix_for(&self_index)),
+                                    name = self.name,
+                                    arguments = arguments.join(", "),
+                                    context = cast.sql_context(),
+            );
+            tracing::trace!(sql = %cast_sql);
+            rendered + &cast_sql
+        } else {
+            rendered
+        };
+
+        let rendered = match &self.event_trigger {
+            Some(event_trigger @ PgEventTriggerEntity { event: Some(event), .. }) => {
+                let event_trigger_sql = format!("\n\n\
+                                                 -- {file}:{line}\n\
+                                                 -- {module_path}::{unaliased_name}\n\
+                                                 CREATE EVENT TRIGGER \"{name}\" ON {event}\n\
+                                                 {when}\
+                                                 \tEXECUTE PROCEDURE {schema}\"{name}\"();\
+                                                 ",
+                                                 file = self.file,
+                                                 line = self.line,
+                                                 module_path = self.module_path,
+                                                 unaliased_name = self.unaliased_name,
+                                                 event = event,
+                                                 when = event_trigger.sql_when(),
+                                                 schema = self.schema.map(|schema| format!("{}.", schema)).unwrap_or_else(|| context.schema_prefix_for(&self_index)),
+                                                 name = self.name,
+                );
+                tracing::trace!(sql = %event_trigger_sql);
+                rendered + &event_trigger_sql
+            }
+            _ => rendered,
+        };
+
+        let rendered = match &self.trigger {
+            Some(trigger @ PgTriggerEntity { table: Some(table), timing: Some(timing), .. }) => {
+                let trigger_sql = format!("\n\n\
+                                           -- {file}:{line}\n\
+                                           -- {module_path}::{unaliased_name}\n\
+                                           {create} \"{name}\"\n\
+                                           \t{timing} {events} ON {table}\n\
+                                           {conditions}\
+                                           \tEXECUTE PROCEDURE {schema}\"{name}\"();\
+                                           ",
+                                           file = self.file,
+                                           line = self.line,
+                                           module_path = self.module_path,
+                                           unaliased_name = self.unaliased_name,
+                                           create = trigger.sql_create(),
+                                           timing = timing.to_uppercase(),
+                                           events = trigger.sql_events(),
+                                           table = table,
+                                           conditions = trigger.sql_conditions(),
+                                           schema = self.schema.map(|schema| format!("{}.", schema)).unwrap_or_else(|| context.schema_prefix_for(&self_index)),
+                                           name = self.name,
+                );
+                tracing::trace!(sql = %trigger_sql);
+                rendered + &trigger_sql
+            }
+            _ => rendered,
+        };
+
+        let rendered = match &self.language_handler {
+            Some(handler @ PgLanguageHandlerEntity { language: Some(language), .. }) => {
+                let language_sql = format!("\n\n\
+                                            -- {file}:{line}\n\
+                                            -- {module_path}::{unaliased_name}\n\
+                                            {create} \"{language}\"\n\
+                                            \tHANDLER {schema}\"{name}\"\
+                                            {validator};\
+                                            ",
+                                            file = self.file,
+                                            line = self.line,
+                                            module_path = self.module_path,
+                                            unaliased_name = self.unaliased_name,
+                                            create = handler.sql_create(),
+                                            language = language,
+                                            schema = self.schema.map(|schema| format!("{}.", schema)).unwrap_or_else(|| context.schema_prefix_for(&self_index)),
+                                            name = self.name,
+                                            validator = handler.sql_validator(),
+                );
+                tracing::trace!(sql = %language_sql);
+                rendered + &language_sql
+            }
+            _ => rendered,
+        };
         Ok(rendered)
     }
 }
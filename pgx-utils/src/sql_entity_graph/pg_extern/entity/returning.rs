@@ -32,5 +32,15 @@ pub enum PgExternReturnEntity {
             Option<&'static str>, // Name
         )>,
     ),
+    /// A single row, returned through `OUT` arguments
+    Record(
+        Vec<(
+            TypeId,
+            &'static str,         // Source
+            &'static str,         // Full path
+            String,               // Module path
+            Option<&'static str>, // Name
+        )>,
+    ),
     Trigger,
 }
@@ -0,0 +1,74 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+/// The output of a [`PgTrigger`](crate::sql_entity_graph::PgTrigger) from `quote::ToTokens::to_tokens`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PgTriggerEntity {
+    pub table: Option<&'static str>,
+    pub timing: Option<&'static str>,
+    pub events: Vec<&'static str>,
+    pub update_of: Vec<&'static str>,
+    pub level: Option<&'static str>,
+    pub when: Option<&'static str>,
+    pub constraint: bool,
+    pub deferrable: bool,
+    pub initially_deferred: bool,
+}
+
+impl PgTriggerEntity {
+    /// The events of the `CREATE TRIGGER` statement, such as `INSERT OR UPDATE OF "name"`.
+    pub(crate) fn sql_events(&self) -> String {
+        self.events
+            .iter()
+            .map(|event| match *event {
+                "update" if !self.update_of.is_empty() => {
+                    let columns = self
+                        .update_of
+                        .iter()
+                        .map(|column| format!("\"{}\"", column))
+                        .collect::<Vec<_>>();
+                    format!("UPDATE OF {}", columns.join(", "))
+                }
+                event => event.to_uppercase(),
+            })
+            .collect::<Vec<_>>()
+            .join(" OR ")
+    }
+
+    /// The `CREATE TRIGGER` or `CREATE CONSTRAINT TRIGGER` of the statement.
+    pub(crate) fn sql_create(&self) -> &'static str {
+        if self.constraint {
+            "CREATE CONSTRAINT TRIGGER"
+        } else {
+            "CREATE TRIGGER"
+        }
+    }
+
+    /// The `DEFERRABLE`, `FOR EACH`, and `WHEN` clauses of the `CREATE TRIGGER` statement, if any.
+    pub(crate) fn sql_conditions(&self) -> String {
+        let mut conditions = String::new();
+        if self.initially_deferred {
+            conditions.push_str("\tDEFERRABLE INITIALLY DEFERRED\n");
+        } else if self.deferrable {
+            conditions.push_str("\tDEFERRABLE\n");
+        }
+        // constraint and `INSTEAD OF` triggers can only be row-level, but that isn't the default
+        match self.level {
+            Some(level) => conditions.push_str(&format!("\tFOR EACH {}\n", level.to_uppercase())),
+            None if self.constraint || self.timing == Some("instead of") => {
+                conditions.push_str("\tFOR EACH ROW\n")
+            }
+            None => (),
+        }
+        if let Some(when) = self.when {
+            conditions.push_str(&format!("\tWHEN ({})\n", when));
+        }
+        conditions
+    }
+}
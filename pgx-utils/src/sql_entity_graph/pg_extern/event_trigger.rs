@@ -0,0 +1,103 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use super::split_attributes;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens, TokenStreamExt};
+use syn::spanned::Spanned;
+
+/// A parsed `#[pg_event_trigger]` event trigger.
+///
+/// It is attached to a [`PgExtern`](crate::sql_entity_graph::PgExtern) by `#[pg_event_trigger]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PgEventTrigger {
+    /// The event to create an event trigger for, such as `ddl_command_end`, if any.
+    pub event: Option<syn::LitStr>,
+    /// The command tags the event trigger is limited to, such as `CREATE TABLE`.
+    pub tags: Vec<syn::LitStr>,
+}
+
+impl PgEventTrigger {
+    /// Separate the `event = "..."` and `tags = [...]` arguments of a `#[pg_event_trigger]`
+    /// attribute from the remaining arguments, which are passed along to `#[pg_extern]`.
+    pub fn split_attributes(attr: TokenStream2) -> Result<(Self, TokenStream2), syn::Error> {
+        let (args, extern_attrs) = split_attributes(attr, &["event", "tags"])?;
+        let mut event_trigger = PgEventTrigger::default();
+        for arg in args {
+            event_trigger.parse_arg(arg)?;
+        }
+
+        if !event_trigger.tags.is_empty() && event_trigger.event.is_none() {
+            return Err(syn::Error::new(
+                event_trigger.tags[0].span(),
+                "`#[pg_event_trigger]` needs an `event` for its `tags`",
+            ));
+        }
+
+        Ok((event_trigger, extern_attrs))
+    }
+
+    fn parse_arg(&mut self, arg: syn::Expr) -> Result<(), syn::Error> {
+        let arg = match arg {
+            syn::Expr::Assign(arg) => arg,
+            other => {
+                return Err(syn::Error::new(
+                    other.span(),
+                    "expected `event = \"event_name\"` or `tags = [\"COMMAND TAG\", ...]`",
+                ))
+            }
+        };
+        let name = arg.left.to_token_stream().to_string();
+        match (name.as_str(), *arg.right) {
+            (
+                "event",
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(event),
+                    ..
+                }),
+            ) => {
+                self.event = Some(event);
+                Ok(())
+            }
+            ("tags", syn::Expr::Array(tags)) => {
+                for tag in tags.elems {
+                    match tag {
+                        syn::Expr::Lit(syn::ExprLit {
+                            lit: syn::Lit::Str(tag),
+                            ..
+                        }) => self.tags.push(tag),
+                        other => {
+                            return Err(syn::Error::new(
+                                other.span(),
+                                "expected a command tag, such as \"CREATE TABLE\"",
+                            ))
+                        }
+                    }
+                }
+                Ok(())
+            }
+            (_, other) => Err(syn::Error::new(
+                other.span(),
+                "expected `event = \"event_name\"` or `tags = [\"COMMAND TAG\", ...]`",
+            )),
+        }
+    }
+}
+
+impl ToTokens for PgEventTrigger {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let event = self.event.iter();
+        let tags = &self.tags;
+        tokens.append_all(quote! {
+            ::pgx::utils::sql_entity_graph::PgEventTriggerEntity {
+                event: None #( .unwrap_or(Some(#event)) )*,
+                tags: vec![#(#tags),*],
+            }
+        });
+    }
+}
@@ -0,0 +1,94 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use super::split_attributes;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, ToTokens, TokenStreamExt};
+use syn::spanned::Spanned;
+
+/// A parsed `#[pg_language_handler]` procedural language handler.
+///
+/// It is attached to a [`PgExtern`](crate::sql_entity_graph::PgExtern) by `#[pg_language_handler]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PgLanguageHandler {
+    /// The language to create with the handler, if any.
+    pub language: Option<syn::LitStr>,
+    /// Whether the language is `TRUSTED`, so that unprivileged users may use it.
+    pub trusted: bool,
+    /// The SQL function which validates the language's functions when they're created, if any.
+    pub validator: Option<syn::LitStr>,
+}
+
+const LANGUAGE_HANDLER_ARGS: &[&str] = &["language", "trusted", "validator"];
+
+impl PgLanguageHandler {
+    /// Separate the `language = "..."`, `trusted`, and `validator = "..."` arguments of a
+    /// `#[pg_language_handler]` attribute from the remaining arguments, which are passed along to
+    /// `#[pg_extern]`.
+    pub fn split_attributes(attr: TokenStream2) -> Result<(Self, TokenStream2), syn::Error> {
+        let (args, extern_attrs) = split_attributes(attr, LANGUAGE_HANDLER_ARGS)?;
+        let mut handler = PgLanguageHandler::default();
+        for arg in args {
+            handler.parse_arg(arg)?;
+        }
+
+        if handler.language.is_none() && (handler.trusted || handler.validator.is_some()) {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "`#[pg_language_handler]` needs a `language` to be `trusted` or have a `validator`",
+            ));
+        }
+
+        Ok((handler, extern_attrs))
+    }
+
+    fn parse_arg(&mut self, arg: syn::Expr) -> Result<(), syn::Error> {
+        let arg = match arg {
+            syn::Expr::Assign(arg) => arg,
+            syn::Expr::Path(flag) if flag.path.is_ident("trusted") => {
+                self.trusted = true;
+                return Ok(());
+            }
+            other => return Err(syn::Error::new(other.span(), "expected `name = value`")),
+        };
+        let name = arg.left.to_token_stream().to_string();
+        let value = match *arg.right {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(value),
+                ..
+            }) => value,
+            other => return Err(syn::Error::new(other.span(), "expected a string")),
+        };
+        match name.as_str() {
+            "language" => self.language = Some(value),
+            "validator" => self.validator = Some(value),
+            _ => {
+                return Err(syn::Error::new(
+                    arg.left.span(),
+                    format!("expected one of {}", LANGUAGE_HANDLER_ARGS.join(", ")),
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ToTokens for PgLanguageHandler {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let language = self.language.iter();
+        let trusted = self.trusted;
+        let validator = self.validator.iter();
+        tokens.append_all(quote! {
+            ::pgx::utils::sql_entity_graph::PgLanguageHandlerEntity {
+                language: None #( .unwrap_or(Some(#language)) )*,
+                trusted: #trusted,
+                validator: None #( .unwrap_or(Some(#validator)) )*,
+            }
+        });
+    }
+}
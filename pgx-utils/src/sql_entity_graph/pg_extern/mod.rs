@@ -8,14 +8,24 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 mod argument;
 mod attribute;
+mod cast;
 pub mod entity;
+mod event_trigger;
+mod language_handler;
 mod operator;
 mod returning;
 mod search_path;
+mod test;
+mod trigger;
 
 pub use argument::PgExternArgument;
+pub use cast::PgCast;
+pub use event_trigger::PgEventTrigger;
+pub use language_handler::PgLanguageHandler;
 pub use operator::PgOperator;
 pub use returning::NameMacro;
+pub use test::{PgBench, PgProptest, PgTest};
+pub use trigger::PgTrigger;
 
 use crate::sql_entity_graph::ToSqlConfig;
 use attribute::Attribute;
@@ -24,7 +34,7 @@ use returning::Returning;
 use search_path::SearchPathList;
 
 use eyre::WrapErr;
-use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2, TokenTree};
 use quote::{quote, ToTokens, TokenStreamExt};
 use std::convert::TryFrom;
 use syn::{
@@ -58,6 +68,11 @@ use syn::{
 pub struct PgExtern {
     attrs: Vec<Attribute>,
     func: syn::ItemFn,
+    cast: Option<PgCast>,
+    event_trigger: Option<PgEventTrigger>,
+    language_handler: Option<PgLanguageHandler>,
+    procedure: bool,
+    trigger: Option<PgTrigger>,
     to_sql_config: ToSqlConfig,
 }
 
@@ -79,6 +94,38 @@ impl PgExtern {
         })
     }
 
+    /// Declare this function as a `CREATE CAST` from its argument type to its return type.
+    pub fn with_cast(mut self, cast: PgCast) -> Self {
+        self.cast = Some(cast);
+        self
+    }
+
+    /// Declare this function as an event trigger function, which `RETURNS event_trigger`.
+    pub fn with_event_trigger(mut self, event_trigger: PgEventTrigger) -> Self {
+        self.event_trigger = Some(event_trigger);
+        self
+    }
+
+    /// Declare this function as a procedural language handler, which `RETURNS language_handler`,
+    /// and with a `language`, the `CREATE LANGUAGE` which uses it.
+    pub fn with_language_handler(mut self, language_handler: PgLanguageHandler) -> Self {
+        self.language_handler = Some(language_handler);
+        self
+    }
+
+    /// Declare this function as a trigger function, which `RETURNS trigger`, and with a `table`,
+    /// the `CREATE TRIGGER` which executes it.
+    pub fn with_trigger(mut self, trigger: PgTrigger) -> Self {
+        self.trigger = Some(trigger);
+        self
+    }
+
+    /// Declare this function as a `CREATE PROCEDURE`, which is invoked with `CALL`.
+    pub fn with_procedure(mut self) -> Self {
+        self.procedure = true;
+        self
+    }
+
     pub fn extern_attrs(&self) -> &[Attribute] {
         self.attrs.as_slice()
     }
@@ -215,7 +262,12 @@ impl PgExtern {
             }
         }
 
+        Attribute::validate(&attrs)?;
+
         let func = syn::parse2::<syn::ItemFn>(item)?;
+        if let Ok(returning) = Returning::try_from(&func.sig.output) {
+            Attribute::validate_returning(&attrs, &returning)?;
+        }
 
         if let Some(ref mut to_sql_config) = to_sql_config {
             if let Some(ref mut content) = to_sql_config.content {
@@ -231,6 +283,11 @@ impl PgExtern {
         Ok(Self {
             attrs,
             func,
+            cast: None,
+            event_trigger: None,
+            language_handler: None,
+            procedure: false,
+            trigger: None,
             to_sql_config: to_sql_config.unwrap_or_default(),
         })
     }
@@ -260,6 +317,11 @@ impl ToTokens for PgExtern {
             }
         };
         let operator = self.operator().into_iter();
+        let cast = self.cast.iter();
+        let event_trigger = self.event_trigger.iter();
+        let language_handler = self.language_handler.iter();
+        let procedure = self.procedure;
+        let trigger = self.trigger.iter();
         let to_sql_config = match self.overridden() {
             None => self.to_sql_config.clone(),
             Some(content) => {
@@ -292,6 +354,11 @@ impl ToTokens for PgExtern {
                     fn_args: vec![#(#inputs),*],
                     fn_return: #returns,
                     operator: None #( .unwrap_or(Some(#operator)) )*,
+                    cast: None #( .unwrap_or(Some(#cast)) )*,
+                    event_trigger: None #( .unwrap_or(Some(#event_trigger)) )*,
+                    language_handler: None #( .unwrap_or(Some(#language_handler)) )*,
+                    procedure: #procedure,
+                    trigger: None #( .unwrap_or(Some(#trigger)) )*,
                     to_sql_config: #to_sql_config,
                 };
                 ::pgx::utils::sql_entity_graph::SqlGraphEntity::Function(submission)
@@ -319,11 +386,319 @@ impl Parse for PgExtern {
             }
         }
 
+        Attribute::validate(&attrs)?;
+
         let func: syn::ItemFn = input.parse()?;
+        if let Ok(returning) = Returning::try_from(&func.sig.output) {
+            Attribute::validate_returning(&attrs, &returning)?;
+        }
         Ok(Self {
             attrs,
             func,
+            cast: None,
+            event_trigger: None,
+            language_handler: None,
+            procedure: false,
+            trigger: None,
             to_sql_config: to_sql_config.unwrap_or_default(),
         })
     }
 }
+
+/// Separate the arguments of an attribute named in `names`, such as `event = "..."`, from the
+/// remaining arguments, which are passed along to `#[pg_extern]`.
+pub(crate) fn split_attributes(
+    attr: TokenStream2,
+    names: &[&str],
+) -> Result<(Vec<syn::Expr>, TokenStream2), syn::Error> {
+    let mut args = Vec::new();
+    let mut extern_attrs = Vec::new();
+    let mut current = Vec::new();
+    let mut tokens = attr.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        let at_end = match &token {
+            TokenTree::Punct(punct) if punct.as_char() == ',' => true,
+            _ => {
+                current.push(token);
+                tokens.peek().is_none()
+            }
+        };
+        if !at_end {
+            continue;
+        }
+
+        match current.first() {
+            Some(TokenTree::Ident(ident)) if names.iter().any(|name| ident == name) => {
+                args.push(syn::parse2::<syn::Expr>(current.drain(..).collect())?);
+            }
+            None => (),
+            Some(_) => extern_attrs.push(current.drain(..).collect::<TokenStream2>()),
+        }
+        current.clear();
+    }
+
+    Ok((args, quote! { #(#extern_attrs),* }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PgBench, PgExtern, PgLanguageHandler, PgProptest, PgTest, PgTrigger};
+    use quote::quote;
+
+    #[test]
+    fn extern_attributes() {
+        let extern_fn = PgExtern::new(
+            quote! { stable, strict, parallel_restricted },
+            quote! { fn example(value: i32) -> i32 { value } },
+        );
+        assert!(extern_fn.is_ok());
+        assert_eq!(extern_fn.unwrap().extern_attrs().len(), 3);
+    }
+
+    #[test]
+    fn conflicting_volatility() {
+        let extern_fn = PgExtern::new(quote! { immutable, volatile }, quote! { fn example() {} });
+        let message = extern_fn.err().unwrap().to_string();
+        assert!(message.starts_with("conflicting volatility attributes"));
+    }
+
+    #[test]
+    fn conflicting_parallel() {
+        let extern_fn = PgExtern::new(
+            quote! { parallel_safe, parallel_unsafe },
+            quote! { fn example() {} },
+        );
+        let message = extern_fn.err().unwrap().to_string();
+        assert!(message.starts_with("conflicting parallel attributes"));
+    }
+
+    #[test]
+    fn duplicate_attribute() {
+        let extern_fn = PgExtern::new(quote! { strict, strict }, quote! { fn example() {} });
+        assert!(extern_fn.is_err());
+    }
+
+    #[test]
+    fn rows_attribute() {
+        let extern_fn = PgExtern::new(
+            quote! { rows = 10, support = example_support },
+            quote! { fn example() -> impl Iterator<Item = i32> { 0..10 } },
+        );
+        assert!(extern_fn.is_ok());
+
+        let extern_fn = PgExtern::new(quote! { rows = 10 }, quote! { fn example() -> i32 { 0 } });
+        let message = extern_fn.err().unwrap().to_string();
+        assert!(message.starts_with("`rows = 10` is only allowed"));
+    }
+
+    #[test]
+    fn security_attributes() {
+        let extern_fn = PgExtern::new(
+            quote! { leakproof, security_definer, set = "search_path TO pg_catalog", set = "work_mem = '8MB'" },
+            quote! { fn example() {} },
+        );
+        assert!(extern_fn.is_ok());
+        assert_eq!(extern_fn.unwrap().extern_attrs().len(), 4);
+
+        let extern_fn = PgExtern::new(quote! { set = "search_path" }, quote! { fn example() {} });
+        let message = extern_fn.err().unwrap().to_string();
+        assert!(message.starts_with("`set` must be"));
+    }
+
+    #[test]
+    fn trigger_attributes() {
+        let (trigger, extern_attr) = PgTrigger::split_attributes(quote! {
+            name = "scritch",
+            table = "dogs",
+            timing = "before",
+            events = ["insert", "update"],
+            update_of = ["scritches"],
+            level = "row",
+            when = "NEW.scritches > 0"
+        })
+        .unwrap();
+        assert_eq!(trigger.table.unwrap().value(), "dogs");
+        assert_eq!(trigger.events.len(), 2);
+        assert_eq!(trigger.update_of[0].value(), "scritches");
+        assert_eq!(extern_attr.to_string(), quote! { name = "scritch" }.to_string());
+    }
+
+    #[test]
+    fn trigger_update_of_without_update() {
+        let trigger = PgTrigger::split_attributes(quote! {
+            table = "dogs", timing = "after", events = ["insert"], update_of = ["name"]
+        });
+        let message = trigger.err().unwrap().to_string();
+        assert!(message.starts_with("`update_of` needs"));
+    }
+
+    #[test]
+    fn constraint_trigger_attributes() {
+        let (trigger, _) = PgTrigger::split_attributes(quote! {
+            table = "dogs", timing = "after", events = ["insert"], constraint, initially_deferred
+        })
+        .unwrap();
+        assert!(trigger.constraint && trigger.initially_deferred);
+
+        let trigger = PgTrigger::split_attributes(quote! {
+            table = "dogs", timing = "before", events = ["insert"], constraint
+        });
+        let message = trigger.err().unwrap().to_string();
+        assert!(message.starts_with("constraint triggers must have"));
+    }
+
+    #[test]
+    fn instead_of_trigger_attributes() {
+        let (trigger, _) = PgTrigger::split_attributes(quote! {
+            table = "dog_view", timing = "instead of", events = ["insert", "update", "delete"]
+        })
+        .unwrap();
+        assert_eq!(trigger.timing.unwrap().value(), "instead of");
+
+        let trigger = PgTrigger::split_attributes(quote! {
+            table = "dog_view", timing = "instead of", events = ["insert"], level = "statement"
+        });
+        let message = trigger.err().unwrap().to_string();
+        assert!(message.starts_with("`instead of` triggers must have"));
+    }
+
+    #[test]
+    fn language_handler_attributes() {
+        let (handler, extern_attr) = PgLanguageHandler::split_attributes(quote! {
+            language = "plsample", trusted, validator = "plsample_validator", name = "plsample_call"
+        })
+        .unwrap();
+        assert_eq!(handler.language.unwrap().value(), "plsample");
+        assert!(handler.trusted);
+        assert_eq!(handler.validator.unwrap().value(), "plsample_validator");
+        assert_eq!(extern_attr.to_string(), quote! { name = "plsample_call" }.to_string());
+
+        let handler = PgLanguageHandler::split_attributes(quote! { trusted });
+        let message = handler.err().unwrap().to_string();
+        assert!(message.starts_with("`#[pg_language_handler]` needs a `language`"));
+    }
+
+    #[test]
+    fn test_attributes() {
+        let (test, extern_attr) = PgTest::split_attributes(quote! {
+            setup = "create_dogs", error = "no dogs", teardown = "fixtures::drop_dogs"
+        })
+        .unwrap();
+        assert!(test.setup.unwrap().is_ident("create_dogs"));
+        assert_eq!(test.teardown.unwrap().segments.len(), 2);
+        assert_eq!(extern_attr.to_string(), quote! { error = "no dogs" }.to_string());
+
+        let test = PgTest::split_attributes(quote! { setup = create_dogs });
+        let message = test.err().unwrap().to_string();
+        assert!(message.starts_with("expected `setup = \"function_name\"`"));
+    }
+
+    #[test]
+    fn expected_error_attributes() {
+        let (test, extern_attr) = PgTest::split_attributes(quote! {
+            sqlstate = ERRCODE_DIVISION_BY_ZERO, hint_contains = "zero", strict
+        })
+        .unwrap();
+        assert!(test.expects_error());
+        assert_eq!(test.hint_contains.unwrap().value(), "zero");
+        assert_eq!(extern_attr.to_string(), quote! { strict }.to_string());
+
+        let test = PgTest::split_attributes(quote! { sqlstate = "220" });
+        let message = test.err().unwrap().to_string();
+        assert!(message.starts_with("a SQLSTATE has five characters"));
+    }
+
+    #[test]
+    fn guc_attributes() {
+        let (test, _) = PgTest::split_attributes(
+            quote! { guc = "work_mem=64MB", guc = "my_ext.mode = strict" },
+        )
+        .unwrap();
+        assert_eq!(
+            test.settings,
+            vec![
+                ("work_mem".to_string(), "64MB".to_string()),
+                ("my_ext.mode".to_string(), "strict".to_string()),
+            ]
+        );
+
+        let test = PgTest::split_attributes(quote! { guc = "work_mem" });
+        let message = test.err().unwrap().to_string();
+        assert!(message.starts_with("expected `guc = \"name=value\"`"));
+    }
+
+    #[test]
+    fn pg_version_attributes() {
+        let (test, _) = PgTest::split_attributes(quote! { min_pg = 11, skip_pg = 13 }).unwrap();
+        assert_eq!(test.pg_versions(), Some(vec![11, 12, 14]));
+
+        let (test, _) = PgTest::split_attributes(quote! { strict }).unwrap();
+        assert_eq!(test.pg_versions(), None);
+
+        let test = PgTest::split_attributes(quote! { min_pg = 9 });
+        let message = test.err().unwrap().to_string();
+        assert!(message.starts_with("expected a supported Postgres major version"));
+
+        let test = PgTest::split_attributes(quote! { min_pg = 14, max_pg = 13 });
+        let message = test.err().unwrap().to_string();
+        assert_eq!(
+            message,
+            "the test doesn't run against any supported Postgres version"
+        );
+    }
+
+    #[test]
+    fn role_attribute() {
+        let (test, extern_attr) =
+            PgTest::split_attributes(quote! { role = "app_user", strict }).unwrap();
+        assert_eq!(test.role.unwrap().value(), "app_user");
+        assert_eq!(extern_attr.to_string(), quote! { strict }.to_string());
+
+        let test = PgTest::split_attributes(quote! { role = app_user });
+        let message = test.err().unwrap().to_string();
+        assert_eq!(message, "expected `role = \"role_name\"`");
+    }
+
+    #[test]
+    fn proptest_attributes() {
+        let (proptest, test_attr) =
+            PgProptest::split_attributes(quote! { cases = 64, setup = "create_dogs" }).unwrap();
+        assert!(proptest.cases.is_some());
+        assert_eq!(
+            test_attr.to_string(),
+            quote! { setup = "create_dogs" }.to_string()
+        );
+
+        let func = proptest
+            .rewrite(syn::parse_quote! {
+                fn prop_example(value: i32, #[strategy(0..10u8)] small: u8) {}
+            })
+            .unwrap();
+        assert!(func.sig.inputs.is_empty());
+
+        let rewritten = PgProptest::default().rewrite(syn::parse_quote! { fn prop_example() {} });
+        let message = rewritten.err().unwrap().to_string();
+        assert!(message.starts_with("a `#[pg_proptest]` function needs arguments"));
+    }
+
+    #[test]
+    fn bench_attributes() {
+        let (bench, extern_attr) =
+            PgBench::split_attributes(quote! { samples = 20, warm_up_ms = 100, strict }).unwrap();
+        assert!(bench.samples.is_some() && bench.warm_up_ms.is_some());
+        assert!(bench.measurement_ms.is_none());
+        assert_eq!(extern_attr.to_string(), quote! { strict }.to_string());
+
+        let func = bench
+            .rewrite(syn::parse_quote! {
+                fn bench_example(b: &mut Bencher) {}
+            })
+            .unwrap();
+        assert!(func.sig.inputs.is_empty());
+        assert!(matches!(func.sig.output, syn::ReturnType::Type(..)));
+
+        let rewritten = PgBench::default().rewrite(syn::parse_quote! { fn bench_example() {} });
+        let message = rewritten.err().unwrap().to_string();
+        assert!(message.starts_with("a `#[pg_bench]` function takes only its `&mut Bencher`"));
+    }
+}
@@ -22,8 +22,12 @@ pub enum Returning {
     Type(syn::Type),
     SetOf(syn::TypePath),
     Iterated(Vec<(syn::Type, Option<String>)>),
+    /// A single row, returned through `OUT` arguments
+    Record(Vec<(syn::Type, Option<String>)>),
     /// `pgx_pg_sys::Datum`
     Trigger,
+    /// A `TableIterator` of rows whose columns are declared by their `TableRow` implementation
+    TableRow(syn::Type),
 }
 
 impl Returning {
@@ -33,7 +37,9 @@ impl Returning {
             "Iterator" => match &mut last_path_segment.arguments {
                 syn::PathArguments::AngleBracketed(args) => match args.args.first_mut().unwrap() {
                     syn::GenericArgument::Binding(binding) => match &mut binding.ty {
-                        syn::Type::Tuple(tuple_type) => Self::parse_type_tuple(tuple_type),
+                        syn::Type::Tuple(tuple_type) => {
+                            Returning::Iterated(Self::parse_type_tuple(tuple_type))
+                        }
                         syn::Type::Path(path) => {
                             Returning::SetOf(anonymonize_lifetimes_in_type_path(path.clone()))
                         }
@@ -53,7 +59,7 @@ impl Returning {
         }
     }
 
-    fn parse_type_tuple(type_tuple: &mut syn::TypeTuple) -> Returning {
+    fn parse_type_tuple(type_tuple: &mut syn::TypeTuple) -> Vec<(syn::Type, Option<String>)> {
         let returns: Vec<(syn::Type, Option<_>)> = type_tuple
             .elems
             .iter_mut()
@@ -79,7 +85,24 @@ impl Returning {
                 }
             })
             .collect();
-        Returning::Iterated(returns)
+        returns
+    }
+
+    /// The row type of a `TableIterator<'a, Row>`
+    fn parse_table_iterator(type_path: &syn::TypePath) -> Option<syn::Type> {
+        let last_path_segment = type_path.path.segments.last()?;
+        if last_path_segment.ident != "TableIterator" {
+            return None;
+        }
+        match &last_path_segment.arguments {
+            syn::PathArguments::AngleBracketed(args) => {
+                args.args.iter().find_map(|arg| match arg {
+                    syn::GenericArgument::Type(row) => Some(row.clone()),
+                    _ => None,
+                })
+            }
+            _ => None,
+        }
     }
 
     fn parse_impl_trait(impl_trait: &mut syn::TypeImplTrait) -> Returning {
@@ -114,6 +137,11 @@ impl TryFrom<&syn::ReturnType> for Returning {
                     syn::Type::TraitObject(mut dyn_trait) => {
                         Returning::parse_dyn_trait(&mut dyn_trait)
                     }
+                    syn::Type::Path(typepath)
+                        if Self::parse_table_iterator(&typepath).is_some() =>
+                    {
+                        Returning::TableRow(Self::parse_table_iterator(&typepath).unwrap())
+                    }
                     syn::Type::Path(mut typepath) => {
                         let path = &mut typepath.path;
                         let mut saw_pg_sys = false;
@@ -147,6 +175,22 @@ impl TryFrom<&syn::ReturnType> for Returning {
                                                 maybe_inner_impl_trait =
                                                     Some(Returning::parse_dyn_trait(dyn_trait))
                                             }
+                                            Some(syn::GenericArgument::Type(syn::Type::Path(
+                                                inner_path,
+                                            ))) if Returning::parse_table_iterator(inner_path)
+                                                .is_some() =>
+                                            {
+                                                maybe_inner_impl_trait =
+                                                    Returning::parse_table_iterator(inner_path)
+                                                        .map(Returning::TableRow)
+                                            }
+                                            Some(syn::GenericArgument::Type(syn::Type::Tuple(
+                                                tuple_type,
+                                            ))) if !tuple_type.elems.is_empty() => {
+                                                maybe_inner_impl_trait = Some(Returning::Record(
+                                                    Returning::parse_type_tuple(tuple_type),
+                                                ))
+                                            }
                                             _ => (),
                                         }
                                     }
@@ -192,7 +236,7 @@ impl TryFrom<&syn::ReturnType> for Returning {
                         if tup.elems.is_empty() {
                             Returning::Type(ty.clone())
                         } else {
-                            Self::parse_type_tuple(tup)
+                            Returning::Record(Self::parse_type_tuple(tup))
                         }
                     }
                     _ => return Err(eyre!("Got unknown return type: {}", &ty.to_token_stream())),
@@ -240,7 +284,7 @@ impl ToTokens for Returning {
                     }
                 }
             }
-            Returning::Iterated(items) => {
+            Returning::Iterated(items) | Returning::Record(items) => {
                 let quoted_items = items
                     .iter()
                     .map(|(ty, name)| {
@@ -262,15 +306,27 @@ impl ToTokens for Returning {
                         }
                     })
                     .collect::<Vec<_>>();
-                quote! {
-                    ::pgx::utils::sql_entity_graph::PgExternReturnEntity::Iterated(vec![
-                        #(#quoted_items),*
-                    ])
+                match self {
+                    Returning::Record(_) => quote! {
+                        ::pgx::utils::sql_entity_graph::PgExternReturnEntity::Record(vec![
+                            #(#quoted_items),*
+                        ])
+                    },
+                    _ => quote! {
+                        ::pgx::utils::sql_entity_graph::PgExternReturnEntity::Iterated(vec![
+                            #(#quoted_items),*
+                        ])
+                    },
                 }
             }
             Returning::Trigger => quote! {
                 ::pgx::utils::sql_entity_graph::PgExternReturnEntity::Trigger
             },
+            Returning::TableRow(row) => quote! {
+                ::pgx::utils::sql_entity_graph::PgExternReturnEntity::Iterated(
+                    <#row as ::pgx::TableRow>::table_columns()
+                )
+            },
         };
         tokens.append_all(quoted);
     }
@@ -0,0 +1,417 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use super::split_attributes;
+use crate::SUPPORTED_MAJOR_VERSIONS;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::spanned::Spanned;
+
+/// A parsed `#[pg_test]` test's fixtures, and the ERROR it expects.
+///
+/// The test itself is a [`PgExtern`](crate::sql_entity_graph::PgExtern), which `#[pg_test]` wraps
+/// in its fixtures.
+#[derive(Debug, Clone, Default)]
+pub struct PgTest {
+    /// The function run before the test, in its transaction.
+    pub setup: Option<syn::Path>,
+    /// The function run after the test passes, in its transaction.
+    pub teardown: Option<syn::Path>,
+    /// The SQLSTATE of the expected ERROR, as a literal, such as `"22012"`, or a
+    /// `PgSqlErrorCode`, such as `ERRCODE_DIVISION_BY_ZERO`.
+    pub sqlstate: Option<syn::Expr>,
+    /// Text the expected ERROR's message contains.
+    pub error_contains: Option<syn::LitStr>,
+    /// Text the expected ERROR's detail contains.
+    pub detail_contains: Option<syn::LitStr>,
+    /// Text the expected ERROR's hint contains.
+    pub hint_contains: Option<syn::LitStr>,
+    /// The GUCs set for the test, such as `("work_mem", "64MB")`.
+    pub settings: Vec<(String, String)>,
+    /// The oldest Postgres major version the test runs against.
+    pub min_pg: Option<u16>,
+    /// The newest Postgres major version the test runs against.
+    pub max_pg: Option<u16>,
+    /// The Postgres major versions the test doesn't run against.
+    pub skip_pg: Vec<u16>,
+    /// The role the test runs as, rather than the superuser.
+    pub role: Option<syn::LitStr>,
+}
+
+const TEST_ARGS: &[&str] = &[
+    "setup",
+    "teardown",
+    "sqlstate",
+    "error_contains",
+    "detail_contains",
+    "hint_contains",
+    "guc",
+    "min_pg",
+    "max_pg",
+    "skip_pg",
+    "role",
+];
+
+impl PgTest {
+    /// Separate the `setup = "..."`, `teardown = "..."`, `sqlstate = ...`,
+    /// `error_contains = "..."`, `detail_contains = "..."`, `hint_contains = "..."`,
+    /// `guc = "..."`, `min_pg = ...`, `max_pg = ...`, `skip_pg = ...`, and `role = "..."`
+    /// arguments of a `#[pg_test]` attribute from the remaining arguments, which are passed along
+    /// to `#[pg_extern]`.
+    pub fn split_attributes(attr: TokenStream2) -> Result<(Self, TokenStream2), syn::Error> {
+        let span = attr.span();
+        let (args, extern_attrs) = split_attributes(attr, TEST_ARGS)?;
+        let mut test = PgTest::default();
+        for arg in args {
+            test.parse_arg(arg)?;
+        }
+        if test.pg_versions() == Some(Vec::new()) {
+            return Err(syn::Error::new(
+                span,
+                "the test doesn't run against any supported Postgres version",
+            ));
+        }
+        Ok((test, extern_attrs))
+    }
+
+    /// The Postgres major versions the test runs against, if its `min_pg`, `max_pg`, or
+    /// `skip_pg` leave out any of the supported versions.
+    pub fn pg_versions(&self) -> Option<Vec<u16>> {
+        let versions = SUPPORTED_MAJOR_VERSIONS
+            .iter()
+            .copied()
+            .filter(|version| self.min_pg.is_none_or(|min| *version >= min))
+            .filter(|version| self.max_pg.is_none_or(|max| *version <= max))
+            .filter(|version| !self.skip_pg.contains(version))
+            .collect::<Vec<_>>();
+        if versions.len() == SUPPORTED_MAJOR_VERSIONS.len() {
+            None
+        } else {
+            Some(versions)
+        }
+    }
+
+    /// Does the test expect an ERROR with a SQLSTATE, or with text in its message, detail, or
+    /// hint?
+    pub fn expects_error(&self) -> bool {
+        self.sqlstate.is_some()
+            || self.error_contains.is_some()
+            || self.detail_contains.is_some()
+            || self.hint_contains.is_some()
+    }
+
+    fn parse_arg(&mut self, arg: syn::Expr) -> Result<(), syn::Error> {
+        let arg = match arg {
+            syn::Expr::Assign(arg) => arg,
+            other => return Err(syn::Error::new(other.span(), "expected `name = value`")),
+        };
+        let name = arg.left.to_token_stream().to_string();
+        let value = *arg.right;
+        if matches!(name.as_str(), "min_pg" | "max_pg" | "skip_pg") {
+            let version = match &value {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Int(version),
+                    ..
+                }) => version.base10_parse::<u16>()?,
+                other => {
+                    return Err(syn::Error::new(
+                        other.span(),
+                        format!("expected `{} = 14`", name),
+                    ))
+                }
+            };
+            if !SUPPORTED_MAJOR_VERSIONS.contains(&version) {
+                return Err(syn::Error::new(
+                    value.span(),
+                    format!(
+                        "expected a supported Postgres major version: {:?}",
+                        SUPPORTED_MAJOR_VERSIONS
+                    ),
+                ));
+            }
+            match name.as_str() {
+                "min_pg" => self.min_pg = Some(version),
+                "max_pg" => self.max_pg = Some(version),
+                _ => self.skip_pg.push(version),
+            }
+            return Ok(());
+        }
+        if name == "sqlstate" {
+            return match value {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(ref sqlstate),
+                    ..
+                }) if sqlstate.value().len() != 5 => Err(syn::Error::new(
+                    sqlstate.span(),
+                    "a SQLSTATE has five characters, such as \"22012\"",
+                )),
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(_),
+                    ..
+                })
+                | syn::Expr::Path(_) => {
+                    self.sqlstate = Some(value);
+                    Ok(())
+                }
+                other => Err(syn::Error::new(
+                    other.span(),
+                    "expected `sqlstate = \"22012\"` or `sqlstate = ERRCODE_DIVISION_BY_ZERO`",
+                )),
+            };
+        }
+
+        let value = match value {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(value),
+                ..
+            }) => value,
+            other => {
+                let expected = match name.as_str() {
+                    "setup" | "teardown" => "\"function_name\"",
+                    "guc" => "\"name=value\"",
+                    "role" => "\"role_name\"",
+                    _ => "\"text\"",
+                };
+                return Err(syn::Error::new(
+                    other.span(),
+                    format!("expected `{} = {}`", name, expected),
+                ));
+            }
+        };
+        match name.as_str() {
+            "setup" => self.setup = Some(value.parse()?),
+            "teardown" => self.teardown = Some(value.parse()?),
+            "error_contains" => self.error_contains = Some(value),
+            "detail_contains" => self.detail_contains = Some(value),
+            "hint_contains" => self.hint_contains = Some(value),
+            "role" => self.role = Some(value),
+            "guc" => match value.value().split_once('=') {
+                Some((guc, setting)) if !guc.trim().is_empty() => self
+                    .settings
+                    .push((guc.trim().to_string(), setting.trim().to_string())),
+                _ => {
+                    return Err(syn::Error::new(
+                        value.span(),
+                        "expected `guc = \"name=value\"`, such as `guc = \"work_mem=64MB\"`",
+                    ))
+                }
+            },
+            _ => unreachable!("not a `#[pg_test]` argument"),
+        }
+        Ok(())
+    }
+
+    /// Wrap the body of the test `func` in its fixtures.
+    ///
+    /// The teardown runs however the test returns, unless it panics, as its transaction is then
+    /// rolled back, and Postgres can't run anything more in it.  The transaction is rolled back
+    /// when the test passes too, so nothing the teardown does in the database outlasts the test.
+    pub fn wrap(&self, mut func: syn::ItemFn) -> syn::ItemFn {
+        let setup = self.setup.iter();
+        let teardown = self.teardown.iter();
+        let block = &func.block;
+        func.block = syn::parse_quote! {{
+            #(#setup();)*
+            #(
+                struct __PgxTeardown;
+                impl Drop for __PgxTeardown {
+                    fn drop(&mut self) {
+                        if !::std::thread::panicking() {
+                            #teardown();
+                        }
+                    }
+                }
+                let _teardown = __PgxTeardown;
+            )*
+            #block
+        }};
+        func
+    }
+}
+
+/// A parsed `#[pg_proptest]` property test.
+///
+/// `#[pg_proptest]` rewrites the function into a `#[pg_test]`, which runs it with the inputs
+/// proptest generates for its arguments.
+#[derive(Debug, Clone, Default)]
+pub struct PgProptest {
+    /// The number of passing cases the test needs, rather than proptest's default.
+    pub cases: Option<syn::Expr>,
+}
+
+impl PgProptest {
+    /// Separate the `cases = ...` argument of a `#[pg_proptest]` attribute from the remaining
+    /// arguments, which are passed along to `#[pg_test]`.
+    pub fn split_attributes(attr: TokenStream2) -> Result<(Self, TokenStream2), syn::Error> {
+        let (args, test_attrs) = split_attributes(attr, &["cases"])?;
+        let mut proptest = PgProptest::default();
+        for arg in args {
+            match arg {
+                syn::Expr::Assign(arg) => proptest.cases = Some(*arg.right),
+                other => return Err(syn::Error::new(other.span(), "expected `cases = ...`")),
+            }
+        }
+        Ok((proptest, test_attrs))
+    }
+
+    /// Rewrite the property test `func`, whose arguments are generated by proptest, into a test
+    /// without arguments.
+    ///
+    /// An argument's input is generated by its `#[strategy(...)]`, if it has one, or else by
+    /// proptest's `any::<T>()` for its type.
+    pub fn rewrite(&self, mut func: syn::ItemFn) -> Result<syn::ItemFn, syn::Error> {
+        if let syn::ReturnType::Type(_, ty) = &func.sig.output {
+            return Err(syn::Error::new(
+                ty.span(),
+                "a `#[pg_proptest]` function can't return a value",
+            ));
+        }
+        if func.sig.inputs.is_empty() {
+            return Err(syn::Error::new(
+                func.sig.span(),
+                "a `#[pg_proptest]` function needs arguments to generate",
+            ));
+        }
+
+        let mut patterns = Vec::new();
+        let mut types = Vec::new();
+        let mut strategies = Vec::new();
+        for input in &func.sig.inputs {
+            let arg = match input {
+                syn::FnArg::Typed(arg) => arg,
+                syn::FnArg::Receiver(receiver) => {
+                    return Err(syn::Error::new(
+                        receiver.span(),
+                        "a `#[pg_proptest]` function can't take `self`",
+                    ))
+                }
+            };
+            let ty = &arg.ty;
+            let strategy = match arg.attrs.iter().find(|attr| attr.path.is_ident("strategy")) {
+                Some(attr) => attr.parse_args::<syn::Expr>()?.to_token_stream(),
+                None => quote! { ::pgx::proptest::arbitrary::any::<#ty>() },
+            };
+            patterns.push(&arg.pat);
+            types.push(ty);
+            strategies.push(strategy);
+        }
+
+        let config = match &self.cases {
+            Some(cases) => quote! {
+                ::pgx::proptest::test_runner::Config {
+                    cases: #cases,
+                    ..::pgx::proptest::test_runner::Config::default()
+                }
+            },
+            None => quote! { ::pgx::proptest::test_runner::Config::default() },
+        };
+        let name = func.sig.ident.to_string();
+        let block = &func.block;
+        let body: syn::Block = syn::parse_quote! {{
+            ::pgx::run_proptest(
+                #name,
+                #config,
+                (#(#strategies,)*),
+                |(#(#patterns,)*): (#(#types,)*)| #block,
+            )
+        }};
+        func.block = Box::new(body);
+        func.sig.inputs.clear();
+        Ok(func)
+    }
+}
+
+/// A parsed `#[pg_bench]` benchmark.
+///
+/// `#[pg_bench]` rewrites the function, which times its routine with the `Bencher` it's passed,
+/// into a `#[pg_extern]` function returning the benchmark's `BenchResult`.
+#[derive(Debug, Clone, Default)]
+pub struct PgBench {
+    /// The number of samples taken, rather than the default of 100.
+    pub samples: Option<syn::Expr>,
+    /// The milliseconds the routine runs for before it's measured, rather than the default of
+    /// 3000.
+    pub warm_up_ms: Option<syn::Expr>,
+    /// The milliseconds the routine is measured for, rather than the default of 5000.
+    pub measurement_ms: Option<syn::Expr>,
+}
+
+impl PgBench {
+    /// Separate the `samples = ...`, `warm_up_ms = ...`, and `measurement_ms = ...` arguments of
+    /// a `#[pg_bench]` attribute from the remaining arguments, which are passed along to
+    /// `#[pg_extern]`.
+    pub fn split_attributes(attr: TokenStream2) -> Result<(Self, TokenStream2), syn::Error> {
+        let (args, extern_attrs) =
+            split_attributes(attr, &["samples", "warm_up_ms", "measurement_ms"])?;
+        let mut bench = PgBench::default();
+        for arg in args {
+            let arg = match arg {
+                syn::Expr::Assign(arg) => arg,
+                other => return Err(syn::Error::new(other.span(), "expected `name = value`")),
+            };
+            match arg.left.to_token_stream().to_string().as_str() {
+                "samples" => bench.samples = Some(*arg.right),
+                "warm_up_ms" => bench.warm_up_ms = Some(*arg.right),
+                "measurement_ms" => bench.measurement_ms = Some(*arg.right),
+                _ => unreachable!("not a `#[pg_bench]` argument"),
+            }
+        }
+        Ok((bench, extern_attrs))
+    }
+
+    /// Rewrite the benchmark `func`, whose only argument is its `&mut Bencher`, into a function
+    /// without arguments which returns its `BenchResult`.
+    pub fn rewrite(&self, mut func: syn::ItemFn) -> Result<syn::ItemFn, syn::Error> {
+        if let syn::ReturnType::Type(_, ty) = &func.sig.output {
+            return Err(syn::Error::new(
+                ty.span(),
+                "a `#[pg_bench]` function can't return a value",
+            ));
+        }
+        let bencher = match func.sig.inputs.first() {
+            Some(syn::FnArg::Typed(arg)) if func.sig.inputs.len() == 1 => &arg.pat,
+            _ => {
+                return Err(syn::Error::new(
+                    func.sig.inputs.span(),
+                    "a `#[pg_bench]` function takes only its `&mut Bencher`",
+                ))
+            }
+        };
+
+        let mut fields = Vec::new();
+        if let Some(samples) = &self.samples {
+            fields.push(quote! { samples: #samples, });
+        }
+        if let Some(warm_up_ms) = &self.warm_up_ms {
+            fields.push(quote! { warm_up: ::std::time::Duration::from_millis(#warm_up_ms), });
+        }
+        if let Some(measurement_ms) = &self.measurement_ms {
+            fields
+                .push(quote! { measurement: ::std::time::Duration::from_millis(#measurement_ms), });
+        }
+        let name = func.sig.ident.to_string();
+        let block = &func.block;
+        let body: syn::Block = syn::parse_quote! {{
+            let config = ::pgx::BenchConfig {
+                #(#fields)*
+                ..::pgx::BenchConfig::default()
+            };
+            ::pgx::TableIterator::once(::pgx::run_bench(
+                #name,
+                config,
+                |#bencher: &mut ::pgx::Bencher| #block,
+            ))
+        }};
+        func.block = Box::new(body);
+        func.sig.inputs.clear();
+        func.sig.output = syn::parse_quote! {
+            -> ::pgx::TableIterator<'static, ::pgx::BenchResult>
+        };
+        Ok(func)
+    }
+}
@@ -0,0 +1,271 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use super::split_attributes;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, ToTokens, TokenStreamExt};
+use syn::spanned::Spanned;
+
+/// A parsed `#[pg_trigger]` trigger.
+///
+/// It is attached to a [`PgExtern`](crate::sql_entity_graph::PgExtern) by `#[pg_trigger]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PgTrigger {
+    /// The table to create a trigger on, if any.
+    pub table: Option<syn::LitStr>,
+    /// When the trigger fires, such as `before`, or `instead of` for a trigger on a view.
+    pub timing: Option<syn::LitStr>,
+    /// The events the trigger fires for, such as `insert`.
+    pub events: Vec<syn::LitStr>,
+    /// The columns an `update` must set for the trigger to fire, if it's limited to some.
+    pub update_of: Vec<syn::LitStr>,
+    /// Whether the trigger fires for each `row` or `statement`.
+    pub level: Option<syn::LitStr>,
+    /// The condition rows must meet for the trigger to fire, such as `NEW.name IS NOT NULL`.
+    pub when: Option<syn::LitStr>,
+    /// Whether the trigger is a `CONSTRAINT TRIGGER`.
+    pub constraint: bool,
+    /// Whether the constraint trigger is `DEFERRABLE`.
+    pub deferrable: bool,
+    /// Whether the constraint trigger is `INITIALLY DEFERRED`.
+    pub initially_deferred: bool,
+}
+
+const TRIGGER_ARGS: &[&str] = &[
+    "table",
+    "timing",
+    "events",
+    "update_of",
+    "level",
+    "when",
+    "constraint",
+    "deferrable",
+    "initially_deferred",
+];
+
+impl PgTrigger {
+    /// Separate the `table = "..."`, `timing = "..."`, `events = [...]`, `update_of = [...]`,
+    /// `level = "..."`, and `when = "..."` arguments of a `#[pg_trigger]` attribute from the
+    /// remaining arguments, which are passed along to `#[pg_extern]`.
+    pub fn split_attributes(attr: TokenStream2) -> Result<(Self, TokenStream2), syn::Error> {
+        let (args, extern_attrs) = split_attributes(attr, TRIGGER_ARGS)?;
+        let mut trigger = PgTrigger::default();
+        for arg in args {
+            trigger.parse_arg(arg)?;
+        }
+        trigger.validate()?;
+        Ok((trigger, extern_attrs))
+    }
+
+    fn parse_arg(&mut self, arg: syn::Expr) -> Result<(), syn::Error> {
+        let arg = match arg {
+            syn::Expr::Assign(arg) => arg,
+            syn::Expr::Path(flag) if flag.path.is_ident("constraint") => {
+                self.constraint = true;
+                return Ok(());
+            }
+            syn::Expr::Path(flag) if flag.path.is_ident("deferrable") => {
+                self.deferrable = true;
+                return Ok(());
+            }
+            syn::Expr::Path(flag) if flag.path.is_ident("initially_deferred") => {
+                self.initially_deferred = true;
+                return Ok(());
+            }
+            other => return Err(syn::Error::new(other.span(), "expected `name = value`")),
+        };
+        let name = arg.left.to_token_stream().to_string();
+        match name.as_str() {
+            "table" => self.table = Some(lit_str(*arg.right)?),
+            "timing" => self.timing = Some(lit_str(*arg.right)?),
+            "level" => self.level = Some(lit_str(*arg.right)?),
+            "when" => self.when = Some(lit_str(*arg.right)?),
+            "events" => self.events = lit_strs(*arg.right)?,
+            "update_of" => self.update_of = lit_strs(*arg.right)?,
+            _ => {
+                return Err(syn::Error::new(
+                    arg.left.span(),
+                    format!("expected one of {}", TRIGGER_ARGS.join(", ")),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), syn::Error> {
+        let table = match &self.table {
+            Some(table) => table,
+            None if *self == PgTrigger::default() => return Ok(()),
+            None => {
+                return Err(syn::Error::new(
+                    Span::call_site(),
+                    "`#[pg_trigger]` needs a `table` to create its trigger on",
+                ))
+            }
+        };
+
+        match &self.timing {
+            Some(timing) => one_of(timing, &["before", "after", "instead of"])?,
+            None => {
+                return Err(syn::Error::new(
+                    table.span(),
+                    "`#[pg_trigger]` needs a `timing` for its trigger, such as \"before\"",
+                ))
+            }
+        }
+        if self.events.is_empty() {
+            return Err(syn::Error::new(
+                table.span(),
+                "`#[pg_trigger]` needs `events` for its trigger, such as [\"insert\"]",
+            ));
+        }
+        for event in &self.events {
+            one_of(event, &["insert", "update", "delete", "truncate"])?;
+        }
+        if let Some(level) = &self.level {
+            one_of(level, &["row", "statement"])?;
+        }
+        if self.is_instead_of() {
+            self.validate_instead_of()?;
+        }
+        if self.constraint {
+            match (&self.timing, &self.level) {
+                (Some(timing), _) if timing.value() != "after" => {
+                    return Err(syn::Error::new(
+                        timing.span(),
+                        "constraint triggers must have `timing = \"after\"`",
+                    ))
+                }
+                (_, Some(level)) if level.value() != "row" => {
+                    return Err(syn::Error::new(
+                        level.span(),
+                        "constraint triggers must have `level = \"row\"`",
+                    ))
+                }
+                _ => (),
+            }
+            if let Some(event) = self.events.iter().find(|event| event.value() == "truncate") {
+                return Err(syn::Error::new(
+                    event.span(),
+                    "constraint triggers can't fire for \"truncate\"",
+                ));
+            }
+        } else if self.deferrable || self.initially_deferred {
+            return Err(syn::Error::new(
+                table.span(),
+                "only `constraint` triggers can be `deferrable` or `initially_deferred`",
+            ));
+        }
+        if let Some(column) = self.update_of.first() {
+            if !self.events.iter().any(|event| event.value() == "update") {
+                return Err(syn::Error::new(
+                    column.span(),
+                    "`update_of` needs the trigger's `events` to include \"update\"",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn is_instead_of(&self) -> bool {
+        matches!(&self.timing, Some(timing) if timing.value() == "instead of")
+    }
+
+    /// `INSTEAD OF` triggers are only on views, where they replace the operation on each row.
+    fn validate_instead_of(&self) -> Result<(), syn::Error> {
+        if let Some(level) = self.level.iter().find(|level| level.value() != "row") {
+            return Err(syn::Error::new(
+                level.span(),
+                "`instead of` triggers must have `level = \"row\"`",
+            ));
+        }
+        if let Some(event) = self.events.iter().find(|event| event.value() == "truncate") {
+            return Err(syn::Error::new(
+                event.span(),
+                "`instead of` triggers can't fire for \"truncate\"",
+            ));
+        }
+        if let Some(column) = self.update_of.first() {
+            return Err(syn::Error::new(
+                column.span(),
+                "`instead of` triggers can't have `update_of`",
+            ));
+        }
+        if let Some(when) = &self.when {
+            return Err(syn::Error::new(
+                when.span(),
+                "`instead of` triggers can't have a `when` condition",
+            ));
+        }
+        if self.constraint {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "`instead of` triggers can't be `constraint` triggers",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl ToTokens for PgTrigger {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let table = self.table.iter();
+        let timing = self.timing.iter();
+        let events = &self.events;
+        let update_of = &self.update_of;
+        let level = self.level.iter();
+        let when = self.when.iter();
+        let constraint = self.constraint;
+        let deferrable = self.deferrable;
+        let initially_deferred = self.initially_deferred;
+        tokens.append_all(quote! {
+            ::pgx::utils::sql_entity_graph::PgTriggerEntity {
+                table: None #( .unwrap_or(Some(#table)) )*,
+                timing: None #( .unwrap_or(Some(#timing)) )*,
+                events: vec![#(#events),*],
+                update_of: vec![#(#update_of),*],
+                level: None #( .unwrap_or(Some(#level)) )*,
+                when: None #( .unwrap_or(Some(#when)) )*,
+                constraint: #constraint,
+                deferrable: #deferrable,
+                initially_deferred: #initially_deferred,
+            }
+        });
+    }
+}
+
+fn lit_str(expr: syn::Expr) -> Result<syn::LitStr, syn::Error> {
+    match expr {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(lit),
+            ..
+        }) => Ok(lit),
+        other => Err(syn::Error::new(other.span(), "expected a string")),
+    }
+}
+
+fn lit_strs(expr: syn::Expr) -> Result<Vec<syn::LitStr>, syn::Error> {
+    match expr {
+        syn::Expr::Array(array) => array.elems.into_iter().map(lit_str).collect(),
+        other => Err(syn::Error::new(
+            other.span(),
+            "expected an array of strings, such as [\"a\", \"b\"]",
+        )),
+    }
+}
+
+fn one_of(lit: &syn::LitStr, allowed: &[&str]) -> Result<(), syn::Error> {
+    if allowed.contains(&lit.value().as_str()) {
+        Ok(())
+    } else {
+        Err(syn::Error::new(
+            lit.span(),
+            format!("expected one of \"{}\"", allowed.join("\", \"")),
+        ))
+    }
+}
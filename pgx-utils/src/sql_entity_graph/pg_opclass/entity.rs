@@ -0,0 +1,288 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::sql_entity_graph::{
+    pg_opclass::PgOpClassMethod,
+    pgx_sql::PgxSql,
+    to_sql::{entity::ToSqlConfigEntity, ToSql},
+    SqlGraphEntity, SqlGraphIdentifier,
+};
+use core::any::TypeId;
+use eyre::eyre;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct PgOpClassTypeEntity {
+    pub ty_source: &'static str,
+    pub ty_id: TypeId,
+    pub full_path: &'static str,
+}
+
+impl PgOpClassTypeEntity {
+    fn sql_type(&self, context: &PgxSql) -> eyre::Result<String> {
+        context
+            .rust_to_sql(self.ty_id, self.ty_source, self.full_path)
+            .ok_or_else(|| eyre!("Failed to map `{}` to SQL type.", self.full_path))
+    }
+}
+
+/// The output of a [`PgOpClass`](crate::sql_entity_graph::pg_opclass::PgOpClass) from `quote::ToTokens::to_tokens`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct PgOpClassEntity {
+    pub name: &'static str,
+    pub method: PgOpClassMethod,
+    pub file: &'static str,
+    pub line: u32,
+    pub full_path: &'static str,
+    pub module_path: &'static str,
+    /// The indexed type.
+    pub ty: PgOpClassTypeEntity,
+    /// The `STORAGE` type.
+    pub key: PgOpClassTypeEntity,
+    /// The right-hand side type of the operators.
+    pub query: PgOpClassTypeEntity,
+    /// `(strategy, operator)` pairs.
+    pub operators: Vec<(u16, &'static str)>,
+    pub default: bool,
+    pub to_sql_config: ToSqlConfigEntity,
+}
+
+impl PgOpClassEntity {
+    pub(crate) fn opclass_name(&self) -> String {
+        format!("{}_{}_ops", self.name, self.method.as_str())
+    }
+
+    /// The SQL argument list and return type of a support function.
+    fn support_fn_signature(
+        &self,
+        support: &str,
+        ty: &str,
+        key: &str,
+        query: &str,
+    ) -> eyre::Result<(String, String)> {
+        let (args, returns) = match (self.method, support) {
+            (PgOpClassMethod::Gin, "extract_value") => {
+                (format!("{}, internal, internal", ty), "internal".into())
+            }
+            (PgOpClassMethod::Gin, "extract_query") => (
+                format!(
+                    "{}, internal, int2, internal, internal, internal, internal",
+                    query
+                ),
+                "internal".into(),
+            ),
+            (PgOpClassMethod::Gin, "consistent") => (
+                format!(
+                    "internal, int2, {}, int4, internal, internal, internal, internal",
+                    query
+                ),
+                "bool".into(),
+            ),
+            (PgOpClassMethod::Gist, "consistent") => (
+                format!("internal, {}, int2, oid, internal", query),
+                "bool".into(),
+            ),
+            (PgOpClassMethod::Gist, "union") => ("internal, internal".into(), key.to_string()),
+            (PgOpClassMethod::Gist, "compress") | (PgOpClassMethod::Gist, "decompress") => {
+                ("internal".into(), "internal".into())
+            }
+            (PgOpClassMethod::Gist, "penalty") => {
+                ("internal, internal, internal".into(), "internal".into())
+            }
+            (PgOpClassMethod::Gist, "picksplit") => {
+                ("internal, internal".into(), "internal".into())
+            }
+            (PgOpClassMethod::Gist, "same") => {
+                (format!("{}, {}, internal", key, key), "internal".into())
+            }
+            (method, support) => {
+                return Err(eyre!(
+                    "Unknown `{}` support function `{}`.",
+                    method.as_str(),
+                    support
+                ))
+            }
+        };
+        Ok((args, returns))
+    }
+}
+
+impl Ord for PgOpClassEntity {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.file
+            .cmp(other.file)
+            .then_with(|| self.line.cmp(&other.line))
+    }
+}
+
+impl PartialOrd for PgOpClassEntity {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl From<PgOpClassEntity> for SqlGraphEntity {
+    fn from(opclass: PgOpClassEntity) -> Self {
+        SqlGraphEntity::OpClass(opclass)
+    }
+}
+
+impl SqlGraphIdentifier for PgOpClassEntity {
+    fn dot_identifier(&self) -> String {
+        format!("opclass {} {}", self.method.as_str(), self.full_path)
+    }
+    fn rust_identifier(&self) -> String {
+        format!("{}::{}", self.full_path, self.opclass_name())
+    }
+
+    fn file(&self) -> Option<&'static str> {
+        Some(self.file)
+    }
+
+    fn line(&self) -> Option<u32> {
+        Some(self.line)
+    }
+}
+
+impl ToSql for PgOpClassEntity {
+    #[tracing::instrument(level = "debug", err, skip(self, context), fields(identifier = %self.rust_identifier()))]
+    fn to_sql(&self, context: &PgxSql) -> eyre::Result<String> {
+        let self_index = context.opclasses[self];
+        let schema = context.schema_prefix_for(&self_index);
+        let module_pathname = context.get_module_pathname();
+        let ty = self.ty.sql_type(context)?;
+        let key = self.key.sql_type(context)?;
+        let query = self.query.sql_type(context)?;
+
+        let mut functions = Vec::new();
+        let mut items = Vec::new();
+        for (operator_number, operator) in &self.operators {
+            items.push(format!(
+                "\tOPERATOR {operator_number} {operator} ({ty}, {query})",
+                operator_number = operator_number,
+                operator = operator,
+                ty = ty,
+                query = query,
+            ));
+        }
+        for (support_number, support) in self.method.support_functions() {
+            let fn_name = self.method.support_fn_name(self.name, support);
+            let (args, returns) = self.support_fn_signature(support, &ty, &key, &query)?;
+            functions.push(format!(
+                "CREATE FUNCTION {schema}\"{fn_name}\"({args}) RETURNS {returns}\n\
+                 IMMUTABLE STRICT PARALLEL SAFE\n\
+                 LANGUAGE c /* Rust */\n\
+                 AS '{module_pathname}', '{fn_name}_wrapper';",
+                schema = schema,
+                fn_name = fn_name,
+                args = args,
+                returns = returns,
+                module_pathname = module_pathname,
+            ));
+            items.push(format!(
+                "\tFUNCTION {support_number} {schema}\"{fn_name}\"({args})",
+                support_number = support_number,
+                schema = schema,
+                fn_name = fn_name,
+                args = args,
+            ));
+        }
+        items.push(format!("\tSTORAGE {}", key));
+
+        let sql = format!(
+            "\n\
+            -- {file}:{line}\n\
+            -- {full_path}\n\
+            {functions}\n\
+            CREATE OPERATOR CLASS {schema}{opclass_name} {default}FOR TYPE {ty} USING {method} AS\n\
+            {items};\
+            ",
+            file = self.file,
+            line = self.line,
+            full_path = self.full_path,
+            functions = functions.join("\n"),
+            schema = schema,
+            opclass_name = self.opclass_name(),
+            default = if self.default { "DEFAULT " } else { "" },
+            ty = ty,
+            method = self.method.as_str(),
+            items = items.join(",\n"),
+        );
+        tracing::trace!(%sql);
+        Ok(sql)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opclass(method: PgOpClassMethod) -> PgOpClassEntity {
+        let ty = PgOpClassTypeEntity {
+            ty_source: "String",
+            ty_id: TypeId::of::<String>(),
+            full_path: "alloc::string::String",
+        };
+        PgOpClassEntity {
+            name: "tags",
+            method,
+            file: "src/lib.rs",
+            line: 1,
+            full_path: "tags::Tags",
+            module_path: "tags",
+            ty: ty.clone(),
+            key: ty.clone(),
+            query: ty,
+            operators: vec![],
+            default: false,
+            to_sql_config: ToSqlConfigEntity {
+                enabled: true,
+                callback: None,
+                content: None,
+            },
+        }
+    }
+
+    #[test]
+    fn gin_query_arguments_have_the_query_type() {
+        let opclass = opclass(PgOpClassMethod::Gin);
+        let (args, _) = opclass
+            .support_fn_signature("extract_value", "text[]", "text", "tsquery")
+            .unwrap();
+        assert_eq!(args, "text[], internal, internal");
+        let (args, _) = opclass
+            .support_fn_signature("extract_query", "text[]", "text", "tsquery")
+            .unwrap();
+        assert_eq!(
+            args,
+            "tsquery, internal, int2, internal, internal, internal, internal"
+        );
+        let (args, _) = opclass
+            .support_fn_signature("consistent", "text[]", "text", "tsquery")
+            .unwrap();
+        assert_eq!(
+            args,
+            "internal, int2, tsquery, int4, internal, internal, internal, internal"
+        );
+    }
+
+    #[test]
+    fn gist_query_arguments_have_the_query_type() {
+        let opclass = opclass(PgOpClassMethod::Gist);
+        let (args, returns) = opclass
+            .support_fn_signature("consistent", "box", "box", "point")
+            .unwrap();
+        assert_eq!(args, "internal, point, int2, oid, internal");
+        assert_eq!(returns, "bool");
+        let (args, returns) = opclass
+            .support_fn_signature("union", "box", "box", "point")
+            .unwrap();
+        assert_eq!(args, "internal, internal");
+        assert_eq!(returns, "box");
+    }
+}
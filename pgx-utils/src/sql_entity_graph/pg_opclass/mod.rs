@@ -0,0 +1,276 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+pub mod entity;
+
+use convert_case::{Case, Casing};
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use quote::{quote, ToTokens, TokenStreamExt};
+use syn::{parse_quote, spanned::Spanned, ItemImpl, Path, Type};
+
+use crate::sql_entity_graph::ToSqlConfig;
+
+/// The index access method of a [`PgOpClass`].
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PgOpClassMethod {
+    Gin,
+    Gist,
+}
+
+impl PgOpClassMethod {
+    /// The SQL name of the access method.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PgOpClassMethod::Gin => "gin",
+            PgOpClassMethod::Gist => "gist",
+        }
+    }
+
+    /// The `pgx` trait implemented for this access method.
+    fn trait_name(&self) -> &'static str {
+        match self {
+            PgOpClassMethod::Gin => "GinOpClass",
+            PgOpClassMethod::Gist => "GistOpClass",
+        }
+    }
+
+    /// The support functions generated for this access method, in order of their support number.
+    pub fn support_functions(&self) -> &'static [(u16, &'static str)] {
+        match self {
+            PgOpClassMethod::Gin => &[(2, "extract_value"), (3, "extract_query"), (4, "consistent")],
+            PgOpClassMethod::Gist => &[
+                (1, "consistent"),
+                (2, "union"),
+                (3, "compress"),
+                (4, "decompress"),
+                (5, "penalty"),
+                (6, "picksplit"),
+                (7, "same"),
+            ],
+        }
+    }
+
+    /// The name of the SQL function backing the given support function of `type_name`'s operator class.
+    pub fn support_fn_name(&self, type_name: &str, support: &str) -> String {
+        format!(
+            "{}_{}_{}",
+            type_name.to_case(Case::Snake),
+            self.as_str(),
+            support
+        )
+    }
+}
+
+impl ToTokens for PgOpClassMethod {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let quoted = match self {
+            PgOpClassMethod::Gin => quote! { ::pgx::utils::sql_entity_graph::PgOpClassMethod::Gin },
+            PgOpClassMethod::Gist => {
+                quote! { ::pgx::utils::sql_entity_graph::PgOpClassMethod::Gist }
+            }
+        };
+        tokens.append_all(quoted);
+    }
+}
+
+/// A parsed `#[pg_gin_opclass]` or `#[pg_gist_opclass]` item.
+///
+/// Using [`quote::ToTokens`] will output the `impl` item, the support function wrappers, and the
+/// declaration for a `pgx::utils::sql_entity_graph::PgOpClassEntity`.
+#[derive(Debug, Clone)]
+pub struct PgOpClass {
+    item_impl: ItemImpl,
+    method: PgOpClassMethod,
+    target_path: Path,
+    target_ident: Ident,
+    type_key: Type,
+    type_query: Type,
+    to_sql_config: ToSqlConfig,
+}
+
+impl PgOpClass {
+    pub fn new(item_impl: ItemImpl, method: PgOpClassMethod) -> Result<Self, syn::Error> {
+        let to_sql_config =
+            ToSqlConfig::from_attributes(item_impl.attrs.as_slice())?.unwrap_or_default();
+
+        match item_impl.trait_ {
+            Some((_, ref path, _))
+                if path
+                    .segments
+                    .last()
+                    .map(|last| last.ident == method.trait_name())
+                    .unwrap_or(false) => {}
+            _ => {
+                return Err(syn::Error::new(
+                    item_impl.span(),
+                    format!(
+                        "`#[pg_{}_opclass]` only works with the `{}` trait.",
+                        method.as_str(),
+                        method.trait_name()
+                    ),
+                ))
+            }
+        }
+
+        let target_path = match &*item_impl.self_ty {
+            Type::Path(type_path) => type_path.path.clone(),
+            other => {
+                return Err(syn::Error::new(
+                    other.span(),
+                    "Operator classes can only be implemented for type paths.",
+                ))
+            }
+        };
+        let target_ident = target_path
+            .segments
+            .last()
+            .map(|last| last.ident.clone())
+            .ok_or_else(|| {
+                syn::Error::new(
+                    target_path.span(),
+                    "Operator classes only work with types whose path have a final segment.",
+                )
+            })?;
+
+        let type_key = get_impl_type_by_name(&item_impl, "Key")?;
+        let type_query = get_impl_type_by_name(&item_impl, "Query")?;
+
+        Ok(Self {
+            item_impl,
+            method,
+            target_path,
+            target_ident,
+            type_key,
+            type_query,
+            to_sql_config,
+        })
+    }
+
+    fn wrapper_tokens(&self) -> TokenStream2 {
+        let target_path = &self.target_path;
+        let mut tokens = TokenStream2::new();
+        for (_, support) in self.method.support_functions() {
+            let fn_name = self
+                .method
+                .support_fn_name(&self.target_ident.to_string(), support);
+            let wrapper_name =
+                Ident::new(&format!("{}_wrapper", fn_name), self.target_ident.span());
+            let finfo_name = Ident::new(
+                &format!("pg_finfo_{}_wrapper", fn_name),
+                self.target_ident.span(),
+            );
+            let method = Ident::new(self.method.as_str(), Span::call_site());
+            let glue = Ident::new(
+                &format!("{}_{}", self.method.as_str(), support),
+                Span::call_site(),
+            );
+            tokens.append_all(quote! {
+                #[no_mangle]
+                #[doc(hidden)]
+                pub extern "C" fn #finfo_name() -> &'static pg_sys::Pg_finfo_record {
+                    const V1_API: pg_sys::Pg_finfo_record = pg_sys::Pg_finfo_record { api_version: 1 };
+                    &V1_API
+                }
+
+                #[pg_guard]
+                #[no_mangle]
+                #[doc(hidden)]
+                pub unsafe extern "C" fn #wrapper_name(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+                    ::pgx::#method::#glue::<#target_path>(fcinfo)
+                }
+            });
+        }
+        tokens
+    }
+
+    fn entity_tokens(&self) -> TokenStream2 {
+        let target_ident = &self.target_ident;
+        let target_path = &self.target_path;
+        let trait_ident = Ident::new(self.method.trait_name(), Span::call_site());
+        let method = &self.method;
+        let sql_graph_entity_fn_name = Ident::new(
+            &format!(
+                "__pgx_internals_opclass_{}_{}",
+                target_ident.to_string().to_case(Case::Snake),
+                self.method.as_str()
+            ),
+            target_ident.span(),
+        );
+        let target_ty: Type = parse_quote! { #target_path };
+        let target_entity = type_entity_tokens(&target_ty);
+        let key_entity = type_entity_tokens(&self.type_key);
+        let query_entity = type_entity_tokens(&self.type_query);
+        let to_sql_config = &self.to_sql_config;
+        quote! {
+            #[no_mangle]
+            #[doc(hidden)]
+            pub extern "C" fn #sql_graph_entity_fn_name() -> ::pgx::utils::sql_entity_graph::SqlGraphEntity {
+                let submission = ::pgx::utils::sql_entity_graph::PgOpClassEntity {
+                    name: stringify!(#target_ident),
+                    method: #method,
+                    file: file!(),
+                    line: line!(),
+                    full_path: ::core::any::type_name::<#target_path>(),
+                    module_path: module_path!(),
+                    ty: #target_entity,
+                    key: #key_entity,
+                    query: #query_entity,
+                    operators: <#target_path as ::pgx::#trait_ident>::OPERATORS.to_vec(),
+                    default: <#target_path as ::pgx::#trait_ident>::DEFAULT,
+                    to_sql_config: #to_sql_config,
+                };
+                ::pgx::utils::sql_entity_graph::SqlGraphEntity::OpClass(submission)
+            }
+        }
+    }
+}
+
+impl ToTokens for PgOpClass {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let impl_item = &self.item_impl;
+        let wrappers = self.wrapper_tokens();
+        let entity_fn = self.entity_tokens();
+        let inv = quote! {
+            #impl_item
+
+            #wrappers
+
+            #entity_fn
+        };
+        tokens.append_all(inv);
+    }
+}
+
+fn type_entity_tokens(ty: &Type) -> TokenStream2 {
+    let ty_string = ty.to_token_stream().to_string().replace(" ", "");
+    quote! {
+        ::pgx::utils::sql_entity_graph::PgOpClassTypeEntity {
+            ty_source: #ty_string,
+            ty_id: ::core::any::TypeId::of::<#ty>(),
+            full_path: ::core::any::type_name::<#ty>(),
+        }
+    }
+}
+
+fn get_impl_type_by_name(item_impl: &ItemImpl, name: &str) -> Result<Type, syn::Error> {
+    item_impl
+        .items
+        .iter()
+        .find_map(|impl_item| match impl_item {
+            syn::ImplItem::Type(impl_item_type) if impl_item_type.ident == name => {
+                Some(impl_item_type.ty.clone())
+            }
+            _ => None,
+        })
+        .ok_or_else(|| {
+            syn::Error::new(
+                item_impl.span(),
+                format!("Operator class implementations require the `{}` type defined.", name),
+            )
+        })
+}
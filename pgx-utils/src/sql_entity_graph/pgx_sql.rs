@@ -22,6 +22,7 @@ use crate::sql_entity_graph::{
     },
     mapping::{RustSourceOnlySqlMapping, RustSqlMapping},
     pg_extern::entity::{PgExternEntity, PgExternReturnEntity},
+    pg_opclass::entity::PgOpClassEntity,
     positioning_ref::PositioningRef,
     postgres_enum::entity::PostgresEnumEntity,
     postgres_hash::entity::PostgresHashEntity,
@@ -65,6 +66,7 @@ pub struct PgxSql {
     pub ords: HashMap<PostgresOrdEntity, NodeIndex>,
     pub hashes: HashMap<PostgresHashEntity, NodeIndex>,
     pub aggregates: HashMap<PgAggregateEntity, NodeIndex>,
+    pub opclasses: HashMap<PgOpClassEntity, NodeIndex>,
     pub extension_name: String,
     pub versioned_so: bool,
 }
@@ -99,6 +101,7 @@ impl PgxSql {
         let mut ords: Vec<PostgresOrdEntity> = Vec::default();
         let mut hashes: Vec<PostgresHashEntity> = Vec::default();
         let mut aggregates: Vec<PgAggregateEntity> = Vec::default();
+        let mut opclasses: Vec<PgOpClassEntity> = Vec::default();
         for entity in entities {
             match entity {
                 SqlGraphEntity::ExtensionRoot(input_control) => {
@@ -129,6 +132,9 @@ impl PgxSql {
                 SqlGraphEntity::Aggregate(input_hash) => {
                     aggregates.push(input_hash);
                 }
+                SqlGraphEntity::OpClass(input_opclass) => {
+                    opclasses.push(input_opclass);
+                }
             }
         }
 
@@ -169,6 +175,8 @@ impl PgxSql {
             &mapped_enums,
             &mapped_types,
         )?;
+        let mapped_opclasses =
+            initialize_opclasses(&mut graph, root, bootstrap, finalize, opclasses)?;
 
         // Now we can circle back and build up the edge sets.
         connect_schemas(&mut graph, &mapped_schemas, root);
@@ -216,6 +224,14 @@ impl PgxSql {
             &mapped_builtin_types,
             &mapped_externs,
         );
+        connect_opclasses(
+            &mut graph,
+            &mapped_opclasses,
+            &mapped_schemas,
+            &mapped_types,
+            &mapped_enums,
+            &mapped_externs,
+        );
 
         let mut this = Self {
             type_mappings: type_mappings.map(|x| (x.id.clone(), x)).collect(),
@@ -230,6 +246,7 @@ impl PgxSql {
             ords: mapped_ords,
             hashes: mapped_hashes,
             aggregates: mapped_aggregates,
+            opclasses: mapped_opclasses,
             graph: graph,
             graph_root: root,
             graph_bootstrap: bootstrap,
@@ -360,6 +377,10 @@ impl PgxSql {
                         "label = \"{}\", penwidth = 0, style = \"filled\", fillcolor = \"#FFE4E0\", weight = 5, shape = \"diamond\"",
                         node.dot_identifier()
                     ),
+                    SqlGraphEntity::OpClass(_item) => format!(
+                        "label = \"{}\", penwidth = 0, style = \"filled\", fillcolor = \"#FFCFD3\", weight = 5, shape = \"diamond\"",
+                        node.dot_identifier()
+                    ),
                     SqlGraphEntity::CustomSql(_item) => format!(
                         "label = \"{}\", weight = 3, shape = \"signature\"",
                         node.dot_identifier()
@@ -864,7 +885,8 @@ fn initialize_externs(
                         });
                 }
             }
-            PgExternReturnEntity::Iterated(iterated_returns) => {
+            PgExternReturnEntity::Iterated(iterated_returns)
+            | PgExternReturnEntity::Record(iterated_returns) => {
                 for iterated_return in iterated_returns {
                     let mut found = false;
                     for (ty_item, &_ty_index) in mapped_types {
@@ -934,6 +956,21 @@ fn connect_externs(
                         }
                     }
                 }
+                crate::ExternArgs::Support(support) => {
+                    if let Some(target) = find_positioning_ref_target(
+                        support,
+                        types,
+                        enums,
+                        externs,
+                        schemas,
+                        extension_sqls,
+                    ) {
+                        tracing::debug!(from = %item.rust_identifier(), to = %graph[*target].rust_identifier(), "Adding Extern after its support function");
+                        graph.add_edge(*target, index, SqlGraphRelationship::RequiredBy);
+                    } else {
+                        return Err(eyre!("Could not find `support` target: {:?}", support));
+                    }
+                }
                 _ => (),
             }
         }
@@ -1031,7 +1068,8 @@ fn connect_externs(
                     }
                 }
             }
-            PgExternReturnEntity::Iterated(iterated_returns) => {
+            PgExternReturnEntity::Iterated(iterated_returns)
+            | PgExternReturnEntity::Record(iterated_returns) => {
                 for iterated_return in iterated_returns {
                     let mut found = false;
                     for (ty_item, &ty_index) in types {
@@ -1128,6 +1166,7 @@ fn connect_ords(
     externs: &HashMap<PgExternEntity, NodeIndex>,
 ) {
     for (item, &index) in ords {
+        let cross_fn_names = item.cross_fn_names();
         make_schema_connection(
             graph,
             "Ord",
@@ -1147,6 +1186,18 @@ fn connect_ords(
             enums,
         );
 
+        for cross in &item.cross_types {
+            make_type_or_enum_connection(
+                graph,
+                "Ord",
+                index,
+                &item.rust_identifier(),
+                &cross.ty_id,
+                types,
+                enums,
+            );
+        }
+
         for (extern_item, &extern_index) in externs {
             let fn_matches = |fn_name| {
                 item.module_path == extern_item.module_path && extern_item.name == fn_name
@@ -1157,12 +1208,16 @@ fn connect_ords(
             let eq_fn_matches = fn_matches(item.eq_fn_name());
             let gt_fn_matches = fn_matches(item.gt_fn_name());
             let gte_fn_matches = fn_matches(item.ge_fn_name());
+            let cross_fn_matches = cross_fn_names
+                .iter()
+                .any(|cross_fn_name| fn_matches(cross_fn_name.clone()));
             if cmp_fn_matches
                 || lt_fn_matches
                 || lte_fn_matches
                 || eq_fn_matches
                 || gt_fn_matches
                 || gte_fn_matches
+                || cross_fn_matches
             {
                 tracing::debug!(from = ?item.full_path, to = extern_item.full_path, "Adding Ord after Extern edge");
                 graph.add_edge(extern_index, index, SqlGraphRelationship::RequiredBy);
@@ -1199,6 +1254,7 @@ fn connect_hashes(
     externs: &HashMap<PgExternEntity, NodeIndex>,
 ) {
     for (item, &index) in hashes {
+        let cross_fn_names = item.cross_fn_names();
         make_schema_connection(
             graph,
             "Hash",
@@ -1218,15 +1274,30 @@ fn connect_hashes(
             enums,
         );
 
+        for cross in &item.cross_types {
+            make_type_or_enum_connection(
+                graph,
+                "Hash",
+                index,
+                &item.rust_identifier(),
+                &cross.ty_id,
+                types,
+                enums,
+            );
+        }
+
         for (extern_item, &extern_index) in externs {
             let hash_fn_name = item.fn_name();
             let hash_fn_matches =
                 item.module_path == extern_item.module_path && extern_item.name == hash_fn_name;
+            let cross_fn_matches = item.module_path == extern_item.module_path
+                && cross_fn_names
+                    .iter()
+                    .any(|cross_fn_name| extern_item.name == cross_fn_name);
 
-            if hash_fn_matches {
+            if hash_fn_matches || cross_fn_matches {
                 tracing::debug!(from = ?item.full_path, to = extern_item.full_path, "Adding Hash after Extern edge");
                 graph.add_edge(extern_index, index, SqlGraphRelationship::RequiredBy);
-                break;
             }
         }
     }
@@ -1476,6 +1547,75 @@ fn connect_aggregates(
     }
 }
 
+#[tracing::instrument(level = "error", skip_all)]
+fn initialize_opclasses(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    root: NodeIndex,
+    bootstrap: Option<NodeIndex>,
+    finalize: Option<NodeIndex>,
+    opclasses: Vec<PgOpClassEntity>,
+) -> eyre::Result<HashMap<PgOpClassEntity, NodeIndex>> {
+    let mut mapped_opclasses = HashMap::default();
+    for item in opclasses {
+        let entity: SqlGraphEntity = item.clone().into();
+        let index = graph.add_node(entity);
+        mapped_opclasses.insert(item, index);
+        build_base_edges(graph, index, root, bootstrap, finalize);
+    }
+    Ok(mapped_opclasses)
+}
+
+#[tracing::instrument(level = "error", skip_all)]
+fn connect_opclasses(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    opclasses: &HashMap<PgOpClassEntity, NodeIndex>,
+    schemas: &HashMap<SchemaEntity, NodeIndex>,
+    types: &HashMap<PostgresTypeEntity, NodeIndex>,
+    enums: &HashMap<PostgresEnumEntity, NodeIndex>,
+    externs: &HashMap<PgExternEntity, NodeIndex>,
+) {
+    for (item, &index) in opclasses {
+        make_schema_connection(
+            graph,
+            "OpClass",
+            index,
+            &item.rust_identifier(),
+            item.module_path,
+            schemas,
+        );
+
+        for ty in [&item.ty, &item.key, &item.query] {
+            make_type_or_enum_connection(
+                graph,
+                "OpClass",
+                index,
+                &item.rust_identifier(),
+                &ty.ty_id,
+                types,
+                enums,
+            );
+        }
+
+        // The operators of the operator class must exist before it.
+        for (extern_item, &extern_index) in externs {
+            let opname = match extern_item.operator.as_ref().and_then(|op| op.opname) {
+                Some(opname) => opname,
+                None => continue,
+            };
+            let is_operator = item.operators.iter().any(|(_, item_op)| *item_op == opname);
+            let left_matches = extern_item
+                .fn_args
+                .first()
+                .map(|arg| arg.ty_id == item.ty.ty_id)
+                .unwrap_or(false);
+            if is_operator && left_matches {
+                tracing::debug!(from = ?item.full_path, to = extern_item.full_path, "Adding OpClass after Extern edge");
+                graph.add_edge(extern_index, index, SqlGraphRelationship::RequiredBy);
+            }
+        }
+    }
+}
+
 fn make_schema_connection(
     graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
     kind: &str,
@@ -7,6 +7,7 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 use crate::sql_entity_graph::{
+    cross_type::CrossTypeEntity,
     pgx_sql::PgxSql,
     to_sql::{entity::ToSqlConfigEntity, ToSql},
     SqlGraphEntity, SqlGraphIdentifier,
@@ -22,6 +23,7 @@ pub struct PostgresHashEntity {
     pub full_path: &'static str,
     pub module_path: &'static str,
     pub id: core::any::TypeId,
+    pub cross_types: Vec<CrossTypeEntity>,
     pub to_sql_config: ToSqlConfigEntity,
 }
 
@@ -29,6 +31,18 @@ impl PostgresHashEntity {
     pub(crate) fn fn_name(&self) -> String {
         format!("{}_hash", self.name.to_lowercase())
     }
+
+    /// The names of all functions generated for the cross types of this entity.
+    pub(crate) fn cross_fn_names(&self) -> Vec<String> {
+        self.cross_types
+            .iter()
+            .flat_map(|cross| {
+                ["hash", "eq"]
+                    .iter()
+                    .map(move |op| cross.fn_name(self.name, op))
+            })
+            .collect()
+    }
 }
 
 impl Ord for PostgresHashEntity {
@@ -69,9 +83,9 @@ impl SqlGraphIdentifier for PostgresHashEntity {
 }
 
 impl ToSql for PostgresHashEntity {
-    #[tracing::instrument(level = "debug", err, skip(self, _context), fields(identifier = %self.rust_identifier()))]
-    fn to_sql(&self, _context: &PgxSql) -> eyre::Result<String> {
-        let sql = format!("\n\
+    #[tracing::instrument(level = "debug", err, skip(self, context), fields(identifier = %self.rust_identifier()))]
+    fn to_sql(&self, context: &PgxSql) -> eyre::Result<String> {
+        let mut sql = format!("\n\
                             -- {file}:{line}\n\
                             -- {full_path}\n\
                             CREATE OPERATOR FAMILY {name}_hash_ops USING hash;\n\
@@ -85,6 +99,17 @@ impl ToSql for PostgresHashEntity {
                           line = self.line,
                           fn_name = self.fn_name(),
         );
+        for cross in &self.cross_types {
+            sql.push_str(&format!("\n\
+                            ALTER OPERATOR FAMILY {name}_hash_ops USING hash ADD\n\
+                                \tOPERATOR    1   =  ({name}, {cross_ty}),\n\
+                                \tFUNCTION    1   ({cross_ty}) {fn_name}({cross_ty});\
+                            ",
+                          name = self.name,
+                          cross_ty = cross.sql_type(context)?,
+                          fn_name = cross.fn_name(self.name, "hash"),
+            ));
+        }
         tracing::trace!(%sql);
         Ok(sql)
     }
@@ -15,7 +15,7 @@ use syn::{
     DeriveInput, Ident,
 };
 
-use crate::sql_entity_graph::ToSqlConfig;
+use crate::sql_entity_graph::{CrossType, ToSqlConfig};
 
 /// A parsed `#[derive(PostgresHash)]` item.
 ///
@@ -63,13 +63,15 @@ use crate::sql_entity_graph::ToSqlConfig;
 #[derive(Debug, Clone)]
 pub struct PostgresHash {
     pub name: Ident,
+    pub cross_types: Vec<CrossType>,
     pub to_sql_config: ToSqlConfig,
 }
 
 impl PostgresHash {
-    pub fn new(name: Ident, to_sql_config: ToSqlConfig) -> Self {
+    pub fn new(name: Ident, cross_types: Vec<CrossType>, to_sql_config: ToSqlConfig) -> Self {
         Self {
             name,
+            cross_types,
             to_sql_config,
         }
     }
@@ -77,7 +79,8 @@ impl PostgresHash {
     pub fn from_derive_input(derive_input: DeriveInput) -> Result<Self, syn::Error> {
         let to_sql_config =
             ToSqlConfig::from_attributes(derive_input.attrs.as_slice())?.unwrap_or_default();
-        Ok(Self::new(derive_input.ident, to_sql_config))
+        let cross_types = CrossType::from_attributes(derive_input.attrs.as_slice())?;
+        Ok(Self::new(derive_input.ident, cross_types, to_sql_config))
     }
 }
 
@@ -92,7 +95,8 @@ impl Parse for PostgresHash {
             _ => return Err(syn::Error::new(input.span(), "expected enum or struct")),
         };
         let to_sql_config = ToSqlConfig::from_attributes(attrs)?.unwrap_or_default();
-        Ok(Self::new(ident, to_sql_config))
+        let cross_types = CrossType::from_attributes(attrs)?;
+        Ok(Self::new(ident, cross_types, to_sql_config))
     }
 }
 
@@ -103,6 +107,7 @@ impl ToTokens for PostgresHash {
             &format!("__pgx_internals_hash_{}", self.name),
             Span::call_site(),
         );
+        let cross_types = &self.cross_types;
         let to_sql_config = &self.to_sql_config;
         let inv = quote! {
             #[no_mangle]
@@ -119,6 +124,7 @@ impl ToTokens for PostgresHash {
                     full_path: core::any::type_name::<#name>(),
                     module_path: module_path!(),
                     id: TypeId::of::<#name>(),
+                    cross_types: vec![#(#cross_types),*],
                     to_sql_config: #to_sql_config,
                 };
                 ::pgx::utils::sql_entity_graph::SqlGraphEntity::Hash(submission)
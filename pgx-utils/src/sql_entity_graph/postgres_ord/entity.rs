@@ -7,6 +7,7 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 use crate::sql_entity_graph::{
+    cross_type::CrossTypeEntity,
     pgx_sql::PgxSql,
     to_sql::{entity::ToSqlConfigEntity, ToSql},
     SqlGraphEntity, SqlGraphIdentifier,
@@ -22,6 +23,7 @@ pub struct PostgresOrdEntity {
     pub full_path: &'static str,
     pub module_path: &'static str,
     pub id: core::any::TypeId,
+    pub cross_types: Vec<CrossTypeEntity>,
     pub to_sql_config: ToSqlConfigEntity,
 }
 
@@ -49,6 +51,18 @@ impl PostgresOrdEntity {
     pub(crate) fn ge_fn_name(&self) -> String {
         format!("{}_ge", self.name.to_lowercase())
     }
+
+    /// The names of all functions generated for the cross types of this entity.
+    pub(crate) fn cross_fn_names(&self) -> Vec<String> {
+        self.cross_types
+            .iter()
+            .flat_map(|cross| {
+                ["cmp", "lt", "le", "eq", "gt", "ge"]
+                    .iter()
+                    .map(move |op| cross.fn_name(self.name, op))
+            })
+            .collect()
+    }
 }
 
 impl Ord for PostgresOrdEntity {
@@ -89,9 +103,9 @@ impl SqlGraphIdentifier for PostgresOrdEntity {
 }
 
 impl ToSql for PostgresOrdEntity {
-    #[tracing::instrument(level = "debug", err, skip(self, _context), fields(identifier = %self.rust_identifier()))]
-    fn to_sql(&self, _context: &PgxSql) -> eyre::Result<String> {
-        let sql = format!("\n\
+    #[tracing::instrument(level = "debug", err, skip(self, context), fields(identifier = %self.rust_identifier()))]
+    fn to_sql(&self, context: &PgxSql) -> eyre::Result<String> {
+        let mut sql = format!("\n\
                             -- {file}:{line}\n\
                             -- {full_path}\n\
                             CREATE OPERATOR FAMILY {name}_btree_ops USING btree;\n\
@@ -109,6 +123,21 @@ impl ToSql for PostgresOrdEntity {
                           line = self.line,
                           cmp_fn_name = self.cmp_fn_name(),
         );
+        for cross in &self.cross_types {
+            sql.push_str(&format!("\n\
+                            ALTER OPERATOR FAMILY {name}_btree_ops USING btree ADD\n\
+                                  \tOPERATOR 1 < ({name}, {cross_ty}),\n\
+                                  \tOPERATOR 2 <= ({name}, {cross_ty}),\n\
+                                  \tOPERATOR 3 = ({name}, {cross_ty}),\n\
+                                  \tOPERATOR 4 >= ({name}, {cross_ty}),\n\
+                                  \tOPERATOR 5 > ({name}, {cross_ty}),\n\
+                                  \tFUNCTION 1 {cmp_fn_name}({name}, {cross_ty});\
+                            ",
+                          name = self.name,
+                          cross_ty = cross.sql_type(context)?,
+                          cmp_fn_name = cross.fn_name(self.name, "cmp"),
+            ));
+        }
         tracing::trace!(%sql);
         Ok(sql)
     }
@@ -0,0 +1,116 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+//! The outcomes of `#[pg_test]` tests against each Postgres version, which `cargo pgx test all`
+//! reports as a version × test matrix.
+//!
+//! Each test records its outcome as it finishes, in `target/pgx-test-results-$PGVER.json`.
+use crate::get_target_dir;
+use eyre::WrapErr;
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// How a test went against one Postgres version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestOutcome {
+    Passed,
+    Failed,
+    /// The test doesn't run against the version, by its `min_pg`, `max_pg`, or `skip_pg`
+    Skipped,
+}
+
+/// The outcomes of the tests run against one Postgres version, by the tests' names
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestResults(pub BTreeMap<String, TestOutcome>);
+
+impl TestResults {
+    /// The file the outcomes of the tests against Postgres `pg_major_version` are recorded in
+    pub fn path(pg_major_version: u16) -> eyre::Result<PathBuf> {
+        let mut path = get_target_dir()?;
+        path.push(format!("pgx-test-results-{}.json", pg_major_version));
+        Ok(path)
+    }
+
+    /// The outcomes recorded in `path`, which are none if it doesn't exist
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .wrap_err_with(|| format!("invalid test results in {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(TestResults::default()),
+            Err(e) => Err(e).wrap_err_with(|| format!("couldn't read {}", path.display())),
+        }
+    }
+
+    /// Record the `outcome` of the test `name` in `path`, along with those already there
+    ///
+    /// Tests which run at the same time must be serialized around this, as it rewrites the
+    /// whole file.
+    pub fn record(path: &Path, name: &str, outcome: TestOutcome) -> eyre::Result<()> {
+        let mut results = Self::load(path)?;
+        results.0.insert(name.to_string(), outcome);
+        std::fs::write(path, serde_json::to_string_pretty(&results)?)
+            .wrap_err_with(|| format!("couldn't write {}", path.display()))
+    }
+}
+
+/// The outcomes of the tests against each of several Postgres versions
+#[derive(Debug, Default, Clone)]
+pub struct TestMatrix {
+    versions: Vec<(u16, TestResults)>,
+}
+
+impl TestMatrix {
+    pub fn push(&mut self, pg_major_version: u16, results: TestResults) {
+        self.versions.push((pg_major_version, results));
+    }
+}
+
+impl std::fmt::Display for TestMatrix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = self
+            .versions
+            .iter()
+            .flat_map(|(_, results)| results.0.keys())
+            .collect::<BTreeSet<_>>();
+        let width = names
+            .iter()
+            .map(|name| name.len())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+
+        write!(f, "{:width$}", "test", width = width)?;
+        for (version, _) in &self.versions {
+            write!(f, "  {:8}", format!("pg{}", version))?;
+        }
+        writeln!(f)?;
+
+        for name in names {
+            write!(f, "{:width$}", name, width = width)?;
+            for (_, results) in &self.versions {
+                // pad before coloring, as the escape codes would count towards the width
+                match results.0.get(name) {
+                    Some(TestOutcome::Passed) => write!(f, "  {}", format!("{:8}", "ok").green()),
+                    Some(TestOutcome::Failed) => {
+                        write!(f, "  {}", format!("{:8}", "FAILED").red().bold())
+                    }
+                    Some(TestOutcome::Skipped) => {
+                        write!(f, "  {}", format!("{:8}", "skipped").dimmed())
+                    }
+                    // the run stopped before the test, such as when a test before it crashed
+                    None => write!(f, "  {:8}", "-"),
+                }?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
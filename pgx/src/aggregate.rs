@@ -19,6 +19,9 @@ Aggregates are created by implementing [`Aggregate`] for a type and decorating t
 Definition of the aggregate is done via settings in the type's [`Aggregate`] implementation. While
 the trait itself has several items, only a few are required, the macro will fill in the others with unused stubs.
 
+An aggregate which only needs a `state` and a `finish` function can implement [`SimpleAggregate`]
+instead, and the rest of its [`Aggregate`] is derived from that.
+
 # Minimal Example
 
 ```rust
@@ -266,13 +269,160 @@ CREATE AGGREGATE DemoSum (
 );
 ```
 
+## Ordered-Set and Hypothetical-Set Aggregates
+
+Setting `ORDERED_SET` makes an aggregate which is called with a `WITHIN GROUP (ORDER BY ...)`,
+such as `percentile_disc`. `state` is passed the sorted rows, and `finalize` is passed the direct
+arguments, which are set with `OrderedSetArgs`.
+
+Setting `HYPOTHETICAL` as well makes a hypothetical-set aggregate, such as `rank`, whose last
+direct arguments are a row to compare to the sorted rows.
+
+```rust
+# use pgx::*;
+#
+pub struct DemoRank;
+
+#[pg_aggregate]
+impl Aggregate for DemoRank {
+    const ORDERED_SET: bool = true;
+    const HYPOTHETICAL: bool = true;
+    type Args = i32;
+    type OrderedSetArgs = name!(hypothetical, i32);
+    type State = Internal;
+    type Finalize = i64;
+    fn state(
+        mut current: Self::State,
+        arg: Self::Args,
+        _fcinfo: pg_sys::FunctionCallInfo
+    ) -> Self::State {
+        todo!()
+    }
+    fn finalize(
+        mut current: Self::State,
+        hypothetical: Self::OrderedSetArgs,
+        _fcinfo: pg_sys::FunctionCallInfo
+    ) -> Self::Finalize {
+        todo!()
+    }
+}
+```
+
+Creates:
+
+```sql
+-- src/lib.rs:4
+-- aggregate::DemoRank
+CREATE AGGREGATE DemoRank (
+    "hypothetical" integer /* i32 */
+    ORDER BY
+    integer /* i32 */
+)
+(
+    SFUNC = "demo_rank_state", /* aggregate::DemoRank::state */
+    STYPE = internal, /* pgx::datum::internal::Internal */
+    FINALFUNC = "demo_rank_finalize", /* aggregate::DemoRank::final */
+    HYPOTHETICAL /* aggregate::DemoRank::hypothetical */
+);
+```
+
+Called as `SELECT DemoRank(5) WITHIN GROUP (ORDER BY value) FROM ...`.
+
+## Parallel and Moving Aggregates
+
+Implementing `combine` lets the aggregate run in parallel workers, once `PARALLEL` is
+[`ParallelOption::Safe`]. An [`Internal`](crate::Internal) state must also be sent from the
+workers, so needs `serial` and `deserial` too.
+
+Implementing `moving_state` and `moving_state_inverse`, with a `MovingState`, lets window frames
+whose start moves update the state rather than recompute it for each row.
+
+```rust
+# use pgx::*;
+#
+pub struct DemoDistinct;
+
+#[pg_aggregate]
+impl Aggregate for DemoDistinct {
+    const PARALLEL: Option<ParallelOption> = Some(ParallelOption::Safe);
+    type Args = i32;
+    type State = Internal;
+    type Finalize = i64;
+    type MovingState = i64;
+    const MOVING_INITIAL_CONDITION: Option<&'static str> = Some("0");
+    # fn state(
+    #     mut current: Self::State,
+    #     arg: Self::Args,
+    #     _fcinfo: pg_sys::FunctionCallInfo
+    # ) -> Self::State {
+    #     todo!()
+    # }
+    # fn finalize(
+    #     mut current: Self::State,
+    #     _direct_args: Self::OrderedSetArgs,
+    #     _fcinfo: pg_sys::FunctionCallInfo
+    # ) -> Self::Finalize {
+    #     todo!()
+    # }
+    fn combine(
+        mut current: Self::State,
+        mut other: Self::State,
+        _fcinfo: pg_sys::FunctionCallInfo
+    ) -> Self::State {
+        todo!()
+    }
+    fn serial(mut current: Self::State, _fcinfo: pg_sys::FunctionCallInfo) -> Vec<u8> {
+        todo!()
+    }
+    fn deserial(buf: Vec<u8>, _fcinfo: pg_sys::FunctionCallInfo) -> Self::State {
+        todo!()
+    }
+    fn moving_state(
+        mstate: Self::MovingState,
+        arg: Self::Args,
+        _fcinfo: pg_sys::FunctionCallInfo
+    ) -> Self::MovingState {
+        todo!()
+    }
+    fn moving_state_inverse(
+        mstate: Self::MovingState,
+        arg: Self::Args,
+        _fcinfo: pg_sys::FunctionCallInfo
+    ) -> Self::MovingState {
+        todo!()
+    }
+}
+```
+
+Creates:
+
+```sql
+-- src/lib.rs:6
+-- aggregate::DemoDistinct
+CREATE AGGREGATE DemoDistinct (
+    integer /* i32 */
+)
+(
+    SFUNC = "demo_distinct_state", /* aggregate::DemoDistinct::state */
+    STYPE = internal, /* pgx::datum::internal::Internal */
+    FINALFUNC = "demo_distinct_finalize", /* aggregate::DemoDistinct::final */
+    COMBINEFUNC = "demo_distinct_combine", /* aggregate::DemoDistinct::combine */
+    SERIALFUNC = "demo_distinct_serial", /* aggregate::DemoDistinct::serial */
+    DESERIALFUNC = "demo_distinct_deserial", /* aggregate::DemoDistinct::deserial */
+    MSFUNC = "demo_distinct_moving_state", /* aggregate::DemoDistinct::moving_state */
+    MINVFUNC = "demo_distinct_moving_state_inverse", /* aggregate::DemoDistinct::moving_state_inverse */
+    MINITCOND = '0', /* aggregate::DemoDistinct::MOVING_INITIAL_CONDITION */
+    PARALLEL = SAFE, /* aggregate::DemoDistinct::PARALLEL */
+    MSTYPE = bigint /* aggregate::DemoDistinct::MovingState = i64 */
+);
+```
+
 */
 
 use crate::{
     error,
     memcxt::PgMemoryContexts,
     pg_sys::{AggCheckCallContext, CurrentMemoryContext, FunctionCallInfo, MemoryContext},
-    pgbox::PgBox,
 };
 
 pub use pgx_utils::sql_entity_graph::{FinalizeModify, ParallelOption};
@@ -367,6 +517,14 @@ where
     /// **Optional:** This const can be skipped, `#[pg_aggregate]` will create a stub.
     const MOVING_INITIAL_CONDITION: Option<&'static str> = None;
 
+    /// Set to true if this is a hypothetical-set aggregate, such as `rank`.
+    ///
+    /// **Only effective if `ORDERED_SET` is `true`.** The last of the `OrderedSetArgs` are the
+    /// hypothetical row, one for each of the `Args`, and must be of the same types.
+    ///
+    /// See <https://www.postgresql.org/docs/current/functions-aggregate.html#FUNCTIONS-HYPOTHETICAL-TABLE>
+    /// for more information.
+    ///
     /// **Optional:** This const can be skipped, `#[pg_aggregate]` will create a stub.
     const HYPOTHETICAL: bool = false;
 
@@ -381,20 +539,31 @@ where
         fcinfo: FunctionCallInfo,
     ) -> Self::Finalize;
 
+    /// Merges two states built from different rows, so the aggregate can be computed in
+    /// parallel, with each worker's state combined at the end.
+    ///
+    /// When `State` is [`Internal`](crate::Internal), either state may be uninitialized.
+    ///
     /// **Optional:** This function can be skipped, `#[pg_aggregate]` will create a stub.
     fn combine(current: Self::State, _other: Self::State, fcinfo: FunctionCallInfo) -> Self::State;
 
+    /// Serializes a state to be sent from a parallel worker.
+    ///
+    /// **Only effective if `State` is [`Internal`](crate::Internal),** as Postgres sends other states as they are.
+    ///
     /// **Optional:** This function can be skipped, `#[pg_aggregate]` will create a stub.
     fn serial(current: Self::State, fcinfo: FunctionCallInfo) -> Vec<u8>;
 
+    /// Deserializes a state from what `serial` returned.
+    ///
+    /// **Only effective if `State` is [`Internal`](crate::Internal).**
+    ///
     /// **Optional:** This function can be skipped, `#[pg_aggregate]` will create a stub.
-    fn deserial(
-        current: Self::State,
-        _buf: Vec<u8>,
-        _internal: PgBox<Self::State>,
-        fcinfo: FunctionCallInfo,
-    ) -> PgBox<Self::State>;
+    fn deserial(_buf: Vec<u8>, fcinfo: FunctionCallInfo) -> Self::State;
 
+    /// The `state` function of moving-aggregate mode, which Postgres uses for window frames
+    /// whose start moves, together with `moving_state_inverse`.
+    ///
     /// **Optional:** This function can be skipped, `#[pg_aggregate]` will create a stub.
     fn moving_state(
         _mstate: Self::MovingState,
@@ -402,6 +571,8 @@ where
         fcinfo: FunctionCallInfo,
     ) -> Self::MovingState;
 
+    /// Removes a row from the moving state, as it leaves the window frame.
+    ///
     /// **Optional:** This function can be skipped, `#[pg_aggregate]` will create a stub.
     fn moving_state_inverse(
         _mstate: Self::MovingState,
@@ -448,3 +619,61 @@ where
         PgMemoryContexts::For(aggregate_memory_context).switch_to(f)
     }
 }
+
+/// A simpler aggregate, of only a `state` and a `finish` function.
+///
+/// When decorated with [`#[pgx_macros::pg_aggregate]`](pgx_macros::pg_aggregate), an [`Aggregate`]
+/// is made from it, whose `State` is an `Option` of this `State`. That starts out as `NULL`, and is
+/// the `State`'s [`Default`] until a row is aggregated, so no `INITIAL_CONDITION` is needed, and
+/// `finish` is passed the `Default` when no rows are aggregated.
+///
+/// Rows where any of the `Args` are `NULL` are skipped, unless the argument is an `Option`.
+///
+/// ```rust
+/// # use pgx::*;
+/// #
+/// pub struct DemoSum;
+///
+/// #[pg_aggregate]
+/// impl SimpleAggregate for DemoSum {
+///     type State = i64;
+///     type Args = name!(value, i32);
+///     type Finalize = i64;
+///
+///     fn state(current: Self::State, value: Self::Args) -> Self::State {
+///         current + value as i64
+///     }
+///
+///     fn finish(current: Self::State) -> Self::Finalize {
+///         current
+///     }
+/// }
+/// ```
+pub trait SimpleAggregate
+where
+    Self: Sized,
+{
+    /// The type of the value passed to and returned from `state`.
+    ///
+    /// For an aggregate type which does not have a `PgVarlenaInOutFuncs` implementation,
+    /// this can be left out, or set to it's default, `Self`.
+    type State: Default;
+
+    /// The type of the argument(s), as with [`Aggregate::Args`], though it can't be variadic.
+    type Args;
+
+    /// The type of the value returned from `finish`.
+    ///
+    /// **Optional:** This type can be skipped, with `finish`, and is then `State`.
+    type Finalize;
+
+    /// The name of the aggregate. (eg. What you'd pass to `SELECT agg(col) FROM tab`.)
+    ///
+    /// **Optional:** This const can be skipped, `#[pg_aggregate]` will use the type's name.
+    const NAME: &'static str;
+
+    fn state(current: Self::State, v: Self::Args) -> Self::State;
+
+    /// **Optional:** This function can be skipped, `#[pg_aggregate]` will return the `State`.
+    fn finish(current: Self::State) -> Self::Finalize;
+}
@@ -0,0 +1,190 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Drive Rust futures from a background worker's main thread, woken by its latch
+//!
+//! Postgres can only be used from a backend's main thread, but async I/O libraries such as
+//! `tokio` do their work on threads of their own.  [`block_on`] bridges the two: it runs a future
+//! on the main thread, where it can use SPI and the rest of Postgres, sleeping on the worker's
+//! latch whenever the future is waiting.  Wakeups from any thread, such as a `tokio` task
+//! finishing, and the latch being set, such as by a SIGTERM or SIGHUP, both wake it up to poll
+//! the future again.  [`shutdown`] is a future that completes once the worker receives a SIGTERM.
+//!
+//! A worker running a `tokio` runtime would look like this:
+//!
+//! ```rust,ignore
+//! use pgx::async_bgworker;
+//! use pgx::bgworkers::{BackgroundWorker, SignalWakeFlags};
+//! use pgx::*;
+//!
+//! #[pg_guard]
+//! pub extern "C" fn background_worker_main(_arg: pg_sys::Datum) {
+//!     BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+//!     BackgroundWorker::connect_worker_to_spi(Some("postgres"), None);
+//!
+//!     // the runtime's threads do the network I/O, and must never call into Postgres
+//!     let runtime = tokio::runtime::Builder::new_multi_thread()
+//!         .enable_all()
+//!         .build()
+//!         .unwrap();
+//!
+//!     async_bgworker::run_until_shutdown(async {
+//!         loop {
+//!             let job = runtime.spawn(fetch_next_job()).await.unwrap();
+//!
+//!             // back on the main thread, where Postgres can be used
+//!             BackgroundWorker::transaction(|| {
+//!                 Spi::run(&format!("INSERT INTO jobs VALUES ({})", job.id));
+//!             });
+//!         }
+//!     });
+//!     // the runtime, and any tasks still running on it, are dropped here
+//! }
+//! ```
+use crate::bgworkers::BackgroundWorker;
+use crate::{pg_sys, WaitEvent};
+use once_cell::sync::OnceCell;
+use std::future::Future;
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+/// Run `future` to completion on this thread, sleeping on the latch while it's waiting
+///
+/// Query cancels and other interrupts are checked for each time it wakes, and the process
+/// exits if the postmaster dies.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let pipe = wake_pipe();
+    let waker = Waker::from(pipe.waker.clone());
+    let mut context = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+        pipe.wait();
+    }
+}
+
+/// Run `future` until it completes, returning its output, or until the worker receives a
+/// SIGTERM, returning `None`
+///
+/// The worker must have attached a SIGTERM handler with
+/// [`BackgroundWorker::attach_signal_handlers`].
+pub fn run_until_shutdown<F: Future>(future: F) -> Option<F::Output> {
+    let mut future = Box::pin(future);
+    let mut shutdown = shutdown();
+    block_on(std::future::poll_fn(move |cx| {
+        if let Poll::Ready(()) = Pin::new(&mut shutdown).poll(cx) {
+            return Poll::Ready(None);
+        }
+        future.as_mut().poll(cx).map(Some)
+    }))
+}
+
+/// A future that completes once the worker receives a SIGTERM
+///
+/// It's only woken by [`block_on`], and the worker must have attached a SIGTERM handler with
+/// [`BackgroundWorker::attach_signal_handlers`].
+pub fn shutdown() -> Shutdown {
+    Shutdown
+}
+
+/// See [`shutdown`]
+pub struct Shutdown;
+
+impl Future for Shutdown {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        // SIGTERM sets the latch, so `block_on()` polls us again when it arrives
+        if BackgroundWorker::sigterm_pending() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// A way for other threads to wake the main thread, which is sleeping on the latch
+///
+/// Other threads can't safely call into Postgres, not even to set the latch, so instead they
+/// write to a socket the main thread waits on along with it.
+struct WakePipe {
+    reader: UnixStream,
+    waker: Arc<PipeWaker>,
+}
+
+struct PipeWaker {
+    writer: UnixStream,
+}
+
+impl Wake for PipeWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        // if the socket's full, the main thread has wakeups pending already
+        let _ = (&self.writer).write(&[1]);
+    }
+}
+
+impl WakePipe {
+    fn wait(&self) {
+        let events =
+            pg_sys::WL_LATCH_SET | pg_sys::WL_SOCKET_READABLE | pg_sys::WL_POSTMASTER_DEATH;
+        unsafe {
+            let rc = pg_sys::WaitLatchOrSocket(
+                pg_sys::MyLatch,
+                events as i32,
+                self.reader.as_raw_fd(),
+                -1,
+                WaitEvent::EXTENSION.info(),
+            );
+            pg_sys::ResetLatch(pg_sys::MyLatch);
+
+            if rc & pg_sys::WL_POSTMASTER_DEATH as i32 != 0 {
+                pg_sys::proc_exit(1);
+            }
+        }
+
+        let mut buffer = [0; 64];
+        loop {
+            match (&self.reader).read(&mut buffer) {
+                Ok(0) => break,
+                Ok(_) => continue,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => panic!("failed to read from the wakeup socket: {}", e),
+            }
+        }
+        check_for_interrupts!();
+    }
+}
+
+fn wake_pipe() -> &'static WakePipe {
+    static WAKE_PIPE: OnceCell<WakePipe> = OnceCell::new();
+    WAKE_PIPE.get_or_init(|| {
+        let (reader, writer) = UnixStream::pair().expect("failed to create the wakeup socket");
+        reader
+            .set_nonblocking(true)
+            .expect("failed to make the wakeup socket nonblocking");
+        writer
+            .set_nonblocking(true)
+            .expect("failed to make the wakeup socket nonblocking");
+        WakePipe {
+            reader,
+            waker: Arc::new(PipeWaker { writer }),
+        }
+    })
+}
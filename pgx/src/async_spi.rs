@@ -0,0 +1,345 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Run queries asynchronously, each in its own dynamic background worker, in the style of
+//! `dblink`'s asynchronous queries or `pg_background`
+//!
+//! [`AsyncQuery::start`] launches a worker, connected to the same database as the same user, that
+//! runs the query in its own transaction and sends its results back over a shared memory queue.
+//! The calling backend is free to do other work, or start more queries, in the meantime, and
+//! collects the results with [`AsyncQuery::wait`].  Because the query's transaction is
+//! independent of the caller's, it doesn't see the caller's uncommitted changes, and what it does
+//! is committed even if the caller's transaction later aborts.
+//!
+//! The worker's main function is part of pgx, and so is part of your extension's shared library,
+//! which must be named when starting a query.
+//!
+//! ```rust,no_run
+//! use pgx::async_spi::AsyncQuery;
+//!
+//! let slow = AsyncQuery::start("my_extension", "SELECT count(*) FROM big_table");
+//! let also_slow = AsyncQuery::start("my_extension", "SELECT count(*) FROM other_big_table");
+//!
+//! let slow = slow.wait().expect("query failed");
+//! let also_slow = also_slow.wait().expect("query failed");
+//! assert_eq!(slow.rows.len(), 1);
+//! assert_eq!(also_slow.rows.len(), 1);
+//! ```
+//!
+//! Values are returned as text, formatted by their types' output functions, as `psql` would
+//! show them.
+use crate::bgworkers::{BackgroundWorker, BackgroundWorkerBuilder};
+use crate::{pg_guard, pg_sys, PgMemoryContexts, Spi, SpiSqlError};
+use serde::{Deserialize, Serialize};
+use std::ffi::CStr;
+use std::fmt::{Display, Formatter};
+
+/// The size of the queue the worker sends its results over
+const QUEUE_SIZE: usize = 64 * 1024;
+
+/// At the start of the dynamic shared memory segment, followed by the query text and then the
+/// queue
+#[repr(C)]
+struct SegmentHeader {
+    database: pg_sys::Oid,
+    user: pg_sys::Oid,
+    query_len: usize,
+}
+
+impl SegmentHeader {
+    fn queue_offset(query_len: usize) -> usize {
+        // MAXALIGN() the queue
+        (std::mem::size_of::<SegmentHeader>() + query_len + 7) & !7
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum Message {
+    Columns(Vec<String>),
+    Row(Vec<Option<String>>),
+    Complete(u64),
+    Error(SpiSqlError),
+}
+
+/// The results of an [`AsyncQuery`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AsyncQueryResult {
+    /// The names of the result's columns, if the query returns rows
+    pub columns: Vec<String>,
+    /// The rows the query returned, their values formatted as text
+    pub rows: Vec<Vec<Option<String>>>,
+    /// The number of rows the query processed
+    pub processed: u64,
+}
+
+/// Why an [`AsyncQuery`] failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsyncQueryError {
+    /// The query raised an ERROR, and its transaction was rolled back
+    Sql(SpiSqlError),
+    /// The worker exited without sending the query's results, such as when it was terminated
+    WorkerExited,
+}
+
+impl Display for AsyncQueryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsyncQueryError::Sql(e) => Display::fmt(e, f),
+            AsyncQueryError::WorkerExited => {
+                f.write_str("the async query worker exited without returning a result")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AsyncQueryError {}
+
+/// A query running in a dynamic background worker
+///
+/// Dropping an `AsyncQuery` before it's finished terminates the worker, cancelling the query.  Use
+/// [`AsyncQuery::detach`] to let it run to completion instead.
+pub struct AsyncQuery {
+    segment: *mut pg_sys::dsm_segment,
+    queue: *mut pg_sys::shm_mq_handle,
+    worker: *mut pg_sys::BackgroundWorkerHandle,
+    result: AsyncQueryResult,
+    outcome: Option<Result<(), AsyncQueryError>>,
+}
+
+impl AsyncQuery {
+    /// Start running `query` in a new dynamic background worker, whose main function is looked up
+    /// in the shared `library` -- typically your extension's name
+    ///
+    /// Raises a Postgres ERROR if the worker can't be started, such as when there are already
+    /// `max_worker_processes` running.
+    pub fn start(library: &str, query: &str) -> Self {
+        unsafe {
+            // everything must outlive the current transaction, in case this does
+            PgMemoryContexts::TopMemoryContext.switch_to(|_| Self::start_worker(library, query))
+        }
+    }
+
+    unsafe fn start_worker(library: &str, query: &str) -> Self {
+        let queue_offset = SegmentHeader::queue_offset(query.len());
+        let segment = pg_sys::dsm_create(queue_offset + QUEUE_SIZE, 0);
+        pg_sys::dsm_pin_mapping(segment);
+
+        let address = pg_sys::dsm_segment_address(segment) as *mut u8;
+        let header = address as *mut SegmentHeader;
+        (*header).database = pg_sys::MyDatabaseId;
+        (*header).user = pg_sys::GetUserId();
+        (*header).query_len = query.len();
+        std::ptr::copy_nonoverlapping(
+            query.as_ptr(),
+            address.add(std::mem::size_of::<SegmentHeader>()),
+            query.len(),
+        );
+
+        let mq = pg_sys::shm_mq_create(address.add(queue_offset) as _, QUEUE_SIZE);
+        pg_sys::shm_mq_set_receiver(mq, pg_sys::MyProc);
+        let queue = pg_sys::shm_mq_attach(mq, segment, std::ptr::null_mut());
+
+        let mut bgw = BackgroundWorkerBuilder::new("pgx async query")
+            .set_library(library)
+            .set_function("pgx_async_spi_main")
+            .enable_spi_access()
+            .set_argument(Some(pg_sys::dsm_segment_handle(segment) as pg_sys::Datum))
+            .set_notify_pid(pg_sys::MyProcPid)
+            .to_pg();
+        let mut worker = std::ptr::null_mut();
+        if !pg_sys::RegisterDynamicBackgroundWorker(&mut bgw, &mut worker) {
+            pg_sys::dsm_detach(segment);
+            panic!("could not start an async query worker: max_worker_processes may be too low");
+        }
+
+        // if the worker fails to start, receiving from the queue reports it as detached
+        pg_sys::shm_mq_set_handle(queue, worker);
+
+        let mut pid = 0;
+        if pg_sys::WaitForBackgroundWorkerStartup(worker, &mut pid)
+            == pg_sys::BgwHandleStatus_BGWH_POSTMASTER_DIED
+        {
+            panic!("cannot start an async query worker without the postmaster");
+        }
+
+        AsyncQuery {
+            segment,
+            queue,
+            worker,
+            result: AsyncQueryResult::default(),
+            outcome: None,
+        }
+    }
+
+    /// Receive whatever results the worker has sent so far, without blocking, and return `true`
+    /// if the query has finished
+    pub fn poll(&mut self) -> bool {
+        while self.outcome.is_none() {
+            if !self.receive(true) {
+                break;
+            }
+        }
+        self.outcome.is_some()
+    }
+
+    /// Wait for the query to finish, and return its results
+    pub fn wait(mut self) -> Result<AsyncQueryResult, AsyncQueryError> {
+        while self.outcome.is_none() {
+            self.receive(false);
+        }
+
+        match self.outcome.clone().unwrap() {
+            Ok(()) => Ok(std::mem::take(&mut self.result)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Stop listening for the query's results, but let it run to completion
+    pub fn detach(mut self) {
+        if !self.worker.is_null() {
+            unsafe { pg_sys::pfree(self.worker as _) };
+            self.worker = std::ptr::null_mut();
+        }
+    }
+
+    /// Receive one message from the queue, returning `false` if there isn't one waiting and
+    /// `nowait` is set
+    fn receive(&mut self, nowait: bool) -> bool {
+        let mut nbytes = 0;
+        let mut data = std::ptr::null_mut();
+        let result = unsafe { pg_sys::shm_mq_receive(self.queue, &mut nbytes, &mut data, nowait) };
+
+        match result {
+            pg_sys::shm_mq_result_SHM_MQ_SUCCESS => {
+                let bytes = unsafe { std::slice::from_raw_parts(data as *const u8, nbytes) };
+                match serde_cbor::from_slice(bytes).expect("failed to decode async query message") {
+                    Message::Columns(columns) => self.result.columns = columns,
+                    Message::Row(row) => self.result.rows.push(row),
+                    Message::Complete(processed) => {
+                        self.result.processed = processed;
+                        self.outcome = Some(Ok(()));
+                    }
+                    Message::Error(e) => self.outcome = Some(Err(AsyncQueryError::Sql(e))),
+                }
+                true
+            }
+            pg_sys::shm_mq_result_SHM_MQ_WOULD_BLOCK => false,
+            _ => {
+                self.outcome = Some(Err(AsyncQueryError::WorkerExited));
+                true
+            }
+        }
+    }
+}
+
+impl Drop for AsyncQuery {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.worker.is_null() {
+                if self.outcome.is_none() {
+                    pg_sys::TerminateBackgroundWorker(self.worker);
+                }
+                pg_sys::pfree(self.worker as _);
+            }
+            pg_sys::shm_mq_detach(self.queue);
+            pg_sys::dsm_detach(self.segment);
+        }
+    }
+}
+
+/// The main function of the dynamic background workers started by [`AsyncQuery::start`]
+#[pg_guard]
+pub extern "C" fn pgx_async_spi_main(arg: pg_sys::Datum) {
+    unsafe {
+        pg_sys::BackgroundWorkerUnblockSignals();
+
+        let segment = pg_sys::dsm_attach(arg as pg_sys::dsm_handle);
+        if segment.is_null() {
+            panic!("could not map the async query's dynamic shared memory segment");
+        }
+
+        let address = pg_sys::dsm_segment_address(segment) as *mut u8;
+        let header = address as *mut SegmentHeader;
+        let query = std::str::from_utf8(std::slice::from_raw_parts(
+            address.add(std::mem::size_of::<SegmentHeader>()),
+            (*header).query_len,
+        ))
+        .expect("async query is not valid UTF8")
+        .to_string();
+
+        let mq = address.add(SegmentHeader::queue_offset((*header).query_len));
+        pg_sys::shm_mq_set_sender(mq as _, pg_sys::MyProc);
+        let queue = pg_sys::shm_mq_attach(mq as _, segment, std::ptr::null_mut());
+
+        #[cfg(feature = "pg10")]
+        pg_sys::BackgroundWorkerInitializeConnectionByOid((*header).database, (*header).user);
+        #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+        pg_sys::BackgroundWorkerInitializeConnectionByOid((*header).database, (*header).user, 0);
+
+        BackgroundWorker::transaction(move || run_query(&query, queue));
+    }
+}
+
+fn run_query(query: &str, queue: *mut pg_sys::shm_mq_handle) {
+    // if the caller has detached, `send()` fails, but the query has already run, and its
+    // transaction still commits
+    let send = |message: Message| {
+        let bytes = serde_cbor::to_vec(&message).expect("failed to encode async query message");
+        let result = unsafe { pg_sys::shm_mq_send(queue, bytes.len(), bytes.as_ptr() as _, false) };
+        result == pg_sys::shm_mq_result_SHM_MQ_SUCCESS
+    };
+
+    Spi::connect(|client| {
+        let table = match client.try_select(query, None, None) {
+            Ok(table) => table,
+            Err(e) => {
+                send(Message::Error(e));
+                return Ok(Some(()));
+            }
+        };
+
+        let processed = table.len() as u64;
+        if let Some(tupdesc) = table.tupdesc {
+            let natts = unsafe { (*tupdesc).natts };
+            let columns = (1..=natts)
+                .map(|i| unsafe { CStr::from_ptr(pg_sys::SPI_fname(tupdesc, i)) })
+                .map(|name| name.to_string_lossy().into_owned())
+                .collect();
+            if !send(Message::Columns(columns)) {
+                return Ok(Some(()));
+            }
+
+            for row in table {
+                let values = (1..=natts as usize)
+                    .map(|i| {
+                        let datum = row.by_ordinal(i).unwrap().value::<pg_sys::Datum>()?;
+                        Some(unsafe {
+                            output_text(datum, pg_sys::SPI_gettypeid(tupdesc, i as i32))
+                        })
+                    })
+                    .collect();
+                if !send(Message::Row(values)) {
+                    return Ok(Some(()));
+                }
+            }
+        }
+
+        send(Message::Complete(processed));
+        Ok(Some(()))
+    });
+}
+
+unsafe fn output_text(datum: pg_sys::Datum, typoid: pg_sys::Oid) -> String {
+    let mut output_func = pg_sys::InvalidOid;
+    let mut is_varlena = false;
+    pg_sys::getTypeOutputInfo(typoid, &mut output_func, &mut is_varlena);
+    CStr::from_ptr(pg_sys::OidOutputFunctionCall(output_func, datum))
+        .to_string_lossy()
+        .into_owned()
+}
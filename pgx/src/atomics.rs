@@ -6,7 +6,25 @@ All rights reserved.
 
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
+use crate::pg_sys;
 use once_cell::sync::OnceCell;
+use std::cell::UnsafeCell;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Types that backends can share through a [`PgAtomic`] without a lock, because everything they
+/// do through a shared reference is atomic
+///
+/// Every Rust atomic is one, as are [`SharedAtomicU64`] and [`SharedSpinLock`].  A struct made
+/// only of these, such as a set of statistics counters, can be one too.
+///
+/// ## Safety
+///
+/// Implementors must be `#[repr(C)]`, and contain no pointers, as each backend may map shared
+/// memory at a different address.
+pub unsafe trait PGXSharedAtomic: Default + Sync {}
+
+unsafe impl<T> PGXSharedAtomic for T where T: atomic_traits::Atomic + Default + Sync {}
 
 pub struct PgAtomic<T> {
     inner: OnceCell<*mut T>,
@@ -22,7 +40,7 @@ impl<T> PgAtomic<T> {
 
 impl<T> PgAtomic<T>
 where
-    T: atomic_traits::Atomic + Default,
+    T: PGXSharedAtomic,
 {
     pub fn attach(&self, value: *mut T) {
         self.inner
@@ -41,5 +59,199 @@ where
     }
 }
 
-unsafe impl<T> Send for PgAtomic<T> where T: atomic_traits::Atomic + Default {}
-unsafe impl<T> Sync for PgAtomic<T> where T: atomic_traits::Atomic + Default {}
+unsafe impl<T> Send for PgAtomic<T> where T: PGXSharedAtomic {}
+unsafe impl<T> Sync for PgAtomic<T> where T: PGXSharedAtomic {}
+
+/// A 64-bit counter in shared memory, laid out like Postgres' own `pg_atomic_uint64`
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[derive(Default)]
+/// #[repr(C)]
+/// struct Stats {
+///     calls: SharedAtomicU64,
+///     bytes: SharedAtomicU64,
+/// }
+/// unsafe impl PGXSharedAtomic for Stats {}
+///
+/// static STATS: PgAtomic<Stats> = PgAtomic::new();
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     pg_shmem_init!(STATS);
+/// }
+///
+/// #[pg_extern]
+/// fn process(data: &[u8]) {
+///     STATS.get().calls.increment();
+///     STATS.get().bytes.fetch_add(data.len() as u64);
+/// }
+/// ```
+///
+/// All its operations are sequentially consistent, like Postgres' `pg_atomic_*_u64()`
+/// functions.
+#[derive(Debug, Default)]
+#[repr(transparent)]
+pub struct SharedAtomicU64(AtomicU64);
+
+impl SharedAtomicU64 {
+    pub const fn new(value: u64) -> Self {
+        SharedAtomicU64(AtomicU64::new(value))
+    }
+
+    pub fn load(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    pub fn store(&self, value: u64) {
+        self.0.store(value, Ordering::SeqCst)
+    }
+
+    /// Add 1, returning the new value
+    pub fn increment(&self) -> u64 {
+        self.fetch_add(1).wrapping_add(1)
+    }
+
+    /// Add `value`, wrapping around on overflow, and return the previous value
+    pub fn fetch_add(&self, value: u64) -> u64 {
+        self.0.fetch_add(value, Ordering::SeqCst)
+    }
+
+    /// Subtract `value`, wrapping around on overflow, and return the previous value
+    pub fn fetch_sub(&self, value: u64) -> u64 {
+        self.0.fetch_sub(value, Ordering::SeqCst)
+    }
+
+    /// Store `value`, returning the previous value
+    pub fn swap(&self, value: u64) -> u64 {
+        self.0.swap(value, Ordering::SeqCst)
+    }
+
+    /// Store `new` if the current value is `current`, returning the previous value, which is
+    /// `current` if `new` was stored
+    pub fn compare_exchange(&self, current: u64, new: u64) -> Result<u64, u64> {
+        self.0
+            .compare_exchange(current, new, Ordering::SeqCst, Ordering::SeqCst)
+    }
+
+    /// For passing to Postgres' `pg_atomic_*_u64()` functions
+    pub fn as_ptr(&self) -> *mut pg_sys::pg_atomic_uint64 {
+        self as *const Self as *mut pg_sys::pg_atomic_uint64
+    }
+}
+
+unsafe impl PGXSharedAtomic for SharedAtomicU64 {}
+unsafe impl crate::PGXSharedMemory for SharedAtomicU64 {}
+
+/// A value in shared memory, protected by a spinlock
+///
+/// A spinlock is much cheaper than a `PgLwLock`, but backends waiting for it busy-wait rather
+/// than sleeping, so it's only suitable for a few instructions' worth of work, such as updating
+/// a handful of related fields together.  Never do anything that could wait, such as I/O or SPI,
+/// while holding one.
+///
+/// As with Postgres' own spinlocks, a backend that can't acquire the lock for about a minute
+/// raises a `PANIC`, on the assumption that it's stuck.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[derive(Default, Copy, Clone)]
+/// #[repr(C)]
+/// struct Extremes {
+///     min: i64,
+///     max: i64,
+/// }
+/// unsafe impl PGXSharedMemory for Extremes {}
+///
+/// static EXTREMES: PgAtomic<SharedSpinLock<Extremes>> = PgAtomic::new();
+///
+/// #[pg_extern]
+/// fn observe(value: i64) {
+///     let mut extremes = EXTREMES.get().lock();
+///     extremes.min = extremes.min.min(value);
+///     extremes.max = extremes.max.max(value);
+/// }
+/// ```
+#[derive(Default)]
+#[repr(C)]
+pub struct SharedSpinLock<T> {
+    locked: AtomicBool,
+    value: UnsafeCell<T>,
+}
+
+impl<T> SharedSpinLock<T> {
+    pub const fn new(value: T) -> Self {
+        SharedSpinLock {
+            locked: AtomicBool::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Acquire the lock, spinning until it's free, and return a guard that releases it when
+    /// dropped
+    pub fn lock(&self) -> SharedSpinLockGuard<'_, T> {
+        if !self.acquire() {
+            let mut delay = pg_sys::SpinDelayStatus {
+                file: concat!(file!(), "\0").as_ptr() as _,
+                line: line!() as i32,
+                func: "SharedSpinLock::lock\0".as_ptr() as _,
+                ..Default::default()
+            };
+            while !self.acquire() {
+                unsafe { pg_sys::perform_spin_delay(&mut delay) };
+            }
+            unsafe { pg_sys::finish_spin_delay(&mut delay) };
+        }
+        SharedSpinLockGuard { lock: self }
+    }
+
+    /// Acquire the lock if it's free, without waiting
+    pub fn try_lock(&self) -> Option<SharedSpinLockGuard<'_, T>> {
+        if self.acquire() {
+            Some(SharedSpinLockGuard { lock: self })
+        } else {
+            None
+        }
+    }
+
+    fn acquire(&self) -> bool {
+        // test before test-and-set, as Postgres does, to avoid bouncing the cache line between
+        // waiting CPUs
+        !self.locked.load(Ordering::Relaxed)
+            && self
+                .locked
+                .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+    }
+}
+
+unsafe impl<T: Send> Sync for SharedSpinLock<T> {}
+unsafe impl<T: crate::PGXSharedMemory + Default + Send> PGXSharedAtomic for SharedSpinLock<T> {}
+unsafe impl<T: crate::PGXSharedMemory> crate::PGXSharedMemory for SharedSpinLock<T> {}
+
+/// Access to the value in a [`SharedSpinLock`], while holding its lock
+pub struct SharedSpinLockGuard<'a, T> {
+    lock: &'a SharedSpinLock<T>,
+}
+
+impl<T> Deref for SharedSpinLockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T> DerefMut for SharedSpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<T> Drop for SharedSpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.locked.store(false, Ordering::Release);
+    }
+}
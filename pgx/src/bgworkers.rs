@@ -10,17 +10,24 @@ Use of this source code is governed by the MIT license that can be found in the
 //! Safely create Postgres Background Workers, including with full SPI support
 //!
 //! See: [https://www.postgresql.org/docs/12/bgworker.html](https://www.postgresql.org/docs/12/bgworker.html)
-use crate::pg_sys;
+use crate::{pg_guard, pg_sys, PgAtomic, PgMemoryContexts, WaitEvent};
 use std::convert::TryInto;
 use std::ffi::CStr;
 use std::ffi::CString;
 use std::os::raw::c_char;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::time::Duration;
 
 pub static mut PREV_SHMEM_STARTUP_HOOK: Option<unsafe extern "C" fn()> = None;
 static GOT_SIGHUP: AtomicBool = AtomicBool::new(false);
 static GOT_SIGTERM: AtomicBool = AtomicBool::new(false);
+static GOT_SIGUSR1: AtomicBool = AtomicBool::new(false);
+static GOT_SIGUSR2: AtomicBool = AtomicBool::new(false);
+static mut DRAIN_TIMEOUT: Option<(pg_sys::TimeoutId, Duration)> = None;
+static mut DRAIN_TIMEOUT_ARMED: bool = false;
+static mut EXIT_CALLBACKS: Option<Vec<ExitCallback>> = None;
+
+type ExitCallback = Box<dyn FnOnce() + std::panic::UnwindSafe + std::panic::RefUnwindSafe>;
 
 bitflags! {
     struct BGWflags: i32 {
@@ -34,6 +41,10 @@ bitflags! {
     pub struct SignalWakeFlags: i32 {
         const SIGHUP = 0x1;
         const SIGTERM = 0x2;
+        /// Postgres itself also uses SIGUSR1 to signal its processes, so receiving one doesn't
+        /// necessarily mean someone meant to signal the worker
+        const SIGUSR1 = 0x4;
+        const SIGUSR2 = 0x8;
     }
 }
 
@@ -53,9 +64,13 @@ bitflags! {
 }
 
 /// The various points in which a BackgroundWorker can be started by Postgres
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BgWorkerStartTime {
+    /// As soon as the postmaster has started
     PostmasterStart = pg_sys::BgWorkerStartTime_BgWorkerStart_PostmasterStart as isize,
+    /// Once a hot standby accepts read-only connections
     ConsistentState = pg_sys::BgWorkerStartTime_BgWorkerStart_ConsistentState as isize,
+    /// Once the system has entered normal read-write operation
     RecoveryFinished = pg_sys::BgWorkerStartTime_BgWorkerStart_RecoveryFinished as isize,
 }
 
@@ -103,28 +118,225 @@ impl BackgroundWorker {
     /// Have we received a SIGTERM?
     pub fn sigterm_received() -> bool {
         // toggle the bool to false, returning whatever it was
-        GOT_SIGTERM.swap(false, Ordering::SeqCst)
+        let received = GOT_SIGTERM.swap(false, Ordering::SeqCst);
+        if received {
+            shutdown_observed();
+        }
+        received
+    }
+
+    /// Have we received a SIGTERM, without resetting it as [`BackgroundWorker::sigterm_received`]
+    /// does
+    pub(crate) fn sigterm_pending() -> bool {
+        GOT_SIGTERM.load(Ordering::SeqCst)
+    }
+
+    /// Have we received a SIGUSR1?
+    pub fn sigusr1_received() -> bool {
+        // toggle the bool to false, returning whatever it was
+        GOT_SIGUSR1.swap(false, Ordering::SeqCst)
+    }
+
+    /// Have we received a SIGUSR2?
+    pub fn sigusr2_received() -> bool {
+        // toggle the bool to false, returning whatever it was
+        GOT_SIGUSR2.swap(false, Ordering::SeqCst)
+    }
+
+    /// Which of the signals attached with [`BackgroundWorker::attach_signal_handlers`] have we
+    /// received since we last checked?
+    ///
+    /// Like the individual `*_received()` functions, this resets each signal it returns.
+    pub fn signals_received() -> SignalWakeFlags {
+        let mut signals = SignalWakeFlags::empty();
+        signals.set(SignalWakeFlags::SIGHUP, BackgroundWorker::sighup_received());
+        signals.set(
+            SignalWakeFlags::SIGTERM,
+            BackgroundWorker::sigterm_received(),
+        );
+        signals.set(
+            SignalWakeFlags::SIGUSR1,
+            BackgroundWorker::sigusr1_received(),
+        );
+        signals.set(
+            SignalWakeFlags::SIGUSR2,
+            BackgroundWorker::sigusr2_received(),
+        );
+        signals
     }
 
     /// Wait for the specified amount of time on the background worker's latch
     ///
     /// Returns true if we're still supposed to be alive and haven't received a SIGTERM
     pub fn wait_latch(timeout: Option<Duration>) -> bool {
-        match timeout {
-            Some(t) => wait_latch(
-                t.as_millis().try_into().unwrap(),
-                WLflags::WL_LATCH_SET | WLflags::WL_TIMEOUT | WLflags::WL_POSTMASTER_DEATH,
-            ),
-            None => wait_latch(0, WLflags::WL_LATCH_SET | WLflags::WL_POSTMASTER_DEATH),
-        };
+        BackgroundWorker::wait_latch_for(timeout, WaitEvent::EXTENSION)
+    }
+
+    /// Like [`BackgroundWorker::wait_latch`], but reporting the wait in `pg_stat_activity` as
+    /// `event`
+    pub fn wait_latch_for(timeout: Option<Duration>, event: WaitEvent) -> bool {
+        wait_latch_timeout(timeout, event);
         !BackgroundWorker::sigterm_received()
     }
 
+    /// Wait for the specified amount of time on the background worker's latch, returning the
+    /// signals received since they were last checked, which is empty if none arrived in time
+    ///
+    /// This allows a worker to respond to commands sent as signals, such as with
+    /// [`BackgroundWorker::signal`] from a SQL function:
+    ///
+    /// ```rust,no_run
+    /// use pgx::bgworkers::{BackgroundWorker, SignalWakeFlags};
+    /// use std::time::Duration;
+    ///
+    /// let wake = SignalWakeFlags::SIGTERM | SignalWakeFlags::SIGUSR2;
+    /// BackgroundWorker::attach_signal_handlers(wake);
+    /// loop {
+    ///     let signals = BackgroundWorker::wait_for_signals(Some(Duration::from_secs(10)));
+    ///     if signals.contains(SignalWakeFlags::SIGTERM) {
+    ///         break;
+    ///     }
+    ///     if signals.contains(SignalWakeFlags::SIGUSR2) {
+    ///         // flush now
+    ///     }
+    /// }
+    /// ```
+    pub fn wait_for_signals(timeout: Option<Duration>) -> SignalWakeFlags {
+        let signals = BackgroundWorker::signals_received();
+        if !signals.is_empty() {
+            return signals;
+        }
+
+        wait_latch_timeout(timeout, WaitEvent::EXTENSION);
+        BackgroundWorker::signals_received()
+    }
+
+    /// Send `signals` to the process with the specified `pid`, typically a BackgroundWorker
+    /// that attached handlers for them, returning whether they could be sent
+    ///
+    /// Only SIGHUP, SIGTERM, SIGUSR1, and SIGUSR2 are sent.  As Postgres itself uses SIGUSR1,
+    /// SIGUSR2 is the better choice for an extension's own commands.
+    pub fn signal(pid: pg_sys::pid_t, signals: SignalWakeFlags) -> bool {
+        let mut sent = true;
+        for (flag, signal) in [
+            (SignalWakeFlags::SIGHUP, pg_sys::SIGHUP),
+            (SignalWakeFlags::SIGTERM, pg_sys::SIGTERM),
+            (SignalWakeFlags::SIGUSR1, pg_sys::SIGUSR1),
+            (SignalWakeFlags::SIGUSR2, pg_sys::SIGUSR2),
+        ] {
+            if signals.contains(flag) {
+                sent &= unsafe { pg_sys::kill(pid, signal as i32) } == 0;
+            }
+        }
+        sent
+    }
+
     /// Is this `BackgroundWorker` allowed to continue?
     pub fn worker_continue() -> bool {
         pg_sys::WL_POSTMASTER_DEATH as i32 != 0
     }
 
+    /// Count this start of the worker in `starts`, returning how many times it had been started
+    /// before, which is nonzero if it's been restarted
+    ///
+    /// `starts` must be in shared memory, initialized with `pg_shmem_init!()`.  As that's reset
+    /// whenever Postgres reinitializes after a crash, this only counts restarts since then,
+    /// such as those after the worker exits with [`BackgroundWorker::exit`].
+    ///
+    /// ```rust,no_run
+    /// use pgx::bgworkers::BackgroundWorker;
+    /// use pgx::*;
+    /// use std::sync::atomic::AtomicU32;
+    ///
+    /// static STARTS: PgAtomic<AtomicU32> = PgAtomic::new();
+    ///
+    /// #[pg_guard]
+    /// pub extern "C" fn background_worker_main(_arg: pg_sys::Datum) {
+    ///     if BackgroundWorker::record_start(&STARTS) > 0 {
+    ///         log!("recovering from an earlier failure");
+    ///     }
+    /// }
+    /// ```
+    pub fn record_start(starts: &PgAtomic<AtomicU32>) -> u32 {
+        starts.get().fetch_add(1, Ordering::SeqCst)
+    }
+
+    /// Exit the worker now, asking Postgres to start it again, after its restart time, if
+    /// `restart` is true, or to unregister it for good otherwise
+    ///
+    /// A worker whose restart time is `None` is never restarted, and returning from the main
+    /// function is the same as exiting with `restart` false.
+    pub fn exit(restart: bool) -> ! {
+        unsafe { pg_sys::proc_exit(if restart { 1 } else { 0 }) };
+        unreachable!("proc_exit() returned")
+    }
+
+    /// A token the worker's code can check to see whether it's been asked to stop
+    ///
+    /// The worker must have attached a SIGTERM handler with
+    /// [`BackgroundWorker::attach_signal_handlers`].
+    pub fn shutdown_token() -> ShutdownToken {
+        ShutdownToken { _private: () }
+    }
+
+    /// Give the worker at most `timeout` to finish up once it notices it's been asked to stop,
+    /// after which it's terminated, at its next check for interrupts, as if by
+    /// `pg_terminate_backend()`
+    ///
+    /// The time starts when the worker first sees the request, through a [`ShutdownToken`],
+    /// [`BackgroundWorker::sigterm_received`], or the result of one of the `wait_latch()`
+    /// functions.  Like any other `FATAL` error, this exits with a status of 1, so a worker
+    /// with a restart time will be restarted, unless the server is shutting down.
+    pub fn set_drain_timeout(timeout: Duration) {
+        unsafe {
+            let id = match DRAIN_TIMEOUT {
+                Some((id, _)) => id,
+                None => pg_sys::RegisterTimeout(
+                    pg_sys::TimeoutId_USER_TIMEOUT,
+                    Some(drain_timeout_handler),
+                ),
+            };
+            DRAIN_TIMEOUT = Some((id, timeout));
+        }
+    }
+
+    /// Run `callback` in a transaction of its own when the worker exits, however it exits,
+    /// such as to flush what it's buffered, or to mark work it didn't finish as unfinished
+    ///
+    /// Callbacks run in the reverse of the order they were registered, each after any
+    /// transaction the worker was in the middle of has been aborted.  An error in one is
+    /// logged, and doesn't stop the others from running.  Must be called after connecting
+    /// with [`BackgroundWorker::connect_worker_to_spi`].
+    ///
+    /// ```rust,no_run
+    /// use pgx::bgworkers::{BackgroundWorker, SignalWakeFlags};
+    /// use pgx::*;
+    /// use std::time::Duration;
+    ///
+    /// BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    /// BackgroundWorker::connect_worker_to_spi(Some("postgres"), None);
+    /// BackgroundWorker::set_drain_timeout(Duration::from_secs(5));
+    /// BackgroundWorker::on_exit(|| Spi::run("UPDATE jobs SET state = 'queued' WHERE state = 'running'"));
+    ///
+    /// let shutdown = BackgroundWorker::shutdown_token();
+    /// while !shutdown.wait(Duration::from_secs(10)) {
+    ///     BackgroundWorker::transaction(|| Spi::run("SELECT run_next_job()"));
+    /// }
+    /// ```
+    pub fn on_exit<F: FnOnce() + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static>(
+        callback: F,
+    ) {
+        unsafe {
+            let callbacks = EXIT_CALLBACKS.get_or_insert_with(|| {
+                // registered after `InitPostgres()`'s own callback, so ours run first, while
+                // the backend can still run transactions
+                pg_sys::before_shmem_exit(Some(run_exit_callbacks), 0);
+                Vec::new()
+            });
+            callbacks.push(Box::new(callback));
+        }
+    }
+
     /// Intended to be called once to indicate the database and user to use to
     /// connect to via SPI
     pub fn connect_worker_to_spi(dbname: Option<&str>, username: Option<&str>) {
@@ -143,6 +355,61 @@ impl BackgroundWorker {
         };
     }
 
+    /// Connect to the database whose oid is `database`, such as one from
+    /// [`BackgroundWorker::databases`], as the user whose oid is `user`, or the bootstrap
+    /// superuser if `None`
+    pub fn connect_worker_to_database(database: pg_sys::Oid, user: Option<pg_sys::Oid>) {
+        let user = user.unwrap_or(pg_sys::InvalidOid);
+        unsafe {
+            #[cfg(feature = "pg10")]
+            pg_sys::BackgroundWorkerInitializeConnectionByOid(database, user);
+
+            #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+            pg_sys::BackgroundWorkerInitializeConnectionByOid(database, user, 0);
+        }
+    }
+
+    /// The databases in the cluster that allow connections, read from `pg_database` in a
+    /// transaction of their own
+    ///
+    /// A worker that only needs this, such as a launcher for [`DatabaseWorkers`], can connect to
+    /// just the shared catalogs with `BackgroundWorker::connect_worker_to_spi(None, None)`.
+    pub fn databases() -> Vec<Database> {
+        BackgroundWorker::transaction(|| unsafe {
+            let lockmode = pg_sys::AccessShareLock as pg_sys::LOCKMODE;
+            let rel = pg_sys::relation_open(pg_sys::DatabaseRelationId, lockmode);
+
+            #[cfg(any(feature = "pg10", feature = "pg11"))]
+            let scan = pg_sys::heap_beginscan_catalog(rel, 0, std::ptr::null_mut());
+            #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+            let scan = pg_sys::table_beginscan_catalog(rel, 0, std::ptr::null_mut());
+
+            let mut databases = Vec::new();
+            loop {
+                let tuple = pg_sys::heap_getnext(scan, pg_sys::ScanDirection_ForwardScanDirection);
+                if tuple.is_null() {
+                    break;
+                }
+
+                let form = pg_sys::heap_tuple_get_struct::<pg_sys::FormData_pg_database>(tuple);
+                if !(*form).datallowconn {
+                    continue;
+                }
+                databases.push(Database {
+                    oid: database_oid(tuple, form),
+                    name: CStr::from_ptr((*form).datname.data.as_ptr())
+                        .to_string_lossy()
+                        .into_owned(),
+                    is_template: (*form).datistemplate,
+                });
+            }
+
+            pg_sys::heap_endscan(scan);
+            pg_sys::relation_close(rel, lockmode);
+            databases
+        })
+    }
+
     /// Indicate the set of signal handlers we want to receive.
     ///
     /// You likely always want to do this:
@@ -159,24 +426,174 @@ impl BackgroundWorker {
             if wake.contains(SignalWakeFlags::SIGTERM) {
                 pg_sys::pqsignal(pg_sys::SIGTERM as i32, Some(worker_spi_sigterm));
             }
+            if wake.contains(SignalWakeFlags::SIGUSR1) {
+                pg_sys::pqsignal(pg_sys::SIGUSR1 as i32, Some(worker_spi_sigusr1));
+            }
+            if wake.contains(SignalWakeFlags::SIGUSR2) {
+                pg_sys::pqsignal(pg_sys::SIGUSR2 as i32, Some(worker_spi_sigusr2));
+            }
             pg_sys::BackgroundWorkerUnblockSignals();
         }
     }
 
     /// Once connected to SPI via `connect_worker_to_spi()`, begin a transaction to
-    /// use the `pgx::Spi` interface.
-    pub fn transaction<F: FnOnce() + std::panic::UnwindSafe + std::panic::RefUnwindSafe>(
+    /// use the `pgx::Spi` interface, returning whatever `transaction_body` does.
+    ///
+    /// The transaction is committed once `transaction_body` returns.  If it raises an error
+    /// instead, the error is rethrown, which ends the worker, so a worker that should survive
+    /// errors should use [`BackgroundWorker::try_transaction`].
+    pub fn transaction<F: FnOnce() -> R + std::panic::UnwindSafe + std::panic::RefUnwindSafe, R>(
         transaction_body: F,
-    ) {
+    ) -> R {
+        BackgroundWorker::start_transaction();
+        let result = pg_sys::guard(|| transaction_body());
+        BackgroundWorker::commit_transaction();
+        result
+    }
+
+    /// Like [`BackgroundWorker::transaction`], except that if `transaction_body` raises an
+    /// error, it's logged, the transaction is aborted, and `None` is returned, so the worker can
+    /// carry on with its next tick
+    ///
+    /// ```rust,no_run
+    /// use pgx::bgworkers::BackgroundWorker;
+    /// use pgx::*;
+    /// use std::time::Duration;
+    ///
+    /// while BackgroundWorker::wait_latch(Some(Duration::from_secs(10))) {
+    ///     BackgroundWorker::try_transaction(|| {
+    ///         Spi::run("DELETE FROM jobs WHERE finished_at < now() - interval '1 day'");
+    ///     });
+    /// }
+    /// ```
+    pub fn try_transaction<
+        F: FnOnce() -> R + std::panic::UnwindSafe + std::panic::RefUnwindSafe,
+        R,
+    >(
+        transaction_body: F,
+    ) -> Option<R> {
+        BackgroundWorker::start_transaction();
+        match std::panic::catch_unwind(transaction_body) {
+            Ok(result) => {
+                BackgroundWorker::commit_transaction();
+                Some(result)
+            }
+            Err(e) => {
+                if let Some(message) = e.downcast_ref::<String>() {
+                    warning!("{}", message);
+                } else if let Some(message) = e.downcast_ref::<&str>() {
+                    warning!("{}", message);
+                } else {
+                    // it was a Postgres ERROR, which is still on the error stack
+                    unsafe { pg_sys::EmitErrorReport() };
+                }
+                BackgroundWorker::abort_transaction();
+                None
+            }
+        }
+    }
+
+    /// Start a transaction, with a snapshot for it pushed as the active snapshot, so the
+    /// `pgx::Spi` interface can be used until [`BackgroundWorker::commit_transaction`] or
+    /// [`BackgroundWorker::abort_transaction`] is called
+    ///
+    /// Prefer [`BackgroundWorker::transaction`] or [`BackgroundWorker::try_transaction`], which
+    /// always end the transaction they start.
+    pub fn start_transaction() {
         unsafe {
             pg_sys::SetCurrentStatementStartTimestamp();
             pg_sys::StartTransactionCommand();
             pg_sys::PushActiveSnapshot(pg_sys::GetTransactionSnapshot());
         }
-        pg_sys::guard(|| transaction_body());
+    }
+
+    /// Commit the transaction begun with [`BackgroundWorker::start_transaction`], and report
+    /// the worker's activity to the statistics collector
+    pub fn commit_transaction() {
         unsafe {
             pg_sys::PopActiveSnapshot();
             pg_sys::CommitTransactionCommand();
+            pg_sys::pgstat_report_stat(false);
+        }
+    }
+
+    /// Abort the transaction begun with [`BackgroundWorker::start_transaction`], discarding any
+    /// error raised in it
+    ///
+    /// Aborting releases the transaction's snapshots and resources, and any SPI connection
+    /// still open in it.
+    pub fn abort_transaction() {
+        unsafe {
+            pg_sys::AbortCurrentTransaction();
+            pg_sys::FlushErrorState();
+        }
+    }
+}
+
+/// Whether a [`BackgroundWorker`] has been asked to stop, by a SIGTERM
+///
+/// Unlike [`BackgroundWorker::sigterm_received`], checking doesn't reset it, so any part of the
+/// worker's code can check the same token.
+#[derive(Debug, Clone, Copy)]
+pub struct ShutdownToken {
+    _private: (),
+}
+
+impl ShutdownToken {
+    /// Has the worker been asked to stop?
+    pub fn is_requested(&self) -> bool {
+        let requested = GOT_SIGTERM.load(Ordering::SeqCst);
+        if requested {
+            shutdown_observed();
+        }
+        requested
+    }
+
+    /// Wait on the latch for up to `timeout`, returning early if the worker is asked to stop,
+    /// and returning whether it has been
+    pub fn wait(&self, timeout: Duration) -> bool {
+        if !self.is_requested() {
+            wait_latch_timeout(Some(timeout), WaitEvent::EXTENSION);
+        }
+        self.is_requested()
+    }
+}
+
+/// Start the drain timeout, if one's set and it hasn't been started already
+fn shutdown_observed() {
+    unsafe {
+        if let (Some((id, timeout)), false) = (DRAIN_TIMEOUT, DRAIN_TIMEOUT_ARMED) {
+            DRAIN_TIMEOUT_ARMED = true;
+            pg_sys::enable_timeout_after(id, timeout.as_millis().clamp(1, i32::MAX as u128) as i32);
+        }
+    }
+}
+
+/// Runs in a signal handler, so must only do async-signal-safe things
+unsafe extern "C" fn drain_timeout_handler() {
+    set_proc_die_pending();
+    pg_sys::SetLatch(pg_sys::MyLatch);
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11"))]
+unsafe fn set_proc_die_pending() {
+    pg_sys::ProcDiePending = true;
+    pg_sys::InterruptPending = true;
+}
+
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+unsafe fn set_proc_die_pending() {
+    pg_sys::ProcDiePending = 1;
+    pg_sys::InterruptPending = 1;
+}
+
+#[pg_guard]
+unsafe extern "C" fn run_exit_callbacks(_code: i32, _arg: pg_sys::Datum) {
+    // whatever the worker was doing when it exited is abandoned
+    pg_sys::AbortOutOfAnyTransaction();
+    if let Some(callbacks) = EXIT_CALLBACKS.take() {
+        for callback in callbacks.into_iter().rev() {
+            BackgroundWorker::try_transaction(callback);
         }
     }
 }
@@ -192,6 +609,19 @@ unsafe extern "C" fn worker_spi_sigterm(_signal_args: i32) {
     pg_sys::SetLatch(pg_sys::MyLatch);
 }
 
+unsafe extern "C" fn worker_spi_sigusr1(signal_args: i32) {
+    GOT_SIGUSR1.store(true, Ordering::SeqCst);
+    // Postgres relies on SIGUSR1 for latches and its own inter-process signals, so it must
+    // still see every one
+    pg_sys::procsignal_sigusr1_handler(signal_args);
+    pg_sys::SetLatch(pg_sys::MyLatch);
+}
+
+unsafe extern "C" fn worker_spi_sigusr2(_signal_args: i32) {
+    GOT_SIGUSR2.store(true, Ordering::SeqCst);
+    pg_sys::SetLatch(pg_sys::MyLatch);
+}
+
 /// A builder-style interface for creating a new Background Worker
 ///
 /// This must be used from within your extension's `_PG_init()` function,
@@ -288,6 +718,10 @@ impl BackgroundWorkerBuilder {
     /// the interval, in seconds, that postgres should wait before restarting the process,
     /// in case it crashes. It can be `Some(any positive duration value), or
     /// `None`, indicating not to restart the process in case of a crash.
+    ///
+    /// Fractions of a second are rounded up, and the interval is capped at `i32::MAX` seconds.
+    /// A running worker can use [`BackgroundWorker::record_start`] to tell whether it's been
+    /// restarted.
     pub fn set_restart_time(mut self: Self, input: Option<Duration>) -> Self {
         self.bgw_restart_time = input;
         self
@@ -370,8 +804,9 @@ impl BackgroundWorkerBuilder {
     /// when the process is started or exits. It should be 0 for workers registered at
     /// postmaster startup time, or when the backend registering the worker does not wish
     /// to wait for the worker to start up. Otherwise, it should be initialized to
-    /// `pgx::pg_sys::MyProcPid`
-    pub fn set_notify_pid(mut self: Self, input: i32) -> Self {
+    /// `pgx::pg_sys::MyProcPid`, which [`BackgroundWorkerBuilder::load_dynamic`] does by
+    /// default.
+    pub fn set_notify_pid(mut self: Self, input: pg_sys::pid_t) -> Self {
         self.bgw_notify_pid = input;
         self
     }
@@ -379,15 +814,65 @@ impl BackgroundWorkerBuilder {
     /// Once properly configured, call `load()` to get the BackgroundWorker registered and
     /// started at the proper time by Postgres.
     pub fn load(self: Self) {
+        let mut bgw = self.to_pg();
+
+        unsafe {
+            pg_sys::RegisterBackgroundWorker(&mut bgw);
+            if self.bgw_flags.contains(BGWflags::BGWORKER_SHMEM_ACCESS)
+                && self.shared_memory_startup_fn.is_some()
+            {
+                PREV_SHMEM_STARTUP_HOOK = pg_sys::shmem_startup_hook;
+                pg_sys::shmem_startup_hook = self.shared_memory_startup_fn;
+            }
+        };
+    }
+
+    /// Register and start the BackgroundWorker now, rather than at a configured start time, and
+    /// return a handle for managing it
+    ///
+    /// Unlike [`BackgroundWorkerBuilder::load`], this is for use once Postgres is running, such as
+    /// from a function or another BackgroundWorker.  If no `notify_pid` was set, it defaults to
+    /// the current process, so the handle can wait for the worker to start and stop.
+    ///
+    /// Fails if the worker can't be registered, typically because there are already
+    /// `max_worker_processes` workers.
+    ///
+    /// ```rust,no_run
+    /// use pgx::bgworkers::BackgroundWorkerBuilder;
+    /// use pgx::IntoDatum;
+    ///
+    /// let worker = BackgroundWorkerBuilder::new("Job Runner")
+    ///     .set_function("job_runner_main")
+    ///     .set_library("example")
+    ///     .set_argument(42i32.into_datum())
+    ///     .enable_spi_access()
+    ///     .load_dynamic()
+    ///     .expect("failed to start job runner");
+    /// let pid = worker.wait_for_startup().expect("job runner didn't start");
+    /// ```
+    pub fn load_dynamic(
+        mut self: Self,
+    ) -> Result<DynamicBackgroundWorker, RegisterBackgroundWorkerError> {
+        if self.bgw_notify_pid == 0 {
+            self.bgw_notify_pid = unsafe { pg_sys::MyProcPid };
+        }
+
+        let mut bgw = self.to_pg();
+        let mut handle = std::ptr::null_mut();
+        if unsafe { pg_sys::RegisterDynamicBackgroundWorker(&mut bgw, &mut handle) } {
+            Ok(DynamicBackgroundWorker { handle })
+        } else {
+            Err(RegisterBackgroundWorkerError)
+        }
+    }
+
+    pub(crate) fn to_pg(&self) -> pg_sys::BackgroundWorker {
         #[cfg(feature = "pg10")]
-        let mut bgw = pg_sys::BackgroundWorker {
+        let bgw = pg_sys::BackgroundWorker {
             bgw_name: RpgffiChar::from(&self.bgw_name[..]).0,
             bgw_flags: self.bgw_flags.bits(),
             bgw_start_time: self.bgw_start_time as u32,
-            bgw_restart_time: match self.bgw_restart_time {
-                None => pg_sys::BGW_NEVER_RESTART,
-                Some(d) => d.as_secs() as i32,
-            },
+            bgw_restart_time: self.restart_time(),
             bgw_library_name: RpgffiChar::from(&self.bgw_library_name[..]).0,
             bgw_function_name: RpgffiChar::from(&self.bgw_function_name[..]).0,
             bgw_main_arg: self.bgw_main_arg,
@@ -396,15 +881,12 @@ impl BackgroundWorkerBuilder {
         };
 
         #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
-        let mut bgw = pg_sys::BackgroundWorker {
+        let bgw = pg_sys::BackgroundWorker {
             bgw_name: RpgffiChar::from(&self.bgw_name[..]).0,
             bgw_type: RpgffiChar::from(&self.bgw_type[..]).0,
             bgw_flags: self.bgw_flags.bits(),
             bgw_start_time: self.bgw_start_time as u32,
-            bgw_restart_time: match self.bgw_restart_time {
-                None => -1,
-                Some(d) => d.as_secs() as i32,
-            },
+            bgw_restart_time: self.restart_time(),
             bgw_library_name: RpgffiChar::from(&self.bgw_library_name[..]).0,
             bgw_function_name: RpgffiChar::from(&self.bgw_function_name[..]).0,
             bgw_main_arg: self.bgw_main_arg,
@@ -412,28 +894,296 @@ impl BackgroundWorkerBuilder {
             bgw_notify_pid: self.bgw_notify_pid,
         };
 
-        unsafe {
-            pg_sys::RegisterBackgroundWorker(&mut bgw);
-            if self.bgw_flags.contains(BGWflags::BGWORKER_SHMEM_ACCESS)
-                && self.shared_memory_startup_fn.is_some()
-            {
-                PREV_SHMEM_STARTUP_HOOK = pg_sys::shmem_startup_hook;
-                pg_sys::shmem_startup_hook = self.shared_memory_startup_fn;
+        bgw
+    }
+
+    fn restart_time(&self) -> i32 {
+        match self.bgw_restart_time {
+            None => pg_sys::BGW_NEVER_RESTART,
+            Some(d) => {
+                let secs = d.as_secs() + if d.subsec_nanos() > 0 { 1 } else { 0 };
+                secs.try_into().unwrap_or(i32::MAX)
             }
-        };
+        }
+    }
+}
+
+/// The state of a dynamic BackgroundWorker, as reported by Postgres
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundWorkerStatus {
+    /// The worker is running, as this process
+    Started(pg_sys::pid_t),
+    /// The worker is registered, but the postmaster hasn't started it yet
+    NotYetStarted,
+    /// The worker has exited, or was never started
+    Stopped,
+    /// The postmaster has died, so the worker's state can't be known
+    PostmasterDied,
+}
+
+impl BackgroundWorkerStatus {
+    fn from_pg(status: pg_sys::BgwHandleStatus, pid: pg_sys::pid_t) -> Self {
+        match status {
+            pg_sys::BgwHandleStatus_BGWH_STARTED => BackgroundWorkerStatus::Started(pid),
+            pg_sys::BgwHandleStatus_BGWH_NOT_YET_STARTED => BackgroundWorkerStatus::NotYetStarted,
+            pg_sys::BgwHandleStatus_BGWH_STOPPED => BackgroundWorkerStatus::Stopped,
+            pg_sys::BgwHandleStatus_BGWH_POSTMASTER_DIED => BackgroundWorkerStatus::PostmasterDied,
+            _ => panic!("unrecognized BgwHandleStatus: {}", status),
+        }
+    }
+}
+
+/// [`BackgroundWorkerBuilder::load_dynamic`] couldn't register the worker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterBackgroundWorkerError;
+
+impl std::fmt::Display for RegisterBackgroundWorkerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "could not register background worker: max_worker_processes may be too low"
+        )
     }
 }
 
-fn wait_latch(timeout: i64, wakeup_flags: WLflags) -> i32 {
+impl std::error::Error for RegisterBackgroundWorkerError {}
+
+/// A handle to a BackgroundWorker started by [`BackgroundWorkerBuilder::load_dynamic`]
+///
+/// The handle is allocated in the `CurrentMemoryContext` of the time the worker was started, and
+/// must not outlive it.  Dropping the handle doesn't stop the worker.
+pub struct DynamicBackgroundWorker {
+    handle: *mut pg_sys::BackgroundWorkerHandle,
+}
+
+impl DynamicBackgroundWorker {
+    /// The worker's current state
+    pub fn status(&self) -> BackgroundWorkerStatus {
+        let mut pid = 0;
+        let status = unsafe { pg_sys::GetBackgroundWorkerPid(self.handle, &mut pid) };
+        BackgroundWorkerStatus::from_pg(status, pid)
+    }
+
+    /// The worker's process id, if it's running
+    pub fn pid(&self) -> Option<pg_sys::pid_t> {
+        match self.status() {
+            BackgroundWorkerStatus::Started(pid) => Some(pid),
+            _ => None,
+        }
+    }
+
+    /// Wait for the worker to start, returning its process id, or the state it ended up in
+    /// instead if it couldn't be started
+    pub fn wait_for_startup(&self) -> Result<pg_sys::pid_t, BackgroundWorkerStatus> {
+        let mut pid = 0;
+        let status = unsafe { pg_sys::WaitForBackgroundWorkerStartup(self.handle, &mut pid) };
+        match BackgroundWorkerStatus::from_pg(status, pid) {
+            BackgroundWorkerStatus::Started(pid) => Ok(pid),
+            status => Err(status),
+        }
+    }
+
+    /// Ask the postmaster to send the worker a SIGTERM, or not to start it, if it hasn't been
+    /// started yet
+    pub fn terminate(&self) {
+        unsafe { pg_sys::TerminateBackgroundWorker(self.handle) }
+    }
+
+    /// Wait for the worker to exit
+    ///
+    /// Fails with [`BackgroundWorkerStatus::PostmasterDied`] if the postmaster died first.
+    pub fn wait_for_shutdown(&self) -> Result<(), BackgroundWorkerStatus> {
+        let status = unsafe { pg_sys::WaitForBackgroundWorkerShutdown(self.handle) };
+        match BackgroundWorkerStatus::from_pg(status, 0) {
+            BackgroundWorkerStatus::Stopped => Ok(()),
+            status => Err(status),
+        }
+    }
+
+    /// Send `signals` to the worker, returning whether it's running and they could be sent
+    ///
+    /// See [`BackgroundWorker::signal`].
+    pub fn signal(&self, signals: SignalWakeFlags) -> bool {
+        match self.pid() {
+            Some(pid) => BackgroundWorker::signal(pid, signals),
+            None => false,
+        }
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::BackgroundWorkerHandle {
+        self.handle
+    }
+}
+
+/// A database, as listed by [`BackgroundWorker::databases`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Database {
+    pub oid: pg_sys::Oid,
+    pub name: String,
+    /// Whether it's a template, such as `template1`, which can be cloned by `CREATE DATABASE`
+    pub is_template: bool,
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11"))]
+unsafe fn database_oid(
+    tuple: pg_sys::HeapTuple,
+    _form: *mut pg_sys::FormData_pg_database,
+) -> pg_sys::Oid {
+    extern "C" {
+        fn pgx_HeapTupleHeaderGetOid(htup_header: pg_sys::HeapTupleHeader) -> pg_sys::Oid;
+    }
+
+    pgx_HeapTupleHeaderGetOid((*tuple).t_data)
+}
+
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+unsafe fn database_oid(
+    _tuple: pg_sys::HeapTuple,
+    form: *mut pg_sys::FormData_pg_database,
+) -> pg_sys::Oid {
+    (*form).oid
+}
+
+/// A dynamic BackgroundWorker for each database, in the style of autovacuum's workers
+///
+/// A launcher worker calls [`DatabaseWorkers::sync`] on each tick of its main loop, which starts
+/// a worker for each database that doesn't have one running, including any created since the last
+/// tick, and stops those whose databases have been dropped.  Each worker's argument is its
+/// database's oid, to pass to [`BackgroundWorker::connect_worker_to_database`].
+///
+/// ```rust,no_run
+/// use pgx::bgworkers::*;
+/// use pgx::*;
+/// use std::time::Duration;
+///
+/// #[pg_guard]
+/// pub extern "C" fn launcher_main(_arg: pg_sys::Datum) {
+///     BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+///     BackgroundWorker::connect_worker_to_spi(None, None);
+///
+///     let mut workers = DatabaseWorkers::new(|database| {
+///         Some(
+///             BackgroundWorkerBuilder::new(&format!("my daemon for {}", database.name))
+///                 .set_library("example")
+///                 .set_function("per_database_main")
+///                 .enable_spi_access(),
+///         )
+///     });
+///     while BackgroundWorker::wait_latch(Some(Duration::from_secs(10))) {
+///         workers.sync();
+///     }
+///     workers.terminate_all();
+/// }
+///
+/// #[pg_guard]
+/// pub extern "C" fn per_database_main(arg: pg_sys::Datum) {
+///     BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+///     BackgroundWorker::connect_worker_to_database(arg as pg_sys::Oid, None);
+///     // do per-database maintenance here
+/// }
+/// ```
+pub struct DatabaseWorkers {
+    builder: Box<dyn Fn(&Database) -> Option<BackgroundWorkerBuilder>>,
+    workers: Vec<(Database, DynamicBackgroundWorker)>,
+}
+
+impl DatabaseWorkers {
+    /// Workers built by `builder` for each database, or for those it returns a builder for
+    ///
+    /// No workers are started until [`DatabaseWorkers::sync`] is called.
+    pub fn new<F: Fn(&Database) -> Option<BackgroundWorkerBuilder> + 'static>(builder: F) -> Self {
+        DatabaseWorkers {
+            builder: Box::new(builder),
+            workers: Vec::new(),
+        }
+    }
+
+    /// Start a worker for each database that doesn't have one running, and stop those whose
+    /// databases no longer exist
+    ///
+    /// Workers that exited since the last call are started again.  A database whose worker
+    /// can't be registered, as there aren't enough free worker processes, is tried again on the
+    /// next call.  Must be called outside a transaction.
+    pub fn sync(&mut self) {
+        let databases = BackgroundWorker::databases();
+
+        self.workers.retain(|(database, worker)| {
+            let exists = databases.iter().any(|d| d.oid == database.oid);
+            if !exists {
+                worker.terminate();
+            }
+            let keep = exists
+                && matches!(
+                    worker.status(),
+                    BackgroundWorkerStatus::Started(_) | BackgroundWorkerStatus::NotYetStarted
+                );
+            if !keep {
+                unsafe { pg_sys::pfree(worker.as_ptr() as _) };
+            }
+            keep
+        });
+
+        for database in databases {
+            if self.workers.iter().any(|(d, _)| d.oid == database.oid) {
+                continue;
+            }
+            let builder = match (self.builder)(&database) {
+                Some(builder) => builder.set_argument(Some(database.oid as pg_sys::Datum)),
+                None => continue,
+            };
+            // the handle must outlive the transaction contexts of later ticks
+            let worker = PgMemoryContexts::TopMemoryContext.switch_to(|_| builder.load_dynamic());
+            match worker {
+                Ok(worker) => self.workers.push((database, worker)),
+                Err(e) => {
+                    warning!(
+                        "couldn't start a worker for database \"{}\": {}",
+                        database.name,
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    /// The running workers, and the databases they're for
+    pub fn workers(&self) -> impl Iterator<Item = (&Database, &DynamicBackgroundWorker)> {
+        self.workers
+            .iter()
+            .map(|(database, worker)| (database, worker))
+    }
+
+    /// Stop every worker, such as when the launcher is shutting down
+    pub fn terminate_all(&mut self) {
+        for (_, worker) in self.workers.drain(..) {
+            worker.terminate();
+            unsafe { pg_sys::pfree(worker.as_ptr() as _) };
+        }
+    }
+}
+
+fn wait_latch_timeout(timeout: Option<Duration>, event: WaitEvent) -> i32 {
+    match timeout {
+        Some(t) => wait_latch(
+            t.as_millis().try_into().unwrap(),
+            WLflags::WL_LATCH_SET | WLflags::WL_TIMEOUT | WLflags::WL_POSTMASTER_DEATH,
+            event,
+        ),
+        None => wait_latch(
+            0,
+            WLflags::WL_LATCH_SET | WLflags::WL_POSTMASTER_DEATH,
+            event,
+        ),
+    }
+}
+
+fn wait_latch(timeout: i64, wakeup_flags: WLflags, event: WaitEvent) -> i32 {
     unsafe {
-        let latch = pg_sys::WaitLatch(
-            pg_sys::MyLatch,
-            wakeup_flags.bits(),
-            timeout,
-            pg_sys::PG_WAIT_EXTENSION,
-        );
+        let latch = pg_sys::WaitLatch(pg_sys::MyLatch, wakeup_flags.bits(), timeout, event.info());
         pg_sys::ResetLatch(pg_sys::MyLatch);
         check_for_interrupts!();
+        // a SIGHUP reloaded the configuration files as it arrived
+        crate::GucRegistry::process_reload();
 
         latch
     }
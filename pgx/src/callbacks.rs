@@ -9,7 +9,7 @@ Use of this source code is governed by the MIT license that can be found in the
 
 //! Provides safe wrappers around Postgres' "Transaction" and "Sub Transaction" hook system
 
-use crate::pg_sys;
+use crate::{pg_sys, PgMemoryContexts};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
@@ -109,8 +109,9 @@ type CallbackMap = HashMap<PgXactCallbackEvent, Vec<Rc<RefCell<Option<XactCallba
 /// closures can be registered per event (one at a time), and they are called in the order in which
 /// they were registered.
 ///
-/// Registered callbacks only remain registered for the life of a single transaction.  Registration
-/// of permanet callbacks should be done through the unsafe `pg_sys::RegisterXactCallback()` function.
+/// Registered callbacks only remain registered for the life of a single transaction.  Callbacks
+/// which remain registered for every transaction are registered by
+/// [`register_persistent_xact_callback`].
 ///
 ///
 /// ## Examples
@@ -376,3 +377,289 @@ where
 
     SubXactCallbackReceipt(wrapped_func)
 }
+
+/// Call `f` once the current transaction has committed, as for the `PgXactCallbackEvent::Commit`
+/// event, such as to publish changes to a cache once they're visible to other sessions
+///
+/// `f` is forgotten if the transaction aborts instead, and, like any `Commit` callback, it must
+/// not `panic!()` or raise an `ERROR`.  It runs in a memory context of its own, which is deleted
+/// once it returns.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// on_commit(|| {
+///     info!("the transaction committed");
+/// });
+/// ```
+pub fn on_commit<F>(f: F) -> XactCallbackReceipt
+where
+    F: FnOnce() + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static,
+{
+    register_xact_callback(PgXactCallbackEvent::Commit, move || in_callback_context(f))
+}
+
+/// Call `f` once the current transaction has aborted, as for the `PgXactCallbackEvent::Abort`
+/// event, such as to release an external resource acquired during it
+///
+/// `f` is forgotten if the transaction commits instead, and, like any `Abort` callback, it must
+/// not `panic!()` or raise an `ERROR`.  It runs in a memory context of its own, as for
+/// [`on_commit`].
+pub fn on_abort<F>(f: F) -> XactCallbackReceipt
+where
+    F: FnOnce() + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static,
+{
+    register_xact_callback(PgXactCallbackEvent::Abort, move || in_callback_context(f))
+}
+
+/// Call `f` after every transaction commits, from now until it's unregistered, rather than for
+/// the current transaction only, such as to flush a per-backend cache
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     on_every_commit(|| { /* flush this backend's cache */ });
+/// }
+/// ```
+pub fn on_every_commit<F>(f: F) -> PersistentXactCallbackReceipt
+where
+    F: Fn() + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static,
+{
+    register_persistent_xact_callback(PgXactCallbackEvent::Commit, f)
+}
+
+/// Call `f` after every transaction aborts, from now until it's unregistered
+pub fn on_every_abort<F>(f: F) -> PersistentXactCallbackReceipt
+where
+    F: Fn() + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static,
+{
+    register_persistent_xact_callback(PgXactCallbackEvent::Abort, f)
+}
+
+/// Registering a persistent transaction event callback returns a `PersistentXactCallbackReceipt`
+/// that can be used to unregister the callback, in this or any later transaction
+pub struct PersistentXactCallbackReceipt(Rc<RefCell<Option<PersistentXactCallbackWrapper>>>);
+
+impl PersistentXactCallbackReceipt {
+    /// Consumes this `PersistentXactCallbackReceipt` and unregisters the registered callback it
+    /// represents
+    pub fn unregister_callback(self) {
+        self.0.replace(None);
+    }
+}
+
+struct PersistentXactCallbackWrapper(
+    Box<dyn Fn() + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static>,
+);
+
+type PersistentCallbacks = Vec<(
+    PgXactCallbackEvent,
+    Rc<RefCell<Option<PersistentXactCallbackWrapper>>>,
+)>;
+
+/// Register a closure to be called during every `PgXactCallbackEvent` event of its kind, in every
+/// transaction, until it's unregistered.  Multiple closures can be registered per event, and
+/// they are called in the order in which they were registered.
+///
+/// The same restrictions apply to panicking, or raising an `ERROR`, as for
+/// [`register_xact_callback`].  Each call runs in a memory context of its own, which is deleted
+/// once it returns, as the transaction's contexts are being torn down.
+pub fn register_persistent_xact_callback<F>(
+    which_event: PgXactCallbackEvent,
+    f: F,
+) -> PersistentXactCallbackReceipt
+where
+    F: Fn() + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static,
+{
+    static mut PERSISTENT_HOOKS: Option<PersistentCallbacks> = None;
+
+    unsafe extern "C" fn callback(event: pg_sys::XactEvent, _arg: *mut ::std::os::raw::c_void) {
+        let which_event = PgXactCallbackEvent::translate_pg_event(event);
+
+        // forget the callbacks which were unregistered, and copy the rest, so that the callbacks
+        // can register and unregister others while they're called
+        let hooks = match PERSISTENT_HOOKS.as_mut() {
+            Some(hooks) => {
+                hooks.retain(|(_, hook)| hook.borrow().is_some());
+                hooks
+                    .iter()
+                    .filter(|(event, _)| *event == which_event)
+                    .map(|(_, hook)| Rc::clone(hook))
+                    .collect::<Vec<_>>()
+            }
+            None => return,
+        };
+
+        for hook in hooks {
+            let hook = hook.borrow();
+            if let Some(hook) = hook.as_ref() {
+                in_callback_context(|| crate::guard::guard(|| (hook.0)()));
+            }
+        }
+    }
+
+    let hooks = unsafe {
+        PERSISTENT_HOOKS.get_or_insert_with(|| {
+            pg_sys::RegisterXactCallback(Some(callback), std::ptr::null_mut());
+            Vec::new()
+        })
+    };
+
+    let wrapper = PersistentXactCallbackWrapper(Box::new(f));
+    let wrapped_func = Rc::new(RefCell::new(Some(wrapper)));
+    hooks.push((which_event, Rc::clone(&wrapped_func)));
+    PersistentXactCallbackReceipt(wrapped_func)
+}
+
+/// Registering a persistent sub-transaction event callback returns a
+/// `PersistentSubXactCallbackReceipt` that can be used to unregister the callback, in this or any
+/// later transaction
+pub struct PersistentSubXactCallbackReceipt(Rc<RefCell<Option<SubXactCallbackWrapper>>>);
+
+impl PersistentSubXactCallbackReceipt {
+    /// Consumes this `PersistentSubXactCallbackReceipt` and unregisters the registered callback it
+    /// represents
+    pub fn unregister_callback(self) {
+        self.0.replace(None);
+    }
+}
+
+type PersistentSubCallbacks = Vec<(
+    PgSubXactCallbackEvent,
+    Rc<RefCell<Option<SubXactCallbackWrapper>>>,
+)>;
+
+/// Like [`register_subxact_callback`], but the closure is called for every subtransaction, in
+/// every transaction, until it's unregistered, and in a memory context of its own, as for
+/// [`register_persistent_xact_callback`]
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// register_persistent_subxact_callback(PgSubXactCallbackEvent::AbortSub, |my_subid, _| {
+///     // release what this backend acquired in subtransaction `my_subid`
+/// });
+/// ```
+pub fn register_persistent_subxact_callback<F>(
+    which_event: PgSubXactCallbackEvent,
+    f: F,
+) -> PersistentSubXactCallbackReceipt
+where
+    F: Fn(pg_sys::SubTransactionId, pg_sys::SubTransactionId)
+        + std::panic::UnwindSafe
+        + std::panic::RefUnwindSafe
+        + 'static,
+{
+    static mut PERSISTENT_SUB_HOOKS: Option<PersistentSubCallbacks> = None;
+
+    unsafe extern "C" fn callback(
+        event: pg_sys::SubXactEvent,
+        my_subid: pg_sys::SubTransactionId,
+        parent_subid: pg_sys::SubTransactionId,
+        _arg: *mut ::std::os::raw::c_void,
+    ) {
+        let which_event = PgSubXactCallbackEvent::translate_pg_event(event);
+
+        let hooks = match PERSISTENT_SUB_HOOKS.as_mut() {
+            Some(hooks) => {
+                hooks.retain(|(_, hook)| hook.borrow().is_some());
+                hooks
+                    .iter()
+                    .filter(|(event, _)| *event == which_event)
+                    .map(|(_, hook)| Rc::clone(hook))
+                    .collect::<Vec<_>>()
+            }
+            None => return,
+        };
+
+        for hook in hooks {
+            let hook = hook.borrow();
+            if let Some(hook) = hook.as_ref() {
+                in_callback_context(|| crate::guard::guard(|| (hook.0)(my_subid, parent_subid)));
+            }
+        }
+    }
+
+    let hooks = unsafe {
+        PERSISTENT_SUB_HOOKS.get_or_insert_with(|| {
+            pg_sys::RegisterSubXactCallback(Some(callback), std::ptr::null_mut());
+            Vec::new()
+        })
+    };
+
+    let wrapped_func = Rc::new(RefCell::new(Some(SubXactCallbackWrapper(Box::new(f)))));
+    hooks.push((which_event, Rc::clone(&wrapped_func)));
+    PersistentSubXactCallbackReceipt(wrapped_func)
+}
+
+/// Call `f` in a memory context of its own, which is deleted once it returns, or unwinds
+///
+/// Callbacks are called at the end of a transaction, when the `CurrentMemoryContext` is about to
+/// be deleted, or, as a transaction aborts, may be the small context Postgres reserves for
+/// aborting, so what a callback `palloc()`s is freed here instead.  Each call has its own
+/// context, as a callback's queries can fire other callbacks, such as for the subtransactions of
+/// PL/pgSQL exception blocks, while the outer callback's memory is still in use.
+fn in_callback_context<R, F: FnOnce() -> R>(f: F) -> R {
+    let context = PgMemoryContexts::TopMemoryContext.new_child("pgx transaction callback");
+    let _switched = context.switch();
+    f()
+}
+
+/// Defer `f` until the end of the current transaction, immediately before it commits, as for
+/// the `PgXactCallbackEvent::PreCommit` event
+///
+/// Deferred triggers have already fired by then, so work deferred by them still runs, and `f`
+/// can abort the transaction with a Rust `panic!()` or Postgres `ereport(ERROR)`.
+///
+/// Unlike a `PgXactCallbackEvent::PreCommit` callback, `f` is forgotten if the savepoint (or the
+/// exception block) it was deferred in is rolled back, so that it only runs for work which is
+/// actually committed.
+///
+/// ## Examples
+///
+/// Audit the rows a trigger sees once, when the transaction commits:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// defer_until_commit(|| {
+///     info!("all of the transaction's rows are audited");
+/// });
+/// ```
+pub fn defer_until_commit<F>(f: F) -> XactCallbackReceipt
+where
+    F: FnOnce() + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static,
+{
+    let receipt = register_xact_callback(PgXactCallbackEvent::PreCommit, f);
+    if !unsafe { pg_sys::IsSubTransaction() } {
+        return receipt;
+    }
+
+    // the subtransaction `f` is deferred in, which becomes its parent when it's released, so
+    // that `f` is forgotten if any of the subtransactions it belongs to is rolled back
+    let deferred = std::panic::AssertUnwindSafe(Rc::new((
+        Rc::clone(&receipt.0),
+        std::cell::Cell::new(unsafe { pg_sys::GetCurrentSubTransactionId() }),
+    )));
+
+    let released = std::panic::AssertUnwindSafe(Rc::clone(&deferred));
+    register_subxact_callback(
+        PgSubXactCallbackEvent::CommitSub,
+        move |my_subid, parent_subid| {
+            let (_, subid) = &**released;
+            if subid.get() == my_subid {
+                subid.set(parent_subid);
+            }
+        },
+    );
+    register_subxact_callback(PgSubXactCallbackEvent::AbortSub, move |my_subid, _| {
+        let (wrapped, subid) = &**deferred;
+        if subid.get() == my_subid {
+            wrapped.replace(None);
+        }
+    });
+
+    receipt
+}
@@ -0,0 +1,85 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Catching Postgres ERRORs in Rust, as a `Result`
+use crate::log::{ErrorReport, PgLogLevel};
+use crate::spi::trap_sql_error;
+use crate::SpiSqlError;
+
+/// A Postgres ERROR caught by [`catch_error`], with its SQLSTATE, message, detail, hint, and
+/// context
+pub type CaughtError = SpiSqlError;
+
+/// Run `f`, returning the Postgres ERROR it raises, if it does, rather than aborting the
+/// transaction
+///
+/// `f` runs in a subtransaction, which is rolled back when it raises an ERROR, undoing what it
+/// did, releasing its locks and buffers, and resetting the memory it allocated, so the backend is
+/// as it was before `f` ran.  The current memory context and resource owner are restored either
+/// way.  Rust panics aren't caught; they go on unwinding once the subtransaction is rolled back.
+///
+/// An ERROR which isn't `f`'s to handle, such as a cancelled query, should be rethrown, as
+/// [`CaughtError::must_rethrow`] says:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// let mut attempts = 0;
+/// let name = loop {
+///     match catch_error(|| Spi::get_one::<String>("SELECT name FROM dogs FOR UPDATE NOWAIT")) {
+///         Ok(name) => break name,
+///         Err(error) if error.is(PgSqlErrorCode::ERRCODE_LOCK_NOT_AVAILABLE) && attempts < 3 => {
+///             attempts += 1;
+///         }
+///         Err(error) => error.rethrow(),
+///     }
+/// };
+/// ```
+pub fn catch_error<T, F: FnOnce() -> T + std::panic::UnwindSafe>(f: F) -> Result<T, CaughtError> {
+    trap_sql_error(f)
+}
+
+impl SpiSqlError {
+    /// Should this ERROR be rethrown, rather than handled, as it means the query was cancelled,
+    /// or the backend is being terminated, or it's out of resources, or Postgres hit an internal
+    /// error?
+    ///
+    /// Cancellations and terminations in particular are only noticed once, so swallowing one
+    /// keeps the backend running a query it was asked to stop.
+    pub fn must_rethrow(&self) -> bool {
+        // Class 53 - Insufficient Resources, 57 - Operator Intervention, 58 - System Error, and
+        // XX - Internal Error
+        matches!(&self.sqlstate[..2], "53" | "57" | "58" | "XX")
+    }
+
+    /// Raise this ERROR again, with the same SQLSTATE, message, detail, hint, and context, from
+    /// where this is called
+    #[track_caller]
+    pub fn rethrow(self) -> ! {
+        let location = std::panic::Location::caller();
+        let mut report = ErrorReport::from_sqlerrcode(
+            self.sqlerrcode,
+            self.message,
+            location.file(),
+            location.line(),
+            module_path!(),
+        );
+        if let Some(detail) = self.detail {
+            report = report.detail(detail);
+        }
+        if let Some(hint) = self.hint {
+            report = report.hint(hint);
+        }
+        if let Some(context) = self.context {
+            report = report.context(context);
+        }
+        report.report(PgLogLevel::ERROR);
+        unreachable!("ERROR didn't abort the transaction")
+    }
+}
@@ -0,0 +1,117 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Collation-aware comparison and case-mapping of text, using Postgres' own (libc or ICU) routines
+//! rather than Rust's locale-unaware defaults
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[pg_extern]
+//! fn same_ignoring_case(a: &str, b: &str, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+//!     let collation = PgCollation::from_fcinfo(fcinfo);
+//!     collation.to_lower(a) == collation.to_lower(b)
+//! }
+//! ```
+use crate::{direct_function_call_with_collation, pg_get_collation, pg_sys, IntoDatum};
+use std::cmp::Ordering;
+use std::ffi::CStr;
+
+// from `catalog/pg_collation.h`
+const DEFAULT_COLLATION_OID: pg_sys::Oid = 100;
+const C_COLLATION_OID: pg_sys::Oid = 950;
+
+/// A collation, used to compare and case-map text the same way Postgres would.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PgCollation {
+    oid: pg_sys::Oid,
+}
+
+impl Default for PgCollation {
+    /// The database's default collation
+    fn default() -> Self {
+        Self::from_oid(DEFAULT_COLLATION_OID)
+    }
+}
+
+impl PgCollation {
+    /// The `"C"` collation, which compares bytes.
+    pub fn c() -> Self {
+        Self::from_oid(C_COLLATION_OID)
+    }
+
+    /// The collation with the specified `oid`.
+    pub fn from_oid(oid: pg_sys::Oid) -> Self {
+        Self { oid }
+    }
+
+    /// The input collation of the function call, such as one provided by a `COLLATE` clause.
+    ///
+    /// Falls back to the database's default collation if the call has no input collation.
+    pub fn from_fcinfo(fcinfo: pg_sys::FunctionCallInfo) -> Self {
+        match pg_get_collation(fcinfo) {
+            pg_sys::InvalidOid => Self::default(),
+            oid => Self::from_oid(oid),
+        }
+    }
+
+    pub fn oid(&self) -> pg_sys::Oid {
+        self.oid
+    }
+
+    /// The name of this collation, or `None` if no such collation exists.
+    pub fn name(&self) -> Option<String> {
+        unsafe {
+            let name = pg_sys::get_collation_name(self.oid);
+            if name.is_null() {
+                None
+            } else {
+                Some(CStr::from_ptr(name).to_string_lossy().into_owned())
+            }
+        }
+    }
+
+    /// Compare `a` and `b` as Postgres' `text` comparison operators would under this collation.
+    pub fn cmp(&self, a: &str, b: &str) -> Ordering {
+        let result = unsafe {
+            direct_function_call_with_collation::<i32>(
+                pg_sys::bttextcmp,
+                self.oid,
+                vec![a.into_datum(), b.into_datum()],
+            )
+        }
+        .expect("bttextcmp returned NULL");
+        result.cmp(&0)
+    }
+
+    /// Equivalent to SQL's `lower()` under this collation.
+    pub fn to_lower(&self, s: &str) -> String {
+        self.case_map(pg_sys::lower, s)
+    }
+
+    /// Equivalent to SQL's `upper()` under this collation.
+    pub fn to_upper(&self, s: &str) -> String {
+        self.case_map(pg_sys::upper, s)
+    }
+
+    /// Equivalent to SQL's `initcap()` under this collation.
+    pub fn initcap(&self, s: &str) -> String {
+        self.case_map(pg_sys::initcap, s)
+    }
+
+    fn case_map(
+        &self,
+        func: unsafe fn(pg_sys::FunctionCallInfo) -> pg_sys::Datum,
+        s: &str,
+    ) -> String {
+        unsafe { direct_function_call_with_collation::<&str>(func, self.oid, vec![s.into_datum()]) }
+            .expect("case mapping function returned NULL")
+            .to_string()
+    }
+}
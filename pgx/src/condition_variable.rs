@@ -0,0 +1,142 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Postgres' condition variables, for backends to sleep until another signals that something
+//! they're waiting for may have changed
+use crate::{pg_sys, PGXSharedAtomic, PGXSharedMemory, WaitEvent};
+use std::cell::UnsafeCell;
+
+/// A condition variable in shared memory
+///
+/// Backends sleep on it until some condition is true, and whoever changes what the condition
+/// depends on signals it to wake them up.  It doesn't protect the state itself, which must be
+/// atomic or protected by a lock of its own.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[derive(Default)]
+/// #[repr(C)]
+/// struct Progress {
+///     done: SharedAtomicU64,
+///     changed: PgConditionVariable,
+/// }
+/// unsafe impl PGXSharedAtomic for Progress {}
+///
+/// static PROGRESS: PgAtomic<Progress> = PgAtomic::new();
+///
+/// #[pg_extern]
+/// fn finish_one() {
+///     PROGRESS.get().done.increment();
+///     PROGRESS.get().changed.broadcast();
+/// }
+///
+/// #[pg_extern]
+/// fn wait_for(n: i64) {
+///     let progress = PROGRESS.get();
+///     progress.changed.sleep_until(WaitEvent::EXTENSION, || progress.done.load() >= n as u64);
+/// }
+/// ```
+#[repr(transparent)]
+pub struct PgConditionVariable {
+    cv: UnsafeCell<pg_sys::ConditionVariable>,
+}
+
+impl PgConditionVariable {
+    /// Sleep, reporting the wait as `event`, until `condition` returns true
+    ///
+    /// `condition` is checked first, and again whenever the condition variable is signaled.
+    /// Like any other wait, this can be interrupted by a query cancel, raising an error.
+    pub fn sleep_until<F: FnMut() -> bool>(&self, event: WaitEvent, mut condition: F) {
+        let _sleep = Sleep::prepare(self);
+        while !condition() {
+            unsafe { pg_sys::ConditionVariableSleep(self.as_ptr(), event.info()) }
+        }
+    }
+
+    /// Like [`PgConditionVariable::sleep_until`], but giving up once `timeout` has passed,
+    /// returning whether `condition` became true
+    #[cfg(any(feature = "pg13", feature = "pg14"))]
+    pub fn sleep_until_timeout<F: FnMut() -> bool>(
+        &self,
+        timeout: std::time::Duration,
+        event: WaitEvent,
+        mut condition: F,
+    ) -> bool {
+        let deadline = std::time::Instant::now() + timeout;
+        let _sleep = Sleep::prepare(self);
+        loop {
+            if condition() {
+                return true;
+            }
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            let timed_out = unsafe {
+                pg_sys::ConditionVariableTimedSleep(
+                    self.as_ptr(),
+                    remaining.as_millis() as _,
+                    event.info(),
+                )
+            };
+            if timed_out {
+                return condition();
+            }
+        }
+    }
+
+    /// Wake one of the backends sleeping on the condition variable, if any
+    pub fn signal(&self) {
+        unsafe {
+            pg_sys::ConditionVariableSignal(self.as_ptr());
+        }
+    }
+
+    /// Wake every backend sleeping on the condition variable
+    pub fn broadcast(&self) {
+        unsafe {
+            pg_sys::ConditionVariableBroadcast(self.as_ptr());
+        }
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::ConditionVariable {
+        self.cv.get()
+    }
+}
+
+impl Default for PgConditionVariable {
+    fn default() -> Self {
+        let mut cv = pg_sys::ConditionVariable::default();
+        // it doesn't point into itself, so it can be moved into place once initialized
+        unsafe { pg_sys::ConditionVariableInit(&mut cv) };
+        PgConditionVariable {
+            cv: UnsafeCell::new(cv),
+        }
+    }
+}
+
+unsafe impl Send for PgConditionVariable {}
+unsafe impl Sync for PgConditionVariable {}
+unsafe impl PGXSharedAtomic for PgConditionVariable {}
+unsafe impl PGXSharedMemory for PgConditionVariable {}
+
+/// Takes this backend off a condition variable's wait list once it's done sleeping, even if the
+/// sleep was interrupted by an error
+struct Sleep;
+
+impl Sleep {
+    fn prepare(cv: &PgConditionVariable) -> Self {
+        unsafe { pg_sys::ConditionVariablePrepareToSleep(cv.as_ptr()) };
+        Sleep
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        unsafe { pg_sys::ConditionVariableCancelSleep() };
+    }
+}
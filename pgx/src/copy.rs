@@ -0,0 +1,427 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! `COPY FROM` driven from Rust, reading its data from any [`Read`]er rather than a file or the
+//! client
+//!
+//! Postgres doesn't let extensions add their own `COPY` formats, so an extension importing, say,
+//! Parquet or protobuf converts it to one of Postgres' own formats, preferably `binary`, as it's
+//! read, and [`CopyFrom::execute`] inserts the rows just as `COPY FROM` does, with its batching,
+//! and with the table's triggers, constraints, and indexes:
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[pg_extern]
+//! fn import_dogs(csv: &str) -> i64 {
+//!     CopyFrom::new("dogs")
+//!         .columns(&["name", "age"])
+//!         .format(CopyFormat::Csv)
+//!         .option("header", "true")
+//!         .execute(csv.as_bytes()) as i64
+//! }
+//! ```
+//!
+//! [`CopyFrom::for_each_row`] instead hands each row to a callback, parsed into the table's
+//! columns, without inserting it.
+//!
+//! Exporting rows to Rust doesn't need `COPY TO`, as a [`PlannedQuery`](crate::PlannedQuery)
+//! hands each row of a query to a callback as the executor produces it.
+//!
+//! This needs Postgres 12 or later.
+use crate::{
+    direct_function_call, ereport, name_data_to_str, pg_guard, pg_sys, FromDatum, IntoDatum,
+    PgMemoryContexts, PgRelation,
+};
+use std::cell::Cell;
+use std::ffi::CString;
+use std::io::{ErrorKind, Read};
+
+/// The formats of `COPY`'s `FORMAT` option
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyFormat {
+    Text,
+    Csv,
+    Binary,
+}
+
+impl CopyFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CopyFormat::Text => "text",
+            CopyFormat::Csv => "csv",
+            CopyFormat::Binary => "binary",
+        }
+    }
+}
+
+/// A `COPY relation (columns) FROM ... WITH (options)`, whose data is read from a [`Read`]er
+#[derive(Debug, Clone)]
+pub struct CopyFrom {
+    relation: String,
+    columns: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+impl CopyFrom {
+    /// Copy into `relation`, a table name, which is qualified by its schema, or found on the
+    /// `search_path`
+    pub fn new(relation: &str) -> Self {
+        CopyFrom {
+            relation: relation.to_string(),
+            columns: Vec::new(),
+            options: Vec::new(),
+        }
+    }
+
+    /// Copy only these columns, in this order, leaving the others at their defaults, rather than
+    /// every column of the table
+    pub fn columns(mut self, columns: &[&str]) -> Self {
+        self.columns = columns.iter().map(|column| column.to_string()).collect();
+        self
+    }
+
+    pub fn format(self, format: CopyFormat) -> Self {
+        self.option("format", format.name())
+    }
+
+    /// Set one of `COPY`'s options which take a single value, such as `delimiter`, `null`,
+    /// `header`, or `encoding`
+    pub fn option(mut self, name: &str, value: &str) -> Self {
+        self.options.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Insert the rows read from `source` into the table, as `COPY FROM` does, returning how many
+    /// were inserted
+    ///
+    /// The current user must be allowed to insert into the table, or its columns, and the table
+    /// mustn't have row-level security enabled.  An ERROR reading `source` aborts the transaction.
+    pub fn execute<R: Read>(&self, mut source: R) -> u64 {
+        let relation = self.open(pg_sys::RowExclusiveLock);
+        unsafe {
+            if pg_sys::XactReadOnly && !relation.rd_islocaltemp {
+                pg_sys::PreventCommandIfReadOnly(b"COPY FROM\0".as_ptr() as *const _);
+            }
+
+            let pstate = pg_sys::make_parsestate(std::ptr::null_mut());
+            let rte = add_range_table_entry(pstate, relation.as_ptr());
+            (*rte).requiredPerms = pg_sys::ACL_INSERT as pg_sys::AclMode;
+            // the columns copied into, as `COPY` finds them, whose permissions are checked
+            for (i, attribute) in relation.tuple_desc().iter().enumerate() {
+                let copied = if self.columns.is_empty() {
+                    attribute.attgenerated == 0
+                } else {
+                    let name = name_data_to_str(&attribute.attname);
+                    self.columns.iter().any(|column| column == name)
+                };
+                if copied && !attribute.attisdropped {
+                    (*rte).insertedCols = pg_sys::bms_add_member(
+                        (*rte).insertedCols,
+                        i as i32 + 1 - pg_sys::FirstLowInvalidHeapAttributeNumber,
+                    );
+                }
+            }
+            pg_sys::ExecCheckRTPerms((*pstate).p_rtable, true);
+
+            if pg_sys::check_enable_rls(relation.oid(), pg_sys::InvalidOid, false)
+                == pg_sys::CheckEnableRlsResult_RLS_ENABLED
+            {
+                ereport!(
+                    ERROR,
+                    crate::PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+                    "COPY FROM not supported with row-level security",
+                    hint = "Use INSERT statements instead.",
+                );
+            }
+
+            let _source = SourceGuard::set(&mut source);
+            pg_sys::CommandCounterIncrement();
+            pg_sys::PushActiveSnapshot(pg_sys::GetTransactionSnapshot());
+
+            let cstate = begin_copy_from(
+                pstate,
+                relation.as_ptr(),
+                self.column_list(),
+                self.option_list(),
+            );
+            let processed = pg_sys::CopyFrom(cstate);
+            pg_sys::EndCopyFrom(cstate);
+
+            pg_sys::PopActiveSnapshot();
+            pg_sys::CommandCounterIncrement();
+            pg_sys::free_parsestate(pstate);
+
+            processed
+        }
+    }
+
+    /// Call `f` with each row read from `source`, parsed into the table's columns, without
+    /// inserting it, returning how many rows were read
+    ///
+    /// Columns which aren't copied are at their defaults.
+    pub fn for_each_row<R: Read, F: FnMut(&CopyRow)>(&self, mut source: R, mut f: F) -> u64 {
+        let relation = self.open(pg_sys::AccessShareLock);
+        let natts = relation.tuple_desc().len();
+        let mut values = vec![0 as pg_sys::Datum; natts];
+        let mut nulls = vec![true; natts];
+        let mut processed = 0;
+        unsafe {
+            let _source = SourceGuard::set(&mut source);
+            let cstate = begin_copy_from(
+                std::ptr::null_mut(),
+                relation.as_ptr(),
+                self.column_list(),
+                self.option_list(),
+            );
+
+            // each row is parsed in the per-tuple memory, which is reset for the next
+            let econtext = pg_sys::CreateStandaloneExprContext();
+            let mut row_context = PgMemoryContexts::For((*econtext).ecxt_per_tuple_memory);
+            loop {
+                crate::check_for_interrupts!();
+                row_context.reset();
+                let more = {
+                    let _switched = row_context.switch();
+                    pg_sys::NextCopyFrom(cstate, econtext, values.as_mut_ptr(), nulls.as_mut_ptr())
+                };
+                if !more {
+                    break;
+                }
+
+                f(&CopyRow {
+                    tupdesc: (*relation.as_ptr()).rd_att,
+                    values: &values,
+                    nulls: &nulls,
+                });
+                processed += 1;
+            }
+
+            pg_sys::FreeExprContext(econtext, true);
+            pg_sys::EndCopyFrom(cstate);
+        }
+        processed
+    }
+
+    fn open(&self, lockmode: u32) -> PgRelation {
+        let oid = unsafe {
+            direct_function_call::<pg_sys::Oid>(
+                pg_sys::to_regclass,
+                vec![self.relation.as_str().into_datum()],
+            )
+        };
+        match oid {
+            Some(oid) => PgRelation::with_lock(oid, lockmode as pg_sys::LOCKMODE),
+            None => {
+                ereport!(
+                    ERROR,
+                    crate::PgSqlErrorCode::ERRCODE_UNDEFINED_TABLE,
+                    format!("relation \"{}\" does not exist", self.relation),
+                );
+                unreachable!()
+            }
+        }
+    }
+
+    /// The columns, as a `List` of `String` nodes, in the `CurrentMemoryContext`
+    unsafe fn column_list(&self) -> *mut pg_sys::List {
+        let mut list = std::ptr::null_mut();
+        for column in &self.columns {
+            list = pg_sys::lappend(list, pg_sys::makeString(pstrdup(column)) as *mut _);
+        }
+        list
+    }
+
+    /// The options, as a `List` of `DefElem`s, in the `CurrentMemoryContext`
+    unsafe fn option_list(&self) -> *mut pg_sys::List {
+        let mut list = std::ptr::null_mut();
+        for (name, value) in &self.options {
+            let value = pg_sys::makeString(pstrdup(value)) as *mut pg_sys::Node;
+            list = pg_sys::lappend(
+                list,
+                pg_sys::makeDefElem(pstrdup(name), value, -1) as *mut _,
+            );
+        }
+        list
+    }
+}
+
+/// A row parsed by [`CopyFrom::for_each_row`]
+///
+/// Pass-by-reference values, such as a `&str`, are only valid until the callback returns.
+pub struct CopyRow<'a> {
+    tupdesc: pg_sys::TupleDesc,
+    values: &'a [pg_sys::Datum],
+    nulls: &'a [bool],
+}
+
+impl CopyRow<'_> {
+    /// The number of columns in the row, which are all the table's columns
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Get the value of the column at the specified 1-based `ordinal` position
+    ///
+    /// Panics if the row has no such column.
+    pub fn get<T: FromDatum>(&self, ordinal: usize) -> Option<T> {
+        if ordinal < 1 || ordinal > self.len() {
+            panic!(
+                "ordinal {} is out of bounds for a row of {} columns",
+                ordinal,
+                self.len()
+            );
+        }
+
+        unsafe {
+            let typoid = pg_sys::SPI_gettypeid(self.tupdesc, ordinal as i32);
+            T::from_datum(self.values[ordinal - 1], self.nulls[ordinal - 1], typoid)
+        }
+    }
+
+    /// The column values, with `None` for NULLs, as the table's columns are ordered
+    pub fn datums(&self) -> Vec<Option<pg_sys::Datum>> {
+        self.values
+            .iter()
+            .zip(self.nulls)
+            .map(|(value, null)| if *null { None } else { Some(*value) })
+            .collect()
+    }
+}
+
+thread_local! {
+    /// The source of the `COPY` being run, which `read_source()` reads from
+    static SOURCE: Cell<Option<*mut dyn Read>> = Cell::new(None);
+}
+
+/// Sets the `SOURCE`, for as long as it's alive, and restores the previous source, of an outer
+/// `COPY`, even when unwinding from an ERROR
+struct SourceGuard(Option<*mut dyn Read>);
+
+impl SourceGuard {
+    fn set<'a>(source: &'a mut (dyn Read + 'a)) -> Self {
+        // only `read_source()` uses the source, and only until this is dropped
+        let source: *mut (dyn Read + 'a) = source;
+        let source: *mut (dyn Read + 'static) = unsafe { std::mem::transmute(source) };
+        SourceGuard(SOURCE.with(|current| current.replace(Some(source))))
+    }
+}
+
+impl Drop for SourceGuard {
+    fn drop(&mut self) {
+        SOURCE.with(|current| current.set(self.0));
+    }
+}
+
+/// Postgres' `copy_data_source_cb`, which reads at least `minread`, and at most `maxread`, bytes
+/// into `outbuf`, unless the data ends first, returning how many were read
+#[pg_guard]
+unsafe extern "C" fn read_source(
+    outbuf: *mut std::os::raw::c_void,
+    minread: i32,
+    maxread: i32,
+) -> i32 {
+    let source = &mut *SOURCE
+        .with(|current| current.get())
+        .expect("COPY has no source to read from");
+    let buf = std::slice::from_raw_parts_mut(outbuf as *mut u8, maxread as usize);
+    let mut read = 0;
+    while read < minread as usize {
+        match source.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => ereport!(
+                ERROR,
+                crate::PgSqlErrorCode::ERRCODE_IO_ERROR,
+                format!("could not read COPY data: {}", e),
+            ),
+        }
+    }
+    read as i32
+}
+
+fn pstrdup(s: &str) -> *mut std::os::raw::c_char {
+    let s = CString::new(s).expect("COPY column or option contained a null byte");
+    unsafe { pg_sys::pstrdup(s.as_ptr()) }
+}
+
+/// Begin reading the rows of `relation` from the current `SOURCE`, in the text format unless
+/// `options` says otherwise
+#[cfg(any(feature = "pg12", feature = "pg13"))]
+unsafe fn begin_copy_from(
+    pstate: *mut pg_sys::ParseState,
+    relation: pg_sys::Relation,
+    columns: *mut pg_sys::List,
+    options: *mut pg_sys::List,
+) -> pg_sys::CopyState {
+    pg_sys::BeginCopyFrom(
+        pstate,
+        relation,
+        std::ptr::null(),
+        false,
+        Some(read_source),
+        columns,
+        options,
+    )
+}
+
+#[cfg(feature = "pg14")]
+unsafe fn begin_copy_from(
+    pstate: *mut pg_sys::ParseState,
+    relation: pg_sys::Relation,
+    columns: *mut pg_sys::List,
+    options: *mut pg_sys::List,
+) -> pg_sys::CopyFromState {
+    pg_sys::BeginCopyFrom(
+        pstate,
+        relation,
+        std::ptr::null_mut(),
+        std::ptr::null(),
+        false,
+        Some(read_source),
+        columns,
+        options,
+    )
+}
+
+#[cfg(feature = "pg12")]
+unsafe fn add_range_table_entry(
+    pstate: *mut pg_sys::ParseState,
+    relation: pg_sys::Relation,
+) -> *mut pg_sys::RangeTblEntry {
+    pg_sys::addRangeTableEntryForRelation(
+        pstate,
+        relation,
+        pg_sys::RowExclusiveLock as i32,
+        std::ptr::null_mut(),
+        false,
+        false,
+    )
+}
+
+#[cfg(any(feature = "pg13", feature = "pg14"))]
+unsafe fn add_range_table_entry(
+    pstate: *mut pg_sys::ParseState,
+    relation: pg_sys::Relation,
+) -> *mut pg_sys::RangeTblEntry {
+    let nsitem = pg_sys::addRangeTableEntryForRelation(
+        pstate,
+        relation,
+        pg_sys::RowExclusiveLock as i32,
+        std::ptr::null_mut(),
+        false,
+        false,
+    );
+    (*nsitem).p_rte
+}
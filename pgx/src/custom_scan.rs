@@ -0,0 +1,406 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Custom scan providers, which offer the planner their own way of scanning a table, and then
+//! produce its rows when the executor runs the scan
+//!
+//! A [`CustomScanProvider`] is asked about every table the planner considers scanning, and
+//! returns the estimated cost of scanning it, if it can.  Should its scan be the cheapest, the
+//! provider's [`CustomScanExecution`] is then asked for one row at a time.  Postgres itself
+//! applies the query's `WHERE` clauses and computes its target list from each row.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! struct Numbers;
+//! struct NumbersScan(i32);
+//!
+//! impl CustomScanProvider for Numbers {
+//!     fn name(&self) -> &'static str {
+//!         "Numbers"
+//!     }
+//!
+//!     fn create_path(
+//!         &self,
+//!         _root: &PgBox<pg_sys::PlannerInfo>,
+//!         _rel: &PgBox<pg_sys::RelOptInfo>,
+//!         rte: &PgBox<pg_sys::RangeTblEntry>,
+//!     ) -> Option<CustomPathCost> {
+//!         let relation = PgRelation::with_lock(rte.relid, pg_sys::AccessShareLock as _);
+//!         if relation.name() != "numbers" {
+//!             return None;
+//!         }
+//!         Some(CustomPathCost { rows: 10.0, startup_cost: 0.0, total_cost: 1.0 })
+//!     }
+//!
+//!     fn begin_scan(
+//!         &self,
+//!         _scan: &PgBox<pg_sys::CustomScan>,
+//!         _relation: &PgRelation,
+//!         _eflags: i32,
+//!     ) -> Box<dyn CustomScanExecution> {
+//!         Box::new(NumbersScan(0))
+//!     }
+//! }
+//!
+//! impl CustomScanExecution for NumbersScan {
+//!     fn next(&mut self, slot: &mut CustomScanSlot) -> bool {
+//!         self.0 += 1;
+//!         self.0 <= 10 && slot.store(&[self.0.into_datum()])
+//!     }
+//! }
+//!
+//! static NUMBERS: Numbers = Numbers;
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     register_custom_scan(&NUMBERS);
+//! }
+//! ```
+use crate::{pg_guard, pg_sys, Explain, PgBox, PgRelation};
+use std::ffi::CString;
+
+/// Offers the planner a custom way of scanning tables, and starts its execution
+pub trait CustomScanProvider {
+    /// The name of the scan, which is shown by `EXPLAIN`, and must be unique among the custom
+    /// scans loaded in the backend
+    fn name(&self) -> &'static str;
+
+    /// Called by the planner for each table it considers scanning, which is `rel`, described by
+    /// `rte`, to offer a custom scan of it, with its estimated cost, or not with `None`
+    ///
+    /// The custom scan is chosen if it's the cheapest way of scanning the table.
+    fn create_path(
+        &self,
+        root: &PgBox<pg_sys::PlannerInfo>,
+        rel: &PgBox<pg_sys::RelOptInfo>,
+        rte: &PgBox<pg_sys::RangeTblEntry>,
+    ) -> Option<CustomPathCost>;
+
+    /// Called when the planner has chosen the custom scan, to finish its plan, such as to add
+    /// what its execution will need to `custom_private`
+    fn plan_custom_path(&self, _scan: &mut PgBox<pg_sys::CustomScan>) {}
+
+    /// Start executing the `scan` of `relation`
+    ///
+    /// If `eflags` contains `pg_sys::EXEC_FLAG_EXPLAIN_ONLY`, the scan won't be run, only
+    /// explained, so shouldn't do anything expensive.
+    fn begin_scan(
+        &self,
+        scan: &PgBox<pg_sys::CustomScan>,
+        relation: &PgRelation,
+        eflags: i32,
+    ) -> Box<dyn CustomScanExecution>;
+}
+
+/// One execution of a custom scan, which is dropped when the executor finishes with it
+///
+/// If the query is aborted, by an error or by being cancelled, it's not dropped.
+pub trait CustomScanExecution {
+    /// Store the next row in `slot`, returning `false` once there are no more rows
+    fn next(&mut self, slot: &mut CustomScanSlot) -> bool;
+
+    /// Start the scan over again, from the first row
+    fn rescan(&mut self) {}
+
+    /// Add what's worth knowing about the scan to `EXPLAIN`'s output
+    fn explain(&self, _explain: &mut Explain) {}
+}
+
+/// The planner's estimate of what a custom scan will produce, and what it will cost
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CustomPathCost {
+    pub rows: f64,
+    pub startup_cost: f64,
+    pub total_cost: f64,
+}
+
+/// The slot a [`CustomScanExecution`] stores each row in
+pub struct CustomScanSlot {
+    slot: *mut pg_sys::TupleTableSlot,
+}
+
+impl CustomScanSlot {
+    /// The number of columns each row must have, which is the number the table has
+    pub fn len(&self) -> usize {
+        unsafe { (*(*self.slot).tts_tupleDescriptor).natts as usize }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Store a row with these values in the slot, returning `true`
+    ///
+    /// Pass-by-reference values must remain valid until the next row is requested.
+    ///
+    /// Panics if there isn't one value for each of the table's columns.
+    pub fn store(&mut self, values: &[Option<pg_sys::Datum>]) -> bool {
+        if values.len() != self.len() {
+            panic!(
+                "custom scan row has {} values, but the table has {} columns",
+                values.len(),
+                self.len()
+            );
+        }
+
+        unsafe {
+            clear_slot(self.slot);
+            for (i, value) in values.iter().enumerate() {
+                *(*self.slot).tts_values.add(i) = value.unwrap_or(0);
+                *(*self.slot).tts_isnull.add(i) = value.is_none();
+            }
+            pg_sys::ExecStoreVirtualTuple(self.slot);
+        }
+        true
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::TupleTableSlot {
+        self.slot
+    }
+}
+
+/// Register `provider` to offer its custom scan to the planner from now on
+///
+/// Must be called from `_PG_init()`.
+pub fn register_custom_scan(provider: &'static dyn CustomScanProvider) {
+    let name = CString::new(provider.name())
+        .expect("custom scan name contained a null byte")
+        .into_raw();
+
+    let path_methods = pg_sys::CustomPathMethods {
+        CustomName: name,
+        PlanCustomPath: Some(plan_custom_path),
+        ..Default::default()
+    };
+    let scan_methods = pg_sys::CustomScanMethods {
+        CustomName: name,
+        CreateCustomScanState: Some(create_custom_scan_state),
+    };
+    let exec_methods = pg_sys::CustomExecMethods {
+        CustomName: name,
+        BeginCustomScan: Some(begin_custom_scan),
+        ExecCustomScan: Some(exec_custom_scan),
+        EndCustomScan: Some(end_custom_scan),
+        ReScanCustomScan: Some(rescan_custom_scan),
+        ExplainCustomScan: Some(explain_custom_scan),
+        ..Default::default()
+    };
+
+    // these live as long as the backend does, as do the plans that point to them
+    let exec: &'static ExecMethods = Box::leak(Box::new(ExecMethods {
+        methods: exec_methods,
+        provider,
+    }));
+    let scan: &'static ScanMethods = Box::leak(Box::new(ScanMethods {
+        methods: scan_methods,
+        exec,
+    }));
+    let path: &'static PathMethods = Box::leak(Box::new(PathMethods {
+        methods: path_methods,
+        provider,
+        scan,
+    }));
+
+    unsafe {
+        pg_sys::RegisterCustomScanMethods(&scan.methods);
+
+        let providers = PROVIDERS.get_or_insert_with(|| {
+            PREV_SET_REL_PATHLIST_HOOK = pg_sys::set_rel_pathlist_hook;
+            pg_sys::set_rel_pathlist_hook = Some(set_rel_pathlist);
+            Vec::new()
+        });
+        providers.push(path);
+    }
+}
+
+static mut PROVIDERS: Option<Vec<&'static PathMethods>> = None;
+static mut PREV_SET_REL_PATHLIST_HOOK: pg_sys::set_rel_pathlist_hook_type = None;
+
+// Postgres only knows about the `methods` at the start of each of these, and hands them back to
+// us, so we can find what goes with them
+
+#[repr(C)]
+struct PathMethods {
+    methods: pg_sys::CustomPathMethods,
+    provider: &'static dyn CustomScanProvider,
+    scan: &'static ScanMethods,
+}
+
+#[repr(C)]
+struct ScanMethods {
+    methods: pg_sys::CustomScanMethods,
+    exec: &'static ExecMethods,
+}
+
+#[repr(C)]
+struct ExecMethods {
+    methods: pg_sys::CustomExecMethods,
+    provider: &'static dyn CustomScanProvider,
+}
+
+#[repr(C)]
+struct ProviderScanState {
+    // must be first, as Postgres only knows about this part
+    css: pg_sys::CustomScanState,
+    execution: Option<Box<dyn CustomScanExecution>>,
+}
+
+#[pg_guard]
+unsafe extern "C" fn set_rel_pathlist(
+    root: *mut pg_sys::PlannerInfo,
+    rel: *mut pg_sys::RelOptInfo,
+    rti: pg_sys::Index,
+    rte: *mut pg_sys::RangeTblEntry,
+) {
+    if let Some(prev) = PREV_SET_REL_PATHLIST_HOOK {
+        prev(root, rel, rti, rte);
+    }
+
+    // only plain tables can be scanned
+    if (*rel).reloptkind != pg_sys::RelOptKind_RELOPT_BASEREL
+        || (*rte).rtekind != pg_sys::RTEKind_RTE_RELATION
+    {
+        return;
+    }
+
+    let root_box = PgBox::from_pg(root);
+    let rel_box = PgBox::from_pg(rel);
+    let rte_box = PgBox::from_pg(rte);
+    for methods in PROVIDERS.as_ref().unwrap() {
+        if let Some(cost) = methods.provider.create_path(&root_box, &rel_box, &rte_box) {
+            let mut path = PgBox::<pg_sys::CustomPath>::alloc_node(pg_sys::NodeTag_T_CustomPath);
+            path.path.pathtype = pg_sys::NodeTag_T_CustomScan;
+            path.path.parent = rel;
+            path.path.pathtarget = (*rel).reltarget;
+            path.path.param_info =
+                pg_sys::get_baserel_parampathinfo(root, rel, (*rel).lateral_relids);
+            path.path.rows = cost.rows;
+            path.path.startup_cost = cost.startup_cost;
+            path.path.total_cost = cost.total_cost;
+            path.methods = &methods.methods;
+            pg_sys::add_path(rel, path.into_pg() as *mut pg_sys::Path);
+        }
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn plan_custom_path(
+    _root: *mut pg_sys::PlannerInfo,
+    rel: *mut pg_sys::RelOptInfo,
+    best_path: *mut pg_sys::CustomPath,
+    tlist: *mut pg_sys::List,
+    clauses: *mut pg_sys::List,
+    _custom_plans: *mut pg_sys::List,
+) -> *mut pg_sys::Plan {
+    let methods = &*((*best_path).methods as *const PathMethods);
+
+    let mut scan = PgBox::<pg_sys::CustomScan>::alloc_node(pg_sys::NodeTag_T_CustomScan)
+        .into_pg_boxed();
+    scan.scan.plan.targetlist = tlist;
+    scan.scan.plan.qual = pg_sys::extract_actual_clauses(clauses, false);
+    scan.scan.scanrelid = (*rel).relid;
+    scan.flags = (*best_path).flags;
+    scan.custom_private = (*best_path).custom_private;
+    scan.methods = &methods.scan.methods;
+    methods.provider.plan_custom_path(&mut scan);
+
+    scan.into_pg() as *mut pg_sys::Plan
+}
+
+#[pg_guard]
+unsafe extern "C" fn create_custom_scan_state(cscan: *mut pg_sys::CustomScan) -> *mut pg_sys::Node {
+    let methods = &*((*cscan).methods as *const ScanMethods);
+
+    let mut state = PgBox::<ProviderScanState>::alloc_node(pg_sys::NodeTag_T_CustomScanState);
+    state.css.methods = &methods.exec.methods;
+    std::ptr::write(&mut state.execution, None);
+    state.into_pg() as *mut pg_sys::Node
+}
+
+#[pg_guard]
+unsafe extern "C" fn begin_custom_scan(
+    node: *mut pg_sys::CustomScanState,
+    _estate: *mut pg_sys::EState,
+    eflags: i32,
+) {
+    let state = &mut *(node as *mut ProviderScanState);
+    let exec_methods = &*((*node).methods as *const ExecMethods);
+    let scan = PgBox::from_pg((*node).ss.ps.plan as *mut pg_sys::CustomScan);
+    let relation = PgRelation::from_pg((*node).ss.ss_currentRelation);
+    state.execution = Some(exec_methods.provider.begin_scan(&scan, &relation, eflags));
+}
+
+#[pg_guard]
+unsafe extern "C" fn exec_custom_scan(node: *mut pg_sys::CustomScanState) -> *mut pg_sys::TupleTableSlot {
+    pg_sys::ExecScan(&mut (*node).ss, Some(next_row), Some(recheck_row))
+}
+
+#[pg_guard]
+unsafe extern "C" fn next_row(node: *mut pg_sys::ScanState) -> *mut pg_sys::TupleTableSlot {
+    let state = &mut *(node as *mut ProviderScanState);
+    let mut slot = CustomScanSlot {
+        slot: (*node).ss_ScanTupleSlot,
+    };
+    let execution = state
+        .execution
+        .as_mut()
+        .expect("custom scan has not been started");
+    if !execution.next(&mut slot) {
+        // an empty slot tells the executor the scan is done
+        clear_slot(slot.slot);
+    }
+    slot.slot
+}
+
+#[pg_guard]
+unsafe extern "C" fn recheck_row(
+    _node: *mut pg_sys::ScanState,
+    _slot: *mut pg_sys::TupleTableSlot,
+) -> bool {
+    true
+}
+
+#[pg_guard]
+unsafe extern "C" fn end_custom_scan(node: *mut pg_sys::CustomScanState) {
+    let state = &mut *(node as *mut ProviderScanState);
+    state.execution = None;
+}
+
+#[pg_guard]
+unsafe extern "C" fn rescan_custom_scan(node: *mut pg_sys::CustomScanState) {
+    let state = &mut *(node as *mut ProviderScanState);
+    if let Some(execution) = state.execution.as_mut() {
+        execution.rescan();
+    }
+    pg_sys::ExecScanReScan(&mut (*node).ss);
+}
+
+#[pg_guard]
+unsafe extern "C" fn explain_custom_scan(
+    node: *mut pg_sys::CustomScanState,
+    _ancestors: *mut pg_sys::List,
+    es: *mut pg_sys::ExplainState,
+) {
+    let state = &*(node as *mut ProviderScanState);
+    if let Some(execution) = state.execution.as_ref() {
+        execution.explain(&mut Explain::from_pg(es));
+    }
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11"))]
+unsafe fn clear_slot(slot: *mut pg_sys::TupleTableSlot) {
+    pg_sys::ExecClearTuple(slot);
+}
+
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+unsafe fn clear_slot(slot: *mut pg_sys::TupleTableSlot) {
+    // `ExecClearTuple()` is an inline function since Postgres 12
+    ((*(*slot).tts_ops).clear.unwrap())(slot);
+}
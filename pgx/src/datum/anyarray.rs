@@ -7,7 +7,7 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 
-use crate::{pg_sys, FromDatum, IntoDatum};
+use crate::{pg_sys, AnyElement, Array, FromDatum, IntoDatum};
 
 #[derive(Debug, Clone, Copy)]
 pub struct AnyArray {
@@ -28,6 +28,62 @@ impl AnyArray {
     pub fn into<T: FromDatum>(&self) -> Option<T> {
         unsafe { T::from_datum(self.datum(), false, self.oid()) }
     }
+
+    /// The type oid of the array's elements.
+    pub fn element_oid(&self) -> pg_sys::Oid {
+        self.array_type().elemtype
+    }
+
+    /// The number of dimensions of the array.  Empty arrays have zero dimensions.
+    pub fn ndims(&self) -> usize {
+        self.array_type().ndim as usize
+    }
+
+    /// The length of each of the array's dimensions.
+    pub fn dims(&self) -> &[i32] {
+        unsafe { std::slice::from_raw_parts(self.dims_ptr(), self.ndims()) }
+    }
+
+    /// The lower bound of each of the array's dimensions.
+    pub fn lower_bounds(&self) -> &[i32] {
+        unsafe { std::slice::from_raw_parts(self.dims_ptr().add(self.ndims()), self.ndims()) }
+    }
+
+    /// The total number of elements in the array, across all of its dimensions.
+    pub fn len(&self) -> usize {
+        unsafe { pg_sys::ArrayGetNItems(self.ndims() as i32, self.dims_ptr()) as usize }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over the array's elements, in storage order, as type-erased [`AnyElement`]s.
+    /// SQL NULL elements are `None`.
+    pub fn iter(&self) -> impl Iterator<Item = Option<AnyElement>> {
+        let element_oid = self.element_oid();
+        let array =
+            unsafe { Array::<'static, pg_sys::Datum>::from_datum(self.datum, false, self.typoid) }
+                .expect("anyarray datum was NULL");
+        array.into_iter().map(move |datum| {
+            datum.and_then(|datum| unsafe { AnyElement::from_datum(datum, false, element_oid) })
+        })
+    }
+
+    fn array_type(&self) -> &pg_sys::ArrayType {
+        unsafe {
+            (self.datum as *const pg_sys::ArrayType)
+                .as_ref()
+                .expect("ArrayType * was NULL")
+        }
+    }
+
+    /// Equivalent to Postgres' `ARR_DIMS()` macro.  The lower bounds immediately follow.
+    fn dims_ptr(&self) -> *mut i32 {
+        let array = self.array_type() as *const pg_sys::ArrayType as *mut u8;
+        unsafe { array.add(std::mem::size_of::<pg_sys::ArrayType>()) as *mut i32 }
+    }
 }
 
 impl FromDatum for AnyArray {
@@ -40,6 +96,8 @@ impl FromDatum for AnyArray {
         if is_null {
             None
         } else {
+            // detoast once, up front, so that introspecting the array doesn't repeatedly do so
+            let datum = pg_sys::pg_detoast_datum(datum as *mut pg_sys::varlena) as pg_sys::Datum;
             Some(AnyArray { datum, typoid })
         }
     }
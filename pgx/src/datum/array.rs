@@ -7,7 +7,7 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 
-use crate::{pg_sys, void_mut_ptr, FromDatum, IntoDatum, PgMemoryContexts};
+use crate::{pg_sys, set_varsize, void_mut_ptr, FromDatum, IntoDatum, PgMemoryContexts};
 use serde::Serializer;
 use std::marker::PhantomData;
 
@@ -339,6 +339,8 @@ impl<T: FromDatum> FromDatum for Vec<T> {
             None
         } else if datum == 0 {
             panic!("array was flagged not null but datum is zero");
+        } else if let Some(v) = T::vec_from_array_datum(datum) {
+            Some(v)
         } else {
             let array = Array::<T>::from_datum(datum, is_null, typoid).unwrap();
             let mut v = Vec::with_capacity(array.len());
@@ -362,6 +364,8 @@ impl<T: FromDatum> FromDatum for Vec<Option<T>> {
             None
         } else if datum == 0 {
             panic!("array was flagged not null but datum is zero");
+        } else if let Some(v) = T::nullable_vec_from_array_datum(datum) {
+            Some(v)
         } else {
             let array = Array::<T>::from_datum(datum, is_null, typoid).unwrap();
             let mut v = Vec::with_capacity(array.len());
@@ -379,6 +383,11 @@ where
     T: IntoDatum,
 {
     fn into_datum(self) -> Option<pg_sys::Datum> {
+        let values = match T::vec_into_array_datum(self) {
+            Ok(datum) => return Some(datum),
+            Err(values) => values,
+        };
+
         let mut state = unsafe {
             pg_sys::initArrayResult(
                 T::type_oid(),
@@ -386,7 +395,7 @@ where
                 false,
             )
         };
-        for s in self {
+        for s in values {
             let datum = s.into_datum();
             let isnull = datum.is_none();
 
@@ -457,3 +466,180 @@ where
         unsafe { pg_sys::get_array_type(T::type_oid()) }
     }
 }
+
+/// Equivalent to Postgres' `ARR_OVERHEAD_NONULLS()` macro, or `ARR_OVERHEAD_WITHNULLS()` when given
+/// the number of items covered by the null bitmap
+fn array_overhead(ndims: usize, bitmap_items: Option<usize>) -> usize {
+    let len = std::mem::size_of::<pg_sys::ArrayType>()
+        + 2 * std::mem::size_of::<i32>() * ndims
+        + bitmap_items.map(|nitems| (nitems + 7) / 8).unwrap_or(0);
+    let align = pg_sys::MAXIMUM_ALIGNOF as usize;
+    (len + align - 1) & !(align - 1)
+}
+
+/// The data area, number of items, and null bitmap (if any) of an array whose element type is
+/// `elemtype`, or `None` if it has some other element type
+unsafe fn primitive_array_parts<T>(
+    datum: pg_sys::Datum,
+    elemtype: pg_sys::Oid,
+) -> Option<(*const T, usize, Option<&'static [u8]>)> {
+    let array = pg_sys::pg_detoast_datum(datum as *mut pg_sys::varlena) as *mut pg_sys::ArrayType;
+    if (*array).elemtype != elemtype {
+        return None;
+    }
+
+    let ndims = (*array).ndim as usize;
+    let dims = (array as *mut u8).add(std::mem::size_of::<pg_sys::ArrayType>()) as *mut i32;
+    let nitems = pg_sys::ArrayGetNItems(ndims as i32, dims) as usize;
+    if (*array).dataoffset == 0 {
+        let data = (array as *const u8).add(array_overhead(ndims, None));
+        Some((data as *const T, nitems, None))
+    } else {
+        let data = (array as *const u8).add((*array).dataoffset as usize);
+        let bitmap = std::slice::from_raw_parts(dims.add(2 * ndims) as *const u8, (nitems + 7) / 8);
+        Some((data as *const T, nitems, Some(bitmap)))
+    }
+}
+
+#[inline]
+fn bitmap_is_set(bitmap: &[u8], i: usize) -> bool {
+    bitmap[i / 8] & (1 << (i % 8)) != 0
+}
+
+/// Copy the elements of an array of a fixed-width, pass-by-value type directly out of its data
+/// area, rather than converting each element through a `pg_sys::Datum`.
+///
+/// Returns `None` if the array's element type isn't `elemtype`, in which case the generic
+/// conversion should be used instead.
+///
+/// ## Safety
+///
+/// `datum` must be a non-null array, and `T` must have the same size, alignment, and
+/// representation as `elemtype`.
+pub(crate) unsafe fn primitive_array_to_vec<T>(
+    datum: pg_sys::Datum,
+    elemtype: pg_sys::Oid,
+) -> Option<Vec<T>> {
+    let (data, nitems, bitmap) = primitive_array_parts::<T>(datum, elemtype)?;
+    if let Some(bitmap) = bitmap {
+        if (0..nitems).any(|i| !bitmap_is_set(bitmap, i)) {
+            panic!("array element was NULL");
+        }
+    }
+
+    let mut v = Vec::with_capacity(nitems);
+    std::ptr::copy_nonoverlapping(data, v.as_mut_ptr(), nitems);
+    v.set_len(nitems);
+    Some(v)
+}
+
+/// Like [`primitive_array_to_vec`], but translates the array's null bitmap into `None` elements.
+///
+/// ## Safety
+///
+/// Same caveats as [`primitive_array_to_vec`].
+pub(crate) unsafe fn primitive_array_to_nullable_vec<T>(
+    datum: pg_sys::Datum,
+    elemtype: pg_sys::Oid,
+) -> Option<Vec<Option<T>>> {
+    let (data, nitems, bitmap) = primitive_array_parts::<T>(datum, elemtype)?;
+    let mut v = Vec::with_capacity(nitems);
+    match bitmap {
+        None => {
+            for i in 0..nitems {
+                v.push(Some(data.add(i).read()));
+            }
+        }
+        Some(bitmap) => {
+            // NULL elements take no space in the data area
+            let mut next = 0;
+            for i in 0..nitems {
+                if bitmap_is_set(bitmap, i) {
+                    v.push(Some(data.add(next).read()));
+                    next += 1;
+                } else {
+                    v.push(None);
+                }
+            }
+        }
+    }
+    Some(v)
+}
+
+/// Allocate a one-dimensional array of `nitems` elements of `elemtype`, `nnotnull` of which are
+/// not NULL, returning it along with its data area and, if `nnotnull < nitems`, its (zeroed) null
+/// bitmap
+unsafe fn alloc_primitive_array<T>(
+    elemtype: pg_sys::Oid,
+    nitems: usize,
+    nnotnull: usize,
+) -> (*mut pg_sys::ArrayType, *mut T, Option<*mut u8>) {
+    let has_nulls = nnotnull < nitems;
+    let overhead = array_overhead(1, if has_nulls { Some(nitems) } else { None });
+    let nbytes = overhead + nnotnull * std::mem::size_of::<T>();
+
+    let array = pg_sys::palloc0(nbytes) as *mut pg_sys::ArrayType;
+    set_varsize(array as *mut pg_sys::varlena, nbytes as i32);
+    (*array).ndim = 1;
+    (*array).dataoffset = if has_nulls { overhead as i32 } else { 0 };
+    (*array).elemtype = elemtype;
+
+    let dims = (array as *mut u8).add(std::mem::size_of::<pg_sys::ArrayType>()) as *mut i32;
+    *dims = nitems as i32;
+    *dims.add(1) = 1; // lower bound
+
+    let data = (array as *mut u8).add(overhead) as *mut T;
+    let bitmap = if has_nulls {
+        Some(dims.add(2) as *mut u8)
+    } else {
+        None
+    };
+    (array, data, bitmap)
+}
+
+/// Build an array of a fixed-width, pass-by-value type by copying `values` directly into its data
+/// area, rather than converting each element through a `pg_sys::Datum`.
+///
+/// ## Safety
+///
+/// `T` must have the same size, alignment, and representation as `elemtype`.
+pub(crate) unsafe fn primitive_vec_into_array<T>(
+    values: Vec<T>,
+    elemtype: pg_sys::Oid,
+) -> pg_sys::Datum {
+    if values.is_empty() {
+        return pg_sys::construct_empty_array(elemtype) as pg_sys::Datum;
+    }
+
+    let (array, data, _) = alloc_primitive_array::<T>(elemtype, values.len(), values.len());
+    std::ptr::copy_nonoverlapping(values.as_ptr(), data, values.len());
+    array as pg_sys::Datum
+}
+
+/// Like [`primitive_vec_into_array`], but `None` elements become NULLs in the array's null bitmap.
+///
+/// ## Safety
+///
+/// Same caveats as [`primitive_vec_into_array`].
+pub(crate) unsafe fn primitive_nullable_vec_into_array<T>(
+    values: Vec<Option<T>>,
+    elemtype: pg_sys::Oid,
+) -> pg_sys::Datum {
+    if values.is_empty() {
+        return pg_sys::construct_empty_array(elemtype) as pg_sys::Datum;
+    }
+
+    let nnotnull = values.iter().filter(|value| value.is_some()).count();
+    let (array, data, bitmap) = alloc_primitive_array::<T>(elemtype, values.len(), nnotnull);
+    let mut next = 0;
+    for (i, value) in values.into_iter().enumerate() {
+        if let Some(value) = value {
+            data.add(next).write(value);
+            next += 1;
+            if let Some(bitmap) = bitmap {
+                *bitmap.add(i / 8) |= 1 << (i % 8);
+            }
+        }
+    }
+    array as pg_sys::Datum
+}
@@ -9,6 +9,7 @@ Use of this source code is governed by the MIT license that can be found in the
 
 //! for converting a pg_sys::Datum and a corresponding "is_null" bool into a typed Option
 
+use crate::datum::array::{primitive_array_to_nullable_vec, primitive_array_to_vec};
 use crate::{
     pg_sys, text_to_rust_str_unchecked, varlena_to_byte_slice, AllocatedByPostgres, PgBox,
     PgMemoryContexts,
@@ -62,6 +63,49 @@ pub trait FromDatum {
     {
         memory_context.switch_to(|_| FromDatum::from_datum(datum, is_null, typoid))
     }
+
+    /// Convert a non-null array `datum` of `Self` elements into a `Vec` without converting each
+    /// element through a `pg_sys::Datum`, if `Self` supports doing so.
+    ///
+    /// Returns `None` when `Self` has no such fast path.
+    ///
+    /// ## Safety
+    ///
+    /// `datum` must be a non-null array.
+    #[doc(hidden)]
+    unsafe fn vec_from_array_datum(_datum: pg_sys::Datum) -> Option<Vec<Self>>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// Like [`FromDatum::vec_from_array_datum`], but NULL elements become `None`.
+    ///
+    /// ## Safety
+    ///
+    /// `datum` must be a non-null array.
+    #[doc(hidden)]
+    unsafe fn nullable_vec_from_array_datum(_datum: pg_sys::Datum) -> Option<Vec<Option<Self>>>
+    where
+        Self: Sized,
+    {
+        None
+    }
+}
+
+/// Overrides the array fast paths of `FromDatum` for a fixed-width, pass-by-value type whose
+/// in-array representation is the same as its Rust representation
+macro_rules! primitive_array_from_datum {
+    ($oid:expr) => {
+        unsafe fn vec_from_array_datum(datum: pg_sys::Datum) -> Option<Vec<Self>> {
+            primitive_array_to_vec(datum, $oid)
+        }
+
+        unsafe fn nullable_vec_from_array_datum(datum: pg_sys::Datum) -> Option<Vec<Option<Self>>> {
+            primitive_array_to_nullable_vec(datum, $oid)
+        }
+    };
 }
 
 /// for pg_sys::Datum
@@ -92,6 +136,8 @@ impl FromDatum for bool {
             Some(datum != 0)
         }
     }
+
+    primitive_array_from_datum!(pg_sys::BOOLOID);
 }
 
 /// for `"char"`
@@ -105,6 +151,8 @@ impl FromDatum for i8 {
             Some(datum as i8)
         }
     }
+
+    primitive_array_from_datum!(pg_sys::CHAROID);
 }
 
 /// for smallint
@@ -118,6 +166,8 @@ impl FromDatum for i16 {
             Some(datum as i16)
         }
     }
+
+    primitive_array_from_datum!(pg_sys::INT2OID);
 }
 
 /// for integer
@@ -131,6 +181,8 @@ impl FromDatum for i32 {
             Some(datum as i32)
         }
     }
+
+    primitive_array_from_datum!(pg_sys::INT4OID);
 }
 
 /// for oid
@@ -144,6 +196,8 @@ impl FromDatum for u32 {
             Some(datum as u32)
         }
     }
+
+    primitive_array_from_datum!(pg_sys::OIDOID);
 }
 
 /// for bigint
@@ -157,6 +211,8 @@ impl FromDatum for i64 {
             Some(datum as i64)
         }
     }
+
+    primitive_array_from_datum!(pg_sys::INT8OID);
 }
 
 /// for real
@@ -170,6 +226,8 @@ impl FromDatum for f32 {
             Some(f32::from_bits(datum as u32))
         }
     }
+
+    primitive_array_from_datum!(pg_sys::FLOAT4OID);
 }
 
 /// for double precision
@@ -183,6 +241,8 @@ impl FromDatum for f64 {
             Some(f64::from_bits(datum as u64))
         }
     }
+
+    primitive_array_from_datum!(pg_sys::FLOAT8OID);
 }
 
 /// for text, varchar
@@ -15,6 +15,7 @@ use serde::de::{Error, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::ffi::CStr;
 use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::ops::Deref;
 
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq)]
@@ -119,3 +120,78 @@ impl Into<Inet> for String {
         Inet(self)
     }
 }
+
+impl From<IpAddr> for Inet {
+    fn from(addr: IpAddr) -> Self {
+        Inet(addr.to_string())
+    }
+}
+
+impl Inet {
+    /// Parse this `inet` as a host address, returning `None` if it is a network address (ie, it
+    /// has a netmask narrower than the full address)
+    pub fn to_ip_addr(&self) -> Option<IpAddr> {
+        self.0.parse().ok()
+    }
+}
+
+/// for inet, as a host address
+impl FromDatum for IpAddr {
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, typoid: u32) -> Option<IpAddr> {
+        let inet = Inet::from_datum(datum, is_null, typoid)?;
+        match inet.to_ip_addr() {
+            Some(addr) => Some(addr),
+            None => panic!("inet value `{}` is not a host address", inet.0),
+        }
+    }
+}
+
+impl IntoDatum for IpAddr {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Inet::from(self).into_datum()
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::INETOID
+    }
+}
+
+/// for inet, as an IPv4 host address
+impl FromDatum for Ipv4Addr {
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, typoid: u32) -> Option<Ipv4Addr> {
+        match IpAddr::from_datum(datum, is_null, typoid)? {
+            IpAddr::V4(addr) => Some(addr),
+            IpAddr::V6(addr) => panic!("inet value `{}` is not an IPv4 address", addr),
+        }
+    }
+}
+
+impl IntoDatum for Ipv4Addr {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        IpAddr::V4(self).into_datum()
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::INETOID
+    }
+}
+
+/// for inet, as an IPv6 host address
+impl FromDatum for Ipv6Addr {
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, typoid: u32) -> Option<Ipv6Addr> {
+        match IpAddr::from_datum(datum, is_null, typoid)? {
+            IpAddr::V6(addr) => Some(addr),
+            IpAddr::V4(addr) => panic!("inet value `{}` is not an IPv6 address", addr),
+        }
+    }
+}
+
+impl IntoDatum for Ipv6Addr {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        IpAddr::V6(self).into_datum()
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::INETOID
+    }
+}
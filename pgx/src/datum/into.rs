@@ -12,6 +12,7 @@ Use of this source code is governed by the MIT license that can be found in the
 //! Primitive types can never be null, so we do a direct
 //! cast of the primitive type to pg_sys::Datum
 
+use crate::datum::array::{primitive_nullable_vec_into_array, primitive_vec_into_array};
 use crate::{
     pg_sys, rust_byte_slice_to_bytea, rust_regtypein, rust_str_to_text_p, PgBox, PgOid,
     WhoAllocated,
@@ -32,6 +33,45 @@ pub trait IntoDatum {
     fn array_type_oid() -> pg_sys::Oid {
         unsafe { pg_sys::get_array_type(Self::type_oid()) }
     }
+
+    /// Convert `values` into an array `pg_sys::Datum` without converting each element through a
+    /// `pg_sys::Datum`, if `Self` supports doing so.
+    ///
+    /// Returns `values` back when `Self` has no such fast path.
+    #[doc(hidden)]
+    fn vec_into_array_datum(values: Vec<Self>) -> Result<pg_sys::Datum, Vec<Self>>
+    where
+        Self: Sized,
+    {
+        Err(values)
+    }
+
+    /// Like [`IntoDatum::vec_into_array_datum`], but `None` elements become NULL.
+    #[doc(hidden)]
+    fn nullable_vec_into_array_datum(
+        values: Vec<Option<Self>>,
+    ) -> Result<pg_sys::Datum, Vec<Option<Self>>>
+    where
+        Self: Sized,
+    {
+        Err(values)
+    }
+}
+
+/// Overrides the array fast paths of `IntoDatum` for a fixed-width, pass-by-value type whose
+/// in-array representation is the same as its Rust representation
+macro_rules! primitive_array_into_datum {
+    ($oid:expr) => {
+        fn vec_into_array_datum(values: Vec<Self>) -> Result<pg_sys::Datum, Vec<Self>> {
+            Ok(unsafe { primitive_vec_into_array(values, $oid) })
+        }
+
+        fn nullable_vec_into_array_datum(
+            values: Vec<Option<Self>>,
+        ) -> Result<pg_sys::Datum, Vec<Option<Self>>> {
+            Ok(unsafe { primitive_nullable_vec_into_array(values, $oid) })
+        }
+    };
 }
 
 /// for supporting NULL as the None value of an Option<T>
@@ -49,6 +89,10 @@ where
     fn type_oid() -> u32 {
         T::type_oid()
     }
+
+    fn vec_into_array_datum(values: Vec<Self>) -> Result<pg_sys::Datum, Vec<Self>> {
+        T::nullable_vec_into_array_datum(values)
+    }
 }
 
 /// for bool
@@ -61,6 +105,8 @@ impl IntoDatum for bool {
     fn type_oid() -> u32 {
         pg_sys::BOOLOID
     }
+
+    primitive_array_into_datum!(pg_sys::BOOLOID);
 }
 
 /// for "char"
@@ -72,6 +118,8 @@ impl IntoDatum for i8 {
     fn type_oid() -> u32 {
         pg_sys::CHAROID
     }
+
+    primitive_array_into_datum!(pg_sys::CHAROID);
 }
 
 /// for smallint
@@ -84,6 +132,8 @@ impl IntoDatum for i16 {
     fn type_oid() -> u32 {
         pg_sys::INT2OID
     }
+
+    primitive_array_into_datum!(pg_sys::INT2OID);
 }
 
 /// for integer
@@ -96,6 +146,8 @@ impl IntoDatum for i32 {
     fn type_oid() -> u32 {
         pg_sys::INT4OID
     }
+
+    primitive_array_into_datum!(pg_sys::INT4OID);
 }
 
 /// for oid
@@ -108,6 +160,8 @@ impl IntoDatum for u32 {
     fn type_oid() -> u32 {
         pg_sys::OIDOID
     }
+
+    primitive_array_into_datum!(pg_sys::OIDOID);
 }
 
 /// for bigint
@@ -120,6 +174,8 @@ impl IntoDatum for i64 {
     fn type_oid() -> u32 {
         pg_sys::INT8OID
     }
+
+    primitive_array_into_datum!(pg_sys::INT8OID);
 }
 
 /// for real
@@ -132,6 +188,8 @@ impl IntoDatum for f32 {
     fn type_oid() -> u32 {
         pg_sys::FLOAT4OID
     }
+
+    primitive_array_into_datum!(pg_sys::FLOAT4OID);
 }
 
 /// for double precision
@@ -144,6 +202,8 @@ impl IntoDatum for f64 {
     fn type_oid() -> u32 {
         pg_sys::FLOAT8OID
     }
+
+    primitive_array_into_datum!(pg_sys::FLOAT8OID);
 }
 
 impl IntoDatum for PgOid {
@@ -21,6 +21,7 @@ mod into;
 mod item_pointer_data;
 mod json;
 mod numeric;
+mod refcursor;
 mod time;
 mod time_stamp;
 mod time_stamp_with_timezone;
@@ -44,6 +45,7 @@ pub use item_pointer_data::*;
 pub use json::*;
 pub use numeric::*;
 use once_cell::sync::Lazy;
+pub use refcursor::*;
 use std::any::TypeId;
 pub use time_stamp::*;
 pub use time_stamp_with_timezone::*;
@@ -0,0 +1,49 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::{pg_sys, FromDatum, IntoDatum};
+
+/// A `refcursor`: the name of an open cursor (a portal)
+///
+/// Return one from a function, such as with [`crate::SpiCursor::into_refcursor`], so the caller
+/// can `FETCH` the cursor's rows, or accept one as an argument and open it with
+/// [`crate::SpiClient::find_cursor`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RefCursor(pub String);
+
+impl RefCursor {
+    /// The name of the cursor
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromDatum for RefCursor {
+    const NEEDS_TYPID: bool = false;
+
+    #[inline]
+    unsafe fn from_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        typoid: pg_sys::Oid,
+    ) -> Option<RefCursor> {
+        // a refcursor is stored just like `text`
+        String::from_datum(datum, is_null, typoid).map(RefCursor)
+    }
+}
+
+impl IntoDatum for RefCursor {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        self.0.into_datum()
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::REFCURSOROID
+    }
+}
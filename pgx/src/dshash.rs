@@ -0,0 +1,294 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! A hash table in a [`DsaArea`], which can grow as it's used and be shared by many backends
+//!
+//! Postgres' `dshash` tables divide their entries into partitions, each with a lock of its own,
+//! so backends reading and writing different entries rarely wait for one another.  A
+//! [`SharedHashMap`] locks an entry's partition for as long as a reference to the entry is held.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[derive(Copy, Clone, Default)]
+//! struct RelationStats {
+//!     scans: u64,
+//!     rows: u64,
+//! }
+//! unsafe impl PGXSharedMemory for RelationStats {}
+//!
+//! let area = DsaArea::create("my_extension_stats");
+//! let stats = SharedHashMap::<pg_sys::Oid, RelationStats>::create(&area, "my_extension_stats");
+//!
+//! let mut entry = stats.get_or_insert(&16384, RelationStats::default());
+//! entry.scans += 1;
+//! entry.rows += 100;
+//! ```
+use crate::{pg_sys, DsaArea, PGXSharedMemory};
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::CString;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::os::raw::{c_int, c_void};
+
+/// What `dshash` stores for each key, which must begin with the key
+#[repr(C)]
+struct Entry<K, V> {
+    key: K,
+    value: V,
+}
+
+/// A hash table in a [`DsaArea`], mapping `K`s to `V`s
+///
+/// Both must be safe to share between processes, and so implement [`PGXSharedMemory`].  Keys
+/// are hashed with their `Hash` implementation, which must give the same hash in every backend,
+/// as those of integers, arrays, and `#[derive(Hash)]` structs of them do.
+///
+/// Each reference to an entry holds a lock on part of the table, so a backend must only keep one
+/// at a time, as trying to lock the same part again waits forever.  Like the area itself, the
+/// table is detached when it's dropped, and is destroyed along with the area.
+pub struct SharedHashMap<K, V> {
+    table: *mut pg_sys::dshash_table,
+    _marker: PhantomData<*const Entry<K, V>>,
+}
+
+impl<K, V> SharedHashMap<K, V>
+where
+    K: Copy + Eq + Hash + PGXSharedMemory,
+    V: PGXSharedMemory,
+{
+    /// Create a new, empty table in `area`
+    ///
+    /// Its locks are named `tranche_name`, in this process, for `pg_stat_activity`'s wait
+    /// events.
+    pub fn create(area: &DsaArea, tranche_name: &str) -> Self {
+        unsafe {
+            let tranche_id = pg_sys::LWLockNewTrancheId();
+            // Postgres keeps the pointer, so the name must live as long as the process
+            let tranche_name = CString::new(tranche_name)
+                .expect("tranche name contained a null byte")
+                .into_raw();
+            pg_sys::LWLockRegisterTranche(tranche_id, tranche_name as _);
+
+            let params = Self::parameters(tranche_id);
+            SharedHashMap {
+                table: pg_sys::dshash_create(area.as_ptr(), &params, std::ptr::null_mut()),
+                _marker: PhantomData,
+            }
+        }
+    }
+
+    /// Attach to the table in `area` with the specified `handle`
+    ///
+    /// ## Safety
+    ///
+    /// `handle` must have come from [`SharedHashMap::handle`] on a table in `area` with the same
+    /// `K` and `V`.
+    pub unsafe fn attach(area: &DsaArea, handle: pg_sys::dshash_table_handle) -> Self {
+        // the tranche is only used to create the locks, which the table already has
+        let params = Self::parameters(0);
+        SharedHashMap {
+            table: pg_sys::dshash_attach(area.as_ptr(), &params, handle, std::ptr::null_mut()),
+            _marker: PhantomData,
+        }
+    }
+
+    /// The handle another process can pass to [`SharedHashMap::attach`], along with the
+    /// [`DsaArea`]'s own handle
+    pub fn handle(&self) -> pg_sys::dshash_table_handle {
+        unsafe { pg_sys::dshash_get_hash_table_handle(self.table) }
+    }
+
+    /// The value for `key`, locked so that other backends can read it but not change it
+    pub fn get(&self, key: &K) -> Option<SharedHashMapRef<'_, K, V>> {
+        let entry = self.find(key, false)?;
+        Some(SharedHashMapRef { map: self, entry })
+    }
+
+    /// The value for `key`, locked so that no other backend can use it
+    pub fn get_mut(&self, key: &K) -> Option<SharedHashMapRefMut<'_, K, V>> {
+        let entry = self.find(key, true)?;
+        Some(SharedHashMapRefMut { map: self, entry })
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// The value for `key`, inserting `value` first if there isn't one, locked so that no other
+    /// backend can use it
+    pub fn get_or_insert(&self, key: &K, value: V) -> SharedHashMapRefMut<'_, K, V> {
+        let mut found = false;
+        unsafe {
+            let entry = pg_sys::dshash_find_or_insert(self.table, key as *const K as _, &mut found)
+                as *mut Entry<K, V>;
+            if !found {
+                // `dshash` has copied in the key, but the value is uninitialized
+                std::ptr::addr_of_mut!((*entry).value).write(value);
+            }
+            SharedHashMapRefMut { map: self, entry }
+        }
+    }
+
+    /// Set the value for `key`, returning the value it replaced, if any
+    pub fn insert(&self, key: &K, value: V) -> Option<V> {
+        let mut found = false;
+        unsafe {
+            let entry = pg_sys::dshash_find_or_insert(self.table, key as *const K as _, &mut found)
+                as *mut Entry<K, V>;
+            let value_ptr = std::ptr::addr_of_mut!((*entry).value);
+            let previous = if found {
+                Some(std::ptr::replace(value_ptr, value))
+            } else {
+                value_ptr.write(value);
+                None
+            };
+            pg_sys::dshash_release_lock(self.table, entry as _);
+            previous
+        }
+    }
+
+    /// Remove the entry for `key`, returning its value, if there was one
+    pub fn remove(&self, key: &K) -> Option<V> {
+        let entry = self.find(key, true)?;
+        unsafe {
+            let value = std::ptr::addr_of!((*entry).value).read();
+            // this releases the lock too
+            pg_sys::dshash_delete_entry(self.table, entry as _);
+            Some(value)
+        }
+    }
+
+    /// Detach from the table and free it, along with everything in it, for every backend
+    ///
+    /// Values still in the table aren't dropped.
+    ///
+    /// ## Safety
+    ///
+    /// No other backend may use the table, or attach to it, again.
+    pub unsafe fn destroy(self) {
+        let table = self.table;
+        std::mem::forget(self);
+        pg_sys::dshash_destroy(table);
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::dshash_table {
+        self.table
+    }
+
+    fn find(&self, key: &K, exclusive: bool) -> Option<*mut Entry<K, V>> {
+        let entry = unsafe {
+            pg_sys::dshash_find(self.table, key as *const K as _, exclusive) as *mut Entry<K, V>
+        };
+        if entry.is_null() {
+            None
+        } else {
+            Some(entry)
+        }
+    }
+
+    fn parameters(tranche_id: c_int) -> pg_sys::dshash_parameters {
+        pg_sys::dshash_parameters {
+            key_size: std::mem::size_of::<K>(),
+            entry_size: std::mem::size_of::<Entry<K, V>>(),
+            compare_function: Some(compare_keys::<K>),
+            hash_function: Some(hash_key::<K>),
+            tranche_id,
+        }
+    }
+}
+
+impl<K, V> Drop for SharedHashMap<K, V> {
+    fn drop(&mut self) {
+        unsafe { pg_sys::dshash_detach(self.table) }
+    }
+}
+
+unsafe extern "C" fn compare_keys<K: Eq>(
+    a: *const c_void,
+    b: *const c_void,
+    _size: usize,
+    _arg: *mut c_void,
+) -> c_int {
+    if *(a as *const K) == *(b as *const K) {
+        0
+    } else {
+        1
+    }
+}
+
+unsafe extern "C" fn hash_key<K: Hash>(
+    key: *const c_void,
+    _size: usize,
+    _arg: *mut c_void,
+) -> pg_sys::dshash_hash {
+    // unlike `HashMap`'s default, an unkeyed `DefaultHasher` hashes the same in every backend
+    let mut hasher = DefaultHasher::new();
+    (*(key as *const K)).hash(&mut hasher);
+    hasher.finish() as pg_sys::dshash_hash
+}
+
+/// A value in a [`SharedHashMap`], which other backends can read but not change while it's held
+pub struct SharedHashMapRef<'a, K, V> {
+    map: &'a SharedHashMap<K, V>,
+    entry: *mut Entry<K, V>,
+}
+
+impl<K, V> SharedHashMapRef<'_, K, V> {
+    pub fn key(&self) -> &K {
+        unsafe { &(*self.entry).key }
+    }
+}
+
+impl<K, V> Deref for SharedHashMapRef<'_, K, V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        unsafe { &(*self.entry).value }
+    }
+}
+
+impl<K, V> Drop for SharedHashMapRef<'_, K, V> {
+    fn drop(&mut self) {
+        unsafe { pg_sys::dshash_release_lock(self.map.table, self.entry as _) }
+    }
+}
+
+/// A value in a [`SharedHashMap`], which no other backend can use while it's held
+pub struct SharedHashMapRefMut<'a, K, V> {
+    map: &'a SharedHashMap<K, V>,
+    entry: *mut Entry<K, V>,
+}
+
+impl<K, V> SharedHashMapRefMut<'_, K, V> {
+    pub fn key(&self) -> &K {
+        unsafe { &(*self.entry).key }
+    }
+}
+
+impl<K, V> Deref for SharedHashMapRefMut<'_, K, V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        unsafe { &(*self.entry).value }
+    }
+}
+
+impl<K, V> DerefMut for SharedHashMapRefMut<'_, K, V> {
+    fn deref_mut(&mut self) -> &mut V {
+        unsafe { &mut (*self.entry).value }
+    }
+}
+
+impl<K, V> Drop for SharedHashMapRefMut<'_, K, V> {
+    fn drop(&mut self) {
+        unsafe { pg_sys::dshash_release_lock(self.map.table, self.entry as _) }
+    }
+}
@@ -0,0 +1,288 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Dynamic shared memory, which backends can create and share while Postgres is running
+//!
+//! Unlike the shared memory reserved with `pg_shmem_init!()`, which must be requested by an
+//! extension in `shared_preload_libraries` and is fixed in size, these can be created whenever
+//! they're needed, such as for a job a backend hands off to its dynamic background workers.
+//!
+//! A [`DsmSegment`] is a single block of memory whose size is fixed when it's created.  A
+//! [`DsaArea`] is a growable heap built from segments, with typed [`DsaPointer`]s that, unlike
+//! raw pointers, mean the same thing in every process attached to it.
+//!
+//! ```rust,no_run
+//! use pgx::bgworkers::BackgroundWorkerBuilder;
+//! use pgx::*;
+//!
+//! // in the leader
+//! let area = DsaArea::create("my_extension_jobs");
+//! let progress = area.alloc(SharedAtomicU64::new(0));
+//! BackgroundWorkerBuilder::new("job")
+//!     .set_library("example")
+//!     .set_function("job_main")
+//!     .set_argument(Some(area.handle() as pg_sys::Datum))
+//!     .set_extra(&progress.into_raw().to_string())
+//!     .load_dynamic()
+//!     .unwrap();
+//!
+//! // and in the worker
+//! # let (handle, raw) = (0, 0);
+//! let area = DsaArea::attach(handle).expect("the leader has gone");
+//! let progress = unsafe { DsaPointer::<SharedAtomicU64>::from_raw(raw) };
+//! area.get(progress).increment();
+//! ```
+//!
+//! Both are attached through the current resource owner, so they're detached at the end of the
+//! transaction unless `pin_mapping()` is called, and must be dropped before then otherwise.
+use crate::{pg_sys, PGXSharedAtomic, PGXSharedMemory};
+use std::ffi::CString;
+use std::marker::PhantomData;
+
+/// A dynamic shared memory segment
+///
+/// It's destroyed once every process that attached to it has detached, unless it's pinned.
+pub struct DsmSegment {
+    segment: *mut pg_sys::dsm_segment,
+}
+
+impl DsmSegment {
+    /// Create a new segment of `size` bytes, and attach to it
+    pub fn create(size: usize) -> Self {
+        DsmSegment {
+            segment: unsafe { pg_sys::dsm_create(size, 0) },
+        }
+    }
+
+    /// Attach to the segment with the specified `handle`, if it still exists
+    pub fn attach(handle: pg_sys::dsm_handle) -> Option<Self> {
+        let segment = unsafe { pg_sys::dsm_attach(handle) };
+        if segment.is_null() {
+            None
+        } else {
+            Some(DsmSegment { segment })
+        }
+    }
+
+    /// The handle another process can pass to [`DsmSegment::attach`]
+    pub fn handle(&self) -> pg_sys::dsm_handle {
+        unsafe { pg_sys::dsm_segment_handle(self.segment) }
+    }
+
+    /// Where the segment is mapped in this process, which can differ in other processes
+    pub fn address(&self) -> *mut std::os::raw::c_void {
+        unsafe { pg_sys::dsm_segment_address(self.segment) }
+    }
+
+    pub fn len(&self) -> usize {
+        unsafe { pg_sys::dsm_segment_map_length(self.segment) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Keep the segment attached until it's dropped, or the process exits, rather than just
+    /// until the end of the transaction
+    pub fn pin_mapping(&self) {
+        unsafe { pg_sys::dsm_pin_mapping(self.segment) }
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::dsm_segment {
+        self.segment
+    }
+}
+
+impl Drop for DsmSegment {
+    fn drop(&mut self) {
+        unsafe { pg_sys::dsm_detach(self.segment) }
+    }
+}
+
+/// A shared memory heap, which can grow as more is allocated from it
+///
+/// It's destroyed, freeing everything in it, once every process that attached to it has
+/// detached, unless it's pinned with [`DsaArea::pin`].
+pub struct DsaArea {
+    area: *mut pg_sys::dsa_area,
+}
+
+impl DsaArea {
+    /// Create a new area, and attach to it
+    ///
+    /// Its lock is named `tranche_name`, in this process, for `pg_stat_activity`'s wait events.
+    pub fn create(tranche_name: &str) -> Self {
+        unsafe {
+            let tranche_id = pg_sys::LWLockNewTrancheId();
+            // Postgres keeps the pointer, so the name must live as long as the process
+            let tranche_name = CString::new(tranche_name)
+                .expect("tranche name contained a null byte")
+                .into_raw();
+            pg_sys::LWLockRegisterTranche(tranche_id, tranche_name as _);
+
+            DsaArea {
+                area: pg_sys::dsa_create(tranche_id),
+            }
+        }
+    }
+
+    /// Attach to the area with the specified `handle`, if it still exists
+    pub fn attach(handle: pg_sys::dsa_handle) -> Option<Self> {
+        // `dsa_attach()` raises an error, rather than returning null, if the area is gone
+        DsmSegment::attach(handle)?;
+        Some(DsaArea {
+            area: unsafe { pg_sys::dsa_attach(handle) },
+        })
+    }
+
+    /// The handle another process can pass to [`DsaArea::attach`]
+    pub fn handle(&self) -> pg_sys::dsa_handle {
+        unsafe { pg_sys::dsa_get_handle(self.area) }
+    }
+
+    /// Move `value` into the area
+    pub fn alloc<T: PGXSharedMemory>(&self, value: T) -> DsaPointer<T> {
+        let pointer = self.alloc_bytes(std::mem::size_of::<T>());
+        unsafe { self.as_mut_ptr(pointer.cast::<T>()).write(value) };
+        pointer.cast()
+    }
+
+    /// Allocate `len` zeroed bytes in the area
+    pub fn alloc_bytes(&self, len: usize) -> DsaPointer<u8> {
+        let pointer = unsafe {
+            pg_sys::dsa_allocate_extended(
+                self.area,
+                len,
+                (pg_sys::DSA_ALLOC_HUGE | pg_sys::DSA_ALLOC_ZERO) as i32,
+            )
+        };
+        DsaPointer {
+            pointer,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Free what `pointer` points to, without dropping it
+    ///
+    /// ## Safety
+    ///
+    /// `pointer` must have come from this area, and must not be used again, by any process.
+    pub unsafe fn free<T>(&self, pointer: DsaPointer<T>) {
+        pg_sys::dsa_free(self.area, pointer.pointer)
+    }
+
+    /// The value `pointer` points to, which other processes may be using at the same time
+    ///
+    /// Panics if `pointer` is null.
+    pub fn get<T: PGXSharedAtomic>(&self, pointer: DsaPointer<T>) -> &T {
+        unsafe { &*self.as_mut_ptr(pointer) }
+    }
+
+    /// Where `pointer` points in this process, which is only valid while the area is attached
+    ///
+    /// Panics if `pointer` is null.
+    pub fn as_mut_ptr<T>(&self, pointer: DsaPointer<T>) -> *mut T {
+        if pointer.is_null() {
+            panic!("dereferenced a null DsaPointer");
+        }
+        unsafe { pg_sys::dsa_get_address(self.area, pointer.pointer) as *mut T }
+    }
+
+    /// Keep the area attached until it's dropped, or the process exits, rather than just
+    /// until the end of the transaction
+    pub fn pin_mapping(&self) {
+        unsafe { pg_sys::dsa_pin_mapping(self.area) }
+    }
+
+    /// Keep the area in existence even once no process is attached to it, until
+    /// [`DsaArea::unpin`] is called
+    pub fn pin(&self) {
+        unsafe { pg_sys::dsa_pin(self.area) }
+    }
+
+    pub fn unpin(&self) {
+        unsafe { pg_sys::dsa_unpin(self.area) }
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::dsa_area {
+        self.area
+    }
+}
+
+impl Drop for DsaArea {
+    fn drop(&mut self) {
+        unsafe { pg_sys::dsa_detach(self.area) }
+    }
+}
+
+/// A `T` in a [`DsaArea`], which any process attached to the area can reach
+pub struct DsaPointer<T> {
+    pointer: pg_sys::dsa_pointer,
+    _marker: PhantomData<*const T>,
+}
+
+impl<T> DsaPointer<T> {
+    pub fn null() -> Self {
+        DsaPointer {
+            pointer: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.pointer == 0
+    }
+
+    /// The pointer as a plain number, for passing to another process
+    pub fn into_raw(self) -> pg_sys::dsa_pointer {
+        self.pointer
+    }
+
+    /// ## Safety
+    ///
+    /// `pointer` must have come from [`DsaPointer::into_raw`] on a pointer to a `T`.
+    pub unsafe fn from_raw(pointer: pg_sys::dsa_pointer) -> Self {
+        DsaPointer {
+            pointer,
+            _marker: PhantomData,
+        }
+    }
+
+    fn cast<U>(self) -> DsaPointer<U> {
+        DsaPointer {
+            pointer: self.pointer,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for DsaPointer<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for DsaPointer<T> {}
+
+impl<T> std::fmt::Debug for DsaPointer<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DsaPointer({:#x})", self.pointer)
+    }
+}
+
+impl<T> PartialEq for DsaPointer<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.pointer == other.pointer
+    }
+}
+
+impl<T> Eq for DsaPointer<T> {}
+
+unsafe impl<T> Send for DsaPointer<T> {}
+unsafe impl<T> Sync for DsaPointer<T> {}
@@ -0,0 +1,255 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Support for [event triggers](https://www.postgresql.org/docs/current/event-triggers.html),
+//! which fire on DDL commands rather than on changes to a table's rows
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[pg_event_trigger(event = "sql_drop")]
+//! fn forbid_dropping_dogs(trigger: &PgEventTrigger) -> Result<(), String> {
+//!     match trigger
+//!         .dropped_objects()
+//!         .into_iter()
+//!         .find(|dropped| dropped.object_name.as_deref() == Some("dogs"))
+//!     {
+//!         Some(dropped) => Err(format!("{} can't be dropped", dropped.object_identity)),
+//!         None => Ok(()),
+//!     }
+//! }
+//! ```
+use crate::{error, is_a, pg_sys, PgBox, Spi, SpiHeapTupleData};
+use std::ffi::CStr;
+use std::fmt::Display;
+
+/// The event an event trigger fired for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventTriggerEvent {
+    /// Before a DDL command is executed
+    DdlCommandStart,
+    /// After a DDL command is executed, when [`PgEventTrigger::ddl_commands`] are available
+    DdlCommandEnd,
+    /// After a DDL command dropped objects, which are [`PgEventTrigger::dropped_objects`]
+    SqlDrop,
+    /// Before a table is rewritten, which is [`PgEventTrigger::table_rewrite`]
+    TableRewrite,
+}
+
+/// The firing of an event trigger, as passed to a `#[pg_event_trigger]` function
+pub struct PgEventTrigger {
+    data: PgBox<pg_sys::EventTriggerData>,
+}
+
+impl PgEventTrigger {
+    /// The event trigger being fired, which calls the function with `fcinfo`
+    ///
+    /// Panics if the function wasn't called as an event trigger.
+    ///
+    /// ## Safety
+    ///
+    /// `fcinfo` must be the `pg_sys::FunctionCallInfo` of the function being called
+    pub unsafe fn from_fcinfo(fcinfo: pg_sys::FunctionCallInfo) -> Self {
+        if !called_as_event_trigger(fcinfo) {
+            panic!("not fired by event trigger manager");
+        }
+
+        PgEventTrigger {
+            data: PgBox::from_pg((*fcinfo).context as *mut pg_sys::EventTriggerData),
+        }
+    }
+
+    pub fn event(&self) -> EventTriggerEvent {
+        let event = unsafe { CStr::from_ptr(self.data.event) };
+        match event.to_bytes() {
+            b"ddl_command_start" => EventTriggerEvent::DdlCommandStart,
+            b"ddl_command_end" => EventTriggerEvent::DdlCommandEnd,
+            b"sql_drop" => EventTriggerEvent::SqlDrop,
+            b"table_rewrite" => EventTriggerEvent::TableRewrite,
+            other => panic!(
+                "unrecognized event trigger event: {}",
+                String::from_utf8_lossy(other)
+            ),
+        }
+    }
+
+    /// The command tag of the DDL command, such as `CREATE TABLE`
+    pub fn tag(&self) -> &str {
+        #[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12"))]
+        let tag = self.data.tag;
+
+        #[cfg(any(feature = "pg13", feature = "pg14"))]
+        let tag = unsafe { pg_sys::GetCommandTagName(self.data.tag) };
+
+        unsafe { CStr::from_ptr(tag) }
+            .to_str()
+            .expect("command tag is not valid UTF8")
+    }
+
+    /// The parse tree of the DDL command
+    pub fn parsetree(&self) -> *mut pg_sys::Node {
+        self.data.parsetree
+    }
+
+    /// What the DDL command did, as returned by `pg_event_trigger_ddl_commands()`
+    ///
+    /// These are only available at `ddl_command_end`, and Postgres raises an ERROR otherwise.
+    pub fn ddl_commands(&self) -> Vec<DdlCommand> {
+        select_rows(
+            "SELECT classid, objid, objsubid, command_tag, object_type, schema_name, \
+                    object_identity, in_extension \
+               FROM pg_event_trigger_ddl_commands()",
+            |row| DdlCommand {
+                classid: column(row, "classid"),
+                objid: column(row, "objid"),
+                objsubid: column(row, "objsubid"),
+                command_tag: column(row, "command_tag"),
+                object_type: column(row, "object_type"),
+                schema_name: nullable_column(row, "schema_name"),
+                object_identity: column(row, "object_identity"),
+                in_extension: column(row, "in_extension"),
+            },
+        )
+    }
+
+    /// The objects the DDL command dropped, as returned by `pg_event_trigger_dropped_objects()`
+    ///
+    /// These are only available at `sql_drop`, and Postgres raises an ERROR otherwise.
+    pub fn dropped_objects(&self) -> Vec<DroppedObject> {
+        select_rows(
+            "SELECT classid, objid, objsubid, original, normal, is_temporary, object_type, \
+                    schema_name, object_name, object_identity, address_names, address_args \
+               FROM pg_event_trigger_dropped_objects()",
+            |row| DroppedObject {
+                classid: column(row, "classid"),
+                objid: column(row, "objid"),
+                objsubid: column(row, "objsubid"),
+                original: column(row, "original"),
+                normal: column(row, "normal"),
+                is_temporary: column(row, "is_temporary"),
+                object_type: column(row, "object_type"),
+                schema_name: nullable_column(row, "schema_name"),
+                object_name: nullable_column(row, "object_name"),
+                object_identity: column(row, "object_identity"),
+                address_names: column(row, "address_names"),
+                address_args: column(row, "address_args"),
+            },
+        )
+    }
+
+    /// The table being rewritten, and why, at `table_rewrite`
+    pub fn table_rewrite(&self) -> Option<TableRewrite> {
+        if self.event() != EventTriggerEvent::TableRewrite {
+            return None;
+        }
+
+        let (table, reason) = Spi::get::<(pg_sys::Oid, i32)>(
+            "SELECT pg_event_trigger_table_rewrite_oid(), pg_event_trigger_table_rewrite_reason()",
+            None,
+        )
+        .expect("failed to get the table being rewritten")?;
+        Some(TableRewrite { table, reason })
+    }
+
+    /// The `pg_sys::EventTriggerData` the event trigger manager passed to the function
+    pub fn event_trigger_data(&self) -> &PgBox<pg_sys::EventTriggerData> {
+        &self.data
+    }
+}
+
+/// An object created or altered by a DDL command, from `pg_event_trigger_ddl_commands()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DdlCommand {
+    /// The oid of the catalog the object is in
+    pub classid: pg_sys::Oid,
+    pub objid: pg_sys::Oid,
+    /// The column number, for a column, and otherwise 0
+    pub objsubid: i32,
+    pub command_tag: String,
+    /// The type of the object, such as `table`
+    pub object_type: String,
+    pub schema_name: Option<String>,
+    /// The schema-qualified name of the object, such as `public.dogs`
+    pub object_identity: String,
+    /// Whether the command is part of an extension's script
+    pub in_extension: bool,
+}
+
+/// An object dropped by a DDL command, from `pg_event_trigger_dropped_objects()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DroppedObject {
+    /// The oid of the catalog the object was in
+    pub classid: pg_sys::Oid,
+    pub objid: pg_sys::Oid,
+    /// The column number, for a column, and otherwise 0
+    pub objsubid: i32,
+    /// Whether the object was dropped directly, rather than as a dependency
+    pub original: bool,
+    /// Whether the object was dropped as a normal dependency, rather than an automatic one
+    pub normal: bool,
+    pub is_temporary: bool,
+    /// The type of the object, such as `table`
+    pub object_type: String,
+    pub schema_name: Option<String>,
+    pub object_name: Option<String>,
+    /// The schema-qualified name of the object, such as `public.dogs`
+    pub object_identity: String,
+    /// The object's address, for `pg_get_object_address()`
+    pub address_names: Vec<String>,
+    pub address_args: Vec<String>,
+}
+
+/// A table about to be rewritten, at `table_rewrite`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableRewrite {
+    pub table: pg_sys::Oid,
+    /// Why the table is rewritten, as a bitmask of `pg_sys::AT_REWRITE_*`
+    pub reason: i32,
+}
+
+/// What a `#[pg_event_trigger]` function returns
+pub trait IntoEventTriggerResult {
+    fn into_event_trigger_result(self);
+}
+
+impl IntoEventTriggerResult for () {
+    fn into_event_trigger_result(self) {}
+}
+
+impl<E: Display> IntoEventTriggerResult for Result<(), E> {
+    fn into_event_trigger_result(self) {
+        if let Err(e) = self {
+            error!("{}", e)
+        }
+    }
+}
+
+#[inline]
+pub unsafe fn called_as_event_trigger(fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    let fcinfo = fcinfo.as_ref().expect("fcinfo was null");
+    !fcinfo.context.is_null() && is_a(fcinfo.context, pg_sys::NodeTag_T_EventTriggerData)
+}
+
+fn select_rows<T, F: Fn(&SpiHeapTupleData) -> T>(query: &str, build: F) -> Vec<T> {
+    let mut rows = Vec::new();
+    Spi::connect(|client| {
+        rows.extend(client.select(query, None, None).map(|row| build(&row)));
+        Ok(Some(()))
+    });
+    rows
+}
+
+fn column<T: crate::FromDatum>(row: &SpiHeapTupleData, name: &str) -> T {
+    nullable_column(row, name).unwrap_or_else(|| panic!("`{}` is NULL", name))
+}
+
+fn nullable_column<T: crate::FromDatum>(row: &SpiHeapTupleData, name: &str) -> Option<T> {
+    row.get_by_name(name)
+        .unwrap_or_else(|e| panic!("failed to read `{}`: {}", name, e))
+}
@@ -0,0 +1,324 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Direct access to Postgres' planner and executor, for running a statement repeatedly without
+//! SPI's per-call overhead
+//!
+//! A [`PlannedQuery`] is parsed, analyzed, and planned once.  Each call to
+//! [`PlannedQuery::execute`] then runs that plan, handing each row to a callback as the executor
+//! produces it rather than materializing them all into a tuptable first.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! let query = PlannedQuery::new(
+//!     "SELECT x FROM generate_series(1, $1) x",
+//!     &[PgBuiltInOids::INT4OID.oid()],
+//! );
+//!
+//! let mut sum = 0_i64;
+//! query.execute(vec![10_i32.into_datum()], |row| {
+//!     sum += row.get::<i32>(1).unwrap() as i64
+//! });
+//! assert_eq!(sum, 55);
+//! ```
+use crate::{pg_guard, pg_sys, FromDatum, PgList, PgMemoryContexts};
+use std::ffi::CString;
+
+/// A single statement, planned once and run directly by the executor, as often as needed
+///
+/// Unlike an SPI prepared statement, the plan isn't checked for validity before it's run, so it
+/// must not outlive any changes to the tables, functions, or types it refers to.
+pub struct PlannedQuery {
+    stmt: *mut pg_sys::PlannedStmt,
+    source: CString,
+    argtypes: Vec<pg_sys::Oid>,
+    // owns `stmt`
+    _context: PgMemoryContexts,
+}
+
+impl PlannedQuery {
+    /// Parse, analyze, and plan `query`, whose parameters (`$1`, `$2`, etc) are of the types in
+    /// `argtypes`
+    ///
+    /// The plan is allocated in its own memory context, a child of `CurrentMemoryContext`, and is
+    /// freed when this is dropped.  To keep a `PlannedQuery` across transactions, create it while
+    /// `TopMemoryContext` is current.
+    ///
+    /// Panics if `query` isn't exactly one `SELECT`, `INSERT`, `UPDATE`, or `DELETE` statement.
+    pub fn new(query: &str, argtypes: &[pg_sys::Oid]) -> Self {
+        let source = CString::new(query).expect("query contained a null byte");
+        let mut argtypes = argtypes.to_vec();
+        let mut context = PgMemoryContexts::new("PlannedQuery");
+
+        let source_ptr = source.as_ptr();
+        let nargs = argtypes.len() as i32;
+        let argtypes_ptr = if argtypes.is_empty() {
+            std::ptr::null_mut()
+        } else {
+            argtypes.as_mut_ptr()
+        };
+        let stmt = context.switch_to(|_| unsafe { plan_query(source_ptr, argtypes_ptr, nargs) });
+
+        PlannedQuery {
+            stmt,
+            source,
+            argtypes,
+            _context: context,
+        }
+    }
+
+    /// Run the plan with `args` as its parameters, calling `f` with each row it produces, and
+    /// return the number of rows it processed
+    ///
+    /// As with an SPI statement that isn't read-only, the plan sees the effects of earlier
+    /// commands in the transaction, and later ones see its effects.
+    ///
+    /// Panics if there aren't as many `args` as the query has parameters.
+    pub fn execute<F: FnMut(&ExecutorRow)>(
+        &self,
+        args: Vec<Option<pg_sys::Datum>>,
+        mut f: F,
+    ) -> u64 {
+        if args.len() != self.argtypes.len() {
+            panic!(
+                "query has {} parameters, but {} arguments were given",
+                self.argtypes.len(),
+                args.len()
+            );
+        }
+
+        unsafe {
+            let params = self.make_params(args);
+            let mut receiver = CallbackReceiver {
+                receiver: pg_sys::DestReceiver {
+                    receiveSlot: Some(receive_slot),
+                    rStartup: Some(receiver_startup),
+                    rShutdown: Some(receiver_shutdown),
+                    rDestroy: Some(receiver_shutdown),
+                    mydest: pg_sys::CommandDest_DestNone,
+                },
+                callback: &mut f,
+            };
+
+            pg_sys::CommandCounterIncrement();
+            pg_sys::PushActiveSnapshot(pg_sys::GetTransactionSnapshot());
+
+            let query_desc = pg_sys::CreateQueryDesc(
+                self.stmt,
+                self.source.as_ptr(),
+                pg_sys::GetActiveSnapshot(),
+                std::ptr::null_mut(),
+                &mut receiver.receiver,
+                params,
+                std::ptr::null_mut(),
+                0,
+            );
+            pg_sys::ExecutorStart(query_desc, 0);
+            pg_sys::ExecutorRun(
+                query_desc,
+                pg_sys::ScanDirection_ForwardScanDirection,
+                0,
+                true,
+            );
+            let processed = (*(*query_desc).estate).es_processed;
+            pg_sys::ExecutorFinish(query_desc);
+            pg_sys::ExecutorEnd(query_desc);
+            pg_sys::FreeQueryDesc(query_desc);
+
+            pg_sys::PopActiveSnapshot();
+            pg_sys::CommandCounterIncrement();
+
+            processed
+        }
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::PlannedStmt {
+        self.stmt
+    }
+
+    unsafe fn make_params(&self, args: Vec<Option<pg_sys::Datum>>) -> pg_sys::ParamListInfo {
+        if args.is_empty() {
+            return std::ptr::null_mut();
+        }
+
+        // `makeParamList()` is only available since Postgres 12
+        let size = std::mem::size_of::<pg_sys::ParamListInfoData>()
+            + args.len() * std::mem::size_of::<pg_sys::ParamExternData>();
+        let params = pg_sys::palloc0(size) as pg_sys::ParamListInfo;
+        (*params).numParams = args.len() as i32;
+
+        let slots = (*params).params.as_mut_slice(args.len());
+        for (param, (arg, typoid)) in slots
+            .iter_mut()
+            .zip(args.into_iter().zip(self.argtypes.iter()))
+        {
+            param.value = arg.unwrap_or(0);
+            param.isnull = arg.is_none();
+            param.pflags = pg_sys::PARAM_FLAG_CONST as u16;
+            param.ptype = *typoid;
+        }
+        params
+    }
+}
+
+/// A row produced by the executor for [`PlannedQuery::execute`]
+///
+/// Pass-by-reference values, such as a `&str`, are only valid until the callback returns.
+pub struct ExecutorRow {
+    slot: *mut pg_sys::TupleTableSlot,
+}
+
+impl ExecutorRow {
+    /// The number of columns in the row
+    pub fn len(&self) -> usize {
+        unsafe { (*(*self.slot).tts_tupleDescriptor).natts as usize }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the value of the column at the specified 1-based `ordinal` position
+    ///
+    /// Panics if the row has no such column.
+    pub fn get<T: FromDatum>(&self, ordinal: usize) -> Option<T> {
+        if ordinal < 1 || ordinal > self.len() {
+            panic!(
+                "ordinal {} is out of bounds for a row of {} columns",
+                ordinal,
+                self.len()
+            );
+        }
+
+        unsafe {
+            let mut is_null = false;
+            let datum = slot_getattr(self.slot, ordinal, &mut is_null);
+            let typoid = pg_sys::SPI_gettypeid((*self.slot).tts_tupleDescriptor, ordinal as i32);
+            T::from_datum(datum, is_null, typoid)
+        }
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::TupleTableSlot {
+        self.slot
+    }
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12"))]
+unsafe fn plan_query(
+    source: *const std::os::raw::c_char,
+    argtypes: *mut pg_sys::Oid,
+    nargs: i32,
+) -> *mut pg_sys::PlannedStmt {
+    let queries = analyze_and_rewrite(source, argtypes, nargs);
+    check_planned(pg_sys::pg_plan_queries(queries, 0, std::ptr::null_mut()))
+}
+
+#[cfg(any(feature = "pg13", feature = "pg14"))]
+unsafe fn plan_query(
+    source: *const std::os::raw::c_char,
+    argtypes: *mut pg_sys::Oid,
+    nargs: i32,
+) -> *mut pg_sys::PlannedStmt {
+    let queries = analyze_and_rewrite(source, argtypes, nargs);
+    check_planned(pg_sys::pg_plan_queries(
+        queries,
+        source,
+        0,
+        std::ptr::null_mut(),
+    ))
+}
+
+unsafe fn analyze_and_rewrite(
+    source: *const std::os::raw::c_char,
+    argtypes: *mut pg_sys::Oid,
+    nargs: i32,
+) -> *mut pg_sys::List {
+    let statements = PgList::<pg_sys::RawStmt>::from_pg(pg_sys::pg_parse_query(source));
+    if statements.len() != 1 {
+        panic!(
+            "PlannedQuery requires exactly one statement, but the query has {}",
+            statements.len()
+        );
+    }
+
+    pg_sys::pg_analyze_and_rewrite(
+        statements.head().unwrap(),
+        source,
+        argtypes,
+        nargs,
+        std::ptr::null_mut(),
+    )
+}
+
+unsafe fn check_planned(planned: *mut pg_sys::List) -> *mut pg_sys::PlannedStmt {
+    let planned = PgList::<pg_sys::PlannedStmt>::from_pg(planned);
+    if planned.len() != 1 {
+        panic!(
+            "PlannedQuery requires exactly one statement, but the query was rewritten into {}",
+            planned.len()
+        );
+    }
+
+    let stmt = planned.head().unwrap();
+    if (*stmt).commandType == pg_sys::CmdType_CMD_UTILITY {
+        panic!("PlannedQuery cannot execute utility statements");
+    }
+    stmt
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11"))]
+unsafe fn slot_getattr(
+    slot: *mut pg_sys::TupleTableSlot,
+    attnum: usize,
+    is_null: &mut bool,
+) -> pg_sys::Datum {
+    pg_sys::slot_getattr(slot, attnum as i32, is_null)
+}
+
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+unsafe fn slot_getattr(
+    slot: *mut pg_sys::TupleTableSlot,
+    attnum: usize,
+    is_null: &mut bool,
+) -> pg_sys::Datum {
+    // `slot_getattr()` is an inline function since Postgres 12
+    if ((*slot).tts_nvalid as usize) < attnum {
+        pg_sys::slot_getsomeattrs_int(slot, attnum as i32);
+    }
+    *is_null = *(*slot).tts_isnull.add(attnum - 1);
+    *(*slot).tts_values.add(attnum - 1)
+}
+
+/// A `DestReceiver` that hands each row to a Rust closure
+#[repr(C)]
+struct CallbackReceiver<'a> {
+    // must be first, as Postgres only knows about this part
+    receiver: pg_sys::DestReceiver,
+    callback: &'a mut dyn FnMut(&ExecutorRow),
+}
+
+#[pg_guard]
+unsafe extern "C" fn receive_slot(
+    slot: *mut pg_sys::TupleTableSlot,
+    receiver: *mut pg_sys::DestReceiver,
+) -> bool {
+    let receiver = &mut *(receiver as *mut CallbackReceiver<'_>);
+    (receiver.callback)(&ExecutorRow { slot });
+    true
+}
+
+unsafe extern "C" fn receiver_startup(
+    _receiver: *mut pg_sys::DestReceiver,
+    _operation: i32,
+    _tupdesc: pg_sys::TupleDesc,
+) {
+}
+
+unsafe extern "C" fn receiver_shutdown(_receiver: *mut pg_sys::DestReceiver) {}
@@ -0,0 +1,162 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Adding an extension's own details to `EXPLAIN`'s output, from a
+//! [`CustomScanExecution`](crate::CustomScanExecution) or [`PgHooks::explain_one_query`](crate::PgHooks::explain_one_query)
+//!
+//! Properties are written in whichever format `EXPLAIN` was asked for, so what's added to
+//! `EXPLAIN (FORMAT JSON)` is still valid JSON.
+use crate::pg_sys;
+use std::ffi::CString;
+
+/// The `EXPLAIN` being written, which properties are added to
+pub struct Explain {
+    es: *mut pg_sys::ExplainState,
+}
+
+impl Explain {
+    /// ## Safety
+    ///
+    /// `es` must be the state of an `EXPLAIN` being written
+    pub unsafe fn from_pg(es: *mut pg_sys::ExplainState) -> Self {
+        Explain { es }
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::ExplainState {
+        self.es
+    }
+
+    /// Is this `EXPLAIN VERBOSE`?
+    pub fn verbose(&self) -> bool {
+        unsafe { (*self.es).verbose }
+    }
+
+    /// Is this `EXPLAIN ANALYZE`, which runs the query?
+    pub fn analyze(&self) -> bool {
+        unsafe { (*self.es).analyze }
+    }
+
+    /// Were costs asked for, as they are unless `COSTS OFF`?
+    pub fn costs(&self) -> bool {
+        unsafe { (*self.es).costs }
+    }
+
+    /// Were timings asked for, as they are with `ANALYZE` unless `TIMING OFF`?
+    pub fn timing(&self) -> bool {
+        unsafe { (*self.es).timing }
+    }
+
+    /// The output format, such as `pg_sys::ExplainFormat_EXPLAIN_FORMAT_JSON`
+    pub fn format(&self) -> pg_sys::ExplainFormat {
+        unsafe { (*self.es).format }
+    }
+
+    /// Add a property named `label` with the text `value`
+    pub fn property(&mut self, label: &str, value: &str) {
+        let label = cstring(label);
+        let value = cstring(value);
+        unsafe { pg_sys::ExplainPropertyText(label.as_ptr(), value.as_ptr(), self.es) }
+    }
+
+    /// Add a property named `label` with the number `value`, followed by `unit` in the text
+    /// format on Postgres 11 and later
+    pub fn property_integer(&mut self, label: &str, unit: Option<&str>, value: i64) {
+        let label = cstring(label);
+
+        #[cfg(feature = "pg10")]
+        unsafe {
+            let _ = unit;
+            pg_sys::ExplainPropertyLong(label.as_ptr(), value as std::os::raw::c_long, self.es)
+        }
+
+        #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+        unsafe {
+            let unit = unit.map(cstring);
+            pg_sys::ExplainPropertyInteger(
+                label.as_ptr(),
+                unit.as_ref().map_or(std::ptr::null(), |unit| unit.as_ptr()),
+                value,
+                self.es,
+            )
+        }
+    }
+
+    /// Add a property named `label` with the number `value`, shown with `digits` decimal
+    /// places, followed by `unit` in the text format on Postgres 11 and later
+    pub fn property_float(&mut self, label: &str, unit: Option<&str>, value: f64, digits: i32) {
+        let label = cstring(label);
+
+        #[cfg(feature = "pg10")]
+        unsafe {
+            let _ = unit;
+            pg_sys::ExplainPropertyFloat(label.as_ptr(), value, digits, self.es)
+        }
+
+        #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+        unsafe {
+            let unit = unit.map(cstring);
+            pg_sys::ExplainPropertyFloat(
+                label.as_ptr(),
+                unit.as_ref().map_or(std::ptr::null(), |unit| unit.as_ptr()),
+                value,
+                digits,
+                self.es,
+            )
+        }
+    }
+
+    /// Add a property named `label` with the boolean `value`
+    pub fn property_bool(&mut self, label: &str, value: bool) {
+        let label = cstring(label);
+        unsafe { pg_sys::ExplainPropertyBool(label.as_ptr(), value, self.es) }
+    }
+
+    /// Group the properties `f` adds under `label`, such as a JSON object named `label`
+    ///
+    /// Groups are shown as indentation in the text format.
+    #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+    pub fn group<F: FnOnce(&mut Explain)>(&mut self, label: &str, f: F) {
+        let label = cstring(label);
+        unsafe {
+            pg_sys::ExplainOpenGroup(label.as_ptr(), label.as_ptr(), true, self.es);
+            if self.format() == pg_sys::ExplainFormat_EXPLAIN_FORMAT_TEXT {
+                pg_sys::appendStringInfoSpaces((*self.es).str_, (*self.es).indent * 2);
+                pg_sys::appendStringInfoString((*self.es).str_, label.as_ptr());
+                pg_sys::appendStringInfoString((*self.es).str_, b":\n\0".as_ptr() as _);
+                (*self.es).indent += 1;
+            }
+        }
+
+        f(self);
+
+        unsafe {
+            if self.format() == pg_sys::ExplainFormat_EXPLAIN_FORMAT_TEXT {
+                (*self.es).indent -= 1;
+            }
+            pg_sys::ExplainCloseGroup(label.as_ptr(), label.as_ptr(), true, self.es);
+        }
+    }
+
+    /// Add a section of the extension's own, named `name`, after the query's plan
+    ///
+    /// This is for [`PgHooks::explain_one_query`](crate::PgHooks::explain_one_query), after
+    /// calling its `prev_hook`.  Where a plan's details would be an object, the section is
+    /// another object alongside it.
+    #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+    pub fn section<F: FnOnce(&mut Explain)>(&mut self, name: &str, f: F) {
+        let name = cstring(name);
+        unsafe { pg_sys::ExplainOpenGroup(name.as_ptr(), std::ptr::null(), false, self.es) };
+        f(self);
+        unsafe { pg_sys::ExplainCloseGroup(name.as_ptr(), std::ptr::null(), false, self.es) };
+    }
+}
+
+fn cstring(s: &str) -> CString {
+    CString::new(s).expect("EXPLAIN label or value contained a null byte")
+}
@@ -12,6 +12,7 @@ Use of this source code is governed by the MIT license that can be found in the
 //! Other than the exported macros, typically these functions are not necessary to call directly
 //! as they're used behind the scenes by the code generated by the `#[pg_extern]` macro.
 use crate::{pg_sys, void_mut_ptr, AllocatedByRust, FromDatum, PgBox, PgMemoryContexts};
+use std::any::TypeId;
 
 /// A macro for specifying default argument values so they get propery translated to SQL in
 /// `CREATE FUNCTION` statements
@@ -195,6 +196,13 @@ pub use pg_10_11::*;
 pub use pg_12_13_14::*;
 use std::ops::DerefMut;
 
+/// The input collation of the function call, as determined by Postgres from the collations of
+/// its arguments, or `pg_sys::InvalidOid` if none of them are of a collatable type
+#[inline]
+pub fn pg_get_collation(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Oid {
+    unsafe { fcinfo.as_ref() }.unwrap().fncollation
+}
+
 #[inline]
 pub fn pg_getarg_pointer<T>(fcinfo: pg_sys::FunctionCallInfo, num: usize) -> Option<*mut T> {
     match pg_getarg_datum(fcinfo, num) {
@@ -233,6 +241,8 @@ pub fn pg_return_void() -> pg_sys::Datum {
 /// Retrieve the `.flinfo.fn_extra` pointer (as a PgBox'd type) from [`pg_sys::FunctionCallInfo`].
 ///
 /// This function is unsafe as we cannot guarantee the provided [`pg_sys::FunctionCallInfo`] pointer is valid
+///
+/// See [`pg_func_cache`] for a safe alternative.
 pub unsafe fn pg_func_extra<ReturnType, DefaultValue: FnOnce() -> ReturnType>(
     fcinfo: pg_sys::FunctionCallInfo,
     default: DefaultValue,
@@ -247,6 +257,68 @@ pub unsafe fn pg_func_extra<ReturnType, DefaultValue: FnOnce() -> ReturnType>(
     PgBox::from_pg(flinfo.fn_extra as *mut ReturnType)
 }
 
+/// Get the value cached for this call site of the function, creating it with `init` the first
+/// time the function is called, such as on the first row of a query
+///
+/// The value is allocated in `fcinfo->flinfo->fn_mcxt`, so it lives as long as the function's
+/// `FmgrInfo`, which is typically until the end of the statement, and is dropped along with it.
+/// This lets a function do expensive setup, such as compiling a regex or preparing a plan, only
+/// once per statement rather than once per row.
+///
+/// ## Examples
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn greeting(name: &str, fcinfo: pg_sys::FunctionCallInfo) -> String {
+///     let prefix = pg_func_cache(fcinfo, || {
+///         Spi::get_one::<String>("SELECT current_setting('app.greeting')").unwrap()
+///     });
+///     format!("{}, {}", prefix, name)
+/// }
+/// ```
+///
+/// Panics if `fn_extra` already holds a value of some other type.  As `fn_extra` is also used to
+/// keep the state of a set-returning function between calls, this can't be used by one.
+pub fn pg_func_cache<'a, T: 'static, F: FnOnce() -> T>(
+    fcinfo: pg_sys::FunctionCallInfo,
+    init: F,
+) -> &'a T {
+    // `type_id` is always first, so it can be checked before we know `T` is right
+    #[repr(C)]
+    struct Cached<T> {
+        type_id: TypeId,
+        value: T,
+    }
+
+    unsafe {
+        let flinfo = fcinfo
+            .as_ref()
+            .unwrap()
+            .flinfo
+            .as_mut()
+            .expect("function has no FmgrInfo");
+        if flinfo.fn_extra.is_null() {
+            let cached = Cached {
+                type_id: TypeId::of::<T>(),
+                value: init(),
+            };
+            flinfo.fn_extra = PgMemoryContexts::For(flinfo.fn_mcxt).leak_and_drop_on_delete(cached)
+                as void_mut_ptr;
+        }
+
+        let cached = flinfo.fn_extra as *const Cached<T>;
+        if (*cached).type_id != TypeId::of::<T>() {
+            panic!(
+                "fn_extra already holds a value of a type other than `{}`",
+                std::any::type_name::<T>()
+            );
+        }
+        &(*cached).value
+    }
+}
+
 /// This mimics the functionality of Postgres' `DirectFunctionCall` macros, allowing you to call
 /// internal Postgres functions using its "V1" calling convention.  Unlike the Postgres' C macros,
 /// the function is allowed to return a NULL datum.
@@ -282,6 +354,24 @@ pub unsafe fn direct_function_call<R: FromDatum>(
     }
 }
 
+/// Same as [direct_function_call], but calls the function with the specified input `collation`,
+/// for functions which require one, such as `texteq` or `lower`
+///
+/// ## Safety
+///
+/// This function is unsafe as the function you're calling is also unsafe
+pub unsafe fn direct_function_call_with_collation<R: FromDatum>(
+    func: unsafe fn(pg_sys::FunctionCallInfo) -> pg_sys::Datum,
+    collation: pg_sys::Oid,
+    args: Vec<Option<pg_sys::Datum>>,
+) -> Option<R> {
+    let datum = direct_function_call_with_collation_as_datum(func, collation, args);
+    match datum {
+        Some(datum) => R::from_datum(datum, false, pg_sys::InvalidOid),
+        None => None,
+    }
+}
+
 /// Akin to [direct_function_call], but specifically for calling those functions declared with the
 /// `#[pg_extern]` attribute.
 ///
@@ -328,6 +418,20 @@ pub unsafe fn direct_pg_extern_function_call<R: FromDatum>(
 pub unsafe fn direct_function_call_as_datum(
     func: unsafe fn(pg_sys::FunctionCallInfo) -> pg_sys::Datum,
     args: Vec<Option<pg_sys::Datum>>,
+) -> Option<pg_sys::Datum> {
+    direct_function_call_with_collation_as_datum(func, pg_sys::InvalidOid, args)
+}
+
+/// Same as [direct_function_call_with_collation] but instead returns the direct
+/// `Option<pg_sys::Datum>` instead of converting it to a value
+///
+/// ## Safety
+///
+/// This function is unsafe as the function you're calling is also unsafe
+pub unsafe fn direct_function_call_with_collation_as_datum(
+    func: unsafe fn(pg_sys::FunctionCallInfo) -> pg_sys::Datum,
+    collation: pg_sys::Oid,
+    args: Vec<Option<pg_sys::Datum>>,
 ) -> Option<pg_sys::Datum> {
     let mut null_array = [false; 100usize];
     let mut arg_array = [0 as pg_sys::Datum; 100usize];
@@ -347,7 +451,7 @@ pub unsafe fn direct_function_call_as_datum(
         }
     }
 
-    let mut fcid = make_function_call_info(nargs, arg_array, null_array);
+    let mut fcid = make_function_call_info(nargs, arg_array, null_array, collation);
     let datum = func(fcid.deref_mut());
 
     if fcid.isnull {
@@ -385,7 +489,7 @@ pub unsafe fn direct_pg_extern_function_call_as_datum(
         }
     }
 
-    let mut fcid = make_function_call_info(nargs, arg_array, null_array);
+    let mut fcid = make_function_call_info(nargs, arg_array, null_array, pg_sys::InvalidOid);
     let datum = func(fcid.deref_mut());
 
     if fcid.isnull {
@@ -400,11 +504,13 @@ fn make_function_call_info(
     nargs: usize,
     arg_array: [usize; 100],
     null_array: [bool; 100],
+    collation: pg_sys::Oid,
 ) -> PgBox<pg_sys::FunctionCallInfoData, AllocatedByRust> {
     let mut fcinfo_boxed = PgBox::<pg_sys::FunctionCallInfoData>::alloc0();
     let fcinfo = fcinfo_boxed.deref_mut();
 
     fcinfo.nargs = nargs as i16;
+    fcinfo.fncollation = collation;
     fcinfo.arg = arg_array;
     fcinfo.argnull = null_array;
 
@@ -416,6 +522,7 @@ fn make_function_call_info(
     nargs: usize,
     arg_array: [usize; 100],
     null_array: [bool; 100],
+    collation: pg_sys::Oid,
 ) -> PgBox<pg_sys::FunctionCallInfoBaseData, AllocatedByRust> {
     let fcid: *mut pg_sys::FunctionCallInfoBaseData = unsafe {
         pg_sys::palloc0(
@@ -429,6 +536,7 @@ fn make_function_call_info(
     let fcinfo = fcinfo_boxed.deref_mut();
 
     fcinfo.nargs = nargs as i16;
+    fcinfo.fncollation = collation;
 
     let slice = unsafe { fcinfo.args.as_mut_slice(nargs) };
     for i in 0..nargs {
@@ -0,0 +1,448 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Support for implementing [foreign-data wrappers](https://www.postgresql.org/docs/current/fdwhandler.html)
+//!
+//! Implement [`ForeignDataWrapper`] with `#[pg_fdw]`, which generates the wrapper's handler
+//! function, returning its [`fdw_routine`], and `CREATE FOREIGN DATA WRAPPER`.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//! use std::collections::HashMap;
+//!
+//! struct Numbers {
+//!     next: i32,
+//!     last: i32,
+//! }
+//!
+//! #[pg_fdw(name = "numbers_fdw")]
+//! impl ForeignDataWrapper for Numbers {
+//!     type Row = (i32, Option<String>);
+//!
+//!     fn new(_relation: &PgRelation, options: HashMap<String, String>) -> Self {
+//!         let last = options.get("last").map_or(10, |last| last.parse().unwrap());
+//!         Numbers { next: 1, last }
+//!     }
+//!
+//!     fn iterate_scan(&mut self) -> Option<Self::Row> {
+//!         if self.next > self.last {
+//!             return None;
+//!         }
+//!         self.next += 1;
+//!         Some((self.next - 1, None))
+//!     }
+//! }
+//! ```
+//!
+//! ```sql
+//! CREATE SERVER numbers_server FOREIGN DATA WRAPPER numbers_fdw;
+//! CREATE FOREIGN TABLE numbers (n int, name text) SERVER numbers_server OPTIONS (last '5');
+//! ```
+use crate::{
+    ereport, guard, pg_guard, pg_sys, IntoSpiRow, PgBox, PgList, PgLogLevel, PgMemoryContexts,
+    PgRelation, PgSqlErrorCode,
+};
+use std::collections::HashMap;
+use std::ffi::CStr;
+
+/// A foreign-data wrapper, one of which is created for each scan of, or insert into, a foreign
+/// table
+pub trait ForeignDataWrapper: Sized {
+    /// A row of the wrapper's tables, which is a tuple of a value for each of the table's
+    /// columns, such as `(i32, Option<String>)`, where `None` is NULL
+    type Row: IntoSpiRow;
+
+    /// If `INSERT` into the wrapper's tables is supported, by [`ForeignDataWrapper::insert`]
+    const SUPPORTS_INSERT: bool = false;
+
+    /// Create the wrapper for scanning or modifying `relation`
+    ///
+    /// `options` are those of the foreign-data wrapper, its server, and the table, with the
+    /// table's taking precedence over the server's, which take precedence over the wrapper's.
+    fn new(relation: &PgRelation, options: HashMap<String, String>) -> Self;
+
+    /// The planner's estimate of how many rows a scan of `relation` will return
+    fn get_rel_size(_relation: &PgRelation, _options: &HashMap<String, String>) -> f64 {
+        1000.0
+    }
+
+    /// Called before the first row is requested
+    ///
+    /// This isn't called by `EXPLAIN` without `ANALYZE`, which doesn't run the scan.
+    fn begin_scan(&mut self) {}
+
+    /// The next row, or `None` once there are no more rows
+    ///
+    /// Its values are converted in the per-row memory context, which is reset before the next
+    /// row is requested.
+    fn iterate_scan(&mut self) -> Option<Self::Row>;
+
+    /// Start the scan over again, from the first row
+    fn rescan(&mut self) {}
+
+    /// Called once the scan is finished, before the wrapper is dropped
+    fn end_scan(&mut self) {}
+
+    /// Called before the first row is inserted
+    fn begin_modify(&mut self) {}
+
+    /// Insert a row, with one value for each of the table's columns, which must be implemented
+    /// by a wrapper that [supports it](ForeignDataWrapper::SUPPORTS_INSERT)
+    fn insert(&mut self, _row: &[Option<pg_sys::Datum>]) {
+        ereport(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+            "foreign-data wrapper does not support INSERT",
+            file!(),
+            line!(),
+            column!(),
+        );
+    }
+
+    /// Called once all rows are inserted, before the wrapper is dropped
+    fn end_modify(&mut self) {}
+
+    /// The `CREATE FOREIGN TABLE` statements for `IMPORT FOREIGN SCHEMA`
+    ///
+    /// `stmt` names the remote and local schemas and the tables to import, or not, and
+    /// `server_options` are the options of the foreign server the tables are imported from.
+    /// Postgres itself filters the statements by the `LIMIT TO` or `EXCEPT` table list.
+    fn import_foreign_schema(
+        _stmt: &PgBox<pg_sys::ImportForeignSchemaStmt>,
+        _server_options: HashMap<String, String>,
+    ) -> Vec<String> {
+        ereport(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+            "foreign-data wrapper does not support IMPORT FOREIGN SCHEMA",
+            file!(),
+            line!(),
+            column!(),
+        );
+        unreachable!()
+    }
+}
+
+/// The `FdwRoutine` for `T`, to be returned by the wrapper's handler function, which `#[pg_fdw]`
+/// generates
+pub fn fdw_routine<T: ForeignDataWrapper>() -> PgBox<pg_sys::FdwRoutine> {
+    let mut routine = PgBox::<pg_sys::FdwRoutine>::alloc_node(pg_sys::NodeTag_T_FdwRoutine);
+
+    routine.GetForeignRelSize = Some(get_foreign_rel_size::<T>);
+    routine.GetForeignPaths = Some(get_foreign_paths);
+    routine.GetForeignPlan = Some(get_foreign_plan);
+    routine.BeginForeignScan = Some(begin_foreign_scan::<T>);
+    routine.IterateForeignScan = Some(iterate_foreign_scan::<T>);
+    routine.ReScanForeignScan = Some(rescan_foreign_scan::<T>);
+    routine.EndForeignScan = Some(end_foreign_scan::<T>);
+    routine.ImportForeignSchema = Some(import_foreign_schema::<T>);
+
+    routine.IsForeignRelUpdatable = Some(is_foreign_rel_updatable::<T>);
+    if T::SUPPORTS_INSERT {
+        routine.BeginForeignModify = Some(begin_foreign_modify::<T>);
+        routine.ExecForeignInsert = Some(exec_foreign_insert::<T>);
+        routine.EndForeignModify = Some(end_foreign_modify::<T>);
+    }
+
+    routine.into_pg_boxed()
+}
+
+/// The options of foreign table `relid`, merged with those of its server and wrapper
+unsafe fn table_options(relid: pg_sys::Oid) -> HashMap<String, String> {
+    let table = pg_sys::GetForeignTable(relid);
+    let server = pg_sys::GetForeignServer((*table).serverid);
+    let wrapper = pg_sys::GetForeignDataWrapper((*server).fdwid);
+
+    let mut options = HashMap::new();
+    for list in [(*wrapper).options, (*server).options, (*table).options] {
+        add_options(&mut options, list);
+    }
+    options
+}
+
+unsafe fn add_options(options: &mut HashMap<String, String>, list: *mut pg_sys::List) {
+    for option in PgList::<pg_sys::DefElem>::from_pg(list).iter_ptr() {
+        let name = CStr::from_ptr((*option).defname).to_string_lossy();
+        let value = CStr::from_ptr(pg_sys::defGetString(option)).to_string_lossy();
+        options.insert(name.into_owned(), value.into_owned());
+    }
+}
+
+// `#[pg_guard]` can't wrap generic functions, so those for the wrapper guard themselves
+
+unsafe extern "C" fn get_foreign_rel_size<T: ForeignDataWrapper>(
+    _root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    foreigntableid: pg_sys::Oid,
+) {
+    guard::guard(|| {
+        let relation = PgRelation::with_lock(foreigntableid, pg_sys::AccessShareLock as _);
+        (*baserel).rows = T::get_rel_size(&relation, &table_options(foreigntableid));
+    })
+}
+
+#[pg_guard]
+unsafe extern "C" fn get_foreign_paths(
+    root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    _foreigntableid: pg_sys::Oid,
+) {
+    // the one way of scanning the table, which costs one for each row
+    let rows = (*baserel).rows;
+    let path = pg_sys::create_foreignscan_path(
+        root,
+        baserel,
+        (*baserel).reltarget,
+        rows,
+        0.0,
+        rows,
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+    );
+    pg_sys::add_path(baserel, path as *mut pg_sys::Path);
+}
+
+#[pg_guard]
+unsafe extern "C" fn get_foreign_plan(
+    _root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    _foreigntableid: pg_sys::Oid,
+    best_path: *mut pg_sys::ForeignPath,
+    tlist: *mut pg_sys::List,
+    scan_clauses: *mut pg_sys::List,
+    outer_plan: *mut pg_sys::Plan,
+) -> *mut pg_sys::ForeignScan {
+    // Postgres checks every clause against the rows the wrapper returns
+    pg_sys::make_foreignscan(
+        tlist,
+        pg_sys::extract_actual_clauses(scan_clauses, false),
+        (*baserel).relid,
+        std::ptr::null_mut(),
+        (*best_path).fdw_private,
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        outer_plan,
+    )
+}
+
+unsafe extern "C" fn begin_foreign_scan<T: ForeignDataWrapper>(
+    node: *mut pg_sys::ForeignScanState,
+    eflags: i32,
+) {
+    guard::guard(|| {
+        if eflags & pg_sys::EXEC_FLAG_EXPLAIN_ONLY as i32 != 0 {
+            return;
+        }
+
+        let relation = PgRelation::from_pg((*node).ss.ss_currentRelation);
+        check_row_type::<T>(&relation);
+        let mut wrapper = T::new(&relation, table_options(relation.oid()));
+        wrapper.begin_scan();
+        (*node).fdw_state = Box::into_raw(Box::new(wrapper)) as *mut std::os::raw::c_void;
+    })
+}
+
+unsafe extern "C" fn iterate_foreign_scan<T: ForeignDataWrapper>(
+    node: *mut pg_sys::ForeignScanState,
+) -> *mut pg_sys::TupleTableSlot {
+    guard::guard(|| {
+        let wrapper = &mut *((*node).fdw_state as *mut T);
+        let slot = (*node).ss.ss_ScanTupleSlot;
+
+        // an empty slot tells the executor the scan is done
+        clear_slot(slot);
+        let row = PgMemoryContexts::For((*(*node).ss.ps.ps_ExprContext).ecxt_per_tuple_memory)
+            .switch_to(|_| wrapper.iterate_scan().map(IntoSpiRow::into_spi_row));
+        if let Some(row) = row {
+            // the row's values are those of the table's columns which haven't been dropped
+            let relation = PgRelation::from_pg((*node).ss.ss_currentRelation);
+            let mut values = row.into_iter();
+            for (i, att) in relation.tuple_desc().iter().enumerate() {
+                let value = if att.attisdropped {
+                    None
+                } else {
+                    values.next().flatten()
+                };
+                *(*slot).tts_values.add(i) = value.unwrap_or(0);
+                *(*slot).tts_isnull.add(i) = value.is_none();
+            }
+            pg_sys::ExecStoreVirtualTuple(slot);
+        }
+        slot
+    })
+}
+
+/// Raise an ERROR unless `T::Row` has a value of the type of each of `relation`'s columns, or of
+/// the base type of a column's domain
+fn check_row_type<T: ForeignDataWrapper>(relation: &PgRelation) {
+    let tupdesc = relation.tuple_desc();
+    let columns = tupdesc
+        .iter()
+        .filter(|att| !att.attisdropped)
+        .map(|att| att.atttypid)
+        .collect::<Vec<_>>();
+    let values = T::Row::type_oids();
+    let matches = columns.len() == values.len()
+        && columns.iter().zip(&values).all(|(&column, &value)| {
+            value == column || value == unsafe { pg_sys::getBaseType(column) }
+        });
+    if !matches {
+        ereport(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_FDW_INVALID_DATA_TYPE,
+            &format!(
+                "foreign-data wrapper row types {:?} don't match the column types {:?} of \"{}\"",
+                values,
+                columns,
+                relation.name()
+            ),
+            file!(),
+            line!(),
+            column!(),
+        );
+    }
+}
+
+unsafe extern "C" fn rescan_foreign_scan<T: ForeignDataWrapper>(
+    node: *mut pg_sys::ForeignScanState,
+) {
+    guard::guard(|| {
+        let wrapper = &mut *((*node).fdw_state as *mut T);
+        wrapper.rescan();
+    })
+}
+
+unsafe extern "C" fn end_foreign_scan<T: ForeignDataWrapper>(node: *mut pg_sys::ForeignScanState) {
+    guard::guard(|| {
+        if (*node).fdw_state.is_null() {
+            // it was only explained
+            return;
+        }
+
+        let mut wrapper = Box::from_raw((*node).fdw_state as *mut T);
+        (*node).fdw_state = std::ptr::null_mut();
+        wrapper.end_scan();
+    })
+}
+
+unsafe extern "C" fn is_foreign_rel_updatable<T: ForeignDataWrapper>(
+    _rel: pg_sys::Relation,
+) -> i32 {
+    guard::guard(|| {
+        if T::SUPPORTS_INSERT {
+            1 << pg_sys::CmdType_CMD_INSERT
+        } else {
+            0
+        }
+    })
+}
+
+unsafe extern "C" fn begin_foreign_modify<T: ForeignDataWrapper>(
+    _mtstate: *mut pg_sys::ModifyTableState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+    _fdw_private: *mut pg_sys::List,
+    _subplan_index: i32,
+    eflags: i32,
+) {
+    guard::guard(|| {
+        if eflags & pg_sys::EXEC_FLAG_EXPLAIN_ONLY as i32 != 0 {
+            return;
+        }
+
+        let relation = PgRelation::from_pg((*rinfo).ri_RelationDesc);
+        let mut wrapper = T::new(&relation, table_options(relation.oid()));
+        wrapper.begin_modify();
+        (*rinfo).ri_FdwState = Box::into_raw(Box::new(wrapper)) as *mut std::os::raw::c_void;
+    })
+}
+
+unsafe extern "C" fn exec_foreign_insert<T: ForeignDataWrapper>(
+    _estate: *mut pg_sys::EState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+    slot: *mut pg_sys::TupleTableSlot,
+    _plan_slot: *mut pg_sys::TupleTableSlot,
+) -> *mut pg_sys::TupleTableSlot {
+    guard::guard(|| {
+        let wrapper = &mut *((*rinfo).ri_FdwState as *mut T);
+
+        slot_getallattrs(slot);
+        let natts = (*(*slot).tts_tupleDescriptor).natts as usize;
+        let row = (0..natts)
+            .map(|i| {
+                if *(*slot).tts_isnull.add(i) {
+                    None
+                } else {
+                    Some(*(*slot).tts_values.add(i))
+                }
+            })
+            .collect::<Vec<_>>();
+        wrapper.insert(&row);
+        slot
+    })
+}
+
+unsafe extern "C" fn end_foreign_modify<T: ForeignDataWrapper>(
+    _estate: *mut pg_sys::EState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+) {
+    guard::guard(|| {
+        if (*rinfo).ri_FdwState.is_null() {
+            // it was only explained
+            return;
+        }
+
+        let mut wrapper = Box::from_raw((*rinfo).ri_FdwState as *mut T);
+        (*rinfo).ri_FdwState = std::ptr::null_mut();
+        wrapper.end_modify();
+    })
+}
+
+unsafe extern "C" fn import_foreign_schema<T: ForeignDataWrapper>(
+    stmt: *mut pg_sys::ImportForeignSchemaStmt,
+    server_oid: pg_sys::Oid,
+) -> *mut pg_sys::List {
+    guard::guard(|| {
+        let server = pg_sys::GetForeignServer(server_oid);
+        let mut server_options = HashMap::new();
+        add_options(&mut server_options, (*server).options);
+
+        let mut statements = PgList::<std::os::raw::c_char>::new();
+        for statement in T::import_foreign_schema(&PgBox::from_pg(stmt), server_options) {
+            statements.push(PgMemoryContexts::CurrentMemoryContext.pstrdup(&statement));
+        }
+        statements.into_pg()
+    })
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11"))]
+unsafe fn clear_slot(slot: *mut pg_sys::TupleTableSlot) {
+    pg_sys::ExecClearTuple(slot);
+}
+
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+unsafe fn clear_slot(slot: *mut pg_sys::TupleTableSlot) {
+    // `ExecClearTuple()` is an inline function since Postgres 12
+    ((*(*slot).tts_ops).clear.unwrap())(slot);
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11"))]
+unsafe fn slot_getallattrs(slot: *mut pg_sys::TupleTableSlot) {
+    pg_sys::slot_getallattrs(slot);
+}
+
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+unsafe fn slot_getallattrs(slot: *mut pg_sys::TupleTableSlot) {
+    // `slot_getallattrs()` is an inline function since Postgres 12
+    let natts = (*(*slot).tts_tupleDescriptor).natts;
+    if ((*slot).tts_nvalid as i32) < natts {
+        pg_sys::slot_getsomeattrs_int(slot, natts);
+    }
+}
@@ -0,0 +1,107 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! [Generic WAL records](https://www.postgresql.org/docs/current/generic-wal.html), for making
+//! crash-safe, replicated changes to the pages of an extension's own relations, such as those of
+//! a custom index access method
+//!
+//! A change is made to copies of up to 4 pages, which are written back to their buffers, and
+//! WAL-logged as the difference between the old and new pages, all at once, when it's finished:
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! /// Add an empty page to the end of `index`, which is locked against others adding one
+//! fn add_page(index: &PgRelation) -> pg_sys::BlockNumber {
+//!     unsafe {
+//!         let buffer = pg_sys::ReadBuffer(index.as_ptr(), pg_sys::InvalidBlockNumber);
+//!         pg_sys::LockBuffer(buffer, pg_sys::BUFFER_LOCK_EXCLUSIVE as i32);
+//!
+//!         let mut xlog = GenericXLog::start(index);
+//!         let page = xlog.register_buffer(buffer, true);
+//!         pg_sys::PageInit(page, pg_sys::BLCKSZ as usize, 0);
+//!         xlog.finish();
+//!
+//!         let block = pg_sys::BufferGetBlockNumber(buffer);
+//!         pg_sys::UnlockReleaseBuffer(buffer);
+//!         block
+//!     }
+//! }
+//! ```
+//!
+//! Replaying a generic record doesn't need the extension, so a replica applies its changes
+//! without it being installed.
+use crate::{pg_sys, PgRelation};
+use std::marker::PhantomData;
+
+/// A change to the pages of one relation, which is WAL-logged as one generic record when it's
+/// [finished](GenericXLog::finish), and is thrown away if it's dropped first
+pub struct GenericXLog<'a> {
+    state: *mut pg_sys::GenericXLogState,
+    _relation: PhantomData<&'a PgRelation>,
+}
+
+impl<'a> GenericXLog<'a> {
+    /// Start changing the pages of `relation`
+    ///
+    /// The pages of an unlogged or temporary relation are changed too, but without writing WAL.
+    pub fn start(relation: &'a PgRelation) -> Self {
+        GenericXLog {
+            state: unsafe { pg_sys::GenericXLogStart(relation.as_ptr()) },
+            _relation: PhantomData,
+        }
+    }
+
+    /// Register `buffer`, of the relation, returning a copy of its page, which is changed instead
+    /// of the buffer's, until the change is finished
+    ///
+    /// Set `full_image` for a new page, or one that's rewritten entirely, which is then logged as
+    /// a whole, rather than by how it changed.  Postgres raises an ERROR if more than 4 buffers
+    /// are registered.
+    ///
+    /// ## Safety
+    ///
+    /// `buffer` must be pinned and locked exclusively, until the change is finished or dropped.
+    /// The page is only valid until then, and its header, `pd_lower`, and `pd_upper` must be
+    /// kept valid, as only the page's contents outside the hole between `pd_lower` and
+    /// `pd_upper` are logged.
+    pub unsafe fn register_buffer(
+        &mut self,
+        buffer: pg_sys::Buffer,
+        full_image: bool,
+    ) -> pg_sys::Page {
+        let flags = if full_image {
+            pg_sys::GENERIC_XLOG_FULL_IMAGE as i32
+        } else {
+            0
+        };
+        pg_sys::GenericXLogRegisterBuffer(self.state, buffer, flags)
+    }
+
+    /// Write the changed pages to their buffers, marking them dirty, and WAL-log the change,
+    /// returning the LSN of its record, which is `InvalidXLogRecPtr` (0) for a relation which
+    /// isn't WAL-logged
+    pub fn finish(mut self) -> pg_sys::XLogRecPtr {
+        let state = std::mem::replace(&mut self.state, std::ptr::null_mut());
+        unsafe { pg_sys::GenericXLogFinish(state) }
+    }
+
+    /// Throw the change away, leaving the buffers as they were
+    pub fn abort(self) {
+        // dropping does
+    }
+}
+
+impl Drop for GenericXLog<'_> {
+    fn drop(&mut self) {
+        if !self.state.is_null() {
+            unsafe { pg_sys::GenericXLogAbort(self.state) }
+        }
+    }
+}
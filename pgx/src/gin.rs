@@ -0,0 +1,152 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Support for implementing [GIN](https://www.postgresql.org/docs/current/gin.html) operator classes.
+//!
+//! ```rust,ignore
+//! use pgx::*;
+//!
+//! #[pg_gin_opclass]
+//! impl GinOpClass for Tags {
+//!     type Key = String;
+//!     type Query = Tags;
+//!     const OPERATORS: &'static [(u16, &'static str)] = &[(1, "@>")];
+//!
+//!     fn extract_value(value: Self) -> Vec<Option<String>> {
+//!         value.0.into_iter().map(Some).collect()
+//!     }
+//!
+//!     fn extract_query(query: Tags, _strategy: u16) -> (Vec<Option<String>>, GinSearchMode) {
+//!         (query.0.into_iter().map(Some).collect(), GinSearchMode::Default)
+//!     }
+//!
+//!     fn consistent(check: &[bool], _strategy: u16, _query: Tags) -> (bool, bool) {
+//!         (check.iter().all(|c| *c), false)
+//!     }
+//! }
+//! ```
+use crate::{pg_getarg, pg_getarg_pointer, pg_sys, FromDatum, IntoDatum};
+
+/// How a GIN index should be scanned for a query, as returned from [`GinOpClass::extract_query`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GinSearchMode {
+    /// Only items matching at least one of the returned keys are candidates.
+    Default = pg_sys::GIN_SEARCH_MODE_DEFAULT as isize,
+    /// Items containing no keys are also candidates.
+    IncludeEmpty = pg_sys::GIN_SEARCH_MODE_INCLUDE_EMPTY as isize,
+    /// Every non-null item is a candidate.
+    All = pg_sys::GIN_SEARCH_MODE_ALL as isize,
+    /// Every item, including null items, is a candidate.
+    Everything = pg_sys::GIN_SEARCH_MODE_EVERYTHING as isize,
+}
+
+impl Default for GinSearchMode {
+    fn default() -> Self {
+        GinSearchMode::Default
+    }
+}
+
+/// A GIN operator class for `Self`, to be used with the `#[pg_gin_opclass]` macro.
+///
+/// The `Key` type must have a default `btree` operator class, which is used to order keys inside
+/// the index.
+pub trait GinOpClass: FromDatum + Sized {
+    /// The type of the keys extracted from indexed values, the `STORAGE` of the operator class.
+    type Key: FromDatum + IntoDatum;
+
+    /// The type of the right-hand side of the operators supported by this operator class.
+    type Query: FromDatum;
+
+    /// The operators supported by this operator class, as `(strategy number, operator name)` pairs.
+    const OPERATORS: &'static [(u16, &'static str)];
+
+    /// If this is the default GIN operator class for the type.
+    const DEFAULT: bool = true;
+
+    /// Returns the keys of an indexed value.
+    fn extract_value(value: Self) -> Vec<Option<Self::Key>>;
+
+    /// Returns the keys of a query for the given operator `strategy`, and how the index should be searched.
+    fn extract_query(query: Self::Query, strategy: u16) -> (Vec<Option<Self::Key>>, GinSearchMode);
+
+    /// Given which of the query's keys are present in an indexed item (`check`), return if the
+    /// item matches, and if that match needs to be rechecked against the heap tuple.
+    fn consistent(check: &[bool], strategy: u16, query: Self::Query) -> (bool, bool);
+}
+
+#[doc(hidden)]
+pub unsafe fn gin_extract_value<T: GinOpClass>(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    let keys = pg_getarg::<T>(fcinfo, 0)
+        .map(T::extract_value)
+        .unwrap_or_default();
+    keys_into_datums(
+        keys,
+        pg_getarg_pointer::<i32>(fcinfo, 1).expect("GIN `nkeys` argument was null"),
+        pg_getarg_pointer::<*mut bool>(fcinfo, 2),
+    )
+}
+
+#[doc(hidden)]
+pub unsafe fn gin_extract_query<T: GinOpClass>(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    let strategy = pg_getarg::<i16>(fcinfo, 2).expect("GIN `strategy` argument was null") as u16;
+    let (keys, search_mode) = match pg_getarg::<T::Query>(fcinfo, 0) {
+        Some(query) => T::extract_query(query, strategy),
+        None => (Vec::new(), GinSearchMode::Default),
+    };
+    if let Some(search_mode_ptr) = pg_getarg_pointer::<i32>(fcinfo, 6) {
+        *search_mode_ptr = search_mode as i32;
+    }
+    keys_into_datums(
+        keys,
+        pg_getarg_pointer::<i32>(fcinfo, 1).expect("GIN `nkeys` argument was null"),
+        pg_getarg_pointer::<*mut bool>(fcinfo, 5),
+    )
+}
+
+#[doc(hidden)]
+pub unsafe fn gin_consistent<T: GinOpClass>(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    let check = pg_getarg_pointer::<bool>(fcinfo, 0).expect("GIN `check` argument was null");
+    let strategy = pg_getarg::<i16>(fcinfo, 1).expect("GIN `strategy` argument was null") as u16;
+    let nkeys = pg_getarg::<i32>(fcinfo, 3).expect("GIN `nkeys` argument was null");
+    let recheck = pg_getarg_pointer::<bool>(fcinfo, 5).expect("GIN `recheck` argument was null");
+
+    let check = std::slice::from_raw_parts(check, nkeys as usize);
+    let (matches, needs_recheck) = match pg_getarg::<T::Query>(fcinfo, 2) {
+        Some(query) => T::consistent(check, strategy, query),
+        None => (false, false),
+    };
+    *recheck = needs_recheck;
+    matches.into_datum().unwrap()
+}
+
+/// Copy `keys` into a palloc'd `Datum` array, as GIN expects from its extract functions.
+unsafe fn keys_into_datums<K: IntoDatum>(
+    keys: Vec<Option<K>>,
+    nkeys: *mut i32,
+    null_flags: Option<*mut *mut bool>,
+) -> pg_sys::Datum {
+    *nkeys = keys.len() as i32;
+    if keys.is_empty() {
+        return 0 as pg_sys::Datum;
+    }
+
+    let datums =
+        pg_sys::palloc0(keys.len() * std::mem::size_of::<pg_sys::Datum>()) as *mut pg_sys::Datum;
+    let nulls = pg_sys::palloc0(keys.len() * std::mem::size_of::<bool>()) as *mut bool;
+    for (i, key) in keys.into_iter().enumerate() {
+        match key.and_then(|key| key.into_datum()) {
+            Some(datum) => *datums.add(i) = datum,
+            None => *nulls.add(i) = true,
+        }
+    }
+    if let Some(null_flags) = null_flags {
+        *null_flags = nulls;
+    }
+    datums as pg_sys::Datum
+}
@@ -0,0 +1,217 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Support for implementing [GiST](https://www.postgresql.org/docs/current/gist.html) operator classes.
+//!
+//! ```rust,ignore
+//! use pgx::*;
+//!
+//! #[pg_gist_opclass]
+//! impl GistOpClass for Point2 {
+//!     type Key = BoundingBox;
+//!     type Query = BoundingBox;
+//!     const OPERATORS: &'static [(u16, &'static str)] = &[(3, "&&")];
+//!
+//!     fn compress(value: Self) -> BoundingBox { BoundingBox::from_point(value) }
+//!     fn consistent(key: BoundingBox, query: BoundingBox, _strategy: u16, is_leaf: bool) -> (bool, bool) {
+//!         (key.overlaps(&query), false)
+//!     }
+//!     fn union(keys: Vec<BoundingBox>) -> BoundingBox { BoundingBox::union_all(keys) }
+//!     fn penalty(original: BoundingBox, new: BoundingBox) -> f32 { original.enlargement(&new) }
+//!     fn picksplit(keys: &[BoundingBox]) -> (Vec<usize>, Vec<usize>) { BoundingBox::split(keys) }
+//!     fn same(a: BoundingBox, b: BoundingBox) -> bool { a == b }
+//! }
+//! ```
+use crate::{pg_getarg, pg_getarg_pointer, pg_sys, FromDatum, IntoDatum, PgBox};
+
+/// A GiST operator class for `Self`, to be used with the `#[pg_gist_opclass]` macro.
+pub trait GistOpClass: FromDatum + Sized {
+    /// The type of the keys stored in the index, the `STORAGE` of the operator class.
+    type Key: FromDatum + IntoDatum;
+
+    /// The type of the right-hand side of the operators supported by this operator class.
+    type Query: FromDatum;
+
+    /// The operators supported by this operator class, as `(strategy number, operator name)` pairs.
+    const OPERATORS: &'static [(u16, &'static str)];
+
+    /// If this is the default GiST operator class for the type.
+    const DEFAULT: bool = true;
+
+    /// Convert an indexed value into its index key.
+    fn compress(value: Self) -> Self::Key;
+
+    /// Return if `key` may match `query` for the given operator `strategy`, and if that match needs
+    /// to be rechecked against the heap tuple.
+    ///
+    /// `is_leaf` is true when `key` was produced by [`GistOpClass::compress`] rather than by
+    /// [`GistOpClass::union`].
+    fn consistent(key: Self::Key, query: Self::Query, strategy: u16, is_leaf: bool)
+        -> (bool, bool);
+
+    /// Return a key which is consistent with every one of `keys`.
+    fn union(keys: Vec<Self::Key>) -> Self::Key;
+
+    /// The cost of inserting `new` into the subtree described by `original`.
+    fn penalty(original: Self::Key, new: Self::Key) -> f32;
+
+    /// Split `keys` into two groups, returned as the indexes into `keys` for the left and right pages.
+    fn picksplit(keys: &[Self::Key]) -> (Vec<usize>, Vec<usize>);
+
+    /// Return if the two keys are identical.
+    fn same(a: Self::Key, b: Self::Key) -> bool;
+}
+
+#[doc(hidden)]
+pub unsafe fn gist_consistent<T: GistOpClass>(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    let entry =
+        pg_getarg_pointer::<pg_sys::GISTENTRY>(fcinfo, 0).expect("GiST `entry` argument was null");
+    let strategy = pg_getarg::<i16>(fcinfo, 2).expect("GiST `strategy` argument was null") as u16;
+    let recheck = pg_getarg_pointer::<bool>(fcinfo, 4).expect("GiST `recheck` argument was null");
+
+    let is_leaf = gist_page_is_leaf(&*entry);
+    let key = T::Key::from_datum((*entry).key, false, pg_sys::InvalidOid);
+    let (matches, needs_recheck) = match (key, pg_getarg::<T::Query>(fcinfo, 1)) {
+        (Some(key), Some(query)) => T::consistent(key, query, strategy, is_leaf),
+        _ => (false, false),
+    };
+    *recheck = needs_recheck;
+    matches.into_datum().unwrap()
+}
+
+#[doc(hidden)]
+pub unsafe fn gist_union<T: GistOpClass>(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    let keys = entry_vector_keys::<T>(fcinfo, 0);
+    match T::union(keys).into_datum() {
+        Some(datum) => datum,
+        None => crate::pg_return_null(fcinfo),
+    }
+}
+
+#[doc(hidden)]
+pub unsafe fn gist_compress<T: GistOpClass>(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    let entry =
+        pg_getarg_pointer::<pg_sys::GISTENTRY>(fcinfo, 0).expect("GiST `entry` argument was null");
+    if !(*entry).leafkey {
+        return entry as pg_sys::Datum;
+    }
+
+    let key = T::from_datum((*entry).key, false, pg_sys::InvalidOid)
+        .map(T::compress)
+        .and_then(|key| key.into_datum())
+        .unwrap_or(0);
+    let mut compressed = PgBox::<pg_sys::GISTENTRY>::alloc0();
+    compressed.key = key;
+    compressed.rel = (*entry).rel;
+    compressed.page = (*entry).page;
+    compressed.offset = (*entry).offset;
+    compressed.leafkey = false;
+    compressed.into_pg() as pg_sys::Datum
+}
+
+#[doc(hidden)]
+pub unsafe fn gist_decompress<T: GistOpClass>(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    // keys are stored as-is, so there's nothing to decompress
+    pg_getarg_pointer::<pg_sys::GISTENTRY>(fcinfo, 0).expect("GiST `entry` argument was null")
+        as pg_sys::Datum
+}
+
+#[doc(hidden)]
+pub unsafe fn gist_penalty<T: GistOpClass>(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    let original = pg_getarg_pointer::<pg_sys::GISTENTRY>(fcinfo, 0)
+        .expect("GiST `original` argument was null");
+    let new =
+        pg_getarg_pointer::<pg_sys::GISTENTRY>(fcinfo, 1).expect("GiST `new` argument was null");
+    let penalty = pg_getarg_pointer::<f32>(fcinfo, 2).expect("GiST `penalty` argument was null");
+
+    *penalty = match (
+        T::Key::from_datum((*original).key, false, pg_sys::InvalidOid),
+        T::Key::from_datum((*new).key, false, pg_sys::InvalidOid),
+    ) {
+        (Some(original), Some(new)) => T::penalty(original, new),
+        _ => 0.0,
+    };
+    penalty as pg_sys::Datum
+}
+
+#[doc(hidden)]
+pub unsafe fn gist_picksplit<T: GistOpClass>(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    let splitvec = pg_getarg_pointer::<pg_sys::GIST_SPLITVEC>(fcinfo, 1)
+        .expect("GiST `splitvec` argument was null");
+
+    // picksplit entries are numbered starting at `FirstOffsetNumber`
+    let keys = entry_vector_keys::<T>(fcinfo, pg_sys::FirstOffsetNumber as usize);
+    let (left, right) = T::picksplit(&keys);
+
+    let mut keys = keys.into_iter().map(Some).collect::<Vec<_>>();
+    let mut take = |indexes: &[usize]| -> (*mut pg_sys::OffsetNumber, Vec<T::Key>) {
+        let offsets =
+            pg_sys::palloc0((indexes.len() + 1) * std::mem::size_of::<pg_sys::OffsetNumber>())
+                as *mut pg_sys::OffsetNumber;
+        let mut group = Vec::with_capacity(indexes.len());
+        for (i, idx) in indexes.iter().enumerate() {
+            *offsets.add(i) = (*idx + pg_sys::FirstOffsetNumber as usize) as pg_sys::OffsetNumber;
+            group.push(
+                keys[*idx]
+                    .take()
+                    .expect("GiST picksplit returned the same key more than once"),
+            );
+        }
+        (offsets, group)
+    };
+    let (left_offsets, left_keys) = take(&left);
+    let (right_offsets, right_keys) = take(&right);
+
+    (*splitvec).spl_left = left_offsets;
+    (*splitvec).spl_nleft = left.len() as i32;
+    (*splitvec).spl_ldatum = T::union(left_keys).into_datum().unwrap_or(0);
+    (*splitvec).spl_right = right_offsets;
+    (*splitvec).spl_nright = right.len() as i32;
+    (*splitvec).spl_rdatum = T::union(right_keys).into_datum().unwrap_or(0);
+    splitvec as pg_sys::Datum
+}
+
+#[doc(hidden)]
+pub unsafe fn gist_same<T: GistOpClass>(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    let result = pg_getarg_pointer::<bool>(fcinfo, 2).expect("GiST `result` argument was null");
+    *result = match (
+        pg_getarg::<T::Key>(fcinfo, 0),
+        pg_getarg::<T::Key>(fcinfo, 1),
+    ) {
+        (Some(a), Some(b)) => T::same(a, b),
+        (None, None) => true,
+        _ => false,
+    };
+    result as pg_sys::Datum
+}
+
+/// The keys of the `GistEntryVector` in argument zero, starting at entry `first`.
+unsafe fn entry_vector_keys<T: GistOpClass>(
+    fcinfo: pg_sys::FunctionCallInfo,
+    first: usize,
+) -> Vec<T::Key> {
+    let entryvec = pg_getarg_pointer::<pg_sys::GistEntryVector>(fcinfo, 0)
+        .expect("GiST `entryvec` argument was null");
+    let entries = (*entryvec).vector.as_slice((*entryvec).n as usize);
+    entries
+        .iter()
+        .skip(first)
+        .map(|entry| {
+            T::Key::from_datum(entry.key, false, pg_sys::InvalidOid).expect("GiST key was null")
+        })
+        .collect()
+}
+
+/// Equivalent to Postgres' `GIST_LEAF()` macro.
+unsafe fn gist_page_is_leaf(entry: &pg_sys::GISTENTRY) -> bool {
+    let page = entry.page as *mut u8;
+    let header = page as *mut pg_sys::PageHeaderData;
+    let opaque = page.add((*header).pd_special as usize) as *mut pg_sys::GISTPageOpaqueData;
+    (*opaque).flags as u32 & pg_sys::F_LEAF != 0
+}
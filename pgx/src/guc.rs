@@ -8,10 +8,61 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 //! Provides a safe interface into Postgres' Configuration System (GUC)
-use crate::{pg_sys, PgMemoryContexts};
-use std::cell::Cell;
-use std::ffi::CStr;
+//!
+//! A GUC defined by one of [`GucRegistry`]'s `define_*_guc_with_hooks` functions has
+//! [`GucHooks`], which can reject an invalid value when it's `SET`, rather than when it's first
+//! used:
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! static BATCH_SIZE: GucSetting<i32> = GucSetting::new(64);
+//!
+//! struct PowerOfTwo;
+//! impl GucHooks<i32> for PowerOfTwo {
+//!     fn check(value: &i32) -> Result<(), String> {
+//!         match (*value as u32).is_power_of_two() {
+//!             true => Ok(()),
+//!             false => Err(format!("{} is not a power of two", value)),
+//!         }
+//!     }
+//! }
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     GucRegistry::define_int_guc_with_hooks::<PowerOfTwo>(
+//!         "my_ext.batch_size",
+//!         "The number of rows in a batch",
+//!         "The number of rows in a batch, which must be a power of two",
+//!         &BATCH_SIZE,
+//!         1,
+//!         1024,
+//!         GucContext::Userset,
+//!         GucFlags::default(),
+//!     );
+//!     GucRegistry::reserve_prefix("my_ext");
+//! }
+//! ```
+//!
+//! A struct which is `#[derive(PgConfig)]` declares a GUC for each of its fields, which its
+//! [`PgConfig::snapshot`] reads together.
+use crate::hooks::{register_hook, HookResult, JumbleState, PgHooks};
+use crate::{
+    guard, pg_sys, sql, table_column, IntoDatum, PgBox, PgBuiltInOids, PgList, PgMemoryContexts,
+    Spi, TableColumn, TableRow,
+};
+use std::cell::{Cell, UnsafeCell};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_void};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
+/// Where, and by whom, a GUC can be set
+///
+/// A security-relevant GUC, which ordinary users mustn't be able to set, should be
+/// [`GucContext::Suset`], or one of the contexts before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GucContext {
     /// cannot be set by the user at all, but only through
     /// internal processes ("server_version" is an example).  These are GUC
@@ -20,6 +71,9 @@ pub enum GucContext {
 
     /// can only be set when the postmaster starts,
     /// either from the configuration file or the command line.
+    ///
+    /// Such a GUC can only be defined by an extension in `shared_preload_libraries`, from its
+    /// `_PG_init()`.
     Postmaster = pg_sys::GucContext_PGC_POSTMASTER as isize,
 
     /// can only be set at postmaster startup or by changing
@@ -50,6 +104,54 @@ pub enum GucContext {
     Userset = pg_sys::GucContext_PGC_USERSET as isize,
 }
 
+bitflags! {
+    /// Flags for a GUC, which are [`GucFlags::default()`] when it has none
+    #[derive(Default)]
+    pub struct GucFlags: i32 {
+        /// The GUC isn't shown by `SHOW ALL`, nor in `pg_settings`
+        const NO_SHOW_ALL = pg_sys::GUC_NO_SHOW_ALL as i32;
+        /// The GUC isn't reset by `RESET ALL`
+        const NO_RESET_ALL = pg_sys::GUC_NO_RESET_ALL as i32;
+        /// The GUC isn't in `postgresql.conf.sample`, which is only informational for an
+        /// extension's GUCs
+        const NOT_IN_SAMPLE = pg_sys::GUC_NOT_IN_SAMPLE as i32;
+        /// The GUC can't be set in `postgresql.conf`
+        const DISALLOW_IN_FILE = pg_sys::GUC_DISALLOW_IN_FILE as i32;
+        /// The GUC can't be set by `ALTER SYSTEM`
+        const DISALLOW_IN_AUTO_FILE = pg_sys::GUC_DISALLOW_IN_AUTO_FILE as i32;
+        /// The GUC's setting can only be seen by superusers, and members of
+        /// `pg_read_all_settings`
+        ///
+        /// This doesn't keep others from setting it, which its [`GucContext`] does.
+        const SUPERUSER_ONLY = pg_sys::GUC_SUPERUSER_ONLY as i32;
+        /// The GUC can't be set within a security-restricted operation, such as a function
+        /// run by `REFRESH MATERIALIZED VIEW`
+        const NOT_WHILE_SEC_REST = pg_sys::GUC_NOT_WHILE_SEC_REST as i32;
+        /// The GUC is shown by `EXPLAIN (SETTINGS)` when it isn't its default
+        #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+        const EXPLAIN = pg_sys::GUC_EXPLAIN as i32;
+        /// An integer or float GUC's value is in kilobytes, and it may be set with any unit of
+        /// memory, as in `'64MB'`
+        const UNIT_KB = pg_sys::GUC_UNIT_KB as i32;
+        /// An integer or float GUC's value is in blocks, of `BLCKSZ`
+        const UNIT_BLOCKS = pg_sys::GUC_UNIT_BLOCKS as i32;
+        /// An integer or float GUC's value is in WAL blocks, of `XLOG_BLCKSZ`
+        const UNIT_XBLOCKS = pg_sys::GUC_UNIT_XBLOCKS as i32;
+        /// An integer or float GUC's value is in megabytes
+        const UNIT_MB = pg_sys::GUC_UNIT_MB as i32;
+        /// An integer or float GUC's value is in bytes
+        #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+        const UNIT_BYTE = pg_sys::GUC_UNIT_BYTE as i32;
+        /// An integer or float GUC's value is in milliseconds, and it may be set with any unit of
+        /// time, as in `'5min'`
+        const UNIT_MS = pg_sys::GUC_UNIT_MS as i32;
+        /// An integer or float GUC's value is in seconds
+        const UNIT_S = pg_sys::GUC_UNIT_S as i32;
+        /// An integer or float GUC's value is in minutes
+        const UNIT_MIN = pg_sys::GUC_UNIT_MIN as i32;
+    }
+}
+
 pub trait GucEnum<T>
 where
     T: Copy,
@@ -59,6 +161,37 @@ where
     unsafe fn config_matrix(&self) -> *const pg_sys::config_enum_entry;
 }
 
+/// Hooks Postgres calls as a GUC is set and shown, for [`GucRegistry`]'s
+/// `define_*_guc_with_hooks` functions
+///
+/// `T` is the GUC's value, as its [`GucSetting`]'s `get()` returns it, which, for a string GUC,
+/// is an `Option<String>`, and for a list GUC, a `Vec<String>`.
+pub trait GucHooks<T> {
+    /// Is `value` a valid setting for the GUC?
+    ///
+    /// An `Err` rejects `value`, wherever it's set, with the `Err` as the error's detail.
+    fn check(_value: &T) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called as `value` becomes the GUC's setting, just before its [`GucSetting`] has it
+    ///
+    /// This must not fail, as `value` has already been checked.
+    fn assign(_value: &T) {}
+
+    /// Does the GUC have a [`GucHooks::show`] hook?
+    const SHOW: bool = false;
+
+    /// What `SHOW`, and `current_setting()`, display as the GUC's setting, rather than how
+    /// Postgres displays it, when [`GucHooks::SHOW`] is `true`
+    fn show() -> String {
+        String::new()
+    }
+}
+
+/// No hooks at all
+impl<T> GucHooks<T> for () {}
+
 pub struct GucSetting<T> {
     value: Cell<T>,
     char_p: Cell<*mut std::os::raw::c_char>,
@@ -129,6 +262,23 @@ impl GucSetting<Option<&'static str>> {
     }
 }
 
+unsafe impl Sync for GucSetting<&'static [&'static str]> {}
+impl GucSetting<&'static [&'static str]> {
+    /// The elements of the list
+    pub fn get(&self) -> Vec<String> {
+        let ptr = unsafe { *self.char_p.as_ptr() };
+        match unsafe { string_value(ptr) } {
+            // the check hook only lets a list which splits be set
+            Some(list) => split_list(&list).unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
+
+    unsafe fn as_ptr(&self) -> *mut *mut std::os::raw::c_char {
+        self.char_p.as_ptr()
+    }
+}
+
 unsafe impl<T> Sync for GucSetting<T> where T: GucEnum<T> + Copy {}
 impl<T> GucSetting<T>
 where
@@ -143,15 +293,369 @@ where
     }
 }
 
+/// A callback registered with [`GucRegistry::on_reload`], with the settings of its GUCs when it
+/// last checked them
+struct ReloadCallback {
+    names: Vec<String>,
+    settings: Vec<Option<String>>,
+    callback: Box<dyn FnMut(&[String])>,
+}
+
+/// Counts the changes to GUCs defined by [`GucRegistry`]
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// The names of the GUCs defined by [`GucRegistry`], in the order they were defined
+static mut DEFINED: Vec<String> = Vec::new();
+
+static mut RELOAD_CALLBACKS: Option<Vec<ReloadCallback>> = None;
+static mut LAST_RELOAD: pg_sys::TimestampTz = 0;
+
+/// A struct whose fields are GUCs, usually by `#[derive(PgConfig)]`
+pub trait PgConfig: Sized {
+    /// Define the GUCs, which must be done in `_PG_init()`
+    fn define();
+
+    /// The current settings of the GUCs
+    fn snapshot() -> Self;
+}
+
+/// A [`PgConfig`]'s settings, which are only read again once one of them changes
+///
+/// [`Settings::get`] is cheap enough for a function's every call, unlike [`PgConfig::snapshot`],
+/// which reads, and copies, each setting.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[derive(PgConfig)]
+/// struct Config {
+///     #[pg_config(default = ["public"])]
+///     schemas: Vec<String>,
+/// }
+///
+/// static CONFIG: Settings<Config> = Settings::new();
+///
+/// #[pg_extern]
+/// fn schema_allowed(schema: &str) -> bool {
+///     CONFIG.get().schemas.iter().any(|allowed| allowed == schema)
+/// }
+/// ```
+pub struct Settings<T> {
+    generation: Cell<u64>,
+    snapshot: UnsafeCell<Option<Arc<T>>>,
+}
+
+// the settings are only read by the backend's one thread
+unsafe impl<T> Sync for Settings<T> {}
+
+impl<T: PgConfig> Settings<T> {
+    pub const fn new() -> Self {
+        Settings {
+            generation: Cell::new(0),
+            snapshot: UnsafeCell::new(None),
+        }
+    }
+
+    /// The current settings, which are the same as the last call's, unless one of the GUCs
+    /// defined by [`GucRegistry`] changed since
+    pub fn get(&self) -> Arc<T> {
+        let generation = GucRegistry::generation();
+        let snapshot = unsafe { &mut *self.snapshot.get() };
+        match snapshot {
+            Some(snapshot) if self.generation.get() == generation => snapshot.clone(),
+            _ => {
+                let current = Arc::new(T::snapshot());
+                *snapshot = Some(current.clone());
+                self.generation.set(generation);
+                current
+            }
+        }
+    }
+}
+
+impl<T: PgConfig> Default for Settings<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub struct GucRegistry {}
 impl GucRegistry {
+    /// How many times the GUCs defined by [`GucRegistry`] have changed, which is the same until
+    /// one of them is set
+    pub fn generation() -> u64 {
+        GENERATION.load(Ordering::Relaxed)
+    }
+
+    /// The names of the GUCs this extension defined, in the order it defined them
+    pub fn defined() -> Vec<String> {
+        unsafe { DEFINED.clone() }
+    }
+
+    /// Describe the GUCs this extension defined, as `pg_settings` does, in the order it defined
+    /// them
+    ///
+    /// A GUC which `pg_settings` doesn't show the user, such as one with
+    /// [`GucFlags::NO_SHOW_ALL`], or one with [`GucFlags::SUPERUSER_ONLY`] to a user who isn't a
+    /// superuser, isn't described.  `#[derive(PgConfig)]` returns these from a `settings()`
+    /// function.
+    pub fn describe() -> Vec<GucDescription> {
+        let mut descriptions = Vec::new();
+        Spi::connect(|client| {
+            let rows = client.select_read_only(
+                "SELECT name, setting, unit, boot_val, short_desc, context::text \
+                 FROM pg_settings \
+                 WHERE name = ANY($1) \
+                 ORDER BY array_position($1, name)",
+                None,
+                Some(vec![(
+                    PgBuiltInOids::TEXTARRAYOID.oid(),
+                    Self::defined().into_datum(),
+                )]),
+            );
+            descriptions.extend(rows.map(|row| {
+                let column = |name| row.get_by_name::<String>(name).ok().flatten();
+                GucDescription {
+                    name: column("name").unwrap_or_default(),
+                    setting: column("setting"),
+                    unit: column("unit"),
+                    default_setting: column("boot_val"),
+                    description: column("short_desc").unwrap_or_default(),
+                    context: column("context").unwrap_or_default(),
+                }
+            }));
+            Ok(Some(()))
+        });
+        descriptions
+    }
+
+    /// Call `callback` with the names of the GUCs among `names`, such as `["my_ext.interval"]`,
+    /// whose settings changed once the configuration files are reloaded, as by `pg_reload_conf()`
+    ///
+    /// A backend runs the callbacks before it executes its next statement after the reload.  A
+    /// [`BackgroundWorker`](crate::bgworkers::BackgroundWorker), which must attach a handler for
+    /// SIGHUP, runs them when it next wakes from waiting on its latch.  Anywhere else,
+    /// [`GucRegistry::process_reload`] runs them.
+    pub fn on_reload<F: FnMut(&[String]) + 'static>(names: &[&str], callback: F) {
+        let names = names
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        let settings = names.iter().map(|name| Guc::get(name)).collect();
+        let callback = ReloadCallback {
+            names,
+            settings,
+            callback: Box::new(callback),
+        };
+
+        unsafe {
+            match RELOAD_CALLBACKS.as_mut() {
+                Some(callbacks) => callbacks.push(callback),
+                None => {
+                    RELOAD_CALLBACKS = Some(vec![callback]);
+                    LAST_RELOAD = pg_sys::PgReloadTime;
+                    register_hook(Box::leak(Box::new(ReloadHooks)));
+                }
+            }
+        }
+    }
+
+    /// Run the callbacks registered with [`GucRegistry::on_reload`] whose GUCs changed, if the
+    /// configuration files were reloaded since this was last run, which is cheap if they weren't
+    pub fn process_reload() {
+        let callbacks = unsafe {
+            if pg_sys::PgReloadTime == LAST_RELOAD {
+                return;
+            }
+            LAST_RELOAD = pg_sys::PgReloadTime;
+            match RELOAD_CALLBACKS.take() {
+                Some(callbacks) => callbacks,
+                None => return,
+            }
+        };
+
+        let mut callbacks = callbacks;
+        for callback in callbacks.iter_mut() {
+            let settings = callback
+                .names
+                .iter()
+                .map(|name| Guc::get::<String>(name))
+                .collect::<Vec<_>>();
+            let changed = callback
+                .names
+                .iter()
+                .zip(callback.settings.iter().zip(settings.iter()))
+                .filter(|(_, (before, after))| before != after)
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>();
+            callback.settings = settings;
+
+            if !changed.is_empty() {
+                (callback.callback)(&changed);
+            }
+        }
+
+        // keep any callbacks registered by the callbacks themselves
+        unsafe {
+            if let Some(registered) = RELOAD_CALLBACKS.take() {
+                callbacks.extend(registered);
+            }
+            RELOAD_CALLBACKS = Some(callbacks);
+        }
+    }
+
+    /// Warn of any setting of a GUC named `prefix.*` which the extension didn't define, such as a
+    /// misspelling of one it did, which Postgres keeps as a placeholder rather than rejecting
+    ///
+    /// Call this in `_PG_init()`, after defining the extension's GUCs, with the prefix of their
+    /// names, such as `"my_ext"`.  Only the settings made before the extension was loaded, such as
+    /// those in `postgresql.conf`, are warned of.
+    pub fn reserve_prefix(prefix: &str) {
+        unsafe {
+            pg_sys::EmitWarningsOnPlaceholders(PgMemoryContexts::TopMemoryContext.pstrdup(prefix))
+        }
+    }
+
     pub fn define_bool_guc(
         name: &str,
         short_description: &str,
         long_description: &str,
         setting: &GucSetting<bool>,
         context: GucContext,
+        flags: GucFlags,
     ) {
+        Self::define_bool_guc_with_hooks::<()>(
+            name,
+            short_description,
+            long_description,
+            setting,
+            context,
+            flags,
+        )
+    }
+
+    /// Define an integer GUC, whose value is in a unit when `flags` has one, such as
+    /// [`GucFlags::UNIT_KB`]
+    ///
+    /// A GUC with a unit may be set in any other unit of the same kind, such as `'64MB'`, which
+    /// Postgres converts to the GUC's unit, and rounds.
+    pub fn define_int_guc(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<i32>,
+        min_value: i32,
+        max_value: i32,
+        context: GucContext,
+        flags: GucFlags,
+    ) {
+        Self::define_int_guc_with_hooks::<()>(
+            name,
+            short_description,
+            long_description,
+            setting,
+            min_value,
+            max_value,
+            context,
+            flags,
+        )
+    }
+
+    pub fn define_string_guc(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<Option<&'static str>>,
+        context: GucContext,
+        flags: GucFlags,
+    ) {
+        Self::define_string_guc_with_hooks::<()>(
+            name,
+            short_description,
+            long_description,
+            setting,
+            context,
+            flags,
+        )
+    }
+
+    /// Define a GUC which is a comma-separated list, such as of schemas, as `search_path` is
+    ///
+    /// An element may be double-quoted, as an identifier is, to include commas, spaces, or double
+    /// quotes.  Unlike an identifier, an unquoted element isn't downcased.
+    ///
+    /// As for `search_path`, `SET my_ext.schemas = public, "My Schema"` sets two elements, but
+    /// `SET my_ext.schemas = 'public, other'` sets the one element `public, other`.
+    pub fn define_list_guc(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<&'static [&'static str]>,
+        context: GucContext,
+        flags: GucFlags,
+    ) {
+        Self::define_list_guc_with_hooks::<()>(
+            name,
+            short_description,
+            long_description,
+            setting,
+            context,
+            flags,
+        )
+    }
+
+    pub fn define_float_guc(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<f64>,
+        min_value: f64,
+        max_value: f64,
+        context: GucContext,
+        flags: GucFlags,
+    ) {
+        Self::define_float_guc_with_hooks::<()>(
+            name,
+            short_description,
+            long_description,
+            setting,
+            min_value,
+            max_value,
+            context,
+            flags,
+        )
+    }
+
+    pub fn define_enum_guc<T>(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<T>,
+        context: GucContext,
+        flags: GucFlags,
+    ) where
+        T: GucEnum<T> + Copy,
+    {
+        Self::define_enum_guc_with_hooks::<T, ()>(
+            name,
+            short_description,
+            long_description,
+            setting,
+            context,
+            flags,
+        )
+    }
+
+    /// [`GucRegistry::define_bool_guc`], with the hooks `H`
+    pub fn define_bool_guc_with_hooks<H: GucHooks<bool>>(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<bool>,
+        context: GucContext,
+        flags: GucFlags,
+    ) {
+        defined(name);
         unsafe {
             pg_sys::DefineCustomBoolVariable(
                 PgMemoryContexts::TopMemoryContext.pstrdup(name),
@@ -160,15 +664,16 @@ impl GucRegistry {
                 setting.as_ptr(),
                 setting.get(),
                 context as isize as u32,
-                0,
-                None,
-                None,
-                None,
+                flags.bits(),
+                Some(check_bool::<H>),
+                Some(assign_bool::<H>),
+                show_hook::<bool, H>(),
             )
         }
     }
 
-    pub fn define_int_guc(
+    /// [`GucRegistry::define_int_guc`], with the hooks `H`
+    pub fn define_int_guc_with_hooks<H: GucHooks<i32>>(
         name: &str,
         short_description: &str,
         long_description: &str,
@@ -176,7 +681,9 @@ impl GucRegistry {
         min_value: i32,
         max_value: i32,
         context: GucContext,
+        flags: GucFlags,
     ) {
+        defined(name);
         unsafe {
             pg_sys::DefineCustomIntVariable(
                 PgMemoryContexts::TopMemoryContext.pstrdup(name),
@@ -187,21 +694,24 @@ impl GucRegistry {
                 min_value,
                 max_value,
                 context as isize as u32,
-                0,
-                None,
-                None,
-                None,
+                flags.bits(),
+                Some(check_int::<H>),
+                Some(assign_int::<H>),
+                show_hook::<i32, H>(),
             )
         }
     }
 
-    pub fn define_string_guc(
+    /// [`GucRegistry::define_string_guc`], with the hooks `H`
+    pub fn define_string_guc_with_hooks<H: GucHooks<Option<String>>>(
         name: &str,
         short_description: &str,
         long_description: &str,
         setting: &GucSetting<Option<&'static str>>,
         context: GucContext,
+        flags: GucFlags,
     ) {
+        defined(name);
         unsafe {
             let boot_value = match setting.value.get() {
                 Some(s) => PgMemoryContexts::TopMemoryContext.pstrdup(s),
@@ -215,15 +725,16 @@ impl GucRegistry {
                 setting.as_ptr(),
                 boot_value,
                 context as isize as u32,
-                0,
-                None,
-                None,
-                None,
+                flags.bits(),
+                Some(check_string::<H>),
+                Some(assign_string::<H>),
+                show_hook::<Option<String>, H>(),
             )
         }
     }
 
-    pub fn define_float_guc(
+    /// [`GucRegistry::define_float_guc`], with the hooks `H`
+    pub fn define_float_guc_with_hooks<H: GucHooks<f64>>(
         name: &str,
         short_description: &str,
         long_description: &str,
@@ -231,7 +742,9 @@ impl GucRegistry {
         min_value: f64,
         max_value: f64,
         context: GucContext,
+        flags: GucFlags,
     ) {
+        defined(name);
         unsafe {
             pg_sys::DefineCustomRealVariable(
                 PgMemoryContexts::TopMemoryContext.pstrdup(name),
@@ -242,23 +755,55 @@ impl GucRegistry {
                 min_value,
                 max_value,
                 context as isize as u32,
-                0,
-                None,
-                None,
-                None,
+                flags.bits(),
+                Some(check_float::<H>),
+                Some(assign_float::<H>),
+                show_hook::<f64, H>(),
             )
         }
     }
 
-    pub fn define_enum_guc<T>(
+    /// [`GucRegistry::define_list_guc`], with the hooks `H`, which are given the list's elements
+    pub fn define_list_guc_with_hooks<H: GucHooks<Vec<String>>>(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<&'static [&'static str]>,
+        context: GucContext,
+        flags: GucFlags,
+    ) {
+        defined(name);
+        unsafe {
+            let boot_value = join_list(setting.value.get());
+
+            pg_sys::DefineCustomStringVariable(
+                PgMemoryContexts::TopMemoryContext.pstrdup(name),
+                PgMemoryContexts::TopMemoryContext.pstrdup(short_description),
+                PgMemoryContexts::TopMemoryContext.pstrdup(long_description),
+                setting.as_ptr(),
+                PgMemoryContexts::TopMemoryContext.pstrdup(&boot_value),
+                context as isize as u32,
+                flags.bits() | (pg_sys::GUC_LIST_INPUT | pg_sys::GUC_LIST_QUOTE) as i32,
+                Some(check_list::<H>),
+                Some(assign_list::<H>),
+                show_hook::<Vec<String>, H>(),
+            )
+        }
+    }
+
+    /// [`GucRegistry::define_enum_guc`], with the hooks `H`
+    pub fn define_enum_guc_with_hooks<T, H>(
         name: &str,
         short_description: &str,
         long_description: &str,
         setting: &GucSetting<T>,
         context: GucContext,
+        flags: GucFlags,
     ) where
         T: GucEnum<T> + Copy,
+        H: GucHooks<T>,
     {
+        defined(name);
         unsafe {
             pg_sys::DefineCustomEnumVariable(
                 PgMemoryContexts::TopMemoryContext.pstrdup(name),
@@ -268,11 +813,446 @@ impl GucRegistry {
                 setting.value.get().to_ordinal(),
                 setting.value.get().config_matrix(),
                 context as isize as u32,
-                0,
-                None,
-                None,
-                None,
+                flags.bits(),
+                Some(check_enum::<T, H>),
+                Some(assign_enum::<T, H>),
+                show_hook::<T, H>(),
             )
         }
     }
 }
+
+/// The settings of the server's GUCs, whether the extension defined them or not
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use std::time::Duration;
+///
+/// let work_mem = Guc::get::<GucBytes>("work_mem").unwrap();
+/// let timeout = Guc::get::<Duration>("statement_timeout").unwrap_or(Duration::ZERO);
+/// let seqscan = Guc::get::<bool>("enable_seqscan").unwrap();
+/// ```
+pub struct Guc {}
+impl Guc {
+    /// The current setting of the GUC named `name`, such as `"work_mem"`, as a `T`
+    ///
+    /// `None` when there's no such GUC, or its setting isn't a `T`, such as a memory setting as
+    /// an `i32`, or a disabled timeout, of `-1`, as a `Duration`.
+    pub fn get<T: GucValue>(name: &str) -> Option<T> {
+        let name = CString::new(name).ok()?;
+        let value = unsafe { pg_sys::GetConfigOption(name.as_ptr(), true, false) };
+        let value = unsafe { string_value(value) }?;
+        T::from_guc_value(&value)
+    }
+
+    /// The setting of the GUC named `name` which `role` gets as it connects to `database`, from
+    /// `ALTER ROLE ... IN DATABASE ... SET`, `ALTER ROLE ... SET`, `ALTER DATABASE ... SET`, or
+    /// `ALTER ROLE ALL SET`, in that order of precedence, as a `T`
+    ///
+    /// `None` when none of them set it, as then the role gets the server's setting, or when the
+    /// setting isn't a `T`.
+    pub fn get_for_role<T: GucValue>(name: &str, role: &str, database: &str) -> Option<T> {
+        let value = Spi::get_one_with_args::<String>(
+            "SELECT substr(c, strpos(c, '=') + 1) \
+             FROM pg_db_role_setting s, unnest(s.setconfig) c \
+             WHERE lower(split_part(c, '=', 1)) = lower($1) \
+               AND s.setrole IN (0, (SELECT oid FROM pg_roles WHERE rolname = $2)) \
+               AND s.setdatabase IN (0, (SELECT oid FROM pg_database WHERE datname = $3)) \
+             ORDER BY s.setrole = 0, s.setdatabase = 0 \
+             LIMIT 1",
+            vec![
+                (PgBuiltInOids::TEXTOID.oid(), name.into_datum()),
+                (PgBuiltInOids::TEXTOID.oid(), role.into_datum()),
+                (PgBuiltInOids::TEXTOID.oid(), database.into_datum()),
+            ],
+        )?;
+        T::from_guc_value(&value)
+    }
+
+    /// Set the GUC named `name` to `value` in `postgresql.auto.conf`, as `ALTER SYSTEM` does, or
+    /// remove its setting from it when `value` is `None`
+    ///
+    /// As for `ALTER SYSTEM`, Postgres checks that the user is a superuser, that there's such a
+    /// GUC, and that `value` is a valid setting of it, raising an ERROR if not.  Unlike
+    /// `ALTER SYSTEM`, this can be run in a transaction, such as from an extension's setup
+    /// function, but the file is written at once, and isn't restored if the transaction then
+    /// aborts.  The setting takes effect once the configuration is reloaded, as by
+    /// `SELECT pg_reload_conf()`.
+    pub fn alter_system(name: &str, value: Option<&str>) {
+        let statement = match value {
+            Some(value) => format!(
+                "ALTER SYSTEM SET {} = {}",
+                sql::quote_identifier(name),
+                sql::quote_literal(value)
+            ),
+            None => format!("ALTER SYSTEM RESET {}", sql::quote_identifier(name)),
+        };
+        let statement = CString::new(statement).expect("setting contained a null byte");
+
+        unsafe {
+            #[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+            let parsed = pg_sys::raw_parser(statement.as_ptr());
+            #[cfg(feature = "pg14")]
+            let parsed =
+                pg_sys::raw_parser(statement.as_ptr(), pg_sys::RawParseMode_RAW_PARSE_DEFAULT);
+
+            let raw_stmt = PgList::<pg_sys::RawStmt>::from_pg(parsed)
+                .head()
+                .expect("ALTER SYSTEM didn't parse");
+            // the standard ProcessUtility() would refuse to run it in a transaction
+            pg_sys::AlterSystemSetConfigFile((*raw_stmt).stmt as *mut pg_sys::AlterSystemStmt);
+        }
+    }
+}
+
+/// A GUC's setting, as [`Guc::get`] parses it from the setting Postgres shows, as in `SHOW`
+pub trait GucValue: Sized {
+    fn from_guc_value(value: &str) -> Option<Self>;
+}
+
+impl GucValue for String {
+    fn from_guc_value(value: &str) -> Option<Self> {
+        Some(value.to_string())
+    }
+}
+
+impl GucValue for bool {
+    fn from_guc_value(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "on" | "true" | "yes" | "1" => Some(true),
+            "off" | "false" | "no" | "0" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+impl GucValue for i32 {
+    fn from_guc_value(value: &str) -> Option<Self> {
+        value.parse().ok()
+    }
+}
+
+impl GucValue for i64 {
+    fn from_guc_value(value: &str) -> Option<Self> {
+        value.parse().ok()
+    }
+}
+
+impl GucValue for f64 {
+    fn from_guc_value(value: &str) -> Option<Self> {
+        value.parse().ok()
+    }
+}
+
+/// A time setting, such as `'5min'`
+impl GucValue for Duration {
+    fn from_guc_value(value: &str) -> Option<Self> {
+        let seconds = match split_unit(value)? {
+            (n, _) if n < 0.0 => return None,
+            (n, "us") => n / 1_000_000.0,
+            (n, "ms") => n / 1_000.0,
+            (n, "s") => n,
+            (n, "min") => n * 60.0,
+            (n, "h") => n * 60.0 * 60.0,
+            (n, "d") => n * 60.0 * 60.0 * 24.0,
+            // Postgres shows zero without a unit
+            (n, "") if n == 0.0 => 0.0,
+            _ => return None,
+        };
+        Some(Duration::from_secs_f64(seconds))
+    }
+}
+
+/// A memory setting, such as `'64MB'`, in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GucBytes(pub u64);
+
+impl GucValue for GucBytes {
+    fn from_guc_value(value: &str) -> Option<Self> {
+        let bytes = match split_unit(value)? {
+            (n, _) if n < 0.0 => return None,
+            (n, "B") => n,
+            (n, "kB") => n * 1024.0,
+            (n, "MB") => n * 1024.0 * 1024.0,
+            (n, "GB") => n * 1024.0 * 1024.0 * 1024.0,
+            (n, "TB") => n * 1024.0 * 1024.0 * 1024.0 * 1024.0,
+            // Postgres shows zero without a unit
+            (n, "") if n == 0.0 => 0.0,
+            _ => return None,
+        };
+        Some(GucBytes(bytes.round() as u64))
+    }
+}
+
+/// Split `value`, such as `"64MB"`, into its number and its unit, which may be empty
+fn split_unit(value: &str) -> Option<(f64, &str)> {
+    let value = value.trim();
+    let at = value
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(value.len());
+    let number = value[..at].trim_end().parse().ok()?;
+    Some((number, &value[at..]))
+}
+
+/// A GUC defined by [`GucRegistry`], as described by `pg_settings`, returned by
+/// [`GucRegistry::describe`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GucDescription {
+    pub name: String,
+    /// The current setting, as shown by `SHOW`, but without its unit
+    pub setting: Option<String>,
+    /// The unit of the setting, such as `"ms"`, which is `None` for a setting without one
+    pub unit: Option<String>,
+    /// The setting the GUC was defined with, before any configuration sets it
+    pub default_setting: Option<String>,
+    pub description: String,
+    /// Where the GUC can be set, such as `"user"` or `"superuser"`
+    pub context: String,
+}
+
+impl TableRow for GucDescription {
+    fn table_columns() -> Vec<TableColumn> {
+        vec![
+            table_column::<String>("String", "name"),
+            table_column::<Option<String>>("Option<String>", "setting"),
+            table_column::<Option<String>>("Option<String>", "unit"),
+            table_column::<Option<String>>("Option<String>", "default_setting"),
+            table_column::<String>("String", "description"),
+            table_column::<String>("String", "context"),
+        ]
+    }
+
+    fn into_datums(self) -> Vec<Option<pg_sys::Datum>> {
+        vec![
+            self.name.into_datum(),
+            self.setting.into_datum(),
+            self.unit.into_datum(),
+            self.default_setting.into_datum(),
+            self.description.into_datum(),
+            self.context.into_datum(),
+        ]
+    }
+}
+
+/// Record that the GUC named `name` was defined
+fn defined(name: &str) {
+    unsafe { DEFINED.push(name.to_string()) }
+}
+
+/// Runs the callbacks registered with [`GucRegistry::on_reload`] before each statement a backend
+/// executes
+struct ReloadHooks;
+
+impl PgHooks for ReloadHooks {
+    fn executor_start(
+        &mut self,
+        query_desc: PgBox<pg_sys::QueryDesc>,
+        eflags: i32,
+        prev_hook: fn(query_desc: PgBox<pg_sys::QueryDesc>, eflags: i32) -> HookResult<()>,
+    ) -> HookResult<()> {
+        GucRegistry::process_reload();
+        prev_hook(query_desc, eflags)
+    }
+
+    fn post_parse_analyze(
+        &mut self,
+        parse_state: PgBox<pg_sys::ParseState>,
+        query: PgBox<pg_sys::Query>,
+        jumble_state: Option<PgBox<JumbleState>>,
+        prev_hook: fn(
+            parse_state: PgBox<pg_sys::ParseState>,
+            query: PgBox<pg_sys::Query>,
+            jumble_state: Option<PgBox<JumbleState>>,
+        ) -> HookResult<()>,
+    ) -> HookResult<()> {
+        // utility statements, which the executor doesn't run, are still parsed
+        GucRegistry::process_reload();
+        prev_hook(parse_state, query, jumble_state)
+    }
+}
+
+// `#[pg_guard]` can't wrap generic functions, so the hooks guard themselves
+
+unsafe extern "C" fn check_bool<H: GucHooks<bool>>(
+    newval: *mut bool,
+    _extra: *mut *mut c_void,
+    _source: pg_sys::GucSource,
+) -> bool {
+    guard::guard(|| check_result(H::check(&*newval)))
+}
+
+unsafe extern "C" fn check_int<H: GucHooks<i32>>(
+    newval: *mut c_int,
+    _extra: *mut *mut c_void,
+    _source: pg_sys::GucSource,
+) -> bool {
+    guard::guard(|| check_result(H::check(&*newval)))
+}
+
+unsafe extern "C" fn check_float<H: GucHooks<f64>>(
+    newval: *mut f64,
+    _extra: *mut *mut c_void,
+    _source: pg_sys::GucSource,
+) -> bool {
+    guard::guard(|| check_result(H::check(&*newval)))
+}
+
+unsafe extern "C" fn check_string<H: GucHooks<Option<String>>>(
+    newval: *mut *mut c_char,
+    _extra: *mut *mut c_void,
+    _source: pg_sys::GucSource,
+) -> bool {
+    guard::guard(|| check_result(H::check(&string_value(*newval))))
+}
+
+unsafe extern "C" fn check_list<H: GucHooks<Vec<String>>>(
+    newval: *mut *mut c_char,
+    _extra: *mut *mut c_void,
+    _source: pg_sys::GucSource,
+) -> bool {
+    guard::guard(|| {
+        let list = string_value(*newval).unwrap_or_default();
+        check_result(split_list(&list).and_then(|elements| H::check(&elements)))
+    })
+}
+
+unsafe extern "C" fn check_enum<T: GucEnum<T> + Copy, H: GucHooks<T>>(
+    newval: *mut c_int,
+    _extra: *mut *mut c_void,
+    _source: pg_sys::GucSource,
+) -> bool {
+    guard::guard(|| check_result(H::check(&T::from_ordinal(*newval))))
+}
+
+unsafe extern "C" fn assign_bool<H: GucHooks<bool>>(newval: bool, _extra: *mut c_void) {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+    guard::guard(|| H::assign(&newval))
+}
+
+unsafe extern "C" fn assign_int<H: GucHooks<i32>>(newval: c_int, _extra: *mut c_void) {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+    guard::guard(|| H::assign(&newval))
+}
+
+unsafe extern "C" fn assign_float<H: GucHooks<f64>>(newval: f64, _extra: *mut c_void) {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+    guard::guard(|| H::assign(&newval))
+}
+
+unsafe extern "C" fn assign_string<H: GucHooks<Option<String>>>(
+    newval: *const c_char,
+    _extra: *mut c_void,
+) {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+    guard::guard(|| H::assign(&string_value(newval)))
+}
+
+unsafe extern "C" fn assign_list<H: GucHooks<Vec<String>>>(
+    newval: *const c_char,
+    _extra: *mut c_void,
+) {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+    guard::guard(|| {
+        let list = string_value(newval).unwrap_or_default();
+        H::assign(&split_list(&list).unwrap_or_default())
+    })
+}
+
+unsafe extern "C" fn assign_enum<T: GucEnum<T> + Copy, H: GucHooks<T>>(
+    newval: c_int,
+    _extra: *mut c_void,
+) {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+    guard::guard(|| H::assign(&T::from_ordinal(newval)))
+}
+
+fn show_hook<T, H: GucHooks<T>>() -> pg_sys::GucShowHook {
+    unsafe extern "C" fn show<T, H: GucHooks<T>>() -> *const c_char {
+        guard::guard(|| PgMemoryContexts::CurrentMemoryContext.pstrdup(&H::show()))
+    }
+
+    match H::SHOW {
+        true => Some(show::<T, H>),
+        false => None,
+    }
+}
+
+fn check_result(result: Result<(), String>) -> bool {
+    match result {
+        Ok(()) => true,
+        Err(detail) => {
+            // Postgres reports it as the detail of its "invalid value for parameter" error
+            unsafe {
+                pg_sys::GUC_check_errdetail_string =
+                    PgMemoryContexts::CurrentMemoryContext.pstrdup(&detail);
+            }
+            false
+        }
+    }
+}
+
+unsafe fn string_value(value: *const c_char) -> Option<String> {
+    match value.is_null() {
+        true => None,
+        false => Some(CStr::from_ptr(value).to_string_lossy().into_owned()),
+    }
+}
+
+/// Split `list`, a comma-separated list whose elements may be double-quoted, into its elements,
+/// as Postgres' `SplitGUCList()` does
+fn split_list(list: &str) -> Result<Vec<String>, String> {
+    let mut elements = Vec::new();
+    let mut chars = list.trim_start().chars().peekable();
+    if chars.peek().is_none() {
+        return Ok(elements);
+    }
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let mut element = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') if chars.next_if_eq(&'"').is_some() => element.push('"'),
+                    Some('"') => break,
+                    Some(c) => element.push(c),
+                    None => return Err(format!("unterminated quoted element in \"{}\"", list)),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                element.push(c);
+            }
+            element.truncate(element.trim_end().len());
+        }
+
+        if element.is_empty() {
+            return Err(format!("zero-length element in \"{}\"", list));
+        }
+        elements.push(element);
+
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        match chars.next() {
+            Some(',') => continue,
+            None => return Ok(elements),
+            Some(_) => return Err(format!("invalid list syntax in \"{}\"", list)),
+        }
+    }
+}
+
+/// Join `elements` into a list [`split_list`] splits, quoting those which need it
+fn join_list(elements: &[&str]) -> String {
+    elements
+        .iter()
+        .map(|element| {
+            let quote = element.is_empty()
+                || element.contains(|c: char| c == ',' || c == '"' || c.is_whitespace());
+            match quote {
+                true => format!("\"{}\"", element.replace('"', "\"\"")),
+                false => element.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
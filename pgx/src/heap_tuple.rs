@@ -0,0 +1,377 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Provides a safe wrapper around Postgres' `pg_sys::HeapTuple`, for reading and building rows of
+//! arbitrary composite types at runtime
+use crate::{
+    heap_getattr_raw, heap_tuple_header_get_datum_length, heap_tuple_header_get_type_id,
+    heap_tuple_header_get_typmod, name_data_to_str, pg_sys, FromDatum, IntoDatum, PgBox,
+    PgTupleDesc,
+};
+use std::ffi::CString;
+use std::fmt::{Display, Formatter};
+use std::num::NonZeroUsize;
+
+/// The errors that can occur when accessing a [`PgHeapTuple`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgHeapTupleError {
+    /// The named type is not a composite type.
+    NotACompositeType(String),
+    /// The tuple has no attribute with this name.
+    NoSuchAttributeName(String),
+    /// The tuple has no attribute with this (1-based) number.
+    NoSuchAttributeNumber(NonZeroUsize),
+    /// The attribute is NULL, but its field is not an `Option`.
+    UnexpectedNull(String),
+    /// The Rust type's Postgres type does not match the attribute's type.
+    MismatchedType {
+        attribute: NonZeroUsize,
+        expected: pg_sys::Oid,
+        found: pg_sys::Oid,
+    },
+}
+
+impl Display for PgHeapTupleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgHeapTupleError::NotACompositeType(name) => {
+                write!(f, "type `{}` is not a composite type", name)
+            }
+            PgHeapTupleError::NoSuchAttributeName(name) => {
+                write!(f, "no attribute named `{}`", name)
+            }
+            PgHeapTupleError::NoSuchAttributeNumber(attno) => {
+                write!(f, "no attribute number {}", attno)
+            }
+            PgHeapTupleError::UnexpectedNull(name) => {
+                write!(
+                    f,
+                    "attribute `{}` is NULL, but its field is not an `Option`",
+                    name
+                )
+            }
+            PgHeapTupleError::MismatchedType {
+                attribute,
+                expected,
+                found,
+            } => write!(
+                f,
+                "attribute {} has type oid {}, but the value has type oid {}",
+                attribute, expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PgHeapTupleError {}
+
+/// A row of a composite type, along with the `PgTupleDesc` describing it.
+///
+/// Attributes can be read and written by name or by their 1-based attribute number.  Writing an
+/// attribute builds a new tuple, in the `CurrentMemoryContext`, with the value replaced.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// let mut dog = PgHeapTuple::new_composite_type("Dog").expect("no such type");
+/// dog.set_by_name("name", "Nami").unwrap();
+/// dog.set_by_name("scritches", 0_i32).unwrap();
+///
+/// let name = dog.get_by_name::<&str>("name").unwrap();
+/// assert_eq!(name, Some("Nami"));
+///
+/// let datum = dog.into_datum();
+/// ```
+pub struct PgHeapTuple<'a> {
+    tupdesc: PgTupleDesc<'a>,
+    tuple: pg_sys::HeapTuple,
+}
+
+impl<'a> PgHeapTuple<'a> {
+    /// Wrap an existing `pg_sys::HeapTuple`, such as the one provided to a trigger.
+    ///
+    /// ## Safety
+    ///
+    /// This function is unsafe as it cannot validate that `tuple` is a valid pointer, nor that it
+    /// is described by `tupdesc`.
+    pub unsafe fn from_heap_tuple(tupdesc: PgTupleDesc<'a>, tuple: pg_sys::HeapTuple) -> Self {
+        Self { tupdesc, tuple }
+    }
+
+    /// Build a new tuple described by `tupdesc`, with every attribute set to NULL.
+    pub fn new_from_tupdesc(tupdesc: PgTupleDesc<'a>) -> Self {
+        let natts = tupdesc.len();
+        let mut datums = vec![0 as pg_sys::Datum; natts];
+        let mut nulls = vec![true; natts];
+        let tuple = unsafe {
+            pg_sys::heap_form_tuple(tupdesc.as_ptr(), datums.as_mut_ptr(), nulls.as_mut_ptr())
+        };
+        Self { tupdesc, tuple }
+    }
+
+    /// The `PgTupleDesc` describing this tuple.
+    pub fn tupdesc(&self) -> &PgTupleDesc<'a> {
+        &self.tupdesc
+    }
+
+    /// The number of attributes in this tuple, including dropped attributes.
+    pub fn len(&self) -> usize {
+        self.tupdesc.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over the (non-dropped) attributes of this tuple, along with their 1-based
+    /// attribute numbers.
+    pub fn attributes(
+        &self,
+    ) -> impl Iterator<Item = (NonZeroUsize, &pg_sys::FormData_pg_attribute)> {
+        self.tupdesc
+            .iter()
+            .enumerate()
+            .filter(|(_, att)| !att.attisdropped)
+            .map(|(i, att)| (NonZeroUsize::new(i + 1).unwrap(), att))
+    }
+
+    /// Get the attribute named `name` and its 1-based attribute number.
+    pub fn get_attribute_by_name(
+        &self,
+        name: &str,
+    ) -> Option<(NonZeroUsize, &pg_sys::FormData_pg_attribute)> {
+        self.attributes()
+            .find(|(_, att)| name_data_to_str(&att.attname) == name)
+    }
+
+    /// Get the attribute with the 1-based attribute number `attno`.
+    pub fn get_attribute_by_index(
+        &self,
+        attno: NonZeroUsize,
+    ) -> Option<&pg_sys::FormData_pg_attribute> {
+        self.tupdesc
+            .get(attno.get() - 1)
+            .filter(|att| !att.attisdropped)
+    }
+
+    /// Get the value of the attribute named `name`, or `None` if it is NULL.
+    pub fn get_by_name<T: FromDatum + IntoDatum>(
+        &self,
+        name: &str,
+    ) -> Result<Option<T>, PgHeapTupleError> {
+        let (attno, _) = self
+            .get_attribute_by_name(name)
+            .ok_or_else(|| PgHeapTupleError::NoSuchAttributeName(name.to_string()))?;
+        self.get_by_index(attno)
+    }
+
+    /// Get the value of the attribute with the 1-based attribute number `attno`, or `None` if it
+    /// is NULL.
+    ///
+    /// The attribute's type must be binary-coercible to the Postgres type of `T`, as `varchar` is
+    /// to `text`, or be a domain over it.
+    pub fn get_by_index<T: FromDatum + IntoDatum>(
+        &self,
+        attno: NonZeroUsize,
+    ) -> Result<Option<T>, PgHeapTupleError> {
+        let att = self
+            .get_attribute_by_index(attno)
+            .ok_or(PgHeapTupleError::NoSuchAttributeNumber(attno))?;
+        check_type::<T>(attno, att, false)?;
+        unsafe {
+            match heap_getattr_raw(self.tuple, attno.get(), self.tupdesc.as_ptr()) {
+                Some(datum) => Ok(T::from_datum(datum, false, att.atttypid)),
+                None => Ok(None),
+            }
+        }
+    }
+
+    /// Set the value of the attribute named `name`.  `None` sets it to NULL.
+    pub fn set_by_name<T: IntoDatum>(
+        &mut self,
+        name: &str,
+        value: T,
+    ) -> Result<(), PgHeapTupleError> {
+        let (attno, _) = self
+            .get_attribute_by_name(name)
+            .ok_or_else(|| PgHeapTupleError::NoSuchAttributeName(name.to_string()))?;
+        self.set_by_index(attno, value)
+    }
+
+    /// Set the value of the attribute with the 1-based attribute number `attno`.  `None` sets it
+    /// to NULL.
+    ///
+    /// The Postgres type of `T` must be binary-coercible to the attribute's type, as `text` is to
+    /// `varchar`, or be the base type of the attribute's domain type.
+    pub fn set_by_index<T: IntoDatum>(
+        &mut self,
+        attno: NonZeroUsize,
+        value: T,
+    ) -> Result<(), PgHeapTupleError> {
+        let att = self
+            .get_attribute_by_index(attno)
+            .ok_or(PgHeapTupleError::NoSuchAttributeNumber(attno))?;
+        check_type::<T>(attno, att, true)?;
+
+        let (mut datum, mut is_null) = match value.into_datum() {
+            Some(datum) => (datum, false),
+            None => (0 as pg_sys::Datum, true),
+        };
+        let mut column = attno.get() as std::os::raw::c_int;
+        self.tuple = unsafe {
+            pg_sys::heap_modify_tuple_by_cols(
+                self.tuple,
+                self.tupdesc.as_ptr(),
+                1,
+                &mut column,
+                &mut datum,
+                &mut is_null,
+            )
+        };
+        Ok(())
+    }
+
+    /// The wrapped `pg_sys::HeapTuple`.
+    pub fn as_ptr(&self) -> pg_sys::HeapTuple {
+        self.tuple
+    }
+
+    /// Consume this `PgHeapTuple`, returning the wrapped `pg_sys::HeapTuple`, such as to return
+    /// it from a trigger.
+    pub fn into_pg(self) -> pg_sys::HeapTuple {
+        self.tuple
+    }
+}
+
+/// Check that a value of `T` can be read from the attribute `att`, whose type must be
+/// binary-coercible to `T`'s Postgres type, or written to it, when `writing`, in which case `T`'s
+/// type must be binary-coercible to the attribute's.  A domain type is its base type here.
+fn check_type<T: IntoDatum>(
+    attno: NonZeroUsize,
+    att: &pg_sys::FormData_pg_attribute,
+    writing: bool,
+) -> Result<(), PgHeapTupleError> {
+    let found = T::type_oid();
+    let coercible = unsafe {
+        found == pg_sys::getBaseType(att.atttypid)
+            || if writing {
+                pg_sys::IsBinaryCoercible(found, att.atttypid)
+            } else {
+                pg_sys::IsBinaryCoercible(att.atttypid, found)
+            }
+    };
+    if coercible {
+        Ok(())
+    } else {
+        Err(PgHeapTupleError::MismatchedType {
+            attribute: attno,
+            expected: att.atttypid,
+            found,
+        })
+    }
+}
+
+impl PgHeapTuple<'static> {
+    /// Build a new tuple of the composite type named `type_name`, with every attribute set to
+    /// NULL.
+    ///
+    /// Raises a Postgres ERROR if the type does not exist.
+    pub fn new_composite_type(type_name: &str) -> Result<Self, PgHeapTupleError> {
+        let name = CString::new(type_name).expect("type name contained a null byte");
+        let mut typoid = pg_sys::InvalidOid;
+        let mut typmod = 0;
+        unsafe {
+            pg_sys::parseTypeString(name.as_ptr(), &mut typoid, &mut typmod, false);
+            if !pg_sys::type_is_rowtype(typoid) {
+                return Err(PgHeapTupleError::NotACompositeType(type_name.to_string()));
+            }
+            let tupdesc =
+                PgTupleDesc::from_pg_is_copy(pg_sys::lookup_rowtype_tupdesc_copy(typoid, typmod));
+            Ok(Self::new_from_tupdesc(tupdesc))
+        }
+    }
+
+    /// Wrap a composite `pg_sys::Datum`, such as an argument of a function.
+    ///
+    /// ## Safety
+    ///
+    /// This function is unsafe as it cannot guarantee that the provided `pg_sys::Datum` actually
+    /// points to a composite type
+    pub unsafe fn from_composite_datum(composite: pg_sys::Datum) -> Self {
+        let htup_header =
+            pg_sys::pg_detoast_datum(composite as *mut pg_sys::varlena) as pg_sys::HeapTupleHeader;
+        let tup_type = heap_tuple_header_get_type_id(htup_header);
+        let tup_typmod = heap_tuple_header_get_typmod(htup_header);
+        let tupdesc =
+            PgTupleDesc::from_pg_is_copy(pg_sys::lookup_rowtype_tupdesc_copy(tup_type, tup_typmod));
+
+        let mut data = PgBox::<pg_sys::HeapTupleData>::alloc0();
+        data.t_len = heap_tuple_header_get_datum_length(htup_header) as u32;
+        data.t_data = htup_header;
+
+        Self {
+            tupdesc,
+            tuple: data.into_pg(),
+        }
+    }
+}
+
+impl FromDatum for PgHeapTuple<'static> {
+    unsafe fn from_datum(
+        composite: pg_sys::Datum,
+        is_null: bool,
+        _typoid: pg_sys::Oid,
+    ) -> Option<Self> {
+        if is_null {
+            None
+        } else {
+            Some(PgHeapTuple::from_composite_datum(composite))
+        }
+    }
+}
+
+impl<'a> IntoDatum for PgHeapTuple<'a> {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Some(unsafe { pg_sys::heap_copy_tuple_as_datum(self.tuple, self.tupdesc.as_ptr()) })
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::RECORDOID
+    }
+}
+
+/// A type which can be built from the attributes of a [`PgHeapTuple`], usually by
+/// `#[derive(FromHeapTuple)]`
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[derive(FromHeapTuple)]
+/// struct Dog {
+///     name: String,
+///     #[heap_tuple(rename = "scritches")]
+///     scritch_count: Option<i32>,
+/// }
+///
+/// #[pg_extern]
+/// fn dog_name(dog: PgHeapTuple<'static>) -> String {
+///     Dog::from_heap_tuple(&dog).unwrap().name
+/// }
+/// ```
+pub trait FromHeapTuple: Sized {
+    fn from_heap_tuple(tuple: &PgHeapTuple) -> Result<Self, PgHeapTupleError>;
+}
+
+/// A type whose fields can be written to the attributes of a [`PgHeapTuple`], usually by
+/// `#[derive(IntoHeapTuple)]`
+///
+/// Attributes without a matching field keep their values.
+pub trait IntoHeapTuple {
+    fn into_heap_tuple(self, tuple: &mut PgHeapTuple) -> Result<(), PgHeapTupleError>;
+}
@@ -8,8 +8,8 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 //! A trait and registration system for hooking Postgres internal operations such as its planner and executor
-use crate::{pg_guard, pg_sys, void_mut_ptr, PgBox, PgList};
-use std::ops::Deref;
+use crate::{pg_guard, pg_sys, void_mut_ptr, Explain, PgBox, PgList, PgMemoryContexts};
+use std::ops::{Deref, DerefMut};
 
 pub struct HookResult<T> {
     pub inner: T,
@@ -76,6 +76,10 @@ pub trait PgHooks {
     }
 
     /// Hook for plugins to get control in ExecCheckRTPerms()
+    ///
+    /// [`RelationAccess::from_range_table`] tells which relations the query accesses, and how.
+    /// To deny access, return `false`, after raising an `ERROR` if `ereport_on_violation` is
+    /// true.
     fn executor_check_perms(
         &mut self,
         range_table: PgList<*mut pg_sys::RangeTblEntry>,
@@ -89,6 +93,8 @@ pub trait PgHooks {
     }
 
     /// Hook for plugins to get control in `ProcessUtility()`
+    ///
+    /// [`UtilityStatement::from_planned_stmt`] tells what kind of statement `pstmt` is.
     fn process_utility_hook(
         &mut self,
         pstmt: PgBox<pg_sys::PlannedStmt>,
@@ -123,6 +129,34 @@ pub trait PgHooks {
     }
 
     /// Hook for plugins to get control of the planner
+    ///
+    /// `query_string` is null on Postgres 10-12, whose planner isn't given the query's text.
+    /// The returned plan can come from `prev_hook`, which is whatever planner hook was installed
+    /// before this one, or from [`standard_planner`] to bypass the other hooks:
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// struct NoParallelism;
+    /// impl PgHooks for NoParallelism {
+    ///     fn planner(
+    ///         &mut self,
+    ///         parse: PgBox<pg_sys::Query>,
+    ///         query_string: *const std::os::raw::c_char,
+    ///         cursor_options: i32,
+    ///         bound_params: PgBox<pg_sys::ParamListInfoData>,
+    ///         prev_hook: fn(
+    ///             PgBox<pg_sys::Query>,
+    ///             *const std::os::raw::c_char,
+    ///             i32,
+    ///             PgBox<pg_sys::ParamListInfoData>,
+    ///         ) -> HookResult<*mut pg_sys::PlannedStmt>,
+    ///     ) -> HookResult<*mut pg_sys::PlannedStmt> {
+    ///         let cursor_options = cursor_options & !(pg_sys::CURSOR_OPT_PARALLEL_OK as i32);
+    ///         prev_hook(parse, query_string, cursor_options, bound_params)
+    ///     }
+    /// }
+    /// ```
     fn planner(
         &mut self,
         parse: PgBox<pg_sys::Query>,
@@ -139,6 +173,125 @@ pub trait PgHooks {
         prev_hook(parse, query_string, cursor_options, bound_params)
     }
 
+    /// Hook for plugins to get control at the end of parse analysis, once `query` has been
+    /// built from `parse_state`'s source text
+    ///
+    /// `jumble_state` is what the query's `queryId` was computed from, and is only passed on
+    /// Postgres 14 when `compute_query_id` is on.  Changes to `query`, such as to its
+    /// `queryId`, are seen by the planner.
+    fn post_parse_analyze(
+        &mut self,
+        parse_state: PgBox<pg_sys::ParseState>,
+        query: PgBox<pg_sys::Query>,
+        jumble_state: Option<PgBox<JumbleState>>,
+        prev_hook: fn(
+            parse_state: PgBox<pg_sys::ParseState>,
+            query: PgBox<pg_sys::Query>,
+            jumble_state: Option<PgBox<JumbleState>>,
+        ) -> HookResult<()>,
+    ) -> HookResult<()> {
+        prev_hook(parse_state, query, jumble_state)
+    }
+
+    /// Hook for plugins to get control when an object is created, dropped, or altered, or
+    /// otherwise accessed in a way that [`ObjectAccess`] describes
+    ///
+    /// This is called for objects created or dropped internally as well, such as a table's row
+    /// type, which [`ObjectAccessEvent::object_address`] can tell apart by their class.
+    fn object_access(
+        &mut self,
+        event: ObjectAccessEvent,
+        prev_hook: fn(event: ObjectAccessEvent) -> HookResult<()>,
+    ) -> HookResult<()> {
+        prev_hook(event)
+    }
+
+    /// Hook for plugins to get control once a client has been authenticated, or has failed to
+    /// be, before it's told the outcome
+    ///
+    /// `status` is `pg_sys::STATUS_OK` if the client was authenticated.  To turn the client away
+    /// regardless, call [`reject_connection`].  As the hook runs before the backend connects to
+    /// its database, it can't access the database, and must be registered from
+    /// `shared_preload_libraries`.
+    fn client_authentication(
+        &mut self,
+        port: PgBox<pg_sys::Port>,
+        status: i32,
+        prev_hook: fn(port: PgBox<pg_sys::Port>, status: i32) -> HookResult<()>,
+    ) -> HookResult<()> {
+        prev_hook(port, status)
+    }
+
+    /// Hook for plugins to check a password given to `CREATE ROLE` or `ALTER ROLE`, and to
+    /// reject it with [`reject_password`]
+    ///
+    /// `valid_until` is the `VALID UNTIL` time, if one was given, which is `i64::MAX` for
+    /// `'infinity'`.  Only a [`Password::Plaintext`] can be checked for its length or content,
+    /// as the others have already been hashed by the client.
+    fn check_password(
+        &mut self,
+        username: &str,
+        password: Password,
+        valid_until: Option<pg_sys::TimestampTz>,
+        prev_hook: fn(
+            username: &str,
+            password: Password,
+            valid_until: Option<pg_sys::TimestampTz>,
+        ) -> HookResult<()>,
+    ) -> HookResult<()> {
+        prev_hook(username, password, valid_until)
+    }
+
+    /// Hook for plugins to get control in `ExplainOneQuery()`, which explains each query given to
+    /// `EXPLAIN`
+    ///
+    /// `prev_hook` plans the query and writes out its plan, after which the extension can add
+    /// a [`section`](Explain::section) of its own, such as what it did while planning or
+    /// running the query.  `query_env` is null on Postgres 10.
+    fn explain_one_query(
+        &mut self,
+        query: PgBox<pg_sys::Query>,
+        cursor_options: i32,
+        into: PgBox<pg_sys::IntoClause>,
+        explain: &mut Explain,
+        query_string: &std::ffi::CStr,
+        params: PgBox<pg_sys::ParamListInfoData>,
+        query_env: PgBox<pg_sys::QueryEnvironment>,
+        prev_hook: fn(
+            query: PgBox<pg_sys::Query>,
+            cursor_options: i32,
+            into: PgBox<pg_sys::IntoClause>,
+            explain: &mut Explain,
+            query_string: &std::ffi::CStr,
+            params: PgBox<pg_sys::ParamListInfoData>,
+            query_env: PgBox<pg_sys::QueryEnvironment>,
+        ) -> HookResult<()>,
+    ) -> HookResult<()> {
+        prev_hook(
+            query,
+            cursor_options,
+            into,
+            explain,
+            query_string,
+            params,
+            query_env,
+        )
+    }
+
+    /// Hook for plugins to get control in `EmitErrorReport()`, before a message is written to
+    /// the server log
+    ///
+    /// The message can be changed, such as with [`set_message`](PgBox::set_message),
+    /// or kept out of the server log by setting `error_data.output_to_server` to false.  As
+    /// this is called while a message is being reported, it must not raise an error or panic.
+    fn emit_log(
+        &mut self,
+        error_data: PgBox<pg_sys::ErrorData>,
+        prev_hook: fn(error_data: PgBox<pg_sys::ErrorData>) -> HookResult<()>,
+    ) -> HookResult<()> {
+        prev_hook(error_data)
+    }
+
     /// Called when the transaction aborts
     fn abort(&mut self) {}
 
@@ -146,8 +299,530 @@ pub trait PgHooks {
     fn commit(&mut self) {}
 }
 
+/// How a query accesses one of the relations in its range table, as checked by
+/// [`PgHooks::executor_check_perms`]
+///
+/// Column numbers are attribute numbers, with 0 meaning the whole row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelationAccess {
+    pub relid: pg_sys::Oid,
+    pub relkind: u8,
+    /// The privileges required, a combination of `pg_sys::ACL_SELECT`, `pg_sys::ACL_INSERT`, etc
+    pub required_perms: pg_sys::AclMode,
+    /// The role to check the privileges of, or `InvalidOid` for the current user
+    pub check_as_user: pg_sys::Oid,
+    pub selected_cols: Vec<i32>,
+    pub inserted_cols: Vec<i32>,
+    pub updated_cols: Vec<i32>,
+}
+
+impl RelationAccess {
+    /// The relations accessed through `range_table`, skipping its entries for subqueries, joins,
+    /// functions, and the like
+    pub fn from_range_table(range_table: &PgList<*mut pg_sys::RangeTblEntry>) -> Vec<Self> {
+        range_table
+            .iter_ptr()
+            .map(|rte| unsafe { &*(rte as *mut pg_sys::RangeTblEntry) })
+            .filter(|rte| rte.rtekind == pg_sys::RTEKind_RTE_RELATION)
+            .map(|rte| RelationAccess {
+                relid: rte.relid,
+                relkind: rte.relkind as u8,
+                required_perms: rte.requiredPerms,
+                check_as_user: rte.checkAsUser,
+                selected_cols: unsafe { column_numbers(rte.selectedCols) },
+                inserted_cols: unsafe { column_numbers(rte.insertedCols) },
+                updated_cols: unsafe { column_numbers(rte.updatedCols) },
+            })
+            .collect()
+    }
+
+    /// Does the query require all of the privileges in `mode`?
+    pub fn requires(&self, mode: pg_sys::AclMode) -> bool {
+        self.required_perms & mode == mode
+    }
+}
+
+/// The attribute numbers in a bitmapset of columns, which are offset so system columns fit
+unsafe fn column_numbers(columns: *mut pg_sys::Bitmapset) -> Vec<i32> {
+    let mut numbers = Vec::new();
+    let mut member = -1;
+    loop {
+        member = pg_sys::bms_next_member(columns, member);
+        if member < 0 {
+            break;
+        }
+        numbers.push(member + pg_sys::FirstLowInvalidHeapAttributeNumber);
+    }
+    numbers
+}
+
+/// Safe access to what the executor hooks are most often interested in about the query they're
+/// called for, as `pg_stat_statements` and the like need
+///
+/// The executor's state, and so [`rows_processed`](#method.rows_processed) and the
+/// instrumentation, only exist from after `prev_hook` is called in
+/// [`PgHooks::executor_start`] until it's called in [`PgHooks::executor_end`].
+impl PgBox<pg_sys::QueryDesc> {
+    /// The text of the query being executed, if it's known and is valid UTF-8
+    ///
+    /// This is the whole query string the client sent, which may contain more than this one
+    /// statement.
+    pub fn query_text(&self) -> Option<&str> {
+        if self.sourceText.is_null() {
+            None
+        } else {
+            unsafe { std::ffi::CStr::from_ptr(self.sourceText) }
+                .to_str()
+                .ok()
+        }
+    }
+
+    /// Whether this is a `SELECT`, `INSERT`, `UPDATE`, or `DELETE`
+    pub fn operation(&self) -> pg_sys::CmdType {
+        self.operation
+    }
+
+    /// The plan being executed
+    pub fn planned_stmt(&self) -> PgBox<pg_sys::PlannedStmt> {
+        PgBox::from_pg(self.plannedstmt)
+    }
+
+    /// The planner's estimate of the cost of fetching every row
+    pub fn estimated_total_cost(&self) -> f64 {
+        self.plan().total_cost
+    }
+
+    /// The planner's estimate of the cost of fetching the first row
+    pub fn estimated_startup_cost(&self) -> f64 {
+        self.plan().startup_cost
+    }
+
+    /// The planner's estimate of how many rows the query will produce
+    pub fn estimated_rows(&self) -> f64 {
+        self.plan().plan_rows
+    }
+
+    /// How many rows the query has processed so far, or `None` if the executor isn't running
+    pub fn rows_processed(&self) -> Option<u64> {
+        if self.estate.is_null() {
+            None
+        } else {
+            Some(unsafe { (*self.estate).es_processed })
+        }
+    }
+
+    /// Have the executor time the whole query, for [`total_time`](#method.total_time)
+    ///
+    /// Must be called in [`PgHooks::executor_start`].  Called before its `prev_hook`, which
+    /// consumes the `QueryDesc`, the timing is set up once the executor has started.
+    pub fn enable_timing(&mut self) {
+        if self.estate.is_null() {
+            unsafe { TIMING_REQUESTED.push(self.as_ptr()) };
+            return;
+        }
+
+        if self.totaltime.is_null() {
+            let estate = self.estate;
+            self.totaltime = PgMemoryContexts::For(unsafe { (*estate).es_query_cxt })
+                .switch_to(|_| unsafe { alloc_instrumentation() });
+        }
+    }
+
+    /// The instrumentation for the whole query, if it's been enabled by this or another
+    /// extension, or by `EXPLAIN ANALYZE`
+    pub fn instrumentation(&self) -> Option<&pg_sys::Instrumentation> {
+        unsafe { self.totaltime.as_ref() }
+    }
+
+    /// How long the query spent in the executor, if [`enable_timing`](#method.enable_timing)
+    /// was called
+    ///
+    /// This finishes the query's instrumentation, so must only be called in
+    /// [`PgHooks::executor_end`], before calling its `prev_hook`.
+    pub fn total_time(&mut self) -> Option<std::time::Duration> {
+        if self.totaltime.is_null() {
+            return None;
+        }
+
+        unsafe {
+            pg_sys::InstrEndLoop(self.totaltime);
+            Some(std::time::Duration::from_secs_f64((*self.totaltime).total))
+        }
+    }
+
+    fn plan(&self) -> &pg_sys::Plan {
+        unsafe { &*(*self.plannedstmt).planTree }
+    }
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+unsafe fn alloc_instrumentation() -> *mut pg_sys::Instrumentation {
+    pg_sys::InstrAlloc(1, pg_sys::InstrumentOption_INSTRUMENT_ALL as i32)
+}
+
+#[cfg(feature = "pg14")]
+unsafe fn alloc_instrumentation() -> *mut pg_sys::Instrumentation {
+    pg_sys::InstrAlloc(1, pg_sys::InstrumentOption_INSTRUMENT_ALL as i32, false)
+}
+
+/// The state a query's `queryId` is computed from, as passed to [`PgHooks::post_parse_analyze`]
+#[cfg(feature = "pg14")]
+pub use pg_sys::JumbleState;
+
+/// The state a query's `queryId` is computed from, which Postgres before 14 doesn't pass to
+/// [`PgHooks::post_parse_analyze`]
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+pub enum JumbleState {}
+
+#[cfg(feature = "pg14")]
+impl PgBox<JumbleState> {
+    /// The byte offsets of the constants in the query's source text, in the order they were
+    /// found, which is what normalizing the query text replaces with `$n` parameters
+    pub fn constant_locations(&self) -> Vec<i32> {
+        (0..self.clocations_count as usize)
+            .map(|i| unsafe { (*self.clocations.add(i)).location })
+            .collect()
+    }
+}
+
+/// How an object is being accessed, as told to [`PgHooks::object_access`]
+pub enum ObjectAccess {
+    /// The object was just created, by the user or, if `is_internal`, as part of another
+    /// command
+    Create { is_internal: bool },
+    /// The object is about to be dropped, with `flags` being a combination of
+    /// `pg_sys::PERFORM_DELETION_INTERNAL`, `pg_sys::PERFORM_DELETION_CONCURRENTLY`, etc
+    Drop { flags: i32 },
+    /// The object was just altered, with `auxiliary_id` being the object that was attached to
+    /// or detached from it, such as an inherited table, if there is one
+    Alter {
+        auxiliary_id: pg_sys::Oid,
+        is_internal: bool,
+    },
+    /// The schema is being searched for an object
+    NamespaceSearch(NamespaceSearch),
+    /// The function is about to be executed
+    FunctionExecute,
+    /// The table is about to be truncated, on Postgres 13 and later
+    Truncate,
+    /// An access of a kind added by a later version of Postgres
+    Other(pg_sys::ObjectAccessType),
+}
+
+/// A search of a schema, which [`PgHooks::object_access`] can deny
+pub struct NamespaceSearch(PgBox<pg_sys::ObjectAccessNamespaceSearch>);
+
+impl NamespaceSearch {
+    /// Whether the hook is expected to raise an error itself to deny the search, rather than
+    /// calling [`deny`](#method.deny)
+    pub fn ereport_on_violation(&self) -> bool {
+        self.0.ereport_on_violation
+    }
+
+    /// Whether the search is still allowed
+    pub fn allowed(&self) -> bool {
+        self.0.result
+    }
+
+    /// Leave the schema out of the search, as if the user had no access to it
+    pub fn deny(&mut self) {
+        self.0.result = false;
+    }
+}
+
+/// An access of an object, as told to [`PgHooks::object_access`]
+///
+/// `class_id` is the OID of the catalog the object is in, such as `pg_sys::RelationRelationId`
+/// for tables, and `sub_id` is the column number for columns of tables, or otherwise 0.
+pub struct ObjectAccessEvent {
+    pub access: ObjectAccess,
+    pub class_id: pg_sys::Oid,
+    pub object_id: pg_sys::Oid,
+    pub sub_id: i32,
+    access_type: pg_sys::ObjectAccessType,
+    arg: void_mut_ptr,
+}
+
+impl ObjectAccessEvent {
+    unsafe fn new(
+        access_type: pg_sys::ObjectAccessType,
+        class_id: pg_sys::Oid,
+        object_id: pg_sys::Oid,
+        sub_id: i32,
+        arg: void_mut_ptr,
+    ) -> Self {
+        let access = match access_type {
+            pg_sys::ObjectAccessType_OAT_POST_CREATE => ObjectAccess::Create {
+                is_internal: (*(arg as *mut pg_sys::ObjectAccessPostCreate)).is_internal,
+            },
+            pg_sys::ObjectAccessType_OAT_DROP => ObjectAccess::Drop {
+                flags: (*(arg as *mut pg_sys::ObjectAccessDrop)).dropflags,
+            },
+            pg_sys::ObjectAccessType_OAT_POST_ALTER => {
+                let post_alter = &*(arg as *mut pg_sys::ObjectAccessPostAlter);
+                ObjectAccess::Alter {
+                    auxiliary_id: post_alter.auxiliary_id,
+                    is_internal: post_alter.is_internal,
+                }
+            }
+            pg_sys::ObjectAccessType_OAT_NAMESPACE_SEARCH => {
+                ObjectAccess::NamespaceSearch(NamespaceSearch(PgBox::from_pg(arg as *mut _)))
+            }
+            pg_sys::ObjectAccessType_OAT_FUNCTION_EXECUTE => ObjectAccess::FunctionExecute,
+            #[cfg(any(feature = "pg13", feature = "pg14"))]
+            pg_sys::ObjectAccessType_OAT_TRUNCATE => ObjectAccess::Truncate,
+            other => ObjectAccess::Other(other),
+        };
+
+        ObjectAccessEvent {
+            access,
+            class_id,
+            object_id,
+            sub_id,
+            access_type,
+            arg,
+        }
+    }
+
+    /// The object being accessed, such as for `getObjectDescription()`
+    pub fn object_address(&self) -> pg_sys::ObjectAddress {
+        pg_sys::ObjectAddress {
+            classId: self.class_id,
+            objectId: self.object_id,
+            objectSubId: self.sub_id,
+        }
+    }
+}
+
+/// Safe access to the connection being authenticated, for [`PgHooks::client_authentication`]
+///
+/// The current backend's connection is `pg_sys::MyProcPort`.
+impl PgBox<pg_sys::Port> {
+    /// The role the client asked to connect as
+    pub fn user_name(&self) -> Option<&str> {
+        unsafe { error_data_str(self.user_name) }
+    }
+
+    /// The database the client asked to connect to
+    pub fn database_name(&self) -> Option<&str> {
+        unsafe { error_data_str(self.database_name) }
+    }
+
+    /// The client's IP address, or `"[local]"` for a Unix-domain socket
+    pub fn remote_host(&self) -> Option<&str> {
+        unsafe { error_data_str(self.remote_host) }
+    }
+
+    /// The client's host name, if `log_hostname` is on or `pg_hba.conf` needed it
+    pub fn remote_hostname(&self) -> Option<&str> {
+        unsafe { error_data_str(self.remote_hostname) }
+    }
+
+    /// The client's port, which is empty for a Unix-domain socket
+    pub fn remote_port(&self) -> Option<&str> {
+        unsafe { error_data_str(self.remote_port) }
+    }
+
+    /// How the client is authenticated, such as `pg_sys::UserAuth_uaSCRAM`, as chosen by its
+    /// `pg_hba.conf` line
+    pub fn auth_method(&self) -> Option<pg_sys::UserAuth> {
+        unsafe { self.hba.as_ref() }.map(|hba| hba.auth_method)
+    }
+}
+
+/// Turn away the client being authenticated, with a `FATAL` error whose message it's sent
+///
+/// This is for [`PgHooks::client_authentication`], and ends the backend.
+pub fn reject_connection(message: &str) -> ! {
+    crate::ereport(
+        crate::PgLogLevel::FATAL,
+        crate::PgSqlErrorCode::ERRCODE_INVALID_AUTHORIZATION_SPECIFICATION,
+        message,
+        file!(),
+        line!(),
+        column!(),
+    );
+    unreachable!("FATAL ereport returned")
+}
+
+/// A password being checked by [`PgHooks::check_password`], in the form it was given
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Password<'a> {
+    Plaintext(&'a str),
+    Md5(&'a str),
+    ScramSha256(&'a str),
+}
+
+impl<'a> Password<'a> {
+    unsafe fn from_pg(
+        password: *const std::os::raw::c_char,
+        password_type: pg_sys::PasswordType,
+    ) -> Self {
+        let password = error_data_str(password).unwrap_or_default();
+        match password_type {
+            pg_sys::PasswordType_PASSWORD_TYPE_MD5 => Password::Md5(password),
+            pg_sys::PasswordType_PASSWORD_TYPE_SCRAM_SHA_256 => Password::ScramSha256(password),
+            _ => Password::Plaintext(password),
+        }
+    }
+
+    /// The password, or its hash
+    pub fn as_str(&self) -> &'a str {
+        match self {
+            Password::Plaintext(s) | Password::Md5(s) | Password::ScramSha256(s) => s,
+        }
+    }
+
+    /// Whether the client hashed the password, so it can't be checked for its content
+    pub fn is_hashed(&self) -> bool {
+        !matches!(self, Password::Plaintext(_))
+    }
+
+    fn password_type(&self) -> pg_sys::PasswordType {
+        match self {
+            Password::Plaintext(_) => pg_sys::PasswordType_PASSWORD_TYPE_PLAINTEXT,
+            Password::Md5(_) => pg_sys::PasswordType_PASSWORD_TYPE_MD5,
+            Password::ScramSha256(_) => pg_sys::PasswordType_PASSWORD_TYPE_SCRAM_SHA_256,
+        }
+    }
+}
+
+/// Reject the password being checked, with an `ERROR` explaining the policy it doesn't meet
+///
+/// This is for [`PgHooks::check_password`].
+pub fn reject_password(message: &str) -> ! {
+    crate::ereport(
+        crate::PgLogLevel::ERROR,
+        crate::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+        message,
+        file!(),
+        line!(),
+        column!(),
+    );
+    unreachable!("ERROR ereport returned")
+}
+
+/// Safe access to the statement being analyzed, for [`PgHooks::post_parse_analyze`]
+impl PgBox<pg_sys::ParseState> {
+    /// The text the query was parsed from, if it's known and is valid UTF-8
+    ///
+    /// Like a `QueryDesc`'s `query_text()`, this may contain more statements than the one
+    /// being analyzed, whose extent is given by the `Query`'s `stmt_location` and `stmt_len`.
+    pub fn source_text(&self) -> Option<&str> {
+        unsafe { error_data_str(self.p_sourcetext) }
+    }
+}
+
+/// Safe access to a message being reported, for [`PgHooks::emit_log`]
+impl PgBox<pg_sys::ErrorData> {
+    /// The message's severity, as it's labeled in the server log, such as `"WARNING"`
+    pub fn severity(&self) -> &'static str {
+        let elevel = self.elevel as u32;
+        if elevel <= pg_sys::DEBUG1 {
+            "DEBUG"
+        } else if elevel == pg_sys::LOG || elevel == pg_sys::LOG_SERVER_ONLY {
+            "LOG"
+        } else if elevel == pg_sys::INFO {
+            "INFO"
+        } else if elevel == pg_sys::NOTICE {
+            "NOTICE"
+        } else if elevel < pg_sys::ERROR {
+            "WARNING"
+        } else if elevel == pg_sys::ERROR {
+            "ERROR"
+        } else if elevel == pg_sys::FATAL {
+            "FATAL"
+        } else {
+            "PANIC"
+        }
+    }
+
+    /// The message's five-character SQLSTATE, such as `"42P01"`
+    pub fn sqlstate(&self) -> String {
+        unsafe { std::ffi::CStr::from_ptr(pg_sys::unpack_sql_state(self.sqlerrcode)) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        unsafe { error_data_str(self.message) }
+    }
+
+    pub fn detail(&self) -> Option<&str> {
+        unsafe { error_data_str(self.detail) }
+    }
+
+    /// The detail meant only for the server log, which is logged instead of
+    /// [`detail`](#method.detail) if it's set
+    pub fn detail_log(&self) -> Option<&str> {
+        unsafe { error_data_str(self.detail_log) }
+    }
+
+    pub fn hint(&self) -> Option<&str> {
+        unsafe { error_data_str(self.hint) }
+    }
+
+    pub fn context(&self) -> Option<&str> {
+        unsafe { error_data_str(self.context) }
+    }
+
+    /// The source file, line, and function that reported the message, if known
+    pub fn location(&self) -> Option<(&str, i32, &str)> {
+        unsafe {
+            Some((
+                error_data_str(self.filename)?,
+                self.lineno,
+                error_data_str(self.funcname).unwrap_or_default(),
+            ))
+        }
+    }
+
+    /// The process ID of the backend reporting the message
+    pub fn backend_pid(&self) -> i32 {
+        unsafe { pg_sys::MyProcPid }
+    }
+
+    /// The `application_name` of the backend reporting the message
+    pub fn application_name(&self) -> Option<&str> {
+        unsafe { error_data_str(pg_sys::application_name) }
+    }
+
+    /// Replace the message, such as to redact it
+    pub fn set_message(&mut self, message: &str) {
+        self.message = self.copy_str(message);
+    }
+
+    /// Replace the detail, or remove it with `None`
+    pub fn set_detail(&mut self, detail: Option<&str>) {
+        self.detail = detail.map_or(std::ptr::null_mut(), |detail| self.copy_str(detail));
+    }
+
+    /// Replace the server-log-only detail, or remove it with `None`
+    pub fn set_detail_log(&mut self, detail_log: Option<&str>) {
+        self.detail_log =
+            detail_log.map_or(std::ptr::null_mut(), |detail_log| self.copy_str(detail_log));
+    }
+
+    /// Copy `s` into the memory context that the rest of the message is allocated in
+    fn copy_str(&self, s: &str) -> *mut std::os::raw::c_char {
+        // a string with a null byte in it is cut short, as Postgres would see it
+        let s = s.split('\0').next().unwrap_or_default();
+        let s = std::ffi::CString::new(s).unwrap();
+        unsafe { pg_sys::MemoryContextStrdup(self.assoc_context, s.as_ptr()) }
+    }
+}
+
+unsafe fn error_data_str<'a>(s: *const std::os::raw::c_char) -> Option<&'a str> {
+    if s.is_null() {
+        None
+    } else {
+        std::ffi::CStr::from_ptr(s).to_str().ok()
+    }
+}
+
 struct Hooks {
-    current_hook: Box<&'static mut (dyn PgHooks)>,
+    /// In the order they were registered, which is the reverse of the order they're called in
+    registered: Vec<*mut dyn PgHooks>,
     prev_executor_start_hook: pg_sys::ExecutorStart_hook_type,
     prev_executor_run_hook: pg_sys::ExecutorRun_hook_type,
     prev_executor_finish_hook: pg_sys::ExecutorFinish_hook_type,
@@ -155,17 +830,34 @@ struct Hooks {
     prev_executor_check_perms_hook: pg_sys::ExecutorCheckPerms_hook_type,
     prev_process_utility_hook: pg_sys::ProcessUtility_hook_type,
     prev_planner_hook: pg_sys::planner_hook_type,
+    prev_post_parse_analyze_hook: pg_sys::post_parse_analyze_hook_type,
+    prev_object_access_hook: pg_sys::object_access_hook_type,
+    prev_client_authentication_hook: pg_sys::ClientAuthentication_hook_type,
+    prev_check_password_hook: pg_sys::check_password_hook_type,
+    prev_explain_one_query_hook: pg_sys::ExplainOneQuery_hook_type,
+    prev_emit_log_hook: pg_sys::emit_log_hook_type,
 }
 
 static mut HOOKS: Option<Hooks> = None;
 
-/// Register a `PgHook` instance to respond to the various hook points
+/// The queries whose hooks called `enable_timing()` before the executor was started
+static mut TIMING_REQUESTED: Vec<*mut pg_sys::QueryDesc> = Vec::new();
+
+/// Register a `PgHooks` instance to respond to the various hook points
+///
+/// Any number of instances can be registered, by this extension and others.  Like Postgres'
+/// own hooks, the most recently registered is called first, and its `prev_hook` calls the one
+/// registered before it, and so on, until the last calls the hook that was installed before
+/// pgx's, or Postgres' standard behavior.  A hook which doesn't call its `prev_hook` keeps the
+/// hooks after it from being called at all.
 pub unsafe fn register_hook(hook: &'static mut (dyn PgHooks)) {
-    if HOOKS.is_some() {
-        panic!("PgHook instance already registered");
+    if let Some(hooks) = HOOKS.as_mut() {
+        hooks.registered.push(hook);
+        return;
     }
+
     HOOKS = Some(Hooks {
-        current_hook: Box::new(hook),
+        registered: vec![hook as *mut dyn PgHooks],
         prev_executor_start_hook: pg_sys::ExecutorStart_hook
             .replace(pgx_executor_start)
             .or(Some(pgx_standard_executor_start_wrapper)),
@@ -187,15 +879,33 @@ pub unsafe fn register_hook(hook: &'static mut (dyn PgHooks)) {
         prev_planner_hook: pg_sys::planner_hook
             .replace(pgx_planner)
             .or(Some(pgx_standard_planner_wrapper)),
+        prev_post_parse_analyze_hook: pg_sys::post_parse_analyze_hook
+            .replace(pgx_post_parse_analyze)
+            .or(Some(pgx_standard_post_parse_analyze_wrapper)),
+        prev_object_access_hook: pg_sys::object_access_hook
+            .replace(pgx_object_access)
+            .or(Some(pgx_standard_object_access_wrapper)),
+        prev_client_authentication_hook: pg_sys::ClientAuthentication_hook
+            .replace(pgx_client_authentication)
+            .or(Some(pgx_standard_client_authentication_wrapper)),
+        prev_check_password_hook: pg_sys::check_password_hook
+            .replace(pgx_check_password)
+            .or(Some(pgx_standard_check_password_wrapper)),
+        prev_explain_one_query_hook: pg_sys::ExplainOneQuery_hook
+            .replace(pgx_explain_one_query)
+            .or(Some(pgx_standard_explain_one_query_wrapper)),
+        prev_emit_log_hook: pg_sys::emit_log_hook
+            .replace(pgx_emit_log)
+            .or(Some(pgx_standard_emit_log_wrapper)),
     });
 
     unsafe extern "C" fn xact_callback(event: pg_sys::XactEvent, _: void_mut_ptr) {
         match event {
             pg_sys::XactEvent_XACT_EVENT_ABORT => {
-                crate::guard(|| HOOKS.as_mut().unwrap().current_hook.abort());
+                crate::guard(|| registered_hooks().for_each(|hook| hook.abort()));
             }
             pg_sys::XactEvent_XACT_EVENT_PRE_COMMIT => {
-                crate::guard(|| HOOKS.as_mut().unwrap().current_hook.commit());
+                crate::guard(|| registered_hooks().for_each(|hook| hook.commit()));
             }
             _ => { /* noop */ }
         }
@@ -204,9 +914,85 @@ pub unsafe fn register_hook(hook: &'static mut (dyn PgHooks)) {
     pg_sys::RegisterXactCallback(Some(xact_callback), std::ptr::null_mut());
 }
 
+/// The registered hooks, in the order they're called in
+unsafe fn registered_hooks() -> impl Iterator<Item = &'static mut dyn PgHooks> {
+    HOOKS
+        .as_ref()
+        .unwrap()
+        .registered
+        .iter()
+        .rev()
+        .map(|hook| &mut **hook)
+}
+
+/// How many of the registered hooks are still to be called for the hook point being run
+static mut REMAINING_HOOKS: usize = 0;
+
+/// The registered hook which a hook point's `prev_hook` calls next, which also puts the chain
+/// back where it was once dropped, so a hook can call its `prev_hook` more than once, and hook
+/// points run while another is, such as the executor's while planning, start a chain of their
+/// own
+struct NextHook {
+    hook: Option<*mut dyn PgHooks>,
+    remaining: usize,
+}
+
+impl NextHook {
+    /// Start calling the registered hooks for a hook point, from the most recently registered
+    fn start() -> Self {
+        unsafe {
+            let remaining = REMAINING_HOOKS;
+            REMAINING_HOOKS = HOOKS.as_ref().unwrap().registered.len();
+            NextHook {
+                hook: None,
+                remaining,
+            }
+        }
+    }
+
+    /// The next registered hook, or `None` once they've all been called
+    fn take() -> Option<Self> {
+        unsafe {
+            if REMAINING_HOOKS == 0 {
+                return None;
+            }
+
+            let remaining = REMAINING_HOOKS;
+            REMAINING_HOOKS -= 1;
+            Some(NextHook {
+                hook: Some(HOOKS.as_ref().unwrap().registered[REMAINING_HOOKS]),
+                remaining,
+            })
+        }
+    }
+}
+
+impl Deref for NextHook {
+    type Target = dyn PgHooks;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.hook.unwrap() }
+    }
+}
+
+impl DerefMut for NextHook {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.hook.unwrap() }
+    }
+}
+
+impl Drop for NextHook {
+    fn drop(&mut self) {
+        unsafe { REMAINING_HOOKS = self.remaining }
+    }
+}
+
 #[pg_guard]
 unsafe extern "C" fn pgx_executor_start(query_desc: *mut pg_sys::QueryDesc, eflags: i32) {
     fn prev(query_desc: PgBox<pg_sys::QueryDesc>, eflags: i32) -> HookResult<()> {
+        if let Some(mut hook) = NextHook::take() {
+            return hook.executor_start(query_desc, eflags, prev);
+        }
         unsafe {
             (HOOKS
                 .as_mut()
@@ -217,8 +1003,14 @@ unsafe extern "C" fn pgx_executor_start(query_desc: *mut pg_sys::QueryDesc, efla
         }
         HookResult::new(())
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.executor_start(PgBox::from_pg(query_desc), eflags, prev);
+    let _chain = NextHook::start();
+    prev(PgBox::from_pg(query_desc), eflags);
+
+    let requests = TIMING_REQUESTED.len();
+    TIMING_REQUESTED.retain(|&requested| requested != query_desc);
+    if TIMING_REQUESTED.len() != requests {
+        PgBox::from_pg(query_desc).enable_timing();
+    }
 }
 
 #[pg_guard]
@@ -234,6 +1026,9 @@ unsafe extern "C" fn pgx_executor_run(
         count: u64,
         execute_once: bool,
     ) -> HookResult<()> {
+        if let Some(mut hook) = NextHook::take() {
+            return hook.executor_run(query_desc, direction, count, execute_once, prev);
+        }
         unsafe {
             (HOOKS
                 .as_mut()
@@ -244,19 +1039,16 @@ unsafe extern "C" fn pgx_executor_run(
         }
         HookResult::new(())
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.executor_run(
-        PgBox::from_pg(query_desc),
-        direction,
-        count,
-        execute_once,
-        prev,
-    );
+    let _chain = NextHook::start();
+    prev(PgBox::from_pg(query_desc), direction, count, execute_once);
 }
 
 #[pg_guard]
 unsafe extern "C" fn pgx_executor_finish(query_desc: *mut pg_sys::QueryDesc) {
     fn prev(query_desc: PgBox<pg_sys::QueryDesc>) -> HookResult<()> {
+        if let Some(mut hook) = NextHook::take() {
+            return hook.executor_finish(query_desc, prev);
+        }
         unsafe {
             (HOOKS
                 .as_mut()
@@ -267,13 +1059,16 @@ unsafe extern "C" fn pgx_executor_finish(query_desc: *mut pg_sys::QueryDesc) {
         }
         HookResult::new(())
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.executor_finish(PgBox::from_pg(query_desc), prev);
+    let _chain = NextHook::start();
+    prev(PgBox::from_pg(query_desc));
 }
 
 #[pg_guard]
 unsafe extern "C" fn pgx_executor_end(query_desc: *mut pg_sys::QueryDesc) {
     fn prev(query_desc: PgBox<pg_sys::QueryDesc>) -> HookResult<()> {
+        if let Some(mut hook) = NextHook::take() {
+            return hook.executor_end(query_desc, prev);
+        }
         unsafe {
             (HOOKS
                 .as_mut()
@@ -284,8 +1079,8 @@ unsafe extern "C" fn pgx_executor_end(query_desc: *mut pg_sys::QueryDesc) {
         }
         HookResult::new(())
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.executor_end(PgBox::from_pg(query_desc), prev);
+    let _chain = NextHook::start();
+    prev(PgBox::from_pg(query_desc));
 }
 
 #[pg_guard]
@@ -297,6 +1092,9 @@ unsafe extern "C" fn pgx_executor_check_perms(
         range_table: PgList<*mut pg_sys::RangeTblEntry>,
         ereport_on_violation: bool,
     ) -> HookResult<bool> {
+        if let Some(mut hook) = NextHook::take() {
+            return hook.executor_check_perms(range_table, ereport_on_violation, prev);
+        }
         HookResult::new(unsafe {
             (HOOKS
                 .as_mut()
@@ -306,9 +1104,8 @@ unsafe extern "C" fn pgx_executor_check_perms(
                 .unwrap())(range_table.into_pg(), ereport_on_violation)
         })
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.executor_check_perms(PgList::from_pg(range_table), ereport_on_violation, prev)
-        .inner
+    let _chain = NextHook::start();
+    prev(PgList::from_pg(range_table), ereport_on_violation).inner
 }
 
 #[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
@@ -325,13 +1122,26 @@ unsafe extern "C" fn pgx_process_utility(
     fn prev(
         pstmt: PgBox<pg_sys::PlannedStmt>,
         query_string: &std::ffi::CStr,
-        _read_only_tree: Option<bool>,
+        read_only_tree: Option<bool>,
         context: pg_sys::ProcessUtilityContext,
         params: PgBox<pg_sys::ParamListInfoData>,
         query_env: PgBox<pg_sys::QueryEnvironment>,
         dest: PgBox<pg_sys::DestReceiver>,
         completion_tag: *mut pg_sys::QueryCompletion,
     ) -> HookResult<()> {
+        if let Some(mut hook) = NextHook::take() {
+            return hook.process_utility_hook(
+                pstmt,
+                query_string,
+                read_only_tree,
+                context,
+                params,
+                query_env,
+                dest,
+                completion_tag,
+                prev,
+            );
+        }
         HookResult::new(unsafe {
             (HOOKS
                 .as_mut()
@@ -350,8 +1160,8 @@ unsafe extern "C" fn pgx_process_utility(
         })
     }
 
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.process_utility_hook(
+    let _chain = NextHook::start();
+    prev(
         PgBox::from_pg(pstmt),
         std::ffi::CStr::from_ptr(query_string),
         None,
@@ -360,7 +1170,6 @@ unsafe extern "C" fn pgx_process_utility(
         PgBox::from_pg(query_env),
         PgBox::from_pg(dest),
         completion_tag,
-        prev,
     )
     .inner
 }
@@ -386,6 +1195,19 @@ unsafe extern "C" fn pgx_process_utility(
         dest: PgBox<pg_sys::DestReceiver>,
         completion_tag: *mut pg_sys::QueryCompletion,
     ) -> HookResult<()> {
+        if let Some(mut hook) = NextHook::take() {
+            return hook.process_utility_hook(
+                pstmt,
+                query_string,
+                read_only_tree,
+                context,
+                params,
+                query_env,
+                dest,
+                completion_tag,
+                prev,
+            );
+        }
         HookResult::new(unsafe {
             (HOOKS
                 .as_mut()
@@ -405,8 +1227,8 @@ unsafe extern "C" fn pgx_process_utility(
         })
     }
 
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.process_utility_hook(
+    let _chain = NextHook::start();
+    prev(
         PgBox::from_pg(pstmt),
         std::ffi::CStr::from_ptr(query_string),
         Some(read_only_tree),
@@ -415,7 +1237,6 @@ unsafe extern "C" fn pgx_process_utility(
         PgBox::from_pg(query_env),
         PgBox::from_pg(dest),
         completion_tag,
-        prev,
     )
     .inner
 }
@@ -450,10 +1271,13 @@ unsafe extern "C" fn pgx_planner_impl(
 ) -> *mut pg_sys::PlannedStmt {
     fn prev(
         parse: PgBox<pg_sys::Query>,
-        #[allow(unused_variables)] query_string: *const ::std::os::raw::c_char,
+        query_string: *const ::std::os::raw::c_char,
         cursor_options: i32,
         bound_params: PgBox<pg_sys::ParamListInfoData>,
     ) -> HookResult<*mut pg_sys::PlannedStmt> {
+        if let Some(mut hook) = NextHook::take() {
+            return hook.planner(parse, query_string, cursor_options, bound_params, prev);
+        }
         HookResult::new(unsafe {
             #[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12"))]
             {
@@ -475,17 +1299,358 @@ unsafe extern "C" fn pgx_planner_impl(
             }
         })
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.planner(
+    let _chain = NextHook::start();
+    prev(
         PgBox::from_pg(parse),
         query_string,
         cursor_options,
         PgBox::from_pg(bound_params),
-        prev,
     )
     .inner
 }
 
+/// Plan `parse` with Postgres' own planner, skipping any `planner_hook`s, such as for a
+/// [`PgHooks::planner`] that wants a plan the other hooks haven't had a chance to change
+///
+/// `query_string` is ignored on Postgres 10-12.
+pub fn standard_planner(
+    parse: PgBox<pg_sys::Query>,
+    #[allow(unused_variables)] query_string: *const std::os::raw::c_char,
+    cursor_options: i32,
+    bound_params: PgBox<pg_sys::ParamListInfoData>,
+) -> HookResult<*mut pg_sys::PlannedStmt> {
+    HookResult::new(unsafe {
+        #[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12"))]
+        {
+            pgx_standard_planner_wrapper(parse.into_pg(), cursor_options, bound_params.into_pg())
+        }
+
+        #[cfg(any(feature = "pg13", feature = "pg14"))]
+        {
+            pgx_standard_planner_wrapper(
+                parse.into_pg(),
+                query_string,
+                cursor_options,
+                bound_params.into_pg(),
+            )
+        }
+    })
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+#[pg_guard]
+unsafe extern "C" fn pgx_post_parse_analyze(
+    parse_state: *mut pg_sys::ParseState,
+    query: *mut pg_sys::Query,
+) {
+    pgx_post_parse_analyze_impl(parse_state, query, std::ptr::null_mut())
+}
+
+#[cfg(feature = "pg14")]
+#[pg_guard]
+unsafe extern "C" fn pgx_post_parse_analyze(
+    parse_state: *mut pg_sys::ParseState,
+    query: *mut pg_sys::Query,
+    jumble_state: *mut pg_sys::JumbleState,
+) {
+    pgx_post_parse_analyze_impl(parse_state, query, jumble_state)
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgx_post_parse_analyze_impl(
+    parse_state: *mut pg_sys::ParseState,
+    query: *mut pg_sys::Query,
+    jumble_state: *mut JumbleState,
+) {
+    fn prev(
+        parse_state: PgBox<pg_sys::ParseState>,
+        query: PgBox<pg_sys::Query>,
+        jumble_state: Option<PgBox<JumbleState>>,
+    ) -> HookResult<()> {
+        if let Some(mut hook) = NextHook::take() {
+            return hook.post_parse_analyze(parse_state, query, jumble_state, prev);
+        }
+        unsafe {
+            #[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+            {
+                (HOOKS
+                    .as_mut()
+                    .unwrap()
+                    .prev_post_parse_analyze_hook
+                    .as_ref()
+                    .unwrap())(parse_state.into_pg(), query.into_pg())
+            }
+
+            #[cfg(feature = "pg14")]
+            {
+                (HOOKS
+                    .as_mut()
+                    .unwrap()
+                    .prev_post_parse_analyze_hook
+                    .as_ref()
+                    .unwrap())(
+                    parse_state.into_pg(),
+                    query.into_pg(),
+                    jumble_state
+                        .map_or(std::ptr::null_mut(), |jumble_state| jumble_state.into_pg()),
+                )
+            }
+        }
+        HookResult::new(())
+    }
+    let jumble_state = if jumble_state.is_null() {
+        None
+    } else {
+        Some(PgBox::from_pg(jumble_state))
+    };
+    let _chain = NextHook::start();
+    prev(
+        PgBox::from_pg(parse_state),
+        PgBox::from_pg(query),
+        jumble_state,
+    );
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgx_object_access(
+    access: pg_sys::ObjectAccessType,
+    class_id: pg_sys::Oid,
+    object_id: pg_sys::Oid,
+    sub_id: i32,
+    arg: void_mut_ptr,
+) {
+    fn prev(event: ObjectAccessEvent) -> HookResult<()> {
+        if let Some(mut hook) = NextHook::take() {
+            return hook.object_access(event, prev);
+        }
+        unsafe {
+            (HOOKS
+                .as_mut()
+                .unwrap()
+                .prev_object_access_hook
+                .as_ref()
+                .unwrap())(
+                event.access_type,
+                event.class_id,
+                event.object_id,
+                event.sub_id,
+                event.arg,
+            )
+        }
+        HookResult::new(())
+    }
+    let _chain = NextHook::start();
+    prev(ObjectAccessEvent::new(
+        access, class_id, object_id, sub_id, arg,
+    ));
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgx_client_authentication(port: *mut pg_sys::Port, status: i32) {
+    fn prev(port: PgBox<pg_sys::Port>, status: i32) -> HookResult<()> {
+        if let Some(mut hook) = NextHook::take() {
+            return hook.client_authentication(port, status, prev);
+        }
+        unsafe {
+            (HOOKS
+                .as_mut()
+                .unwrap()
+                .prev_client_authentication_hook
+                .as_ref()
+                .unwrap())(port.into_pg(), status)
+        }
+        HookResult::new(())
+    }
+    let _chain = NextHook::start();
+    prev(PgBox::from_pg(port), status);
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgx_check_password(
+    username: *const std::os::raw::c_char,
+    shadow_pass: *const std::os::raw::c_char,
+    password_type: pg_sys::PasswordType,
+    validuntil_time: pg_sys::Datum,
+    validuntil_null: bool,
+) {
+    fn prev(
+        username: &str,
+        password: Password,
+        valid_until: Option<pg_sys::TimestampTz>,
+    ) -> HookResult<()> {
+        if let Some(mut hook) = NextHook::take() {
+            return hook.check_password(username, password, valid_until, prev);
+        }
+        let username = std::ffi::CString::new(username).unwrap();
+        let password_str = std::ffi::CString::new(password.as_str()).unwrap();
+        unsafe {
+            (HOOKS
+                .as_mut()
+                .unwrap()
+                .prev_check_password_hook
+                .as_ref()
+                .unwrap())(
+                username.as_ptr(),
+                password_str.as_ptr(),
+                password.password_type(),
+                valid_until.unwrap_or(0) as pg_sys::Datum,
+                valid_until.is_none(),
+            )
+        }
+        HookResult::new(())
+    }
+    let valid_until = if validuntil_null {
+        None
+    } else {
+        Some(validuntil_time as pg_sys::TimestampTz)
+    };
+    let _chain = NextHook::start();
+    prev(
+        error_data_str(username).unwrap_or_default(),
+        Password::from_pg(shadow_pass, password_type),
+        valid_until,
+    );
+}
+
+#[cfg(feature = "pg10")]
+#[pg_guard]
+unsafe extern "C" fn pgx_explain_one_query(
+    query: *mut pg_sys::Query,
+    cursor_options: i32,
+    into: *mut pg_sys::IntoClause,
+    es: *mut pg_sys::ExplainState,
+    query_string: *const std::os::raw::c_char,
+    params: pg_sys::ParamListInfo,
+) {
+    pgx_explain_one_query_impl(
+        query,
+        cursor_options,
+        into,
+        es,
+        query_string,
+        params,
+        std::ptr::null_mut(),
+    )
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+#[pg_guard]
+unsafe extern "C" fn pgx_explain_one_query(
+    query: *mut pg_sys::Query,
+    cursor_options: i32,
+    into: *mut pg_sys::IntoClause,
+    es: *mut pg_sys::ExplainState,
+    query_string: *const std::os::raw::c_char,
+    params: pg_sys::ParamListInfo,
+    query_env: *mut pg_sys::QueryEnvironment,
+) {
+    pgx_explain_one_query_impl(
+        query,
+        cursor_options,
+        into,
+        es,
+        query_string,
+        params,
+        query_env,
+    )
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgx_explain_one_query_impl(
+    query: *mut pg_sys::Query,
+    cursor_options: i32,
+    into: *mut pg_sys::IntoClause,
+    es: *mut pg_sys::ExplainState,
+    query_string: *const std::os::raw::c_char,
+    params: pg_sys::ParamListInfo,
+    query_env: *mut pg_sys::QueryEnvironment,
+) {
+    fn prev(
+        query: PgBox<pg_sys::Query>,
+        cursor_options: i32,
+        into: PgBox<pg_sys::IntoClause>,
+        explain: &mut Explain,
+        query_string: &std::ffi::CStr,
+        params: PgBox<pg_sys::ParamListInfoData>,
+        query_env: PgBox<pg_sys::QueryEnvironment>,
+    ) -> HookResult<()> {
+        if let Some(mut hook) = NextHook::take() {
+            return hook.explain_one_query(
+                query,
+                cursor_options,
+                into,
+                explain,
+                query_string,
+                params,
+                query_env,
+                prev,
+            );
+        }
+        unsafe {
+            #[cfg(feature = "pg10")]
+            {
+                (HOOKS
+                    .as_mut()
+                    .unwrap()
+                    .prev_explain_one_query_hook
+                    .as_ref()
+                    .unwrap())(
+                    query.into_pg(),
+                    cursor_options,
+                    into.into_pg(),
+                    explain.as_ptr(),
+                    query_string.as_ptr(),
+                    params.into_pg(),
+                )
+            }
+
+            #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+            {
+                (HOOKS
+                    .as_mut()
+                    .unwrap()
+                    .prev_explain_one_query_hook
+                    .as_ref()
+                    .unwrap())(
+                    query.into_pg(),
+                    cursor_options,
+                    into.into_pg(),
+                    explain.as_ptr(),
+                    query_string.as_ptr(),
+                    params.into_pg(),
+                    query_env.into_pg(),
+                )
+            }
+        }
+        HookResult::new(())
+    }
+    let _chain = NextHook::start();
+    prev(
+        PgBox::from_pg(query),
+        cursor_options,
+        PgBox::from_pg(into),
+        &mut Explain::from_pg(es),
+        std::ffi::CStr::from_ptr(query_string),
+        PgBox::from_pg(params),
+        PgBox::from_pg(query_env),
+    );
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgx_emit_log(error_data: *mut pg_sys::ErrorData) {
+    fn prev(error_data: PgBox<pg_sys::ErrorData>) -> HookResult<()> {
+        if let Some(mut hook) = NextHook::take() {
+            return hook.emit_log(error_data, prev);
+        }
+        unsafe {
+            (HOOKS.as_mut().unwrap().prev_emit_log_hook.as_ref().unwrap())(error_data.into_pg())
+        }
+        HookResult::new(())
+    }
+    let _chain = NextHook::start();
+    prev(PgBox::from_pg(error_data));
+}
+
 #[pg_guard]
 unsafe extern "C" fn pgx_standard_executor_start_wrapper(
     query_desc: *mut pg_sys::QueryDesc,
@@ -588,3 +1753,158 @@ unsafe extern "C" fn pgx_standard_planner_wrapper(
 ) -> *mut pg_sys::PlannedStmt {
     pg_sys::standard_planner(parse, query_string, cursor_options, bound_params)
 }
+
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+#[pg_guard]
+unsafe extern "C" fn pgx_standard_post_parse_analyze_wrapper(
+    _parse_state: *mut pg_sys::ParseState,
+    _query: *mut pg_sys::Query,
+) {
+    // parse analysis has nothing more to do
+}
+
+#[cfg(feature = "pg14")]
+#[pg_guard]
+unsafe extern "C" fn pgx_standard_post_parse_analyze_wrapper(
+    _parse_state: *mut pg_sys::ParseState,
+    _query: *mut pg_sys::Query,
+    _jumble_state: *mut pg_sys::JumbleState,
+) {
+    // parse analysis has nothing more to do
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgx_standard_object_access_wrapper(
+    _access: pg_sys::ObjectAccessType,
+    _class_id: pg_sys::Oid,
+    _object_id: pg_sys::Oid,
+    _sub_id: i32,
+    _arg: void_mut_ptr,
+) {
+    // there's no standard behavior to fall back to
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgx_standard_client_authentication_wrapper(
+    _port: *mut pg_sys::Port,
+    _status: i32,
+) {
+    // there's no standard behavior to fall back to
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgx_standard_check_password_wrapper(
+    _username: *const std::os::raw::c_char,
+    _shadow_pass: *const std::os::raw::c_char,
+    _password_type: pg_sys::PasswordType,
+    _validuntil_time: pg_sys::Datum,
+    _validuntil_null: bool,
+) {
+    // there's no standard behavior to fall back to
+}
+
+#[cfg(feature = "pg10")]
+#[pg_guard]
+unsafe extern "C" fn pgx_standard_explain_one_query_wrapper(
+    query: *mut pg_sys::Query,
+    cursor_options: i32,
+    into: *mut pg_sys::IntoClause,
+    es: *mut pg_sys::ExplainState,
+    query_string: *const std::os::raw::c_char,
+    params: pg_sys::ParamListInfo,
+) {
+    standard_explain_one_query(
+        query,
+        cursor_options,
+        into,
+        es,
+        query_string,
+        params,
+        std::ptr::null_mut(),
+    )
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+#[pg_guard]
+unsafe extern "C" fn pgx_standard_explain_one_query_wrapper(
+    query: *mut pg_sys::Query,
+    cursor_options: i32,
+    into: *mut pg_sys::IntoClause,
+    es: *mut pg_sys::ExplainState,
+    query_string: *const std::os::raw::c_char,
+    params: pg_sys::ParamListInfo,
+    query_env: *mut pg_sys::QueryEnvironment,
+) {
+    standard_explain_one_query(
+        query,
+        cursor_options,
+        into,
+        es,
+        query_string,
+        params,
+        query_env,
+    )
+}
+
+/// What `ExplainOneQuery()` does when there's no hook, which isn't exported: plan the query,
+/// timing how long that takes, and explain the plan
+unsafe fn standard_explain_one_query(
+    query: *mut pg_sys::Query,
+    cursor_options: i32,
+    into: *mut pg_sys::IntoClause,
+    es: *mut pg_sys::ExplainState,
+    query_string: *const std::os::raw::c_char,
+    params: pg_sys::ParamListInfo,
+    query_env: *mut pg_sys::QueryEnvironment,
+) {
+    #[allow(unused_variables)]
+    let buffers_start = pg_sys::pgBufferUsage;
+    let start = std::time::Instant::now();
+
+    #[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12"))]
+    let plan = pg_sys::pg_plan_query(query, cursor_options, params);
+    #[cfg(any(feature = "pg13", feature = "pg14"))]
+    let plan = pg_sys::pg_plan_query(query, query_string, cursor_options, params);
+
+    let elapsed = start.elapsed();
+    let plan_duration = pg_sys::instr_time {
+        tv_sec: elapsed.as_secs() as _,
+        tv_nsec: elapsed.subsec_nanos() as _,
+    };
+
+    #[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12"))]
+    pg_sys::ExplainOnePlan(
+        plan,
+        into,
+        es,
+        query_string,
+        params,
+        query_env,
+        &plan_duration,
+    );
+
+    #[cfg(any(feature = "pg13", feature = "pg14"))]
+    {
+        let mut buffers = pg_sys::BufferUsage::default();
+        pg_sys::BufferUsageAccumDiff(&mut buffers, &pg_sys::pgBufferUsage, &buffers_start);
+        pg_sys::ExplainOnePlan(
+            plan,
+            into,
+            es,
+            query_string,
+            params,
+            query_env,
+            &plan_duration,
+            if (*es).buffers {
+                &buffers
+            } else {
+                std::ptr::null()
+            },
+        );
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgx_standard_emit_log_wrapper(_error_data: *mut pg_sys::ErrorData) {
+    // there's no standard behavior to fall back to
+}
@@ -0,0 +1,531 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Support for implementing [index access methods](https://www.postgresql.org/docs/current/indexam.html)
+//!
+//! Implement [`IndexAccessMethod`], and return [`index_am_routine`] from the access method's
+//! handler function, which is then named by `CREATE ACCESS METHOD`.  The index is responsible for
+//! storing its keys and their heap tuple ids, most likely in its relation's pages.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! struct MyIndexScan;
+//!
+//! impl IndexAccessMethod for MyIndexScan {
+//!     type Key = i32;
+//!     type Query = i32;
+//!     const STRATEGIES: u16 = 1;
+//!
+//!     fn insert(index: &PgRelation, key: i32, tid: pg_sys::ItemPointerData) {
+//!         // store `key` and `tid` in the index
+//!     }
+//!
+//!     fn bulk_delete(
+//!         index: &PgRelation,
+//!         is_dead: &dyn Fn(pg_sys::ItemPointerData) -> bool,
+//!     ) -> IndexVacuumStats {
+//!         // remove the entries whose tid `is_dead`
+//!         IndexVacuumStats::default()
+//!     }
+//!
+//!     fn begin_scan(index: &PgRelation) -> Self {
+//!         MyIndexScan
+//!     }
+//!
+//!     fn rescan(&mut self, keys: Vec<IndexScanKey<i32>>) {
+//!         // find the entries matching every one of `keys`
+//!     }
+//!
+//!     fn next(&mut self) -> Option<(pg_sys::ItemPointerData, bool)> {
+//!         // the next matching entry's tid, and if the heap tuple needs rechecking
+//!         None
+//!     }
+//! }
+//!
+//! #[pg_extern(sql = r#"
+//!     CREATE FUNCTION my_index_handler(internal) RETURNS index_am_handler
+//!     LANGUAGE c AS '@MODULE_PATHNAME@', '@FUNCTION_NAME@';
+//!     CREATE ACCESS METHOD my_index TYPE INDEX HANDLER my_index_handler;
+//! "#)]
+//! fn my_index_handler(_internal: Internal) -> PgBox<pg_sys::IndexAmRoutine> {
+//!     index_am_routine::<MyIndexScan>()
+//! }
+//! ```
+use crate::{
+    ereport, guard, pg_guard, pg_sys, FromDatum, PgBox, PgLogLevel, PgRelation, PgSqlErrorCode,
+};
+
+/// An index access method for single-column indexes, where each value of `Self` is one scan of
+/// an index
+///
+/// Only non-null keys are indexed, and a scan must have at least one key.
+pub trait IndexAccessMethod: Sized {
+    /// The type of the indexed column
+    type Key: FromDatum;
+
+    /// The type of the right-hand side of the operators the index supports
+    type Query: FromDatum;
+
+    /// The number of operator strategies, which number the operators in an operator class
+    const STRATEGIES: u16;
+
+    /// The number of support functions in an operator class
+    const SUPPORT_FUNCTIONS: u16 = 0;
+
+    /// Initialize an empty `index`, which is about to be built, or to be the init fork of an
+    /// unlogged index
+    fn build_empty(_index: &PgRelation) {}
+
+    /// Add `key`, the indexed value of heap tuple `tid`, to `index`
+    ///
+    /// This is also called for each heap tuple when the index is built.
+    fn insert(index: &PgRelation, key: Self::Key, tid: pg_sys::ItemPointerData);
+
+    /// Remove each entry from `index` whose heap tuple `is_dead`, for `VACUUM`
+    fn bulk_delete(
+        index: &PgRelation,
+        is_dead: &dyn Fn(pg_sys::ItemPointerData) -> bool,
+    ) -> IndexVacuumStats;
+
+    /// Tidy up `index` once `VACUUM` has finished with it, or, if `analyze_only`, when it's
+    /// analyzed
+    fn vacuum_cleanup(_index: &PgRelation, _analyze_only: bool) {}
+
+    /// Start a scan of `index`, whose keys are passed to [`IndexAccessMethod::rescan`]
+    fn begin_scan(index: &PgRelation) -> Self;
+
+    /// Start the scan over again, finding the entries which match every one of `keys`
+    ///
+    /// A key with a `NULL` argument can't match anything, so the scan isn't asked for entries
+    /// when there is one.
+    fn rescan(&mut self, keys: Vec<IndexScanKey<Self::Query>>);
+
+    /// The next matching entry's heap tuple id, and if that heap tuple needs to be rechecked
+    /// against the scan's keys, or `None` once there are no more
+    fn next(&mut self) -> Option<(pg_sys::ItemPointerData, bool)>;
+}
+
+/// One of the conditions of an index scan, which is that the operator numbered `strategy` in
+/// the operator class is true for the key and `argument`
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexScanKey<Q> {
+    pub strategy: u16,
+    pub argument: Q,
+}
+
+/// What's left of an index after `VACUUM` has removed its dead entries
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct IndexVacuumStats {
+    pub index_tuples: f64,
+    pub tuples_removed: f64,
+}
+
+/// The `IndexAmRoutine` for `T`, to be returned by the access method's handler function
+pub fn index_am_routine<T: IndexAccessMethod>() -> PgBox<pg_sys::IndexAmRoutine> {
+    let mut routine = PgBox::<pg_sys::IndexAmRoutine>::alloc_node(pg_sys::NodeTag_T_IndexAmRoutine);
+
+    routine.amstrategies = T::STRATEGIES;
+    routine.amsupport = T::SUPPORT_FUNCTIONS;
+    routine.amkeytype = pg_sys::InvalidOid;
+
+    routine.ambuild = Some(ambuild::<T>);
+    routine.ambuildempty = Some(ambuildempty::<T>);
+    routine.aminsert = Some(aminsert::<T>);
+    routine.ambulkdelete = Some(ambulkdelete::<T>);
+    routine.amvacuumcleanup = Some(amvacuumcleanup::<T>);
+    routine.amcostestimate = Some(amcostestimate);
+    routine.amoptions = Some(amoptions);
+    routine.amvalidate = Some(amvalidate);
+    routine.ambeginscan = Some(ambeginscan::<T>);
+    routine.amrescan = Some(amrescan::<T>);
+    routine.amgettuple = Some(amgettuple::<T>);
+    routine.amendscan = Some(amendscan::<T>);
+
+    routine.into_pg_boxed()
+}
+
+struct BuildState {
+    typoid: pg_sys::Oid,
+    index_tuples: f64,
+}
+
+// `#[pg_guard]` can't wrap generic functions, so those for the access method guard themselves
+
+unsafe extern "C" fn ambuild<T: IndexAccessMethod>(
+    heap_relation: pg_sys::Relation,
+    index_relation: pg_sys::Relation,
+    index_info: *mut pg_sys::IndexInfo,
+) -> *mut pg_sys::IndexBuildResult {
+    guard::guard(|| {
+        let index = PgRelation::from_pg(index_relation);
+        T::build_empty(&index);
+
+        let mut state = BuildState {
+            typoid: key_type(&index),
+            index_tuples: 0.0,
+        };
+        let heap_tuples = build_heap_scan::<T>(
+            heap_relation,
+            index_relation,
+            index_info,
+            &mut state as *mut BuildState as *mut std::os::raw::c_void,
+        );
+
+        let mut result = PgBox::<pg_sys::IndexBuildResult>::alloc0();
+        result.heap_tuples = heap_tuples;
+        result.index_tuples = state.index_tuples;
+        result.into_pg()
+    })
+}
+
+unsafe fn build_insert<T: IndexAccessMethod>(
+    index_relation: pg_sys::Relation,
+    tid: pg_sys::ItemPointerData,
+    values: *mut pg_sys::Datum,
+    isnull: *mut bool,
+    state: *mut std::os::raw::c_void,
+) {
+    let state = &mut *(state as *mut BuildState);
+    if let Some(key) = T::Key::from_datum(*values, *isnull, state.typoid) {
+        T::insert(&PgRelation::from_pg(index_relation), key, tid);
+        state.index_tuples += 1.0;
+    }
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11"))]
+unsafe fn build_heap_scan<T: IndexAccessMethod>(
+    heap_relation: pg_sys::Relation,
+    index_relation: pg_sys::Relation,
+    index_info: *mut pg_sys::IndexInfo,
+    state: *mut std::os::raw::c_void,
+) -> f64 {
+    pg_sys::IndexBuildHeapScan(
+        heap_relation,
+        index_relation,
+        index_info,
+        true,
+        Some(build_callback::<T>),
+        state,
+    )
+}
+
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+unsafe fn build_heap_scan<T: IndexAccessMethod>(
+    heap_relation: pg_sys::Relation,
+    index_relation: pg_sys::Relation,
+    index_info: *mut pg_sys::IndexInfo,
+    state: *mut std::os::raw::c_void,
+) -> f64 {
+    // `table_index_build_scan()` is an inline function
+    ((*(*heap_relation).rd_tableam)
+        .index_build_range_scan
+        .unwrap())(
+        heap_relation,
+        index_relation,
+        index_info,
+        true,
+        false,
+        true,
+        0,
+        u32::MAX, // InvalidBlockNumber, to scan to the end of the table
+        Some(build_callback::<T>),
+        state,
+        std::ptr::null_mut(),
+    )
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12"))]
+unsafe extern "C" fn build_callback<T: IndexAccessMethod>(
+    index_relation: pg_sys::Relation,
+    htup: pg_sys::HeapTuple,
+    values: *mut pg_sys::Datum,
+    isnull: *mut bool,
+    _tuple_is_alive: bool,
+    state: *mut std::os::raw::c_void,
+) {
+    guard::guard(|| build_insert::<T>(index_relation, (*htup).t_self, values, isnull, state))
+}
+
+#[cfg(any(feature = "pg13", feature = "pg14"))]
+unsafe extern "C" fn build_callback<T: IndexAccessMethod>(
+    index_relation: pg_sys::Relation,
+    tid: pg_sys::ItemPointer,
+    values: *mut pg_sys::Datum,
+    isnull: *mut bool,
+    _tuple_is_alive: bool,
+    state: *mut std::os::raw::c_void,
+) {
+    guard::guard(|| build_insert::<T>(index_relation, *tid, values, isnull, state))
+}
+
+unsafe extern "C" fn ambuildempty<T: IndexAccessMethod>(index_relation: pg_sys::Relation) {
+    guard::guard(|| T::build_empty(&PgRelation::from_pg(index_relation)))
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+unsafe extern "C" fn aminsert<T: IndexAccessMethod>(
+    index_relation: pg_sys::Relation,
+    values: *mut pg_sys::Datum,
+    isnull: *mut bool,
+    heap_tid: pg_sys::ItemPointer,
+    _heap_relation: pg_sys::Relation,
+    _check_unique: pg_sys::IndexUniqueCheck,
+    _index_info: *mut pg_sys::IndexInfo,
+) -> bool {
+    guard::guard(|| insert::<T>(index_relation, values, isnull, heap_tid))
+}
+
+#[cfg(feature = "pg14")]
+unsafe extern "C" fn aminsert<T: IndexAccessMethod>(
+    index_relation: pg_sys::Relation,
+    values: *mut pg_sys::Datum,
+    isnull: *mut bool,
+    heap_tid: pg_sys::ItemPointer,
+    _heap_relation: pg_sys::Relation,
+    _check_unique: pg_sys::IndexUniqueCheck,
+    _index_unchanged: bool,
+    _index_info: *mut pg_sys::IndexInfo,
+) -> bool {
+    guard::guard(|| insert::<T>(index_relation, values, isnull, heap_tid))
+}
+
+unsafe fn insert<T: IndexAccessMethod>(
+    index_relation: pg_sys::Relation,
+    values: *mut pg_sys::Datum,
+    isnull: *mut bool,
+    heap_tid: pg_sys::ItemPointer,
+) -> bool {
+    let index = PgRelation::from_pg(index_relation);
+    if let Some(key) = T::Key::from_datum(*values, *isnull, key_type(&index)) {
+        T::insert(&index, key, *heap_tid);
+    }
+
+    // only matters for deferred uniqueness checks, which aren't supported
+    false
+}
+
+unsafe extern "C" fn ambulkdelete<T: IndexAccessMethod>(
+    info: *mut pg_sys::IndexVacuumInfo,
+    stats: *mut pg_sys::IndexBulkDeleteResult,
+    callback: pg_sys::IndexBulkDeleteCallback,
+    callback_state: *mut std::os::raw::c_void,
+) -> *mut pg_sys::IndexBulkDeleteResult {
+    guard::guard(|| {
+        let index = PgRelation::from_pg((*info).index);
+        let callback = callback.expect("index bulk delete has no callback");
+        let is_dead = |mut tid: pg_sys::ItemPointerData| callback(&mut tid, callback_state);
+        let vacuumed = T::bulk_delete(&index, &is_dead);
+
+        let mut stats = vacuum_stats(info, stats);
+        stats.num_index_tuples = vacuumed.index_tuples;
+        stats.tuples_removed += vacuumed.tuples_removed;
+        stats.into_pg()
+    })
+}
+
+unsafe extern "C" fn amvacuumcleanup<T: IndexAccessMethod>(
+    info: *mut pg_sys::IndexVacuumInfo,
+    stats: *mut pg_sys::IndexBulkDeleteResult,
+) -> *mut pg_sys::IndexBulkDeleteResult {
+    guard::guard(|| {
+        T::vacuum_cleanup(&PgRelation::from_pg((*info).index), (*info).analyze_only);
+
+        if (*info).analyze_only {
+            return stats;
+        }
+
+        if stats.is_null() {
+            // nothing was deleted, so the index has as many entries as the heap has tuples
+            let mut stats = vacuum_stats(info, stats);
+            stats.num_index_tuples = (*info).num_heap_tuples;
+            stats.estimated_count = (*info).estimated_count;
+            stats.into_pg()
+        } else {
+            stats
+        }
+    })
+}
+
+/// `stats`, or new ones if this is the first pass over the index, with its number of pages
+unsafe fn vacuum_stats(
+    info: *mut pg_sys::IndexVacuumInfo,
+    stats: *mut pg_sys::IndexBulkDeleteResult,
+) -> PgBox<pg_sys::IndexBulkDeleteResult> {
+    let mut stats = if stats.is_null() {
+        PgBox::<pg_sys::IndexBulkDeleteResult>::alloc0().into_pg_boxed()
+    } else {
+        PgBox::from_pg(stats)
+    };
+    stats.num_pages =
+        pg_sys::RelationGetNumberOfBlocksInFork((*info).index, pg_sys::ForkNumber_MAIN_FORKNUM);
+    stats
+}
+
+#[pg_guard]
+unsafe extern "C" fn amcostestimate(
+    root: *mut pg_sys::PlannerInfo,
+    path: *mut pg_sys::IndexPath,
+    loop_count: f64,
+    index_startup_cost: *mut pg_sys::Cost,
+    index_total_cost: *mut pg_sys::Cost,
+    index_selectivity: *mut pg_sys::Selectivity,
+    index_correlation: *mut f64,
+    index_pages: *mut f64,
+) {
+    let mut costs = pg_sys::GenericCosts::default();
+    generic_cost_estimate(root, path, loop_count, &mut costs);
+
+    *index_startup_cost = costs.indexStartupCost;
+    *index_total_cost = costs.indexTotalCost;
+    *index_selectivity = costs.indexSelectivity;
+    *index_correlation = costs.indexCorrelation;
+    *index_pages = costs.numIndexPages;
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11"))]
+unsafe fn generic_cost_estimate(
+    root: *mut pg_sys::PlannerInfo,
+    path: *mut pg_sys::IndexPath,
+    loop_count: f64,
+    costs: *mut pg_sys::GenericCosts,
+) {
+    let qinfos = pg_sys::deconstruct_indexquals(path);
+    pg_sys::genericcostestimate(root, path, loop_count, qinfos, costs);
+}
+
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+unsafe fn generic_cost_estimate(
+    root: *mut pg_sys::PlannerInfo,
+    path: *mut pg_sys::IndexPath,
+    loop_count: f64,
+    costs: *mut pg_sys::GenericCosts,
+) {
+    pg_sys::genericcostestimate(root, path, loop_count, costs);
+}
+
+#[pg_guard]
+unsafe extern "C" fn amoptions(reloptions: pg_sys::Datum, validate: bool) -> *mut pg_sys::bytea {
+    if validate && reloptions != 0 {
+        ereport(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+            "index access method does not support options",
+            file!(),
+            line!(),
+            column!(),
+        );
+    }
+    std::ptr::null_mut()
+}
+
+#[pg_guard]
+unsafe extern "C" fn amvalidate(_opclassoid: pg_sys::Oid) -> bool {
+    true
+}
+
+unsafe extern "C" fn ambeginscan<T: IndexAccessMethod>(
+    index_relation: pg_sys::Relation,
+    nkeys: i32,
+    norderbys: i32,
+) -> pg_sys::IndexScanDesc {
+    guard::guard(|| {
+        let scan = pg_sys::RelationGetIndexScan(index_relation, nkeys, norderbys);
+        let state: Box<ScanState<T>> = Box::new(ScanState {
+            scan: T::begin_scan(&PgRelation::from_pg(index_relation)),
+            unsatisfiable: false,
+        });
+        (*scan).opaque = Box::into_raw(state) as *mut std::os::raw::c_void;
+        scan
+    })
+}
+
+struct ScanState<T> {
+    scan: T,
+    // if one of the keys is `NULL`
+    unsatisfiable: bool,
+}
+
+unsafe extern "C" fn amrescan<T: IndexAccessMethod>(
+    scan: pg_sys::IndexScanDesc,
+    keys: pg_sys::ScanKey,
+    nkeys: i32,
+    _orderbys: pg_sys::ScanKey,
+    _norderbys: i32,
+) {
+    guard::guard(|| {
+        let state = &mut *((*scan).opaque as *mut ScanState<T>);
+        if !keys.is_null() && nkeys > 0 {
+            std::ptr::copy(keys, (*scan).keyData, nkeys as usize);
+        }
+
+        let mut scan_keys = Vec::with_capacity((*scan).numberOfKeys as usize);
+        state.unsatisfiable = false;
+        for i in 0..(*scan).numberOfKeys as usize {
+            let key = &*(*scan).keyData.add(i);
+            let is_null = key.sk_flags & pg_sys::SK_ISNULL as i32 != 0;
+            match T::Query::from_datum(key.sk_argument, is_null, key.sk_subtype) {
+                Some(argument) => scan_keys.push(IndexScanKey {
+                    strategy: key.sk_strategy,
+                    argument,
+                }),
+                None => state.unsatisfiable = true,
+            }
+        }
+
+        state.scan.rescan(scan_keys);
+    })
+}
+
+unsafe extern "C" fn amgettuple<T: IndexAccessMethod>(
+    scan: pg_sys::IndexScanDesc,
+    _direction: pg_sys::ScanDirection,
+) -> bool {
+    guard::guard(|| {
+        let state = &mut *((*scan).opaque as *mut ScanState<T>);
+        if state.unsatisfiable {
+            return false;
+        }
+
+        match state.scan.next() {
+            Some((tid, recheck)) => {
+                set_heap_tid(scan, tid);
+                (*scan).xs_recheck = recheck;
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+unsafe extern "C" fn amendscan<T: IndexAccessMethod>(scan: pg_sys::IndexScanDesc) {
+    guard::guard(|| {
+        drop(Box::from_raw((*scan).opaque as *mut ScanState<T>));
+        (*scan).opaque = std::ptr::null_mut();
+    })
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11"))]
+unsafe fn set_heap_tid(scan: pg_sys::IndexScanDesc, tid: pg_sys::ItemPointerData) {
+    (*scan).xs_ctup.t_self = tid;
+}
+
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+unsafe fn set_heap_tid(scan: pg_sys::IndexScanDesc, tid: pg_sys::ItemPointerData) {
+    (*scan).xs_heaptid = tid;
+}
+
+/// The type of the index's one column
+fn key_type(index: &PgRelation) -> pg_sys::Oid {
+    index
+        .tuple_desc()
+        .get(0)
+        .expect("index has no columns")
+        .atttypid
+}
@@ -0,0 +1,177 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Interrupts an extension can send from one backend to another, such as to tell every backend
+//! to drop a cache
+//!
+//! Postgres' own inter-process signals, its `ProcSignalReason`s, are a fixed set, which
+//! extensions can't add to before version 17.  A [`PgInterrupt`] works much as they do instead:
+//! sending one sets a flag for the receiving backend in shared memory, and then sets its latch,
+//! waking it if it's waiting.  The receiving backend checks for it with
+//! [`PgInterrupt::received`], typically each time it wakes up, or before using whatever it
+//! caches.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! static RELOAD_RULES: PgInterrupt = PgInterrupt::new();
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     pg_shmem_init!(RELOAD_RULES);
+//! }
+//!
+//! #[pg_extern]
+//! fn rules_changed() {
+//!     RELOAD_RULES.broadcast();
+//! }
+//!
+//! fn check_rules() {
+//!     if RELOAD_RULES.received() {
+//!         // read the rules again
+//!     }
+//! }
+//! ```
+use crate::shmem::check_shared_preload_libraries;
+use crate::{pg_sys, PgSharedMemoryInitialization};
+use once_cell::sync::OnceCell;
+use std::sync::atomic::{AtomicU32, Ordering};
+use uuid::Uuid;
+
+/// Postgres' `MAX_BACKENDS`, the most processes it can have, as it's not known how many there
+/// will be when shared memory is requested
+const MAX_BACKENDS: usize = 0x3FFFF;
+
+/// An interrupt one backend can send to others, which must be passed to `pg_shmem_init!()`
+/// during `_PG_init()`
+///
+/// It needs a bit of shared memory for every backend Postgres could have, 32kB in all.
+pub struct PgInterrupt {
+    pending: OnceCell<PendingBits>,
+}
+
+/// One bit for each `PGPROC`, set while its backend has the interrupt pending
+struct PendingBits {
+    words: *const AtomicU32,
+    len: usize,
+}
+
+unsafe impl Send for PendingBits {}
+unsafe impl Sync for PendingBits {}
+
+impl PgInterrupt {
+    pub const fn new() -> Self {
+        PgInterrupt {
+            pending: OnceCell::new(),
+        }
+    }
+
+    /// Send the interrupt to the backend whose process id is `pid`, returning whether there is
+    /// such a backend
+    pub fn send(&self, pid: pg_sys::pid_t) -> bool {
+        unsafe {
+            let proc = pg_sys::BackendPidGetProc(pid);
+            if proc.is_null() {
+                return false;
+            }
+            self.set(proc);
+            true
+        }
+    }
+
+    /// Send the interrupt to every other backend
+    pub fn broadcast(&self) {
+        unsafe {
+            let global = pg_sys::ProcGlobal;
+            for i in 0..(*global).allProcCount as usize {
+                let proc = (*global).allProcs.add(i);
+                if (*proc).pid != 0 && (*proc).pid != pg_sys::MyProcPid {
+                    self.set(proc);
+                }
+            }
+        }
+    }
+
+    /// Has this backend been sent the interrupt since it last checked?
+    pub fn received(&self) -> bool {
+        let proc = unsafe { pg_sys::MyProc };
+        if proc.is_null() {
+            return false;
+        }
+        let (word, bit) = self.bit(unsafe { (*proc).pgprocno });
+        word.fetch_and(!bit, Ordering::SeqCst) & bit != 0
+    }
+
+    unsafe fn set(&self, proc: *mut pg_sys::PGPROC) {
+        let (word, bit) = self.bit((*proc).pgprocno);
+        word.fetch_or(bit, Ordering::SeqCst);
+        pg_sys::SetLatch(&mut (*proc).procLatch);
+    }
+
+    fn bit(&self, pgprocno: i32) -> (&AtomicU32, u32) {
+        let pending = self
+            .pending
+            .get()
+            .expect("PgInterrupt has not been initialized with pg_shmem_init!()");
+        let index = pgprocno as usize;
+        assert!(index / 32 < pending.len, "invalid pgprocno: {}", pgprocno);
+        let word = unsafe { &*pending.words.add(index / 32) };
+        (word, 1 << (index % 32))
+    }
+
+    /// Find or create the pending bits in shared memory, named `name`
+    fn attach(&self, name: &str) {
+        unsafe {
+            let shm_name = std::ffi::CString::new(name).expect("CString::new() failed");
+            let len = words_for((*pg_sys::ProcGlobal).allProcCount as usize);
+
+            let addin_shmem_init_lock: *mut pg_sys::LWLock =
+                &mut (*pg_sys::MainLWLockArray.add(21)).lock;
+            pg_sys::LWLockAcquire(addin_shmem_init_lock, pg_sys::LWLockMode_LW_EXCLUSIVE);
+
+            let mut found = false;
+            let words = pg_sys::ShmemInitStruct(
+                shm_name.into_raw(),
+                len * std::mem::size_of::<u32>(),
+                &mut found,
+            ) as *mut AtomicU32;
+            if !found {
+                for i in 0..len {
+                    words.add(i).write(AtomicU32::new(0));
+                }
+            }
+            pg_sys::LWLockRelease(addin_shmem_init_lock);
+
+            if self.pending.set(PendingBits { words, len }).is_err() {
+                panic!("This PgInterrupt is not empty, can't re-attach");
+            }
+        }
+    }
+}
+
+impl PgSharedMemoryInitialization for PgInterrupt {
+    fn pg_init(&'static self) {
+        check_shared_preload_libraries();
+        unsafe {
+            pg_sys::RequestAddinShmemSpace(words_for(MAX_BACKENDS) * std::mem::size_of::<u32>());
+        }
+    }
+
+    fn shmem_init(&'static self) {
+        self.attach(&Uuid::new_v4().to_string());
+    }
+
+    fn shmem_init_named(&'static self, name: &'static str) {
+        self.attach(name);
+    }
+}
+
+fn words_for(procs: usize) -> usize {
+    (procs + 31) / 32
+}
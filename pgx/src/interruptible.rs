@@ -0,0 +1,81 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Iterators which check for interrupts as they go, so long loops can be cancelled
+//!
+//! Postgres only notices a cancelled query, a `statement_timeout`, or a terminated backend when
+//! the backend checks for interrupts, which a loop in Rust never does unless it calls
+//! [`check_for_interrupts!`](crate::check_for_interrupts!).  An iterator made
+//! [`.interruptible(n)`](InterruptibleIterator::interruptible) does that every `n` items:
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[pg_extern]
+//! fn count_primes(limit: i64) -> i64 {
+//!     (2..limit)
+//!         .interruptible(10_000)
+//!         .filter(|n| (2..*n).take_while(|d| d * d <= *n).all(|d| n % d != 0))
+//!         .count() as i64
+//! }
+//! ```
+//!
+//! Checking is cheap, as it only reads a flag until there's an interrupt, so `n` need only be
+//! big enough that the checks don't show up next to the work each item does.
+
+/// An iterator which checks for interrupts every so many items, from
+/// [`InterruptibleIterator::interruptible`]
+///
+/// If there's an interrupt, such as the query being cancelled, the check raises the `ERROR`.
+pub struct Interruptible<I> {
+    iter: I,
+    every: usize,
+    until_check: usize,
+}
+
+impl<I: Iterator> Iterator for Interruptible<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if self.until_check == 0 {
+            crate::check_for_interrupts!();
+            self.until_check = self.every;
+        }
+        self.until_check -= 1;
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I: ExactSizeIterator> ExactSizeIterator for Interruptible<I> {}
+
+/// Adds [`.interruptible(n)`](InterruptibleIterator::interruptible) to every iterator
+pub trait InterruptibleIterator: Iterator + Sized {
+    /// Check for interrupts before the first item, and then every `every` items
+    ///
+    /// ## Panics
+    ///
+    /// If `every` is `0`
+    fn interruptible(self, every: usize) -> Interruptible<Self> {
+        assert!(
+            every > 0,
+            "interrupts must be checked every 1 or more items"
+        );
+        Interruptible {
+            iter: self,
+            every,
+            until_check: 0,
+        }
+    }
+}
+
+impl<I: Iterator> InterruptibleIterator for I {}
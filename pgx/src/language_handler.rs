@@ -0,0 +1,305 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Building blocks for [procedural languages](https://www.postgresql.org/docs/current/plhandler.html):
+//! the [`PgLanguageCall`] passed to `#[pg_language_handler]` functions, and the
+//! [`PgLanguageFunction`] passed to `#[pg_language_validator]` functions
+//!
+//! Every function written in the language is called through its handler, which finds the
+//! function's source code, and its arguments, in the [`PgLanguageCall`].
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! /// A language whose functions return their source code, as `text`
+//! #[pg_language_handler(language = "plecho", validator = "plecho_validator", requires = [plecho_validator])]
+//! fn plecho_call_handler(call: &PgLanguageCall) -> Option<pg_sys::Datum> {
+//!     call.function().src.clone().into_datum()
+//! }
+//!
+//! #[pg_language_validator]
+//! fn plecho_validator(function: &PgLanguageFunction) -> Result<(), String> {
+//!     match function.return_type {
+//!         pg_sys::TEXTOID => Ok(()),
+//!         _ => Err(format!("{} must return text", function.name)),
+//!     }
+//! }
+//! ```
+use crate::pg_sys::pgx_GETSTRUCT;
+use crate::{
+    called_as_event_trigger, called_as_trigger, ereport, error, pg_getarg_datum, pg_return_null,
+    pg_sys, FromDatum, PgEventTrigger, PgLogLevel, PgSqlErrorCode, PgTrigger,
+};
+use std::ffi::CStr;
+use std::fmt::Display;
+
+/// A function written in a procedural language, as declared by its `pg_proc` row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgLanguageFunction {
+    pub oid: pg_sys::Oid,
+    pub name: String,
+    pub namespace: pg_sys::Oid,
+    /// The function's source code, its `AS '...'`
+    pub src: String,
+    /// The types of the function's input arguments, which may be polymorphic
+    pub arg_types: Vec<pg_sys::Oid>,
+    /// The names of the function's input arguments, which are `None` for unnamed arguments
+    pub arg_names: Vec<Option<String>>,
+    pub return_type: pg_sys::Oid,
+    pub returns_set: bool,
+    pub is_strict: bool,
+    /// The oid of the function's language
+    pub language: pg_sys::Oid,
+}
+
+impl PgLanguageFunction {
+    /// The function with the oid `oid`
+    ///
+    /// Raises an ERROR if there's no such function.
+    pub fn from_oid(oid: pg_sys::Oid) -> Self {
+        unsafe {
+            let tuple = pg_sys::SearchSysCache(
+                pg_sys::SysCacheIdentifier_PROCOID as i32,
+                oid as pg_sys::Datum,
+                0,
+                0,
+                0,
+            );
+            if tuple.is_null() {
+                ereport(
+                    PgLogLevel::ERROR,
+                    PgSqlErrorCode::ERRCODE_UNDEFINED_FUNCTION,
+                    &format!("cache lookup failed for function {}", oid),
+                    file!(),
+                    line!(),
+                    column!(),
+                );
+            }
+
+            let function = Self::from_proc_tuple(oid, tuple);
+            pg_sys::ReleaseSysCache(tuple);
+            function
+        }
+    }
+
+    /// The function a `#[pg_language_validator]` function called with `fcinfo` is asked to
+    /// validate, which is `None` when the caller isn't allowed to validate it
+    ///
+    /// ## Safety
+    ///
+    /// `fcinfo` must be the `pg_sys::FunctionCallInfo` of the validator function being called
+    pub unsafe fn for_validator(
+        fcinfo: pg_sys::FunctionCallInfo,
+        function_oid: pg_sys::Oid,
+    ) -> Option<Self> {
+        let validator_oid = (*(*fcinfo).flinfo).fn_oid;
+        if pg_sys::CheckFunctionValidatorAccess(validator_oid, function_oid) {
+            Some(Self::from_oid(function_oid))
+        } else {
+            None
+        }
+    }
+
+    unsafe fn from_proc_tuple(oid: pg_sys::Oid, tuple: pg_sys::HeapTuple) -> Self {
+        let proc = (pgx_GETSTRUCT(tuple) as pg_sys::Form_pg_proc)
+            .as_ref()
+            .unwrap();
+
+        let mut isnull = false;
+        let src = pg_sys::SysCacheGetAttr(
+            pg_sys::SysCacheIdentifier_PROCOID as i32,
+            tuple,
+            pg_sys::Anum_pg_proc_prosrc as pg_sys::AttrNumber,
+            &mut isnull,
+        );
+        let src = if isnull {
+            String::new()
+        } else {
+            cstr_to_string(pg_sys::text_to_cstring(src as *const pg_sys::text))
+        };
+
+        // `get_func_arg_info()` has every argument, including the `OUT` arguments
+        let mut types = std::ptr::null_mut();
+        let mut names = std::ptr::null_mut();
+        let mut modes = std::ptr::null_mut();
+        let nargs = pg_sys::get_func_arg_info(tuple, &mut types, &mut names, &mut modes) as usize;
+        let mut arg_types = Vec::new();
+        let mut arg_names = Vec::new();
+        for i in 0..nargs {
+            let mode = if modes.is_null() {
+                pg_sys::PROARGMODE_IN
+            } else {
+                *modes.add(i) as u8
+            };
+            if mode == pg_sys::PROARGMODE_OUT || mode == pg_sys::PROARGMODE_TABLE {
+                continue;
+            }
+
+            arg_types.push(*types.add(i));
+            let name = if names.is_null() {
+                std::ptr::null()
+            } else {
+                *names.add(i)
+            };
+            arg_names.push(if name.is_null() || *name == 0 {
+                None
+            } else {
+                Some(cstr_to_string(name))
+            });
+        }
+
+        PgLanguageFunction {
+            oid,
+            name: cstr_to_string(proc.proname.data.as_ptr()),
+            namespace: proc.pronamespace,
+            src,
+            arg_types,
+            arg_names,
+            return_type: proc.prorettype,
+            returns_set: proc.proretset,
+            is_strict: proc.proisstrict,
+            language: proc.prolang,
+        }
+    }
+}
+
+/// A call of a function written in a procedural language, as passed to the language's
+/// `#[pg_language_handler]` function
+pub struct PgLanguageCall {
+    fcinfo: pg_sys::FunctionCallInfo,
+    function: PgLanguageFunction,
+}
+
+impl PgLanguageCall {
+    /// The call of the function whose handler is called with `fcinfo`
+    ///
+    /// ## Safety
+    ///
+    /// `fcinfo` must be the `pg_sys::FunctionCallInfo` of the handler function being called
+    pub unsafe fn from_fcinfo(fcinfo: pg_sys::FunctionCallInfo) -> Self {
+        let oid = (*(*fcinfo).flinfo).fn_oid;
+        PgLanguageCall {
+            fcinfo,
+            function: PgLanguageFunction::from_oid(oid),
+        }
+    }
+
+    /// The function being called
+    pub fn function(&self) -> &PgLanguageFunction {
+        &self.function
+    }
+
+    /// The number of arguments the function is called with
+    pub fn nargs(&self) -> usize {
+        unsafe { (*self.fcinfo).nargs as usize }
+    }
+
+    /// The argument at `argno`, counting from 0, as a datum, which is `None` when it's NULL
+    pub fn arg_datum(&self, argno: usize) -> Option<pg_sys::Datum> {
+        pg_getarg_datum(self.fcinfo, argno)
+    }
+
+    /// The argument at `argno`, counting from 0, as a `T`, which is `None` when it's NULL
+    pub fn arg<T: FromDatum>(&self, argno: usize) -> Option<T> {
+        let datum = self.arg_datum(argno);
+        unsafe { T::from_datum(datum.unwrap_or(0), datum.is_none(), self.arg_type(argno)) }
+    }
+
+    /// The type of the argument at `argno`, which, for a polymorphic argument, is the actual type
+    /// it's called with, where that's known
+    pub fn arg_type(&self, argno: usize) -> pg_sys::Oid {
+        let actual = unsafe { pg_sys::get_fn_expr_argtype((*self.fcinfo).flinfo, argno as i32) };
+        match actual {
+            pg_sys::InvalidOid => self.function.arg_types[argno],
+            actual => actual,
+        }
+    }
+
+    /// The type the function returns, which, for a polymorphic function, is the actual type it's
+    /// called to return, where that's known
+    pub fn return_type(&self) -> pg_sys::Oid {
+        match unsafe { pg_sys::get_fn_expr_rettype((*self.fcinfo).flinfo) } {
+            pg_sys::InvalidOid => self.function.return_type,
+            actual => actual,
+        }
+    }
+
+    /// The firing trigger, when the function is called as a trigger
+    pub fn trigger(&self) -> Option<PgTrigger> {
+        unsafe { called_as_trigger(self.fcinfo).then(|| PgTrigger::from_fcinfo(self.fcinfo)) }
+    }
+
+    /// The firing event trigger, when the function is called as an event trigger
+    pub fn event_trigger(&self) -> Option<PgEventTrigger> {
+        unsafe {
+            called_as_event_trigger(self.fcinfo).then(|| PgEventTrigger::from_fcinfo(self.fcinfo))
+        }
+    }
+
+    /// The `pg_sys::FunctionCallInfo` of the handler, for what isn't otherwise provided, such as
+    /// returning a set
+    pub fn fcinfo(&self) -> pg_sys::FunctionCallInfo {
+        self.fcinfo
+    }
+}
+
+/// Is `check_function_bodies` on, so that a validator should check the function's source code,
+/// rather than only its declaration?
+pub fn check_function_bodies() -> bool {
+    unsafe { pg_sys::check_function_bodies }
+}
+
+/// What a `#[pg_language_handler]` function returns, which is the result of the function it
+/// called
+///
+/// `None` is NULL, and an `Err` raises an ERROR with its message.
+pub trait IntoLanguageCallResult {
+    fn into_language_call_result(self, fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum;
+}
+
+impl IntoLanguageCallResult for Option<pg_sys::Datum> {
+    fn into_language_call_result(self, fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+        match self {
+            Some(datum) => datum,
+            None => pg_return_null(fcinfo),
+        }
+    }
+}
+
+impl<T: IntoLanguageCallResult, E: Display> IntoLanguageCallResult for Result<T, E> {
+    fn into_language_call_result(self, fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+        match self {
+            Ok(result) => result.into_language_call_result(fcinfo),
+            Err(e) => error!("{}", e),
+        }
+    }
+}
+
+/// What a `#[pg_language_validator]` function returns
+///
+/// An `Err` raises an ERROR with its message, rejecting the function being created.
+pub trait IntoLanguageValidatorResult {
+    fn into_language_validator_result(self);
+}
+
+impl IntoLanguageValidatorResult for () {
+    fn into_language_validator_result(self) {}
+}
+
+impl<E: Display> IntoLanguageValidatorResult for Result<(), E> {
+    fn into_language_validator_result(self) {
+        if let Err(e) = self {
+            error!("{}", e)
+        }
+    }
+}
+
+unsafe fn cstr_to_string(ptr: *const std::os::raw::c_char) -> String {
+    CStr::from_ptr(ptr).to_string_lossy().into_owned()
+}
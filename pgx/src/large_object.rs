@@ -0,0 +1,201 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Safe access to Postgres' [large objects](https://www.postgresql.org/docs/current/largeobjects.html)
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//! use std::io::{Read, Seek, SeekFrom, Write};
+//!
+//! let lo = LargeObject::create();
+//! let mut handle = lo.open(LargeObjectMode::ReadWrite);
+//! handle.write_all(b"hello, world").unwrap();
+//! handle.seek(SeekFrom::Start(7)).unwrap();
+//!
+//! let mut contents = String::new();
+//! handle.read_to_string(&mut contents).unwrap();
+//! assert_eq!(contents, "world");
+//! ```
+use crate::{direct_function_call, pg_sys, IntoDatum};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+// from `storage/large_object.h`
+const INV_WRITE: i32 = 0x00020000;
+const INV_READ: i32 = 0x00040000;
+
+/// How a [`LargeObject`] is opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LargeObjectMode {
+    /// Read-only, seeing the large object as of the current snapshot.
+    Read,
+    /// Write-only.
+    Write,
+    /// Reading and writing.
+    ReadWrite,
+}
+
+impl LargeObjectMode {
+    fn flags(&self) -> i32 {
+        match self {
+            LargeObjectMode::Read => INV_READ,
+            LargeObjectMode::Write => INV_WRITE,
+            LargeObjectMode::ReadWrite => INV_READ | INV_WRITE,
+        }
+    }
+}
+
+/// A large object, identified by its `oid` in `pg_largeobject_metadata`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LargeObject {
+    oid: pg_sys::Oid,
+}
+
+impl LargeObject {
+    /// Create a new, empty large object with a system-assigned `oid`.
+    pub fn create() -> Self {
+        Self::create_with_oid(pg_sys::InvalidOid)
+    }
+
+    /// Create a new, empty large object with the specified `oid`.
+    ///
+    /// Raises a Postgres ERROR if a large object with that `oid` already exists.
+    pub fn create_with_oid(oid: pg_sys::Oid) -> Self {
+        let oid = unsafe {
+            direct_function_call::<pg_sys::Oid>(pg_sys::be_lo_create, vec![oid.into_datum()])
+        }
+        .expect("lo_create returned NULL");
+        Self { oid }
+    }
+
+    /// Refer to the existing large object `oid`.  It isn't checked for existence until it's opened.
+    pub fn from_oid(oid: pg_sys::Oid) -> Self {
+        Self { oid }
+    }
+
+    /// The `oid` of this large object.
+    pub fn oid(&self) -> pg_sys::Oid {
+        self.oid
+    }
+
+    /// Open this large object for reading and/or writing.
+    ///
+    /// Raises a Postgres ERROR if the large object doesn't exist or the current user lacks the
+    /// necessary privileges.
+    pub fn open(&self, mode: LargeObjectMode) -> LargeObjectHandle {
+        let fd = unsafe {
+            direct_function_call::<i32>(
+                pg_sys::be_lo_open,
+                vec![self.oid.into_datum(), mode.flags().into_datum()],
+            )
+        }
+        .expect("lo_open returned NULL");
+        LargeObjectHandle { fd }
+    }
+
+    /// Delete this large object.
+    pub fn unlink(self) {
+        unsafe {
+            direct_function_call::<i32>(pg_sys::be_lo_unlink, vec![self.oid.into_datum()]);
+        }
+    }
+}
+
+/// An open [`LargeObject`], implementing [`std::io::Read`], [`std::io::Write`], and
+/// [`std::io::Seek`].
+///
+/// The large object is closed when this is dropped, or by Postgres at the end of the
+/// transaction, whichever comes first.
+#[derive(Debug)]
+pub struct LargeObjectHandle {
+    fd: i32,
+}
+
+impl LargeObjectHandle {
+    /// The current read/write position.
+    pub fn tell(&self) -> u64 {
+        unsafe { direct_function_call::<i64>(pg_sys::be_lo_tell64, vec![self.fd.into_datum()]) }
+            .expect("lo_tell64 returned NULL") as u64
+    }
+
+    /// Truncate (or zero-extend) the large object to `len` bytes.
+    pub fn truncate(&mut self, len: u64) {
+        unsafe {
+            direct_function_call::<i32>(
+                pg_sys::be_lo_truncate64,
+                vec![self.fd.into_datum(), (len as i64).into_datum()],
+            );
+        }
+    }
+}
+
+impl Read for LargeObjectHandle {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(i32::MAX as usize) as i32;
+        let data = unsafe {
+            direct_function_call::<&[u8]>(
+                pg_sys::be_loread,
+                vec![self.fd.into_datum(), len.into_datum()],
+            )
+        }
+        .expect("loread returned NULL");
+        buf[..data.len()].copy_from_slice(data);
+        Ok(data.len())
+    }
+}
+
+impl Write for LargeObjectHandle {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = unsafe {
+            direct_function_call::<i32>(
+                pg_sys::be_lowrite,
+                vec![self.fd.into_datum(), buf.into_datum()],
+            )
+        }
+        .expect("lowrite returned NULL");
+        Ok(written as usize)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        // writes go straight to `pg_largeobject`
+        Ok(())
+    }
+}
+
+impl Seek for LargeObjectHandle {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let (offset, whence) = match pos {
+            SeekFrom::Start(offset) => (offset as i64, pg_sys::SEEK_SET as i32),
+            SeekFrom::Current(offset) => (offset, pg_sys::SEEK_CUR as i32),
+            SeekFrom::End(offset) => (offset, pg_sys::SEEK_END as i32),
+        };
+        let position = unsafe {
+            direct_function_call::<i64>(
+                pg_sys::be_lo_lseek64,
+                vec![
+                    self.fd.into_datum(),
+                    offset.into_datum(),
+                    whence.into_datum(),
+                ],
+            )
+        }
+        .expect("lo_lseek64 returned NULL");
+        Ok(position as u64)
+    }
+}
+
+impl Drop for LargeObjectHandle {
+    fn drop(&mut self) {
+        unsafe {
+            // if the transaction is aborting, Postgres has already closed it
+            if pg_sys::IsTransactionState() {
+                direct_function_call::<i32>(pg_sys::be_lo_close, vec![self.fd.into_datum()]);
+            }
+        }
+    }
+}
@@ -33,65 +33,168 @@ extern crate bitflags;
 // expose our various derive macros
 pub use pgx_macros::*;
 
+// declared first, so that the modules below can use its macros
+#[macro_use]
+pub mod log;
 pub mod aggregate;
+pub mod async_bgworker;
+pub mod async_spi;
+pub mod atomics;
+pub mod bgworkers;
 pub mod callbacks;
+pub mod catch_error;
+pub mod collation;
+pub mod condition_variable;
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+pub mod copy;
+pub mod custom_scan;
 pub mod datum;
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+pub mod dshash;
+pub mod dsm;
 pub mod enum_helper;
+pub mod event_trigger;
+pub mod executor;
+pub mod explain;
 pub mod fcinfo;
+pub mod fdw;
+pub mod generic_xlog;
+pub mod gin;
+pub mod gist;
 pub mod guc;
+pub mod heap_tuple;
 pub mod hooks;
 pub mod htup;
+pub mod index_am;
 pub mod inoutfuncs;
+pub mod interrupt;
+pub mod interruptible;
 pub mod itemptr;
+pub mod language_handler;
+pub mod large_object;
 pub mod list;
-#[macro_use]
-pub mod log;
-pub mod atomics;
-pub mod bgworkers;
+pub mod lock;
+#[cfg(feature = "tracing-subscriber")]
+pub mod log_layer;
 pub mod lwlock;
 pub mod memcxt;
 pub mod misc;
 pub mod namespace;
 pub mod nodes;
+pub mod notify;
+pub mod output_plugin;
+pub mod parallel;
+pub mod pg_bench;
 pub mod pgbox;
+pub mod pgvec;
+#[cfg(feature = "proptest")]
+pub mod pg_proptest;
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+pub mod planner_support;
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+pub mod procedure;
 pub mod rel;
+pub mod replication_slot;
+pub mod scheduler;
+pub mod shm_mq;
 pub mod shmem;
+pub mod snapshot;
 pub mod spi;
+pub mod sql;
+pub mod stats;
 pub mod stringinfo;
+pub mod structured_log;
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+pub mod table_am;
+pub mod table_iterator;
 pub mod trigger_support;
+pub mod trigger_test;
 pub mod tupdesc;
+pub mod utility;
 pub mod varlena;
+pub mod wait_event;
+pub mod window;
 pub mod wrappers;
 pub mod xid;
 
 #[doc(hidden)]
 pub use once_cell;
+#[cfg(feature = "proptest")]
+pub use proptest;
 
 pub use aggregate::*;
 pub use atomics::*;
 pub use callbacks::*;
+pub use catch_error::*;
+pub use collation::*;
+pub use condition_variable::*;
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+pub use copy::*;
+pub use custom_scan::*;
 pub use datum::*;
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+pub use dshash::*;
+pub use dsm::*;
 pub use enum_helper::*;
+pub use event_trigger::*;
+pub use executor::*;
+pub use explain::*;
 pub use fcinfo::*;
+pub use fdw::*;
+pub use generic_xlog::*;
+pub use gin::*;
+pub use gist::*;
 pub use guc::*;
+pub use heap_tuple::*;
 pub use hooks::*;
 pub use htup::*;
+pub use index_am::*;
 pub use inoutfuncs::*;
+pub use interrupt::*;
+pub use interruptible::*;
 pub use itemptr::*;
+pub use language_handler::*;
+pub use large_object::*;
 pub use list::*;
+pub use lock::*;
 pub use log::*;
+#[cfg(feature = "tracing-subscriber")]
+pub use log_layer::*;
 pub use lwlock::*;
 pub use memcxt::*;
 pub use namespace::*;
 pub use nodes::*;
+pub use notify::*;
+pub use output_plugin::*;
+pub use parallel::*;
+pub use pg_bench::*;
 pub use pgbox::*;
+pub use pgvec::*;
+#[cfg(feature = "proptest")]
+pub use pg_proptest::*;
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+pub use planner_support::*;
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+pub use procedure::*;
 pub use rel::*;
+pub use replication_slot::*;
+pub use shm_mq::*;
 pub use shmem::*;
+pub use snapshot::*;
 pub use spi::*;
+pub use stats::*;
 pub use stringinfo::*;
+pub use structured_log::*;
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+pub use table_am::*;
+pub use table_iterator::*;
 pub use trigger_support::*;
+pub use trigger_test::*;
 pub use tupdesc::*;
+pub use utility::*;
 pub use varlena::*;
+pub use wait_event::*;
+pub use window::*;
 pub use wrappers::*;
 pub use xid::*;
 
@@ -227,12 +330,24 @@ pub static DEFAULT_TYPEID_SQL_MAPPING: Lazy<HashSet<RustSqlMapping>> = Lazy::new
     map_type!(m, pgx_pg_sys::PlannerInfo, "internal");
     map_type!(m, datum::Internal, "internal");
     map_type!(m, pgbox::PgBox<pgx_pg_sys::IndexAmRoutine>, "internal");
+    map_type!(m, pgbox::PgBox<pgx_pg_sys::FdwRoutine>, "fdw_handler");
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+    map_type!(m, pgbox::PgBox<pgx_pg_sys::TableAmRoutine>, "table_am_handler");
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+    map_type!(m, planner_support::SupportRequest, "internal");
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+    map_type!(m, planner_support::SupportResponse, "internal");
     map_type!(m, rel::PgRelation, "regclass");
     map_type!(m, datum::Numeric, "numeric");
     map_type!(m, datum::AnyElement, "anyelement");
     map_type!(m, datum::AnyArray, "anyarray");
     map_type!(m, datum::Inet, "inet");
+    map_type!(m, std::net::IpAddr, "inet");
+    map_type!(m, std::net::Ipv4Addr, "inet");
+    map_type!(m, std::net::Ipv6Addr, "inet");
     map_type!(m, datum::Uuid, "uuid");
+    map_type!(m, datum::RefCursor, "refcursor");
+    map_type!(m, heap_tuple::PgHeapTuple<'static>, "record");
 
     m
 });
@@ -0,0 +1,280 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Postgres' heavyweight locks: [advisory locks](https://www.postgresql.org/docs/current/explicit-locking.html#ADVISORY-LOCKS),
+//! for coordinating backends by a key an extension chooses, and locks on relations
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[pg_extern]
+//! fn refresh_cache() {
+//!     // only one backend refreshes at a time, and the others skip it
+//!     if let Some(_refreshing) = AdvisoryLock::new(42).try_lock() {
+//!         // ... refresh ...
+//!     }
+//! }
+//! ```
+use crate::{pg_sys, IntoDatum};
+
+/// The mode of a lock on a relation, as with `LOCK TABLE ... IN mode MODE`, from the weakest to
+/// the strongest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PgLockMode {
+    /// Taken by `SELECT`, and conflicting only with `AccessExclusive`
+    AccessShare,
+    /// Taken by `SELECT FOR UPDATE` and `SELECT FOR SHARE`
+    RowShare,
+    /// Taken by `INSERT`, `UPDATE`, and `DELETE`
+    RowExclusive,
+    /// Taken by `VACUUM`, `ANALYZE`, and `CREATE INDEX CONCURRENTLY`
+    ShareUpdateExclusive,
+    /// Taken by `CREATE INDEX`, and conflicting with changes to the rows
+    Share,
+    /// Taken by `CREATE TRIGGER`, and self-exclusive
+    ShareRowExclusive,
+    /// Taken by `REFRESH MATERIALIZED VIEW CONCURRENTLY`, and allowing only reads
+    Exclusive,
+    /// Taken by `DROP TABLE`, `TRUNCATE`, and most `ALTER TABLE`s, and conflicting with all others
+    AccessExclusive,
+}
+
+impl PgLockMode {
+    /// The mode as Postgres' `LOCKMODE`, such as `pg_sys::AccessShareLock`
+    pub fn as_lockmode(&self) -> pg_sys::LOCKMODE {
+        (match self {
+            PgLockMode::AccessShare => pg_sys::AccessShareLock,
+            PgLockMode::RowShare => pg_sys::RowShareLock,
+            PgLockMode::RowExclusive => pg_sys::RowExclusiveLock,
+            PgLockMode::ShareUpdateExclusive => pg_sys::ShareUpdateExclusiveLock,
+            PgLockMode::Share => pg_sys::ShareLock,
+            PgLockMode::ShareRowExclusive => pg_sys::ShareRowExclusiveLock,
+            PgLockMode::Exclusive => pg_sys::ExclusiveLock,
+            PgLockMode::AccessExclusive => pg_sys::AccessExclusiveLock,
+        }) as pg_sys::LOCKMODE
+    }
+}
+
+/// Locks on relations, which are held until the transaction ends, like those `LOCK TABLE` takes
+pub struct RelationLock;
+
+impl RelationLock {
+    /// Lock the relation `oid`, waiting for the backends holding conflicting locks
+    pub fn acquire(oid: pg_sys::Oid, mode: PgLockMode) {
+        unsafe { pg_sys::LockRelationOid(oid, mode.as_lockmode()) }
+    }
+
+    /// Lock the relation `oid` if that's possible without waiting, returning whether it was
+    /// locked
+    pub fn try_acquire(oid: pg_sys::Oid, mode: PgLockMode) -> bool {
+        unsafe { pg_sys::ConditionalLockRelationOid(oid, mode.as_lockmode()) }
+    }
+
+    /// Lock the relation named `name`, which may be schema-qualified, returning its oid
+    ///
+    /// The name is looked up again once it's locked, in case the relation was dropped or renamed
+    /// while this waited.  Postgres raises an ERROR if there's no such relation.
+    pub fn acquire_by_name(name: &str, mode: PgLockMode) -> pg_sys::Oid {
+        unsafe {
+            let names = pg_sys::textToQualifiedNameList(
+                name.into_datum().expect("name was NULL") as *mut pg_sys::text
+            );
+            let range_var = pg_sys::makeRangeVarFromNameList(names);
+            range_var_get_relid(range_var, mode.as_lockmode())
+        }
+    }
+
+    /// Release a lock taken on the relation `oid` in `mode` before the transaction ends, which is
+    /// only safe for a relation the transaction hasn't read or changed since it was locked
+    pub fn release(oid: pg_sys::Oid, mode: PgLockMode) {
+        unsafe { pg_sys::UnlockRelationOid(oid, mode.as_lockmode()) }
+    }
+}
+
+#[cfg(feature = "pg10")]
+unsafe fn range_var_get_relid(
+    range_var: *mut pg_sys::RangeVar,
+    lockmode: pg_sys::LOCKMODE,
+) -> pg_sys::Oid {
+    pg_sys::RangeVarGetRelidExtended(
+        range_var,
+        lockmode,
+        false,
+        false,
+        None,
+        std::ptr::null_mut(),
+    )
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+unsafe fn range_var_get_relid(
+    range_var: *mut pg_sys::RangeVar,
+    lockmode: pg_sys::LOCKMODE,
+) -> pg_sys::Oid {
+    pg_sys::RangeVarGetRelidExtended(range_var, lockmode, 0, None, std::ptr::null_mut())
+}
+
+/// The key of an advisory lock, which is either one `bigint`, or two `integer`s, as for
+/// `pg_advisory_lock()`, whose keys are distinct from one another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AdvisoryLockKey {
+    Int8(i64),
+    Int4(i32, i32),
+}
+
+impl From<i64> for AdvisoryLockKey {
+    fn from(key: i64) -> Self {
+        AdvisoryLockKey::Int8(key)
+    }
+}
+
+impl From<(i32, i32)> for AdvisoryLockKey {
+    fn from((key1, key2): (i32, i32)) -> Self {
+        AdvisoryLockKey::Int4(key1, key2)
+    }
+}
+
+/// An advisory lock, of this database, which is held by the session until it's unlocked, or by
+/// the transaction until it ends
+///
+/// An exclusive lock conflicts with every other lock on its key, and a shared lock only with
+/// exclusive locks.  As with `pg_advisory_lock()`, a session can hold the same lock more than
+/// once, and holds it until it's unlocked as many times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AdvisoryLock {
+    key: AdvisoryLockKey,
+    shared: bool,
+}
+
+impl AdvisoryLock {
+    /// The exclusive lock on `key`
+    pub fn new<K: Into<AdvisoryLockKey>>(key: K) -> Self {
+        AdvisoryLock {
+            key: key.into(),
+            shared: false,
+        }
+    }
+
+    /// The shared lock on `key`
+    pub fn shared<K: Into<AdvisoryLockKey>>(key: K) -> Self {
+        AdvisoryLock {
+            key: key.into(),
+            shared: true,
+        }
+    }
+
+    pub fn key(&self) -> AdvisoryLockKey {
+        self.key
+    }
+
+    pub fn is_shared(&self) -> bool {
+        self.shared
+    }
+
+    /// Lock it for the session, waiting for the sessions holding a conflicting lock, as
+    /// `pg_advisory_lock()` does, until the returned guard is dropped
+    ///
+    /// Unlike a transaction's lock, the session's isn't released when the transaction aborts,
+    /// but the guard releases it as an ERROR unwinds past it.  `std::mem::forget()` the guard to
+    /// hold the lock until the session ends, or [`AdvisoryLock::unlock_all`].
+    pub fn lock(&self) -> AdvisoryLockGuard {
+        self.acquire(true, false);
+        AdvisoryLockGuard { lock: *self }
+    }
+
+    /// Lock it for the session if that's possible without waiting, as
+    /// `pg_try_advisory_lock()` does, until the returned guard is dropped
+    pub fn try_lock(&self) -> Option<AdvisoryLockGuard> {
+        if self.acquire(true, true) {
+            Some(AdvisoryLockGuard { lock: *self })
+        } else {
+            None
+        }
+    }
+
+    /// Lock it until the transaction ends, waiting for the sessions holding a conflicting lock,
+    /// as `pg_advisory_xact_lock()` does
+    pub fn lock_xact(&self) {
+        self.acquire(false, false);
+    }
+
+    /// Lock it until the transaction ends if that's possible without waiting, returning whether
+    /// it was locked, as `pg_try_advisory_xact_lock()` does
+    pub fn try_lock_xact(&self) -> bool {
+        self.acquire(false, true)
+    }
+
+    /// Release every advisory lock the session holds, as `pg_advisory_unlock_all()` does, but
+    /// not those the transaction holds
+    pub fn unlock_all() {
+        unsafe { pg_sys::LockReleaseSession(pg_sys::USER_LOCKMETHOD as pg_sys::LOCKMETHODID) }
+    }
+
+    fn acquire(&self, session: bool, dont_wait: bool) -> bool {
+        unsafe {
+            if pg_sys::IsInParallelMode() {
+                crate::ereport!(
+                    ERROR,
+                    crate::PgSqlErrorCode::ERRCODE_INVALID_TRANSACTION_STATE,
+                    "cannot use advisory locks during a parallel operation"
+                );
+            }
+
+            let result = pg_sys::LockAcquire(&self.tag(), self.lockmode(), session, dont_wait);
+            result != pg_sys::LockAcquireResult_LOCKACQUIRE_NOT_AVAIL
+        }
+    }
+
+    fn release(&self) -> bool {
+        unsafe { pg_sys::LockRelease(&self.tag(), self.lockmode(), true) }
+    }
+
+    fn lockmode(&self) -> pg_sys::LOCKMODE {
+        (if self.shared {
+            pg_sys::ShareLock
+        } else {
+            pg_sys::ExclusiveLock
+        }) as pg_sys::LOCKMODE
+    }
+
+    /// The tag `SET_LOCKTAG_ADVISORY()` makes
+    fn tag(&self) -> pg_sys::LOCKTAG {
+        let (field2, field3, field4) = match self.key {
+            AdvisoryLockKey::Int8(key) => ((key >> 32) as u32, key as u32, 1),
+            AdvisoryLockKey::Int4(key1, key2) => (key1 as u32, key2 as u32, 2),
+        };
+        pg_sys::LOCKTAG {
+            locktag_field1: unsafe { pg_sys::MyDatabaseId },
+            locktag_field2: field2,
+            locktag_field3: field3,
+            locktag_field4: field4,
+            locktag_type: pg_sys::LockTagType_LOCKTAG_ADVISORY as u8,
+            locktag_lockmethodid: pg_sys::USER_LOCKMETHOD as u8,
+        }
+    }
+}
+
+/// A session's advisory lock, which is released when this is dropped, from
+/// [`AdvisoryLock::lock`] or [`AdvisoryLock::try_lock`]
+#[must_use = "the lock is released as soon as the guard is dropped"]
+pub struct AdvisoryLockGuard {
+    lock: AdvisoryLock,
+}
+
+impl AdvisoryLockGuard {
+    pub fn lock(&self) -> &AdvisoryLock {
+        &self.lock
+    }
+}
+
+impl Drop for AdvisoryLockGuard {
+    fn drop(&mut self) {
+        self.lock.release();
+    }
+}
@@ -59,7 +59,7 @@ pub enum PgLogLevel {
 
 /// This list of SQL Error Codes is taken directly from Postgres 12's generated "utils/errcodes.h"
 #[allow(non_camel_case_types)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PgSqlErrorCode {
     /// Class 00 - Successful Completion
     ERRCODE_SUCCESSFUL_COMPLETION = MAKE_SQLSTATE('0', '0', '0', '0', '0') as isize,
@@ -433,6 +433,16 @@ pub enum PgSqlErrorCode {
     ERRCODE_INDEX_CORRUPTED = MAKE_SQLSTATE('X', 'X', '0', '0', '2') as isize,
 }
 
+impl PgSqlErrorCode {
+    /// The five-character SQLSTATE of this code, such as `"22012"`
+    pub fn sqlstate(&self) -> String {
+        let code = *self as i32;
+        (0..5)
+            .map(|i| (((code >> (6 * i)) & 0x3F) as u8 + b'0') as char)
+            .collect()
+    }
+}
+
 #[allow(non_snake_case)]
 #[inline]
 const fn PGSIXBIT(ch: i32) -> i32 {
@@ -531,6 +541,214 @@ pub fn ereport(
     }
 }
 
+/// A Postgres `ereport` with every field of the errors Postgres raises itself: a SQLSTATE and
+/// message, along with an optional detail, hint, context, and cursor position
+///
+/// It's usually built and reported by the [`ereport!`](crate::ereport!) macro.  Reported at
+/// `ERROR` or above, it doesn't return; below, such as at `WARNING` or `NOTICE`, the same fields
+/// are sent to the client and the log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorReport {
+    /// The SQLSTATE, as packed by `MAKE_SQLSTATE()`
+    code: i32,
+    message: String,
+    detail: Option<String>,
+    hint: Option<String>,
+    context: Option<String>,
+    position: Option<usize>,
+    file: &'static str,
+    line: u32,
+    funcname: &'static str,
+}
+
+impl ErrorReport {
+    /// A report of `message` with the SQLSTATE `code`, raised at `file:line` in `funcname`, as
+    /// `file!()`, `line!()`, and `module_path!()` say
+    pub fn new(
+        code: PgSqlErrorCode,
+        message: impl Into<String>,
+        file: &'static str,
+        line: u32,
+        funcname: &'static str,
+    ) -> Self {
+        Self::from_sqlerrcode(code as i32, message, file, line, funcname)
+    }
+
+    /// A report like [`ErrorReport::new`]'s, of the SQLSTATE packed in `code`, such as an
+    /// `ErrorData`'s `sqlerrcode`
+    pub(crate) fn from_sqlerrcode(
+        code: i32,
+        message: impl Into<String>,
+        file: &'static str,
+        line: u32,
+        funcname: &'static str,
+    ) -> Self {
+        ErrorReport {
+            code,
+            message: message.into(),
+            detail: None,
+            hint: None,
+            context: None,
+            position: None,
+            file,
+            line,
+            funcname,
+        }
+    }
+
+    /// A secondary message, with more about the problem, as a complete sentence
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Advice on how to fix the problem, as a complete sentence
+    pub fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// Where the problem happened, such as the object being processed
+    pub fn context(mut self, context: impl Into<String>) -> Self {
+        self.context = Some(context.into());
+        self
+    }
+
+    /// The 1-based character position in the query string at which the problem is
+    pub fn position(mut self, position: usize) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Report this at `level`
+    ///
+    /// At `ERROR` or above, this raises the error, which aborts the transaction, so it doesn't
+    /// return.
+    pub fn report(self, level: PgLogLevel) {
+        use std::ffi::CString;
+
+        // strings with interior NULs are cut off at the first
+        let cstring = |s: &str| CString::new(s.split('\0').next().unwrap()).unwrap();
+        let message = cstring(&self.message);
+        let detail = self.detail.as_deref().map(cstring);
+        let hint = self.hint.as_deref().map(cstring);
+        let context = self.context.as_deref().map(cstring);
+        let file = cstring(self.file);
+        let funcname = cstring(self.funcname);
+        let as_ptr = |s: &Option<CString>| s.as_ref().map_or(std::ptr::null(), |s| s.as_ptr());
+
+        unsafe {
+            crate::pg_sys::pgx_ereport_full(
+                level as i32,
+                self.code,
+                message.as_ptr(),
+                as_ptr(&detail),
+                as_ptr(&hint),
+                as_ptr(&context),
+                self.position.unwrap_or(0) as i32,
+                file.as_ptr(),
+                self.line as i32,
+                funcname.as_ptr(),
+            );
+        }
+    }
+}
+
+/// How the Rust panics of this extension, including `error!()`'s, are reported when they reach
+/// Postgres
+///
+/// By default, a panic becomes an `ERROR` with the SQLSTATE `XX000` (`internal_error`), whose
+/// message is the panic's and whose context is where it panicked, and a backtrace is only logged
+/// when the backend's `RUST_BACKTRACE` environment variable asks for one.  Install another, usually
+/// in `_PG_init()`, to change that for this extension alone:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[allow(non_snake_case)]
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     PanicHandling {
+///         sqlstate: PgSqlErrorCode::ERRCODE_DATA_EXCEPTION,
+///         backtrace: true,
+///         ..Default::default()
+///     }
+///     .install();
+/// }
+/// ```
+///
+/// The backtrace is only written to the server log, as the error's `DETAIL`, and never sent to
+/// the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanicHandling {
+    /// The SQLSTATE of the error a panic becomes
+    pub sqlstate: PgSqlErrorCode,
+    /// Whether a panic becomes an `ERROR`, or ends the session, or aborts the backend
+    pub action: crate::PanicAction,
+    /// Whether to log a backtrace of every panic, regardless of `RUST_BACKTRACE`
+    pub backtrace: bool,
+}
+
+impl Default for PanicHandling {
+    fn default() -> Self {
+        PanicHandling {
+            sqlstate: PgSqlErrorCode::ERRCODE_INTERNAL_ERROR,
+            action: crate::PanicAction::Error,
+            backtrace: false,
+        }
+    }
+}
+
+impl PanicHandling {
+    /// Report this extension's panics this way from now on
+    pub fn install(self) {
+        crate::set_panic_sqlstate(self.sqlstate as i32);
+        crate::set_panic_action(self.action);
+        crate::set_panic_backtrace(self.backtrace);
+    }
+}
+
+/// Report a message at a Postgres log level, with a SQLSTATE and any of a detail, hint, context,
+/// and cursor position, like Postgres' own `ereport()`
+///
+/// Its arguments are the level, the [`PgSqlErrorCode`], the message, and then the other fields
+/// by name, as the [`ErrorReport`] methods of the same names take them.  At `ERROR` or above, it
+/// doesn't return.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn adopt(dogs: i32) -> i32 {
+///     if dogs < 1 {
+///         ereport!(
+///             ERROR,
+///             PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+///             "can't adopt fewer than one dog",
+///             detail = format!("{} dogs were asked for.", dogs),
+///             hint = "Adopt one, or adopt two.",
+///         );
+///     }
+///     if dogs > 2 {
+///         ereport!(
+///             WARNING,
+///             PgSqlErrorCode::ERRCODE_WARNING,
+///             "that's a lot of dogs",
+///             context = "adopting dogs",
+///         );
+///     }
+///     dogs
+/// }
+/// ```
+#[macro_export]
+macro_rules! ereport {
+    ($level:ident, $code:expr, $message:expr $(, $field:ident = $value:expr)* $(,)?) => {
+        $crate::log::ErrorReport::new($code, $message, file!(), line!(), module_path!())
+            $(.$field($value))*
+            .report($crate::log::PgLogLevel::$level)
+    };
+}
+
 /// Log to Postgres' `debug5` log level.
 ///
 /// This macro accepts arguments like the [`println`](std::println) and [`format`](std::format) macros.
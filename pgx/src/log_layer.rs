@@ -0,0 +1,223 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! A [tracing-subscriber](https://docs.rs/tracing-subscriber) layer which logs `tracing` events
+//! to the Postgres log, which needs the `tracing-subscriber` feature
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[allow(non_snake_case)]
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     PgLogLayer::new().install().expect("a tracing subscriber was already installed");
+//! }
+//!
+//! #[pg_extern]
+//! #[tracing::instrument]
+//! fn adopt(name: &str) {
+//!     tracing::info!(dogs = 1, "adopted");
+//! }
+//! ```
+//!
+//! `SELECT adopt('brandy')` then logs `adopt{name="brandy"}: adopted dogs=1` at `LOG`.
+use crate::log::{elog, PgLogLevel};
+use crate::pg_sys;
+use std::fmt::Write;
+use std::thread::ThreadId;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Record};
+use tracing::{Event, Id, Level, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Logs `tracing` events to the Postgres log, prefixed by the names and fields of the spans
+/// they're in
+///
+/// Events are logged at the levels [`PgLogLayer::with_level`] maps theirs to, which are never
+/// `ERROR` or above, as that would abort the transaction, and only if Postgres would output
+/// them, as `log_min_messages` and `client_min_messages` say, so the events filtered out cost
+/// next to nothing.  Postgres can only be used from the backend's main thread, so the events of
+/// other threads are dropped.
+pub struct PgLogLayer {
+    /// The Postgres levels of `ERROR`, `WARN`, `INFO`, `DEBUG`, and `TRACE` events
+    levels: [PgLogLevel; 5],
+    main_thread: ThreadId,
+}
+
+impl Default for PgLogLayer {
+    fn default() -> Self {
+        PgLogLayer::new()
+    }
+}
+
+impl PgLogLayer {
+    /// A layer logging the events of the thread which makes it, which must be the backend's main
+    /// thread, mapping `ERROR` and `WARN` events to `WARNING`, `INFO` to `LOG`, `DEBUG` to
+    /// `DEBUG1`, and `TRACE` to `DEBUG2`
+    pub fn new() -> Self {
+        PgLogLayer {
+            levels: [
+                PgLogLevel::WARNING,
+                PgLogLevel::WARNING,
+                PgLogLevel::LOG,
+                PgLogLevel::DEBUG1,
+                PgLogLevel::DEBUG2,
+            ],
+            main_thread: std::thread::current().id(),
+        }
+    }
+
+    /// Log `tracing` events of `level` at the Postgres `pg_level`
+    ///
+    /// ## Panics
+    ///
+    /// If `pg_level` is `ERROR` or above
+    pub fn with_level(mut self, level: Level, pg_level: PgLogLevel) -> Self {
+        if pg_level as i32 >= PgLogLevel::ERROR as i32 {
+            panic!(
+                "tracing events can't be logged at ERROR or above, as that aborts the transaction"
+            );
+        }
+        self.levels[level_index(&level)] = pg_level;
+        self
+    }
+
+    /// Make this layer, over a registry of spans, the global `tracing` subscriber
+    pub fn install(self) -> Result<(), tracing::subscriber::SetGlobalDefaultError> {
+        tracing::subscriber::set_global_default(tracing_subscriber::registry().with(self))
+    }
+
+    fn pg_level(&self, level: &Level) -> PgLogLevel {
+        self.levels[level_index(level)]
+    }
+
+    fn would_output(&self, metadata: &Metadata<'_>) -> bool {
+        std::thread::current().id() == self.main_thread
+            && would_output(self.pg_level(metadata.level()))
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for PgLogLayer {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("the new span isn't registered");
+        let mut fields = SpanFields::default();
+        attrs.record(&mut FieldVisitor(&mut fields.0));
+        span.extensions_mut().insert(fields);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("the recorded span isn't registered");
+        let mut extensions = span.extensions_mut();
+        if let Some(fields) = extensions.get_mut::<SpanFields>() {
+            values.record(&mut FieldVisitor(&mut fields.0));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if !self.would_output(metadata) {
+            return;
+        }
+
+        let mut line = String::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                line.push_str(span.name());
+                if let Some(fields) = span.extensions().get::<SpanFields>() {
+                    if !fields.0.is_empty() {
+                        // the fields were each written with a leading space
+                        write!(line, "{{{}}}", &fields.0[1..]).unwrap();
+                    }
+                }
+                line.push(':');
+            }
+            line.push(' ');
+        }
+
+        let mut fields = String::new();
+        let mut visitor = EventVisitor {
+            message: None,
+            fields: FieldVisitor(&mut fields),
+        };
+        event.record(&mut visitor);
+        match visitor.message {
+            Some(message) => line.push_str(&message),
+            None => line.push_str(metadata.target()),
+        }
+        line.push_str(&fields);
+
+        elog(self.pg_level(metadata.level()), &line);
+    }
+}
+
+/// The fields of a span, formatted as ` name=value` each
+#[derive(Default)]
+struct SpanFields(String);
+
+struct FieldVisitor<'a>(&'a mut String);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record_debug(field, &value)
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        write!(self.0, " {}={:?}", field.name(), value).unwrap();
+    }
+}
+
+/// Formats an event's message as it is, and its other fields as a [`FieldVisitor`] does
+struct EventVisitor<'a> {
+    message: Option<String>,
+    fields: FieldVisitor<'a>,
+}
+
+impl Visit for EventVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = Some(format!("{:?}", value));
+        } else {
+            self.fields.record_debug(field, value);
+        }
+    }
+}
+
+fn level_index(level: &Level) -> usize {
+    match *level {
+        Level::ERROR => 0,
+        Level::WARN => 1,
+        Level::INFO => 2,
+        Level::DEBUG => 3,
+        Level::TRACE => 4,
+    }
+}
+
+/// Would Postgres send a message at `level` to the server log or the client, as its
+/// `is_log_level_output()` and `errstart()` decide?
+fn would_output(level: PgLogLevel) -> bool {
+    let level = level as i32;
+    let (log_min_messages, client_min_messages) =
+        unsafe { (pg_sys::log_min_messages, pg_sys::client_min_messages) };
+    let log = pg_sys::LOG as i32;
+
+    // LOG sorts above ERROR for the server log
+    let to_server = if level == log || level == pg_sys::LOG_SERVER_ONLY as i32 {
+        log_min_messages == log || log_min_messages <= pg_sys::ERROR as i32
+    } else if log_min_messages == log {
+        level >= pg_sys::FATAL as i32
+    } else {
+        level >= log_min_messages
+    };
+    // INFO is always sent to the client
+    let to_client = level != pg_sys::LOG_SERVER_ONLY as i32
+        && (level >= client_min_messages || level == pg_sys::INFO as i32);
+
+    to_server || to_client
+}
@@ -29,9 +29,41 @@ use uuid::Uuid;
 /// # Poisoning
 /// This lock can not be poisoned from Rust. Panic and Abort are handled by
 /// PostgreSQL cleanly.
+///
+/// # Deadlocks
+/// LWLocks have no deadlock detection, so acquiring a lock this backend already holds, in any
+/// mode, panics instead of waiting forever.  Guards release their lock when dropped, including
+/// while unwinding from a panic or ERROR, so to hold several locks at once, always acquire them
+/// in the same order and let them drop in reverse.
+///
+/// A `PgLwLock<()>` is just a named lock, for protecting state that lives elsewhere:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// static REFRESH_LOCK: PgLwLock<()> = PgLwLock::new_named("my_extension_refresh");
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     pg_shmem_init!(REFRESH_LOCK);
+/// }
+///
+/// #[pg_extern]
+/// fn refresh() -> bool {
+///     match REFRESH_LOCK.try_exclusive() {
+///         Some(_guard) => {
+///             Spi::run("REFRESH MATERIALIZED VIEW my_view");
+///             true
+///         }
+///         // someone else is already refreshing
+///         None => false,
+///     }
+/// }
+/// ```
 pub struct PgLwLock<T> {
     inner: OnceCell<PgLwLockInner<T>>,
     name: OnceCell<&'static str>,
+    fixed_name: Option<&'static str>,
 }
 
 unsafe impl<T> Send for PgLwLock<T> {}
@@ -44,6 +76,21 @@ impl<T> PgLwLock<T> {
         PgLwLock {
             inner: OnceCell::new(),
             name: OnceCell::new(),
+            fixed_name: None,
+        }
+    }
+
+    /// Create an empty lock, like [`PgLwLock::new`], whose LWLock tranche and shared memory are
+    /// named `name` rather than a random name chosen when the extension is loaded
+    ///
+    /// The name must be unique across all extensions.  Unlike a random name, it's the same in
+    /// every backend, so the lock shows up by name in `pg_stat_activity.wait_event`, and works on
+    /// platforms where each backend loads the extension itself, such as Windows.
+    pub const fn new_named(name: &'static str) -> Self {
+        PgLwLock {
+            inner: OnceCell::new(),
+            name: OnceCell::new(),
+            fixed_name: Some(name),
         }
     }
 
@@ -55,11 +102,18 @@ impl<T> PgLwLock<T> {
             .set(PgLwLockInner::<T>::new(input_name, value))
             .unwrap();
         name.set(input_name).unwrap();
-        PgLwLock { inner, name }
+        PgLwLock {
+            inner,
+            name,
+            fixed_name: None,
+        }
     }
 
     /// Get the name of the PgLwLock
     pub fn get_name(&self) -> &'static str {
+        if let Some(name) = self.fixed_name {
+            return name;
+        }
         match self.name.get() {
             None => {
                 let name = Box::leak(Uuid::new_v4().to_string().into_boxed_str());
@@ -86,6 +140,30 @@ impl<T> PgLwLock<T> {
             .exclusive()
     }
 
+    /// Obtain a shared lock if it's available right now, without waiting
+    pub fn try_share(&self) -> Option<PgLwLockShareGuard<T>> {
+        self.inner
+            .get()
+            .expect("Can't give out share, lock is in an empty state")
+            .try_share()
+    }
+
+    /// Obtain an exclusive lock if it's available right now, without waiting
+    pub fn try_exclusive(&self) -> Option<PgLwLockExclusiveGuard<T>> {
+        self.inner
+            .get()
+            .expect("Can't give out exclusive, lock is in an empty state")
+            .try_exclusive()
+    }
+
+    /// Does this backend currently hold the lock, in either mode?
+    pub fn is_held(&self) -> bool {
+        match self.inner.get() {
+            Some(inner) => unsafe { pg_sys::LWLockHeldByMe(inner.lock_ptr) },
+            None => false,
+        }
+    }
+
     /// Attach an empty PgLwLock lock to a LWLock, and wrap T
     pub fn attach(&self, value: *mut T) {
         self.inner
@@ -95,6 +173,7 @@ impl<T> PgLwLock<T> {
 }
 
 pub struct PgLwLockInner<T> {
+    name: &'static str,
     lock_ptr: *mut pg_sys::LWLock,
     data: *mut T,
 }
@@ -110,6 +189,7 @@ impl<'a, T> PgLwLockInner<T> {
         unsafe {
             let lock = std::ffi::CString::new(name).expect("CString::new failed");
             PgLwLockInner {
+                name,
                 lock_ptr: &mut (*pg_sys::GetNamedLWLockTranche(lock.as_ptr())).lock,
                 data,
             }
@@ -118,25 +198,63 @@ impl<'a, T> PgLwLockInner<T> {
 
     fn share(&self) -> PgLwLockShareGuard<T> {
         unsafe {
+            self.check_not_held();
             pg_sys::LWLockAcquire(self.lock_ptr, pg_sys::LWLockMode_LW_SHARED);
-
-            PgLwLockShareGuard {
-                data: self.data.as_ref().unwrap(),
-                lock: self.lock_ptr,
-            }
+            self.share_guard()
         }
     }
 
     fn exclusive(&self) -> PgLwLockExclusiveGuard<T> {
         unsafe {
+            self.check_not_held();
             pg_sys::LWLockAcquire(self.lock_ptr, pg_sys::LWLockMode_LW_EXCLUSIVE);
+            self.exclusive_guard()
+        }
+    }
 
-            PgLwLockExclusiveGuard {
-                data: self.data.as_mut().unwrap(),
-                lock: self.lock_ptr,
+    fn try_share(&self) -> Option<PgLwLockShareGuard<T>> {
+        unsafe {
+            self.check_not_held();
+            if pg_sys::LWLockConditionalAcquire(self.lock_ptr, pg_sys::LWLockMode_LW_SHARED) {
+                Some(self.share_guard())
+            } else {
+                None
             }
         }
     }
+
+    fn try_exclusive(&self) -> Option<PgLwLockExclusiveGuard<T>> {
+        unsafe {
+            self.check_not_held();
+            if pg_sys::LWLockConditionalAcquire(self.lock_ptr, pg_sys::LWLockMode_LW_EXCLUSIVE) {
+                Some(self.exclusive_guard())
+            } else {
+                None
+            }
+        }
+    }
+
+    unsafe fn check_not_held(&self) {
+        if pg_sys::LWLockHeldByMe(self.lock_ptr) {
+            panic!("LWLock `{}` is already held by this backend", self.name);
+        }
+    }
+
+    /// The lock must be held in (at least) shared mode
+    unsafe fn share_guard(&self) -> PgLwLockShareGuard<T> {
+        PgLwLockShareGuard {
+            data: self.data.as_ref().unwrap(),
+            lock: self.lock_ptr,
+        }
+    }
+
+    /// The lock must be held in exclusive mode
+    unsafe fn exclusive_guard(&self) -> PgLwLockExclusiveGuard<T> {
+        PgLwLockExclusiveGuard {
+            data: self.data.as_mut().unwrap(),
+            lock: self.lock_ptr,
+        }
+    }
 }
 
 pub struct PgLwLockShareGuard<'a, T> {
@@ -15,8 +15,12 @@ Use of this source code is governed by the MIT license that can be found in the
 //! An enum-based interface (`PgMemoryContexts`) around Postgres' various `MemoryContext`s provides
 //! simple accessibility to working with MemoryContexts in a compiler-checked manner
 //!
+//! Child contexts are created with `PgMemoryContexts::new_child()`, made current for a scope with
+//! `::switch()`, and measured with `::stats()`.
+//!
 use crate::pg_sys::AsPgCStr;
 use crate::{guard, pg_sys, PgBox};
+use std::cell::Cell;
 use std::fmt::Debug;
 
 /// A shorter type name for a `*const std::os::raw::c_void`
@@ -171,22 +175,108 @@ pub enum PgMemoryContexts {
 
 /// A `pg_sys::MemoryContext` that is owned by `PgMemoryContexts::Owned`
 #[derive(Debug)]
-pub struct OwnedMemoryContext(pg_sys::MemoryContext);
+pub struct OwnedMemoryContext {
+    context: pg_sys::MemoryContext,
+    /// Set by the context's reset callback, as it's deleted along with its parent, or reset
+    gone: Box<Cell<bool>>,
+}
+
+impl OwnedMemoryContext {
+    fn new(context: pg_sys::MemoryContext) -> Self {
+        let owned = OwnedMemoryContext {
+            context,
+            gone: Box::new(Cell::new(false)),
+        };
+        owned.watch();
+        owned
+    }
+
+    /// Register the callback which marks the context as gone when it's next reset or deleted
+    fn watch(&self) {
+        unsafe extern "C" fn mark_gone(arg: void_mut_ptr) {
+            (*(arg as *const Cell<bool>)).set(true);
+        }
+
+        self.gone.set(false);
+        unsafe {
+            let callback = pg_sys::MemoryContextAlloc(
+                self.context,
+                std::mem::size_of::<pg_sys::MemoryContextCallback>(),
+            ) as *mut pg_sys::MemoryContextCallback;
+            callback.write(pg_sys::MemoryContextCallback {
+                func: Some(mark_gone),
+                arg: &*self.gone as *const Cell<bool> as void_mut_ptr,
+                next: std::ptr::null_mut(),
+            });
+            pg_sys::MemoryContextRegisterResetCallback(self.context, callback);
+        }
+    }
+}
 
 impl Drop for OwnedMemoryContext {
+    fn drop(&mut self) {
+        // a context that's been reset, other than by `PgMemoryContexts::reset()`, can't be told
+        // apart from one that's been deleted, so it's left for its parent to delete
+        if !self.gone.get() {
+            unsafe {
+                pg_sys::MemoryContextDelete(self.context);
+            }
+        }
+    }
+}
+
+/// Makes the context that was the `CurrentMemoryContext` before [`PgMemoryContexts::switch`]
+/// current again, when it's dropped
+#[must_use = "the context is only current until the guard is dropped"]
+pub struct MemoryContextGuard<'a> {
+    previous: pg_sys::MemoryContext,
+    _context: std::marker::PhantomData<&'a PgMemoryContexts>,
+}
+
+impl Drop for MemoryContextGuard<'_> {
     fn drop(&mut self) {
         unsafe {
-            pg_sys::MemoryContextDelete(self.0);
+            pg_sys::CurrentMemoryContext = self.previous;
         }
     }
 }
 
+/// How much memory a context has, from [`PgMemoryContexts::stats`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryContextStats {
+    /// The number of blocks the context has allocated from the system
+    pub blocks: usize,
+    /// The number of freed chunks, which are kept for reuse
+    pub free_chunks: usize,
+    /// The bytes of all the blocks
+    pub total_bytes: usize,
+    /// The bytes of the blocks which aren't in use
+    pub free_bytes: usize,
+}
+
+impl MemoryContextStats {
+    /// The bytes which are in use
+    pub fn used_bytes(&self) -> usize {
+        self.total_bytes - self.free_bytes
+    }
+}
+
 impl PgMemoryContexts {
     /// Create a new `PgMemoryContext::Owned`
     pub fn new(name: &str) -> PgMemoryContexts {
-        PgMemoryContexts::Owned(OwnedMemoryContext(unsafe {
+        PgMemoryContexts::CurrentMemoryContext.new_child(name)
+    }
+
+    /// Create a new `PgMemoryContext::Owned`, named `name`, as a child of this context
+    ///
+    /// It's deleted, along with everything allocated in it, when it's dropped, so it's the place
+    /// for allocations which should only live as long as, say, one batch of rows.  When this
+    /// context is reset or deleted first, the child is deleted along with it, and dropping the
+    /// child then does nothing.
+    pub fn new_child(&self, name: &str) -> PgMemoryContexts {
+        PgMemoryContexts::Owned(OwnedMemoryContext::new(unsafe {
             pg_sys::AllocSetContextCreateExtended(
-                PgMemoryContexts::CurrentMemoryContext.value(),
+                self.value(),
                 name.as_pg_cstr(),
                 pg_sys::ALLOCSET_DEFAULT_MINSIZE as usize,
                 pg_sys::ALLOCSET_DEFAULT_INITSIZE as usize,
@@ -210,7 +300,7 @@ impl PgMemoryContexts {
             PgMemoryContexts::TopTransactionContext => unsafe { pg_sys::TopTransactionContext },
             PgMemoryContexts::CurTransactionContext => unsafe { pg_sys::CurTransactionContext },
             PgMemoryContexts::For(mc) => *mc,
-            PgMemoryContexts::Owned(mc) => mc.0,
+            PgMemoryContexts::Owned(mc) => mc.context,
             PgMemoryContexts::Of(ptr) => PgMemoryContexts::get_context_for_pointer(*ptr),
             PgMemoryContexts::Transient { .. } => {
                 panic!("cannot use value() to retrieve a Transient PgMemoryContext")
@@ -229,12 +319,100 @@ impl PgMemoryContexts {
         }
     }
 
+    /// Make this the `CurrentMemoryContext` until the returned guard is dropped, which makes the
+    /// context that was current before current again
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// let batch = PgMemoryContexts::new("batch");
+    /// {
+    ///     let _switched = batch.switch();
+    ///     // palloc()s here are in `batch`
+    /// }
+    /// ```
+    pub fn switch(&self) -> MemoryContextGuard<'_> {
+        let previous = unsafe { pg_sys::CurrentMemoryContext };
+        unsafe {
+            pg_sys::CurrentMemoryContext = self.value();
+        }
+        MemoryContextGuard {
+            previous,
+            _context: std::marker::PhantomData,
+        }
+    }
+
     /// Release all space allocated within a context and delete all its descendant contexts (but not
     /// the context itself).
     pub fn reset(&mut self) {
         unsafe {
             pg_sys::MemoryContextReset(self.value());
         }
+        // the reset ran, and so unregistered, the callback watching for the context's deletion
+        if let PgMemoryContexts::Owned(owned) = self {
+            owned.watch();
+        }
+    }
+
+    /// Delete all the descendant contexts of this context, along with the space allocated within
+    /// them, but not the space allocated within this context itself
+    pub fn delete_children(&mut self) {
+        unsafe {
+            pg_sys::MemoryContextDeleteChildren(self.value());
+        }
+    }
+
+    /// The name this context was created with
+    pub fn name(&self) -> String {
+        unsafe { std::ffi::CStr::from_ptr((*self.value()).name) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// The context this context is a child of, unless it's the `TopMemoryContext`
+    pub fn parent(&self) -> Option<PgMemoryContexts> {
+        let parent = unsafe { (*self.value()).parent };
+        if parent.is_null() {
+            None
+        } else {
+            Some(PgMemoryContexts::For(parent))
+        }
+    }
+
+    /// How much memory this context has, and how much of it is in use, along with that of its
+    /// descendants if `recurse` is `true`
+    pub fn stats(&self, recurse: bool) -> MemoryContextStats {
+        let mut counters = pg_sys::MemoryContextCounters::default();
+        unsafe {
+            pg_sys::pgx_MemoryContextCounters(self.value(), recurse, &mut counters);
+        }
+        MemoryContextStats {
+            blocks: counters.nblocks as usize,
+            free_chunks: counters.freechunks as usize,
+            total_bytes: counters.totalspace as usize,
+            free_bytes: counters.freespace as usize,
+        }
+    }
+
+    /// Call `callback` when this context is next reset, or deleted
+    ///
+    /// The callback runs once, just before the memory is released, so it's where to release what
+    /// Rust owns on behalf of what's allocated in the context, such as a file or a cache entry.
+    pub fn register_reset_callback<F: FnOnce() + 'static>(&mut self, callback: F) {
+        unsafe extern "C" fn call_on_reset(arg: void_mut_ptr) {
+            let callback = Box::from_raw(arg as *mut Box<dyn FnOnce()>);
+            guard::guard(std::panic::AssertUnwindSafe(move || callback()));
+        }
+
+        let callback: Box<Box<dyn FnOnce()>> = Box::new(Box::new(callback));
+        // SAFETY:  we know the result of `self.palloc_struct()` is a valid pointer
+        let mut memcxt_callback =
+            unsafe { PgBox::from_pg(self.palloc_struct::<pg_sys::MemoryContextCallback>()) };
+        memcxt_callback.func = Some(call_on_reset);
+        memcxt_callback.arg = Box::into_raw(callback) as void_mut_ptr;
+        unsafe {
+            pg_sys::MemoryContextRegisterResetCallback(self.value(), memcxt_callback.into_pg());
+        }
     }
 
     /// Run the specified function "within" the `MemoryContext` represented by this enum.
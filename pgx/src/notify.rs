@@ -0,0 +1,195 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Postgres' `LISTEN`/`NOTIFY`, for sending notifications from Rust, and for receiving them in
+//! background workers
+//!
+//! A notification is sent when the transaction sending it commits, to every session listening on
+//! its channel, so an extension can tell others something happened without their polling a
+//! table:
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[pg_extern]
+//! fn adopt(name: &str) {
+//!     // record the adoption, then
+//!     Notify::send("adoptions", name);
+//! }
+//! ```
+//!
+//! A background worker listens on a channel, and then receives its notifications as its latch
+//! wakes:
+//!
+//! ```rust,no_run
+//! use pgx::bgworkers::BackgroundWorker;
+//! use pgx::*;
+//! use std::time::Duration;
+//!
+//! BackgroundWorker::transaction(|| Notify::listen("adoptions"));
+//! while BackgroundWorker::wait_latch(Some(Duration::from_secs(10))) {
+//!     for notification in Notify::receive() {
+//!         log!("{} was adopted", notification.payload);
+//!     }
+//! }
+//! ```
+use crate::{pg_guard, pg_sys};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+
+/// Sending and listening for notifications, as `NOTIFY`, `LISTEN`, and `UNLISTEN` do
+pub struct Notify;
+
+/// A notification received by [`Notify::receive`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub channel: String,
+    pub payload: String,
+}
+
+impl Notify {
+    /// Notify the sessions listening on `channel`, with `payload`, once the transaction commits,
+    /// as `NOTIFY channel, 'payload'` does
+    ///
+    /// Like `NOTIFY`, a notification which is sent more than once in a transaction, with the
+    /// same channel and payload, is only delivered once, and none are delivered if the
+    /// transaction aborts.
+    ///
+    /// ## Panics
+    ///
+    /// If `channel` or `payload` contain a NUL byte.  Postgres raises an ERROR if `channel` is
+    /// empty or longer than an identifier, or if `payload` is 8000 bytes or longer.
+    pub fn send(channel: &str, payload: &str) {
+        let channel = CString::new(channel).expect("channel contained a null byte");
+        let payload = CString::new(payload).expect("payload contained a null byte");
+        unsafe { pg_sys::Async_Notify(channel.as_ptr(), payload.as_ptr()) }
+    }
+
+    /// Listen on `channel`, once the transaction commits, as `LISTEN channel` does
+    pub fn listen(channel: &str) {
+        let channel = CString::new(channel).expect("channel contained a null byte");
+        unsafe { pg_sys::Async_Listen(channel.as_ptr()) }
+    }
+
+    /// Stop listening on `channel`, once the transaction commits, as `UNLISTEN channel` does
+    pub fn unlisten(channel: &str) {
+        let channel = CString::new(channel).expect("channel contained a null byte");
+        unsafe { pg_sys::Async_Unlisten(channel.as_ptr()) }
+    }
+
+    /// Stop listening on every channel, once the transaction commits, as `UNLISTEN *` does
+    pub fn unlisten_all() {
+        unsafe { pg_sys::Async_UnlistenAll() }
+    }
+
+    /// The notifications which have arrived on the channels this background worker listens on,
+    /// since it last received them, oldest first
+    ///
+    /// This must be called outside of a transaction, such as after the worker's latch wakes, as
+    /// it reads the notifications in a transaction of its own.  It returns nothing in a
+    /// transaction, and nothing in a regular backend, whose notifications are sent to its client.
+    ///
+    /// Postgres wakes the worker's latch when a notification arrives, by `SIGUSR1`, so a worker
+    /// which attaches its own handler for `SIGUSR1` must use
+    /// [`BackgroundWorker::attach_signal_handlers`](crate::bgworkers::BackgroundWorker::attach_signal_handlers),
+    /// which passes the signal on to Postgres.
+    pub fn receive() -> Vec<Notification> {
+        unsafe {
+            if pg_sys::IsTransactionOrTransactionBlock()
+                || pg_sys::whereToSendOutput == pg_sys::CommandDest_DestRemote
+            {
+                return Vec::new();
+            }
+
+            // a background worker has no client to send its notifications to, so Postgres logs
+            // them at INFO instead, as `NOTIFY for "channel" payload "payload"`, which are
+            // caught here, and kept out of the log
+            let _capture = Capture::start();
+
+            // Postgres before 14.1 only signals the listeners of the notifications this worker
+            // sent, itself included, once asked to after the sending transaction
+            pg_sys::ProcessCompletedNotifies();
+            if std::ptr::read_volatile(&pg_sys::notifyInterruptPending) != 0 {
+                pg_sys::ProcessNotifyInterrupt();
+            }
+        }
+        RECEIVED.with(|received| received.borrow_mut().split_off(0))
+    }
+}
+
+const NOTIFY_PREFIX: &str = "NOTIFY for \"";
+const PAYLOAD_SEPARATOR: &str = "\" payload \"";
+
+thread_local! {
+    static RECEIVED: RefCell<Vec<Notification>> = RefCell::new(Vec::new());
+}
+
+static mut PREV_EMIT_LOG_HOOK: pg_sys::emit_log_hook_type = None;
+static mut PREV_LOG_MIN_MESSAGES: i32 = 0;
+
+/// Catches the notifications Postgres logs, while it's alive
+struct Capture;
+
+impl Capture {
+    unsafe fn start() -> Self {
+        PREV_EMIT_LOG_HOOK = pg_sys::emit_log_hook.replace(capture_notifications);
+        // Postgres doesn't report a message, or call the hook, at a level it wouldn't log
+        PREV_LOG_MIN_MESSAGES = pg_sys::log_min_messages;
+        if pg_sys::log_min_messages > pg_sys::INFO as i32 {
+            pg_sys::log_min_messages = pg_sys::INFO as i32;
+        }
+        Capture
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        unsafe {
+            pg_sys::emit_log_hook = PREV_EMIT_LOG_HOOK.take();
+            pg_sys::log_min_messages = PREV_LOG_MIN_MESSAGES;
+        }
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn capture_notifications(error_data: *mut pg_sys::ErrorData) {
+    let edata = &mut *error_data;
+    let notification = if edata.elevel == pg_sys::INFO as i32 && !edata.message.is_null() {
+        parse_notification(&CStr::from_ptr(edata.message).to_string_lossy())
+    } else {
+        None
+    };
+
+    match notification {
+        Some(notification) => {
+            RECEIVED.with(|received| received.borrow_mut().push(notification));
+            edata.output_to_server = false;
+            edata.output_to_client = false;
+        }
+        None => {
+            // other messages are logged as they would have been
+            if edata.elevel < PREV_LOG_MIN_MESSAGES && edata.elevel != pg_sys::LOG as i32 {
+                edata.output_to_server = false;
+            }
+            if let Some(prev) = PREV_EMIT_LOG_HOOK {
+                prev(error_data);
+            }
+        }
+    }
+}
+
+/// The channel and payload of `NOTIFY for "channel" payload "payload"`
+fn parse_notification(message: &str) -> Option<Notification> {
+    let rest = message.strip_prefix(NOTIFY_PREFIX)?.strip_suffix('"')?;
+    let (channel, payload) = rest.split_once(PAYLOAD_SEPARATOR)?;
+    Some(Notification {
+        channel: channel.to_string(),
+        payload: payload.to_string(),
+    })
+}
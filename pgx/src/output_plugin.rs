@@ -0,0 +1,376 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Support for writing [logical decoding output plugins](https://www.postgresql.org/docs/current/logicaldecoding-output-plugin.html)
+//!
+//! Implement [`OutputPlugin`], and fill in Postgres' callbacks with [`output_plugin_callbacks`]
+//! from the library's `_PG_output_plugin_init()`.  The plugin is then named by the library, such
+//! as in `pg_create_logical_replication_slot('my_slot', 'my_extension')`, and writes each
+//! committed transaction's changes, in whatever format it likes, to
+//! `pg_logical_slot_get_changes()` or to a replication connection.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! struct Changes {
+//!     with_xids: bool,
+//! }
+//!
+//! impl OutputPlugin for Changes {
+//!     fn startup(options: &[(String, Option<String>)], _is_init: bool) -> Self {
+//!         Changes {
+//!             with_xids: options.iter().any(|(name, _)| name == "include-xids"),
+//!         }
+//!     }
+//!
+//!     fn change(
+//!         &mut self,
+//!         out: &mut OutputWriter,
+//!         txn: &DecodedTransaction,
+//!         relation: &PgRelation,
+//!         change: Change,
+//!     ) {
+//!         let kind = match change {
+//!             Change::Insert { .. } => "INSERT",
+//!             Change::Update { .. } => "UPDATE",
+//!             Change::Delete { .. } => "DELETE",
+//!         };
+//!         match self.with_xids {
+//!             true => out.write(format!("{} {} {}", txn.xid(), kind, relation.name())),
+//!             false => out.write(format!("{} {}", kind, relation.name())),
+//!         }
+//!     }
+//! }
+//!
+//! #[allow(non_snake_case)]
+//! #[pg_guard]
+//! pub unsafe extern "C" fn _PG_output_plugin_init(callbacks: *mut pg_sys::OutputPluginCallbacks) {
+//!     output_plugin_callbacks::<Changes>(callbacks);
+//! }
+//! ```
+use crate::{
+    guard, heap_getattr_raw, pg_sys, varatt_is_1b_e, vartag_external, PgHeapTuple, PgList,
+    PgMemoryContexts, PgRelation, PgTupleDesc, TimestampWithTimeZone,
+};
+use pg_sys::XLogRecPtr;
+use std::ffi::CStr;
+
+/// A logical decoding output plugin, where each value of `Self` is one decoding session, of a
+/// replication slot
+///
+/// Transactions are decoded once they commit, in the order they committed, with the changes of
+/// each in the order they were made.  Each callback's allocations are freed when it returns.
+pub trait OutputPlugin: Sized {
+    /// Does the plugin write binary output, rather than text in the database's encoding?
+    ///
+    /// Binary output can only be read with `pg_logical_slot_get_binary_changes()`, or over a
+    /// replication connection.
+    const BINARY_OUTPUT: bool = false;
+
+    /// Start decoding, with the options passed to `pg_logical_slot_get_changes()`, or to
+    /// `START_REPLICATION`, as names and values, or when `is_init`, because the slot is being
+    /// created, with none
+    ///
+    /// An unrecognized option should raise an ERROR.
+    fn startup(options: &[(String, Option<String>)], is_init: bool) -> Self;
+
+    /// A transaction `txn` begins, before its changes
+    fn begin(&mut self, _out: &mut OutputWriter, _txn: &DecodedTransaction) {}
+
+    /// A row of `relation` was inserted, updated, or deleted by `txn`
+    fn change(
+        &mut self,
+        out: &mut OutputWriter,
+        txn: &DecodedTransaction,
+        relation: &PgRelation,
+        change: Change,
+    );
+
+    /// Transaction `txn` committed, at `commit_lsn`, after its changes
+    fn commit(
+        &mut self,
+        _out: &mut OutputWriter,
+        _txn: &DecodedTransaction,
+        _commit_lsn: XLogRecPtr,
+    ) {
+    }
+
+    /// Should the changes which were replicated from `origin_id`, rather than made locally, be
+    /// skipped?
+    fn filter_by_origin(&mut self, _origin_id: pg_sys::RepOriginId) -> bool {
+        false
+    }
+
+    /// Decoding is over, and the plugin is about to be dropped
+    fn shutdown(&mut self) {}
+}
+
+/// A change decoded from the WAL, with its tuples, as they're described by the relation
+///
+/// The old tuple is only logged for a table with `REPLICA IDENTITY FULL`, and otherwise holds only
+/// the columns of the table's replica identity, only when they changed, or `None` if it has
+/// none.  The new tuple of an update has no value for a TOASTed column which didn't change; see
+/// [`is_unchanged_toast`].
+pub enum Change<'a> {
+    Insert {
+        new: PgHeapTuple<'a>,
+    },
+    Update {
+        old: Option<PgHeapTuple<'a>>,
+        new: PgHeapTuple<'a>,
+    },
+    Delete {
+        old: Option<PgHeapTuple<'a>>,
+    },
+}
+
+/// Is the value of attribute `attno` of `tuple` a TOASTed value which an update didn't change,
+/// and which isn't in the WAL, so can't be read?
+pub fn is_unchanged_toast(tuple: &PgHeapTuple, attno: usize) -> bool {
+    match tuple.tupdesc().get(attno - 1) {
+        Some(attribute) if attribute.attlen == -1 => {}
+        _ => return false,
+    }
+    unsafe {
+        match heap_getattr_raw(tuple.as_ptr(), attno, tuple.tupdesc().as_ptr()) {
+            // `VARATT_IS_EXTERNAL_ONDISK()`
+            Some(datum) => {
+                let varlena = datum as *const pg_sys::varlena;
+                varatt_is_1b_e(varlena)
+                    && vartag_external(varlena) == pg_sys::vartag_external_VARTAG_ONDISK as u8
+            }
+            None => false,
+        }
+    }
+}
+
+/// A transaction being decoded
+pub struct DecodedTransaction {
+    txn: *mut pg_sys::ReorderBufferTXN,
+}
+
+impl DecodedTransaction {
+    pub fn xid(&self) -> pg_sys::TransactionId {
+        unsafe { (*self.txn).xid }
+    }
+
+    /// The LSN of the transaction's commit record
+    pub fn final_lsn(&self) -> XLogRecPtr {
+        unsafe { (*self.txn).final_lsn }
+    }
+
+    pub fn commit_time(&self) -> TimestampWithTimeZone {
+        unsafe { (*self.txn).commit_time }.into()
+    }
+
+    /// The replication origin the transaction came from, which is `InvalidRepOriginId` (0) for
+    /// a transaction made locally
+    pub fn origin_id(&self) -> pg_sys::RepOriginId {
+        unsafe { (*self.txn).origin_id }
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::ReorderBufferTXN {
+        self.txn
+    }
+}
+
+/// Where an [`OutputPlugin`] writes its output, which is a series of messages
+pub struct OutputWriter {
+    ctx: *mut pg_sys::LogicalDecodingContext,
+}
+
+impl OutputWriter {
+    /// Write `data` as one message, which is a row of `pg_logical_slot_get_changes()`, or a
+    /// `CopyData` message of a replication connection
+    ///
+    /// Text output must be in the database's encoding.
+    pub fn write<T: AsRef<[u8]>>(&mut self, data: T) {
+        let data = data.as_ref();
+        unsafe {
+            pg_sys::OutputPluginPrepareWrite(self.ctx, true);
+            pg_sys::appendBinaryStringInfo(
+                (*self.ctx).out,
+                data.as_ptr() as *const std::os::raw::c_char,
+                data.len() as i32,
+            );
+            pg_sys::OutputPluginWrite(self.ctx, true);
+        }
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::LogicalDecodingContext {
+        self.ctx
+    }
+}
+
+/// Fill in `callbacks` with those of `T`, from the library's `_PG_output_plugin_init()`
+///
+/// ## Safety
+///
+/// `callbacks` must be the `OutputPluginCallbacks` Postgres passed to `_PG_output_plugin_init()`
+pub unsafe fn output_plugin_callbacks<T: OutputPlugin>(
+    callbacks: *mut pg_sys::OutputPluginCallbacks,
+) {
+    let callbacks = &mut *callbacks;
+    callbacks.startup_cb = Some(startup::<T>);
+    callbacks.begin_cb = Some(begin::<T>);
+    callbacks.change_cb = Some(change::<T>);
+    callbacks.commit_cb = Some(commit::<T>);
+    callbacks.filter_by_origin_cb = Some(filter_by_origin::<T>);
+    callbacks.shutdown_cb = Some(shutdown::<T>);
+}
+
+/// A decoding session's plugin, and the memory its callbacks allocate in, which is reset after
+/// each
+struct PluginState<T> {
+    plugin: T,
+    context: PgMemoryContexts,
+}
+
+unsafe fn plugin_state<'a, T>(ctx: *mut pg_sys::LogicalDecodingContext) -> &'a mut PluginState<T> {
+    &mut *((*ctx).output_plugin_private as *mut PluginState<T>)
+}
+
+/// Run `f` with the plugin, in its memory context, which is reset once `f` returns
+unsafe fn with_plugin<T: OutputPlugin, R, F: FnOnce(&mut T, &mut OutputWriter) -> R>(
+    ctx: *mut pg_sys::LogicalDecodingContext,
+    f: F,
+) -> R {
+    let state = plugin_state::<T>(ctx);
+    let mut out = OutputWriter { ctx };
+    let result = {
+        let _switched = state.context.switch();
+        f(&mut state.plugin, &mut out)
+    };
+    state.context.reset();
+    result
+}
+
+// `#[pg_guard]` can't wrap generic functions, so the callbacks guard themselves
+
+unsafe extern "C" fn startup<T: OutputPlugin>(
+    ctx: *mut pg_sys::LogicalDecodingContext,
+    options: *mut pg_sys::OutputPluginOptions,
+    is_init: bool,
+) {
+    guard::guard(|| {
+        let parsed = PgList::<pg_sys::DefElem>::from_pg((*ctx).output_plugin_options)
+            .iter_ptr()
+            .map(|elem| {
+                let name = CStr::from_ptr((*elem).defname)
+                    .to_string_lossy()
+                    .into_owned();
+                let value = if (*elem).arg.is_null() {
+                    None
+                } else {
+                    Some(
+                        CStr::from_ptr(pg_sys::defGetString(elem))
+                            .to_string_lossy()
+                            .into_owned(),
+                    )
+                };
+                (name, value)
+            })
+            .collect::<Vec<_>>();
+
+        (*options).output_type = if T::BINARY_OUTPUT {
+            pg_sys::OutputPluginOutputType_OUTPUT_PLUGIN_BINARY_OUTPUT
+        } else {
+            pg_sys::OutputPluginOutputType_OUTPUT_PLUGIN_TEXTUAL_OUTPUT
+        };
+
+        let context = PgMemoryContexts::For((*ctx).context).new_child("pgx output plugin");
+        let state = Box::new(PluginState {
+            plugin: T::startup(&parsed, is_init),
+            context,
+        });
+        (*ctx).output_plugin_private = Box::into_raw(state) as *mut std::os::raw::c_void;
+    })
+}
+
+unsafe extern "C" fn begin<T: OutputPlugin>(
+    ctx: *mut pg_sys::LogicalDecodingContext,
+    txn: *mut pg_sys::ReorderBufferTXN,
+) {
+    guard::guard(|| {
+        with_plugin::<T, _, _>(ctx, |plugin, out| {
+            plugin.begin(out, &DecodedTransaction { txn })
+        })
+    })
+}
+
+unsafe extern "C" fn change<T: OutputPlugin>(
+    ctx: *mut pg_sys::LogicalDecodingContext,
+    txn: *mut pg_sys::ReorderBufferTXN,
+    relation: pg_sys::Relation,
+    change: *mut pg_sys::ReorderBufferChange,
+) {
+    guard::guard(|| {
+        let relation = PgRelation::from_pg(relation);
+        let tp = (*change).data.tp;
+        let tuple = |buf: *mut pg_sys::ReorderBufferTupleBuf| {
+            if buf.is_null() {
+                None
+            } else {
+                Some(PgHeapTuple::from_heap_tuple(
+                    PgTupleDesc::from_relation(&relation),
+                    &mut (*buf).tuple,
+                ))
+            }
+        };
+
+        let decoded = match (*change).action {
+            pg_sys::ReorderBufferChangeType_REORDER_BUFFER_CHANGE_INSERT => Change::Insert {
+                new: tuple(tp.newtuple).expect("an insert has no new tuple"),
+            },
+            pg_sys::ReorderBufferChangeType_REORDER_BUFFER_CHANGE_UPDATE => Change::Update {
+                old: tuple(tp.oldtuple),
+                new: tuple(tp.newtuple).expect("an update has no new tuple"),
+            },
+            pg_sys::ReorderBufferChangeType_REORDER_BUFFER_CHANGE_DELETE => Change::Delete {
+                old: tuple(tp.oldtuple),
+            },
+            // Postgres only passes the changes above to the change callback
+            other => panic!("unexpected logical decoding change: {}", other),
+        };
+
+        with_plugin::<T, _, _>(ctx, |plugin, out| {
+            plugin.change(out, &DecodedTransaction { txn }, &relation, decoded)
+        })
+    })
+}
+
+unsafe extern "C" fn commit<T: OutputPlugin>(
+    ctx: *mut pg_sys::LogicalDecodingContext,
+    txn: *mut pg_sys::ReorderBufferTXN,
+    commit_lsn: pg_sys::XLogRecPtr,
+) {
+    guard::guard(|| {
+        with_plugin::<T, _, _>(ctx, |plugin, out| {
+            plugin.commit(out, &DecodedTransaction { txn }, commit_lsn)
+        })
+    })
+}
+
+unsafe extern "C" fn filter_by_origin<T: OutputPlugin>(
+    ctx: *mut pg_sys::LogicalDecodingContext,
+    origin_id: pg_sys::RepOriginId,
+) -> bool {
+    guard::guard(|| plugin_state::<T>(ctx).plugin.filter_by_origin(origin_id))
+}
+
+unsafe extern "C" fn shutdown<T: OutputPlugin>(ctx: *mut pg_sys::LogicalDecodingContext) {
+    guard::guard(|| {
+        if (*ctx).output_plugin_private.is_null() {
+            return;
+        }
+        let mut state = Box::from_raw((*ctx).output_plugin_private as *mut PluginState<T>);
+        (*ctx).output_plugin_private = std::ptr::null_mut();
+        state.plugin.shutdown();
+    })
+}
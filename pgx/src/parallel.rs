@@ -0,0 +1,255 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Postgres' parallel workers, for spreading CPU-bound work in a function across several
+//! processes
+//!
+//! The leader creates a [`ParallelContext`] with a job description, which is serialized into
+//! the dynamic shared memory segment Postgres creates for the workers, and launches them.  Each
+//! worker runs the named `extern "C"` function with a [`ParallelWorker`], which gives it the job
+//! and a queue for sending its results back to the leader.
+//!
+//! Workers share the leader's transaction and snapshot, and errors they raise are raised again
+//! in the leader.  As in any parallel query, neither the leader nor its workers may write to the
+//! database until the context is dropped.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[pg_extern]
+//! fn parallel_sum(n: i64) -> i64 {
+//!     let mut pcxt = ParallelContext::new("example", "parallel_sum_main", 4, &n);
+//!     let launched = pcxt.launch();
+//!     let mut sum: i64 = pcxt.collect::<i64>().unwrap().into_iter().sum();
+//!
+//!     // do the share of any workers that couldn't be started
+//!     for number in launched..4 {
+//!         sum += (1..=n).skip(number).step_by(4).sum::<i64>();
+//!     }
+//!     sum
+//! }
+//!
+//! #[pg_guard]
+//! pub extern "C" fn parallel_sum_main(seg: *mut pg_sys::dsm_segment, toc: *mut pg_sys::shm_toc) {
+//!     let mut worker = unsafe { ParallelWorker::new(seg, toc) };
+//!     let n: i64 = worker.job().unwrap();
+//!     let sum = (1..=n)
+//!         .skip(worker.number())
+//!         .step_by(worker.nworkers())
+//!         .sum::<i64>();
+//!     worker.send(&sum).unwrap();
+//! }
+//! ```
+use crate::pg_sys;
+use crate::shm_mq::{ShmMqError, ShmMqReceiver, ShmMqSender};
+use serde::{de::DeserializeOwned, Serialize};
+use std::ffi::CString;
+
+/// The size of the queue each worker sends its results on
+const QUEUE_SIZE: usize = 65536;
+
+// keys for our entries in the segment's table of contents.  Postgres' own are all near
+// `u64::MAX`, so these can't collide with them
+const PGX_KEY_HEADER: u64 = 1;
+const PGX_KEY_QUEUES: u64 = 2;
+
+/// What the leader tells every worker, followed in the segment by the serialized job
+#[repr(C)]
+struct ParallelHeader {
+    nworkers: usize,
+    job_len: usize,
+}
+
+/// A set of parallel workers started by this backend, and the shared memory they use
+///
+/// Creating one enters parallel mode, and dropping it waits for any workers still running,
+/// frees their shared memory, and leaves parallel mode again.
+pub struct ParallelContext {
+    pcxt: *mut pg_sys::ParallelContext,
+    // must be detached before the segment they're in is
+    receivers: Vec<ShmMqReceiver>,
+}
+
+impl ParallelContext {
+    /// Prepare to run `nworkers` workers, each of which runs `function` from `library` with
+    /// `job`
+    ///
+    /// `function` must be `extern "C"`, guarded with `#[pg_guard]`, and take
+    /// a `*mut pg_sys::dsm_segment` and a `*mut pg_sys::shm_toc`, which it passes to
+    /// [`ParallelWorker::new`].
+    ///
+    /// Panics if `job` can't be serialized.
+    pub fn new<T: Serialize>(library: &str, function: &str, nworkers: usize, job: &T) -> Self {
+        let job = serde_cbor::to_vec(job).expect("failed to serialize parallel job");
+        let library = CString::new(library).expect("library name contained a null byte");
+        let function = CString::new(function).expect("function name contained a null byte");
+
+        unsafe {
+            pg_sys::EnterParallelMode();
+            let pcxt = create_parallel_context(&library, &function, nworkers as i32);
+
+            let header_size = std::mem::size_of::<ParallelHeader>() + job.len();
+            estimate_chunk(&mut (*pcxt).estimator, header_size);
+            estimate_chunk(&mut (*pcxt).estimator, nworkers * QUEUE_SIZE);
+            (*pcxt).estimator.number_of_keys += 2;
+            pg_sys::InitializeParallelDSM(pcxt);
+
+            let header = pg_sys::shm_toc_allocate((*pcxt).toc, header_size) as *mut ParallelHeader;
+            header.write(ParallelHeader {
+                nworkers,
+                job_len: job.len(),
+            });
+            std::ptr::copy_nonoverlapping(job.as_ptr(), header.add(1) as *mut u8, job.len());
+            pg_sys::shm_toc_insert((*pcxt).toc, PGX_KEY_HEADER, header as _);
+
+            let queues = pg_sys::shm_toc_allocate((*pcxt).toc, nworkers * QUEUE_SIZE) as *mut u8;
+            pg_sys::shm_toc_insert((*pcxt).toc, PGX_KEY_QUEUES, queues as _);
+            let receivers = (0..nworkers)
+                .map(|i| {
+                    ShmMqReceiver::create(queues.add(i * QUEUE_SIZE) as _, QUEUE_SIZE, (*pcxt).seg)
+                })
+                .collect();
+
+            ParallelContext { pcxt, receivers }
+        }
+    }
+
+    /// Start the workers, returning how many were actually started
+    ///
+    /// This can be fewer than were asked for, even none, if there aren't enough free worker
+    /// processes, so the leader must be prepared to do the work missing workers would have.
+    /// Those that were started are always numbered from 0.
+    pub fn launch(&mut self) -> usize {
+        unsafe {
+            pg_sys::LaunchParallelWorkers(self.pcxt);
+
+            let launched = self.nworkers_launched();
+            self.receivers.truncate(launched);
+            for (i, receiver) in self.receivers.iter_mut().enumerate() {
+                let handle = (*(*self.pcxt).worker.add(i)).bgwhandle;
+                pg_sys::shm_mq_set_handle(receiver.as_ptr(), handle);
+            }
+            launched
+        }
+    }
+
+    /// How many workers [`ParallelContext::launch`] started
+    pub fn nworkers_launched(&self) -> usize {
+        unsafe { (*self.pcxt).nworkers_launched as usize }
+    }
+
+    /// Wait for every launched worker to finish, returning all the results they sent
+    ///
+    /// Results from the same worker are in the order it sent them, and those from different
+    /// workers are in the order of the workers' [`ParallelWorker::number`].
+    pub fn collect<R: DeserializeOwned>(&mut self) -> Result<Vec<R>, ShmMqError> {
+        let mut results = Vec::new();
+        for receiver in self.receivers.iter_mut() {
+            loop {
+                match receiver.recv() {
+                    Ok(result) => results.push(result),
+                    Err(ShmMqError::Detached) => break,
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        self.wait_for_finish();
+        Ok(results)
+    }
+
+    /// Wait for every launched worker to exit, raising any error one of them raised
+    pub fn wait_for_finish(&mut self) {
+        unsafe { pg_sys::WaitForParallelWorkersToFinish(self.pcxt) }
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::ParallelContext {
+        self.pcxt
+    }
+}
+
+impl Drop for ParallelContext {
+    fn drop(&mut self) {
+        self.receivers.clear();
+        unsafe {
+            pg_sys::DestroyParallelContext(self.pcxt);
+            pg_sys::ExitParallelMode();
+        }
+    }
+}
+
+/// A parallel worker's view of the job its [`ParallelContext`] gave it
+pub struct ParallelWorker {
+    header: *const ParallelHeader,
+    sender: ShmMqSender,
+}
+
+impl ParallelWorker {
+    /// Attach to the job in the dynamic shared memory `seg`, whose table of contents is `toc`
+    ///
+    /// ## Safety
+    ///
+    /// `seg` and `toc` must be the arguments Postgres called the worker's function with.
+    pub unsafe fn new(seg: *mut pg_sys::dsm_segment, toc: *mut pg_sys::shm_toc) -> Self {
+        let header = pg_sys::shm_toc_lookup(toc, PGX_KEY_HEADER, false) as *const ParallelHeader;
+        let queues = pg_sys::shm_toc_lookup(toc, PGX_KEY_QUEUES, false) as *mut u8;
+        let mq = queues.add(pg_sys::ParallelWorkerNumber as usize * QUEUE_SIZE);
+
+        ParallelWorker {
+            header,
+            sender: ShmMqSender::attach(mq as *mut pg_sys::shm_mq, seg),
+        }
+    }
+
+    /// This worker's number, from 0 up to, but not including, [`ParallelWorker::nworkers`]
+    pub fn number(&self) -> usize {
+        unsafe { pg_sys::ParallelWorkerNumber as usize }
+    }
+
+    /// How many workers the leader asked for, although fewer may have been started
+    pub fn nworkers(&self) -> usize {
+        unsafe { (*self.header).nworkers }
+    }
+
+    /// Deserialize the job given to the [`ParallelContext`]
+    pub fn job<T: DeserializeOwned>(&self) -> Result<T, ShmMqError> {
+        let job = unsafe {
+            std::slice::from_raw_parts(self.header.add(1) as *const u8, (*self.header).job_len)
+        };
+        serde_cbor::from_slice(job).map_err(|e| ShmMqError::Serialization(e.to_string()))
+    }
+
+    /// Send a result to the leader, waiting for room in the queue as necessary
+    pub fn send<R: Serialize>(&mut self, result: &R) -> Result<(), ShmMqError> {
+        self.sender.send(result)
+    }
+}
+
+/// `shm_toc_estimate_chunk()` is a macro
+fn estimate_chunk(estimator: &mut pg_sys::shm_toc_estimator, size: usize) {
+    let align = pg_sys::ALIGNOF_BUFFER as usize;
+    estimator.space_for_chunks += (size + align - 1) & !(align - 1);
+}
+
+#[cfg(any(feature = "pg10", feature = "pg12", feature = "pg13", feature = "pg14"))]
+unsafe fn create_parallel_context(
+    library: &CString,
+    function: &CString,
+    nworkers: i32,
+) -> *mut pg_sys::ParallelContext {
+    pg_sys::CreateParallelContext(library.as_ptr(), function.as_ptr(), nworkers)
+}
+
+#[cfg(feature = "pg11")]
+unsafe fn create_parallel_context(
+    library: &CString,
+    function: &CString,
+    nworkers: i32,
+) -> *mut pg_sys::ParallelContext {
+    pg_sys::CreateParallelContext(library.as_ptr(), function.as_ptr(), nworkers, false)
+}
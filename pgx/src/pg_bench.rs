@@ -0,0 +1,220 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Micro-benchmarks which run inside Postgres, by `#[pg_bench]`
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[pg_bench(samples = 50)]
+//! fn bench_text_into_datum(b: &mut Bencher) {
+//!     b.iter(|| "brandy".into_datum());
+//! }
+//! ```
+//!
+//! The benchmark runs in a backend, where its routine is warmed up before it's measured, and its
+//! [`BenchResult`] is returned to the test process, which reports it, along with how it changed
+//! since the benchmark last ran.
+use crate::{pg_sys, table_column, IntoDatum, TableColumn, TableRow};
+use std::time::{Duration, Instant};
+
+/// How long a benchmark is warmed up and measured for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchConfig {
+    /// How long the routine runs before it's measured, to warm up caches
+    pub warm_up: Duration,
+    /// About how long the routine runs while it's measured, across all its samples
+    pub measurement: Duration,
+    /// The number of samples taken, each of which times the same number of iterations
+    pub samples: usize,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        BenchConfig {
+            warm_up: Duration::from_secs(3),
+            measurement: Duration::from_secs(5),
+            samples: 100,
+        }
+    }
+}
+
+/// Times the routine of a `#[pg_bench]` benchmark, with [`Bencher::iter`]
+pub struct Bencher {
+    config: BenchConfig,
+    /// The time of one iteration, in nanoseconds, of each sample
+    samples: Vec<f64>,
+    iterations: u64,
+}
+
+impl Bencher {
+    /// Time `routine`, first running it for the warm-up time, then measuring it for the
+    /// measurement time
+    ///
+    /// Each sample runs `routine` as many times as fit in its share of the measurement time, as
+    /// estimated during the warm-up.  What `routine` returns is passed to [`black_box`], so the
+    /// compiler can't optimize computing it away.
+    pub fn iter<O, R: FnMut() -> O>(&mut self, mut routine: R) {
+        // warm up, doubling the iterations of each round, to estimate the time of one
+        let started = Instant::now();
+        let mut round = 1u64;
+        let mut warm_up_iterations = 0u64;
+        loop {
+            for _ in 0..round {
+                black_box(routine());
+            }
+            warm_up_iterations += round;
+            check_for_interrupts!();
+            if started.elapsed() >= self.config.warm_up {
+                break;
+            }
+            round = round.saturating_mul(2);
+        }
+        let estimate = started.elapsed().as_nanos() as f64 / warm_up_iterations as f64;
+
+        let samples = self.config.samples.max(2);
+        let sample_time = self.config.measurement.as_nanos() as f64 / samples as f64;
+        let iterations = ((sample_time / estimate.max(1.0)).ceil() as u64).max(1);
+
+        self.samples.clear();
+        for _ in 0..samples {
+            let started = Instant::now();
+            for _ in 0..iterations {
+                black_box(routine());
+            }
+            self.samples
+                .push(started.elapsed().as_nanos() as f64 / iterations as f64);
+            check_for_interrupts!();
+        }
+        self.iterations = iterations * samples as u64;
+    }
+}
+
+/// The statistics of a benchmark's samples, where each time is of one iteration, in nanoseconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    pub name: String,
+    pub samples: i64,
+    /// The number of iterations measured, across all the samples
+    pub iterations: i64,
+    pub mean_ns: f64,
+    pub stddev_ns: f64,
+    pub median_ns: f64,
+    pub min_ns: f64,
+    pub max_ns: f64,
+    /// The number of samples outside Tukey's fences, 1.5 interquartile ranges beyond the
+    /// quartiles, which are usually the times the backend was interrupted
+    pub outliers: i64,
+}
+
+impl BenchResult {
+    /// The statistics of `samples`, the times of one iteration of each sample, from measuring
+    /// `iterations` in all
+    pub fn from_samples(name: &str, samples: &[f64], iterations: u64) -> Self {
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).expect("a sample isn't a number"));
+
+        let count = sorted.len() as f64;
+        let mean = sorted.iter().sum::<f64>() / count;
+        let variance = if sorted.len() > 1 {
+            sorted.iter().map(|time| (time - mean).powi(2)).sum::<f64>() / (count - 1.0)
+        } else {
+            0.0
+        };
+
+        let (q1, q3) = (percentile(&sorted, 25.0), percentile(&sorted, 75.0));
+        let iqr = q3 - q1;
+        let (low, high) = (q1 - 1.5 * iqr, q3 + 1.5 * iqr);
+        let outliers = sorted
+            .iter()
+            .filter(|time| **time < low || **time > high)
+            .count();
+
+        BenchResult {
+            name: name.to_string(),
+            samples: sorted.len() as i64,
+            iterations: iterations as i64,
+            mean_ns: mean,
+            stddev_ns: variance.sqrt(),
+            median_ns: percentile(&sorted, 50.0),
+            min_ns: sorted.first().copied().unwrap_or_default(),
+            max_ns: sorted.last().copied().unwrap_or_default(),
+            outliers: outliers as i64,
+        }
+    }
+}
+
+impl TableRow for BenchResult {
+    fn table_columns() -> Vec<TableColumn> {
+        vec![
+            table_column::<String>("String", "name"),
+            table_column::<i64>("i64", "samples"),
+            table_column::<i64>("i64", "iterations"),
+            table_column::<f64>("f64", "mean_ns"),
+            table_column::<f64>("f64", "stddev_ns"),
+            table_column::<f64>("f64", "median_ns"),
+            table_column::<f64>("f64", "min_ns"),
+            table_column::<f64>("f64", "max_ns"),
+            table_column::<i64>("i64", "outliers"),
+        ]
+    }
+
+    fn into_datums(self) -> Vec<Option<pg_sys::Datum>> {
+        vec![
+            self.name.into_datum(),
+            self.samples.into_datum(),
+            self.iterations.into_datum(),
+            self.mean_ns.into_datum(),
+            self.stddev_ns.into_datum(),
+            self.median_ns.into_datum(),
+            self.min_ns.into_datum(),
+            self.max_ns.into_datum(),
+            self.outliers.into_datum(),
+        ]
+    }
+}
+
+/// Run the benchmark named `name`, which times its routine with the [`Bencher`] it's passed, as
+/// `config` says
+pub fn run_bench<F: FnOnce(&mut Bencher)>(
+    name: &str,
+    config: BenchConfig,
+    bench: F,
+) -> BenchResult {
+    let mut bencher = Bencher {
+        config,
+        samples: Vec::new(),
+        iterations: 0,
+    };
+    bench(&mut bencher);
+
+    if bencher.samples.is_empty() {
+        error!("{} didn't time anything with `Bencher::iter()`", name);
+    }
+    BenchResult::from_samples(name, &bencher.samples, bencher.iterations)
+}
+
+/// Keep the compiler from optimizing away `value`, or computing it
+pub fn black_box<T>(value: T) -> T {
+    unsafe {
+        let copy = std::ptr::read_volatile(&value);
+        std::mem::forget(value);
+        copy
+    }
+}
+
+/// The `p`th percentile of `sorted`, interpolating between the samples either side of it
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[below] + (sorted[above] - sorted[below]) * (rank - below as f64)
+}
@@ -0,0 +1,86 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Property-based testing inside Postgres with [proptest](https://docs.rs/proptest), by
+//! `#[pg_proptest]`, which needs the `proptest` feature
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[pg_proptest(cases = 64)]
+//! fn prop_text_roundtrip(value: String, #[strategy(0..100i32)] repeat: i32) {
+//!     let repeated = Spi::get_one_with_args::<String>(
+//!         "SELECT repeat($1, $2)",
+//!         vec![
+//!             (PgBuiltInOids::TEXTOID.oid(), value.clone().into_datum()),
+//!             (PgBuiltInOids::INT4OID.oid(), repeat.into_datum()),
+//!         ],
+//!     );
+//!     assert_eq!(repeated.unwrap_or_default(), value.repeat(repeat as usize));
+//! }
+//! ```
+use crate::spi::trap_sql_error;
+use proptest::strategy::Strategy;
+use proptest::test_runner::{Config, TestCaseError, TestError, TestRunner};
+use std::any::Any;
+use std::fmt::Debug;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+
+/// Run the property test named `name`, calling `test` with each of the inputs `strategy`
+/// generates, until it panics, or raises an ERROR, or `config.cases` of them pass
+///
+/// Each case runs in its own subtransaction, so a case which raises an ERROR is rolled back, and
+/// doesn't abort the cases after it, as proptest shrinks the input to the smallest which still
+/// fails.  The test then raises an ERROR with that input.
+pub fn run_proptest<S, F>(name: &str, config: Config, strategy: S, test: F)
+where
+    S: Strategy,
+    S::Value: Debug + UnwindSafe,
+    F: Fn(S::Value) + RefUnwindSafe,
+{
+    // the backend's working directory is the data directory, where failures mustn't be written
+    let config = Config {
+        failure_persistence: None,
+        ..config
+    };
+    let mut runner = TestRunner::new(config);
+    let result = runner.run(&strategy, |value| {
+        run_case(|| test(value)).map_err(TestCaseError::fail)
+    });
+
+    match result {
+        Ok(()) => (),
+        Err(TestError::Fail(reason, value)) => {
+            error!(
+                "{} failed: {}\nminimal failing input: {:?}",
+                name, reason, value
+            )
+        }
+        Err(TestError::Abort(reason)) => error!("{} aborted: {}", name, reason),
+    }
+}
+
+/// Run one case of a property test, returning why it failed, if it panicked or raised an ERROR
+fn run_case<F: FnOnce() + UnwindSafe>(case: F) -> Result<(), String> {
+    match std::panic::catch_unwind(|| trap_sql_error(case)) {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(error)) => Err(error.to_string()),
+        Err(panic) => Err(panic_message(panic)),
+    }
+}
+
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => match panic.downcast::<&'static str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "panicked".to_string(),
+        },
+    }
+}
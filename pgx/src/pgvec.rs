@@ -0,0 +1,334 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Growable collections, `PgVec<T>` and `PgString`, whose memory is `palloc()`'d in a Postgres
+//! `MemoryContext`, rather than by Rust's global allocator
+//!
+//! Like a [`PgBox`](crate::PgBox), a collection `pfree()`s its memory when it's dropped, but its
+//! context frees the memory when it's reset or deleted, such as at the end of the statement, so
+//! the memory of a collection that's leaked, with `::into_pg()`, or that's lost to an `ERROR`,
+//! isn't lost for the life of the backend.
+//!
+//! A collection mustn't outlive its context, or using or dropping it reads or `pfree()`s memory
+//! that's already been freed, which the compiler can't check, so creating one is `unsafe`.
+use crate::{pg_sys, PgMemoryContexts};
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::{align_of, size_of};
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+/// A `Vec<T>` whose elements are `palloc()`'d in a Postgres `MemoryContext`
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// let mut batch = PgMemoryContexts::new("batch");
+/// // SAFETY: `ids` is dropped before `batch` is reset
+/// let mut ids = unsafe { PgVec::new_in(&batch) };
+/// ids.extend(1..=100i64);
+/// assert_eq!(ids.iter().sum::<i64>(), 5050);
+/// drop(ids);
+/// batch.reset();
+/// ```
+pub struct PgVec<T> {
+    ptr: NonNull<T>,
+    len: usize,
+    capacity: usize,
+    context: pg_sys::MemoryContext,
+    _marker: PhantomData<T>,
+}
+
+impl<T> PgVec<T> {
+    /// An empty vector, allocated in the `CurrentMemoryContext` once it grows
+    ///
+    /// ## Safety
+    ///
+    /// As with [`PgVec::new_in`], the vector mustn't be used, nor dropped, once the
+    /// `CurrentMemoryContext` is reset or deleted
+    pub unsafe fn new() -> Self {
+        PgVec::new_in(&PgMemoryContexts::CurrentMemoryContext)
+    }
+
+    /// An empty vector, allocated in `context` once it grows
+    ///
+    /// ## Panics
+    ///
+    /// If `T` needs an alignment beyond the `MAXALIGN` of Postgres' allocations
+    ///
+    /// ## Safety
+    ///
+    /// The vector mustn't be used, nor dropped, once `context` is reset or deleted, though it
+    /// may be handed over to Postgres with [`PgVec::into_pg`] before then
+    pub unsafe fn new_in(context: &PgMemoryContexts) -> Self {
+        assert!(
+            align_of::<T>() <= pg_sys::MAXIMUM_ALIGNOF as usize,
+            "PgVec elements can't be aligned beyond MAXALIGN"
+        );
+        PgVec {
+            ptr: NonNull::dangling(),
+            len: 0,
+            capacity: if size_of::<T>() == 0 { usize::MAX } else { 0 },
+            context: context.value(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// An empty vector, with room for `capacity` elements, allocated in `context`
+    ///
+    /// ## Safety
+    ///
+    /// As with [`PgVec::new_in`]
+    pub unsafe fn with_capacity_in(capacity: usize, context: &PgMemoryContexts) -> Self {
+        let mut vec = PgVec::new_in(context);
+        vec.reserve(capacity);
+        vec
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The context the elements are allocated in
+    pub fn context(&self) -> PgMemoryContexts {
+        PgMemoryContexts::For(self.context)
+    }
+
+    /// Make room for at least `additional` more elements
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self
+            .len
+            .checked_add(additional)
+            .expect("PgVec capacity overflow");
+        if needed <= self.capacity {
+            return;
+        }
+
+        let capacity = needed.max(self.capacity * 2).max(4);
+        let bytes = capacity
+            .checked_mul(size_of::<T>())
+            .expect("PgVec capacity overflow");
+        let ptr = unsafe {
+            if self.capacity == 0 {
+                pg_sys::MemoryContextAllocHuge(self.context, bytes)
+            } else {
+                // repalloc() keeps the memory in the context it was allocated in
+                pg_sys::repalloc_huge(self.ptr.as_ptr() as *mut std::os::raw::c_void, bytes)
+            }
+        };
+        self.ptr = NonNull::new(ptr as *mut T).expect("palloc() returned NULL");
+        self.capacity = capacity;
+    }
+
+    pub fn push(&mut self, value: T) {
+        if self.len == self.capacity {
+            self.reserve(1);
+        }
+        unsafe {
+            self.ptr.as_ptr().add(self.len).write(value);
+        }
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            Some(unsafe { self.ptr.as_ptr().add(self.len).read() })
+        }
+    }
+
+    /// Drop the elements after the first `len`
+    pub fn truncate(&mut self, len: usize) {
+        while self.len > len {
+            drop(self.pop());
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0)
+    }
+
+    /// Hand the elements over to Postgres, which frees them with their context, without
+    /// dropping them
+    ///
+    /// The pointer is dangling when the vector never allocated.
+    pub fn into_pg(self) -> *mut T {
+        let ptr = self.ptr.as_ptr();
+        std::mem::forget(self);
+        ptr
+    }
+}
+
+impl<T> Drop for PgVec<T> {
+    fn drop(&mut self) {
+        unsafe {
+            std::ptr::drop_in_place(self.deref_mut() as *mut [T]);
+            if size_of::<T>() != 0 && self.capacity != 0 {
+                pg_sys::pfree(self.ptr.as_ptr() as *mut std::os::raw::c_void);
+            }
+        }
+    }
+}
+
+impl<T> Deref for PgVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> DerefMut for PgVec<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> Extend<T> for PgVec<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a PgVec<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for PgVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for PgVec<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.deref() == other.deref()
+    }
+}
+
+/// A `String` whose bytes are `palloc()`'d in a Postgres `MemoryContext`
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use std::fmt::Write;
+///
+/// // SAFETY: `sql` is handed over to Postgres before the `CurrentMemoryContext` is reset
+/// let mut sql = unsafe { PgString::new() };
+/// write!(sql, "SELECT {} FROM dogs", "name").unwrap();
+/// let cstr = sql.into_pg_cstr(); // freed with the CurrentMemoryContext
+/// ```
+#[derive(PartialEq)]
+pub struct PgString {
+    bytes: PgVec<u8>,
+}
+
+impl PgString {
+    /// An empty string, allocated in the `CurrentMemoryContext` once it grows
+    ///
+    /// ## Safety
+    ///
+    /// As with [`PgVec::new`]
+    pub unsafe fn new() -> Self {
+        PgString {
+            bytes: PgVec::new(),
+        }
+    }
+
+    /// An empty string, allocated in `context` once it grows
+    ///
+    /// ## Safety
+    ///
+    /// As with [`PgVec::new_in`]
+    pub unsafe fn new_in(context: &PgMemoryContexts) -> Self {
+        PgString {
+            bytes: PgVec::new_in(context),
+        }
+    }
+
+    /// A copy of `s`, allocated in `context`
+    ///
+    /// ## Safety
+    ///
+    /// As with [`PgVec::new_in`]
+    pub unsafe fn from_str_in(s: &str, context: &PgMemoryContexts) -> Self {
+        let mut string = PgString::new_in(context);
+        string.push_str(s);
+        string
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        self.bytes.extend(s.bytes());
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]));
+    }
+
+    pub fn as_str(&self) -> &str {
+        // only whole strs are ever pushed
+        unsafe { std::str::from_utf8_unchecked(&self.bytes) }
+    }
+
+    pub fn clear(&mut self) {
+        self.bytes.clear()
+    }
+
+    /// Hand the string over to Postgres as a NUL-terminated `char *`, which is freed with its
+    /// context
+    pub fn into_pg_cstr(mut self) -> *mut std::os::raw::c_char {
+        self.bytes.push(0);
+        self.bytes.into_pg() as *mut std::os::raw::c_char
+    }
+}
+
+impl Deref for PgString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Write for PgString {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl fmt::Display for PgString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Debug for PgString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
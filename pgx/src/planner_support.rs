@@ -0,0 +1,306 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Support for [planner support functions](https://www.postgresql.org/docs/current/xfunc-optimization.html),
+//! which are available since Postgres 12
+//!
+//! A support function is attached to a `#[pg_extern]` with `support = other_fn`, and is asked
+//! by the planner about calls to it through a [`SupportRequest`].  Requests it doesn't answer
+//! get [`SupportResponse::none()`], which leaves the planner to its defaults.
+//!
+//! A set-returning function can be given a fixed estimate of the rows it returns with
+//! `#[pg_extern(rows = n)]`, rather than the default of 1000, or answer a [`RowsRequest`] to
+//! estimate them for each call.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[pg_extern(support = add_support)]
+//! fn add(a: i32, b: i32) -> i32 {
+//!     a + b
+//! }
+//!
+//! /// Simplifies `add(x, 0)` to `x`
+//! #[pg_extern]
+//! fn add_support(request: SupportRequest) -> SupportResponse {
+//!     match request {
+//!         SupportRequest::Simplify(simplify) if simplify.const_arg::<i32>(1) == Some(Some(0)) => {
+//!             simplify.replace_with(simplify.args()[0])
+//!         }
+//!         _ => SupportResponse::none(),
+//!     }
+//! }
+//! ```
+use crate::{is_a, pg_sys, FromDatum, IntoDatum, PgBox, PgList};
+
+/// A request from the planner, which a support function is passed as its `internal` argument
+pub enum SupportRequest {
+    /// A call to the function may be replaced by a simpler expression
+    Simplify(SimplifyRequest),
+    /// The function's result is used as a qual, whose selectivity may be estimated
+    Selectivity(SelectivityRequest),
+    /// The function's result is used as a qual, from which index conditions may be derived
+    IndexCondition(IndexConditionRequest),
+    /// The function returns a set, whose number of rows may be estimated
+    Rows(RowsRequest),
+    /// Any other request, such as for the function's cost
+    Other(*mut pg_sys::Node),
+}
+
+impl FromDatum for SupportRequest {
+    const NEEDS_TYPID: bool = false;
+
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _: pg_sys::Oid) -> Option<Self> {
+        if is_null || datum == 0 {
+            return None;
+        }
+
+        let node = datum as *mut pg_sys::Node;
+        Some(if is_a(node, pg_sys::NodeTag_T_SupportRequestSimplify) {
+            SupportRequest::Simplify(SimplifyRequest(PgBox::from_pg(node as _)))
+        } else if is_a(node, pg_sys::NodeTag_T_SupportRequestSelectivity) {
+            SupportRequest::Selectivity(SelectivityRequest(PgBox::from_pg(node as _)))
+        } else if is_a(node, pg_sys::NodeTag_T_SupportRequestIndexCondition) {
+            SupportRequest::IndexCondition(IndexConditionRequest(PgBox::from_pg(node as _)))
+        } else if is_a(node, pg_sys::NodeTag_T_SupportRequestRows) {
+            SupportRequest::Rows(RowsRequest(PgBox::from_pg(node as _)))
+        } else {
+            SupportRequest::Other(node)
+        })
+    }
+}
+
+/// What a support function returns to the planner
+///
+/// The meaning of the node depends on the request it answers, so it's built by the methods of
+/// the request.
+pub struct SupportResponse(*mut pg_sys::Node);
+
+impl SupportResponse {
+    /// Leaves the request unanswered
+    pub fn none() -> Self {
+        SupportResponse(std::ptr::null_mut())
+    }
+}
+
+impl IntoDatum for SupportResponse {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        // an unanswered request is a null pointer, as the planner errors on a SQL NULL
+        Some(self.0 as pg_sys::Datum)
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::INTERNALOID
+    }
+}
+
+/// Asks whether a call of the function can be replaced by a simpler expression, such as when
+/// some of its arguments are constants
+pub struct SimplifyRequest(PgBox<pg_sys::SupportRequestSimplify>);
+
+impl SimplifyRequest {
+    pub fn root(&self) -> *mut pg_sys::PlannerInfo {
+        self.0.root
+    }
+
+    /// The call being simplified
+    pub fn fcall(&self) -> PgBox<pg_sys::FuncExpr> {
+        unsafe { PgBox::from_pg(self.0.fcall) }
+    }
+
+    /// The argument expressions of the call
+    pub fn args(&self) -> Vec<*mut pg_sys::Node> {
+        list_nodes(self.fcall().args)
+    }
+
+    /// The value of the `i`th argument, if it's a constant
+    pub fn const_arg<T: FromDatum>(&self, i: usize) -> Option<Option<T>> {
+        const_value(self.args().get(i).copied()?)
+    }
+
+    /// Replaces the call with `node`, which must be of the same type as the function's result
+    pub fn replace_with(&self, node: *mut pg_sys::Node) -> SupportResponse {
+        SupportResponse(node)
+    }
+
+    /// Replaces the call with the constant `value`
+    pub fn replace_with_const<T: IntoDatum>(&self, value: Option<T>) -> SupportResponse {
+        let fcall = self.fcall();
+        let datum = value.and_then(|value| value.into_datum());
+        let mut typlen = 0;
+        let mut typbyval = false;
+        unsafe {
+            pg_sys::get_typlenbyval(fcall.funcresulttype, &mut typlen, &mut typbyval);
+            SupportResponse(pg_sys::makeConst(
+                fcall.funcresulttype,
+                -1,
+                fcall.funccollid,
+                typlen as i32,
+                datum.unwrap_or(0),
+                datum.is_none(),
+                typbyval,
+            ) as *mut pg_sys::Node)
+        }
+    }
+}
+
+/// Asks for the selectivity of the function when it's used as a qual
+pub struct SelectivityRequest(PgBox<pg_sys::SupportRequestSelectivity>);
+
+impl SelectivityRequest {
+    pub fn root(&self) -> *mut pg_sys::PlannerInfo {
+        self.0.root
+    }
+
+    /// Whether the qual joins relations, rather than restricting a single one
+    pub fn is_join(&self) -> bool {
+        self.0.is_join
+    }
+
+    /// The argument expressions of the call
+    pub fn args(&self) -> Vec<*mut pg_sys::Node> {
+        list_nodes(self.0.args)
+    }
+
+    /// The value of the `i`th argument, if it's a constant
+    pub fn const_arg<T: FromDatum>(&self, i: usize) -> Option<Option<T>> {
+        const_value(self.args().get(i).copied()?)
+    }
+
+    /// Answers with the fraction of rows, between 0 and 1, the qual is expected to be true for
+    pub fn set_selectivity(mut self, selectivity: f64) -> SupportResponse {
+        self.0.selectivity = selectivity.clamp(0.0, 1.0);
+        SupportResponse(self.0.into_pg() as *mut pg_sys::Node)
+    }
+}
+
+/// Asks for the number of rows a call of the set-returning function will return, such as when
+/// it's used in `FROM`, rather than the `ROWS` it was created with
+pub struct RowsRequest(PgBox<pg_sys::SupportRequestRows>);
+
+impl RowsRequest {
+    pub fn root(&self) -> *mut pg_sys::PlannerInfo {
+        self.0.root
+    }
+
+    /// The function being called
+    pub fn funcid(&self) -> pg_sys::Oid {
+        self.0.funcid
+    }
+
+    /// The function or operator call the rows are returned by
+    pub fn node(&self) -> *mut pg_sys::Node {
+        self.0.node
+    }
+
+    /// The argument expressions of the call
+    pub fn args(&self) -> Vec<*mut pg_sys::Node> {
+        call_args(self.0.node)
+    }
+
+    /// The value of the `i`th argument, if it's a constant
+    pub fn const_arg<T: FromDatum>(&self, i: usize) -> Option<Option<T>> {
+        const_value(self.args().get(i).copied()?)
+    }
+
+    /// Answers with the number of rows the call is expected to return
+    pub fn set_rows(mut self, rows: f64) -> SupportResponse {
+        self.0.rows = rows.max(0.0);
+        SupportResponse(self.0.into_pg() as *mut pg_sys::Node)
+    }
+}
+
+/// Asks for conditions an index can search by, which are implied by the function being true
+pub struct IndexConditionRequest(PgBox<pg_sys::SupportRequestIndexCondition>);
+
+impl IndexConditionRequest {
+    pub fn root(&self) -> *mut pg_sys::PlannerInfo {
+        self.0.root
+    }
+
+    /// The function or operator call the conditions are for
+    pub fn node(&self) -> *mut pg_sys::Node {
+        self.0.node
+    }
+
+    /// The argument expressions of the call
+    pub fn args(&self) -> Vec<*mut pg_sys::Node> {
+        call_args(self.0.node)
+    }
+
+    /// Which of [`IndexConditionRequest::args`] matches the index column
+    pub fn index_arg(&self) -> usize {
+        self.0.indexarg as usize
+    }
+
+    /// The column of the index which is matched, counting from 0
+    pub fn index_col(&self) -> usize {
+        self.0.indexcol as usize
+    }
+
+    /// The operator family of the index column, whose operators the conditions may use
+    pub fn opfamily(&self) -> pg_sys::Oid {
+        self.0.opfamily
+    }
+
+    /// The collation of the index column
+    pub fn index_collation(&self) -> pg_sys::Oid {
+        self.0.indexcollation
+    }
+
+    /// Answers with the index conditions, which are `lossy` if the function must still be
+    /// checked against the rows the index finds
+    pub fn conditions(
+        mut self,
+        conditions: Vec<*mut pg_sys::Node>,
+        lossy: bool,
+    ) -> SupportResponse {
+        if conditions.is_empty() {
+            return SupportResponse::none();
+        }
+
+        self.0.lossy = lossy;
+        let mut list = PgList::<pg_sys::Node>::new();
+        for condition in conditions {
+            list.push(condition);
+        }
+        SupportResponse(list.into_pg() as *mut pg_sys::Node)
+    }
+}
+
+fn call_args(node: *mut pg_sys::Node) -> Vec<*mut pg_sys::Node> {
+    unsafe {
+        if is_a(node, pg_sys::NodeTag_T_FuncExpr) {
+            list_nodes((*(node as *mut pg_sys::FuncExpr)).args)
+        } else if is_a(node, pg_sys::NodeTag_T_OpExpr) {
+            list_nodes((*(node as *mut pg_sys::OpExpr)).args)
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+fn list_nodes(list: *mut pg_sys::List) -> Vec<*mut pg_sys::Node> {
+    let list = unsafe { PgList::<pg_sys::Node>::from_pg(list) };
+    list.iter_ptr().collect()
+}
+
+fn const_value<T: FromDatum>(node: *mut pg_sys::Node) -> Option<Option<T>> {
+    unsafe {
+        if !is_a(node, pg_sys::NodeTag_T_Const) {
+            return None;
+        }
+        let constant = node as *mut pg_sys::Const;
+        Some(T::from_datum(
+            (*constant).constvalue,
+            (*constant).constisnull,
+            (*constant).consttype,
+        ))
+    }
+}
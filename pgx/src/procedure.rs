@@ -0,0 +1,120 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Support for [procedures](https://www.postgresql.org/docs/current/sql-createprocedure.html),
+//! and the [`PgProcedure`] passed to `#[pg_procedure]` functions
+//!
+//! A procedure `CALL`ed outside of a transaction block can commit or roll back its transaction,
+//! which immediately starts a new one, so that a long-running procedure can make its work
+//! visible, and release its locks, as it goes.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! /// Insert `count` rows into `numbers`, committing after each `batch_size` of them
+//! #[pg_procedure]
+//! fn insert_numbers(procedure: &PgProcedure, count: i32, batch_size: i32) {
+//!     for n in 1..=count {
+//!         Spi::run(&format!("INSERT INTO numbers VALUES ({})", n));
+//!         if n % batch_size == 0 {
+//!             procedure.commit();
+//!         }
+//!     }
+//! }
+//! ```
+use crate::{ereport, is_a, pg_sys, PgLogLevel, PgSqlErrorCode, Spi};
+
+/// The `CALL` of a `#[pg_procedure]` function, which can end its transaction
+pub struct PgProcedure {
+    atomic: bool,
+}
+
+impl PgProcedure {
+    /// The `CALL` of the procedure called with `fcinfo`
+    ///
+    /// Panics if the function wasn't called as a procedure.
+    ///
+    /// ## Safety
+    ///
+    /// `fcinfo` must be the `pg_sys::FunctionCallInfo` of the function being called
+    pub unsafe fn from_fcinfo(fcinfo: pg_sys::FunctionCallInfo) -> Self {
+        if !called_as_procedure(fcinfo) {
+            panic!("not called as a procedure");
+        }
+
+        let context = (*fcinfo).context as *mut pg_sys::CallContext;
+        PgProcedure {
+            atomic: (*context).atomic,
+        }
+    }
+
+    /// Must the procedure run within the transaction it was `CALL`ed in?
+    ///
+    /// It must when `CALL`ed within a transaction block, or from a function, and then
+    /// [`PgProcedure::commit`] and [`PgProcedure::rollback`] raise an ERROR.
+    pub fn is_atomic(&self) -> bool {
+        self.atomic
+    }
+
+    /// Commit the current transaction, and start a new one
+    ///
+    /// Raises an ERROR when called from within [`Spi::connect`](crate::Spi::connect), or while a
+    /// subtransaction is open.  `#[pg_procedure]` only accepts owned arguments, as the commit
+    /// frees the memory they were passed in.
+    pub fn commit(&self) {
+        self.end_transaction(|| unsafe { pg_sys::SPI_commit() })
+    }
+
+    /// Roll back the current transaction, and start a new one
+    ///
+    /// As with [`PgProcedure::commit`], raises an ERROR when called from within
+    /// [`Spi::connect`](crate::Spi::connect).
+    pub fn rollback(&self) {
+        self.end_transaction(|| unsafe { pg_sys::SPI_rollback() })
+    }
+
+    fn end_transaction<F: FnOnce()>(&self, end: F) {
+        if self.atomic {
+            ereport(
+                PgLogLevel::ERROR,
+                PgSqlErrorCode::ERRCODE_INVALID_TRANSACTION_TERMINATION,
+                "invalid transaction termination",
+                file!(),
+                line!(),
+                column!(),
+            );
+        }
+
+        // the transaction's end would free the connection's memory out from under it
+        if Spi::is_connected() {
+            ereport(
+                PgLogLevel::ERROR,
+                PgSqlErrorCode::ERRCODE_INVALID_TRANSACTION_TERMINATION,
+                "cannot end a procedure's transaction within Spi::connect()",
+                file!(),
+                line!(),
+                column!(),
+            );
+        }
+
+        // only a nonatomic SPI connection may end the transaction it was made in
+        unsafe {
+            Spi::check_status(pg_sys::SPI_connect_ext(pg_sys::SPI_OPT_NONATOMIC as i32));
+            end();
+            pg_sys::SPI_start_transaction();
+            pg_sys::SPI_finish();
+        }
+    }
+}
+
+#[inline]
+pub unsafe fn called_as_procedure(fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    let fcinfo = fcinfo.as_ref().expect("fcinfo was null");
+    !fcinfo.context.is_null() && is_a(fcinfo.context, pg_sys::NodeTag_T_CallContext)
+}
@@ -0,0 +1,319 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! [Replication slots](https://www.postgresql.org/docs/current/logicaldecoding-explanation.html#LOGICALDECODING-REPLICATION-SLOTS),
+//! for creating and dropping them, reading their status, and consuming a logical slot's changes
+//! from within Postgres, rather than by `pg_recvlogical`
+//!
+//! A background worker which streams changes elsewhere peeks at a batch of them, and only
+//! advances the slot past them once they're delivered, so a batch that fails to be delivered is
+//! decoded again:
+//!
+//! ```rust,no_run
+//! use pgx::bgworkers::BackgroundWorker;
+//! use pgx::*;
+//! use std::time::Duration;
+//!
+//! # fn deliver(_: &[LogicalChange]) {}
+//! let slot = ReplicationSlot::named("to_kafka");
+//! while BackgroundWorker::wait_latch(Some(Duration::from_secs(1))) {
+//!     BackgroundWorker::transaction(|| {
+//!         let changes = slot.peek_changes(Some(1000), &[("include-xids", "0")]);
+//!         if let Some(last) = changes.last() {
+//!             deliver(&changes);
+//!             slot.advance(last.lsn);
+//!         }
+//!     });
+//! }
+//! ```
+//!
+//! A logical slot must be created in a transaction which hasn't written anything, such as a
+//! worker's first.
+use crate::{pg_sys, IntoDatum, PgBuiltInOids, Spi, SpiHeapTupleData};
+
+/// A replication slot, by name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicationSlot {
+    name: String,
+}
+
+/// Whether a slot is for streaming WAL, or decoded changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplicationSlotKind {
+    Physical,
+    Logical,
+}
+
+/// The status of a slot, from `pg_replication_slots`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicationSlotStatus {
+    pub name: String,
+    pub kind: ReplicationSlotKind,
+    /// The output plugin of a logical slot
+    pub plugin: Option<String>,
+    /// The database a logical slot decodes the changes of
+    pub database: Option<String>,
+    pub temporary: bool,
+    /// Whether a session is consuming the slot
+    pub active: bool,
+    pub active_pid: Option<i32>,
+    /// The oldest transaction whose rows the slot keeps from being vacuumed
+    pub xmin: Option<pg_sys::TransactionId>,
+    /// The oldest transaction whose catalog rows the slot keeps from being vacuumed
+    pub catalog_xmin: Option<pg_sys::TransactionId>,
+    /// The oldest WAL the slot keeps from being removed
+    pub restart_lsn: Option<pg_sys::XLogRecPtr>,
+    /// How far a logical slot's consumer has confirmed receiving changes
+    pub confirmed_flush_lsn: Option<pg_sys::XLogRecPtr>,
+}
+
+/// A change decoded by a logical slot's output plugin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogicalChange {
+    /// The LSN of the change, which the slot is advanced to once it's consumed
+    pub lsn: pg_sys::XLogRecPtr,
+    pub xid: pg_sys::TransactionId,
+    /// The plugin's output, which is text unless the plugin's output is binary
+    pub data: Vec<u8>,
+}
+
+impl LogicalChange {
+    /// The plugin's output, as text
+    pub fn text(&self) -> std::borrow::Cow<str> {
+        String::from_utf8_lossy(&self.data)
+    }
+}
+
+const STATUS_COLUMNS: &str = "slot_name::text, slot_type, plugin::text, database::text, \
+     temporary, active, active_pid, xmin, catalog_xmin, \
+     (restart_lsn - '0/0')::int8 AS restart_lsn, \
+     (confirmed_flush_lsn - '0/0')::int8 AS confirmed_flush_lsn";
+
+impl ReplicationSlot {
+    /// The slot named `name`, which isn't checked for existence until it's used
+    pub fn named(name: &str) -> Self {
+        ReplicationSlot {
+            name: name.to_string(),
+        }
+    }
+
+    /// Create a logical slot named `name`, which decodes the changes of this database with the
+    /// output `plugin`, as `pg_create_logical_replication_slot()` does
+    ///
+    /// A `temporary` slot is dropped when this session ends, or when it raises an ERROR.
+    /// Postgres raises an ERROR if this transaction has written anything, if `wal_level` isn't
+    /// `logical`, or if the slot already exists.
+    pub fn create_logical(name: &str, plugin: &str, temporary: bool) -> Self {
+        Spi::get_one_with_args::<String>(
+            "SELECT slot_name::text FROM pg_create_logical_replication_slot($1, $2, $3)",
+            vec![
+                (PgBuiltInOids::TEXTOID.oid(), name.into_datum()),
+                (PgBuiltInOids::TEXTOID.oid(), plugin.into_datum()),
+                (PgBuiltInOids::BOOLOID.oid(), temporary.into_datum()),
+            ],
+        );
+        ReplicationSlot::named(name)
+    }
+
+    /// Create a physical slot named `name`, as `pg_create_physical_replication_slot()` does
+    ///
+    /// With `reserve_wal`, the slot keeps WAL from being removed from now on, rather than from
+    /// when a standby first connects to it.
+    pub fn create_physical(name: &str, reserve_wal: bool, temporary: bool) -> Self {
+        Spi::get_one_with_args::<String>(
+            "SELECT slot_name::text FROM pg_create_physical_replication_slot($1, $2, $3)",
+            vec![
+                (PgBuiltInOids::TEXTOID.oid(), name.into_datum()),
+                (PgBuiltInOids::BOOLOID.oid(), reserve_wal.into_datum()),
+                (PgBuiltInOids::BOOLOID.oid(), temporary.into_datum()),
+            ],
+        );
+        ReplicationSlot::named(name)
+    }
+
+    /// The status of every slot
+    pub fn all() -> Vec<ReplicationSlotStatus> {
+        let mut slots = Vec::new();
+        Spi::connect(|client| {
+            let query = format!(
+                "SELECT {} FROM pg_replication_slots ORDER BY slot_name",
+                STATUS_COLUMNS
+            );
+            slots.extend(client.select(&query, None, None).map(|row| status(&row)));
+            Ok(Some(()))
+        });
+        slots
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The slot's status, or `None` if it doesn't exist
+    pub fn status(&self) -> Option<ReplicationSlotStatus> {
+        let mut slot = None;
+        Spi::connect(|client| {
+            let query = format!(
+                "SELECT {} FROM pg_replication_slots WHERE slot_name = $1",
+                STATUS_COLUMNS
+            );
+            slot = client
+                .select(
+                    &query,
+                    Some(1),
+                    Some(vec![(
+                        PgBuiltInOids::TEXTOID.oid(),
+                        self.name.as_str().into_datum(),
+                    )]),
+                )
+                .next()
+                .map(|row| status(&row));
+            Ok(Some(()))
+        });
+        slot
+    }
+
+    /// Drop the slot, as `pg_drop_replication_slot()` does
+    ///
+    /// Postgres raises an ERROR if the slot doesn't exist, or if another session is consuming it.
+    pub fn drop_slot(self) {
+        Spi::connect(|client| {
+            client.select(
+                "SELECT pg_drop_replication_slot($1)",
+                None,
+                Some(vec![(
+                    PgBuiltInOids::TEXTOID.oid(),
+                    self.name.as_str().into_datum(),
+                )]),
+            );
+            Ok(Some(()))
+        });
+    }
+
+    /// The logical slot's changes, up to `upto_changes` of them, leaving the slot where it is, as
+    /// `pg_logical_slot_peek_binary_changes()` does
+    ///
+    /// The `options` are passed to the output plugin.  Decoding stops at the end of the
+    /// transaction which reaches `upto_changes`, so a few more may be returned.
+    pub fn peek_changes(
+        &self,
+        upto_changes: Option<i32>,
+        options: &[(&str, &str)],
+    ) -> Vec<LogicalChange> {
+        self.changes("pg_logical_slot_peek_binary_changes", upto_changes, options)
+    }
+
+    /// Like [`ReplicationSlot::peek_changes`], but consuming the changes, as
+    /// `pg_logical_slot_get_binary_changes()` does, so they aren't returned again even if this
+    /// transaction aborts
+    pub fn get_changes(
+        &self,
+        upto_changes: Option<i32>,
+        options: &[(&str, &str)],
+    ) -> Vec<LogicalChange> {
+        self.changes("pg_logical_slot_get_binary_changes", upto_changes, options)
+    }
+
+    /// Advance the slot to `upto`, or to the end of the WAL that's been flushed, if that's
+    /// sooner, returning the LSN it's advanced to, as `pg_replication_slot_advance()` does
+    ///
+    /// A logical slot's changes before `upto` are consumed, so a consumer advances it to the
+    /// `lsn` of the last change it's delivered.  A slot isn't moved backwards.
+    #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+    pub fn advance(&self, upto: pg_sys::XLogRecPtr) -> pg_sys::XLogRecPtr {
+        Spi::get_one_with_args::<i64>(
+            "SELECT (end_lsn - '0/0')::int8 FROM pg_replication_slot_advance($1, $2::pg_lsn)",
+            vec![
+                (
+                    PgBuiltInOids::TEXTOID.oid(),
+                    self.name.as_str().into_datum(),
+                ),
+                (
+                    PgBuiltInOids::TEXTOID.oid(),
+                    lsn_to_string(upto).into_datum(),
+                ),
+            ],
+        )
+        .expect("pg_replication_slot_advance() returned NULL") as pg_sys::XLogRecPtr
+    }
+
+    fn changes(
+        &self,
+        function: &str,
+        upto_changes: Option<i32>,
+        options: &[(&str, &str)],
+    ) -> Vec<LogicalChange> {
+        let options = options
+            .iter()
+            .flat_map(|(name, value)| vec![name.to_string(), value.to_string()])
+            .collect::<Vec<_>>();
+        let query = format!(
+            "SELECT (lsn - '0/0')::int8 AS lsn, xid, data FROM {}($1, NULL, $2, VARIADIC $3)",
+            function
+        );
+
+        let mut changes = Vec::new();
+        Spi::connect(|client| {
+            let args = vec![
+                (
+                    PgBuiltInOids::TEXTOID.oid(),
+                    self.name.as_str().into_datum(),
+                ),
+                (PgBuiltInOids::INT4OID.oid(), upto_changes.into_datum()),
+                (PgBuiltInOids::TEXTARRAYOID.oid(), options.into_datum()),
+            ];
+            changes.extend(
+                client
+                    .select(&query, None, Some(args))
+                    .map(|row| LogicalChange {
+                        lsn: column::<i64>(&row, "lsn") as pg_sys::XLogRecPtr,
+                        xid: column(&row, "xid"),
+                        data: column(&row, "data"),
+                    }),
+            );
+            Ok(Some(()))
+        });
+        changes
+    }
+}
+
+/// `lsn` as Postgres writes a `pg_lsn`
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+fn lsn_to_string(lsn: pg_sys::XLogRecPtr) -> String {
+    format!("{:X}/{:X}", lsn >> 32, lsn as u32)
+}
+
+fn status(row: &SpiHeapTupleData) -> ReplicationSlotStatus {
+    ReplicationSlotStatus {
+        name: column(row, "slot_name"),
+        kind: match column::<String>(row, "slot_type").as_str() {
+            "logical" => ReplicationSlotKind::Logical,
+            _ => ReplicationSlotKind::Physical,
+        },
+        plugin: nullable_column(row, "plugin"),
+        database: nullable_column(row, "database"),
+        temporary: column(row, "temporary"),
+        active: column(row, "active"),
+        active_pid: nullable_column(row, "active_pid"),
+        xmin: nullable_column(row, "xmin"),
+        catalog_xmin: nullable_column(row, "catalog_xmin"),
+        restart_lsn: nullable_column::<i64>(row, "restart_lsn").map(|lsn| lsn as u64),
+        confirmed_flush_lsn: nullable_column::<i64>(row, "confirmed_flush_lsn")
+            .map(|lsn| lsn as u64),
+    }
+}
+
+fn column<T: crate::FromDatum>(row: &SpiHeapTupleData, name: &str) -> T {
+    nullable_column(row, name).unwrap_or_else(|| panic!("`{}` is NULL", name))
+}
+
+fn nullable_column<T: crate::FromDatum>(row: &SpiHeapTupleData, name: &str) -> Option<T> {
+    row.get_by_name(name)
+        .unwrap_or_else(|e| panic!("failed to read `{}`: {}", name, e))
+}
@@ -0,0 +1,574 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Run functions on a cron-like schedule, from background workers, in the style of `pg_cron`
+//!
+//! An extension in `shared_preload_libraries` registers its jobs with a [`Scheduler`] from
+//! `_PG_init()`.  This starts a coordinator background worker, which launches a dynamic
+//! background worker to run each job whenever its [`Schedule`] says it's due.  Each run happens
+//! in its own transaction, so a job can use `pgx::Spi` directly, and a job that raises an error
+//! doesn't affect the scheduler or any other job.
+//!
+//! ```rust,no_run
+//! use pgx::scheduler::{Job, Overlap, Scheduler};
+//! use pgx::*;
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     Scheduler::new("my_extension")
+//!         .set_database("app")
+//!         .schedule("expire_sessions", "*/5 * * * *", expire_sessions)
+//!         .job(Job::new("rebuild_summary", "@hourly", rebuild_summary).overlap(Overlap::Queue))
+//!         .start();
+//! }
+//!
+//! fn expire_sessions() {
+//!     Spi::run("DELETE FROM sessions WHERE expires_at < now()");
+//! }
+//!
+//! fn rebuild_summary() {
+//!     Spi::run("REFRESH MATERIALIZED VIEW summary");
+//! }
+//! ```
+//!
+//! Schedules are in UTC.  Runs that come due while the scheduler isn't running, such as while
+//! the server is down, are skipped rather than run late.
+//!
+//! The scheduler keeps a table, `pgx_scheduled_jobs` by default, with a row for each job: its
+//! `name` and `schedule`, when it last started and finished (`last_start` and `last_finish`),
+//! how that run went (`last_status`, one of `running`, `succeeded`, `failed`, or `interrupted`),
+//! and how many times it's been run (`runs`) or `skipped`.  It's created, if need be, when the
+//! scheduler starts.
+//!
+//! The coordinator and the job runners' main functions are part of pgx, and so are part of your
+//! extension's shared library, which must be named when creating the [`Scheduler`].
+use crate::bgworkers::{
+    BackgroundWorker, BackgroundWorkerBuilder, BackgroundWorkerStatus, DynamicBackgroundWorker,
+    SignalWakeFlags,
+};
+use crate::{pg_guard, pg_sys, IntoDatum, PgBuiltInOids, Spi};
+use once_cell::sync::OnceCell;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use time::OffsetDateTime;
+
+/// The jobs registered with [`Scheduler::start`], which every process forked from the
+/// postmaster inherits
+static SCHEDULER: OnceCell<Scheduler> = OnceCell::new();
+
+/// What to do when a job comes due while a previous run of it is still going
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overlap {
+    /// Don't run it this time, counting the run as skipped
+    Skip,
+    /// Run it once the previous run finishes, however many runs came due in the meantime
+    Queue,
+    /// Run it anyway, alongside the previous run
+    Allow,
+}
+
+impl Default for Overlap {
+    fn default() -> Self {
+        Overlap::Skip
+    }
+}
+
+/// A function to run on a [`Schedule`]
+pub struct Job {
+    name: String,
+    schedule: Schedule,
+    run: fn(),
+    overlap: Overlap,
+}
+
+impl Job {
+    /// A job called `name`, which runs `run` on the cron-style `schedule`
+    ///
+    /// Its name must be unique among the jobs of its [`Scheduler`].  By default, runs that come
+    /// due while it's still running are skipped.
+    ///
+    /// Panics if `schedule` isn't valid.
+    pub fn new(name: &str, schedule: &str, run: fn()) -> Self {
+        let schedule = Schedule::parse(schedule)
+            .unwrap_or_else(|e| panic!("invalid schedule for job \"{}\": {}", name, e));
+        Job {
+            name: name.to_string(),
+            schedule,
+            run,
+            overlap: Overlap::default(),
+        }
+    }
+
+    /// What to do when the job comes due while it's still running
+    pub fn overlap(mut self, overlap: Overlap) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+}
+
+/// A builder for the set of jobs an extension runs on a schedule
+///
+/// Like [`BackgroundWorkerBuilder::load`], [`Scheduler::start`] must be called from within your
+/// extension's `_PG_init()` function, while it's being loaded by `shared_preload_libraries`.
+pub struct Scheduler {
+    library: String,
+    database: String,
+    table: String,
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    /// A scheduler for the extension whose shared library is named `library`
+    ///
+    /// By default, jobs are run connected to the `postgres` database, and are recorded in its
+    /// `pgx_scheduled_jobs` table.
+    pub fn new(library: &str) -> Self {
+        Scheduler {
+            library: library.to_string(),
+            database: "postgres".to_string(),
+            table: "pgx_scheduled_jobs".to_string(),
+            jobs: Vec::new(),
+        }
+    }
+
+    /// The database jobs connect to, and in which the scheduler's table is kept
+    pub fn set_database(mut self, database: &str) -> Self {
+        self.database = database.to_string();
+        self
+    }
+
+    /// The name of the table in which the scheduler records each job's runs, which may be
+    /// schema-qualified
+    ///
+    /// It's used in the scheduler's queries as written, so it must be a valid SQL name.
+    pub fn set_table(mut self, table: &str) -> Self {
+        self.table = table.to_string();
+        self
+    }
+
+    /// Add a job called `name`, which runs `run` on the cron-style `schedule`
+    ///
+    /// See [`Job::new`].
+    pub fn schedule(self, name: &str, schedule: &str, run: fn()) -> Self {
+        self.job(Job::new(name, schedule, run))
+    }
+
+    /// Add `job`
+    ///
+    /// Panics if there's already a job of the same name.
+    pub fn job(mut self, job: Job) -> Self {
+        if self.jobs.iter().any(|existing| existing.name == job.name) {
+            panic!("there is already a scheduled job named \"{}\"", job.name);
+        }
+        self.jobs.push(job);
+        self
+    }
+
+    /// Register the scheduler's coordinator background worker, which starts running jobs once
+    /// Postgres has finished starting up
+    ///
+    /// Panics if a scheduler has already been started.
+    pub fn start(self) {
+        let builder = BackgroundWorkerBuilder::new("pgx scheduler")
+            .set_library(&self.library)
+            .set_function("pgx_scheduler_main")
+            .set_restart_time(Some(Duration::from_secs(10)))
+            .enable_spi_access();
+
+        if SCHEDULER.set(self).is_err() {
+            panic!("a pgx scheduler has already been started");
+        }
+        builder.load();
+    }
+}
+
+fn scheduler() -> &'static Scheduler {
+    SCHEDULER
+        .get()
+        .expect("no pgx scheduler was started by this library")
+}
+
+/// When a [`Job`] is due: a cron-style schedule, checked once a minute, in UTC
+///
+/// It has the five fields of a crontab entry, separated by spaces: the minute (0-59), hour
+/// (0-23), day of the month (1-31), month (1-12), and day of the week (0-7, where both 0 and 7
+/// are Sunday).  Each field is either `*`, for any value, or a comma-separated list of values
+/// and ranges (`a-b`), either of which may be followed by a step (`*/n` or `a-b/n`).  As with
+/// cron, if both the day of the month and the day of the week are restricted, a time matches if
+/// either does.
+///
+/// `@yearly`, `@monthly`, `@weekly`, `@daily`, and `@hourly` are accepted too.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    source: String,
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Schedule {
+    pub fn parse(source: &str) -> Result<Self, ScheduleError> {
+        let expanded = match source.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+
+        let fields = expanded.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != 5 {
+            return Err(ScheduleError::FieldCount(fields.len()));
+        }
+
+        let minutes = parse_field(fields[0], "minute", 0, 59)?;
+        let hours = parse_field(fields[1], "hour", 0, 23)?;
+        let days = parse_field(fields[2], "day of month", 1, 31)?;
+        let months = parse_field(fields[3], "month", 1, 12)?;
+        let mut weekdays = parse_field(fields[4], "day of week", 0, 7)?;
+        // 7 is also Sunday
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Ok(Schedule {
+            source: source.to_string(),
+            minutes,
+            hours: hours as u32,
+            days: days as u32,
+            months: months as u16,
+            weekdays: weekdays as u8,
+            // as in cron, `*/n` counts as unrestricted too
+            any_day: fields[2].starts_with('*'),
+            any_weekday: fields[4].starts_with('*'),
+        })
+    }
+
+    /// Is the schedule due in the minute `time` is in?
+    pub fn matches(&self, time: OffsetDateTime) -> bool {
+        let time = time.to_offset(time::UtcOffset::UTC);
+        self.minutes & (1 << time.minute()) != 0
+            && self.hours & (1 << time.hour()) != 0
+            && self.months & (1 << time.month() as u8) != 0
+            && self.matches_day(time)
+    }
+
+    /// The first minute after `time` in which the schedule is due, if it ever is
+    pub fn next_after(&self, time: OffsetDateTime) -> Option<OffsetDateTime> {
+        let time = time.to_offset(time::UtcOffset::UTC);
+        let mut next = time.replace_time(time::Time::from_hms(time.hour(), time.minute(), 0).ok()?)
+            + time::Duration::minutes(1);
+
+        // every schedule that ever matches does so within eight years, even one that only
+        // matches on February 29th
+        let limit = next + time::Duration::days(366 * 8);
+        while next < limit {
+            if self.months & (1 << next.month() as u8) == 0 || !self.matches_day(next) {
+                next = next.replace_time(time::Time::MIDNIGHT) + time::Duration::days(1);
+            } else if self.hours & (1 << next.hour()) == 0 {
+                next = next.replace_time(time::Time::from_hms(next.hour(), 0, 0).ok()?)
+                    + time::Duration::hours(1);
+            } else if self.minutes & (1 << next.minute()) == 0 {
+                next += time::Duration::minutes(1);
+            } else {
+                return Some(next);
+            }
+        }
+        None
+    }
+
+    fn matches_day(&self, time: OffsetDateTime) -> bool {
+        let day = self.days & (1 << time.day()) != 0;
+        let weekday = self.weekdays & (1 << time.weekday().number_days_from_sunday()) != 0;
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+}
+
+impl Display for Schedule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl std::str::FromStr for Schedule {
+    type Err = ScheduleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Schedule::parse(s)
+    }
+}
+
+/// Why a [`Schedule`] couldn't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleError {
+    /// The schedule didn't have exactly five fields
+    FieldCount(usize),
+    /// One of its fields wasn't valid
+    InvalidField { field: &'static str, value: String },
+}
+
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleError::FieldCount(count) => {
+                write!(f, "expected 5 fields, but found {}", count)
+            }
+            ScheduleError::InvalidField { field, value } => {
+                write!(f, "invalid {} field: \"{}\"", field, value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+/// One field of a schedule, as a bit set of the values it matches
+fn parse_field(field: &str, name: &'static str, min: u32, max: u32) -> Result<u64, ScheduleError> {
+    let invalid = || ScheduleError::InvalidField {
+        field: name,
+        value: field.to_string(),
+    };
+    let number = |s: &str| -> Result<u32, ScheduleError> {
+        match s.parse::<u32>() {
+            Ok(n) if (min..=max).contains(&n) => Ok(n),
+            _ => Err(invalid()),
+        }
+    };
+
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(invalid()),
+            },
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (number(start)?, number(end)?),
+                // like cron, `a/n` means from a to the maximum
+                None if step > 1 => (number(range)?, max),
+                None => {
+                    let n = number(range)?;
+                    (n, n)
+                }
+            },
+        };
+        if start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+/// A job's runs, as the coordinator tracks them
+struct JobState {
+    next: Option<OffsetDateTime>,
+    running: Vec<DynamicBackgroundWorker>,
+    queued: bool,
+}
+
+/// The main function of the coordinator background worker registered by [`Scheduler::start`]
+#[pg_guard]
+pub extern "C" fn pgx_scheduler_main(_arg: pg_sys::Datum) {
+    let scheduler = scheduler();
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    BackgroundWorker::connect_worker_to_spi(Some(&scheduler.database), None);
+    BackgroundWorker::transaction(|| create_table(scheduler));
+
+    let now = OffsetDateTime::now_utc();
+    let mut states = scheduler
+        .jobs
+        .iter()
+        .map(|job| JobState {
+            next: job.schedule.next_after(now),
+            running: Vec::new(),
+            queued: false,
+        })
+        .collect::<Vec<_>>();
+
+    loop {
+        let now = OffsetDateTime::now_utc();
+        for (index, (job, state)) in scheduler.jobs.iter().zip(states.iter_mut()).enumerate() {
+            state.running.retain(|worker| match worker.status() {
+                BackgroundWorkerStatus::Started(_) | BackgroundWorkerStatus::NotYetStarted => true,
+                _ => {
+                    unsafe { pg_sys::pfree(worker.as_ptr() as _) };
+                    false
+                }
+            });
+            if state.queued && state.running.is_empty() {
+                state.queued = false;
+                launch(scheduler, index, state);
+            }
+
+            if let Some(next) = state.next.filter(|next| *next <= now) {
+                state.next = job.schedule.next_after(next.max(now));
+                match job.overlap {
+                    _ if state.running.is_empty() => launch(scheduler, index, state),
+                    Overlap::Skip => record_skipped(scheduler, job),
+                    Overlap::Queue => state.queued = true,
+                    Overlap::Allow => launch(scheduler, index, state),
+                }
+            }
+        }
+
+        // runners exiting wake us too, as we're their notify pid
+        let timeout = states
+            .iter()
+            .filter_map(|state| state.next)
+            .min()
+            .map(|next| (next - OffsetDateTime::now_utc()).whole_milliseconds())
+            .unwrap_or(i128::MAX)
+            .clamp(1, 60_000);
+        if !BackgroundWorker::wait_latch(Some(Duration::from_millis(timeout as u64))) {
+            break;
+        }
+    }
+}
+
+/// The main function of the dynamic background workers the coordinator starts to run each job
+#[pg_guard]
+pub extern "C" fn pgx_scheduler_job_main(arg: pg_sys::Datum) {
+    let scheduler = scheduler();
+    let job = &scheduler.jobs[arg as usize];
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    BackgroundWorker::connect_worker_to_spi(Some(&scheduler.database), None);
+
+    BackgroundWorker::transaction(|| {
+        update_job(
+            scheduler,
+            job,
+            "last_start = now(), last_finish = NULL, last_status = 'running', runs = runs + 1",
+        )
+    });
+
+    let run = job.run;
+    let status = match BackgroundWorker::try_transaction(run) {
+        Some(()) => "succeeded",
+        None => "failed",
+    };
+
+    BackgroundWorker::transaction(|| {
+        update_job(
+            scheduler,
+            job,
+            &format!(
+                "last_finish = clock_timestamp(), last_status = '{}'",
+                status
+            ),
+        )
+    });
+}
+
+fn launch(scheduler: &Scheduler, index: usize, state: &mut JobState) {
+    let job = &scheduler.jobs[index];
+    let worker = BackgroundWorkerBuilder::new(&format!("pgx scheduler job {}", job.name))
+        .set_type("pgx scheduler job")
+        .set_library(&scheduler.library)
+        .set_function("pgx_scheduler_job_main")
+        .set_argument(Some(index as pg_sys::Datum))
+        .enable_spi_access()
+        .load_dynamic();
+
+    match worker {
+        Ok(worker) => state.running.push(worker),
+        Err(e) => {
+            warning!("couldn't start scheduled job \"{}\": {}", job.name, e);
+            record_skipped(scheduler, job);
+        }
+    }
+}
+
+fn record_skipped(scheduler: &Scheduler, job: &Job) {
+    BackgroundWorker::transaction(|| update_job(scheduler, job, "skipped = skipped + 1"));
+}
+
+fn create_table(scheduler: &Scheduler) {
+    Spi::execute(|mut client| {
+        client.update(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    name text PRIMARY KEY,
+                    schedule text NOT NULL,
+                    last_start timestamptz,
+                    last_finish timestamptz,
+                    last_status text,
+                    runs bigint NOT NULL DEFAULT 0,
+                    skipped bigint NOT NULL DEFAULT 0
+                )",
+                scheduler.table
+            ),
+            None,
+            None,
+        );
+
+        // the runs in progress when the scheduler last stopped never finished
+        client.update(
+            &format!(
+                "UPDATE {} SET last_status = 'interrupted' WHERE last_status = 'running'",
+                scheduler.table
+            ),
+            None,
+            None,
+        );
+
+        for job in &scheduler.jobs {
+            client.update(
+                &format!(
+                    "INSERT INTO {} (name, schedule) VALUES ($1, $2)
+                     ON CONFLICT (name) DO UPDATE SET schedule = excluded.schedule",
+                    scheduler.table
+                ),
+                None,
+                Some(vec![
+                    (PgBuiltInOids::TEXTOID.oid(), job.name.as_str().into_datum()),
+                    (
+                        PgBuiltInOids::TEXTOID.oid(),
+                        job.schedule.source.as_str().into_datum(),
+                    ),
+                ]),
+            );
+        }
+    });
+}
+
+fn update_job(scheduler: &Scheduler, job: &Job, set: &str) {
+    Spi::execute(|mut client| {
+        client.update(
+            &format!("UPDATE {} SET {} WHERE name = $1", scheduler.table, set),
+            None,
+            Some(vec![(
+                PgBuiltInOids::TEXTOID.oid(),
+                job.name.as_str().into_datum(),
+            )]),
+        );
+    });
+}
@@ -0,0 +1,253 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Postgres' shared memory message queues, for passing messages between a backend and the
+//! background workers it starts
+//!
+//! A queue has exactly one [`ShmMqSender`] and one [`ShmMqReceiver`], typically in separate
+//! processes.  It lives in shared memory the caller provides, usually a dynamic shared memory
+//! segment the coordinating backend creates and passes to its workers.
+//!
+//! ```rust,no_run
+//! use pgx::bgworkers::BackgroundWorkerBuilder;
+//! use pgx::*;
+//!
+//! // in the coordinating backend
+//! let mut receiver = unsafe {
+//!     let segment = pg_sys::dsm_create(16384, 0);
+//!     ShmMqReceiver::create(pg_sys::dsm_segment_address(segment), 16384, segment)
+//! };
+//! let worker = BackgroundWorkerBuilder::new("worker")
+//!     .set_library("example")
+//!     .set_function("worker_main")
+//!     .load_dynamic()
+//!     .unwrap();
+//! receiver.set_worker(&worker);
+//! let total: i64 = receiver.recv().unwrap();
+//!
+//! // and in the worker, having attached to the segment
+//! # let (mq, segment) = (std::ptr::null_mut(), std::ptr::null_mut());
+//! let mut sender = unsafe { ShmMqSender::attach(mq, segment) };
+//! sender.send(&42_i64).unwrap();
+//! ```
+use crate::bgworkers::DynamicBackgroundWorker;
+use crate::pg_sys;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Why a message couldn't be sent or received
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShmMqError {
+    /// The other side of the queue has detached, or its worker exited without attaching
+    Detached,
+    /// A typed message couldn't be encoded or decoded
+    Serialization(String),
+}
+
+/// The sending side of a shared memory message queue
+///
+/// Its handle is allocated in the `CurrentMemoryContext`, and it must be dropped before the
+/// shared memory the queue is in is detached.  Dropping it detaches from the queue, after which
+/// the receiver gets [`ShmMqError::Detached`] once it has read every message.
+pub struct ShmMqSender {
+    handle: *mut pg_sys::shm_mq_handle,
+}
+
+/// The receiving side of a shared memory message queue
+///
+/// Its handle is allocated in the `CurrentMemoryContext`, and it must be dropped before the
+/// shared memory the queue is in is detached.  Dropping it detaches from the queue, after which
+/// the sender gets [`ShmMqError::Detached`].
+pub struct ShmMqReceiver {
+    handle: *mut pg_sys::shm_mq_handle,
+}
+
+impl ShmMqSender {
+    /// Create a new queue of `size` bytes at `address` and attach to it as its sender
+    ///
+    /// ## Safety
+    ///
+    /// `address` must point to at least `size` bytes of shared memory, in `segment` if it isn't
+    /// null, and `size` must be at least `pg_sys::shm_mq_minimum_size`.
+    pub unsafe fn create(
+        address: *mut std::os::raw::c_void,
+        size: usize,
+        segment: *mut pg_sys::dsm_segment,
+    ) -> Self {
+        Self::attach(pg_sys::shm_mq_create(address, size), segment)
+    }
+
+    /// Attach to an existing queue as its sender
+    ///
+    /// ## Safety
+    ///
+    /// `mq` must be a queue created by `shm_mq_create()`, in `segment` if it isn't null, that
+    /// doesn't already have a sender.
+    pub unsafe fn attach(mq: *mut pg_sys::shm_mq, segment: *mut pg_sys::dsm_segment) -> Self {
+        pg_sys::shm_mq_set_sender(mq, pg_sys::MyProc);
+        ShmMqSender {
+            handle: pg_sys::shm_mq_attach(mq, segment, std::ptr::null_mut()),
+        }
+    }
+
+    /// Identify the worker that will receive from the queue, so sending fails with
+    /// [`ShmMqError::Detached`] rather than waiting forever if it exits without attaching
+    pub fn set_worker(&mut self, worker: &DynamicBackgroundWorker) {
+        unsafe { pg_sys::shm_mq_set_handle(self.handle, worker.as_ptr()) }
+    }
+
+    /// Send `data` as one message, waiting for room in the queue as necessary
+    pub fn send_bytes(&mut self, data: &[u8]) -> Result<(), ShmMqError> {
+        self.send_impl(data, false).map(|_| ())
+    }
+
+    /// Send `data` as one message if it can be done without waiting, returning whether it was
+    /// sent
+    ///
+    /// If it wasn't, part of it may have been, so the same message must be sent again before any
+    /// other.
+    pub fn try_send_bytes(&mut self, data: &[u8]) -> Result<bool, ShmMqError> {
+        self.send_impl(data, true)
+    }
+
+    /// Encode `message` and send it, waiting for room in the queue as necessary
+    pub fn send<T: Serialize>(&mut self, message: &T) -> Result<(), ShmMqError> {
+        self.send_bytes(&encode(message)?)
+    }
+
+    /// Encode `message` and send it if it can be done without waiting, returning whether it was
+    /// sent
+    ///
+    /// As with [`ShmMqSender::try_send_bytes`], the same message must be sent again if it wasn't.
+    pub fn try_send<T: Serialize>(&mut self, message: &T) -> Result<bool, ShmMqError> {
+        self.try_send_bytes(&encode(message)?)
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::shm_mq_handle {
+        self.handle
+    }
+
+    fn send_impl(&mut self, data: &[u8], nowait: bool) -> Result<bool, ShmMqError> {
+        let result =
+            unsafe { pg_sys::shm_mq_send(self.handle, data.len(), data.as_ptr() as _, nowait) };
+        match result {
+            pg_sys::shm_mq_result_SHM_MQ_SUCCESS => Ok(true),
+            pg_sys::shm_mq_result_SHM_MQ_WOULD_BLOCK => Ok(false),
+            _ => Err(ShmMqError::Detached),
+        }
+    }
+}
+
+impl ShmMqReceiver {
+    /// Create a new queue of `size` bytes at `address` and attach to it as its receiver
+    ///
+    /// ## Safety
+    ///
+    /// `address` must point to at least `size` bytes of shared memory, in `segment` if it isn't
+    /// null, and `size` must be at least `pg_sys::shm_mq_minimum_size`.
+    pub unsafe fn create(
+        address: *mut std::os::raw::c_void,
+        size: usize,
+        segment: *mut pg_sys::dsm_segment,
+    ) -> Self {
+        Self::attach(pg_sys::shm_mq_create(address, size), segment)
+    }
+
+    /// Attach to an existing queue as its receiver
+    ///
+    /// ## Safety
+    ///
+    /// `mq` must be a queue created by `shm_mq_create()`, in `segment` if it isn't null, that
+    /// doesn't already have a receiver.
+    pub unsafe fn attach(mq: *mut pg_sys::shm_mq, segment: *mut pg_sys::dsm_segment) -> Self {
+        pg_sys::shm_mq_set_receiver(mq, pg_sys::MyProc);
+        ShmMqReceiver {
+            handle: pg_sys::shm_mq_attach(mq, segment, std::ptr::null_mut()),
+        }
+    }
+
+    /// Identify the worker that will send to the queue, so receiving fails with
+    /// [`ShmMqError::Detached`] rather than waiting forever if it exits without attaching
+    pub fn set_worker(&mut self, worker: &DynamicBackgroundWorker) {
+        unsafe { pg_sys::shm_mq_set_handle(self.handle, worker.as_ptr()) }
+    }
+
+    /// Receive the next message, waiting for one as necessary
+    pub fn recv_bytes(&mut self) -> Result<Vec<u8>, ShmMqError> {
+        self.recv_impl(false)
+            .map(|message| message.expect("blocking shm_mq_receive() returned nothing"))
+    }
+
+    /// Receive the next message if one is waiting
+    pub fn try_recv_bytes(&mut self) -> Result<Option<Vec<u8>>, ShmMqError> {
+        self.recv_impl(true)
+    }
+
+    /// Receive the next message and decode it, waiting for one as necessary
+    pub fn recv<T: DeserializeOwned>(&mut self) -> Result<T, ShmMqError> {
+        decode(&self.recv_bytes()?)
+    }
+
+    /// Receive the next message and decode it, if one is waiting
+    pub fn try_recv<T: DeserializeOwned>(&mut self) -> Result<Option<T>, ShmMqError> {
+        match self.try_recv_bytes()? {
+            Some(bytes) => decode(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::shm_mq_handle {
+        self.handle
+    }
+
+    fn recv_impl(&mut self, nowait: bool) -> Result<Option<Vec<u8>>, ShmMqError> {
+        let mut nbytes = 0;
+        let mut data = std::ptr::null_mut();
+        let result = unsafe { pg_sys::shm_mq_receive(self.handle, &mut nbytes, &mut data, nowait) };
+        match result {
+            pg_sys::shm_mq_result_SHM_MQ_SUCCESS => {
+                // the message is only valid until the next receive, so copy it out
+                let bytes = unsafe { std::slice::from_raw_parts(data as *const u8, nbytes) };
+                Ok(Some(bytes.to_vec()))
+            }
+            pg_sys::shm_mq_result_SHM_MQ_WOULD_BLOCK => Ok(None),
+            _ => Err(ShmMqError::Detached),
+        }
+    }
+}
+
+impl Drop for ShmMqSender {
+    fn drop(&mut self) {
+        unsafe { pg_sys::shm_mq_detach(self.handle) }
+    }
+}
+
+impl Drop for ShmMqReceiver {
+    fn drop(&mut self) {
+        unsafe { pg_sys::shm_mq_detach(self.handle) }
+    }
+}
+
+fn encode<T: Serialize>(message: &T) -> Result<Vec<u8>, ShmMqError> {
+    serde_cbor::to_vec(message).map_err(|e| ShmMqError::Serialization(e.to_string()))
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ShmMqError> {
+    serde_cbor::from_slice(bytes).map_err(|e| ShmMqError::Serialization(e.to_string()))
+}
+
+impl std::fmt::Display for ShmMqError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShmMqError::Detached => write!(f, "the other side of the queue has detached"),
+            ShmMqError::Serialization(e) => write!(f, "invalid message: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ShmMqError {}
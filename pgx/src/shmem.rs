@@ -7,7 +7,7 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 use crate::lwlock::*;
-use crate::{pg_sys, PgAtomic};
+use crate::{pg_sys, PGXSharedAtomic, PgAtomic};
 use std::hash::Hash;
 use uuid::Uuid;
 
@@ -37,15 +37,38 @@ pub unsafe trait PGXSharedMemory {}
 /// // primitive types must be protected behind a `PgLwLock`
 /// static PRIMITIVE: PgLwLock<i32> = PgLwLock::new();
 ///
+/// // a bounded ring buffer, whose lock is named so it's identifiable in `pg_stat_activity`
+/// static RECENT: PgLwLock<heapless::HistoryBuffer<i64, 32>> =
+///     PgLwLock::new_named("my_extension_recent");
+///
 /// // Rust atomics can be used without locks, wrapped in a `PgAtomic`
 /// static ATOMIC: PgAtomic<std::sync::atomic::AtomicBool> = PgAtomic::new();
 ///
+/// // as can spinlocks, for a small value updated in one go
+/// static LAST_SEEN: PgAtomic<SharedSpinLock<(i64, i64)>> = PgAtomic::new();
+///
 /// #[pg_guard]
 /// pub extern "C" fn _PG_init() {
 ///     pg_shmem_init!(PRIMITIVE);
+///     pg_shmem_init!(RECENT);
 ///     pg_shmem_init!(ATOMIC);
+///     pg_shmem_init!(LAST_SEEN);
+/// }
+///
+/// #[pg_extern]
+/// fn record(value: i64) {
+///     RECENT.exclusive().write(value);
 /// }
 /// ```
+///
+/// The value is created with `T::default()` by whichever process first initializes shared
+/// memory, and is then shared by every backend.
+///
+/// This takes care of both halves of setting up shared memory: reserving space for the value
+/// while the postmaster loads the extension, and then finding or creating it once shared memory
+/// exists.  Each value's shared memory is named after the `static` it's held in, so a process
+/// that runs the startup hook after another already has, such as each backend on Windows,
+/// attaches to the value that's already there rather than resetting it.
 #[macro_export]
 macro_rules! pg_shmem_init {
     ($thing:expr) => {
@@ -63,7 +86,7 @@ macro_rules! pg_shmem_init {
                         i();
                     }
                 }
-                $thing.shmem_init();
+                $thing.shmem_init_named(concat!(module_path!(), "::", stringify!($thing)));
             }
         }
     };
@@ -77,6 +100,14 @@ pub trait PgSharedMemoryInitialization {
     /// Automatically called by the `pg_shmem_init!()` macro, when Postgres is initializing its
     /// shared memory system
     fn shmem_init(&'static self);
+
+    /// Like [`PgSharedMemoryInitialization::shmem_init`], but with a `name` that's the same in
+    /// every process, for types that have no name of their own to find their shared memory by
+    ///
+    /// This is what `pg_shmem_init!()` calls, with the path of the `static` being initialized.
+    fn shmem_init_named(&'static self, _name: &'static str) {
+        self.shmem_init();
+    }
 }
 
 impl<T> PgSharedMemoryInitialization for PgLwLock<T>
@@ -94,7 +125,7 @@ where
 
 impl<T> PgSharedMemoryInitialization for PgAtomic<T>
 where
-    T: atomic_traits::Atomic + Default,
+    T: PGXSharedAtomic,
 {
     fn pg_init(&'static self) {
         PgSharedMem::pg_init_atomic(self);
@@ -103,6 +134,10 @@ where
     fn shmem_init(&'static self) {
         PgSharedMem::shmem_init_atomic(self);
     }
+
+    fn shmem_init_named(&'static self, name: &'static str) {
+        PgSharedMem::shmem_init_atomic_named(self, name);
+    }
 }
 
 /// This struct contains methods to drive creation of types in shared memory
@@ -111,6 +146,7 @@ pub struct PgSharedMem {}
 impl PgSharedMem {
     /// Must be run from PG_init, use for types which are guarded by a LWLock
     pub fn pg_init_locked<T: Default + PGXSharedMemory>(lock: &PgLwLock<T>) {
+        check_shared_preload_libraries();
         unsafe {
             let lock = std::ffi::CString::new(lock.get_name()).expect("CString::new failed");
             pg_sys::RequestAddinShmemSpace(std::mem::size_of::<T>());
@@ -119,7 +155,8 @@ impl PgSharedMem {
     }
 
     /// Must be run from _PG_init for atomics
-    pub fn pg_init_atomic<T: atomic_traits::Atomic + Default>(_atomic: &PgAtomic<T>) {
+    pub fn pg_init_atomic<T: PGXSharedAtomic>(_atomic: &PgAtomic<T>) {
+        check_shared_preload_libraries();
         unsafe {
             pg_sys::RequestAddinShmemSpace(std::mem::size_of::<T>());
         }
@@ -138,7 +175,11 @@ impl PgSharedMem {
                 pg_sys::ShmemInitStruct(shm_name.into_raw(), std::mem::size_of::<T>(), &mut found)
                     as *mut T;
 
-            std::ptr::write(fv_shmem, <T>::default());
+            // another process may have already initialized it, such as on platforms where each
+            // backend runs the shmem startup hook itself
+            if !found {
+                std::ptr::write(fv_shmem, <T>::default());
+            }
 
             lock.attach(fv_shmem);
             pg_sys::LWLockRelease(addin_shmem_init_lock);
@@ -146,7 +187,10 @@ impl PgSharedMem {
     }
 
     /// Must be run from the shared memory init hook, use for rust atomics behind `PgAtomic`
-    pub fn shmem_init_atomic<T: atomic_traits::Atomic + Default>(atomic: &PgAtomic<T>) {
+    ///
+    /// Each call creates a new value, so prefer [`PgSharedMem::shmem_init_atomic_named`], which
+    /// attaches to the value created by another process instead.
+    pub fn shmem_init_atomic<T: PGXSharedAtomic>(atomic: &PgAtomic<T>) {
         unsafe {
             let shm_name =
                 std::ffi::CString::new(Uuid::new_v4().to_string()).expect("CString::new() failed");
@@ -161,11 +205,40 @@ impl PgSharedMem {
                     as *mut T;
 
             atomic.attach(fv_shmem);
-            let atomic = T::default();
-            std::ptr::copy(&atomic, fv_shmem, 1);
+            std::ptr::write(fv_shmem, T::default());
             pg_sys::LWLockRelease(addin_shmem_init_lock);
         }
     }
+    /// Must be run from the shared memory init hook, use for rust atomics behind `PgAtomic`,
+    /// which are found by `name`, and only created by the first process to look for them
+    pub fn shmem_init_atomic_named<T: PGXSharedAtomic>(atomic: &PgAtomic<T>, name: &str) {
+        let mut found = false;
+        unsafe {
+            let shm_name = std::ffi::CString::new(name).expect("CString::new() failed");
+
+            let addin_shmem_init_lock: *mut pg_sys::LWLock =
+                &mut (*pg_sys::MainLWLockArray.add(21)).lock;
+            pg_sys::LWLockAcquire(addin_shmem_init_lock, pg_sys::LWLockMode_LW_EXCLUSIVE);
+
+            let fv_shmem =
+                pg_sys::ShmemInitStruct(shm_name.into_raw(), std::mem::size_of::<T>(), &mut found)
+                    as *mut T;
+
+            if !found {
+                std::ptr::write(fv_shmem, T::default());
+            }
+
+            atomic.attach(fv_shmem);
+            pg_sys::LWLockRelease(addin_shmem_init_lock);
+        }
+    }
+}
+
+/// Shared memory can only be requested while the postmaster is loading `shared_preload_libraries`
+pub(crate) fn check_shared_preload_libraries() {
+    if unsafe { !pg_sys::process_shared_preload_libraries_in_progress } {
+        panic!("shared memory can only be initialized by an extension loaded via `shared_preload_libraries`");
+    }
 }
 
 unsafe impl PGXSharedMemory for bool {}
@@ -218,6 +291,11 @@ where
 {
 }
 unsafe impl<T, const N: usize> PGXSharedMemory for heapless::Vec<T, N> {}
+unsafe impl<T, const N: usize> PGXSharedMemory for heapless::Deque<T, N> {}
+unsafe impl<T, const N: usize> PGXSharedMemory for heapless::HistoryBuffer<T, N> {}
+unsafe impl<const N: usize> PGXSharedMemory for heapless::String<N> {}
+unsafe impl<K: Eq, V, const N: usize> PGXSharedMemory for heapless::LinearMap<K, V, N> {}
+unsafe impl<T: Eq + Hash, S, const N: usize> PGXSharedMemory for heapless::IndexSet<T, S, N> {}
 unsafe impl<K: Eq + Hash, V: Default, S, const N: usize> PGXSharedMemory
     for heapless::IndexMap<K, V, S, N>
 {
@@ -0,0 +1,166 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! MVCC snapshots, which are kept registered while they're alive, and which can be exported to,
+//! and imported by, other transactions, so they all see the database as of the same moment
+//!
+//! A coordinator exports its transaction's snapshot, and keeps the transaction open while
+//! background workers import it, before their first query, as `SET TRANSACTION SNAPSHOT` does:
+//!
+//! ```rust,no_run
+//! use pgx::bgworkers::BackgroundWorker;
+//! use pgx::*;
+//!
+//! # fn snapshot_id_from_coordinator() -> String { unimplemented!() }
+//! let id = snapshot_id_from_coordinator();
+//! BackgroundWorker::transaction(|| {
+//!     unsafe { pg_sys::XactIsoLevel = pg_sys::XACT_REPEATABLE_READ as i32 };
+//!     PgSnapshot::import(&id);
+//!     // every query sees what the coordinator's transaction saw
+//! });
+//! ```
+use crate::pg_sys;
+use std::ffi::{CStr, CString};
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+/// A snapshot, which is registered with the current resource owner until it's dropped, so it
+/// keeps the rows it sees from being vacuumed away
+///
+/// A snapshot mustn't outlive the transaction it was taken in.
+pub struct PgSnapshot {
+    snapshot: NonNull<pg_sys::SnapshotData>,
+}
+
+impl PgSnapshot {
+    /// The transaction's snapshot, which is the statement's, unless the transaction is
+    /// `REPEATABLE READ` or `SERIALIZABLE`, in which case it's the same for every statement
+    pub fn transaction() -> Self {
+        unsafe { PgSnapshot::from_pg(pg_sys::GetTransactionSnapshot()) }
+    }
+
+    /// A new snapshot, seeing every transaction which has committed by now
+    pub fn latest() -> Self {
+        unsafe { PgSnapshot::from_pg(pg_sys::GetLatestSnapshot()) }
+    }
+
+    /// The snapshot the current query runs with, if there is one
+    pub fn active() -> Option<Self> {
+        unsafe {
+            if pg_sys::ActiveSnapshotSet() {
+                Some(PgSnapshot::from_pg(pg_sys::GetActiveSnapshot()))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Register `snapshot`, which is copied unless it's been copied already
+    ///
+    /// ## Safety
+    ///
+    /// `snapshot` must be a valid, MVCC snapshot
+    pub unsafe fn from_pg(snapshot: pg_sys::Snapshot) -> Self {
+        PgSnapshot {
+            snapshot: NonNull::new(pg_sys::RegisterSnapshot(snapshot))
+                .expect("RegisterSnapshot() returned NULL"),
+        }
+    }
+
+    pub fn as_ptr(&self) -> pg_sys::Snapshot {
+        self.snapshot.as_ptr()
+    }
+
+    /// Every transaction before this one was finished when the snapshot was taken
+    pub fn xmin(&self) -> pg_sys::TransactionId {
+        unsafe { self.snapshot.as_ref().xmin }
+    }
+
+    /// Every transaction from this one on was unfinished when the snapshot was taken
+    pub fn xmax(&self) -> pg_sys::TransactionId {
+        unsafe { self.snapshot.as_ref().xmax }
+    }
+
+    /// The transactions in between `xmin` and `xmax` which were in progress when the snapshot
+    /// was taken
+    pub fn in_progress(&self) -> &[pg_sys::TransactionId] {
+        unsafe {
+            let snapshot = self.snapshot.as_ref();
+            if snapshot.xcnt == 0 {
+                &[]
+            } else {
+                std::slice::from_raw_parts(snapshot.xip, snapshot.xcnt as usize)
+            }
+        }
+    }
+
+    /// Export the snapshot, returning the id another transaction can [import](PgSnapshot::import)
+    /// it by, as `pg_export_snapshot()` does for the transaction's snapshot
+    ///
+    /// The snapshot can be imported until this transaction ends.  Postgres raises an ERROR in a
+    /// subtransaction.
+    pub fn export(&self) -> String {
+        unsafe {
+            let id = pg_sys::ExportSnapshot(self.as_ptr());
+            let exported = CStr::from_ptr(id).to_string_lossy().into_owned();
+            pg_sys::pfree(id as *mut std::os::raw::c_void);
+            exported
+        }
+    }
+
+    /// Make the snapshot the transaction's, by the `id` it was [exported](PgSnapshot::export)
+    /// with, as `SET TRANSACTION SNAPSHOT` does
+    ///
+    /// Postgres raises an ERROR unless this is called before the transaction's first query,
+    /// and the transaction is `REPEATABLE READ` or `SERIALIZABLE`, or if the exporting
+    /// transaction has ended.
+    ///
+    /// ## Panics
+    ///
+    /// If `id` contains a NUL byte
+    pub fn import(id: &str) {
+        let id = CString::new(id).expect("snapshot id contained a null byte");
+        unsafe { pg_sys::ImportSnapshot(id.as_ptr()) }
+    }
+
+    /// Make the snapshot the one queries run with, until the returned guard is dropped
+    pub fn push_active(&self) -> ActiveSnapshot<'_> {
+        unsafe { pg_sys::PushActiveSnapshot(self.as_ptr()) }
+        ActiveSnapshot {
+            _snapshot: PhantomData,
+        }
+    }
+}
+
+impl Clone for PgSnapshot {
+    fn clone(&self) -> Self {
+        // registering it again only counts another reference to it
+        unsafe { PgSnapshot::from_pg(self.as_ptr()) }
+    }
+}
+
+impl Drop for PgSnapshot {
+    fn drop(&mut self) {
+        unsafe { pg_sys::UnregisterSnapshot(self.as_ptr()) }
+    }
+}
+
+/// The active snapshot, pushed by [`PgSnapshot::push_active`], which is popped when this is
+/// dropped
+///
+/// Guards must be dropped in the reverse order they were pushed.
+pub struct ActiveSnapshot<'a> {
+    _snapshot: PhantomData<&'a PgSnapshot>,
+}
+
+impl Drop for ActiveSnapshot<'_> {
+    fn drop(&mut self) {
+        unsafe { pg_sys::PopActiveSnapshot() }
+    }
+}
@@ -9,12 +9,19 @@ Use of this source code is governed by the MIT license that can be found in the
 
 //! Safe access to Postgres' *Server Programming Interface* (SPI).
 
-use crate::{pg_sys, FromDatum, IntoDatum, Json, PgMemoryContexts, PgOid};
+use crate::{
+    pg_sys, pg_try, FromDatum, IntoDatum, Json, PgList, PgMemoryContexts, PgOid, PgSqlErrorCode,
+    PgTupleDesc, RefCursor,
+};
 use enum_primitive_derive::*;
 use num_traits::FromPrimitive;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::ffi::CStr;
 use std::fmt::Debug;
+use std::marker::PhantomData;
 use std::ops::{Index, IndexMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[derive(Debug, Primitive)]
 pub enum SpiOk {
@@ -58,18 +65,139 @@ pub enum SpiError {
     RelNotFound = 13,
 }
 
+/// A Postgres ERROR raised by a query run through one of the `try_` SPI functions, such as
+/// [`Spi::try_run`] or [`SpiClient::try_select`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpiSqlError {
+    pub(crate) sqlerrcode: i32,
+    /// The five-character SQLSTATE of the error, such as `"22012"`
+    pub sqlstate: String,
+    pub message: String,
+    pub detail: Option<String>,
+    pub hint: Option<String>,
+    /// The context lines of the error, such as the statement or function it was raised in
+    pub context: Option<String>,
+    /// The 1-based character position in the query at which the error occurred, if Postgres
+    /// reported one
+    pub position: Option<usize>,
+}
+
+/// An ERROR raised by a script run through [`SpiClient::run_script`], along with where in the
+/// script it occurred
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpiScriptError {
+    /// The 0-based index of the statement that raised the error, or `None` if the script couldn't
+    /// be parsed
+    pub statement: Option<usize>,
+    /// The 1-based line of the script at which the error occurred, or at which the statement
+    /// begins if Postgres didn't report a position
+    pub line: usize,
+    /// The 1-based column, in characters, that goes with `line`
+    pub column: usize,
+    pub error: SpiSqlError,
+}
+
+/// Why the work run by [`Spi::with_timeout`] didn't finish
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpiTimeoutError {
+    /// The timeout elapsed before the work finished
+    TimedOut(std::time::Duration),
+    /// The work was canceled some other way, such as by `pg_cancel_backend()`
+    Canceled,
+    /// The work raised some other ERROR
+    Sql(SpiSqlError),
+}
+
 pub struct Spi;
 
-pub struct SpiClient;
+/// A connection to SPI, made by [`Spi::connect`] and friends
+///
+/// Everything SPI returns -- [`SpiTupleTable`]s, their rows, [`PreparedStatement`]s, and
+/// [`SpiCursor`]s -- borrows the connection it came from, through the `'conn` lifetime.  Since the
+/// closure given to [`Spi::connect`] must accept a connection of any lifetime, none of them can
+/// outlive the closure and be read after SPI has freed them.
+///
+/// ```rust,compile_fail
+/// use pgx::*;
+///
+/// let mut table = None;
+/// Spi::connect(|client| {
+///     // error: the table can't escape the closure
+///     table = Some(client.select("SELECT 1", None, None));
+///     Ok(Some(()))
+/// });
+/// ```
+pub struct SpiClient<'conn> {
+    _connection: PhantomData<&'conn SpiConnection>,
+}
+
+/// a struct to manage our SPI connection lifetime
+struct SpiConnection;
+
+/// How many `SpiConnection`s are open
+static CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+impl SpiConnection {
+    /// Connect to Postgres' SPI system
+    fn connect() -> Self {
+        // connect to SPI
+        Spi::check_status(unsafe { pg_sys::SPI_connect() });
+        CONNECTIONS.fetch_add(1, Ordering::Relaxed);
+        SpiConnection
+    }
+
+    fn client(&self) -> SpiClient<'_> {
+        SpiClient {
+            _connection: PhantomData,
+        }
+    }
+}
+
+impl Drop for SpiConnection {
+    /// when SpiConnection is dropped, we make sure to disconnect from SPI
+    fn drop(&mut self) {
+        // disconnect from SPI
+        CONNECTIONS.fetch_sub(1, Ordering::Relaxed);
+        Spi::check_status(unsafe { pg_sys::SPI_finish() });
+    }
+}
 
 #[derive(Debug)]
-pub struct SpiTupleTable {
+pub struct SpiTupleTable<'conn> {
     #[allow(dead_code)]
     status_code: SpiOk,
     table: *mut pg_sys::SPITupleTable,
     size: usize,
     pub tupdesc: Option<pg_sys::TupleDesc>,
     current: isize,
+    _connection: PhantomData<&'conn SpiConnection>,
+}
+
+/// The name, type, and nullability of a column of an SPI result, from its `TupleDesc`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpiColumn {
+    pub name: String,
+    pub type_oid: pg_sys::Oid,
+    pub typmod: i32,
+    /// Whether the column is known to be `NOT NULL`.  Postgres rarely propagates this from
+    /// tables into query results, so `false` doesn't mean the column has NULLs.
+    pub not_null: bool,
+}
+
+impl SpiColumn {
+    fn from_tupdesc(tupdesc: pg_sys::TupleDesc) -> Vec<SpiColumn> {
+        let tupdesc = unsafe { PgTupleDesc::from_pg_copy(tupdesc) };
+        tupdesc
+            .iter()
+            .filter(|attr| !attr.is_dropped())
+            .map(|attr| SpiColumn {
+                name: attr.name().to_string(),
+                type_oid: attr.atttypid,
+                typmod: attr.type_mod(),
+                not_null: attr.attnotnull,
+            })
+            .collect()
+    }
 }
 
 /// Represents a single `pg_sys::Datum` inside a `SpiHeapTupleData`
@@ -79,9 +207,10 @@ pub struct SpiHeapTupleDataEntry {
 }
 
 /// Represents the set of `pg_sys::Datum`s in a `pg_sys::HeapTuple`
-pub struct SpiHeapTupleData {
+pub struct SpiHeapTupleData<'conn> {
     tupdesc: pg_sys::TupleDesc,
     entries: HashMap<usize, SpiHeapTupleDataEntry>,
+    _connection: PhantomData<&'conn SpiConnection>,
 }
 
 impl Spi {
@@ -161,6 +290,38 @@ impl Spi {
         .unwrap()
     }
 
+    /// Get the first row of `query`'s result as a tuple (or any other [`FromSpiRow`] type), or
+    /// `None` if it returned no rows
+    ///
+    /// Each element of the tuple is read from the next column, and must be an `Option` if that
+    /// column can be NULL.  The elements must own their values, as SPI's memory is freed
+    /// before this returns.
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// let row = Spi::get::<(i32, String, Option<i64>, bool)>(
+    ///     "SELECT 1, 'two', NULL::bigint, true",
+    ///     None,
+    /// );
+    /// assert_eq!(row, Ok(Some((1, "two".to_string(), None, true))));
+    /// ```
+    pub fn get<R: FromSpiRow>(
+        query: &str,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+    ) -> std::result::Result<Option<R>, FromSpiRowError> {
+        let mut result = None;
+        Spi::connect(|client| {
+            result = client
+                .select(query, Some(1), args)
+                .first()
+                .get_heap_tuple()
+                .map(|row| R::from_spi_row(&row));
+            Ok(Some(()))
+        });
+        result.transpose()
+    }
+
     /// just run an arbitrary SQL statement.
     ///
     /// ## Safety
@@ -187,8 +348,95 @@ impl Spi {
         .unwrap()
     }
 
+    /// just run an arbitrary SQL statement, returning the ERROR it raised, if any, instead of
+    /// aborting the transaction
+    ///
+    /// The statement runs in a subtransaction, so an ERROR undoes only what the statement did.
+    pub fn try_run(query: &str) -> std::result::Result<(), SpiSqlError> {
+        trap_sql_error(|| Spi::run(query))
+    }
+
+    /// Like [`Spi::get_one`], but returns the ERROR the query raised, if any, instead of aborting
+    /// the transaction
+    pub fn try_get_one<A: FromDatum + IntoDatum>(
+        query: &str,
+    ) -> std::result::Result<Option<A>, SpiSqlError> {
+        trap_sql_error(|| Spi::get_one(query))
+    }
+
+    /// Like [`Spi::get_one_with_args`], but returns the ERROR the query raised, if any, instead of
+    /// aborting the transaction
+    pub fn try_get_one_with_args<A: FromDatum + IntoDatum>(
+        query: &str,
+        args: Vec<(PgOid, Option<pg_sys::Datum>)>,
+    ) -> std::result::Result<Option<A>, SpiSqlError> {
+        trap_sql_error(|| Spi::get_one_with_args(query, args))
+    }
+
+    /// Run `query` and copy its results out of SPI, into the `CurrentMemoryContext`, as an
+    /// [`OwnedSpiResult`] that can be read after SPI has disconnected
+    pub fn select_owned(
+        query: &str,
+        limit: Option<i64>,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+    ) -> OwnedSpiResult {
+        let parent = PgMemoryContexts::For(PgMemoryContexts::CurrentMemoryContext.value());
+        let mut result = None;
+        Spi::connect(|client| {
+            result = Some(client.select(query, limit, args).to_owned_in(&parent));
+            Ok(Some(()))
+        });
+        result.unwrap()
+    }
+
+    /// execute SPI commands via the provided `SpiClient`, canceling them if they haven't finished
+    /// once `timeout` has elapsed
+    ///
+    /// The timeout covers everything the closure does, including waiting for locks.  As with
+    /// [`Spi::subtransaction`], the closure runs in a subtransaction, which is rolled back if it's
+    /// canceled or raises an ERROR, so the outer transaction can carry on.
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    /// use std::time::Duration;
+    ///
+    /// let result = Spi::with_timeout(Duration::from_secs(1), |mut client| {
+    ///     client.update("LOCK TABLE dogs IN EXCLUSIVE MODE", None, None);
+    /// });
+    /// if let Err(SpiTimeoutError::TimedOut(_)) = result {
+    ///     warning!("someone else is using the dogs");
+    /// }
+    /// ```
+    ///
+    /// Panics if called from within another `Spi::with_timeout()`.
+    pub fn with_timeout<R, F: for<'conn> FnOnce(SpiClient<'conn>) -> R + std::panic::UnwindSafe>(
+        timeout: std::time::Duration,
+        f: F,
+    ) -> std::result::Result<R, SpiTimeoutError> {
+        let guard = SpiTimeoutGuard::enable(timeout);
+        let result = trap_sql_error(move || {
+            let mut result = None;
+            Spi::connect(|client| {
+                result = Some(f(client));
+                Ok(Some(()))
+            });
+            result.unwrap()
+        });
+        let timed_out = guard.disable();
+
+        result.map_err(|error| {
+            if !error.is(PgSqlErrorCode::ERRCODE_QUERY_CANCELED) {
+                SpiTimeoutError::Sql(error)
+            } else if timed_out {
+                SpiTimeoutError::TimedOut(timeout)
+            } else {
+                SpiTimeoutError::Canceled
+            }
+        })
+    }
+
     /// execute SPI commands via the provided `SpiClient`
-    pub fn execute<F: FnOnce(SpiClient) + std::panic::UnwindSafe>(f: F) {
+    pub fn execute<F: for<'conn> FnOnce(SpiClient<'conn>) + std::panic::UnwindSafe>(f: F) {
         Spi::connect(|client| {
             f(client);
             Ok(Some(()))
@@ -199,40 +447,21 @@ impl Spi {
     /// automatically copied into the `CurrentMemoryContext` at the time of this function call
     pub fn connect<
         R: FromDatum + IntoDatum,
-        F: FnOnce(SpiClient) -> std::result::Result<Option<R>, SpiError>,
+        F: for<'conn> FnOnce(SpiClient<'conn>) -> std::result::Result<Option<R>, SpiError>,
     >(
         f: F,
     ) -> Option<R> {
         let outer_memory_context =
             PgMemoryContexts::For(PgMemoryContexts::CurrentMemoryContext.value());
 
-        /// a struct to manage our SPI connection lifetime
-        struct SpiConnection;
-        impl SpiConnection {
-            /// Connect to Postgres' SPI system
-            fn connect() -> Self {
-                // connect to SPI
-                Spi::check_status(unsafe { pg_sys::SPI_connect() });
-                SpiConnection
-            }
-        }
-
-        impl Drop for SpiConnection {
-            /// when SpiConnection is dropped, we make sure to disconnect from SPI
-            fn drop(&mut self) {
-                // disconnect from SPI
-                Spi::check_status(unsafe { pg_sys::SPI_finish() });
-            }
-        }
-
         // connect to SPI
-        let _connection = SpiConnection::connect();
+        let connection = SpiConnection::connect();
 
         // run the provided closure within the memory context that SPI_connect()
         // just put us un.  We'll disconnect from SPI when the closure is finished.
         // If there's a panic or elog(ERROR), we don't care about also disconnecting from
         // SPI b/c Postgres will do that for us automatically
-        match f(SpiClient) {
+        match f(connection.client()) {
             // copy the result to the outer memory context we saved above
             Ok(result) => {
                 // we need to copy the resulting Datum into the outer memory context
@@ -266,6 +495,80 @@ impl Spi {
         }
     }
 
+    /// execute SPI commands via the provided `SpiClient` within a subtransaction (a savepoint),
+    /// returning the closure's result
+    ///
+    /// The subtransaction is committed if the closure returns `Ok`.  If it returns `Err`, panics,
+    /// or raises a Postgres ERROR, the subtransaction is rolled back, undoing everything the
+    /// closure did, while the outer transaction carries on.  A panic or ERROR is then rethrown.
+    ///
+    /// The returned value must not reference memory allocated by SPI, as it's freed when the
+    /// closure returns.
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// let result: Result<(), &str> = Spi::subtransaction(|mut client| {
+    ///     client.update("INSERT INTO dogs (name) VALUES ('Nami')", None, None);
+    ///     Err("changed my mind")
+    /// });
+    /// assert!(result.is_err()); // and there's no new row in `dogs`
+    /// ```
+    pub fn subtransaction<
+        R,
+        E,
+        F: for<'conn> FnOnce(SpiClient<'conn>) -> std::result::Result<R, E>
+            + std::panic::UnwindSafe
+            + std::panic::RefUnwindSafe,
+    >(
+        f: F,
+    ) -> std::result::Result<R, E> {
+        unsafe {
+            let outer_memory_context = pg_sys::CurrentMemoryContext;
+            let outer_resource_owner = pg_sys::CurrentResourceOwner;
+
+            pg_sys::BeginInternalSubTransaction(std::ptr::null());
+            // BeginInternalSubTransaction() switches to the subtransaction's memory context, but
+            // we want the closure to allocate, and return its result, where the caller is
+            pg_sys::CurrentMemoryContext = outer_memory_context;
+
+            let restore = move || {
+                pg_sys::CurrentMemoryContext = outer_memory_context;
+                pg_sys::CurrentResourceOwner = outer_resource_owner;
+            };
+
+            let result = pg_try(move || {
+                let connection = SpiConnection::connect();
+                f(connection.client())
+            })
+            .unwrap_or_rethrow(|| {
+                // Postgres disconnects from SPI as part of the rollback
+                pg_sys::RollbackAndReleaseCurrentSubTransaction();
+                restore();
+            });
+
+            match result {
+                Ok(_) => pg_sys::ReleaseCurrentSubTransaction(),
+                Err(_) => pg_sys::RollbackAndReleaseCurrentSubTransaction(),
+            }
+            restore();
+
+            result
+        }
+    }
+
+    /// free every statement prepared by [`SpiClient::prepare_cached`]
+    pub fn clear_plan_cache() {
+        unsafe {
+            PLAN_CACHE = None;
+        }
+    }
+
+    /// Is an SPI connection open, such as within [`Spi::connect`]?
+    pub(crate) fn is_connected() -> bool {
+        CONNECTIONS.load(Ordering::Relaxed) > 0
+    }
+
     pub fn check_status(status_code: i32) -> SpiOk {
         if status_code > 0 {
             let status_enum = SpiOk::from_i32(status_code);
@@ -283,14 +586,201 @@ impl Spi {
     }
 }
 
-impl SpiClient {
+/// Run `f` in a subtransaction, which is committed if `f` returns.  If `f` raises a Postgres
+/// ERROR, the subtransaction is rolled back and the ERROR is returned rather than rethrown.
+///
+/// Rust panics are not trapped; they're rethrown after the rollback.
+pub(crate) fn trap_sql_error<R, F: FnOnce() -> R + std::panic::UnwindSafe>(
+    f: F,
+) -> std::result::Result<R, SpiSqlError> {
+    unsafe {
+        let outer_memory_context = pg_sys::CurrentMemoryContext;
+        let outer_resource_owner = pg_sys::CurrentResourceOwner;
+        let restore = || {
+            pg_sys::CurrentMemoryContext = outer_memory_context;
+            pg_sys::CurrentResourceOwner = outer_resource_owner;
+        };
+
+        pg_sys::BeginInternalSubTransaction(std::ptr::null());
+        pg_sys::CurrentMemoryContext = outer_memory_context;
+
+        match std::panic::catch_unwind(f) {
+            Ok(result) => {
+                pg_sys::ReleaseCurrentSubTransaction();
+                restore();
+                Ok(result)
+            }
+            Err(e) => {
+                // the ERROR lives in `ErrorContext`, so copy it out before it's flushed
+                pg_sys::CurrentMemoryContext = outer_memory_context;
+                let error = if e.is::<pg_sys::JumpContext>() {
+                    let edata = pg_sys::CopyErrorData();
+                    pg_sys::FlushErrorState();
+                    Some(SpiSqlError::from_error_data(edata))
+                } else {
+                    None
+                };
+
+                // Postgres disconnects any SPI connections made in the subtransaction as part
+                // of the rollback
+                pg_sys::RollbackAndReleaseCurrentSubTransaction();
+                restore();
+
+                match error {
+                    Some(error) => Err(error),
+                    None => std::panic::resume_unwind(e),
+                }
+            }
+        }
+    }
+}
+
+/// Assert that `f` raises an ERROR whose SQLSTATE is `code`, such as
+/// `PgSqlErrorCode::ERRCODE_DIVISION_BY_ZERO`, or a SQLSTATE literal, such as `"22012"`, returning
+/// the ERROR, so its message, detail, and hint can be checked too
+///
+/// `f` runs in a subtransaction, which is rolled back when it raises the ERROR, so the test goes
+/// on after it.  Unlike a message, a SQLSTATE is the same across Postgres versions and locales.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// let error = assert_pg_error!(PgSqlErrorCode::ERRCODE_DIVISION_BY_ZERO, || {
+///     Spi::get_one::<i32>("SELECT 1 / 0")
+/// });
+/// assert_eq!(error.hint, None);
+/// ```
+#[macro_export]
+macro_rules! assert_pg_error {
+    ($sqlstate:literal, $f:expr) => {
+        $crate::spi::expect_sql_error($sqlstate, $f)
+    };
+    ($code:expr, $f:expr) => {
+        $crate::spi::expect_sql_error(&$crate::PgSqlErrorCode::sqlstate(&$code), $f)
+    };
+}
+
+#[doc(hidden)]
+pub fn expect_sql_error<R: std::fmt::Debug, F: FnOnce() -> R + std::panic::UnwindSafe>(
+    sqlstate: &str,
+    f: F,
+) -> SpiSqlError {
+    match trap_sql_error(f) {
+        Err(error) if error.sqlstate == sqlstate => error,
+        Err(error) => panic!(
+            "expected an ERROR with SQLSTATE {}, but got: {}",
+            sqlstate, error
+        ),
+        Ok(result) => panic!(
+            "expected an ERROR with SQLSTATE {}, but got: {:?}",
+            sqlstate, result
+        ),
+    }
+}
+
+static mut SPI_TIMEOUT_ID: Option<pg_sys::TimeoutId> = None;
+static mut SPI_TIMEOUT_ACTIVE: bool = false;
+
+/// Arms the timeout for [`Spi::with_timeout`], disarming it when dropped, even if the work
+/// panicked
+struct SpiTimeoutGuard {
+    id: pg_sys::TimeoutId,
+}
+
+impl SpiTimeoutGuard {
+    fn enable(timeout: std::time::Duration) -> Self {
+        unsafe {
+            if SPI_TIMEOUT_ACTIVE {
+                panic!("Spi::with_timeout() cannot be nested");
+            }
+
+            let id = *SPI_TIMEOUT_ID.get_or_insert_with(|| {
+                pg_sys::RegisterTimeout(pg_sys::TimeoutId_USER_TIMEOUT, Some(spi_timeout_handler))
+            });
+            SPI_TIMEOUT_ACTIVE = true;
+            pg_sys::enable_timeout_after(id, timeout.as_millis().clamp(1, i32::MAX as u128) as i32);
+            SpiTimeoutGuard { id }
+        }
+    }
+
+    /// Disarm the timeout, returning whether it fired
+    fn disable(self) -> bool {
+        // `Drop` does the work
+        unsafe { pg_sys::get_timeout_indicator(self.id, false) }
+    }
+}
+
+impl Drop for SpiTimeoutGuard {
+    fn drop(&mut self) {
+        unsafe {
+            pg_sys::disable_timeout(self.id, false);
+            if pg_sys::get_timeout_indicator(self.id, true) {
+                // the timeout fired after the work's last check for interrupts, so make sure the
+                // cancel it requested doesn't fall on whatever runs next
+                set_query_cancel_pending(false);
+            }
+            SPI_TIMEOUT_ACTIVE = false;
+        }
+    }
+}
+
+/// Runs in a signal handler, so must only do async-signal-safe things
+unsafe extern "C" fn spi_timeout_handler() {
+    set_query_cancel_pending(true);
+    pg_sys::SetLatch(pg_sys::MyLatch);
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11"))]
+unsafe fn set_query_cancel_pending(pending: bool) {
+    pg_sys::QueryCancelPending = pending;
+    if pending {
+        pg_sys::InterruptPending = true;
+    }
+}
+
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+unsafe fn set_query_cancel_pending(pending: bool) {
+    pg_sys::QueryCancelPending = pending as pg_sys::sig_atomic_t;
+    if pending {
+        pg_sys::InterruptPending = 1;
+    }
+}
+
+/// Split `script` into the byte ranges of its statements, without their trailing semicolons
+fn split_script(script: &str) -> Vec<std::ops::Range<usize>> {
+    let src = std::ffi::CString::new(script).expect("script contained a null byte");
+    unsafe {
+        PgList::<pg_sys::RawStmt>::from_pg(pg_sys::pg_parse_query(src.as_ptr()))
+            .iter_ptr()
+            .map(|stmt| {
+                let start = (*stmt).stmt_location as usize;
+                // a length of zero means "the rest of the string"
+                let end = match (*stmt).stmt_len {
+                    0 => script.len(),
+                    len => start + len as usize,
+                };
+                start..end
+            })
+            .collect()
+    }
+}
+
+/// Convert Postgres' 1-based character `position` in `text` to a byte offset
+fn char_to_byte_offset(text: &str, position: usize) -> usize {
+    text.char_indices()
+        .nth(position.saturating_sub(1))
+        .map(|(offset, _)| offset)
+        .unwrap_or_else(|| text.len())
+}
+
+impl<'conn> SpiClient<'conn> {
     /// perform a SELECT statement
     pub fn select(
         &self,
         query: &str,
         limit: Option<i64>,
         args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
-    ) -> SpiTupleTable {
+    ) -> SpiTupleTable<'conn> {
         // Postgres docs say:
         //
         //    It is generally unwise to mix read-only and read-write commands within a single function
@@ -305,28 +795,103 @@ impl SpiClient {
         SpiClient::execute(query, false, limit, args)
     }
 
+    /// perform a SELECT statement in read-only mode
+    ///
+    /// A read-only query runs against the snapshot of the statement that called this function,
+    /// rather than taking a new one, so it doesn't see changes made since that statement started.
+    /// It's cheaper, it's what a `STABLE` function should use, and it's the only kind of query SPI
+    /// allows within a parallel worker.
+    ///
+    /// Postgres raises an ERROR if the query tries to modify the database.
+    pub fn select_read_only(
+        &self,
+        query: &str,
+        limit: Option<i64>,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+    ) -> SpiTupleTable<'conn> {
+        SpiClient::execute(query, true, limit, args)
+    }
+
     /// perform any query (including utility statements) that modify the database in some way
     pub fn update(
         &mut self,
         query: &str,
         limit: Option<i64>,
         args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
-    ) -> SpiTupleTable {
+    ) -> SpiTupleTable<'conn> {
         SpiClient::execute(query, false, limit, args)
     }
 
+    /// Like [`SpiClient::select`], but returns the ERROR the statement raised, if any, instead of
+    /// aborting the transaction
+    pub fn try_select(
+        &self,
+        query: &str,
+        limit: Option<i64>,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+    ) -> std::result::Result<SpiTupleTable<'conn>, SpiSqlError> {
+        trap_sql_error(|| SpiClient::execute(query, false, limit, args))
+    }
+
+    /// Like [`SpiClient::update`], but returns the ERROR the statement raised, if any, instead of
+    /// aborting the transaction
+    ///
+    /// The statement runs in a subtransaction, so an ERROR undoes only what the statement did.
+    pub fn try_update(
+        &mut self,
+        query: &str,
+        limit: Option<i64>,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+    ) -> std::result::Result<SpiTupleTable<'conn>, SpiSqlError> {
+        trap_sql_error(|| SpiClient::execute(query, false, limit, args))
+    }
+
+    /// Run `script`, a sequence of semicolon-separated statements, such as an embedded migration
+    ///
+    /// The statements may be any mix of DDL, DML, and anonymous `DO` blocks, and run one after
+    /// another with each seeing the effects of those before it.  They run together in a
+    /// subtransaction, so if one raises an ERROR, everything the script did is undone and the
+    /// ERROR is returned along with its location in the script.
+    pub fn run_script(&mut self, script: &str) -> std::result::Result<(), SpiScriptError> {
+        let statements = trap_sql_error(|| split_script(script)).map_err(|error| {
+            let offset = error
+                .position
+                .map(|position| char_to_byte_offset(script, position))
+                .unwrap_or(0);
+            SpiScriptError::new(script, None, offset, error)
+        })?;
+
+        let current = std::cell::Cell::new(0);
+        trap_sql_error(std::panic::AssertUnwindSafe(|| {
+            for (idx, range) in statements.iter().enumerate() {
+                current.set(idx);
+                SpiClient::execute(&script[range.clone()], false, None, None);
+            }
+        }))
+        .map_err(|error| {
+            let idx = current.get();
+            let range = statements[idx].clone();
+            let offset = range.start
+                + error
+                    .position
+                    .map(|position| char_to_byte_offset(&script[range], position))
+                    .unwrap_or(0);
+            SpiScriptError::new(script, Some(idx), offset, error)
+        })
+    }
+
     fn execute(
         query: &str,
         read_only: bool,
         limit: Option<i64>,
         args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
-    ) -> SpiTupleTable {
+    ) -> SpiTupleTable<'conn> {
         unsafe {
             pg_sys::SPI_tuptable = std::ptr::null_mut();
         }
 
         let src = std::ffi::CString::new(query).expect("query contained a null byte");
-        let status_code = match args {
+        traced_execute(query, read_only, || match args {
             Some(args) => {
                 let nargs = args.len();
                 let mut argtypes = vec![];
@@ -364,8 +929,799 @@ impl SpiClient {
                 }
             }
             None => unsafe { pg_sys::SPI_execute(src.as_ptr(), read_only, limit.unwrap_or(0)) },
+        })
+    }
+
+    /// `EXPLAIN` a query, as configured by `options`
+    ///
+    /// With [`ExplainOptions::analyze`], the query is actually run, and any changes it makes are
+    /// kept.
+    pub fn explain(&mut self, query: &str, options: ExplainOptions) -> ExplainOutput {
+        let table = SpiClient::execute(
+            &format!("EXPLAIN ({}) {}", options.to_sql(), query),
+            false,
+            None,
+            None,
+        );
+        match options.format {
+            ExplainFormat::Json => ExplainOutput::Json(
+                table
+                    .first()
+                    .get_one::<Json>()
+                    .expect("failed to get json EXPLAIN result"),
+            ),
+            _ => ExplainOutput::Text(
+                table
+                    .map(|row| {
+                        row.by_ordinal(1)
+                            .unwrap()
+                            .value::<String>()
+                            .expect("EXPLAIN returned a NULL line")
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+        }
+    }
+
+    /// open a cursor over the results of `query`, so they can be fetched in batches rather than
+    /// all at once as [`SpiClient::select`] does
+    pub fn open_cursor(
+        &self,
+        query: &str,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+    ) -> SpiCursor<'conn> {
+        self.open_cursor_impl(None, query, args)
+    }
+
+    /// open a cursor, like [`SpiClient::open_cursor`], with the specified `name` rather than a
+    /// generated one
+    ///
+    /// Raises a Postgres ERROR if a cursor with that name is already open.
+    pub fn open_named_cursor(
+        &self,
+        name: &str,
+        query: &str,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+    ) -> SpiCursor<'conn> {
+        self.open_cursor_impl(Some(name), query, args)
+    }
+
+    /// find the open cursor named `name`, such as one passed to this function as a `refcursor`
+    ///
+    /// As with any other `SpiCursor`, the cursor is closed when the returned `SpiCursor` is
+    /// dropped, unless it's given back with [`SpiCursor::into_refcursor`].
+    pub fn find_cursor(&self, name: &str) -> Option<SpiCursor<'conn>> {
+        let name = std::ffi::CString::new(name).expect("cursor name contained a null byte");
+        let portal = unsafe { pg_sys::SPI_cursor_find(name.as_ptr()) };
+        if portal.is_null() {
+            None
+        } else {
+            Some(SpiCursor::new(portal))
+        }
+    }
+
+    fn open_cursor_impl(
+        &self,
+        name: Option<&str>,
+        query: &str,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+    ) -> SpiCursor<'conn> {
+        let name = name
+            .map(|name| std::ffi::CString::new(name).expect("cursor name contained a null byte"));
+        let src = std::ffi::CString::new(query).expect("query contained a null byte");
+        let args = args.unwrap_or_default();
+        let nargs = args.len();
+        let mut argtypes = args.iter().map(|(oid, _)| oid.value()).collect::<Vec<_>>();
+        let (mut datums, nulls) =
+            args_to_datums_and_nulls(args.into_iter().map(|(_, datum)| datum).collect());
+
+        let portal = unsafe {
+            pg_sys::SPI_cursor_open_with_args(
+                name.as_ref().map_or(std::ptr::null(), |name| name.as_ptr()),
+                src.as_ptr(),
+                nargs as i32,
+                argtypes.as_mut_ptr(),
+                datums.as_mut_ptr(),
+                nulls.as_ptr(),
+                false,
+                0,
+            )
+        };
+        SpiCursor::new(portal)
+    }
+
+    /// prepare a statement, with parameters of the specified types, that can be executed
+    /// repeatedly without re-parsing or re-planning it
+    ///
+    /// The statement is freed when this `SpiClient` disconnects, unless [`PreparedStatement::keep`]
+    /// is used to retain it
+    pub fn prepare(&self, query: &str, args: &[PgOid]) -> PreparedStatement<'conn> {
+        self.prepare_with_mode(query, args, SpiPlanMode::Auto)
+    }
+
+    /// prepare a statement, like [`SpiClient::prepare`], choosing how its plan is cached
+    pub fn prepare_with_mode(
+        &self,
+        query: &str,
+        args: &[PgOid],
+        mode: SpiPlanMode,
+    ) -> PreparedStatement<'conn> {
+        let src = std::ffi::CString::new(query).expect("query contained a null byte");
+        let mut argtypes = args.iter().map(|oid| oid.value()).collect::<Vec<_>>();
+
+        let plan = unsafe {
+            pg_sys::SPI_prepare_cursor(
+                src.as_ptr(),
+                argtypes.len() as i32,
+                argtypes.as_mut_ptr(),
+                mode.cursor_options(),
+            )
+        };
+        if plan.is_null() {
+            Spi::check_status(unsafe { pg_sys::SPI_result });
+        }
+
+        PreparedStatement {
+            plan,
+            read_only: false,
+            _connection: PhantomData,
+        }
+    }
+
+    /// prepare a statement, like [`SpiClient::prepare_with_mode`], or reuse the statement
+    /// prepared by an earlier call with the same query, argument types, and mode
+    ///
+    /// Cached statements are kept for the life of the backend (or until [`Spi::clear_plan_cache`]),
+    /// and Postgres re-plans them as needed when the objects they reference change.
+    pub fn prepare_cached(
+        &self,
+        query: &str,
+        args: &[PgOid],
+        mode: SpiPlanMode,
+    ) -> PreparedStatement<'conn> {
+        let key = (
+            query.to_string(),
+            args.iter().map(|oid| oid.value()).collect::<Vec<_>>(),
+            mode,
+        );
+        let cache = unsafe { PLAN_CACHE.get_or_insert_with(HashMap::default) };
+        cache
+            .entry(key)
+            .or_insert_with(|| self.prepare_with_mode(query, args, mode).keep())
+            .with_client(self)
+    }
+
+    /// insert rows into `table`, which is named as it'd appear in SQL, setting the specified
+    /// `columns` from each row's values in turn
+    ///
+    /// Rows are buffered and inserted in batches, each with a single multi-row `INSERT` statement
+    /// whose plan is reused from one batch to the next.  The final, partial batch is inserted by
+    /// [`BulkInsert::finish`], or when the `BulkInsert` is dropped.
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// Spi::execute(|mut client| {
+    ///     let mut dogs = client.copy_in::<(&str, i32)>("dogs", &["name", "scritches"]);
+    ///     dogs.insert(("Nami", 0));
+    ///     dogs.insert(("Brandy", 42));
+    ///     assert_eq!(dogs.finish(), 2);
+    /// });
+    /// ```
+    pub fn copy_in<R: IntoSpiRow>(
+        &mut self,
+        table: &str,
+        columns: &[&str],
+    ) -> BulkInsert<'conn, R> {
+        let types = R::type_oids();
+        if columns.len() != types.len() {
+            panic!(
+                "copy_in of {} columns, but rows have {} values",
+                columns.len(),
+                types.len()
+            );
+        }
+
+        BulkInsert {
+            table: table.to_string(),
+            columns: columns
+                .iter()
+                .map(|column| crate::sql::quote_identifier(column))
+                .collect(),
+            types,
+            batch_size: DEFAULT_BULK_INSERT_BATCH_SIZE.min(MAX_STATEMENT_PARAMETERS / types.len()),
+            values: vec![],
+            nrows: 0,
+            plan: None,
+            memory_context: PgMemoryContexts::new("BulkInsert"),
+            inserted: 0,
+            _row: PhantomData,
+        }
+    }
+}
+
+/// How Postgres chooses between a generic plan and custom plans when executing a
+/// [`PreparedStatement`].  This is the per-statement equivalent of the `plan_cache_mode` GUC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpiPlanMode {
+    /// Use custom plans for the first few executions, then switch to a generic plan if it isn't
+    /// much more expensive than they were.
+    Auto,
+    /// Always use a generic plan, made once without regard to the argument values.
+    Generic,
+    /// Always make a custom plan for the argument values of each execution.
+    Custom,
+}
+
+impl SpiPlanMode {
+    fn cursor_options(&self) -> i32 {
+        (match self {
+            SpiPlanMode::Auto => 0,
+            SpiPlanMode::Generic => pg_sys::CURSOR_OPT_GENERIC_PLAN,
+            SpiPlanMode::Custom => pg_sys::CURSOR_OPT_CUSTOM_PLAN,
+        }) as i32
+    }
+}
+
+/// The statements prepared by [`SpiClient::prepare_cached`], for this backend
+static mut PLAN_CACHE: Option<
+    HashMap<(String, Vec<pg_sys::Oid>, SpiPlanMode), OwnedPreparedStatement>,
+> = None;
+
+/// Run an SPI statement, via `execute`, within a `tracing` span describing it, recording how many
+/// rows it processed and how long it took
+///
+/// The span is at the `DEBUG` level, so a subscriber can capture the timing of the statements an
+/// extension runs by enabling it for `pgx::spi`.
+fn traced_execute<'conn, F: FnOnce() -> i32>(
+    query: &str,
+    read_only: bool,
+    execute: F,
+) -> SpiTupleTable<'conn> {
+    let span = tracing::debug_span!(
+        "spi_execute",
+        query,
+        read_only,
+        rows = tracing::field::Empty,
+        elapsed_us = tracing::field::Empty,
+    );
+    let _entered = span.enter();
+
+    let start = std::time::Instant::now();
+    let table = SpiTupleTable::from_status(execute());
+    span.record("rows", &table.len());
+    span.record("elapsed_us", &(start.elapsed().as_micros() as u64));
+    table
+}
+
+/// The output format of [`SpiClient::explain`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExplainFormat {
+    Text,
+    Json,
+    Xml,
+    Yaml,
+}
+
+/// Options for [`SpiClient::explain`], corresponding to those of SQL's `EXPLAIN`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExplainOptions {
+    /// Run the query, and report the actual row counts and run time of each plan node
+    pub analyze: bool,
+    /// Report buffer usage.  Before Postgres 13, this requires `analyze`
+    pub buffers: bool,
+    /// Report additional detail, such as each node's output columns
+    pub verbose: bool,
+    pub format: ExplainFormat,
+}
+
+impl Default for ExplainOptions {
+    fn default() -> Self {
+        ExplainOptions {
+            analyze: false,
+            buffers: false,
+            verbose: false,
+            format: ExplainFormat::Json,
+        }
+    }
+}
+
+impl ExplainOptions {
+    fn to_sql(&self) -> String {
+        format!(
+            "ANALYZE {}, BUFFERS {}, VERBOSE {}, FORMAT {}",
+            self.analyze,
+            self.buffers,
+            self.verbose,
+            match self.format {
+                ExplainFormat::Text => "TEXT",
+                ExplainFormat::Json => "JSON",
+                ExplainFormat::Xml => "XML",
+                ExplainFormat::Yaml => "YAML",
+            }
+        )
+    }
+}
+
+/// The output of [`SpiClient::explain`]
+#[derive(Debug)]
+pub enum ExplainOutput {
+    /// The plan, from [`ExplainFormat::Json`]
+    Json(Json),
+    /// The plan, from any other [`ExplainFormat`]
+    Text(String),
+}
+
+/// Convert argument values into the `Values` and `Nulls` arrays expected by `SPI_execute_plan()`
+/// and friends
+fn args_to_datums_and_nulls(
+    args: Vec<Option<pg_sys::Datum>>,
+) -> (Vec<pg_sys::Datum>, Vec<std::os::raw::c_char>) {
+    let mut datums = vec![];
+    let mut nulls = vec![];
+    for datum in args {
+        match datum {
+            Some(datum) => {
+                // ' ' here means that the datum is not null
+                datums.push(datum);
+                nulls.push(' ' as std::os::raw::c_char);
+            }
+
+            None => {
+                // 'n' here means that the datum is null
+                datums.push(0);
+                nulls.push('n' as std::os::raw::c_char);
+            }
+        }
+    }
+    (datums, nulls)
+}
+
+/// A statement prepared by [`SpiClient::prepare`], valid for as long as the `SpiClient` that
+/// prepared it
+pub struct PreparedStatement<'conn> {
+    plan: pg_sys::SPIPlanPtr,
+    read_only: bool,
+    _connection: PhantomData<&'conn SpiConnection>,
+}
+
+/// A [`PreparedStatement`] that has been retained with [`PreparedStatement::keep`], and can be
+/// executed from within any later SPI connection by borrowing it with
+/// [`OwnedPreparedStatement::with_client`]
+///
+/// The statement is freed when this is dropped
+pub struct OwnedPreparedStatement {
+    plan: pg_sys::SPIPlanPtr,
+    read_only: bool,
+}
+
+impl<'conn> PreparedStatement<'conn> {
+    /// The columns the statement returns, without executing it, or none if it doesn't return rows
+    pub fn columns(&self) -> Vec<SpiColumn> {
+        unsafe {
+            // like a multi-statement query, the last statement determines the result
+            let sources = PgList::<pg_sys::CachedPlanSource>::from_pg(
+                pg_sys::SPI_plan_get_plan_sources(self.plan),
+            );
+            match sources.tail() {
+                Some(source) if !(*source).resultDesc.is_null() => {
+                    SpiColumn::from_tupdesc((*source).resultDesc)
+                }
+                _ => Vec::new(),
+            }
+        }
+    }
+
+    /// execute this statement, and open cursors over it, in read-only mode, as
+    /// [`SpiClient::select_read_only`] does
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// execute this statement with the provided argument values, which must match, in number
+    /// and type, the argument types the statement was prepared with
+    ///
+    /// The statement runs in read/write mode, unless [`PreparedStatement::read_only`] was set
+    pub fn execute(
+        &self,
+        limit: Option<i64>,
+        args: Vec<Option<pg_sys::Datum>>,
+    ) -> SpiTupleTable<'conn> {
+        let nargs = self.nargs();
+        if args.len() != nargs {
+            panic!(
+                "prepared statement expects {} arguments, but {} were provided",
+                nargs,
+                args.len()
+            );
+        }
+
+        unsafe {
+            pg_sys::SPI_tuptable = std::ptr::null_mut();
+        }
+
+        let (mut datums, nulls) = args_to_datums_and_nulls(args);
+        traced_execute("<prepared statement>", self.read_only, || unsafe {
+            pg_sys::SPI_execute_plan(
+                self.plan,
+                datums.as_mut_ptr(),
+                nulls.as_ptr(),
+                self.read_only,
+                limit.unwrap_or(0),
+            )
+        })
+    }
+
+    /// open a cursor over the results of this statement, executed with the provided argument
+    /// values, which must match the argument types the statement was prepared with
+    pub fn open_cursor(&self, args: Vec<Option<pg_sys::Datum>>) -> SpiCursor<'conn> {
+        let nargs = self.nargs();
+        if args.len() != nargs {
+            panic!(
+                "prepared statement expects {} arguments, but {} were provided",
+                nargs,
+                args.len()
+            );
+        }
+
+        let (mut datums, nulls) = args_to_datums_and_nulls(args);
+        let portal = unsafe {
+            pg_sys::SPI_cursor_open(
+                std::ptr::null(),
+                self.plan,
+                datums.as_mut_ptr(),
+                nulls.as_ptr(),
+                self.read_only,
+            )
+        };
+        SpiCursor::new(portal)
+    }
+
+    /// the number of arguments this statement was prepared with
+    pub fn nargs(&self) -> usize {
+        unsafe { pg_sys::SPI_getargcount(self.plan) as usize }
+    }
+
+    /// the type of each argument this statement was prepared with
+    pub fn arg_types(&self) -> Vec<PgOid> {
+        (0..self.nargs())
+            .map(|i| PgOid::from(unsafe { pg_sys::SPI_getargtypeid(self.plan, i as i32) }))
+            .collect()
+    }
+
+    /// retain this statement beyond the current SPI connection, such as in a `static` or a
+    /// function's `fn_extra`, so that it needn't be prepared again on every call
+    pub fn keep(self) -> OwnedPreparedStatement {
+        Spi::check_status(unsafe { pg_sys::SPI_keepplan(self.plan) });
+        OwnedPreparedStatement {
+            plan: self.plan,
+            read_only: self.read_only,
+        }
+    }
+
+    pub fn as_ptr(&self) -> pg_sys::SPIPlanPtr {
+        self.plan
+    }
+}
+
+impl OwnedPreparedStatement {
+    /// borrow this statement for use with `client`, such that the results of executing it are
+    /// bound to `client`'s connection
+    pub fn with_client<'conn>(&self, _client: &SpiClient<'conn>) -> PreparedStatement<'conn> {
+        PreparedStatement {
+            plan: self.plan,
+            read_only: self.read_only,
+            _connection: PhantomData,
+        }
+    }
+
+    pub fn as_ptr(&self) -> pg_sys::SPIPlanPtr {
+        self.plan
+    }
+}
+
+impl Drop for OwnedPreparedStatement {
+    fn drop(&mut self) {
+        unsafe {
+            pg_sys::SPI_freeplan(self.plan);
+        }
+    }
+}
+
+/// The default number of rows an [`SpiCursor`] fetches at a time while iterating.
+pub const DEFAULT_CURSOR_BATCH_SIZE: i64 = 1000;
+
+/// A cursor opened with [`SpiClient::open_cursor`] or [`PreparedStatement::open_cursor`], whose
+/// rows are fetched on demand
+///
+/// Iterating an `SpiCursor` fetches rows in batches of [`SpiCursor::batch_size`], and frees each
+/// batch when fetching the next.  Any pass-by-reference values taken from a row must be copied
+/// (ie, into an owned Rust type) before the iterator moves on to the next batch.
+///
+/// The cursor is closed when this is dropped.
+pub struct SpiCursor<'conn> {
+    portal: pg_sys::Portal,
+    batch_size: i64,
+    batch: Option<SpiTupleTable<'conn>>,
+    _connection: PhantomData<&'conn SpiConnection>,
+}
+
+impl<'conn> SpiCursor<'conn> {
+    fn new(portal: pg_sys::Portal) -> Self {
+        if portal.is_null() {
+            Spi::check_status(unsafe { pg_sys::SPI_result });
+        }
+
+        SpiCursor {
+            portal,
+            batch_size: DEFAULT_CURSOR_BATCH_SIZE,
+            batch: None,
+            _connection: PhantomData,
+        }
+    }
+
+    /// set the number of rows fetched at a time while iterating
+    pub fn batch_size(mut self, batch_size: i64) -> Self {
+        assert!(batch_size > 0, "cursor batch size must be positive");
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// fetch up to the next `count` rows
+    ///
+    /// Unlike iterating, the returned `SpiTupleTable` is not freed until SPI disconnects.
+    pub fn fetch(&mut self, count: i64) -> SpiTupleTable<'conn> {
+        unsafe {
+            pg_sys::SPI_tuptable = std::ptr::null_mut();
+            pg_sys::SPI_cursor_fetch(self.portal, true, count);
+        }
+        SpiTupleTable::from_status(pg_sys::SPI_OK_FETCH as i32)
+    }
+
+    /// the name of the underlying portal, which SQL can refer to, such as with `FETCH`
+    pub fn name(&self) -> &str {
+        unsafe { std::ffi::CStr::from_ptr((*self.portal).name) }
+            .to_str()
+            .expect("cursor name is not valid UTF8")
+    }
+
+    pub fn as_ptr(&self) -> pg_sys::Portal {
+        self.portal
+    }
+
+    /// leave the cursor open, returning its name so it can be returned from a function as a
+    /// `refcursor`, for the caller to `FETCH` from
+    ///
+    /// The cursor stays open until the caller closes it or the transaction ends.  Any rows already
+    /// fetched while iterating this `SpiCursor` won't be fetched again.
+    pub fn into_refcursor(mut self) -> RefCursor {
+        let refcursor = RefCursor(self.name().to_string());
+        self.free_batch();
+        // don't close the cursor
+        std::mem::forget(self);
+        refcursor
+    }
+
+    /// free the current batch, if any
+    fn free_batch(&mut self) {
+        if let Some(batch) = self.batch.take() {
+            if !batch.table.is_null() {
+                unsafe { pg_sys::SPI_freetuptable(batch.table) }
+            }
+        }
+    }
+}
+
+impl<'conn> Iterator for SpiCursor<'conn> {
+    type Item = SpiHeapTupleData<'conn>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(row) = self.batch.as_mut().and_then(|batch| batch.next()) {
+            return Some(row);
+        }
+
+        self.free_batch();
+        let batch = self.fetch(self.batch_size);
+        if batch.is_empty() {
+            return None;
+        }
+        self.batch = Some(batch);
+        self.batch.as_mut().and_then(|batch| batch.next())
+    }
+}
+
+impl<'conn> Drop for SpiCursor<'conn> {
+    fn drop(&mut self) {
+        self.free_batch();
+        unsafe {
+            // if the transaction is aborting, Postgres has already closed it
+            if pg_sys::IsTransactionState() {
+                pg_sys::SPI_cursor_close(self.portal);
+            }
+        }
+    }
+}
+
+/// A row of values to insert with a [`BulkInsert`], implemented for tuples of up to 16
+/// [`IntoDatum`] values
+pub trait IntoSpiRow {
+    /// The Postgres type of each value
+    fn type_oids() -> Vec<pg_sys::Oid>;
+
+    /// Convert this row into its values
+    fn into_spi_row(self) -> Vec<Option<pg_sys::Datum>>;
+}
+
+macro_rules! impl_into_spi_row {
+    ($($t:ident),+) => {
+        impl<$($t: IntoDatum),+> IntoSpiRow for ($($t,)+) {
+            fn type_oids() -> Vec<pg_sys::Oid> {
+                vec![$($t::type_oid()),+]
+            }
+
+            #[allow(non_snake_case)]
+            fn into_spi_row(self) -> Vec<Option<pg_sys::Datum>> {
+                let ($($t,)+) = self;
+                vec![$($t.into_datum()),+]
+            }
+        }
+    };
+}
+
+impl_into_spi_row!(A);
+impl_into_spi_row!(A, B);
+impl_into_spi_row!(A, B, C);
+impl_into_spi_row!(A, B, C, D);
+impl_into_spi_row!(A, B, C, D, E);
+impl_into_spi_row!(A, B, C, D, E, F);
+impl_into_spi_row!(A, B, C, D, E, F, G);
+impl_into_spi_row!(A, B, C, D, E, F, G, H);
+impl_into_spi_row!(A, B, C, D, E, F, G, H, I);
+impl_into_spi_row!(A, B, C, D, E, F, G, H, I, J);
+impl_into_spi_row!(A, B, C, D, E, F, G, H, I, J, K);
+impl_into_spi_row!(A, B, C, D, E, F, G, H, I, J, K, L);
+impl_into_spi_row!(A, B, C, D, E, F, G, H, I, J, K, L, M);
+impl_into_spi_row!(A, B, C, D, E, F, G, H, I, J, K, L, M, N);
+impl_into_spi_row!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O);
+impl_into_spi_row!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P);
+
+/// The default number of rows a [`BulkInsert`] inserts at a time.
+pub const DEFAULT_BULK_INSERT_BATCH_SIZE: usize = 1000;
+
+/// The most parameters Postgres allows a statement to have.
+const MAX_STATEMENT_PARAMETERS: usize = u16::MAX as usize;
+
+/// Inserts rows into a table in batches, made with [`SpiClient::copy_in`]
+///
+/// The values of each batch are built in a memory context that's reset once the batch is
+/// inserted, so memory use stays flat no matter how many rows are inserted.
+pub struct BulkInsert<'conn, R: IntoSpiRow> {
+    table: String,
+    columns: Vec<String>,
+    types: Vec<pg_sys::Oid>,
+    batch_size: usize,
+    values: Vec<Option<pg_sys::Datum>>,
+    nrows: usize,
+    plan: Option<PreparedStatement<'conn>>,
+    memory_context: PgMemoryContexts,
+    inserted: u64,
+    _row: PhantomData<R>,
+}
+
+impl<'conn, R: IntoSpiRow> BulkInsert<'conn, R> {
+    /// set the number of rows inserted at a time
+    ///
+    /// It's limited such that a batch's `INSERT` statement has no more than 65535 parameters.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        assert!(batch_size > 0, "bulk insert batch size must be positive");
+        self.flush();
+        self.free_plan();
+        self.batch_size = batch_size.min(MAX_STATEMENT_PARAMETERS / self.types.len());
+        self
+    }
+
+    /// add a row, inserting the current batch if it's now full
+    pub fn insert(&mut self, row: R) {
+        let previous_context = self.memory_context.set_as_current();
+        let values = row.into_spi_row();
+        previous_context.set_as_current();
+
+        self.values.extend(values);
+        self.nrows += 1;
+        if self.nrows >= self.batch_size {
+            self.flush();
+        }
+    }
+
+    /// insert the current, partial, batch now
+    pub fn flush(&mut self) {
+        if self.nrows == 0 {
+            return;
+        }
+
+        let values = std::mem::take(&mut self.values);
+        let inserted = if self.nrows == self.batch_size {
+            if self.plan.is_none() {
+                self.plan = Some(self.prepare(self.batch_size));
+            }
+            self.plan.as_ref().unwrap().execute(None, values).len()
+        } else {
+            let plan = self.prepare(self.nrows);
+            let inserted = plan.execute(None, values).len();
+            unsafe { pg_sys::SPI_freeplan(plan.as_ptr()) };
+            inserted
         };
 
+        self.inserted += inserted as u64;
+        self.nrows = 0;
+        self.memory_context.reset();
+    }
+
+    /// insert any remaining rows, returning the total number of rows inserted
+    pub fn finish(mut self) -> u64 {
+        self.flush();
+        self.inserted
+    }
+
+    /// prepare an `INSERT` of `nrows` rows
+    fn prepare(&self, nrows: usize) -> PreparedStatement<'conn> {
+        let ncolumns = self.types.len();
+        let rows = (0..nrows)
+            .map(|row| {
+                let params = (1..=ncolumns)
+                    .map(|column| format!("${}", row * ncolumns + column))
+                    .collect::<Vec<_>>();
+                format!("({})", params.join(", "))
+            })
+            .collect::<Vec<_>>();
+        let query = format!(
+            "INSERT INTO {} ({}) VALUES {}",
+            self.table,
+            self.columns.join(", "),
+            rows.join(", ")
+        );
+        let types = std::iter::repeat(self.types.iter().map(|oid| PgOid::from(*oid)))
+            .take(nrows)
+            .flatten()
+            .collect::<Vec<_>>();
+
+        // we were made by a `SpiClient<'conn>`, so its connection is still open
+        SpiClient {
+            _connection: PhantomData,
+        }
+        .prepare(&query, &types)
+    }
+
+    fn free_plan(&mut self) {
+        if let Some(plan) = self.plan.take() {
+            unsafe { pg_sys::SPI_freeplan(plan.as_ptr()) };
+        }
+    }
+}
+
+impl<'conn, R: IntoSpiRow> Extend<R> for BulkInsert<'conn, R> {
+    fn extend<I: IntoIterator<Item = R>>(&mut self, rows: I) {
+        for row in rows {
+            self.insert(row);
+        }
+    }
+}
+
+impl<'conn, R: IntoSpiRow> Drop for BulkInsert<'conn, R> {
+    fn drop(&mut self) {
+        // if we're unwinding, the transaction is aborting and there's no point
+        if !std::thread::panicking() {
+            self.flush();
+            self.free_plan();
+        }
+    }
+}
+
+impl<'conn> SpiTupleTable<'conn> {
+    /// wrap the `SPI_tuptable` produced by the SPI call that returned `status_code`
+    fn from_status(status_code: i32) -> Self {
         SpiTupleTable {
             status_code: Spi::check_status(status_code),
             table: unsafe { pg_sys::SPI_tuptable },
@@ -376,11 +1732,10 @@ impl SpiClient {
                 Some(unsafe { (*pg_sys::SPI_tuptable).tupdesc })
             },
             current: -1,
+            _connection: PhantomData,
         }
     }
-}
 
-impl SpiTupleTable {
     /// `SpiTupleTable`s are positioned before the start, for iteration purposes.
     ///
     /// This method moves the position to the first row.  If there are no rows, this
@@ -399,6 +1754,47 @@ impl SpiTupleTable {
         self.len() == 0
     }
 
+    /// The result's columns, or none if the statement didn't return rows
+    pub fn columns(&self) -> Vec<SpiColumn> {
+        self.tupdesc
+            .map(SpiColumn::from_tupdesc)
+            .unwrap_or_default()
+    }
+
+    /// Copy every row of this result out of SPI's memory and into a new memory context, a child
+    /// of `parent`, so the rows can be read after the SPI connection is gone
+    ///
+    /// The `OwnedSpiResult` deletes its memory context when it's dropped.  With
+    /// `PgMemoryContexts::TopMemoryContext` as the `parent`, it can be kept across transactions,
+    /// such as in a `static`.
+    pub fn to_owned_in(&self, parent: &PgMemoryContexts) -> OwnedSpiResult {
+        let mut context = PgMemoryContexts::For(parent.value())
+            .switch_to(|_| PgMemoryContexts::new("OwnedSpiResult"));
+
+        let tupdesc = self.tupdesc;
+        let tuples = match tupdesc {
+            Some(_) if !self.table.is_null() => unsafe {
+                std::slice::from_raw_parts((*self.table).vals, self.size)
+            },
+            _ => &[],
+        };
+        let (tupdesc, tuples) = context.switch_to(|_| unsafe {
+            (
+                tupdesc.map(|tupdesc| pg_sys::CreateTupleDescCopy(tupdesc)),
+                tuples
+                    .iter()
+                    .map(|tuple| pg_sys::heap_copytuple(*tuple))
+                    .collect::<Vec<_>>(),
+            )
+        });
+
+        OwnedSpiResult {
+            tupdesc,
+            tuples,
+            _context: context,
+        }
+    }
+
     pub fn get_one<A: FromDatum>(&self) -> Option<A> {
         self.get_datum(1)
     }
@@ -418,11 +1814,11 @@ impl SpiTupleTable {
         (a, b, c)
     }
 
-    pub fn get_heap_tuple(&self) -> Option<SpiHeapTupleData> {
+    pub fn get_heap_tuple(&self) -> Option<SpiHeapTupleData<'conn>> {
         if self.current < 0 {
             panic!("SpiTupleTable positioned before start")
         }
-        if self.current as u64 >= unsafe { pg_sys::SPI_processed } {
+        if self.current as usize >= self.size {
             None
         } else {
             match self.tupdesc {
@@ -442,7 +1838,7 @@ impl SpiTupleTable {
         if self.current < 0 {
             panic!("SpiTupleTable positioned before start")
         }
-        if self.current as u64 >= unsafe { pg_sys::SPI_processed } {
+        if self.current as usize >= self.size {
             None
         } else {
             match self.tupdesc {
@@ -467,12 +1863,13 @@ impl SpiTupleTable {
     }
 }
 
-impl SpiHeapTupleData {
+impl<'conn> SpiHeapTupleData<'conn> {
     /// Create a new `SpiHeapTupleData` from its constituent parts
     pub unsafe fn new(tupdesc: pg_sys::TupleDesc, htup: *mut pg_sys::HeapTupleData) -> Self {
         let mut data = SpiHeapTupleData {
             tupdesc,
             entries: HashMap::default(),
+            _connection: PhantomData,
         };
 
         for i in 1..=tupdesc.as_ref().unwrap().natts {
@@ -490,6 +1887,13 @@ impl SpiHeapTupleData {
         data
     }
 
+    /// Get the value of the column named `name`, or `None` if it is NULL
+    pub fn get_by_name<T: FromDatum>(&self, name: &str) -> Result<Option<T>, FromSpiRowError> {
+        self.by_name(name)
+            .map(|entry| entry.value())
+            .map_err(|_| FromSpiRowError::NoSuchColumn(name.to_string()))
+    }
+
     /// Get a typed Datum value from this HeapTuple by its ordinal position.  
     ///
     /// The ordinal position is 1-based
@@ -516,6 +1920,18 @@ impl SpiHeapTupleData {
         }
     }
 
+    /// The name of the column at the 1-based `ordinal` position
+    fn column_name(&self, ordinal: usize) -> String {
+        unsafe {
+            let name = pg_sys::SPI_fname(self.tupdesc, ordinal as i32);
+            if name.is_null() {
+                ordinal.to_string()
+            } else {
+                CStr::from_ptr(name).to_string_lossy().into_owned()
+            }
+        }
+    }
+
     /// Get a typed Datum value from this HeapTuple by its field name.  
     ///
     /// If the specified name does not exist a `Err(SpiError::Noattribute)` is returned
@@ -608,6 +2024,205 @@ impl SpiHeapTupleData {
     }
 }
 
+/// A type which can be built from a row of an SPI result, usually by `#[derive(FromSpiRow)]`
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[derive(FromSpiRow)]
+/// struct Dog {
+///     name: String,
+///     #[spi(rename = "scritches")]
+///     scritch_count: Option<i32>,
+/// }
+///
+/// Spi::execute(|client| {
+///     for row in client.select("SELECT name, scritches FROM dogs", None, None) {
+///         let dog = Dog::from_spi_row(&row).unwrap();
+///     }
+/// });
+/// ```
+pub trait FromSpiRow: Sized {
+    fn from_spi_row(row: &SpiHeapTupleData) -> Result<Self, FromSpiRowError>;
+}
+
+/// A value which can be read from a column of an SPI result, such as an element of a tuple passed
+/// to [`Spi::get`]
+///
+/// A NULL column is an error unless the value is an `Option`.
+pub trait FromSpiColumn: Sized {
+    /// Read the column at the 1-based `ordinal` position of `row`
+    fn from_spi_column(row: &SpiHeapTupleData, ordinal: usize) -> Result<Self, FromSpiRowError>;
+}
+
+impl<T: FromDatum> FromSpiColumn for Option<T> {
+    fn from_spi_column(row: &SpiHeapTupleData, ordinal: usize) -> Result<Self, FromSpiRowError> {
+        row.by_ordinal(ordinal)
+            .map(|entry| entry.value())
+            .map_err(|_| FromSpiRowError::NoSuchOrdinal(ordinal))
+    }
+}
+
+impl<T: FromDatum> FromSpiColumn for T {
+    fn from_spi_column(row: &SpiHeapTupleData, ordinal: usize) -> Result<Self, FromSpiRowError> {
+        Option::<T>::from_spi_column(row, ordinal)?
+            .ok_or_else(|| FromSpiRowError::UnexpectedNull(row.column_name(ordinal)))
+    }
+}
+
+macro_rules! impl_from_spi_row_for_tuple {
+    ($($t:ident),+) => {
+        impl<$($t: FromSpiColumn),+> FromSpiRow for ($($t,)+) {
+            fn from_spi_row(row: &SpiHeapTupleData) -> Result<Self, FromSpiRowError> {
+                // tuple fields are evaluated in order, so each one is the next column
+                let mut ordinal = 0;
+                Ok(($({
+                    ordinal += 1;
+                    $t::from_spi_column(row, ordinal)?
+                },)+))
+            }
+        }
+    };
+}
+
+impl_from_spi_row_for_tuple!(A);
+impl_from_spi_row_for_tuple!(A, B);
+impl_from_spi_row_for_tuple!(A, B, C);
+impl_from_spi_row_for_tuple!(A, B, C, D);
+impl_from_spi_row_for_tuple!(A, B, C, D, E);
+impl_from_spi_row_for_tuple!(A, B, C, D, E, F);
+impl_from_spi_row_for_tuple!(A, B, C, D, E, F, G);
+impl_from_spi_row_for_tuple!(A, B, C, D, E, F, G, H);
+impl_from_spi_row_for_tuple!(A, B, C, D, E, F, G, H, I);
+impl_from_spi_row_for_tuple!(A, B, C, D, E, F, G, H, I, J);
+impl_from_spi_row_for_tuple!(A, B, C, D, E, F, G, H, I, J, K);
+impl_from_spi_row_for_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
+impl_from_spi_row_for_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M);
+impl_from_spi_row_for_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M, N);
+impl_from_spi_row_for_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O);
+impl_from_spi_row_for_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P);
+
+/// The errors that can occur when building a [`FromSpiRow`] type from a row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FromSpiRowError {
+    /// The row has no column with this name.
+    NoSuchColumn(String),
+    /// The column is NULL, but its field is not an `Option`.
+    UnexpectedNull(String),
+    /// The row has fewer columns than this 1-based position.
+    NoSuchOrdinal(usize),
+}
+
+impl std::fmt::Display for FromSpiRowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FromSpiRowError::NoSuchColumn(name) => write!(f, "no column named `{}`", name),
+            FromSpiRowError::UnexpectedNull(name) => {
+                write!(
+                    f,
+                    "column `{}` is NULL, but its field is not an `Option`",
+                    name
+                )
+            }
+            FromSpiRowError::NoSuchOrdinal(ordinal) => {
+                write!(f, "no column at position {}", ordinal)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FromSpiRowError {}
+
+impl SpiSqlError {
+    /// Take ownership of `edata`, from `pg_sys::CopyErrorData()`.
+    unsafe fn from_error_data(edata: *mut pg_sys::ErrorData) -> Self {
+        let to_string = |s: *mut std::os::raw::c_char| {
+            if s.is_null() {
+                None
+            } else {
+                Some(CStr::from_ptr(s).to_string_lossy().into_owned())
+            }
+        };
+        let error = SpiSqlError {
+            sqlerrcode: (*edata).sqlerrcode,
+            sqlstate: CStr::from_ptr(pg_sys::unpack_sql_state((*edata).sqlerrcode))
+                .to_string_lossy()
+                .into_owned(),
+            message: to_string((*edata).message).unwrap_or_default(),
+            detail: to_string((*edata).detail),
+            hint: to_string((*edata).hint),
+            context: to_string((*edata).context),
+            // errors in a statement run through SPI are reported as "internal" positions
+            position: match ((*edata).cursorpos, (*edata).internalpos) {
+                (0, 0) => None,
+                (0, position) | (position, _) => Some(position as usize),
+            },
+        };
+        pg_sys::FreeErrorData(edata);
+        error
+    }
+
+    /// Is this error's SQLSTATE `code`?
+    pub fn is(&self, code: PgSqlErrorCode) -> bool {
+        self.sqlerrcode == code as i32
+    }
+}
+
+impl std::fmt::Display for SpiSqlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (SQLSTATE {})", self.message, self.sqlstate)
+    }
+}
+
+impl std::error::Error for SpiSqlError {}
+
+impl SpiScriptError {
+    fn new(script: &str, statement: Option<usize>, offset: usize, error: SpiSqlError) -> Self {
+        let before = &script[..offset];
+        let line_start = before.rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+        SpiScriptError {
+            statement,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            error,
+        }
+    }
+}
+
+impl std::fmt::Display for SpiScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.statement {
+            Some(statement) => write!(
+                f,
+                "statement {} (line {}, column {}): {}",
+                statement + 1,
+                self.line,
+                self.column,
+                self.error
+            ),
+            None => write!(
+                f,
+                "line {}, column {}: {}",
+                self.line, self.column, self.error
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SpiScriptError {}
+
+impl std::fmt::Display for SpiTimeoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpiTimeoutError::TimedOut(timeout) => write!(f, "timed out after {:?}", timeout),
+            SpiTimeoutError::Canceled => write!(f, "canceled"),
+            SpiTimeoutError::Sql(error) => std::fmt::Display::fmt(error, f),
+        }
+    }
+}
+
+impl std::error::Error for SpiTimeoutError {}
+
 impl<Datum: IntoDatum + FromDatum> From<Datum> for SpiHeapTupleDataEntry {
     fn from(datum: Datum) -> Self {
         SpiHeapTupleDataEntry {
@@ -629,7 +2244,7 @@ impl SpiHeapTupleDataEntry {
 /// Provide ordinal indexing into a `SpiHeapTupleData`.
 ///
 /// If the index is out of bounds, it will panic
-impl Index<usize> for SpiHeapTupleData {
+impl<'conn> Index<usize> for SpiHeapTupleData<'conn> {
     type Output = SpiHeapTupleDataEntry;
 
     fn index(&self, index: usize) -> &Self::Output {
@@ -640,7 +2255,7 @@ impl Index<usize> for SpiHeapTupleData {
 /// Provide named indexing into a `SpiHeapTupleData`.  
 ///
 /// If the field name doesn't exist, it will panic
-impl Index<&str> for SpiHeapTupleData {
+impl<'conn> Index<&str> for SpiHeapTupleData<'conn> {
     type Output = SpiHeapTupleDataEntry;
 
     fn index(&self, index: &str) -> &Self::Output {
@@ -651,7 +2266,7 @@ impl Index<&str> for SpiHeapTupleData {
 /// Provide mutable ordinal indexing into a `SpiHeapTupleData`.  
 ///
 /// If the index is out of bounds, it will panic
-impl IndexMut<usize> for SpiHeapTupleData {
+impl<'conn> IndexMut<usize> for SpiHeapTupleData<'conn> {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
         self.by_ordinal_mut(index).expect("invalid ordinal value")
     }
@@ -660,14 +2275,64 @@ impl IndexMut<usize> for SpiHeapTupleData {
 /// Provide mutable named indexing into a `SpiHeapTupleData`.  
 ///
 /// If the field name doesn't exist, it will panic
-impl IndexMut<&str> for SpiHeapTupleData {
+impl<'conn> IndexMut<&str> for SpiHeapTupleData<'conn> {
     fn index_mut(&mut self, index: &str) -> &mut Self::Output {
         self.by_name_mut(index).expect("invalid field name")
     }
 }
 
-impl Iterator for SpiTupleTable {
-    type Item = SpiHeapTupleData;
+/// The rows of an [`SpiTupleTable`], copied out of SPI's memory by
+/// [`SpiTupleTable::to_owned_in`] or [`Spi::select_owned`]
+///
+/// Its rows are read the same way as an `SpiTupleTable`'s, as [`SpiHeapTupleData`]s, except
+/// they borrow the `OwnedSpiResult` instead of an SPI connection.
+pub struct OwnedSpiResult {
+    tupdesc: Option<pg_sys::TupleDesc>,
+    tuples: Vec<pg_sys::HeapTuple>,
+    // owns `tupdesc` and `tuples`
+    _context: PgMemoryContexts,
+}
+
+impl OwnedSpiResult {
+    /// How many rows were copied?
+    pub fn len(&self) -> usize {
+        self.tuples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The result's `TupleDesc`, or `None` if the statement didn't return rows
+    pub fn tupdesc(&self) -> Option<pg_sys::TupleDesc> {
+        self.tupdesc
+    }
+
+    /// The result's columns, or none if the statement didn't return rows
+    pub fn columns(&self) -> Vec<SpiColumn> {
+        self.tupdesc
+            .map(SpiColumn::from_tupdesc)
+            .unwrap_or_default()
+    }
+
+    /// Get the 0-based `row`, or `None` if there is no such row
+    pub fn get(&self, row: usize) -> Option<SpiHeapTupleData<'_>> {
+        let tuple = *self.tuples.get(row)?;
+        Some(unsafe { SpiHeapTupleData::new(self.tupdesc.unwrap(), tuple) })
+    }
+
+    /// Get the value of the 1-based `ordinal` column of the first row
+    pub fn get_one<A: FromDatum>(&self) -> Option<A> {
+        self.get(0)?.by_ordinal(1).ok()?.value()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = SpiHeapTupleData<'_>> + '_ {
+        (0..self.len()).map(move |row| self.get(row).unwrap())
+    }
+}
+
+impl<'conn> Iterator for SpiTupleTable<'conn> {
+    type Item = SpiHeapTupleData<'conn>;
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
@@ -0,0 +1,129 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Safe construction of dynamic SQL, quoting identifiers and literals the same way Postgres'
+//! `quote_ident()` and `quote_literal()` do
+//!
+//! [`Ident`] and [`Literal`] quote their value when they're formatted, so they can be interpolated
+//! into a query with `format!()`:
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//! use pgx::sql::{Ident, Literal};
+//!
+//! fn add_audit_column(schema: &str, table: &str, column: &str, default: &str) {
+//!     Spi::run(&format!(
+//!         "ALTER TABLE {} ADD COLUMN {} text DEFAULT {}",
+//!         Ident::qualified(schema, table),
+//!         Ident::new(column),
+//!         Literal::new(default)
+//!     ));
+//! }
+//! ```
+use crate::pg_sys;
+use std::ffi::{CStr, CString};
+use std::fmt::{Display, Formatter};
+
+/// Quote `ident` for use as an SQL identifier, if it needs quoting, like SQL's `quote_ident()`
+pub fn quote_identifier(ident: &str) -> String {
+    let ident = CString::new(ident).expect("identifier contained a null byte");
+    unsafe { CStr::from_ptr(pg_sys::quote_identifier(ident.as_ptr())) }
+        .to_str()
+        .expect("quoted identifier is not valid UTF8")
+        .to_string()
+}
+
+/// Quote `schema` and `ident` for use as a schema-qualified SQL identifier
+pub fn quote_qualified_identifier(schema: &str, ident: &str) -> String {
+    format!("{}.{}", quote_identifier(schema), quote_identifier(ident))
+}
+
+/// Quote `literal` for use as an SQL string literal, like SQL's `quote_literal()`
+pub fn quote_literal(literal: &str) -> String {
+    let literal = CString::new(literal).expect("literal contained a null byte");
+    unsafe { CStr::from_ptr(pg_sys::quote_literal_cstr(literal.as_ptr())) }
+        .to_str()
+        .expect("quoted literal is not valid UTF8")
+        .to_string()
+}
+
+/// An SQL identifier, optionally schema-qualified, which is quoted as necessary when formatted
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Ident {
+    schema: Option<String>,
+    name: String,
+}
+
+impl Ident {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Ident {
+            schema: None,
+            name: name.into(),
+        }
+    }
+
+    /// An identifier qualified by the `schema` it belongs to
+    pub fn qualified<S: Into<String>, N: Into<String>>(schema: S, name: N) -> Self {
+        Ident {
+            schema: Some(schema.into()),
+            name: name.into(),
+        }
+    }
+
+    /// The unquoted name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The unquoted schema, if this identifier is schema-qualified
+    pub fn schema(&self) -> Option<&str> {
+        self.schema.as_deref()
+    }
+}
+
+impl Display for Ident {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.schema {
+            Some(schema) => f.write_str(&quote_qualified_identifier(schema, &self.name)),
+            None => f.write_str(&quote_identifier(&self.name)),
+        }
+    }
+}
+
+/// An SQL string literal, or `NULL`, which is quoted when formatted
+///
+/// As with any string literal, Postgres will cast it to the type its context requires.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Literal(Option<String>);
+
+impl Literal {
+    pub fn new<S: Into<String>>(value: S) -> Self {
+        Literal(Some(value.into()))
+    }
+
+    /// The `NULL` literal
+    pub fn null() -> Self {
+        Literal(None)
+    }
+}
+
+impl<S: Into<String>> From<Option<S>> for Literal {
+    fn from(value: Option<S>) -> Self {
+        Literal(value.map(Into::into))
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Some(value) => f.write_str(&quote_literal(value)),
+            None => f.write_str("NULL"),
+        }
+    }
+}
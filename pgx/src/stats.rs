@@ -0,0 +1,223 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Statistics an extension keeps in shared memory, such as the rows it's processed, or its cache
+//! hits, which are shown by a view, like Postgres' own `pg_stat_*` views
+//!
+//! `#[derive(PgStats)]` declares a struct's fields as statistics, each a [`PgStatCounter`] or a
+//! [`PgStatGauge`], and generates the view, and a function resetting its counters:
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[derive(PgStats, Default)]
+//! #[repr(C)]
+//! #[pg_stats(view = "my_ext_stats")]
+//! struct Stats {
+//!     /// Rows processed since the counters were reset
+//!     rows_processed: PgStatCounter,
+//!     cache_hits: PgStatCounter,
+//!     /// Rows waiting to be processed
+//!     queue_depth: PgStatGauge,
+//! }
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     Stats::init();
+//! }
+//!
+//! #[pg_extern]
+//! fn process(rows: i64) {
+//!     Stats::get().rows_processed.add(rows as u64);
+//! }
+//! ```
+//!
+//! ```sql
+//! SELECT name, value FROM my_ext_stats WHERE kind = 'counter';
+//! SELECT my_ext_stats_reset();
+//! ```
+use crate::{
+    pg_guard, pg_sys, table_column, IntoDatum, PGXSharedAtomic, PgAtomic, PgSharedMem,
+    SharedAtomicU64, TableColumn, TableRow,
+};
+use std::sync::atomic::{AtomicI64, Ordering};
+
+/// A struct whose fields are statistics, kept in shared memory, usually by `#[derive(PgStats)]`
+pub trait PgStats: PGXSharedAtomic + 'static {
+    /// The name the statistics' shared memory is found by, which is the same in every process
+    const NAME: &'static str;
+
+    /// Where the statistics are attached, once shared memory is initialized
+    fn shared() -> &'static PgAtomic<Self>;
+
+    /// The statistics, in the order they're declared
+    fn describe(&self) -> Vec<PgStatDescription>;
+
+    /// Reset the counters to zero, leaving the gauges as they are
+    fn reset(&self);
+
+    /// Reserve the statistics' shared memory, which must be done in `_PG_init()`, of an
+    /// extension loaded by `shared_preload_libraries`
+    fn init() {
+        PgSharedMem::pg_init_atomic(Self::shared());
+        unsafe {
+            if STATS_TO_ATTACH.is_empty() {
+                PREV_SHMEM_STARTUP_HOOK = pg_sys::shmem_startup_hook;
+                pg_sys::shmem_startup_hook = Some(pgx_stats_shmem_startup);
+            }
+            STATS_TO_ATTACH.push(attach::<Self>);
+        }
+    }
+
+    /// The statistics, shared by every backend
+    ///
+    /// ## Panics
+    ///
+    /// If they haven't been [initialized](PgStats::init)
+    fn get() -> &'static Self {
+        Self::shared().get()
+    }
+}
+
+/// A statistic, which is a field of a [`PgStats`]
+pub trait PgStat: Default + Sync {
+    /// How the statistic changes, as shown in the view's `kind` column
+    const KIND: &'static str;
+
+    fn value(&self) -> i64;
+
+    /// Reset the statistic, if it's reset with the others
+    fn reset(&self);
+}
+
+/// A count of how often something happened, which only goes up, until it's reset to zero
+#[derive(Debug, Default)]
+#[repr(transparent)]
+pub struct PgStatCounter(SharedAtomicU64);
+
+impl PgStatCounter {
+    /// Add 1
+    pub fn increment(&self) {
+        self.0.increment();
+    }
+
+    pub fn add(&self, value: u64) {
+        self.0.fetch_add(value);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load()
+    }
+}
+
+impl PgStat for PgStatCounter {
+    const KIND: &'static str = "counter";
+
+    fn value(&self) -> i64 {
+        self.get() as i64
+    }
+
+    fn reset(&self) {
+        self.0.store(0);
+    }
+}
+
+/// A measurement of something right now, such as the length of a queue, which goes up and down,
+/// and isn't reset with the counters
+#[derive(Debug, Default)]
+#[repr(transparent)]
+pub struct PgStatGauge(AtomicI64);
+
+impl PgStatGauge {
+    pub fn set(&self, value: i64) {
+        self.0.store(value, Ordering::SeqCst)
+    }
+
+    pub fn add(&self, value: i64) {
+        self.0.fetch_add(value, Ordering::SeqCst);
+    }
+
+    pub fn sub(&self, value: i64) {
+        self.0.fetch_sub(value, Ordering::SeqCst);
+    }
+
+    pub fn get(&self) -> i64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+impl PgStat for PgStatGauge {
+    const KIND: &'static str = "gauge";
+
+    fn value(&self) -> i64 {
+        self.get()
+    }
+
+    fn reset(&self) {}
+}
+
+/// A statistic, as shown by the view `#[derive(PgStats)]` generates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgStatDescription {
+    pub name: String,
+    /// Whether it's a `"counter"` or a `"gauge"`
+    pub kind: String,
+    pub value: i64,
+    pub description: String,
+}
+
+impl PgStatDescription {
+    pub fn new<S: PgStat>(name: &str, stat: &S, description: &str) -> Self {
+        PgStatDescription {
+            name: name.to_string(),
+            kind: S::KIND.to_string(),
+            value: stat.value(),
+            description: description.to_string(),
+        }
+    }
+}
+
+impl TableRow for PgStatDescription {
+    fn table_columns() -> Vec<TableColumn> {
+        vec![
+            table_column::<String>("String", "name"),
+            table_column::<String>("String", "kind"),
+            table_column::<i64>("i64", "value"),
+            table_column::<String>("String", "description"),
+        ]
+    }
+
+    fn into_datums(self) -> Vec<Option<pg_sys::Datum>> {
+        vec![
+            self.name.into_datum(),
+            self.kind.into_datum(),
+            self.value.into_datum(),
+            self.description.into_datum(),
+        ]
+    }
+}
+
+static mut PREV_SHMEM_STARTUP_HOOK: Option<unsafe extern "C" fn()> = None;
+static mut STATS_TO_ATTACH: Vec<fn()> = Vec::new();
+
+fn attach<T: PgStats>() {
+    PgSharedMem::shmem_init_atomic_named(T::shared(), T::NAME);
+}
+
+#[pg_guard]
+extern "C" fn pgx_stats_shmem_startup() {
+    unsafe {
+        if let Some(prev) = PREV_SHMEM_STARTUP_HOOK {
+            prev();
+        }
+        for attach in STATS_TO_ATTACH.iter() {
+            attach();
+        }
+    }
+}
@@ -0,0 +1,150 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Structured logging, of a message along with named fields, which log pipelines can parse
+//! without scraping the text
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! structured_log!(LOG, "adopted a dog", name = "brandy", age = 3, tricks = vec!["sit", "stay"]);
+//! ```
+//!
+//! When the server logs to the `jsonlog` destination, of Postgres 15 and up, the message is a
+//! JSON object, which is the `message` of the record `jsonlog` writes:
+//!
+//! ```text
+//! {"msg":"adopted a dog","name":"brandy","age":3,"tricks":["sit","stay"]}
+//! ```
+//!
+//! Otherwise it's `key=value` text, in the "logfmt" style:
+//!
+//! ```text
+//! adopted a dog name=brandy age=3 tricks=["sit","stay"]
+//! ```
+use crate::log::{elog, PgLogLevel};
+use crate::Guc;
+use serde::Serialize;
+use serde_json::Value;
+
+/// How a [`StructuredLog`] is written in the log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// A JSON object, with the message as `"msg"`, followed by the fields
+    Json,
+    /// The message, followed by the fields as `name=value`
+    Text,
+}
+
+impl LogFormat {
+    /// `Json` if the server's `log_destination` includes `jsonlog`, and `Text` otherwise
+    pub fn current() -> Self {
+        let destinations = Guc::get::<String>("log_destination").unwrap_or_default();
+        if destinations
+            .split(',')
+            .any(|destination| destination.trim().eq_ignore_ascii_case("jsonlog"))
+        {
+            LogFormat::Json
+        } else {
+            LogFormat::Text
+        }
+    }
+}
+
+/// A log message along with named fields, whose values are anything [`Serialize`], usually
+/// written by the [`structured_log!`](crate::structured_log!) macro
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructuredLog {
+    message: String,
+    /// In the order they were added, which is the order they're written in
+    fields: Vec<(String, Value)>,
+}
+
+impl StructuredLog {
+    pub fn new(message: impl Into<String>) -> Self {
+        StructuredLog {
+            message: message.into(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Add the field `name`, whose value is `value` as JSON
+    ///
+    /// A value which can't be serialized, such as a map whose keys aren't strings, is logged as
+    /// the error serializing it.
+    pub fn field<T: Serialize>(mut self, name: &str, value: T) -> Self {
+        let value = serde_json::to_value(value)
+            .unwrap_or_else(|e| Value::String(format!("<unserializable: {}>", e)));
+        self.fields.push((name.to_string(), value));
+        self
+    }
+
+    /// Write this as `format` says
+    pub fn format(&self, format: LogFormat) -> String {
+        match format {
+            LogFormat::Json => {
+                let mut json = format!("{{\"msg\":{}", Value::String(self.message.clone()));
+                for (name, value) in &self.fields {
+                    json.push_str(&format!(",{}:{}", Value::String(name.clone()), value));
+                }
+                json.push('}');
+                json
+            }
+            LogFormat::Text => {
+                let mut text = self.message.clone();
+                for (name, value) in &self.fields {
+                    text.push_str(&format!(" {}={}", name, logfmt_value(value)));
+                }
+                text
+            }
+        }
+    }
+
+    /// Log this at `level`, as the server's `log_destination` says, by [`LogFormat::current`]
+    ///
+    /// Logged at `ERROR` or above, this aborts the transaction, as [`elog`] does.
+    pub fn log(&self, level: PgLogLevel) {
+        elog(level, &self.format(LogFormat::current()));
+    }
+}
+
+/// A string as it is, if it doesn't need quoting, and any other value as JSON
+fn logfmt_value(value: &Value) -> String {
+    match value {
+        Value::String(s)
+            if !s.is_empty()
+                && !s
+                    .chars()
+                    .any(|c| c.is_whitespace() || c.is_control() || c == '=' || c == '"') =>
+        {
+            s.clone()
+        }
+        value => value.to_string(),
+    }
+}
+
+/// Log a message, along with named fields, at a Postgres log level, as JSON when the server logs
+/// to `jsonlog`, and as `name=value` text otherwise
+///
+/// Its arguments are the level, the message, and then the fields, as `name = value`, where each
+/// value is anything [`Serialize`](serde::Serialize).
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// structured_log!(WARNING, "slow adoption", dog = "brandy", seconds = 12.5);
+/// ```
+#[macro_export]
+macro_rules! structured_log {
+    ($level:ident, $message:expr $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::structured_log::StructuredLog::new($message)
+            $(.field(stringify!($name), $value))*
+            .log($crate::log::PgLogLevel::$level)
+    };
+}
@@ -0,0 +1,268 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Support for implementing [table access methods](https://www.postgresql.org/docs/current/tableam.html),
+//! which are available since Postgres 12
+//!
+//! A [`TableAccessMethod`] starts out as Postgres' own heap, and overrides those of its
+//! callbacks that it stores and finds tuples differently in.  Everything else, such as vacuum,
+//! updates, and deletes, is left to the heap.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! /// A heap which counts the tuples inserted into it
+//! struct CountingHeap;
+//!
+//! static mut INSERTED: u64 = 0;
+//!
+//! impl TableAccessMethod for CountingHeap {
+//!     fn tuple_insert(
+//!         relation: &PgRelation,
+//!         slot: *mut pg_sys::TupleTableSlot,
+//!         cid: pg_sys::CommandId,
+//!         options: i32,
+//!         bistate: *mut pg_sys::BulkInsertStateData,
+//!     ) {
+//!         unsafe { INSERTED += 1 };
+//!         unsafe { (heap_table_am().tuple_insert.unwrap())(relation.as_ptr(), slot, cid, options, bistate) }
+//!     }
+//! }
+//!
+//! #[pg_extern]
+//! fn counting_heap_handler(_internal: Internal) -> PgBox<pg_sys::TableAmRoutine> {
+//!     table_am_routine::<CountingHeap>()
+//! }
+//!
+//! extension_sql!(
+//!     r#"CREATE ACCESS METHOD counting_heap TYPE TABLE HANDLER counting_heap_handler;"#,
+//!     name = "create_counting_heap",
+//!     requires = [counting_heap_handler]
+//! );
+//! ```
+use crate::{guard, pg_sys, PgBox, PgRelation};
+use std::any::TypeId;
+use std::collections::HashMap;
+
+/// A table access method, whose callbacks all default to those of the heap
+///
+/// The tuples of a scan begun by [`TableAccessMethod::scan_begin`] are fetched by the other
+/// `scan_` callbacks, and the slots they're stored in are of the type returned by
+/// [`TableAccessMethod::slot_callbacks`], so these are overridden together.
+pub trait TableAccessMethod: 'static {
+    /// The type of slot the table's tuples are stored in
+    fn slot_callbacks(relation: &PgRelation) -> *const pg_sys::TupleTableSlotOps {
+        unsafe { (heap_table_am().slot_callbacks.unwrap())(relation.as_ptr()) }
+    }
+
+    /// Start a scan of `relation`, which sees the tuples visible to `snapshot`
+    fn scan_begin(
+        relation: &PgRelation,
+        snapshot: pg_sys::Snapshot,
+        nkeys: i32,
+        keys: *mut pg_sys::ScanKeyData,
+        parallel_scan: pg_sys::ParallelTableScanDesc,
+        flags: u32,
+    ) -> pg_sys::TableScanDesc {
+        unsafe {
+            (heap_table_am().scan_begin.unwrap())(
+                relation.as_ptr(),
+                snapshot,
+                nkeys,
+                keys,
+                parallel_scan,
+                flags,
+            )
+        }
+    }
+
+    /// Finish `scan`, freeing what it allocated
+    fn scan_end(scan: pg_sys::TableScanDesc) {
+        unsafe { (heap_table_am().scan_end.unwrap())(scan) }
+    }
+
+    /// Start `scan` over again, with new `keys` if they're not null, and new parameters if
+    /// `set_params`
+    fn scan_rescan(
+        scan: pg_sys::TableScanDesc,
+        keys: *mut pg_sys::ScanKeyData,
+        set_params: bool,
+        allow_strat: bool,
+        allow_sync: bool,
+        allow_pagemode: bool,
+    ) {
+        unsafe {
+            (heap_table_am().scan_rescan.unwrap())(
+                scan,
+                keys,
+                set_params,
+                allow_strat,
+                allow_sync,
+                allow_pagemode,
+            )
+        }
+    }
+
+    /// Store the next tuple of `scan` in `slot`, returning `false` once there are no more
+    fn scan_getnextslot(
+        scan: pg_sys::TableScanDesc,
+        direction: pg_sys::ScanDirection,
+        slot: *mut pg_sys::TupleTableSlot,
+    ) -> bool {
+        unsafe { (heap_table_am().scan_getnextslot.unwrap())(scan, direction, slot) }
+    }
+
+    /// Insert the tuple in `slot` into `relation`, setting the slot's `tts_tid` to where it was
+    /// stored
+    fn tuple_insert(
+        relation: &PgRelation,
+        slot: *mut pg_sys::TupleTableSlot,
+        cid: pg_sys::CommandId,
+        options: i32,
+        bistate: *mut pg_sys::BulkInsertStateData,
+    ) {
+        unsafe {
+            (heap_table_am().tuple_insert.unwrap())(relation.as_ptr(), slot, cid, options, bistate)
+        }
+    }
+
+    /// Insert the tuples in `slots` into `relation`, as `COPY` does
+    fn multi_insert(
+        relation: &PgRelation,
+        slots: &mut [*mut pg_sys::TupleTableSlot],
+        cid: pg_sys::CommandId,
+        options: i32,
+        bistate: *mut pg_sys::BulkInsertStateData,
+    ) {
+        unsafe {
+            (heap_table_am().multi_insert.unwrap())(
+                relation.as_ptr(),
+                slots.as_mut_ptr(),
+                slots.len() as i32,
+                cid,
+                options,
+                bistate,
+            )
+        }
+    }
+}
+
+/// The heap's table access method, for a [`TableAccessMethod`] to delegate to
+pub fn heap_table_am() -> &'static pg_sys::TableAmRoutine {
+    unsafe { &*pg_sys::GetHeapamTableAmRoutine() }
+}
+
+/// The `TableAmRoutine` for `T`, to be returned by the access method's handler function
+///
+/// Postgres keeps a pointer to it for as long as the backend runs, so there's only ever one for
+/// each access method.
+pub fn table_am_routine<T: TableAccessMethod>() -> PgBox<pg_sys::TableAmRoutine> {
+    let routines = unsafe { ROUTINES.get_or_insert_with(HashMap::new) };
+    let routine = routines.entry(TypeId::of::<T>()).or_insert_with(|| {
+        let routine = pg_sys::TableAmRoutine {
+            slot_callbacks: Some(slot_callbacks::<T>),
+            scan_begin: Some(scan_begin::<T>),
+            scan_end: Some(scan_end::<T>),
+            scan_rescan: Some(scan_rescan::<T>),
+            scan_getnextslot: Some(scan_getnextslot::<T>),
+            tuple_insert: Some(tuple_insert::<T>),
+            multi_insert: Some(multi_insert::<T>),
+            ..*heap_table_am()
+        };
+        Box::into_raw(Box::new(routine))
+    });
+
+    unsafe { PgBox::from_pg(*routine) }
+}
+
+static mut ROUTINES: Option<HashMap<TypeId, *mut pg_sys::TableAmRoutine>> = None;
+
+// `#[pg_guard]` can't wrap generic functions, so those for the access method guard themselves
+
+unsafe extern "C" fn slot_callbacks<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+) -> *const pg_sys::TupleTableSlotOps {
+    guard::guard(|| T::slot_callbacks(&PgRelation::from_pg(rel)))
+}
+
+unsafe extern "C" fn scan_begin<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    snapshot: pg_sys::Snapshot,
+    nkeys: i32,
+    key: *mut pg_sys::ScanKeyData,
+    pscan: pg_sys::ParallelTableScanDesc,
+    flags: u32,
+) -> pg_sys::TableScanDesc {
+    guard::guard(|| {
+        T::scan_begin(
+            &PgRelation::from_pg(rel),
+            snapshot,
+            nkeys,
+            key,
+            pscan,
+            flags,
+        )
+    })
+}
+
+unsafe extern "C" fn scan_end<T: TableAccessMethod>(scan: pg_sys::TableScanDesc) {
+    guard::guard(|| T::scan_end(scan))
+}
+
+unsafe extern "C" fn scan_rescan<T: TableAccessMethod>(
+    scan: pg_sys::TableScanDesc,
+    key: *mut pg_sys::ScanKeyData,
+    set_params: bool,
+    allow_strat: bool,
+    allow_sync: bool,
+    allow_pagemode: bool,
+) {
+    guard::guard(|| {
+        T::scan_rescan(
+            scan,
+            key,
+            set_params,
+            allow_strat,
+            allow_sync,
+            allow_pagemode,
+        )
+    })
+}
+
+unsafe extern "C" fn scan_getnextslot<T: TableAccessMethod>(
+    scan: pg_sys::TableScanDesc,
+    direction: pg_sys::ScanDirection,
+    slot: *mut pg_sys::TupleTableSlot,
+) -> bool {
+    guard::guard(|| T::scan_getnextslot(scan, direction, slot))
+}
+
+unsafe extern "C" fn tuple_insert<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    slot: *mut pg_sys::TupleTableSlot,
+    cid: pg_sys::CommandId,
+    options: i32,
+    bistate: *mut pg_sys::BulkInsertStateData,
+) {
+    guard::guard(|| T::tuple_insert(&PgRelation::from_pg(rel), slot, cid, options, bistate))
+}
+
+unsafe extern "C" fn multi_insert<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    slots: *mut *mut pg_sys::TupleTableSlot,
+    nslots: i32,
+    cid: pg_sys::CommandId,
+    options: i32,
+    bistate: *mut pg_sys::BulkInsertStateData,
+) {
+    guard::guard(|| {
+        let slots = std::slice::from_raw_parts_mut(slots, nslots as usize);
+        T::multi_insert(&PgRelation::from_pg(rel), slots, cid, options, bistate)
+    })
+}
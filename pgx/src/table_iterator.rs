@@ -0,0 +1,107 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Returning the rows of a `RETURNS TABLE (...)` function as structs, whose fields are its columns
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[derive(TableRow)]
+//! struct Dog {
+//!     name: String,
+//!     #[table_row(rename = "scritches")]
+//!     scritch_count: Option<i32>,
+//! }
+//!
+//! #[pg_extern]
+//! fn good_dogs() -> TableIterator<'static, Dog> {
+//!     TableIterator::new(vec![
+//!         Dog { name: "Brandy".into(), scritch_count: Some(42) },
+//!         Dog { name: "Nami".into(), scritch_count: None },
+//!     ])
+//! }
+//! ```
+//!
+//! Which is created as `good_dogs() RETURNS TABLE (name text, scritches integer)`.
+use crate::{pg_sys, IntoDatum};
+use core::any::TypeId;
+
+/// One of a [`TableRow`]'s columns: the `TypeId`, source, and full path of its type, the module
+/// path of its type, and its name
+pub type TableColumn = (
+    TypeId,
+    &'static str,
+    &'static str,
+    String,
+    Option<&'static str>,
+);
+
+/// A struct which is a row of a `RETURNS TABLE (...)` function, usually by `#[derive(TableRow)]`
+pub trait TableRow {
+    /// The row's columns, in the order of `RETURNS TABLE (...)`
+    fn table_columns() -> Vec<TableColumn>;
+
+    /// The row's values, in the same order as its columns, where `None` is NULL
+    fn into_datums(self) -> Vec<Option<pg_sys::Datum>>;
+}
+
+/// The rows returned by a `RETURNS TABLE (...)` function, whose columns are declared by `T`'s
+/// [`TableRow`] implementation
+pub struct TableIterator<'a, T> {
+    iter: Box<dyn Iterator<Item = T> + 'a>,
+}
+
+impl<'a, T: TableRow> TableIterator<'a, T> {
+    pub fn new<I>(rows: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: 'a,
+    {
+        TableIterator {
+            iter: Box::new(rows.into_iter()),
+        }
+    }
+
+    /// Return the single row `row`
+    pub fn once(row: T) -> Self
+    where
+        T: 'a,
+    {
+        Self::new(std::iter::once(row))
+    }
+}
+
+impl<'a, T> Iterator for TableIterator<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// The [`TableColumn`] of a column named `name` of type `T`
+pub fn table_column<T: IntoDatum + 'static>(
+    source: &'static str,
+    name: &'static str,
+) -> TableColumn {
+    let full_path = core::any::type_name::<T>();
+    let mut path_items: Vec<_> = full_path.split("::").collect();
+    let _ = path_items.pop(); // Drop the one we don't want.
+    (
+        TypeId::of::<T>(),
+        source,
+        full_path,
+        path_items.join("::"),
+        Some(name),
+    )
+}
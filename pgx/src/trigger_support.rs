@@ -7,9 +7,15 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 
-//! Helper functions for working with custom Rust trigger functions
+//! Helper functions for working with custom Rust trigger functions, and the [`PgTrigger`]
+//! passed to `#[pg_trigger]` functions
 
-use crate::{is_a, pg_sys};
+use crate::{
+    is_a, pg_sys, FromHeapTuple, IntoHeapTuple, PgBox, PgHeapTuple, PgHeapTupleError, PgRelation,
+    PgTupleDesc,
+};
+use std::ffi::CStr;
+use std::fmt::Display;
 
 #[inline]
 pub unsafe fn called_as_trigger(fcinfo: pg_sys::FunctionCallInfo) -> bool {
@@ -61,3 +67,391 @@ pub fn trigger_fired_after(event: u32) -> bool {
 pub fn trigger_fired_instead(event: u32) -> bool {
     event & pg_sys::TRIGGER_EVENT_TIMINGMASK == pg_sys::TRIGGER_EVENT_INSTEAD
 }
+
+/// The operation which fired a trigger
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerEvent {
+    Insert,
+    Update,
+    Delete,
+    Truncate,
+}
+
+/// When a trigger fires, relative to the operation which fired it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerTiming {
+    Before,
+    After,
+    InsteadOf,
+}
+
+/// Whether a trigger fires once for each row or once for each statement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerLevel {
+    Row,
+    Statement,
+}
+
+/// The firing of a trigger, as passed to a `#[pg_trigger]` function
+///
+/// The `OLD` and `NEW` rows are those of plpgsql: `OLD` is the row being updated or deleted, and
+/// `NEW` is the row being inserted or what an updated row is becoming.  Neither is set for a
+/// statement-level trigger.
+pub struct PgTrigger {
+    trigdata: PgBox<pg_sys::TriggerData>,
+    relation: PgRelation,
+}
+
+impl PgTrigger {
+    /// The trigger being fired by the trigger manager, which calls the function with `fcinfo`
+    ///
+    /// Panics if the function wasn't called as a trigger.
+    ///
+    /// ## Safety
+    ///
+    /// `fcinfo` must be the `pg_sys::FunctionCallInfo` of the function being called
+    pub unsafe fn from_fcinfo(fcinfo: pg_sys::FunctionCallInfo) -> Self {
+        if !called_as_trigger(fcinfo) {
+            panic!("not called by trigger manager");
+        }
+
+        let trigdata = PgBox::<pg_sys::TriggerData>::from_pg((*fcinfo).context as _);
+        let relation = PgRelation::from_pg(trigdata.tg_relation);
+        let trigger = PgTrigger { trigdata, relation };
+        crate::trigger_test::record_fired(&trigger);
+        trigger
+    }
+
+    /// The name of the trigger
+    pub fn name(&self) -> &str {
+        unsafe { CStr::from_ptr((*self.trigdata.tg_trigger).tgname) }
+            .to_str()
+            .expect("trigger name is not valid UTF8")
+    }
+
+    /// The arguments given to the function in `CREATE TRIGGER`
+    pub fn args(&self) -> Vec<&str> {
+        unsafe {
+            let trigger = &*self.trigdata.tg_trigger;
+            (0..trigger.tgnargs as usize)
+                .map(|i| {
+                    CStr::from_ptr(*trigger.tgargs.add(i))
+                        .to_str()
+                        .expect("trigger argument is not valid UTF8")
+                })
+                .collect()
+        }
+    }
+
+    pub fn event(&self) -> TriggerEvent {
+        let event = self.trigdata.tg_event;
+        if trigger_fired_by_insert(event) {
+            TriggerEvent::Insert
+        } else if trigger_fired_by_update(event) {
+            TriggerEvent::Update
+        } else if trigger_fired_by_delete(event) {
+            TriggerEvent::Delete
+        } else {
+            TriggerEvent::Truncate
+        }
+    }
+
+    pub fn timing(&self) -> TriggerTiming {
+        let event = self.trigdata.tg_event;
+        if trigger_fired_before(event) {
+            TriggerTiming::Before
+        } else if trigger_fired_after(event) {
+            TriggerTiming::After
+        } else {
+            TriggerTiming::InsteadOf
+        }
+    }
+
+    pub fn level(&self) -> TriggerLevel {
+        if trigger_fired_for_row(self.trigdata.tg_event) {
+            TriggerLevel::Row
+        } else {
+            TriggerLevel::Statement
+        }
+    }
+
+    /// Is this a `CONSTRAINT TRIGGER`?
+    pub fn is_constraint_trigger(&self) -> bool {
+        unsafe { (*self.trigdata.tg_trigger).tgconstraint != pg_sys::InvalidOid }
+    }
+
+    /// Is this a `DEFERRABLE` constraint trigger, which can be fired at the end of the transaction
+    /// rather than after each statement?
+    pub fn is_deferrable(&self) -> bool {
+        unsafe { (*self.trigdata.tg_trigger).tgdeferrable }
+    }
+
+    /// Is this an `INITIALLY DEFERRED` constraint trigger, which fires at the end of the
+    /// transaction unless `SET CONSTRAINTS ... IMMEDIATE` makes it fire after each statement?
+    pub fn is_initially_deferred(&self) -> bool {
+        unsafe { (*self.trigdata.tg_trigger).tginitdeferred }
+    }
+
+    /// How many savepoints (or exception blocks) deep the trigger is fired, where 1 is the
+    /// transaction itself
+    ///
+    /// A deferred trigger fires at the savepoint `SET CONSTRAINTS ... IMMEDIATE` is run in, or
+    /// at 1 when the transaction commits.
+    pub fn transaction_nest_level(&self) -> i32 {
+        unsafe { pg_sys::GetCurrentTransactionNestLevel() }
+    }
+
+    /// The subtransaction the trigger is fired in, which is 1 outside of any savepoint
+    pub fn subtransaction_id(&self) -> pg_sys::SubTransactionId {
+        unsafe { pg_sys::GetCurrentSubTransactionId() }
+    }
+
+    /// Is the trigger fired within a savepoint (or exception block), whose rollback undoes the
+    /// row it was fired for?
+    pub fn in_subtransaction(&self) -> bool {
+        unsafe { pg_sys::IsSubTransaction() }
+    }
+
+    /// The table (or view) the trigger is on
+    pub fn relation(&self) -> &PgRelation {
+        &self.relation
+    }
+
+    /// The `OLD` row, for a row-level trigger fired by `UPDATE` or `DELETE`
+    pub fn old(&self) -> Option<PgHeapTuple<'_>> {
+        match (self.level(), self.event()) {
+            (TriggerLevel::Row, TriggerEvent::Update | TriggerEvent::Delete) => {
+                Some(self.heap_tuple(self.trigdata.tg_trigtuple))
+            }
+            _ => None,
+        }
+    }
+
+    /// The `NEW` row, for a row-level trigger fired by `INSERT` or `UPDATE`
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(&self) -> Option<PgHeapTuple<'_>> {
+        match (self.level(), self.event()) {
+            (TriggerLevel::Row, TriggerEvent::Insert) => {
+                Some(self.heap_tuple(self.trigdata.tg_trigtuple))
+            }
+            (TriggerLevel::Row, TriggerEvent::Update) => {
+                Some(self.heap_tuple(self.trigdata.tg_newtuple))
+            }
+            _ => None,
+        }
+    }
+
+    /// The row a row-level trigger is fired for, which is `NEW` for `INSERT` and `UPDATE`, and
+    /// `OLD` for `DELETE`
+    ///
+    /// An `INSTEAD OF` trigger on a view returns it (or, for `INSERT` and `UPDATE`, a copy
+    /// modified as by [`PgTrigger::new_with`]) once it has done the operation, to report that the
+    /// row was affected, and as the row for a `RETURNING` clause.  Returning `None` instead
+    /// reports that the row wasn't affected.
+    pub fn affected_row(&self) -> Option<PgHeapTuple<'_>> {
+        match self.event() {
+            TriggerEvent::Insert | TriggerEvent::Update => self.new(),
+            TriggerEvent::Delete => self.old(),
+            TriggerEvent::Truncate => None,
+        }
+    }
+
+    /// The `OLD` row as a [`FromHeapTuple`] type, such as a struct with `#[derive(FromHeapTuple)]`
+    pub fn old_as<T: FromHeapTuple>(&self) -> Result<Option<T>, PgHeapTupleError> {
+        self.old().map(|old| T::from_heap_tuple(&old)).transpose()
+    }
+
+    /// The `NEW` row as a [`FromHeapTuple`] type, such as a struct with `#[derive(FromHeapTuple)]`
+    pub fn new_as<T: FromHeapTuple>(&self) -> Result<Option<T>, PgHeapTupleError> {
+        self.new().map(|new| T::from_heap_tuple(&new)).transpose()
+    }
+
+    /// A copy of the `NEW` row with the fields of `row` written to it, to be returned as the
+    /// row to store
+    ///
+    /// Where there's no `NEW` row, the attributes `row` doesn't have a field for are NULL.
+    pub fn new_with<T: IntoHeapTuple>(&self, row: T) -> Result<PgHeapTuple<'_>, PgHeapTupleError> {
+        let mut tuple = match self.new() {
+            Some(new) => new,
+            None => PgHeapTuple::new_from_tupdesc(PgTupleDesc::from_relation(&self.relation)),
+        };
+        row.into_heap_tuple(&mut tuple)?;
+        Ok(tuple)
+    }
+
+    /// The `REFERENCING OLD TABLE` transition table, of the rows updated or deleted by the
+    /// statement, for an `AFTER` trigger which declares one
+    pub fn old_table(&self) -> Option<TransitionTable<'_>> {
+        TransitionTable::new(self.trigdata.tg_oldtable, &self.relation)
+    }
+
+    /// The `REFERENCING NEW TABLE` transition table, of the rows inserted by the statement or
+    /// what its updated rows became, for an `AFTER` trigger which declares one
+    pub fn new_table(&self) -> Option<TransitionTable<'_>> {
+        TransitionTable::new(self.trigdata.tg_newtable, &self.relation)
+    }
+
+    /// The `pg_sys::TriggerData` the trigger manager passed to the function
+    pub fn trigger_data(&self) -> &PgBox<pg_sys::TriggerData> {
+        &self.trigdata
+    }
+
+    fn heap_tuple(&self, tuple: pg_sys::HeapTuple) -> PgHeapTuple<'_> {
+        unsafe { PgHeapTuple::from_heap_tuple(PgTupleDesc::from_relation(&self.relation), tuple) }
+    }
+}
+
+/// The rows of a transition table, which are those affected by the statement that fired a
+/// trigger
+pub struct TransitionTable<'a> {
+    store: *mut pg_sys::Tuplestorestate,
+    relation: &'a PgRelation,
+}
+
+impl<'a> TransitionTable<'a> {
+    fn new(store: *mut pg_sys::Tuplestorestate, relation: &'a PgRelation) -> Option<Self> {
+        if store.is_null() {
+            None
+        } else {
+            Some(TransitionTable { store, relation })
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        unsafe { pg_sys::tuplestore_tuple_count(self.store) as usize }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over the rows, as [`PgHeapTuple`]s
+    pub fn iter(&self) -> TransitionTableIterator<'a> {
+        TransitionTableIterator::new(self.store, self.relation)
+    }
+
+    /// Iterate over the rows as a [`FromHeapTuple`] type, such as a struct with
+    /// `#[derive(FromHeapTuple)]`
+    pub fn rows<T: FromHeapTuple + 'a>(
+        &self,
+    ) -> impl Iterator<Item = Result<T, PgHeapTupleError>> + 'a {
+        self.iter().map(|tuple| T::from_heap_tuple(&tuple))
+    }
+}
+
+impl<'a> IntoIterator for &TransitionTable<'a> {
+    type Item = PgHeapTuple<'a>;
+    type IntoIter = TransitionTableIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the rows of a [`TransitionTable`], each of which is copied into the
+/// `CurrentMemoryContext`
+pub struct TransitionTableIterator<'a> {
+    store: *mut pg_sys::Tuplestorestate,
+    read_pointer: i32,
+    slot: *mut pg_sys::TupleTableSlot,
+    relation: &'a PgRelation,
+}
+
+impl<'a> TransitionTableIterator<'a> {
+    fn new(store: *mut pg_sys::Tuplestorestate, relation: &'a PgRelation) -> Self {
+        unsafe {
+            // each iterator reads with its own read pointer, as the scan of a transition table in
+            // a query does, so that it doesn't disturb any other reader
+            let read_pointer =
+                pg_sys::tuplestore_alloc_read_pointer(store, pg_sys::EXEC_FLAG_REWIND as i32);
+            pg_sys::tuplestore_select_read_pointer(store, read_pointer);
+            pg_sys::tuplestore_rescan(store);
+
+            #[cfg(any(feature = "pg10", feature = "pg11"))]
+            let slot = pg_sys::MakeSingleTupleTableSlot(relation.rd_att);
+
+            #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+            let slot =
+                pg_sys::MakeSingleTupleTableSlot(relation.rd_att, &pg_sys::TTSOpsMinimalTuple);
+
+            TransitionTableIterator {
+                store,
+                read_pointer,
+                slot,
+                relation,
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for TransitionTableIterator<'a> {
+    type Item = PgHeapTuple<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe {
+            pg_sys::tuplestore_select_read_pointer(self.store, self.read_pointer);
+            if !pg_sys::tuplestore_gettupleslot(self.store, true, false, self.slot) {
+                return None;
+            }
+
+            #[cfg(any(feature = "pg10", feature = "pg11"))]
+            let tuple = pg_sys::ExecCopySlotTuple(self.slot);
+
+            #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+            let tuple = ((*(*self.slot).tts_ops).copy_heap_tuple.unwrap())(self.slot);
+
+            Some(PgHeapTuple::from_heap_tuple(
+                PgTupleDesc::from_relation(self.relation),
+                tuple,
+            ))
+        }
+    }
+}
+
+impl<'a> Drop for TransitionTableIterator<'a> {
+    fn drop(&mut self) {
+        unsafe { pg_sys::ExecDropSingleTupleTableSlot(self.slot) }
+    }
+}
+
+/// What a `#[pg_trigger]` function returns, which is converted to the row the trigger manager
+/// stores
+///
+/// A null row skips the operation on the row, when returned from a `BEFORE` row-level trigger,
+/// and reports that the row wasn't affected, when returned from an `INSTEAD OF` trigger, which
+/// otherwise returns its [`PgTrigger::affected_row`].
+/// The result of `AFTER` and statement-level triggers is ignored, so they can return `()`, which
+/// is a null row.
+pub trait IntoTriggerResult {
+    fn into_trigger_result(self) -> pg_sys::Datum;
+}
+
+impl<'a> IntoTriggerResult for PgHeapTuple<'a> {
+    fn into_trigger_result(self) -> pg_sys::Datum {
+        self.into_pg() as pg_sys::Datum
+    }
+}
+
+impl IntoTriggerResult for () {
+    fn into_trigger_result(self) -> pg_sys::Datum {
+        0 as pg_sys::Datum
+    }
+}
+
+impl<'a> IntoTriggerResult for Option<PgHeapTuple<'a>> {
+    fn into_trigger_result(self) -> pg_sys::Datum {
+        match self {
+            Some(tuple) => tuple.into_trigger_result(),
+            None => 0 as pg_sys::Datum,
+        }
+    }
+}
+
+impl<T: IntoTriggerResult, E: Display> IntoTriggerResult for Result<T, E> {
+    fn into_trigger_result(self) -> pg_sys::Datum {
+        match self {
+            Ok(result) => result.into_trigger_result(),
+            Err(e) => error!("{}", e),
+        }
+    }
+}
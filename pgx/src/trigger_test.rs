@@ -0,0 +1,165 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Testing `#[pg_trigger]` functions, from a `#[pg_test]`, with a [`TriggerTest`] scratch table
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//! use serde::Deserialize;
+//!
+//! #[pg_trigger]
+//! fn shout(trigger: &PgTrigger) -> Result<PgHeapTuple<'_>, PgHeapTupleError> {
+//!     let mut new = trigger.new().expect("not fired by INSERT");
+//!     let name = new.get_by_name::<String>("name")?.unwrap_or_default();
+//!     new.set_by_name("name", name.to_uppercase())?;
+//!     Ok(new)
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct Dog {
+//!     name: String,
+//! }
+//!
+//! fn test_shout() {
+//!     let test = TriggerTest::new("dogs", "id serial, name text");
+//!     test.attach("shout", "BEFORE INSERT");
+//!
+//!     let fired = test.execute("INSERT INTO dogs (name) VALUES ('brandy')");
+//!     assert_eq!(fired.len(), 1);
+//!     assert_eq!(fired[0].event, TriggerEvent::Insert);
+//!     assert_eq!(fired[0].new.as_ref().unwrap()["name"], "brandy");
+//!
+//!     let dogs = test.rows::<Dog>("id");
+//!     assert_eq!(dogs[0].name, "BRANDY");
+//! }
+//! ```
+use crate::{
+    direct_function_call, pg_sys, sql, Json, JsonB, PgHeapTuple, PgTrigger, Spi, TriggerEvent,
+    TriggerLevel, TriggerTiming,
+};
+use serde::de::DeserializeOwned;
+use std::cell::RefCell;
+
+thread_local! {
+    static FIRED: RefCell<Option<Vec<FiredTrigger>>> = RefCell::new(None);
+}
+
+/// A firing of a `#[pg_trigger]` function, as recorded by [`TriggerTest::execute`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FiredTrigger {
+    /// The name of the trigger
+    pub name: String,
+    /// The name of the table the trigger fired on
+    pub table: String,
+    pub event: TriggerEvent,
+    pub timing: TriggerTiming,
+    pub level: TriggerLevel,
+    /// The arguments given to the function in `CREATE TRIGGER`
+    pub args: Vec<String>,
+    /// The `OLD` row, as JSON, as it was passed to the function
+    pub old: Option<serde_json::Value>,
+    /// The `NEW` row, as JSON, as it was passed to the function
+    pub new: Option<serde_json::Value>,
+}
+
+/// A temporary table, which is dropped at the end of the transaction, to attach triggers to and
+/// run DML against in a `#[pg_test]`
+pub struct TriggerTest {
+    table: String,
+}
+
+impl TriggerTest {
+    /// Create the temporary table `table`, whose columns are `columns`, as they would be written
+    /// in `CREATE TABLE`
+    pub fn new(table: &str, columns: &str) -> Self {
+        Spi::run(&format!(
+            "CREATE TEMPORARY TABLE {} ({}) ON COMMIT DROP",
+            sql::quote_identifier(table),
+            columns
+        ));
+        TriggerTest {
+            table: table.to_string(),
+        }
+    }
+
+    /// The name of the table
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// Attach the trigger function `function`, such as `"tests.my_trigger"`, to the table, firing
+    /// `when`, such as `"BEFORE INSERT OR UPDATE"`, for each row
+    ///
+    /// The trigger is named after the function.
+    pub fn attach(&self, function: &str, when: &str) -> &Self {
+        self.attach_with(function, when, "FOR EACH ROW")
+    }
+
+    /// Attach the trigger function `function` to the table, firing `when`, and the rest of the
+    /// `CREATE TRIGGER` after the table's name, such as `"FOR EACH STATEMENT"`, or
+    /// `"FOR EACH ROW WHEN (NEW.id > 0)"`
+    pub fn attach_with(&self, function: &str, when: &str, rest: &str) -> &Self {
+        let name = function.rsplit('.').next().unwrap_or(function);
+        Spi::run(&format!(
+            "CREATE TRIGGER {} {} ON {} {} EXECUTE PROCEDURE {}()",
+            sql::quote_identifier(name),
+            when,
+            sql::quote_identifier(&self.table),
+            rest,
+            function
+        ));
+        self
+    }
+
+    /// Run `query`, such as an `INSERT` into the table, and return each firing of a
+    /// `#[pg_trigger]` function while it ran, in the order they fired
+    pub fn execute(&self, query: &str) -> Vec<FiredTrigger> {
+        FIRED.with(|fired| fired.replace(Some(Vec::new())));
+        Spi::run(query);
+        FIRED.with(|fired| fired.take().unwrap_or_default())
+    }
+
+    /// The table's rows, ordered by `order_by`, such as `"id"`, each deserialized from its JSON
+    /// object, whose keys are the column names
+    pub fn rows<T: DeserializeOwned>(&self, order_by: &str) -> Vec<T> {
+        let rows = Spi::get_one::<JsonB>(&format!(
+            "SELECT coalesce(jsonb_agg(t ORDER BY {}), '[]') FROM {} t",
+            order_by,
+            sql::quote_identifier(&self.table)
+        ))
+        .expect("jsonb_agg was NULL");
+        serde_json::from_value(rows.0).expect("rows could not be deserialized")
+    }
+}
+
+/// Record `trigger` firing, if a [`TriggerTest::execute`] is running
+pub(crate) fn record_fired(trigger: &PgTrigger) {
+    FIRED.with(|fired| {
+        if let Some(fired) = fired.borrow_mut().as_mut() {
+            fired.push(FiredTrigger {
+                name: trigger.name().to_string(),
+                table: trigger.relation().name().to_string(),
+                event: trigger.event(),
+                timing: trigger.timing(),
+                level: trigger.level(),
+                args: trigger.args().into_iter().map(String::from).collect(),
+                old: trigger.old().map(|old| row_to_json(&old)),
+                new: trigger.new().map(|new| row_to_json(&new)),
+            });
+        }
+    })
+}
+
+fn row_to_json(row: &PgHeapTuple) -> serde_json::Value {
+    let json = unsafe {
+        let composite = pg_sys::heap_copy_tuple_as_datum(row.as_ptr(), row.tupdesc().as_ptr());
+        direct_function_call::<Json>(pg_sys::row_to_json, vec![Some(composite)])
+    };
+    json.expect("row_to_json was NULL").0
+}
@@ -0,0 +1,271 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! A parsed view of the utility (DDL and other non-planned) statements handed to
+//! [`PgHooks::process_utility_hook`](crate::PgHooks::process_utility_hook)
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! fn audit(pstmt: &PgBox<pg_sys::PlannedStmt>) {
+//!     match UtilityStatement::from_planned_stmt(pstmt) {
+//!         UtilityStatement::DropObject { names, .. } => {
+//!             info!("dropping {}", names.join(", "));
+//!         }
+//!         UtilityStatement::AlterRole { role, .. } => {
+//!             info!("altering role {}", role);
+//!         }
+//!         _ => {}
+//!     }
+//! }
+//! ```
+use crate::{is_a, node_to_string, pg_sys, PgBox, PgList};
+use std::ffi::CStr;
+use std::fmt::{Display, Formatter};
+use std::os::raw::c_char;
+
+/// A possibly schema-qualified name, such as of a table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QualifiedName {
+    pub schema: Option<String>,
+    pub name: String,
+}
+
+impl Display for QualifiedName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.schema {
+            Some(schema) => write!(f, "{}.{}", schema, self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// The common kinds of utility statement, with what's usually wanted from them copied out
+///
+/// Anything else is an [`UtilityStatement::Other`], with a pointer to the statement's node, whose
+/// tag can be checked with [`is_a`].
+///
+/// Role names are as written in the statement.  The special roles are named by their keywords,
+/// `PUBLIC`, `SESSION_USER`, and `CURRENT_USER`, which includes `CURRENT_ROLE`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UtilityStatement {
+    /// `CREATE TABLE`
+    CreateTable {
+        table: QualifiedName,
+        if_not_exists: bool,
+    },
+    /// `ALTER TABLE`, and `ALTER` of indexes, views, sequences, and the like
+    AlterTable {
+        table: QualifiedName,
+        missing_ok: bool,
+    },
+    /// Any `DROP` other than of a role or database, with the names of the objects being dropped
+    DropObject {
+        object_type: pg_sys::ObjectType,
+        names: Vec<String>,
+        missing_ok: bool,
+        cascade: bool,
+    },
+    /// `TRUNCATE`
+    Truncate {
+        tables: Vec<QualifiedName>,
+        cascade: bool,
+    },
+    /// `GRANT` or `REVOKE` of privileges on objects, where `privileges` is `None` for
+    /// `ALL PRIVILEGES`
+    Grant {
+        is_grant: bool,
+        privileges: Option<Vec<String>>,
+        grantees: Vec<String>,
+    },
+    /// `CREATE ROLE`, `CREATE USER`, or `CREATE GROUP`
+    CreateRole { role: String, options: Vec<String> },
+    /// `ALTER ROLE`, with the names of the options being changed, such as `superuser` or
+    /// `password`
+    AlterRole { role: String, options: Vec<String> },
+    /// `DROP ROLE`
+    DropRole { roles: Vec<String>, missing_ok: bool },
+    /// `CREATE EXTENSION`
+    CreateExtension { name: String, if_not_exists: bool },
+    /// Any other statement
+    Other(*mut pg_sys::Node),
+}
+
+impl UtilityStatement {
+    /// The utility statement that `pstmt` wraps
+    pub fn from_planned_stmt(pstmt: &PgBox<pg_sys::PlannedStmt>) -> Self {
+        unsafe { UtilityStatement::from_node(pstmt.utilityStmt) }
+    }
+
+    /// Parse the utility statement `node`
+    ///
+    /// ### Safety
+    ///
+    /// `node` must point to a valid utility statement
+    pub unsafe fn from_node(node: *mut pg_sys::Node) -> Self {
+        match (*node).type_ {
+            pg_sys::NodeTag_T_CreateStmt => {
+                let stmt = node as *mut pg_sys::CreateStmt;
+                UtilityStatement::CreateTable {
+                    table: range_var_name((*stmt).relation),
+                    if_not_exists: (*stmt).if_not_exists,
+                }
+            }
+            pg_sys::NodeTag_T_AlterTableStmt => {
+                let stmt = node as *mut pg_sys::AlterTableStmt;
+                UtilityStatement::AlterTable {
+                    table: range_var_name((*stmt).relation),
+                    missing_ok: (*stmt).missing_ok,
+                }
+            }
+            pg_sys::NodeTag_T_DropStmt => {
+                let stmt = node as *mut pg_sys::DropStmt;
+                UtilityStatement::DropObject {
+                    object_type: (*stmt).removeType,
+                    names: PgList::<pg_sys::Node>::from_pg((*stmt).objects)
+                        .iter_ptr()
+                        .map(|object| object_name(object))
+                        .collect(),
+                    missing_ok: (*stmt).missing_ok,
+                    cascade: (*stmt).behavior == pg_sys::DropBehavior_DROP_CASCADE,
+                }
+            }
+            pg_sys::NodeTag_T_TruncateStmt => {
+                let stmt = node as *mut pg_sys::TruncateStmt;
+                UtilityStatement::Truncate {
+                    tables: PgList::<pg_sys::RangeVar>::from_pg((*stmt).relations)
+                        .iter_ptr()
+                        .map(|relation| range_var_name(relation))
+                        .collect(),
+                    cascade: (*stmt).behavior == pg_sys::DropBehavior_DROP_CASCADE,
+                }
+            }
+            pg_sys::NodeTag_T_GrantStmt => {
+                let stmt = node as *mut pg_sys::GrantStmt;
+                let privileges = PgList::<pg_sys::AccessPriv>::from_pg((*stmt).privileges);
+                UtilityStatement::Grant {
+                    is_grant: (*stmt).is_grant,
+                    privileges: if privileges.is_empty() {
+                        None
+                    } else {
+                        Some(
+                            privileges
+                                .iter_ptr()
+                                .map(|privilege| string((*privilege).priv_name))
+                                .collect(),
+                        )
+                    },
+                    grantees: role_names((*stmt).grantees),
+                }
+            }
+            pg_sys::NodeTag_T_CreateRoleStmt => {
+                let stmt = node as *mut pg_sys::CreateRoleStmt;
+                UtilityStatement::CreateRole {
+                    role: string((*stmt).role),
+                    options: option_names((*stmt).options),
+                }
+            }
+            pg_sys::NodeTag_T_AlterRoleStmt => {
+                let stmt = node as *mut pg_sys::AlterRoleStmt;
+                UtilityStatement::AlterRole {
+                    role: role_name((*stmt).role),
+                    options: option_names((*stmt).options),
+                }
+            }
+            pg_sys::NodeTag_T_DropRoleStmt => {
+                let stmt = node as *mut pg_sys::DropRoleStmt;
+                UtilityStatement::DropRole {
+                    roles: role_names((*stmt).roles),
+                    missing_ok: (*stmt).missing_ok,
+                }
+            }
+            pg_sys::NodeTag_T_CreateExtensionStmt => {
+                let stmt = node as *mut pg_sys::CreateExtensionStmt;
+                UtilityStatement::CreateExtension {
+                    name: string((*stmt).extname),
+                    if_not_exists: (*stmt).if_not_exists,
+                }
+            }
+            _ => UtilityStatement::Other(node),
+        }
+    }
+}
+
+unsafe fn string(s: *const c_char) -> String {
+    if s.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(s).to_string_lossy().into_owned()
+    }
+}
+
+unsafe fn range_var_name(range_var: *mut pg_sys::RangeVar) -> QualifiedName {
+    QualifiedName {
+        schema: if (*range_var).schemaname.is_null() {
+            None
+        } else {
+            Some(string((*range_var).schemaname))
+        },
+        name: string((*range_var).relname),
+    }
+}
+
+/// The name of one of a `DROP` statement's objects, which depends on the kind of object
+unsafe fn object_name(object: *mut pg_sys::Node) -> String {
+    match (*object).type_ {
+        pg_sys::NodeTag_T_String => string((*(object as *mut pg_sys::Value)).val.str_),
+        pg_sys::NodeTag_T_List => {
+            let names = PgList::<pg_sys::Node>::from_pg(object as *mut pg_sys::List);
+            let separator = if names
+                .iter_ptr()
+                .all(|name| is_a(name, pg_sys::NodeTag_T_String))
+            {
+                "."
+            } else {
+                // such as the pair of types in a `DROP CAST`
+                ", "
+            };
+            names
+                .iter_ptr()
+                .map(|name| object_name(name))
+                .collect::<Vec<_>>()
+                .join(separator)
+        }
+        pg_sys::NodeTag_T_ObjectWithArgs => object_name(
+            (*(object as *mut pg_sys::ObjectWithArgs)).objname as *mut pg_sys::Node,
+        ),
+        pg_sys::NodeTag_T_TypeName => {
+            string(pg_sys::TypeNameToString(object as *const pg_sys::TypeName))
+        }
+        _ => node_to_string(object).unwrap_or_default().to_string(),
+    }
+}
+
+unsafe fn role_name(role: *mut pg_sys::RoleSpec) -> String {
+    match (*role).roletype {
+        pg_sys::RoleSpecType_ROLESPEC_CSTRING => string((*role).rolename),
+        pg_sys::RoleSpecType_ROLESPEC_PUBLIC => "PUBLIC".to_string(),
+        pg_sys::RoleSpecType_ROLESPEC_SESSION_USER => "SESSION_USER".to_string(),
+        _ => "CURRENT_USER".to_string(),
+    }
+}
+
+unsafe fn role_names(roles: *mut pg_sys::List) -> Vec<String> {
+    PgList::<pg_sys::RoleSpec>::from_pg(roles)
+        .iter_ptr()
+        .map(|role| role_name(role))
+        .collect()
+}
+
+unsafe fn option_names(options: *mut pg_sys::List) -> Vec<String> {
+    PgList::<pg_sys::DefElem>::from_pg(options)
+        .iter_ptr()
+        .map(|option| string((*option).defname))
+        .collect()
+}
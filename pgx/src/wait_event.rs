@@ -0,0 +1,123 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Wait events, which tell `pg_stat_activity` what a backend is waiting for
+//!
+//! Postgres reports its own waits, such as for locks or I/O, but an extension that blocks on a
+//! latch, a socket, or a child process should report that too, as a [`WaitEvent`]:
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! static FLUSH: WaitEvent = WaitEvent::new("MyExtensionFlush");
+//!
+//! #[pg_extern]
+//! fn flush() {
+//!     FLUSH.during(|| {
+//!         // write everything out
+//!     });
+//! }
+//! ```
+//!
+//! Postgres only learned to register extensions' own wait event names in version 17, so in
+//! the versions pgx supports, every extension wait event is shown in `pg_stat_activity` as
+//! `wait_event_type = 'Extension'` and `wait_event = 'Extension'`.  Each named event still has
+//! its own, stable id, derived from its name, which tools reading `wait_event_info` directly
+//! can tell apart.
+use crate::pg_sys;
+
+/// Something a backend can wait for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitEvent {
+    name: &'static str,
+    info: u32,
+}
+
+impl WaitEvent {
+    /// The generic "Extension" event, which Postgres uses for waits an extension doesn't
+    /// otherwise describe
+    pub const EXTENSION: WaitEvent = WaitEvent {
+        name: "Extension",
+        info: pg_sys::PG_WAIT_EXTENSION,
+    };
+
+    /// An extension wait event called `name`
+    ///
+    /// Its id is derived from `name`, so it's the same in every backend.
+    pub const fn new(name: &'static str) -> Self {
+        WaitEvent {
+            name,
+            info: pg_sys::PG_WAIT_EXTENSION | event_id(name.as_bytes()) as u32,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The event's class and id, as Postgres' wait functions take them
+    pub fn info(&self) -> u32 {
+        self.info
+    }
+
+    /// Report this backend as waiting for this event while `f` runs
+    pub fn during<R, F: FnOnce() -> R>(&self, f: F) -> R {
+        let _reported = ReportedWait::start(self.info);
+        f()
+    }
+}
+
+impl Default for WaitEvent {
+    fn default() -> Self {
+        WaitEvent::EXTENSION
+    }
+}
+
+/// Clears the reported wait event when dropped, even if the wait panics
+struct ReportedWait;
+
+impl ReportedWait {
+    fn start(info: u32) -> Self {
+        unsafe { report_wait_event(info) };
+        ReportedWait
+    }
+}
+
+impl Drop for ReportedWait {
+    fn drop(&mut self) {
+        unsafe { report_wait_event(0) }
+    }
+}
+
+/// Postgres' `pgstat_report_wait_start()` and `pgstat_report_wait_end()` are inline functions
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+unsafe fn report_wait_event(info: u32) {
+    let proc = pg_sys::MyProc;
+    if !pg_sys::pgstat_track_activities || proc.is_null() {
+        return;
+    }
+    std::ptr::write_volatile(&mut (*proc).wait_event_info, info);
+}
+
+#[cfg(feature = "pg14")]
+unsafe fn report_wait_event(info: u32) {
+    std::ptr::write_volatile(pg_sys::my_wait_event_info, info);
+}
+
+/// A 16-bit FNV-1a hash of `name`, as the low bits of `wait_event_info` are the event's id
+const fn event_id(name: &[u8]) -> u16 {
+    let mut hash: u32 = 0x811c9dc5;
+    let mut i = 0;
+    while i < name.len() {
+        hash ^= name[i] as u32;
+        hash = hash.wrapping_mul(0x01000193);
+        i += 1;
+    }
+    (hash ^ (hash >> 16)) as u16
+}
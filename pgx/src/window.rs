@@ -0,0 +1,250 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Support for [window functions](https://www.postgresql.org/docs/current/functions-window.html),
+//! and the [`PgWindow`] passed to `#[pg_window]` functions
+//!
+//! Rows are numbered from the start of the partition, counting from 0, or relative to a
+//! [`WindowSeek`] position.  A window function's arguments can be read from any row in the
+//! partition, or in the current row's frame.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! /// The value of `value` in the row before the current one
+//! #[pg_window]
+//! fn previous(window: &PgWindow, value: WindowArg<i32>) -> Option<i32> {
+//!     window
+//!         .arg_in_partition(value, -1, WindowSeek::Current, false)
+//!         .flatten()
+//! }
+//! ```
+use crate::{is_a, pg_sys, FromDatum};
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+
+/// Where a row's relative position counts from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowSeek {
+    /// The current row
+    Current,
+    /// The first row of the partition or frame
+    Head,
+    /// The last row of the partition or frame
+    Tail,
+}
+
+impl WindowSeek {
+    fn as_seektype(self) -> i32 {
+        (match self {
+            WindowSeek::Current => pg_sys::WINDOW_SEEK_CURRENT,
+            WindowSeek::Head => pg_sys::WINDOW_SEEK_HEAD,
+            WindowSeek::Tail => pg_sys::WINDOW_SEEK_TAIL,
+        }) as i32
+    }
+}
+
+/// One of a window function's arguments, whose values are read through its [`PgWindow`]
+pub struct WindowArg<T> {
+    argno: i32,
+    _marker: PhantomData<T>,
+}
+
+impl<T> WindowArg<T> {
+    /// The argument at `argno`, counting from 0
+    pub fn new(argno: i32) -> Self {
+        WindowArg {
+            argno,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn argno(&self) -> i32 {
+        self.argno
+    }
+}
+
+impl<T> Clone for WindowArg<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for WindowArg<T> {}
+
+/// The window a `#[pg_window]` function is computed over, for the current row
+pub struct PgWindow {
+    fcinfo: pg_sys::FunctionCallInfo,
+    winobj: pg_sys::WindowObject,
+}
+
+impl PgWindow {
+    /// The window of the window function called with `fcinfo`
+    ///
+    /// Panics if the function wasn't called as a window function.
+    ///
+    /// ## Safety
+    ///
+    /// `fcinfo` must be the `pg_sys::FunctionCallInfo` of the function being called
+    pub unsafe fn from_fcinfo(fcinfo: pg_sys::FunctionCallInfo) -> Self {
+        if !called_as_window(fcinfo) {
+            panic!("not called as a window function");
+        }
+
+        PgWindow {
+            fcinfo,
+            winobj: (*fcinfo).context as pg_sys::WindowObject,
+        }
+    }
+
+    pub fn as_ptr(&self) -> pg_sys::WindowObject {
+        self.winobj
+    }
+
+    /// The number of rows in the current row's partition
+    pub fn partition_row_count(&self) -> i64 {
+        unsafe { pg_sys::WinGetPartitionRowCount(self.winobj) }
+    }
+
+    /// The position of the current row in its partition
+    pub fn current_position(&self) -> i64 {
+        unsafe { pg_sys::WinGetCurrentPosition(self.winobj) }
+    }
+
+    /// Promise not to read rows of the partition before `position` again, so they needn't be
+    /// kept
+    pub fn set_mark_position(&self, position: i64) {
+        unsafe { pg_sys::WinSetMarkPosition(self.winobj, position) }
+    }
+
+    /// Are the rows at `position1` and `position2` peers, by the window's `ORDER BY`?
+    pub fn rows_are_peers(&self, position1: i64, position2: i64) -> bool {
+        unsafe { pg_sys::WinRowsArePeers(self.winobj, position1, position2) }
+    }
+
+    /// The value of `arg` in the current row
+    pub fn current_arg<T: FromDatum>(&self, arg: WindowArg<T>) -> Option<T> {
+        let mut isnull = false;
+        unsafe {
+            let datum = pg_sys::WinGetFuncArgCurrent(self.winobj, arg.argno, &mut isnull);
+            T::from_datum(datum, isnull, self.arg_type(arg))
+        }
+    }
+
+    /// The value of `arg` in the row `relpos` rows from `seek` in the partition, or `None` if
+    /// there is no such row
+    ///
+    /// With `set_mark`, the rows before it are no longer kept, as with [`PgWindow::set_mark_position`].
+    pub fn arg_in_partition<T: FromDatum>(
+        &self,
+        arg: WindowArg<T>,
+        relpos: i32,
+        seek: WindowSeek,
+        set_mark: bool,
+    ) -> Option<Option<T>> {
+        let mut isnull = false;
+        let mut isout = false;
+        unsafe {
+            let datum = pg_sys::WinGetFuncArgInPartition(
+                self.winobj,
+                arg.argno,
+                relpos,
+                seek.as_seektype(),
+                set_mark,
+                &mut isnull,
+                &mut isout,
+            );
+            if isout {
+                None
+            } else {
+                Some(T::from_datum(datum, isnull, self.arg_type(arg)))
+            }
+        }
+    }
+
+    /// The value of `arg` in the row `relpos` rows from `seek` in the current row's frame, or
+    /// `None` if there is no such row
+    ///
+    /// With `set_mark`, the rows before it are no longer kept, as with [`PgWindow::set_mark_position`].
+    pub fn arg_in_frame<T: FromDatum>(
+        &self,
+        arg: WindowArg<T>,
+        relpos: i32,
+        seek: WindowSeek,
+        set_mark: bool,
+    ) -> Option<Option<T>> {
+        let mut isnull = false;
+        let mut isout = false;
+        unsafe {
+            let datum = pg_sys::WinGetFuncArgInFrame(
+                self.winobj,
+                arg.argno,
+                relpos,
+                seek.as_seektype(),
+                set_mark,
+                &mut isnull,
+                &mut isout,
+            );
+            if isout {
+                None
+            } else {
+                Some(T::from_datum(datum, isnull, self.arg_type(arg)))
+            }
+        }
+    }
+
+    /// The value kept for the current row's partition, which is `T::default()` at its first row
+    ///
+    /// A window function must use the same `T` for every call.
+    pub fn partition_local<T: Copy + Default>(&self) -> T {
+        unsafe {
+            let local = self.partition_local_memory::<T>();
+            if (*local).initialized {
+                (*local).value.assume_init()
+            } else {
+                T::default()
+            }
+        }
+    }
+
+    /// Keeps `value` for the remaining rows of the current row's partition
+    pub fn set_partition_local<T: Copy + Default>(&self, value: T) {
+        unsafe {
+            let local = self.partition_local_memory::<T>();
+            (*local).value = MaybeUninit::new(value);
+            (*local).initialized = true;
+        }
+    }
+
+    fn partition_local_memory<T>(&self) -> *mut PartitionLocal<T> {
+        // the memory is zeroed when it's first allocated, so isn't `initialized`
+        unsafe {
+            pg_sys::WinGetPartitionLocalMemory(
+                self.winobj,
+                std::mem::size_of::<PartitionLocal<T>>(),
+            ) as *mut PartitionLocal<T>
+        }
+    }
+
+    fn arg_type<T>(&self, arg: WindowArg<T>) -> pg_sys::Oid {
+        unsafe { pg_sys::get_fn_expr_argtype((*self.fcinfo).flinfo, arg.argno) }
+    }
+}
+
+#[repr(C)]
+struct PartitionLocal<T> {
+    initialized: bool,
+    value: MaybeUninit<T>,
+}
+
+#[inline]
+pub unsafe fn called_as_window(fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    let fcinfo = fcinfo.as_ref().expect("fcinfo was null");
+    !fcinfo.context.is_null() && is_a(fcinfo.context, pg_sys::NodeTag_T_WindowObjectData)
+}